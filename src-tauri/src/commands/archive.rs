@@ -0,0 +1,70 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::archive_inspector::{self, ArchiveMember};
+use crate::db::models::DbOperations;
+use crate::models::file_state::FileLocation;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveMatch {
+    pub archive_path: String,
+    pub member: ArchiveMember,
+}
+
+#[tauri::command]
+pub async fn get_archive_contents(file_path: String) -> Result<Vec<ArchiveMember>, String> {
+    tracing::info!("Get archive contents: {}", file_path);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+
+    // Prefer the persisted index; fall back to reading the archive directly
+    let profile_id = profile.id.unwrap();
+    let states = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?;
+
+    let indexed = states.into_iter()
+        .find(|s| s.location == FileLocation::Local && s.file_path == file_path)
+        .and_then(|s| archive_inspector::read_index(s.metadata.as_deref()));
+
+    if let Some(index) = indexed {
+        return Ok(index.members);
+    }
+
+    if !archive_inspector::is_archive(Path::new(&file_path)) {
+        return Err(format!("Not a supported archive: {}", file_path));
+    }
+
+    archive_inspector::inspect_zip(&Path::new(&profile.local_path).join(&file_path))
+        .map_err(|e| format!("Failed to inspect archive: {}", e))
+}
+
+#[tauri::command]
+pub async fn search_archives(query: String) -> Result<Vec<ArchiveMatch>, String> {
+    tracing::info!("Search archives: {}", query);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+    let states = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?;
+
+    let needle = query.to_lowercase();
+    let mut matches = Vec::new();
+
+    for state in states.into_iter().filter(|s| s.location == FileLocation::Local) {
+        if let Some(index) = archive_inspector::read_index(state.metadata.as_deref()) {
+            for member in index.members {
+                if member.name.to_lowercase().contains(&needle) {
+                    matches.push(ArchiveMatch {
+                        archive_path: state.file_path.clone(),
+                        member,
+                    });
+                }
+            }
+        }
+    }
+
+    tracing::info!("Found {} archive members matching '{}'", matches.len(), query);
+    Ok(matches)
+}
@@ -1,20 +1,37 @@
-use crate::core::auth_manager::AuthManager;
-use serde::{Deserialize, Serialize};
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::auth_manager::{AuthManager, DriveScope};
+use crate::core::service_account::{self, DriveAuth, ServiceAccountKey};
+use serde::Serialize;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 pub struct AuthStatus {
     pub is_authenticated: bool,
     pub provider: String,
+    /// `oauth` or `service_account`, as the settings say.
+    pub method: String,
+    /// With a service account, the account Drive sees the requests come from.
     pub email: Option<String>,
+    /// The scope the stored tokens carry, or a service account asks for.
+    pub scope: Option<DriveScope>,
+    /// The scope the settings ask for at the next sign-in.
+    pub configured_scope: DriveScope,
+    /// Signed in with another scope than the settings ask for; signing in
+    /// again applies them.
+    pub needs_reauthentication: bool,
 }
 
+/// Sign in to Google Drive with the scope the settings ask for.
 #[tauri::command]
 pub async fn google_auth() -> Result<String, String> {
     tracing::info!("Starting Google OAuth flow...");
 
+    let (auth, scope) = configured_auth().await?;
+    if let DriveAuth::ServiceAccount { .. } = auth {
+        return Err("This profile signs in to Google Drive with a service account; add its key instead".to_string());
+    }
     let mut manager = AuthManager::new().map_err(|e| e.to_string())?;
 
-    match manager.authenticate().await {
+    match manager.authenticate(scope).await {
         Ok(tokens) => {
             tracing::info!("Google authentication successful!");
             Ok(format!(
@@ -33,12 +50,30 @@ pub async fn google_auth() -> Result<String, String> {
 pub async fn get_auth_status() -> Result<AuthStatus, String> {
     tracing::debug!("Checking authentication status...");
 
+    let (auth, configured_scope) = configured_auth().await?;
+    if let DriveAuth::ServiceAccount { subject, scope } = &auth {
+        let key = service_account::stored_key().map_err(|e| e.to_string())?;
+        return Ok(AuthStatus {
+            is_authenticated: key.is_some(),
+            provider: "google_drive".to_string(),
+            method: auth.as_str().to_string(),
+            email: subject.clone().or(key.map(|key| key.client_email)),
+            scope: Some(*scope),
+            configured_scope,
+            needs_reauthentication: false,
+        });
+    }
     let manager = AuthManager::new().map_err(|e| e.to_string())?;
+    let scope = manager.granted_scope();
 
     Ok(AuthStatus {
         is_authenticated: manager.is_authenticated(),
         provider: "google_drive".to_string(),
+        method: auth.as_str().to_string(),
         email: None,
+        scope,
+        configured_scope,
+        needs_reauthentication: scope.is_some_and(|scope| scope != configured_scope),
     })
 }
 
@@ -51,3 +86,33 @@ pub async fn logout() -> Result<String, String> {
 
     Ok("Logged out successfully".to_string())
 }
+
+/// Store the service account key to sign in to Google Drive with, from the
+/// contents of the key file downloaded from the Google Cloud console. Returns
+/// the service account's email address, which the synced folder must be shared
+/// with unless the settings name a user to act as.
+#[tauri::command]
+pub async fn set_service_account_key(key_json: String) -> Result<String, String> {
+    tracing::info!("Set service account key command called");
+
+    let key = ServiceAccountKey::parse(&key_json).map_err(|e| e.to_string())?;
+    service_account::store_key(&key).map_err(|e| format!("Failed to store the service account key: {}", e))?;
+    tracing::info!("Stored the key of service account {}", key.client_email);
+    Ok(key.client_email)
+}
+
+#[tauri::command]
+pub async fn remove_service_account_key() -> Result<String, String> {
+    tracing::info!("Remove service account key command called");
+
+    service_account::delete_key().map_err(|e| format!("Failed to remove the service account key: {}", e))?;
+    Ok("Service account key removed".to_string())
+}
+
+async fn configured_auth() -> Result<(DriveAuth, DriveScope), String> {
+    let (profile, _) = get_or_create_default_profile().await?;
+    let scope = DriveScope::from_config(profile.drive_scope.as_deref())
+        .map_err(|e| format!("Invalid Google Drive scope: {}", e))?;
+    let auth = DriveAuth::for_profile(&profile).map_err(|e| format!("Invalid Google Drive sign-in: {}", e))?;
+    Ok((auth, scope))
+}
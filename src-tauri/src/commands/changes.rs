@@ -0,0 +1,43 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::changes::{self, LocationChanges};
+use crate::core::scan_cache;
+use crate::models::file_state::FileLocation;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// Files added, modified and deleted at each location since `since` (RFC 3339).
+#[tauri::command]
+pub async fn get_changes_since(since: String) -> Result<Vec<LocationChanges>, String> {
+    tracing::info!("Get changes since {}", since);
+
+    let since: DateTime<Utc> = DateTime::parse_from_rfc3339(&since)
+        .map_err(|e| format!("Invalid timestamp '{}': {}", since, e))?
+        .with_timezone(&Utc);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| changes::changes_since(conn, profile_id, since))
+        .await
+        .map_err(|e| format!("Failed to load changes: {}", e))
+}
+
+/// Tell the scan cache that `paths` (directories or files, relative to the
+/// location root) changed at `location`, or at every location when None, so the
+/// next sync lists them again. No paths drops the cached scans entirely.
+#[tauri::command]
+pub async fn invalidate_scan_cache(location: Option<String>, paths: Vec<String>) -> Result<(), String> {
+    tracing::info!("Invalidate scan cache at {:?} for {} paths", location, paths.len());
+
+    let location = location.map(|l| l.parse::<FileLocation>()).transpose()?;
+    let (profile, _) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    if paths.is_empty() {
+        scan_cache::invalidate(profile_id, location.as_ref(), Path::new(""));
+    }
+    for path in &paths {
+        scan_cache::invalidate(profile_id, location.as_ref(), Path::new(path));
+    }
+    Ok(())
+}
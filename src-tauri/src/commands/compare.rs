@@ -0,0 +1,113 @@
+use crate::commands::sync::{build_provider, get_or_create_default_profile};
+use crate::core::drive_encryption;
+use crate::core::extension_policy::{self, ExtensionPolicies};
+use crate::core::path_prefix::PathPrefixes;
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::command;
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Staged versions older than this are removed before a new comparison.
+const STAGING_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Download two versions of a file (e.g. both sides of a conflict) to a staging
+/// folder and open them in the configured compare tool. Returns the staged paths.
+#[tauri::command]
+pub async fn compare_files(path: String, left: FileLocation, right: FileLocation) -> Result<Vec<String>, String> {
+    tracing::info!("Compare {} between {:?} and {:?}", path, left, right);
+    if left == right {
+        return Err("Choose two different locations to compare".to_string());
+    }
+
+    let (profile, _) = get_or_create_default_profile().await?;
+    let template = profile.compare_command.clone()
+        .filter(|c| !c.trim().is_empty())
+        .ok_or_else(|| "Compare tool not configured".to_string())?;
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+
+    let staging_root = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| "Failed to get project directory".to_string())?
+        .data_dir()
+        .join("compare");
+    clear_stale_staging(&staging_root);
+    let staging = staging_root.join(chrono::Utc::now().format("%Y%m%d%H%M%S%3f").to_string());
+    tokio::fs::create_dir_all(&staging)
+        .await
+        .map_err(|e| format!("Failed to create staging folder: {}", e))?;
+
+    let relative = Path::new(&path);
+    let left_path = stage_version(&profile, &policies, relative, &left, &staging).await?;
+    let right_path = stage_version(&profile, &policies, relative, &right, &staging).await?;
+
+    let (left_str, right_str) = (left_path.to_string_lossy(), right_path.to_string_lossy());
+    // The tool runs on its own; the user closes it when done
+    command::build(&template, &[("left", &left_str), ("right", &right_str)])
+        .map_err(|e| format!("Compare tool could not be started: {}", e))?
+        .spawn()
+        .map_err(|e| format!("Compare tool could not be started: {}", e))?;
+
+    Ok(vec![left_str.to_string(), right_str.to_string()])
+}
+
+/// Download the copy at `location` as `<stem>.<location>.<ext>`, keeping the
+/// extension so CAD tools recognise the file.
+pub(crate) async fn stage_version(
+    profile: &SyncProfile,
+    policies: &ExtensionPolicies,
+    relative: &Path,
+    location: &FileLocation,
+    staging: &Path,
+) -> Result<PathBuf, String> {
+    let provider = build_provider(profile, location)?;
+
+    let stem = relative.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}.{}", stem, location.as_str());
+    if let Some(extension) = relative.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    let dest = staging.join(name);
+
+    let prefixes = PathPrefixes::from_config(profile.path_prefixes.as_deref())
+        .map_err(|e| format!("Invalid path prefixes: {}", e))?;
+    let stored = prefixes.stored_path(&policies.stored_path(relative, location), location);
+    let compressed = policies.is_compressed_at(relative, location);
+    let fetched = if compressed { dest.with_extension(extension_policy::COMPRESSED_SUFFIX) } else { dest.clone() };
+    provider.download(&stored, &fetched)
+        .await
+        .map_err(|e| format!("Failed to download {} version: {}", location.as_str(), e))?;
+    if *location == FileLocation::GoogleDrive {
+        let keys = drive_encryption::for_profile(profile)
+            .map_err(|e| format!("Failed to load the Drive encryption key: {}", e))?;
+        if let Some(keys) = keys {
+            keys.decrypt_file(&fetched, &fetched)
+                .map_err(|e| format!("Failed to decrypt {} version: {}", location.as_str(), e))?;
+        }
+    }
+    if !compressed {
+        return Ok(dest);
+    }
+
+    let unpacked = extension_policy::decompress_file(&fetched, &dest)
+        .map_err(|e| format!("Failed to decompress {} version: {}", location.as_str(), e));
+    let _ = std::fs::remove_file(&fetched);
+    unpacked.map(|_| dest)
+}
+
+fn clear_stale_staging(staging_root: &Path) {
+    let Ok(entries) = std::fs::read_dir(staging_root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry.metadata()
+            .and_then(|m| m.modified())
+            .map(|modified| modified.elapsed().unwrap_or_default() > STAGING_MAX_AGE)
+            .unwrap_or(false);
+        if stale {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
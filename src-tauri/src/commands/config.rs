@@ -1,50 +1,249 @@
-use crate::db::{models::DbOperations, schema::Database};
+use crate::core::auth_manager::DriveScope;
+use crate::core::cad_temp;
+use crate::core::consistency::ConsistencyGroups;
+use crate::core::drive_encryption;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::naming::NamingConvention;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::nas_discovery::{self, NasServer};
+use crate::core::notifications::ChannelConfig;
+use crate::core::plan_approval::ApprovalPolicy;
+use crate::core::publishing::PublishPolicy;
+use crate::core::relocation::{self, RelocationCheck};
+use crate::core::roles::{self, Permission};
+use crate::core::scan_cache;
+use crate::core::selective_sync::SelectiveSync;
+use crate::core::service_account::DriveAuth;
+use crate::core::signing::{self, DigestSigner};
+use crate::core::smb_mounts::{self, SmbMount};
+use crate::core::sync_engine::DeletionLimits;
+use crate::core::sync_policy::SyncPolicy;
+use crate::core::trash::TrashPolicy;
+use crate::core::updates::{self, UpdateChannel};
+use crate::commands::{managed_policy, notifications, webdav};
+use crate::commands::sync::{begin_sync, build_provider, end_sync, get_or_create_default_profile, get_profile};
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::FileLocation;
 use crate::models::sync_profile::SyncProfile;
+use crate::utils::i18n::{self, Locale};
+use crate::utils::secrets::{self, SecretBackend};
+use crate::utils::unc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub local_path: Option<String>,
     pub gdrive_folder_id: Option<String>,
     pub smb_share_path: Option<String>,
+    pub normalized_extensions: Option<String>,
+    #[serde(default)]
+    pub inspect_archives: bool,
+    pub inbox_location: Option<String>,
+    pub inbox_path: Option<String>,
+    pub inbox_extensions: Option<String>,
+    pub inbox_scan_command: Option<String>,
+    pub inbox_rules: Option<String>,
+    pub naming_rules: Option<String>,
+    pub naming_blocked_locations: Option<String>,
+    pub project_template_path: Option<String>,
+    pub tiering_rules: Option<String>,
+    pub tiering_archive_folder_id: Option<String>,
+    pub extension_policies: Option<String>,
+    pub publish_command: Option<String>,
+    pub publish_formats: Option<String>,
+    pub publish_folder_id: Option<String>,
+    pub compare_command: Option<String>,
+    pub consistency_groups: Option<String>,
+    pub secret_store: Option<String>,
+    pub update_channel: Option<String>,
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub require_plan_approval: bool,
+    pub plan_approvers: Option<String>,
+    pub auto_approved_operations: Option<String>,
+    pub max_deletion_count: Option<i64>,
+    pub max_deletion_percentage: Option<i64>,
+    pub smb_fallback_paths: Option<String>,
+    pub sampled_hash_min_mb: Option<i64>,
+    pub notification_channels: Option<String>,
+    pub webdav_port: Option<i64>,
+    #[serde(default)]
+    pub sign_digests: bool,
+    pub trusted_signing_keys: Option<String>,
+    pub scan_command: Option<String>,
+    #[serde(default)]
+    pub sync_cad_temp_files: bool,
+    pub save_settle_secs: Option<i64>,
+    pub quiet_period_secs: Option<i64>,
+    pub path_prefixes: Option<String>,
+    pub include_patterns: Option<String>,
+    pub exclude_patterns: Option<String>,
+    pub drive_scope: Option<String>,
+    #[serde(default)]
+    pub soft_delete: bool,
+    pub trash_retention_days: Option<i64>,
+    pub drive_auth: Option<String>,
+    pub drive_impersonate: Option<String>,
+    pub managed_policy_source: Option<String>,
+    #[serde(default)]
+    pub override_managed_policy: bool,
+    #[serde(default)]
+    pub encrypt_drive: bool,
+    /// Outcome of the last sync, for display; not changed by update_config.
+    #[serde(default)]
+    pub last_sync_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_successful_sync_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
 }
 
-fn get_config_database() -> Result<Database, String> {
-    let db = Database::new().map_err(|e| format!("Failed to create database: {}", e))?;
-    db.initialize().map_err(|e| format!("Failed to initialize database: {}", e))?;
-    Ok(db)
-}
-
-#[tauri::command]
-pub async fn get_config() -> Result<AppConfig, String> {
-    tracing::info!("Get config command called");
-
-    let db = get_config_database()?;
-    let conn = db.get_connection();
-
-    // Try to get existing profile with id=1
-    if let Some(profile) = DbOperations::get_sync_profile(conn, 1)
-        .map_err(|e| format!("Failed to get sync profile: {}", e))? {
-        return Ok(AppConfig {
+impl AppConfig {
+    fn from_profile(profile: SyncProfile) -> Self {
+        Self {
             local_path: Some(profile.local_path),
             gdrive_folder_id: profile.gdrive_folder_id,
             smb_share_path: profile.smb_share_path,
-        });
+            normalized_extensions: profile.normalized_extensions,
+            inspect_archives: profile.inspect_archives,
+            inbox_location: profile.inbox_location,
+            inbox_path: profile.inbox_path,
+            inbox_extensions: profile.inbox_extensions,
+            inbox_scan_command: profile.inbox_scan_command,
+            inbox_rules: profile.inbox_rules,
+            naming_rules: profile.naming_rules,
+            naming_blocked_locations: profile.naming_blocked_locations,
+            project_template_path: profile.project_template_path,
+            tiering_rules: profile.tiering_rules,
+            tiering_archive_folder_id: profile.tiering_archive_folder_id,
+            extension_policies: profile.extension_policies,
+            publish_command: profile.publish_command,
+            publish_formats: profile.publish_formats,
+            publish_folder_id: profile.publish_folder_id,
+            compare_command: profile.compare_command,
+            consistency_groups: profile.consistency_groups,
+            secret_store: profile.secret_store,
+            // An app setting, filled in by `get_config`
+            update_channel: None,
+            locale: profile.locale,
+            require_plan_approval: profile.require_plan_approval,
+            plan_approvers: profile.plan_approvers,
+            auto_approved_operations: profile.auto_approved_operations,
+            max_deletion_count: profile.max_deletion_count,
+            max_deletion_percentage: profile.max_deletion_percentage,
+            smb_fallback_paths: profile.smb_fallback_paths,
+            sampled_hash_min_mb: profile.sampled_hash_min_mb,
+            notification_channels: profile.notification_channels,
+            webdav_port: profile.webdav_port,
+            sign_digests: profile.sign_digests,
+            trusted_signing_keys: profile.trusted_signing_keys,
+            scan_command: profile.scan_command,
+            sync_cad_temp_files: profile.sync_cad_temp_files,
+            save_settle_secs: profile.save_settle_secs,
+            quiet_period_secs: profile.quiet_period_secs,
+            path_prefixes: profile.path_prefixes,
+            include_patterns: profile.include_patterns,
+            exclude_patterns: profile.exclude_patterns,
+            drive_scope: profile.drive_scope,
+            soft_delete: profile.soft_delete,
+            trash_retention_days: profile.trash_retention_days,
+            drive_auth: profile.drive_auth,
+            drive_impersonate: profile.drive_impersonate,
+            managed_policy_source: profile.managed_policy_source,
+            override_managed_policy: profile.override_managed_policy,
+            encrypt_drive: profile.encrypt_drive,
+            last_sync_at: profile.last_sync_at,
+            last_successful_sync_at: profile.last_successful_sync_at,
+            last_error: profile.last_error,
+        }
     }
 
-    // Return empty config if no profile exists
-    Ok(AppConfig {
-        local_path: None,
-        gdrive_folder_id: None,
-        smb_share_path: None,
-    })
+    /// Copy the user-editable settings onto a profile.
+    fn apply_to(self, profile: &mut SyncProfile) {
+        if let Some(local_path) = self.local_path {
+            profile.local_path = local_path;
+        }
+        profile.gdrive_folder_id = self.gdrive_folder_id;
+        profile.smb_share_path = self.smb_share_path;
+        profile.normalized_extensions = self.normalized_extensions;
+        profile.inspect_archives = self.inspect_archives;
+        profile.inbox_location = self.inbox_location;
+        profile.inbox_path = self.inbox_path;
+        profile.inbox_extensions = self.inbox_extensions;
+        profile.inbox_scan_command = self.inbox_scan_command;
+        profile.inbox_rules = self.inbox_rules;
+        profile.naming_rules = self.naming_rules;
+        profile.naming_blocked_locations = self.naming_blocked_locations;
+        profile.project_template_path = self.project_template_path;
+        profile.tiering_rules = self.tiering_rules;
+        profile.tiering_archive_folder_id = self.tiering_archive_folder_id;
+        profile.extension_policies = self.extension_policies;
+        profile.publish_command = self.publish_command;
+        profile.publish_formats = self.publish_formats;
+        profile.publish_folder_id = self.publish_folder_id;
+        profile.compare_command = self.compare_command;
+        profile.consistency_groups = self.consistency_groups;
+        profile.secret_store = self.secret_store;
+        profile.locale = self.locale;
+        profile.require_plan_approval = self.require_plan_approval;
+        profile.plan_approvers = self.plan_approvers;
+        profile.auto_approved_operations = self.auto_approved_operations;
+        profile.max_deletion_count = self.max_deletion_count;
+        profile.max_deletion_percentage = self.max_deletion_percentage;
+        profile.smb_fallback_paths = self.smb_fallback_paths;
+        profile.sampled_hash_min_mb = self.sampled_hash_min_mb;
+        profile.notification_channels = self.notification_channels;
+        profile.webdav_port = self.webdav_port;
+        profile.sign_digests = self.sign_digests;
+        profile.trusted_signing_keys = self.trusted_signing_keys;
+        profile.scan_command = self.scan_command;
+        profile.sync_cad_temp_files = self.sync_cad_temp_files;
+        profile.save_settle_secs = self.save_settle_secs;
+        profile.quiet_period_secs = self.quiet_period_secs;
+        profile.path_prefixes = self.path_prefixes;
+        profile.include_patterns = self.include_patterns;
+        profile.exclude_patterns = self.exclude_patterns;
+        profile.drive_scope = self.drive_scope;
+        profile.soft_delete = self.soft_delete;
+        profile.trash_retention_days = self.trash_retention_days;
+        profile.drive_auth = self.drive_auth;
+        profile.drive_impersonate = self.drive_impersonate;
+        profile.managed_policy_source = self.managed_policy_source;
+        profile.override_managed_policy = self.override_managed_policy;
+        profile.encrypt_drive = self.encrypt_drive;
+    }
 }
 
+/// The settings of the profile with `profile_id`, or the active one.
 #[tauri::command]
-pub async fn update_config(config: AppConfig) -> Result<String, String> {
+pub async fn get_config(profile_id: Option<i64>) -> Result<AppConfig, String> {
+    tracing::info!("Get config command called");
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let update_channel = db.call(|conn| DbOperations::get_app_setting(conn, updates::CHANNEL_SETTING))
+        .await
+        .map_err(|e| format!("Failed to read the update channel: {}", e))?;
+    Ok(AppConfig { update_channel, ..AppConfig::from_profile(profile) })
+}
+
+/// Save the settings of the profile with `profile_id`, or the active one.
+#[tauri::command]
+pub async fn update_config(mut config: AppConfig, profile_id: Option<i64>) -> Result<String, String> {
     tracing::info!("Update config command called: {:?}", config);
 
+    // Store drive-letter paths in UNC form so every machine sees the same location
+    config.local_path = config.local_path.map(|p| unc::canonicalize(&p));
+    config.smb_share_path = config.smb_share_path.map(|p| unc::canonicalize(&p));
+    config.smb_fallback_paths = config.smb_fallback_paths.map(|paths| {
+        paths.lines().map(unc::canonicalize).collect::<Vec<_>>().join("\n")
+    });
+    config.managed_policy_source = config.managed_policy_source
+        .map(|source| source.trim().to_string())
+        .filter(|source| !source.is_empty());
+
     // Validate local path if provided
     if let Some(ref path) = config.local_path {
         if !Path::new(path).exists() {
@@ -57,46 +256,204 @@ pub async fn update_config(config: AppConfig) -> Result<String, String> {
         return Err("Local path is required".to_string());
     }
 
-    let db = get_config_database()?;
-    let conn = db.get_connection();
-
-    // Try to get existing profile
-    if let Some(mut profile) = DbOperations::get_sync_profile(conn, 1)
-        .map_err(|e| format!("Failed to get sync profile: {}", e))? {
-        // Update existing profile
-        profile.local_path = config.local_path.unwrap();
-        profile.gdrive_folder_id = config.gdrive_folder_id;
-        profile.smb_share_path = config.smb_share_path;
-
-        // Update in database
-        conn.execute(
-            "UPDATE sync_profiles SET local_path = ?1, gdrive_folder_id = ?2, smb_share_path = ?3 WHERE id = ?4",
-            rusqlite::params![
-                profile.local_path,
-                profile.gdrive_folder_id,
-                profile.smb_share_path,
-                profile.id.unwrap(),
-            ],
-        ).map_err(|e| format!("Failed to update sync profile: {}", e))?;
-    } else {
-        // Create new profile
-        let new_profile = SyncProfile {
-            id: None,
-            name: "Default".to_string(),
-            local_path: config.local_path.unwrap(),
-            gdrive_folder_id: config.gdrive_folder_id,
-            smb_share_path: config.smb_share_path,
-            created_at: chrono::Utc::now(),
-            last_sync_at: None,
-        };
+    NamingConvention::from_config(config.naming_rules.as_deref(), config.naming_blocked_locations.as_deref())
+        .map_err(|e| format!("Invalid naming convention: {}", e))?;
+    ExtensionPolicies::from_config(config.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+    PublishPolicy::from_config(config.publish_command.as_deref(), config.publish_formats.as_deref())
+        .map_err(|e| format!("Invalid publish settings: {}", e))?;
+    ConsistencyGroups::from_config(config.consistency_groups.as_deref())
+        .map_err(|e| format!("Invalid consistency groups: {}", e))?;
+    if config.require_plan_approval {
+        ApprovalPolicy::from_config(config.plan_approvers.as_deref(), config.auto_approved_operations.as_deref())
+            .map_err(|e| format!("Invalid plan approval settings: {}", e))?;
+    }
+    UpdateChannel::from_config(config.update_channel.as_deref())
+        .map_err(|e| format!("Invalid update channel: {}", e))?;
+    let drive_scope = DriveScope::from_config(config.drive_scope.as_deref())
+        .map_err(|e| format!("Invalid Google Drive scope: {}", e))?;
+    DriveAuth::from_config(config.drive_auth.as_deref(), config.drive_impersonate.as_deref(), drive_scope)
+        .map_err(|e| format!("Invalid Google Drive sign-in: {}", e))?;
+    Locale::from_config(config.locale.as_deref())
+        .map_err(|e| format!("Invalid language: {}", e))?;
+    if let Some(store) = config.secret_store.as_deref().filter(|s| !s.trim().is_empty()) {
+        SecretBackend::from_str_opt(store).ok_or_else(|| format!("Unknown secret store: {}", store))?;
+    }
+    DeletionLimits::from_config(config.max_deletion_count, config.max_deletion_percentage)
+        .map_err(|e| format!("Invalid deletion thresholds: {}", e))?;
+    TrashPolicy::from_config(config.trash_retention_days)
+        .map_err(|e| format!("Invalid trash settings: {}", e))?;
+    if let Some(mb) = config.sampled_hash_min_mb.filter(|mb| *mb <= 0) {
+        return Err(format!("Invalid size for sampled hashing: {} MB", mb));
+    }
+    ChannelConfig::parse_all(config.notification_channels.as_deref())
+        .map_err(|e| format!("Invalid notification channels: {}", e))?;
+    if let Some(port) = config.webdav_port {
+        webdav::validate_port(port)?;
+    }
+    cad_temp::settle_delay(config.save_settle_secs)
+        .map_err(|e| format!("Invalid save settings: {}", e))?;
+    cad_temp::quiet_period(config.quiet_period_secs)
+        .map_err(|e| format!("Invalid save settings: {}", e))?;
+    signing::parse_trusted_keys(config.trusted_signing_keys.as_deref())
+        .map_err(|e| format!("Invalid trusted signing keys: {}", e))?;
+    PathPrefixes::from_config(config.path_prefixes.as_deref())
+        .map_err(|e| format!("Invalid path prefixes: {}", e))?;
+    SelectiveSync::from_config(config.include_patterns.as_deref(), config.exclude_patterns.as_deref())
+        .map_err(|e| format!("Invalid sync patterns: {}", e))?;
+
+    let (current, db) = get_profile(profile_id).await?;
+    let profile_id = current.id.unwrap();
+
+    // Loosening or tightening the deletion safety check is reserved for admins
+    if current.max_deletion_count != config.max_deletion_count
+        || current.max_deletion_percentage != config.max_deletion_percentage
+    {
+        roles::require(Permission::ChangeDeletionThresholds).map_err(String::from)?;
+    }
+
+    // So is where the managed policy comes from and departing from it
+    let source_changed = current.managed_policy_source != config.managed_policy_source;
+    let override_changed = current.override_managed_policy != config.override_managed_policy;
+    if source_changed || override_changed {
+        roles::require(Permission::OverrideManagedPolicy).map_err(String::from)?;
+    }
+    if let (true, Some(source)) = (source_changed, &config.managed_policy_source) {
+        managed_policy::check_source(source).await?;
+    }
+    let encrypt_drive = config.encrypt_drive;
+    let update_channel = config.update_channel.clone().filter(|c| !c.trim().is_empty());
+    let mut profile = current;
+    config.apply_to(&mut profile);
+    // Turning the override off is followed by applying the policy again
+    managed_policy::check_managed_settings(&SyncPolicy::from_profile(&profile), profile.override_managed_policy || override_changed)?;
+    managed_policy::check_locations(&profile)?;
+
+    db.call(move |conn| {
+        DbOperations::update_sync_profile(conn, &profile)?;
+        DbOperations::set_app_setting(conn, updates::CHANNEL_SETTING, update_channel.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to save sync profile: {}", e))?;
 
-        DbOperations::create_sync_profile(conn, &new_profile)
-            .map_err(|e| format!("Failed to create sync profile: {}", e))?;
+    // Locations or hashing may have changed, so the next sync scans from scratch
+    scan_cache::clear_profile(profile_id);
+    // Only the active profile's settings apply process-wide
+    apply_profile_settings().await?;
+    // Made now rather than at the first sync, so it can be exported right away
+    if encrypt_drive {
+        drive_encryption::load_or_create(profile_id)
+            .map_err(|e| format!("Failed to create the Drive encryption key: {}", e))?;
+    }
+    if source_changed || override_changed {
+        managed_policy::refresh().await?;
     }
 
     Ok("Configuration saved successfully".to_string())
 }
 
+/// Apply the process-wide settings of the active profile: the secret store, the
+/// message language, the notification channels and the WebDAV server. Called at startup before anything reads tokens.
+pub async fn apply_profile_settings() -> Result<(), String> {
+    let (profile, db) = get_or_create_default_profile().await?;
+    i18n::set_locale(Locale::from_config(profile.locale.as_deref()).unwrap_or_default());
+    secrets::select_backend(profile.secret_store.as_deref())
+        .map_err(|e| format!("Failed to select secret store: {}", e))?;
+    notifications::configure(profile.notification_channels.as_deref())?;
+    webdav::configure(profile, db).await
+}
+
+/// The sync policy of the active profile, for editing or exporting as JSON.
+#[tauri::command]
+pub async fn get_sync_policy() -> Result<SyncPolicy, String> {
+    let (profile, _) = get_or_create_default_profile().await?;
+    Ok(SyncPolicy::from_profile(&profile))
+}
+
+/// Replace the sync policy of the active profile, e.g. with one edited in the
+/// settings or exported from another computer. Changing the deletion thresholds
+/// is reserved for admins.
+#[tauri::command]
+pub async fn set_sync_policy(policy: SyncPolicy) -> Result<SyncPolicy, String> {
+    tracing::info!("Set sync policy command called: {:?}", policy);
+
+    policy.compile().map_err(|e| format!("Invalid sync policy: {}", e))?;
+    let (mut profile, db) = get_or_create_default_profile().await?;
+    if policy.changes_deletion_limits(&SyncPolicy::from_profile(&profile)) {
+        roles::require(Permission::ChangeDeletionThresholds).map_err(String::from)?;
+    }
+    managed_policy::check_managed_settings(&policy, profile.override_managed_policy)?;
+
+    policy.clone().apply_to(&mut profile);
+    db.call(move |conn| DbOperations::update_sync_profile(conn, &profile))
+        .await
+        .map_err(|e| format!("Failed to save sync profile: {}", e))?;
+    Ok(policy)
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageCatalog {
+    pub locale: String,
+    /// Message code -> text with `{name}` placeholders.
+    pub messages: HashMap<String, String>,
+}
+
+/// Messages in the configured language, for localizing progress operations,
+/// notifications and other codes sent by the backend.
+#[tauri::command]
+pub async fn get_message_catalog() -> Result<MessageCatalog, String> {
+    Ok(MessageCatalog {
+        locale: i18n::locale().as_str().to_string(),
+        messages: i18n::catalog(),
+    })
+}
+
+/// This computer's public signing key, for the `trusted_signing_keys` of the
+/// other computers syncing the profile. Creates the key on first use.
+#[tauri::command]
+pub async fn get_signing_key() -> Result<String, String> {
+    DigestSigner::for_this_machine(String::new(), None)
+        .map(|signer| signer.public_key())
+        .map_err(|e| format!("Failed to read the signing key: {}", e))
+}
+
+/// Mounted network shares to choose the SMB location from.
+#[tauri::command]
+pub async fn discover_smb_mounts() -> Result<Vec<SmbMount>, String> {
+    tracing::info!("Discover SMB mounts command called");
+
+    tokio::task::spawn_blocking(smb_mounts::discover)
+        .await
+        .map_err(|e| format!("Failed to discover SMB mounts: {}", e))?
+        .map_err(|e| format!("Failed to discover SMB mounts: {}", e))
+}
+
+/// File servers on the local network and the shares they offer to guests.
+/// Share listing is best effort: servers that refuse it are listed without shares.
+#[tauri::command]
+pub async fn discover_file_servers() -> Result<Vec<NasServer>, String> {
+    tracing::info!("Discover file servers command called");
+
+    let mut servers = nas_discovery::discover_servers()
+        .await
+        .map_err(|e| format!("Failed to discover file servers: {}", e))?;
+
+    let listings: Vec<_> = servers.iter()
+        .map(|server| {
+            let host = server.host.clone();
+            tokio::task::spawn_blocking(move || nas_discovery::list_shares(&host))
+        })
+        .collect();
+    for (server, listing) in servers.iter_mut().zip(listings) {
+        match listing.await {
+            Ok(Ok(shares)) => server.shares = shares,
+            Ok(Err(e)) => tracing::warn!("Failed to list shares on {}: {}", server.host, e),
+            Err(e) => tracing::warn!("Failed to list shares on {}: {}", server.host, e),
+        }
+    }
+    Ok(servers)
+}
+
 #[tauri::command]
 pub async fn test_smb_connection(path: String) -> Result<bool, String> {
     tracing::info!("Test SMB connection: {}", path);
@@ -121,3 +478,59 @@ pub async fn test_smb_connection(path: String) -> Result<bool, String> {
         }
     }
 }
+
+/// Point the active profile at its local folder's new place, e.g. after moving
+/// it to another drive. The recorded state is kept, so nothing is transferred
+/// again, once the new folder is checked to hold every synced file unchanged;
+/// otherwise the next sync would see the files as deleted. No sync runs meanwhile.
+#[tauri::command]
+pub async fn relocate_local_root(new_path: String) -> Result<RelocationCheck, String> {
+    tracing::info!("Relocate local root command called: {}", new_path);
+
+    let new_path = unc::canonicalize(&new_path);
+    if !Path::new(&new_path).is_dir() {
+        return Err(format!("Local path is not a directory: {}", new_path));
+    }
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+    let result = relocate(profile, db, new_path).await;
+    end_sync(profile_id);
+    result
+}
+
+async fn relocate(mut profile: SyncProfile, db: DbHandle, new_path: String) -> Result<RelocationCheck, String> {
+    if profile.local_path == new_path {
+        return Err(format!("The local folder is already {}", new_path));
+    }
+    let profile_id = profile.id.unwrap();
+
+    let mut relocated = profile.clone();
+    relocated.local_path = new_path.clone();
+    let found = build_provider(&relocated, &FileLocation::Local)?
+        .list_files(Path::new(""))
+        .await
+        .map_err(|e| format!("Failed to scan {}: {}", new_path, e))?;
+    let recorded = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?;
+
+    let check = relocation::check(&recorded, &found);
+    if !check.is_complete() {
+        return Err(check.describe_mismatches(Path::new(&new_path)));
+    }
+
+    tracing::info!(
+        "Moving the local folder from {} to {}: {} files verified, {} new",
+        profile.local_path, new_path, check.verified, check.new_files
+    );
+    profile.local_path = new_path;
+    let saved = profile.clone();
+    db.call(move |conn| DbOperations::update_sync_profile(conn, &saved))
+        .await
+        .map_err(|e| format!("Failed to save sync profile: {}", e))?;
+    scan_cache::clear_profile(profile_id);
+    webdav::configure(profile, db).await?;
+    Ok(check)
+}
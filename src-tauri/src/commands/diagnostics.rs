@@ -0,0 +1,80 @@
+use crate::commands::simulation;
+use crate::core::simulation::{Scenario, SimulationReport};
+use crate::core::soak::{self, SoakConfig, SoakReport};
+use crate::providers::http_capture::{self, CaptureSession};
+use crate::utils::logging;
+use std::time::Duration;
+
+/// Change how much is logged without restarting: `error`, `warn`, `info`,
+/// `debug`, `trace` or `off`. Modules switched to debug level stay that way.
+#[tauri::command]
+pub async fn set_log_level(level: String) -> Result<(), String> {
+    logging::set_level(&level).map_err(|e| format!("Failed to set log level: {}", e))
+}
+
+/// Log one module at debug level, e.g. `uvcad::providers::samba`, while the
+/// rest keeps the current level.
+#[tauri::command]
+pub async fn enable_debug_for_module(module: String) -> Result<(), String> {
+    logging::enable_debug_for(&module).map_err(|e| format!("Failed to enable debug logging: {}", e))
+}
+
+/// Start logging every Google Drive request and response, with tokens and
+/// upload session IDs redacted, to a new file for troubleshooting API issues,
+/// e.g. with Google support. Returns where the log is written.
+#[tauri::command]
+pub async fn start_http_capture() -> Result<CaptureSession, String> {
+    tracing::info!("Start HTTP capture command called");
+    http_capture::start().map_err(|e| format!("Failed to start capture: {}", e))
+}
+
+/// Stop the capture; returns the finished log, or None when none was running.
+#[tauri::command]
+pub async fn stop_http_capture() -> Result<Option<CaptureSession>, String> {
+    tracing::info!("Stop HTTP capture command called");
+    Ok(http_capture::stop())
+}
+
+#[tauri::command]
+pub async fn get_http_capture() -> Result<Option<CaptureSession>, String> {
+    Ok(http_capture::session())
+}
+
+/// Simulate an outage (`smb_dropout`, `expired_token`) against in-memory
+/// locations to check that safety checks, retries and offline handling behave
+/// as configured. Not offered in the UI; like `simulate_sync`, it never touches
+/// real files or the sync database.
+#[tauri::command]
+pub async fn simulate_outage(outage: String) -> Result<SimulationReport, String> {
+    tracing::info!("Simulate outage: {}", outage);
+    let scenario = Scenario::from_str_opt(&outage)
+        .filter(|s| s.is_outage())
+        .ok_or_else(|| format!("Unknown outage: {}", outage))?;
+    simulation::run(scenario).await
+}
+
+/// Soak the engine with random changes in a sandbox for `minutes` (see
+/// `core::soak`), optionally failing every `fail_every`th Google Drive transfer.
+/// Pass the `seed` of an earlier report to replay it. Not offered in the UI.
+#[tauri::command]
+pub async fn run_soak_test(minutes: u64, seed: Option<u64>, fail_every: Option<usize>) -> Result<SoakReport, String> {
+    let seed = seed.unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+    tracing::info!("Soak test for {} minutes with seed {}", minutes, seed);
+    let config = SoakConfig {
+        duration: Duration::from_secs(minutes * 60),
+        max_rounds: None,
+        seed,
+        fail_every: fail_every.unwrap_or(0),
+    };
+
+    let report = soak::run(&config).await.map_err(|e| format!("Soak test failed: {}", e))?;
+    tracing::info!("Soak test finished after {} rounds with {} violations", report.rounds, report.violations.len());
+    Ok(report)
+}
+
+/// End a running soak test after its current round; it still returns its report.
+#[tauri::command]
+pub async fn stop_soak_test() -> Result<(), String> {
+    soak::stop();
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::scan_cache;
+use crate::models::file_state::FileLocation;
+use crate::providers::google_drive::{DedupeOutcome, GoogleDriveProvider};
+use std::path::{Path, PathBuf};
+
+/// Resolve files sharing a name in one Google Drive folder, which sync skips.
+/// `path` limits this to one reported duplicate; otherwise all are resolved.
+/// Identical copies are merged into the newest; differing ones are renamed.
+#[tauri::command]
+pub async fn dedupe_remote(path: Option<String>) -> Result<Vec<DedupeOutcome>, String> {
+    tracing::info!("Dedupe remote command called: {:?}", path);
+
+    let (profile, _) = get_or_create_default_profile().await?;
+    let folder_id = profile.gdrive_folder_id.clone()
+        .ok_or_else(|| "Google Drive folder not configured".to_string())?;
+    let gdrive = GoogleDriveProvider::for_profile(&profile, folder_id)
+        .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+    if !gdrive.is_authenticated() {
+        return Err("Not authenticated with Google Drive. Please sign in first.".to_string());
+    }
+
+    let only = path.map(PathBuf::from);
+    let outcomes = gdrive.dedupe(only.as_deref())
+        .await
+        .map_err(|e| format!("Failed to resolve duplicates: {}", e))?;
+
+    // Renamed copies show up under new names, so Drive is listed again in full
+    scan_cache::invalidate(profile.id.unwrap(), Some(&FileLocation::GoogleDrive), Path::new(""));
+    Ok(outcomes)
+}
@@ -0,0 +1,210 @@
+use crate::commands::sync::{begin_sync, build_provider, end_sync, get_profile};
+use crate::core::drive_encryption::{self, ProfileKeys};
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::scan_cache;
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::traits::{FileMetadata, StorageProvider};
+use crate::utils::error::UvcadError;
+use serde::Serialize;
+use std::path::Path;
+
+/// Files re-encrypted per call of `rotate_drive_key` unless told otherwise.
+const DEFAULT_ROTATION_BATCH: usize = 200;
+
+/// How far re-encrypting the profile's files on Google Drive with a new key got.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyRotation {
+    /// Fingerprint of the key the files are being encrypted with.
+    pub key: String,
+    pub reencrypted: usize,
+    /// Files already encrypted with the new key, e.g. uploaded since the rotation began.
+    pub skipped: usize,
+    /// Files that could not be re-encrypted, with why; they stay readable with the old key.
+    pub failed: Vec<(String, String)>,
+    /// Files left for the next call; zero when the rotation is done.
+    pub remaining: usize,
+}
+
+/// Save the Drive encryption keys of the profile (the active one unless
+/// `profile_id` is given) to `path`, protected with `passphrase`, for the other
+/// computers syncing it. Returns the fingerprint of the current key.
+#[tauri::command]
+pub async fn export_drive_key(path: String, passphrase: String, profile_id: Option<i64>) -> Result<String, String> {
+    tracing::info!("Export Drive key command called: {}", path);
+
+    if passphrase.is_empty() {
+        return Err("A passphrase is required to export the key".to_string());
+    }
+    let (profile, _) = get_profile(profile_id).await?;
+    let keys = drive_encryption::load(profile.id.unwrap())
+        .map_err(|e| format!("Failed to read the Drive encryption key: {}", e))?
+        .ok_or_else(|| "The profile has no Drive encryption key yet".to_string())?;
+    let exported = keys.export(&passphrase).map_err(|e| format!("Failed to export the key: {}", e))?;
+    tokio::fs::write(&path, exported)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(keys.fingerprint())
+}
+
+/// Take over the Drive encryption keys exported to `path` by another computer
+/// syncing the profile. Keys this computer had are kept for reading older
+/// files. Returns the fingerprint of the key now used.
+#[tauri::command]
+pub async fn import_drive_key(path: String, passphrase: String, profile_id: Option<i64>) -> Result<String, String> {
+    tracing::info!("Import Drive key command called: {}", path);
+
+    let (profile, _) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    let exported = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let imported = ProfileKeys::import(&exported, &passphrase).map_err(String::from)?;
+    let keys = match drive_encryption::load(profile_id) {
+        Ok(Some(mut keys)) => {
+            keys.merge(imported);
+            keys
+        }
+        Ok(None) => imported,
+        Err(e) => return Err(format!("Failed to read the Drive encryption key: {}", e)),
+    };
+    drive_encryption::save(profile_id, &keys).map_err(|e| format!("Failed to save the Drive encryption key: {}", e))?;
+    Ok(keys.fingerprint())
+}
+
+/// Switch the profile to a new Drive encryption key and re-encrypt up to
+/// `batch` of its files on Google Drive with it; call again until nothing
+/// remains. Files stored before encryption was turned on are encrypted too.
+/// The old key is kept, so other computers read every file until they import
+/// the new one.
+#[tauri::command]
+pub async fn rotate_drive_key(profile_id: Option<i64>, batch: Option<usize>) -> Result<KeyRotation, String> {
+    tracing::info!("Rotate Drive key command called");
+
+    let (profile, db) = get_profile(profile_id).await?;
+    if !profile.encrypt_drive {
+        return Err("Files on Google Drive are not encrypted for this profile".to_string());
+    }
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+    let result = rotate(profile, db, batch.unwrap_or(DEFAULT_ROTATION_BATCH).max(1)).await;
+    end_sync(profile_id);
+    result
+}
+
+async fn rotate(profile: SyncProfile, db: DbHandle, batch: usize) -> Result<KeyRotation, String> {
+    let profile_id = profile.id.unwrap();
+    let mut keys = drive_encryption::load_or_create(profile_id)
+        .map_err(|e| format!("Failed to read the Drive encryption key: {}", e))?;
+    if keys.rotation.is_none() {
+        keys.rotate().map_err(|e| format!("Failed to create a new key: {}", e))?;
+        drive_encryption::save(profile_id, &keys).map_err(|e| format!("Failed to save the new key: {}", e))?;
+        tracing::info!("Rotating the Drive encryption key of profile {} to {}", profile_id, keys.fingerprint());
+    }
+    let after = keys.rotation.as_ref().and_then(|r| r.after.clone());
+
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+    let prefixes = PathPrefixes::from_config(profile.path_prefixes.as_deref())
+        .map_err(|e| format!("Invalid path prefixes: {}", e))?;
+    let provider = build_provider(&profile, &FileLocation::GoogleDrive)?;
+
+    // Files are taken in path order, so the next call continues after the last one
+    let mut pending: Vec<FileState> = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?
+        .into_iter()
+        .filter(|s| s.location == FileLocation::GoogleDrive)
+        .filter(|s| after.as_ref().is_none_or(|after| s.file_path > *after))
+        .collect();
+    pending.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    let remaining = pending.len().saturating_sub(batch);
+    pending.truncate(batch);
+
+    let mut rotation = KeyRotation {
+        key: keys.fingerprint(),
+        reencrypted: 0,
+        skipped: 0,
+        failed: Vec::new(),
+        remaining,
+    };
+    let mut states = Vec::new();
+    for state in &pending {
+        let location = FileLocation::GoogleDrive;
+        let stored = prefixes.stored_path(&policies.stored_path(Path::new(&state.file_path), &location), &location);
+        match reencrypt(&*provider, &keys, &stored, state.content_hash.as_deref()).await {
+            Ok(None) => rotation.skipped += 1,
+            Ok(Some(metadata)) => {
+                rotation.reencrypted += 1;
+                scan_cache::invalidate(profile_id, Some(&location), &stored);
+                states.push(FileState {
+                    content_hash: metadata.hash,
+                    size_bytes: Some(metadata.size as i64),
+                    modified_at: Some(metadata.modified),
+                    synced_at: Some(chrono::Utc::now()),
+                    status: SyncStatus::Synced,
+                    ..state.clone()
+                });
+            }
+            Err(e) => {
+                tracing::warn!("Failed to re-encrypt {}: {}", state.file_path, e);
+                rotation.failed.push((state.file_path.clone(), e.to_string()));
+            }
+        }
+    }
+
+    // The new hashes on Drive are the synced ones, so the next sync leaves the files alone
+    db.call(move |conn| {
+        let tx = conn.unchecked_transaction()?;
+        DbOperations::upsert_file_states(&tx, &states)?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Files re-encrypted but their states could not be saved: {}", e))?;
+
+    keys.rotation = match (remaining, pending.last()) {
+        (0, _) => None,
+        (_, Some(last)) => keys.rotation.map(|r| drive_encryption::Rotation { after: Some(last.file_path.clone()), ..r }),
+        (_, None) => keys.rotation,
+    };
+    drive_encryption::save(profile_id, &keys).map_err(|e| format!("Failed to save the rotation progress: {}", e))?;
+    if remaining == 0 {
+        tracing::info!("Finished rotating the Drive encryption key of profile {}", profile_id);
+    }
+    Ok(rotation)
+}
+
+/// Rewrite the file at `stored` encrypted with the current key, unless it
+/// already is. Returns its metadata afterwards, or None when it was skipped.
+async fn reencrypt(
+    provider: &dyn StorageProvider,
+    keys: &ProfileKeys,
+    stored: &Path,
+    expected_hash: Option<&str>,
+) -> crate::utils::error::Result<Option<FileMetadata>> {
+    let temp = std::env::temp_dir().join(format!("uvcad_rekey_{}", uuid::Uuid::new_v4().simple()));
+    let plain = temp.with_extension("plain");
+    let result = async {
+        provider.download(stored, &temp).await?;
+        if keys.is_current(&temp)? {
+            return Ok(None);
+        }
+        keys.decrypt_file(&temp, &plain)?;
+        keys.encrypt_file(&plain, &temp)?;
+        // A file changed during the rotation is left to the next sync, which encrypts it anyway
+        match expected_hash {
+            Some(hash) => provider.upload_if_unchanged(&temp, stored, hash).await?,
+            None => provider.upload(&temp, stored).await?,
+        }
+        provider.get_metadata(stored)
+            .await?
+            .map(Some)
+            .ok_or_else(|| UvcadError::FileNotFound { path: stored.to_string_lossy().to_string() })
+    }.await;
+    let _ = tokio::fs::remove_file(&plain).await;
+    let _ = tokio::fs::remove_file(&temp).await;
+    result
+}
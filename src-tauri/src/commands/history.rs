@@ -0,0 +1,81 @@
+use crate::commands::sync::{get_or_create_default_profile, get_profile};
+use crate::db::models::DbOperations;
+use crate::models::file_event::FileEvent;
+use crate::models::file_timing::FileTiming;
+use crate::models::sync_run::SyncRun;
+use serde::Serialize;
+
+/// A sync run and the file changes it recorded, oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncRunDetails {
+    pub run: SyncRun,
+    pub events: Vec<FileEvent>,
+}
+
+/// Everything recorded about one sync run, by the `run_id` found in its result,
+/// progress events and log lines.
+#[tauri::command]
+pub async fn get_sync_run(run_id: String) -> Result<SyncRunDetails, String> {
+    tracing::info!("Get sync run {}", run_id);
+
+    let (_, db) = get_or_create_default_profile().await?;
+    db.call(move |conn| {
+        let Some(run) = DbOperations::get_sync_run(conn, &run_id)? else {
+            return Ok(None);
+        };
+        let events = DbOperations::get_file_events_for_run(conn, &run_id)?;
+        Ok(Some(SyncRunDetails { run, events }))
+    })
+    .await
+    .map_err(|e| format!("Failed to load sync run: {}", e))?
+    .ok_or_else(|| "Sync run not found".to_string())
+}
+
+/// Runs listed per page by `get_sync_history` unless a limit is given.
+const DEFAULT_HISTORY_PAGE: usize = 50;
+
+/// One page of the runs of a profile.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncHistoryPage {
+    /// Newest first.
+    pub runs: Vec<SyncRun>,
+    /// Runs recorded for the profile in all.
+    pub total: usize,
+}
+
+/// Past sync runs of the profile (the active one unless `profile_id` is given)
+/// with their start, end, file counts and error, newest first. `offset` skips
+/// that many of the newest.
+#[tauri::command]
+pub async fn get_sync_history(profile_id: Option<i64>, limit: Option<usize>, offset: Option<usize>) -> Result<SyncHistoryPage, String> {
+    tracing::info!("Get sync history command called");
+
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_PAGE);
+    let offset = offset.unwrap_or(0);
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| {
+        Ok(SyncHistoryPage {
+            runs: DbOperations::get_sync_runs(conn, profile_id, limit, offset)?,
+            total: DbOperations::count_sync_runs(conn, profile_id)?,
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to load sync history: {}", e))
+}
+
+/// Transfers listed by `get_slowest_files` unless a limit is given.
+const DEFAULT_SLOWEST_FILES: usize = 20;
+
+/// The slowest file transfers of a sync run, with the time spent downloading,
+/// hashing and uploading each.
+#[tauri::command]
+pub async fn get_slowest_files(run_id: String, limit: Option<usize>) -> Result<Vec<FileTiming>, String> {
+    tracing::info!("Get slowest files of run {}", run_id);
+
+    let limit = limit.unwrap_or(DEFAULT_SLOWEST_FILES);
+    let (_, db) = get_or_create_default_profile().await?;
+    db.call(move |conn| DbOperations::get_slowest_files(conn, &run_id, limit))
+        .await
+        .map_err(|e| format!("Failed to load file timings: {}", e))
+}
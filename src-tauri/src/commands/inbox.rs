@@ -0,0 +1,155 @@
+use crate::commands::notifications;
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::cad_temp;
+use crate::core::inbox::{self, InboxPolicy};
+use crate::core::notifications::{Notification, NotificationKind, Severity};
+use crate::core::scan_cache;
+use crate::db::models::DbOperations;
+use crate::models::file_state::FileLocation;
+use crate::models::inbox_entry::InboxEntry;
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::{google_drive::GoogleDriveProvider, traits::StorageProvider};
+use crate::utils::i18n;
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Manager;
+use tokio::sync::Mutex;
+
+/// How often the background watcher checks the inbox.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of log entries returned to the UI.
+const LOG_LIMIT: usize = 200;
+
+/// Serializes inbox passes so the watcher and a manual run never race on the same files.
+static INBOX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Poll the configured inbox forever. Spawned once at startup.
+pub async fn watch_inbox(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        match run_inbox_pass().await {
+            Ok(entries) if !entries.is_empty() => {
+                notify_processed(&entries);
+                let _ = app.emit_all("inbox-processed", &entries);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Inbox pass failed: {}", e),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn process_inbox(app: tauri::AppHandle) -> Result<Vec<InboxEntry>, String> {
+    tracing::info!("Process inbox command called");
+
+    let entries = run_inbox_pass().await?;
+    if !entries.is_empty() {
+        notify_processed(&entries);
+        let _ = app.emit_all("inbox-processed", &entries);
+    }
+    Ok(entries)
+}
+
+fn notify_processed(entries: &[InboxEntry]) {
+    notifications::notify(Notification::new(
+        NotificationKind::InboxProcessed,
+        Severity::Info,
+        i18n::tr("notification.inbox_processed", &[("count", entries.len().to_string())]),
+    ));
+}
+
+#[tauri::command]
+pub async fn get_inbox_log() -> Result<Vec<InboxEntry>, String> {
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| DbOperations::get_inbox_entries(conn, profile_id, LOG_LIMIT))
+        .await
+        .map_err(|e| format!("Failed to load inbox log: {}", e))
+}
+
+async fn run_inbox_pass() -> Result<Vec<InboxEntry>, String> {
+    let _guard = INBOX_LOCK.lock().await;
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let (Some(location), Some(inbox_path)) = (&profile.inbox_location, &profile.inbox_path) else {
+        return Ok(Vec::new());
+    };
+    if profile.local_path.is_empty() {
+        return Err("Local path not configured".to_string());
+    }
+
+    let policy = InboxPolicy::from_profile(&profile)
+        .map_err(|e| format!("Invalid inbox configuration: {}", e))?;
+
+    let (inbox_dir, settle_time) = match location.parse::<FileLocation>()? {
+        FileLocation::Local => {
+            let quiet_period = cad_temp::quiet_period(profile.quiet_period_secs)
+                .map_err(|e| format!("Invalid save settings: {}", e))?;
+            (PathBuf::from(inbox_path), quiet_period.max(inbox::SETTLE_TIME))
+        }
+        // Drive deliveries are complete once listed; stage them locally first
+        FileLocation::GoogleDrive => (stage_drive_inbox(&profile, inbox_path).await?, Duration::ZERO),
+        FileLocation::Smb => return Err("Samba inbox is not supported; use a local path".to_string()),
+    };
+
+    let profile_id = profile.id.unwrap();
+    let local_root = PathBuf::from(&profile.local_path);
+    let entries = db.call(move |conn| inbox::process_inbox(
+        conn,
+        profile_id,
+        &policy,
+        &inbox_dir,
+        &local_root,
+        settle_time,
+    ))
+    .await
+    .map_err(|e| format!("Failed to process inbox: {}", e))?;
+
+    for target in entries.iter().filter_map(|e| e.target_path.as_deref()) {
+        scan_cache::invalidate(profile_id, Some(&FileLocation::Local), Path::new(target));
+    }
+    Ok(entries)
+}
+
+/// Move files from the Drive inbox folder into a local staging directory.
+/// Only files directly in the inbox folder are taken; subfolders are left alone.
+async fn stage_drive_inbox(profile: &SyncProfile, folder_id: &str) -> Result<PathBuf, String> {
+    let staging = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| "Failed to get project directory".to_string())?
+        .data_dir()
+        .join("inbox")
+        .join(profile.id.unwrap_or_default().to_string());
+    tokio::fs::create_dir_all(&staging)
+        .await
+        .map_err(|e| format!("Failed to create inbox staging folder: {}", e))?;
+
+    let gdrive = GoogleDriveProvider::for_profile(profile, folder_id.to_string())
+        .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+    if !gdrive.is_authenticated() {
+        return Err("Google Drive inbox configured but not authenticated".to_string());
+    }
+
+    let files = gdrive.list_files(Path::new(""))
+        .await
+        .map_err(|e| format!("Failed to list Drive inbox: {}", e))?;
+
+    for file in files.iter().filter(|f| f.path.components().count() == 1) {
+        let dest = staging.join(&file.path);
+        if dest.exists() {
+            continue;
+        }
+        gdrive.download(&file.path, &dest)
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", file.path.display(), e))?;
+        gdrive.delete(&file.path)
+            .await
+            .map_err(|e| format!("Failed to remove {} from Drive inbox: {}", file.path.display(), e))?;
+    }
+
+    Ok(staging)
+}
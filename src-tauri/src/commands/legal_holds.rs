@@ -0,0 +1,86 @@
+use crate::commands::sync::get_profile;
+use crate::core::legal_hold;
+use crate::core::plan_approval;
+use crate::core::roles::{self, Permission};
+use crate::db::models::DbOperations;
+use crate::models::legal_hold::LegalHold;
+
+/// Put a file or folder of the profile (the active one unless `profile_id` is
+/// given) under legal hold: from now on no sync deletes or overwrites any copy
+/// of it. Who placed the hold, when and why is recorded.
+#[tauri::command]
+pub async fn place_legal_hold(path: String, reason: String, profile_id: Option<i64>) -> Result<LegalHold, String> {
+    tracing::info!("Place legal hold command called: {}", path);
+
+    let path = legal_hold::normalize_path(&path).map_err(|e| e.to_string())?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err("A reason for the legal hold is required".to_string());
+    }
+    let (profile, db) = get_profile(profile_id).await?;
+
+    let mut hold = LegalHold {
+        id: None,
+        profile_id: profile.id.unwrap(),
+        path,
+        reason,
+        placed_by: plan_approval::current_user(),
+        placed_at: chrono::Utc::now(),
+        released_by: None,
+        released_at: None,
+        release_reason: None,
+    };
+    let placed = hold.clone();
+    hold.id = Some(db.call(move |conn| DbOperations::place_legal_hold(conn, &placed))
+        .await
+        .map_err(|e| format!("Failed to place legal hold: {}", e))?);
+
+    tracing::info!("{} placed a legal hold on {}: {}", hold.placed_by, hold.path, hold.reason);
+    Ok(hold)
+}
+
+/// Lift a legal hold. The hold stays on record with who released it, when
+/// and why. Admins only.
+#[tauri::command]
+pub async fn release_legal_hold(hold_id: i64, reason: String, profile_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("Release legal hold command called: {}", hold_id);
+
+    roles::require(Permission::ReleaseLegalHold).map_err(String::from)?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err("A reason for releasing the legal hold is required".to_string());
+    }
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+
+    let user = plan_approval::current_user();
+    let released = db.call(move |conn| {
+        if !DbOperations::get_legal_holds(conn, profile_id)?.iter().any(|h| h.id == Some(hold_id)) {
+            return Ok(false);
+        }
+        DbOperations::release_legal_hold(conn, hold_id, &user, chrono::Utc::now(), &reason)
+    })
+    .await
+    .map_err(|e| format!("Failed to release legal hold: {}", e))?;
+
+    if !released {
+        return Err(format!("No active legal hold: {}", hold_id));
+    }
+    tracing::info!("Released legal hold {}", hold_id);
+    Ok(())
+}
+
+/// The legal holds of the profile, oldest first; released ones only when
+/// `include_released` is set.
+#[tauri::command]
+pub async fn list_legal_holds(profile_id: Option<i64>, include_released: Option<bool>) -> Result<Vec<LegalHold>, String> {
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    let holds = db.call(move |conn| DbOperations::get_legal_holds(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load legal holds: {}", e))?;
+
+    Ok(holds.into_iter()
+        .filter(|hold| include_released.unwrap_or(false) || hold.is_active())
+        .collect())
+}
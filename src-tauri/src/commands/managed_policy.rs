@@ -0,0 +1,150 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::managed_policy::{self, LoadedPolicy};
+use crate::core::media_exchange::MEDIA_PROFILE_NAME;
+use crate::core::scan_cache;
+use crate::core::sync_policy::SyncPolicy;
+use crate::db::models::DbOperations;
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::unc;
+use serde::Serialize;
+use std::path::Path;
+
+/// What applying the managed policy changed on this computer.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedPolicyRefresh {
+    pub loaded: LoadedPolicy,
+    /// Profiles whose settings were changed to the managed ones.
+    pub updated_profiles: Vec<String>,
+    pub created_profiles: Vec<String>,
+    /// Managed profiles that could not be created here, with why.
+    pub skipped_profiles: Vec<(String, String)>,
+}
+
+/// The managed policy in force on this computer, if any.
+#[tauri::command]
+pub async fn get_managed_policy() -> Result<Option<LoadedPolicy>, String> {
+    tracing::info!("Get managed policy command called");
+    Ok(managed_policy::current())
+}
+
+/// Load the managed policy again, e.g. after it was edited, and apply it.
+#[tauri::command]
+pub async fn refresh_managed_policy() -> Result<Option<ManagedPolicyRefresh>, String> {
+    tracing::info!("Refresh managed policy command called");
+    refresh().await
+}
+
+/// Load the managed policy at startup; the settings it applied the last time
+/// stay in the profiles when that fails.
+pub async fn refresh_at_startup() {
+    match refresh().await {
+        Ok(Some(refreshed)) => tracing::info!(
+            "Applied the managed policy from {}: {} profiles updated, {} created",
+            refreshed.loaded.source, refreshed.updated_profiles.len(), refreshed.created_profiles.len()
+        ),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("{}", e),
+    }
+}
+
+/// Load the managed policy set for this computer and apply it: every profile
+/// gets the managed settings unless an admin let it keep its own, and managed
+/// profiles missing here are created. None when there is no managed policy.
+pub(crate) async fn refresh() -> Result<Option<ManagedPolicyRefresh>, String> {
+    let (active, db) = get_or_create_default_profile().await?;
+    let Some(source) = managed_policy::source(active.managed_policy_source.as_deref()) else {
+        managed_policy::set_current(None);
+        return Ok(None);
+    };
+    let loaded = managed_policy::load(&source)
+        .await
+        .map_err(|e| format!("Failed to load the managed policy: {}", e))?;
+    managed_policy::set_current(Some(loaded.clone()));
+
+    let managed = loaded.policy.clone();
+    let (updated, created, skipped) = db.call(move |conn| {
+        let profiles = DbOperations::list_sync_profiles(conn)?;
+        let mut updated = Vec::new();
+        for profile in profiles.iter().filter(|p| p.name != MEDIA_PROFILE_NAME && !p.override_managed_policy) {
+            let current = SyncPolicy::from_profile(profile);
+            let applied = managed.apply(&current)?;
+            if applied != current {
+                let mut profile = profile.clone();
+                applied.apply_to(&mut profile);
+                DbOperations::update_sync_profile(conn, &profile)?;
+                updated.push((profile.id.unwrap(), profile.name));
+            }
+        }
+
+        let mut created = Vec::new();
+        let mut skipped = Vec::new();
+        for template in managed.profiles.iter().filter(|t| !profiles.iter().any(|p| p.name == t.name)) {
+            let local_path = unc::canonicalize(&template.local_path);
+            if !Path::new(&local_path).is_dir() {
+                skipped.push((template.name.clone(), format!("{} is not a folder on this computer", local_path)));
+                continue;
+            }
+            let mut profile = SyncProfile::new(template.name.clone(), local_path);
+            profile.gdrive_folder_id = template.gdrive_folder_id.clone().filter(|id| !id.trim().is_empty());
+            profile.smb_share_path = template.smb_share_path.as_deref()
+                .filter(|p| !p.trim().is_empty())
+                .map(unc::canonicalize);
+            managed.apply(&SyncPolicy::from_profile(&profile))?.apply_to(&mut profile);
+            DbOperations::create_sync_profile(conn, &profile)?;
+            created.push(profile.name);
+        }
+        Ok((updated, created, skipped))
+    })
+    .await
+    .map_err(|e| format!("Failed to apply the managed policy: {}", e))?;
+
+    // Changed filters decide what the next sync sees, so it scans from scratch
+    for (profile_id, _) in &updated {
+        scan_cache::clear_profile(*profile_id);
+    }
+    Ok(Some(ManagedPolicyRefresh {
+        loaded,
+        updated_profiles: updated.into_iter().map(|(_, name)| name).collect(),
+        created_profiles: created,
+        skipped_profiles: skipped,
+    }))
+}
+
+/// Fail unless a managed policy can be loaded from `source`.
+pub(crate) async fn check_source(source: &str) -> Result<(), String> {
+    // The environment override decides where it comes from anyway
+    if std::env::var(managed_policy::SOURCE_ENV_VAR).is_ok() {
+        return Ok(());
+    }
+    managed_policy::fetch(source)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to load the managed policy: {}", e))
+}
+
+/// Fail when the profile syncs with a location the managed policy does not allow.
+pub(crate) fn check_locations(profile: &SyncProfile) -> Result<(), String> {
+    match managed_policy::current() {
+        Some(loaded) => loaded.policy.check_profile(profile).map_err(String::from),
+        None => Ok(()),
+    }
+}
+
+/// Fail when `proposed` changes settings the managed policy decides while the
+/// profile is not allowed to keep its own.
+pub(crate) fn check_managed_settings(proposed: &SyncPolicy, override_managed_policy: bool) -> Result<(), String> {
+    let Some(loaded) = managed_policy::current() else {
+        return Ok(());
+    };
+    if override_managed_policy {
+        return Ok(());
+    }
+    let deviations = loaded.policy.deviations(proposed).map_err(|e| e.to_string())?;
+    if deviations.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "The managed policy from {} decides {}; an admin can let the profile keep its own settings",
+        loaded.source, deviations.join(", ")
+    ))
+}
@@ -0,0 +1,138 @@
+use crate::commands::sync::{begin_sync, finish_sync, get_or_create_default_profile, SyncResultDto};
+use crate::core::cad_normalizer::HashPolicy;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::media_exchange::{self, ArchiveProvider, MediaArchive, MediaExportSummary, MEDIA_PROFILE_NAME};
+use crate::core::scan_cache;
+use crate::core::sync_engine::{self, DeletionLimits, SyncEngine, SyncResult};
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::{local_fs::LocalFsProvider, traits::StorageProvider};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+fn local_provider(profile: &SyncProfile, db: &DbHandle) -> LocalFsProvider {
+    let hash_policy = HashPolicy::from_config(profile.normalized_extensions.as_deref())
+        .with_sampling_above(profile.sampled_hash_min_mb)
+        .with_known_hashes(db.clone());
+    LocalFsProvider::new(PathBuf::from(&profile.local_path)).with_hash_policy(hash_policy)
+}
+
+/// The profile tracking exchanges over removable media, created on first use.
+async fn media_profile_id(db: &DbHandle, profile: &SyncProfile) -> Result<i64, String> {
+    let local_path = profile.local_path.clone();
+    db.call(move |conn| match DbOperations::find_sync_profile_id(conn, MEDIA_PROFILE_NAME)? {
+        Some(id) => Ok(id),
+        None => DbOperations::create_sync_profile(conn, &SyncProfile::new(MEDIA_PROFILE_NAME.to_string(), local_path)),
+    })
+    .await
+    .map_err(|e| format!("Failed to get media exchange profile: {}", e))
+}
+
+/// Write the files changed since the last exchange (or all with `include_all`)
+/// to an encrypted archive in `folder`, e.g. a USB drive, for import at a site
+/// without connectivity.
+#[tauri::command]
+pub async fn export_to_media(folder: String, passphrase: String, include_all: Option<bool>) -> Result<MediaExportSummary, String> {
+    tracing::info!("Export to media command called for {}", folder);
+
+    if !Path::new(&folder).is_dir() {
+        return Err(format!("Folder does not exist: {}", folder));
+    }
+    let (profile, db) = get_or_create_default_profile().await?;
+    if profile.local_path.is_empty() {
+        return Err("Local path is not configured".to_string());
+    }
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+    let media_id = media_profile_id(&db, &profile).await?;
+    let baseline = db.call(move |conn| media_exchange::baseline(conn, media_id))
+        .await
+        .map_err(|e| format!("Failed to read media exchange state: {}", e))?;
+
+    let output = Path::new(&folder).join(format!(
+        "uvcad_{}.{}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        media_exchange::EXTENSION,
+    ));
+    let (manifest, summary) = media_exchange::export(
+        &local_provider(&profile, &db),
+        &baseline,
+        &policies,
+        &profile.name,
+        &output,
+        &passphrase,
+        include_all.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| format!("Failed to export to media: {}", e))?;
+
+    // The other site will have exactly these files once it imports the archive
+    db.call(move |conn| media_exchange::record_exchanged(conn, media_id, &manifest, &[FileLocation::Local, FileLocation::Smb]))
+        .await
+        .map_err(|e| format!("Failed to record media export: {}", e))?;
+
+    tracing::info!(
+        "Exported {} of {} files to {}",
+        summary.files_included, summary.files_listed, summary.archive_path
+    );
+    Ok(summary)
+}
+
+/// Apply an archive written by `export_to_media` at another site. It is planned
+/// like a sync against the archive: files changed here since the last exchange
+/// are kept and reported as conflicts where the other site changed them too.
+#[tauri::command]
+pub async fn import_from_media(archive_path: String, passphrase: String) -> Result<SyncResultDto, String> {
+    tracing::info!("Import from media command called for {}", archive_path);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+
+    let result = import_inner(profile, db, &archive_path, &passphrase).await;
+    finish_sync(profile_id, result)
+}
+
+async fn import_inner(
+    profile: SyncProfile,
+    db: DbHandle,
+    archive_path: &str,
+    passphrase: &str,
+) -> Result<(SyncResultDto, SyncResult), String> {
+    if profile.local_path.is_empty() {
+        return Err("Local path is not configured".to_string());
+    }
+    let media_id = media_profile_id(&db, &profile).await?;
+    let deletion_limits = DeletionLimits::from_config(profile.max_deletion_count, profile.max_deletion_percentage)
+        .map_err(|e| format!("Invalid deletion thresholds: {}", e))?;
+
+    let (path, passphrase) = (PathBuf::from(archive_path), passphrase.to_string());
+    let archive = tokio::task::spawn_blocking(move || MediaArchive::open(&path, &passphrase))
+        .await
+        .map_err(|e| format!("Failed to open archive: {}", e))?
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let archive = Arc::new(archive);
+    tracing::info!(
+        "Importing {} files from {} exported at {}",
+        archive.manifest.files.len(), archive.manifest.source, archive.manifest.created_at
+    );
+
+    let local: Arc<Mutex<dyn StorageProvider>> = Arc::new(Mutex::new(local_provider(&profile, &db)));
+    let media: Arc<Mutex<dyn StorageProvider>> = Arc::new(Mutex::new(ArchiveProvider::new(archive.clone())));
+    let result = SyncEngine::new(media_id, local, None, Some(media), db.clone())
+        .with_deletion_limits(deletion_limits)
+        .with_run_id(&sync_engine::new_run_id())
+        .start_sync()
+        .await
+        .map_err(|e| format!("Import failed: {}", e))?;
+
+    // Changes made here go back with the next export, so the media keeps the other site's files
+    db.call(move |conn| media_exchange::record_exchanged(conn, media_id, &archive.manifest, &[FileLocation::Smb]))
+        .await
+        .map_err(|e| format!("Failed to record media import: {}", e))?;
+    scan_cache::clear_profile(profile.id.unwrap());
+
+    Ok((SyncResultDto::from_result(&result), result))
+}
@@ -1,3 +1,38 @@
+pub mod archive;
 pub mod auth;
+pub mod changes;
+pub mod compare;
 pub mod config;
+pub mod diagnostics;
+pub mod duplicates;
+pub mod encryption;
+pub mod history;
+pub mod inbox;
+pub mod legal_holds;
+pub mod managed_policy;
+pub mod media;
+pub mod naming;
+pub mod notifications;
+pub mod package;
+pub mod pauses;
+pub mod plans;
+pub mod previews;
+pub mod profiles;
+pub mod publishing;
+pub mod references;
+pub mod release;
+pub mod reports;
+pub mod revisions;
+pub mod roles;
+pub mod shell_integration;
+pub mod shortcuts;
+pub mod simulation;
+pub mod snapshots;
 pub mod sync;
+pub mod templates;
+pub mod tiering;
+pub mod transfers;
+pub mod trash;
+pub mod updates;
+pub mod watcher;
+pub mod webdav;
@@ -0,0 +1,19 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::naming::{self, NamingConvention, NamingViolation};
+
+/// Report tracked files whose names break the profile's naming convention.
+#[tauri::command]
+pub async fn get_naming_violations() -> Result<Vec<NamingViolation>, String> {
+    tracing::info!("Get naming violations command called");
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let convention = NamingConvention::from_config(
+        profile.naming_rules.as_deref(),
+        profile.naming_blocked_locations.as_deref(),
+    ).map_err(|e| format!("Invalid naming convention: {}", e))?;
+
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| naming::find_violations(conn, profile_id, &convention))
+        .await
+        .map_err(|e| format!("Failed to check file names: {}", e))
+}
@@ -0,0 +1,74 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::notifications::{
+    self, ChannelConfig, Dispatcher, Notification, NotificationKind, NotificationSink, Severity,
+};
+use crate::utils::error::{self, UvcadError};
+use crate::utils::i18n;
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+use tauri::Manager;
+
+/// The app, for the channel that shows notifications in its window. Set once at startup.
+static APP: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+/// Emits a "notification" event for the frontend to show.
+struct AppSink(tauri::AppHandle);
+
+#[async_trait]
+impl NotificationSink for AppSink {
+    fn name(&self) -> &str {
+        "app"
+    }
+
+    async fn send(&self, notification: &Notification) -> error::Result<()> {
+        self.0.emit_all("notification", notification)
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to emit notification: {}", e)))
+    }
+}
+
+pub fn set_app(app: tauri::AppHandle) {
+    let _ = APP.set(app);
+}
+
+/// Deliver through the channels of the profile's `notification_channels`
+/// from now on.
+pub fn configure(channels: Option<&str>) -> Result<(), String> {
+    let channels = ChannelConfig::parse_all(channels)
+        .map_err(|e| format!("Invalid notification channels: {}", e))?;
+
+    let mut dispatcher = Dispatcher::new();
+    for channel in channels {
+        let sink = channel.sink().map_err(|e| format!("Invalid notification channels: {}", e))?;
+        dispatcher = match (channel, sink) {
+            (_, Some((sink, filter))) => dispatcher.with_sink(sink, filter),
+            (ChannelConfig::App { filter }, None) => match APP.get() {
+                Some(app) => dispatcher.with_sink(Arc::new(AppSink(app.clone())), filter),
+                None => dispatcher,
+            },
+            (_, None) => dispatcher,
+        };
+    }
+    notifications::install(dispatcher);
+    Ok(())
+}
+
+/// Send in the background, so a slow webhook never holds up a sync.
+pub(crate) fn notify(notification: Notification) {
+    tauri::async_runtime::spawn(notifications::notify(notification));
+}
+
+/// Send a test notification through every configured channel. Returns how many
+/// channels accepted it.
+#[tauri::command]
+pub async fn send_test_notification() -> Result<usize, String> {
+    tracing::info!("Send test notification command called");
+
+    let (profile, _) = get_or_create_default_profile().await?;
+    configure(profile.notification_channels.as_deref())?;
+    Ok(notifications::notify(Notification::new(
+        NotificationKind::Test,
+        Severity::Info,
+        i18n::tr("notification.test", &[]),
+    )).await)
+}
@@ -0,0 +1,130 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::package_export;
+use crate::core::scan_cache;
+use crate::models::file_state::FileLocation;
+use crate::providers::{
+    google_drive::GoogleDriveProvider,
+    local_fs::LocalFsProvider,
+    samba::{self, SambaProvider},
+    traits::StorageProvider,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Deserialize)]
+pub struct PackageExportRequest {
+    pub assembly_path: String,
+    /// Endpoint to upload the package to: "local", "gdrive" or "smb".
+    pub destination: String,
+    /// Folder on the destination, relative to the profile's root there.
+    pub folder: String,
+    #[serde(default)]
+    pub strip_metadata: bool,
+    /// Create an anyone-with-the-link share URL (Google Drive only).
+    #[serde(default)]
+    pub create_share_link: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageExportResult {
+    pub package_path: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub share_link: Option<String>,
+    pub unresolved_references: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn export_package(request: PackageExportRequest) -> Result<PackageExportResult, String> {
+    tracing::info!("Export package: {:?}", request);
+
+    let destination: FileLocation = request.destination.parse()?;
+    let (profile, db) = get_or_create_default_profile().await?;
+    let local_root = PathBuf::from(&profile.local_path);
+
+    let stem = Path::new(&request.assembly_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("package");
+    let package_name = format!("{}_{}.zip", stem, chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    let temp_package = std::env::temp_dir().join(format!("uvcad_{}", package_name));
+
+    let manifest = {
+        let profile_id = profile.id.unwrap();
+        let local_root = local_root.clone();
+        let assembly_path = request.assembly_path.clone();
+        let strip_metadata = request.strip_metadata;
+        let temp_package = temp_package.clone();
+        db.call(move |conn| package_export::build_package(
+            conn,
+            profile_id,
+            &local_root,
+            &assembly_path,
+            strip_metadata,
+            &temp_package,
+        ))
+        .await
+        .map_err(|e| format!("Failed to build package: {}", e))?
+    };
+
+    let package_path = Path::new(&request.folder).join(&package_name);
+    let upload_result = upload_package(&profile, &destination, &temp_package, &package_path, request.create_share_link).await;
+    let _ = tokio::fs::remove_file(&temp_package).await;
+    scan_cache::invalidate(profile.id.unwrap(), Some(&destination), &package_path);
+    let share_link = upload_result?;
+
+    Ok(PackageExportResult {
+        package_path: package_path.to_string_lossy().to_string(),
+        file_count: manifest.files.len(),
+        total_bytes: manifest.files.iter().map(|f| f.size).sum(),
+        share_link,
+        unresolved_references: manifest.unresolved_references,
+    })
+}
+
+async fn upload_package(
+    profile: &crate::models::sync_profile::SyncProfile,
+    destination: &FileLocation,
+    package: &Path,
+    package_path: &Path,
+    create_share_link: bool,
+) -> Result<Option<String>, String> {
+    match destination {
+        FileLocation::Local => {
+            LocalFsProvider::new(PathBuf::from(&profile.local_path))
+                .upload(package, package_path)
+                .await
+                .map_err(|e| format!("Failed to write package: {}", e))?;
+            Ok(None)
+        }
+        FileLocation::Smb => {
+            let share_path = profile.smb_share_path.as_ref()
+                .ok_or_else(|| "Samba share not configured".to_string())?;
+            SambaProvider::new(PathBuf::from(share_path))
+                .with_fallbacks(samba::fallback_paths(profile.smb_fallback_paths.as_deref()))
+                .upload(package, package_path)
+                .await
+                .map_err(|e| format!("Failed to upload package to Samba: {}", e))?;
+            Ok(None)
+        }
+        FileLocation::GoogleDrive => {
+            let folder_id = profile.gdrive_folder_id.as_ref()
+                .ok_or_else(|| "Google Drive folder not configured".to_string())?;
+            let gdrive = GoogleDriveProvider::for_profile(profile, folder_id.clone())
+                .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+
+            gdrive.upload(package, package_path)
+                .await
+                .map_err(|e| format!("Failed to upload package to Google Drive: {}", e))?;
+
+            if create_share_link {
+                let link = gdrive.create_share_link(package_path)
+                    .await
+                    .map_err(|e| format!("Failed to create share link: {}", e))?;
+                Ok(Some(link))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+}
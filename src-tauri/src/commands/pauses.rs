@@ -0,0 +1,74 @@
+use crate::commands::sync::get_profile;
+use crate::core::plan_approval;
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::FileLocation;
+use crate::models::paused_location::PausedLocation;
+
+/// Stop syncs of the profile (the active one unless `profile_id` is given)
+/// from transferring to or from `location` ("local", "gdrive" or "smb"),
+/// e.g. while its quota is exhausted, and keep the other locations in sync.
+/// Changes involving it are deferred until it is resumed.
+#[tauri::command]
+pub async fn pause_provider(location: String, reason: Option<String>, profile_id: Option<i64>) -> Result<PausedLocation, String> {
+    tracing::info!("Pause provider command called: {}", location);
+
+    let location: FileLocation = location.parse()?;
+    let (profile, db) = get_profile(profile_id).await?;
+
+    let paused = PausedLocation {
+        profile_id: profile.id.unwrap(),
+        location,
+        reason: reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty()),
+        paused_by: plan_approval::current_user(),
+        paused_at: chrono::Utc::now(),
+    };
+    let recorded = paused.clone();
+    db.call(move |conn| DbOperations::pause_location(conn, &recorded))
+        .await
+        .map_err(|e| format!("Failed to pause {}: {}", paused.location.as_str(), e))?;
+
+    tracing::info!("{} paused syncing with {}", paused.paused_by, paused.location.as_str());
+    Ok(paused)
+}
+
+/// Let syncs transfer to and from a paused location again. The next sync
+/// catches up on the changes deferred while it was paused.
+#[tauri::command]
+pub async fn resume_provider(location: String, profile_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("Resume provider command called: {}", location);
+
+    let location: FileLocation = location.parse()?;
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+
+    let resumed = location.clone();
+    let was_paused = db.call(move |conn| DbOperations::resume_location(conn, profile_id, &resumed))
+        .await
+        .map_err(|e| format!("Failed to resume {}: {}", location.as_str(), e))?;
+    if !was_paused {
+        return Err(format!("{} is not paused", location.as_str()));
+    }
+    Ok(())
+}
+
+/// The paused locations of the profile, longest paused first.
+#[tauri::command]
+pub async fn list_paused_providers(profile_id: Option<i64>) -> Result<Vec<PausedLocation>, String> {
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| DbOperations::get_paused_locations(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load paused locations: {}", e))
+}
+
+/// Fail when `location` is paused, for operations that talk to it directly
+/// instead of going through a sync plan.
+pub(crate) async fn ensure_not_paused(db: &DbHandle, profile_id: i64, location: &FileLocation) -> Result<(), String> {
+    let paused = db.call(move |conn| DbOperations::get_paused_locations(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load paused locations: {}", e))?;
+    match paused.iter().find(|p| &p.location == location) {
+        Some(p) => Err(format!("{} is paused since {} by {}", location.as_str(), p.paused_at.format("%Y-%m-%d %H:%M"), p.paused_by)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,92 @@
+use crate::commands::sync::{begin_sync, finish_sync, get_or_create_default_profile, run_sync_inner, SyncResultDto};
+use crate::core::plan_approval::{self, ApprovalPolicy};
+use crate::core::roles::{self, Permission};
+use crate::core::sync_engine;
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::sync_plan::{PlanStatus, PlannedOperation, SyncPlan};
+use crate::models::sync_profile::SyncProfile;
+
+/// Sync plans of the active profile awaiting approval, newest first.
+#[tauri::command]
+pub async fn get_pending_plans() -> Result<Vec<SyncPlan>, String> {
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| DbOperations::get_pending_sync_plans(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get pending plans: {}", e))
+}
+
+/// Approve a pending plan and run the sync it describes. If the files changed
+/// since the plan was made, steps it does not cover are held in a new plan.
+#[tauri::command]
+pub async fn approve_plan(app: tauri::AppHandle, plan_id: i64) -> Result<SyncResultDto, String> {
+    tracing::info!("Approve sync plan {}", plan_id);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let plan = db.call(move |conn| DbOperations::get_sync_plan(conn, plan_id))
+        .await
+        .map_err(|e| format!("Failed to get sync plan: {}", e))?;
+    let deletes = plan.iter()
+        .flat_map(|plan| &plan.steps)
+        .any(|step| matches!(step.operation, PlannedOperation::Delete { .. }));
+    if deletes {
+        roles::require(Permission::ApproveDeletions).map_err(String::from)?;
+    }
+    let (plan, user) = decide(&profile, &db, plan_id, PlanStatus::Approved).await?;
+
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+    let result = run_sync_inner(&app, profile, db.clone(), None, Some((plan.steps, user.clone())), &sync_engine::new_run_id()).await;
+    let outcome = match &result {
+        Ok((dto, _)) if dto.pending_plan_id.is_none() => Some(PlanStatus::Executed),
+        Ok(_) => Some(PlanStatus::Superseded),
+        Err(_) => None,
+    };
+    if let Some(status) = outcome {
+        let recorded = db.call(move |conn| DbOperations::update_sync_plan_status(conn, plan_id, &status, None)).await;
+        if let Err(e) = recorded {
+            tracing::warn!("Failed to record outcome of plan {}: {}", plan_id, e);
+        }
+    }
+    tracing::info!("Sync plan {} approved by {}", plan_id, user);
+    finish_sync(profile_id, result)
+}
+
+/// Reject a pending plan. The next sync plans again from scratch.
+#[tauri::command]
+pub async fn reject_plan(plan_id: i64) -> Result<(), String> {
+    tracing::info!("Reject sync plan {}", plan_id);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let (_, user) = decide(&profile, &db, plan_id, PlanStatus::Rejected).await?;
+    tracing::info!("Sync plan {} rejected by {}", plan_id, user);
+    Ok(())
+}
+
+/// Check that the current user may decide on the pending plan, then record the decision.
+async fn decide(profile: &SyncProfile, db: &DbHandle, plan_id: i64, status: PlanStatus) -> Result<(SyncPlan, String), String> {
+    let policy = ApprovalPolicy::from_config(profile.plan_approvers.as_deref(), profile.auto_approved_operations.as_deref())
+        .map_err(|e| format!("Invalid plan approval settings: {}", e))?;
+    let user = plan_approval::current_user();
+    if !policy.can_approve(&user) {
+        return Err(format!("{} is not allowed to approve sync plans", user));
+    }
+
+    let profile_id = profile.id.unwrap();
+    let decided_by = user.clone();
+    db.call(move |conn| {
+        let plan = match DbOperations::get_sync_plan(conn, plan_id)? {
+            Some(plan) if plan.profile_id == profile_id => plan,
+            _ => return Ok(Err(format!("Sync plan not found: {}", plan_id))),
+        };
+        if plan.status != PlanStatus::Pending {
+            return Ok(Err(format!("Sync plan {} is already {}", plan_id, plan.status.as_str())));
+        }
+        DbOperations::update_sync_plan_status(conn, plan_id, &status, Some(&decided_by))?;
+        Ok(Ok(plan))
+    })
+    .await
+    .map_err(|e| format!("Failed to update sync plan: {}", e))?
+    .map(|plan| (plan, user))
+}
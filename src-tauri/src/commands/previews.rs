@@ -0,0 +1,45 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::mesh::MeshInfo;
+use crate::core::previews;
+use std::path::Path;
+
+/// Path of the cached preview image for a local file, extracting it first if
+/// needed. `None` when the file format has no preview.
+#[tauri::command]
+pub async fn get_thumbnail(path: String) -> Result<Option<String>, String> {
+    let (profile, _) = get_or_create_default_profile().await?;
+    let root = previews::thumbnail_root(profile.id.unwrap())
+        .map_err(|e| format!("Failed to locate thumbnail cache: {}", e))?;
+
+    if let Some(cached) = previews::cached_thumbnail(&root, &path) {
+        return Ok(Some(cached.to_string_lossy().to_string()));
+    }
+
+    let source = Path::new(&profile.local_path).join(&path);
+    let preview = previews::extract_preview(&source)
+        .map_err(|e| format!("Failed to extract preview: {}", e))?;
+    let Some(preview) = preview else {
+        return Ok(None);
+    };
+
+    let target = previews::store_thumbnail(&root, &path, &preview)
+        .map_err(|e| format!("Failed to cache preview: {}", e))?;
+    Ok(Some(target.to_string_lossy().to_string()))
+}
+
+/// Triangle count and bounding box of a local mesh file (STL, OBJ, 3MF).
+/// `None` for other formats.
+#[tauri::command]
+pub async fn get_mesh_info(path: String) -> Result<Option<MeshInfo>, String> {
+    let (profile, _) = get_or_create_default_profile().await?;
+    let root = previews::thumbnail_root(profile.id.unwrap())
+        .map_err(|e| format!("Failed to locate thumbnail cache: {}", e))?;
+
+    if let Some(info) = previews::cached_mesh_info(&root, &path) {
+        return Ok(Some(info));
+    }
+
+    // Extracting the thumbnail caches the summary alongside it
+    get_thumbnail(path.clone()).await?;
+    Ok(previews::cached_mesh_info(&root, &path))
+}
@@ -0,0 +1,169 @@
+use crate::commands::config::apply_profile_settings;
+use crate::commands::managed_policy;
+use crate::commands::sync::{begin_sync, create_database, end_sync, get_profile, is_syncing};
+use crate::commands::watcher;
+use crate::core::drive_encryption;
+use crate::core::media_exchange::MEDIA_PROFILE_NAME;
+use crate::core::roles::{self, Permission};
+use crate::core::scan_cache;
+use crate::db::models::DbOperations;
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::unc;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+/// A profile as listed for switching between project folders.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSummary {
+    pub id: i64,
+    pub name: String,
+    pub local_path: String,
+    pub gdrive_folder_id: Option<String>,
+    pub smb_share_path: Option<String>,
+    /// Commands work on this profile unless they are given another one.
+    pub active: bool,
+    pub is_syncing: bool,
+    pub last_sync_at: Option<DateTime<Utc>>,
+    pub last_successful_sync_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl ProfileSummary {
+    fn new(profile: &SyncProfile, active_id: Option<i64>) -> Self {
+        let id = profile.id.unwrap();
+        Self {
+            id,
+            name: profile.name.clone(),
+            local_path: profile.local_path.clone(),
+            gdrive_folder_id: profile.gdrive_folder_id.clone(),
+            smb_share_path: profile.smb_share_path.clone(),
+            active: active_id == Some(id),
+            is_syncing: is_syncing(id),
+            last_sync_at: profile.last_sync_at,
+            last_successful_sync_at: profile.last_successful_sync_at,
+            last_error: profile.last_error.clone(),
+        }
+    }
+}
+
+/// Every profile except the internal one tracking removable media exchanges.
+#[tauri::command]
+pub async fn list_profiles() -> Result<Vec<ProfileSummary>, String> {
+    // Resolves the active profile, creating the default one on first start
+    let (active, db) = get_profile(None).await?;
+
+    let profiles = db.call(DbOperations::list_sync_profiles)
+        .await
+        .map_err(|e| format!("Failed to list sync profiles: {}", e))?;
+    Ok(profiles.iter()
+        .filter(|profile| profile.name != MEDIA_PROFILE_NAME)
+        .map(|profile| ProfileSummary::new(profile, active.id))
+        .collect())
+}
+
+/// Add a profile syncing another project folder. Its other settings start at
+/// their defaults; activate it to change them with `update_config`.
+#[tauri::command]
+pub async fn create_profile(
+    name: String,
+    local_path: String,
+    gdrive_folder_id: Option<String>,
+    smb_share_path: Option<String>,
+) -> Result<ProfileSummary, String> {
+    tracing::info!("Create profile command called: {}", name);
+
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Profile name is required".to_string());
+    }
+    if name == MEDIA_PROFILE_NAME {
+        return Err(format!("The profile name {} is reserved", name));
+    }
+    let local_path = unc::canonicalize(&local_path);
+    if !Path::new(&local_path).is_dir() {
+        return Err(format!("Local path is not a directory: {}", local_path));
+    }
+
+    let db = create_database().await?;
+    let mut profile = SyncProfile::new(name, local_path);
+    profile.gdrive_folder_id = gdrive_folder_id.filter(|id| !id.trim().is_empty());
+    profile.smb_share_path = smb_share_path.filter(|p| !p.trim().is_empty()).map(|p| unc::canonicalize(&p));
+    managed_policy::check_locations(&profile)?;
+
+    let created = profile.clone();
+    let (id, active_id) = db.call(move |conn| {
+        if DbOperations::list_sync_profiles(conn)?.iter().any(|p| p.name == created.name) {
+            return Ok(Err(format!("A profile named {} already exists", created.name)));
+        }
+        let id = DbOperations::create_sync_profile(conn, &created)?;
+        Ok(Ok((id, DbOperations::get_active_sync_profile_id(conn)?)))
+    })
+    .await
+    .map_err(|e| format!("Failed to create sync profile: {}", e))??;
+
+    profile.id = Some(id);
+    Ok(ProfileSummary::new(&profile, active_id))
+}
+
+/// Make the profile the one commands work on, and apply its app-wide settings
+/// (language, secret store, notifications, WebDAV server).
+#[tauri::command]
+pub async fn set_active_profile(profile_id: i64) -> Result<ProfileSummary, String> {
+    tracing::info!("Set active profile command called: {}", profile_id);
+
+    let (profile, db) = get_profile(Some(profile_id)).await?;
+    if profile.name == MEDIA_PROFILE_NAME {
+        return Err(format!("Sync profile not found: {}", profile_id));
+    }
+    db.call(move |conn| DbOperations::set_active_sync_profile(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to activate sync profile: {}", e))?;
+
+    apply_profile_settings().await?;
+    Ok(ProfileSummary::new(&profile, Some(profile_id)))
+}
+
+/// Delete the profile (the active one unless `profile_id` is given) with its
+/// file history, plans, queue and Drive encryption key. When it was the active
+/// profile the oldest remaining one takes over; without any the next command
+/// starts over with a fresh default profile. Profiles with files under legal hold are kept.
+/// Admins only.
+#[tauri::command]
+pub async fn delete_profile(profile_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("Delete profile command called: {:?}", profile_id);
+
+    roles::require(Permission::DeleteProfile).map_err(String::from)?;
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+    let result = db.call(move |conn| {
+        if DbOperations::get_legal_holds(conn, profile_id)?.iter().any(|hold| hold.is_active()) {
+            return Ok(Err("The profile has files under legal hold; release the holds first".to_string()));
+        }
+        let was_active = DbOperations::get_active_sync_profile_id(conn)? == Some(profile_id);
+        DbOperations::delete_sync_profile(conn, profile_id)?;
+        if was_active {
+            let next = DbOperations::list_sync_profiles(conn)?
+                .into_iter()
+                .find(|p| p.name != MEDIA_PROFILE_NAME);
+            if let Some(id) = next.and_then(|p| p.id) {
+                DbOperations::set_active_sync_profile(conn, id)?;
+            }
+        }
+        Ok(Ok(was_active))
+    })
+    .await
+    .map_err(|e| format!("Failed to delete sync profile: {}", e));
+    end_sync(profile_id);
+
+    let was_active = result??;
+    scan_cache::clear_profile(profile_id);
+    watcher::stop_watching(profile_id);
+    if was_active {
+        apply_profile_settings().await?;
+    }
+    drive_encryption::delete(profile_id)
+        .map_err(|e| format!("Profile deleted, but its Drive encryption key could not be removed: {}", e))
+}
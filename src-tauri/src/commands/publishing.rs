@@ -0,0 +1,65 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::publishing::{self, PublishPolicy, PublishResult};
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::{google_drive::GoogleDriveProvider, traits::StorageProvider};
+use std::path::{Path, PathBuf};
+
+/// Publish every drawing whose outputs are out of date.
+#[tauri::command]
+pub async fn publish_drawings() -> Result<PublishResult, String> {
+    tracing::info!("Publish drawings command called");
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    publish_profile(&profile, &db).await
+}
+
+/// Convert out-of-date drawings into the published folder, then upload the new
+/// outputs to the viewer Drive folder when one is configured. Does nothing when
+/// no converter is set.
+pub(crate) async fn publish_profile(profile: &SyncProfile, db: &DbHandle) -> Result<PublishResult, String> {
+    let Some(policy) = PublishPolicy::from_config(profile.publish_command.as_deref(), profile.publish_formats.as_deref())
+        .map_err(|e| format!("Invalid publish settings: {}", e))?
+    else {
+        return Ok(PublishResult::default());
+    };
+
+    let profile_id = profile.id.unwrap();
+    let paths: Vec<String> = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?
+        .into_iter()
+        .filter(|s| s.location == FileLocation::Local)
+        .map(|s| s.file_path)
+        .collect();
+
+    let local_root = PathBuf::from(&profile.local_path);
+    let published_root = publishing::published_root(&local_root);
+    let converted_root = published_root.clone();
+    let mut result = tokio::task::spawn_blocking(move || {
+        publishing::publish_stale(&policy, &local_root, &converted_root, paths.iter().map(String::as_str))
+    })
+    .await
+    .map_err(|e| format!("Publishing was interrupted: {}", e))?;
+
+    if let Some(folder_id) = profile.publish_folder_id.clone().filter(|id| !id.is_empty()) {
+        let viewer = GoogleDriveProvider::for_profile(profile, folder_id)
+            .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+        if !viewer.is_authenticated() {
+            return Err("Google Drive not authenticated".to_string());
+        }
+
+        for output in &result.published {
+            if let Err(e) = viewer.upload(&published_root.join(output), Path::new(output)).await {
+                tracing::warn!("Failed to upload published {}: {}", output, e);
+                result.errors.push(format!("{}: upload failed: {}", output, e));
+                // Drop the output so the next pass converts and uploads it again
+                let _ = tokio::fs::remove_file(published_root.join(output)).await;
+            }
+        }
+    }
+
+    tracing::info!("Published {} files ({} errors)", result.published.len(), result.errors.len());
+    Ok(result)
+}
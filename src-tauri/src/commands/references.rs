@@ -0,0 +1,18 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::references::{self, ReferenceIssue};
+use crate::models::file_state::FileLocation;
+
+/// Report assemblies whose referenced files are missing or out of date on the
+/// given endpoint ("local", "gdrive" or "smb"), e.g. before sending a package out.
+#[tauri::command]
+pub async fn check_references(location: String) -> Result<Vec<ReferenceIssue>, String> {
+    tracing::info!("Check references on: {}", location);
+
+    let location: FileLocation = location.parse()?;
+    let (profile, db) = get_or_create_default_profile().await?;
+
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| references::check_references(conn, profile_id, &location))
+        .await
+        .map_err(|e| format!("Failed to check references: {}", e))
+}
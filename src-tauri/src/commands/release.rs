@@ -0,0 +1,107 @@
+use crate::commands::sync::{begin_sync, build_provider, end_sync, get_or_create_default_profile};
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::release::{self, Endpoint, ReleaseResult};
+use crate::core::scan_cache;
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_event::{FileEvent, FileEventKind};
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use crate::models::sync_profile::SyncProfile;
+use std::path::Path;
+
+/// Publish a release: upload `paths` to every configured remote location so
+/// that either all of them get the new versions or none do.
+#[tauri::command]
+pub async fn publish(paths: Vec<String>) -> Result<ReleaseResult, String> {
+    tracing::info!("Publish release of {} files", paths.len());
+
+    if paths.is_empty() {
+        return Err("No files selected for release".to_string());
+    }
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+    let result = publish_inner(profile, db, paths).await;
+    end_sync(profile_id);
+    result
+}
+
+async fn publish_inner(profile: SyncProfile, db: DbHandle, paths: Vec<String>) -> Result<ReleaseResult, String> {
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+    let prefixes = PathPrefixes::from_config(profile.path_prefixes.as_deref())
+        .map_err(|e| format!("Invalid path prefixes: {}", e))?;
+
+    let mut endpoints = Vec::new();
+    if profile.gdrive_folder_id.is_some() {
+        endpoints.push(endpoint(&profile, FileLocation::GoogleDrive, &prefixes)?);
+    }
+    if profile.smb_share_path.is_some() {
+        endpoints.push(endpoint(&profile, FileLocation::Smb, &prefixes)?);
+    }
+
+    let release_id = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
+    let local_root = Path::new(&profile.local_path);
+    let result = release::publish(&release_id, local_root, &policies, &endpoints, &paths)
+        .await
+        .map_err(|e| format!("Release failed: {}", e))?;
+
+    // Record the released versions as synced so the next sync does not see them as edits
+    let local = endpoint(&profile, FileLocation::Local, &prefixes)?;
+    let mut states = Vec::new();
+    for endpoint in std::iter::once(&local).chain(&endpoints) {
+        for path in &paths {
+            let stored = endpoint.stored_path(&policies, Path::new(path));
+            scan_cache::invalidate(profile.id.unwrap(), Some(&endpoint.location), &stored);
+            match endpoint.provider.get_metadata(&stored).await {
+                Ok(Some(metadata)) => states.push(FileState {
+                    id: None,
+                    profile_id: profile.id.unwrap(),
+                    file_path: path.clone(),
+                    location: endpoint.location.clone(),
+                    content_hash: metadata.hash,
+                    hash_fingerprint: metadata.fingerprint,
+                    size_bytes: Some(metadata.size as i64),
+                    modified_at: Some(metadata.modified),
+                    synced_at: Some(chrono::Utc::now()),
+                    status: SyncStatus::Synced,
+                    metadata: None,
+                }),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Could not read back {} on {:?}: {}", path, endpoint.location, e),
+            }
+        }
+    }
+
+    db.call(move |conn| {
+        let tx = conn.unchecked_transaction()?;
+        let mut events = Vec::new();
+        for state in &states {
+            if state.location == FileLocation::Local {
+                continue;
+            }
+            let known = DbOperations::file_state_exists(&tx, state.profile_id, &state.file_path, &state.location)?;
+            let kind = if known { FileEventKind::Modified } else { FileEventKind::Added };
+            events.push(FileEvent::new(
+                state.profile_id, state.file_path.clone(), state.location.clone(), kind, state.content_hash.clone(),
+            ));
+        }
+        DbOperations::insert_file_events(&tx, &events)?;
+        DbOperations::upsert_file_states(&tx, &states)?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Release published but file states could not be saved: {}", e))?;
+
+    Ok(result)
+}
+
+fn endpoint(profile: &SyncProfile, location: FileLocation, prefixes: &PathPrefixes) -> Result<Endpoint, String> {
+    Ok(Endpoint {
+        provider: build_provider(profile, &location)?,
+        prefix: prefixes.at(&location).map(Path::to_path_buf),
+        location,
+    })
+}
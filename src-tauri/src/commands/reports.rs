@@ -0,0 +1,86 @@
+use crate::commands::sync::get_profile;
+use crate::core::plan_approval;
+use crate::core::size_report::{self, SizeDistribution, TrackedFile};
+use crate::core::stale_files::{self, StaleReport};
+use crate::core::working_set::{self, WorkingFolder};
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, FileState};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The largest files of the profile (the active one unless `profile_id` is
+/// given), `top_n` of them or `size_report::DEFAULT_TOP_N`.
+#[tauri::command]
+pub async fn get_large_files(profile_id: Option<i64>, top_n: Option<usize>) -> Result<Vec<TrackedFile>, String> {
+    tracing::info!("Get large files command called");
+
+    let states = file_states(profile_id).await?;
+    Ok(size_report::largest(&states, top_n.unwrap_or(size_report::DEFAULT_TOP_N)))
+}
+
+/// How the files of the profile spread over size classes and top-level folders.
+#[tauri::command]
+pub async fn get_size_distribution(profile_id: Option<i64>) -> Result<SizeDistribution, String> {
+    tracing::info!("Get size distribution command called");
+
+    let states = file_states(profile_id).await?;
+    Ok(size_report::distribution(&states))
+}
+
+/// Files not modified, or for local copies not opened, in the last `months`
+/// months, at `location` or at every location. Candidates for tiering rules
+/// and for pruning dead projects from Drive.
+#[tauri::command]
+pub async fn get_stale_files(profile_id: Option<i64>, months: u32, location: Option<String>) -> Result<StaleReport, String> {
+    tracing::info!("Get stale files command called: {} months", months);
+
+    let cutoff = stale_files::cutoff(months, chrono::Utc::now()).map_err(|e| e.to_string())?;
+    let location = location.as_deref().map(str::parse::<FileLocation>).transpose()?;
+    let (profile, db) = get_profile(profile_id).await?;
+    let id = profile.id.unwrap();
+    let states: Vec<FileState> = db.call(move |conn| DbOperations::get_file_states(conn, id))
+        .await
+        .map_err(|e| format!("Failed to load file states: {}", e))?
+        .into_iter()
+        .filter(|state| location.as_ref().is_none_or(|l| state.location == *l))
+        .collect();
+
+    let local: Vec<String> = states.iter()
+        .filter(|state| state.location == FileLocation::Local)
+        .map(|state| state.file_path.clone())
+        .collect();
+    let accessed = if local.is_empty() || profile.local_path.is_empty() {
+        HashMap::new()
+    } else {
+        let root = PathBuf::from(&profile.local_path);
+        tokio::task::spawn_blocking(move || stale_files::local_access_times(&root, &local))
+            .await
+            .map_err(|e| format!("Failed to read access times: {}", e))?
+    };
+
+    Ok(stale_files::stale_files(&states, &accessed, cutoff))
+}
+
+/// The folders the current user works in most in the profile, synced ahead
+/// of the rest. Learned from files fetched on demand and, where the system
+/// records them, local access times.
+#[tauri::command]
+pub async fn get_working_set(profile_id: Option<i64>) -> Result<Vec<WorkingFolder>, String> {
+    tracing::info!("Get working set command called");
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    let user = plan_approval::current_user();
+    let usage = db.call(move |conn| DbOperations::get_folder_usage(conn, profile_id, &user))
+        .await
+        .map_err(|e| format!("Failed to load the working set: {}", e))?;
+    Ok(working_set::rank(&usage, chrono::Utc::now(), working_set::WORKING_SET_SIZE))
+}
+
+async fn file_states(profile_id: Option<i64>) -> Result<Vec<FileState>, String> {
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load file states: {}", e))
+}
@@ -0,0 +1,17 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::db::models::DbOperations;
+use crate::models::part_revision::PartRevision;
+
+/// Revision history of every part number seen in the profile, grouped by part
+/// and in the order revisions first appeared.
+#[tauri::command]
+pub async fn get_part_revisions() -> Result<Vec<PartRevision>, String> {
+    tracing::info!("Get part revisions command called");
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| DbOperations::get_part_revisions(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load part revisions: {}", e))
+}
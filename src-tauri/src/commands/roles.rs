@@ -0,0 +1,18 @@
+use crate::core::roles::{self, Role};
+
+/// The role of this installation: "admin" or "operator".
+#[tauri::command]
+pub async fn get_role() -> Result<String, String> {
+    roles::current_role()
+        .map(|role| role.as_str().to_string())
+        .map_err(|e| format!("Failed to read role: {}", e))
+}
+
+/// Change the role of this installation. Only an admin may do this.
+#[tauri::command]
+pub async fn set_role(role: String) -> Result<(), String> {
+    tracing::info!("Set role to {}", role);
+
+    let role = Role::from_str_opt(&role).ok_or_else(|| format!("Unknown role: {}", role))?;
+    roles::set_role(role).map_err(String::from)
+}
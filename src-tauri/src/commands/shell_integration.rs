@@ -0,0 +1,126 @@
+use crate::commands::legal_holds::place_legal_hold;
+use crate::commands::sync::{begin_sync, create_database, finish_sync, get_profile, run_sync_inner, sync_files_inner};
+use crate::core::shell_integration::{self, DeepLink, ShellAction};
+use crate::core::sync_engine;
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::unc;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// A file manager action for the UI, emitted as `shell-action` before it is
+/// carried out. The UI opens the file's versions for `view_versions`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShellRequest {
+    pub action: ShellAction,
+    pub profile_id: i64,
+    /// Relative to the profile's root; empty for the root itself.
+    pub path: String,
+}
+
+/// Add "UVCAD: Sync now", "UVCAD: View versions" and "UVCAD: Lock file" to the
+/// file manager's right-click menu for the current user.
+#[tauri::command]
+pub async fn register_shell_integration() -> Result<(), String> {
+    tracing::info!("Register shell integration command called");
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to find the UVCAD executable: {}", e))?;
+    shell_integration::register(&exe).map_err(|e| format!("Failed to register file manager actions: {}", e))
+}
+
+#[tauri::command]
+pub async fn unregister_shell_integration() -> Result<(), String> {
+    tracing::info!("Unregister shell integration command called");
+
+    shell_integration::unregister().map_err(|e| format!("Failed to remove file manager actions: {}", e))
+}
+
+/// Carry out a `uvcad://` link such as the file manager actions open:
+/// sync the file or folder now, show its versions, or lock it with a legal
+/// hold. Absolute paths pick the profile whose local folder holds them.
+#[tauri::command]
+pub async fn handle_deep_link(app: tauri::AppHandle, url: String) -> Result<(), String> {
+    tracing::info!("Handle deep link command called: {}", url);
+
+    let link = DeepLink::parse(&url).map_err(|e| e.to_string())?;
+    let (profile, db, path) = resolve(&link.path).await?;
+    let profile_id = profile.id.unwrap();
+    let _ = app.emit_all("shell-action", ShellRequest { action: link.action, profile_id, path: path.clone() });
+
+    match link.action {
+        ShellAction::SyncNow => {
+            begin_sync(profile_id)?;
+            let result = if path.is_empty() {
+                run_sync_inner(&app, profile, db, None, None, &sync_engine::new_run_id()).await
+            } else {
+                match files_below(&profile, &db, &path).await {
+                    Ok(paths) => sync_files_inner(&app, profile, db, paths).await,
+                    Err(e) => Err(e),
+                }
+            };
+            finish_sync(profile_id, result).map(|_| ())
+        }
+        ShellAction::LockFile => {
+            place_legal_hold(path, "Locked from the file manager".to_string(), Some(profile_id)).await.map(|_| ())
+        }
+        ShellAction::ViewVersions => Ok(()),
+    }
+}
+
+/// The profile a link's path belongs to and the path relative to its root.
+async fn resolve(path: &Path) -> Result<(SyncProfile, DbHandle, String), String> {
+    if !path.is_absolute() {
+        let (profile, db) = get_profile(None).await?;
+        return Ok((profile, db, relative_path(path)));
+    }
+
+    let db = create_database().await?;
+    let profiles = db.call(DbOperations::list_sync_profiles)
+        .await
+        .map_err(|e| format!("Failed to list profiles: {}", e))?;
+    // The innermost root when profiles are nested
+    profiles.into_iter()
+        .filter(|profile| !profile.local_path.is_empty())
+        .filter_map(|profile| unc::strip_root(path, &profile.local_path).map(|relative| (profile, relative)))
+        .max_by_key(|(profile, _)| profile.local_path.len())
+        .map(|(profile, relative)| (profile, db, relative_path(&relative)))
+        .ok_or_else(|| format!("{} is not in a synced folder", path.display()))
+}
+
+fn relative_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").trim_matches('/').to_string()
+}
+
+/// `path` itself for a file; for a folder every file below it, tracked at
+/// any location or only on disk so far.
+async fn files_below(profile: &SyncProfile, db: &DbHandle, path: &str) -> Result<Vec<String>, String> {
+    let folder = PathBuf::from(&profile.local_path).join(path);
+    if !folder.is_dir() {
+        return Ok(vec![path.to_string()]);
+    }
+
+    let profile_id = profile.id.unwrap();
+    let mut files: BTreeSet<String> = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?
+        .into_iter()
+        .filter(|state| Path::new(&state.file_path).starts_with(path))
+        .map(|state| state.file_path)
+        .collect();
+
+    let mut folders = vec![folder];
+    while let Some(folder) = folders.pop() {
+        let entries = std::fs::read_dir(&folder).map_err(|e| format!("Failed to read {}: {}", folder.display(), e))?;
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                folders.push(entry_path);
+            } else if let Some(relative) = unc::strip_root(&entry_path, &profile.local_path) {
+                files.insert(relative_path(&relative));
+            }
+        }
+    }
+    Ok(files.into_iter().collect())
+}
@@ -0,0 +1,73 @@
+use crate::commands::sync::get_profile;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::shell_integration;
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::google_drive::GoogleDriveProvider;
+use crate::providers::samba::{self, SambaProvider};
+use std::path::{Component, Path, PathBuf};
+
+/// Open a file or folder of the profile (the active one unless `profile_id`
+/// is given) in the Google Drive web UI. `path` is relative to the profile's
+/// root; empty for the synced folder itself. Returns the address opened.
+#[tauri::command]
+pub async fn open_in_drive(path: String, profile_id: Option<i64>) -> Result<String, String> {
+    tracing::info!("Open in Drive command called: {}", path);
+
+    let (profile, _) = get_profile(profile_id).await?;
+    let stored = stored_path(&profile, &path, &FileLocation::GoogleDrive)?;
+    let folder_id = profile.gdrive_folder_id.clone()
+        .ok_or_else(|| "Google Drive folder not configured".to_string())?;
+    let provider = GoogleDriveProvider::for_profile(&profile, folder_id)
+        .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+    if !provider.is_authenticated() {
+        return Err("Google Drive not authenticated".to_string());
+    }
+
+    let url = provider.web_link(&stored)
+        .await
+        .map_err(|e| format!("Failed to find {} on Google Drive: {}", path, e))?;
+    open::that(&url).map_err(|e| format!("Failed to open the browser: {}", e))?;
+    Ok(url)
+}
+
+/// Show a file or folder of the profile on the NAS in the file manager, on
+/// the fallback share when the primary one is unreachable. Returns the path
+/// shown, e.g. `\\nas\engineering\parts\frame.dwg`.
+#[tauri::command]
+pub async fn open_on_nas(path: String, profile_id: Option<i64>) -> Result<String, String> {
+    tracing::info!("Open on NAS command called: {}", path);
+
+    let (profile, _) = get_profile(profile_id).await?;
+    let stored = stored_path(&profile, &path, &FileLocation::Smb)?;
+    let share_path = profile.smb_share_path.clone()
+        .ok_or_else(|| "SMB share not configured".to_string())?;
+    let provider = SambaProvider::new(PathBuf::from(share_path))
+        .with_fallbacks(samba::fallback_paths(profile.smb_fallback_paths.as_deref()));
+
+    let absolute = provider.absolute_path(&stored).map_err(|e| e.to_string())?;
+    if !absolute.exists() {
+        return Err(format!("{} is not on the NAS", path));
+    }
+    shell_integration::reveal(&absolute).map_err(|e| format!("Failed to open the file manager: {}", e))?;
+    Ok(absolute.display().to_string())
+}
+
+/// Where the file at `path`, relative to the profile's root, is kept at
+/// `location`, with the location's folder prefix and compression applied.
+fn stored_path(profile: &SyncProfile, path: &str, location: &FileLocation) -> Result<PathBuf, String> {
+    let relative = PathBuf::from(path.trim().replace('\\', "/").trim_matches('/'));
+    if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("Invalid path: {}", path));
+    }
+    let prefixes = PathPrefixes::from_config(profile.path_prefixes.as_deref())
+        .map_err(|e| format!("Invalid path prefixes: {}", e))?;
+    if relative.as_os_str().is_empty() {
+        return Ok(prefixes.at(location).map(Path::to_path_buf).unwrap_or_default());
+    }
+
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+    Ok(prefixes.stored_path(&policies.stored_path(&relative, location), location))
+}
@@ -0,0 +1,216 @@
+use crate::commands::compare::stage_version;
+use crate::commands::sync::{begin_sync, end_sync, get_profile};
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::legal_hold::LegalHolds;
+use crate::core::plan_approval;
+use crate::core::scan_cache;
+use crate::core::snapshots::{self, RestorePlan, SnapshotDiff};
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::FileLocation;
+use crate::models::snapshot::{Snapshot, SnapshotFile};
+use crate::models::sync_profile::SyncProfile;
+use directories::ProjectDirs;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Outcome of restoring a snapshot into the local folder. The next sync takes
+/// the restored files to the other locations.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotRestore {
+    pub restored: Vec<String>,
+    pub unchanged: usize,
+    /// Files whose content no location holds any more.
+    pub unavailable: Vec<String>,
+    /// Files created since the snapshot, left in place.
+    pub added_since: Vec<String>,
+    /// Changed files under legal hold, left as they are.
+    pub held: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+/// Record the content of every file of the profile (the active one unless
+/// `profile_id` is given) under `label`, e.g. "As sent to customer 2024-06-01".
+/// Only the hashes are stored.
+#[tauri::command]
+pub async fn create_snapshot(label: String, profile_id: Option<i64>) -> Result<Snapshot, String> {
+    tracing::info!("Create snapshot command called: {}", label);
+
+    let label = label.trim().to_string();
+    if label.is_empty() {
+        return Err("Snapshot label is required".to_string());
+    }
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+
+    let mut snapshot = Snapshot {
+        id: None,
+        profile_id,
+        label,
+        created_at: chrono::Utc::now(),
+        created_by: plan_approval::current_user(),
+        file_count: 0,
+        total_bytes: 0,
+    };
+    let recorded = snapshot.clone();
+    let (id, files) = db.call(move |conn| {
+        if DbOperations::list_snapshots(conn, profile_id)?.iter().any(|s| s.label == recorded.label) {
+            return Ok(Err(format!("A snapshot labelled {} already exists", recorded.label)));
+        }
+        let files = snapshots::manifest(&DbOperations::get_file_states(conn, profile_id)?);
+        let id = DbOperations::create_snapshot(conn, &recorded, &files)?;
+        Ok(Ok((id, files)))
+    })
+    .await
+    .map_err(|e| format!("Failed to create snapshot: {}", e))??;
+
+    snapshot.id = Some(id);
+    snapshot.file_count = files.len();
+    snapshot.total_bytes = files.iter().map(|f| f.size_bytes.unwrap_or(0).max(0) as u64).sum();
+    tracing::info!("Snapshot {} records {} files", snapshot.label, snapshot.file_count);
+    Ok(snapshot)
+}
+
+/// The snapshots of the profile, newest first.
+#[tauri::command]
+pub async fn list_snapshots(profile_id: Option<i64>) -> Result<Vec<Snapshot>, String> {
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| DbOperations::list_snapshots(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to list snapshots: {}", e))
+}
+
+/// What changed from snapshot `from_id` to snapshot `to_id`, or to the files
+/// as they are now.
+#[tauri::command]
+pub async fn compare_snapshots(from_id: i64, to_id: Option<i64>, profile_id: Option<i64>) -> Result<SnapshotDiff, String> {
+    tracing::info!("Compare snapshots command called: {} to {:?}", from_id, to_id);
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let from = snapshot_files(&db, profile.id.unwrap(), from_id).await?;
+    let to = match to_id {
+        Some(to_id) => snapshot_files(&db, profile.id.unwrap(), to_id).await?,
+        None => {
+            let profile_id = profile.id.unwrap();
+            let states = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+                .await
+                .map_err(|e| format!("Failed to load file states: {}", e))?;
+            snapshots::manifest(&states)
+        }
+    };
+    Ok(snapshots::compare(&from, &to))
+}
+
+/// Bring the local files back to their content in the snapshot, copying it
+/// from wherever it still is. Only `paths` are restored when given. Files
+/// created since and files under legal hold are left alone.
+#[tauri::command]
+pub async fn restore_snapshot(snapshot_id: i64, paths: Option<Vec<String>>, profile_id: Option<i64>) -> Result<SnapshotRestore, String> {
+    tracing::info!("Restore snapshot command called: {}", snapshot_id);
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    if profile.local_path.is_empty() {
+        return Err("Local path not configured".to_string());
+    }
+    let mut files = snapshot_files(&db, profile_id, snapshot_id).await?;
+    if let Some(paths) = &paths {
+        files.retain(|f| paths.contains(&f.file_path));
+    }
+
+    begin_sync(profile_id)?;
+    let result = restore(&profile, &db, &files).await;
+    end_sync(profile_id);
+    result
+}
+
+#[tauri::command]
+pub async fn delete_snapshot(snapshot_id: i64, profile_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("Delete snapshot command called: {}", snapshot_id);
+
+    let (profile, db) = get_profile(profile_id).await?;
+    find_snapshot(&db, profile.id.unwrap(), snapshot_id).await?;
+    db.call(move |conn| DbOperations::delete_snapshot(conn, snapshot_id))
+        .await
+        .map_err(|e| format!("Failed to delete snapshot: {}", e))
+}
+
+async fn restore(profile: &SyncProfile, db: &DbHandle, files: &[SnapshotFile]) -> Result<SnapshotRestore, String> {
+    let profile_id = profile.id.unwrap();
+    let (states, holds) = db.call(move |conn| {
+        Ok((DbOperations::get_file_states(conn, profile_id)?, LegalHolds::load(conn, profile_id)?))
+    })
+    .await
+    .map_err(|e| format!("Failed to load file states: {}", e))?;
+    let RestorePlan { steps, unchanged, unavailable, added_since, held } = snapshots::plan_restore(files, &states, &holds);
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+
+    let local_root = PathBuf::from(&profile.local_path);
+    let staging = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| "Failed to get project directory".to_string())?
+        .data_dir()
+        .join("restore")
+        .join(chrono::Utc::now().format("%Y%m%d%H%M%S%3f").to_string());
+
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+    for step in steps {
+        let dest = local_root.join(&step.path);
+        let copied = async {
+            let source = match step.source {
+                FileLocation::Local => local_root.join(&step.source_path),
+                _ => {
+                    tokio::fs::create_dir_all(&staging)
+                        .await
+                        .map_err(|e| format!("Failed to create staging folder: {}", e))?;
+                    stage_version(profile, &policies, Path::new(&step.source_path), &step.source, &staging).await?
+                }
+            };
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            tokio::fs::copy(&source, &dest)
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", step.path, e))?;
+            if step.source != FileLocation::Local {
+                let _ = tokio::fs::remove_file(&source).await;
+            }
+            Ok::<(), String>(())
+        }
+        .await;
+
+        match copied {
+            Ok(()) => {
+                scan_cache::invalidate(profile_id, Some(&FileLocation::Local), Path::new(&step.path));
+                restored.push(step.path);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to restore {}: {}", step.path, e);
+                failed.push(step.path);
+            }
+        }
+    }
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+
+    tracing::info!("Restored {} files, {} unavailable, {} failed", restored.len(), unavailable.len(), failed.len());
+    Ok(SnapshotRestore { restored, unchanged, unavailable, added_since, held, failed })
+}
+
+async fn find_snapshot(db: &DbHandle, profile_id: i64, snapshot_id: i64) -> Result<Snapshot, String> {
+    db.call(move |conn| DbOperations::list_snapshots(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to list snapshots: {}", e))?
+        .into_iter()
+        .find(|s| s.id == Some(snapshot_id))
+        .ok_or_else(|| format!("Snapshot not found: {}", snapshot_id))
+}
+
+async fn snapshot_files(db: &DbHandle, profile_id: i64, snapshot_id: i64) -> Result<Vec<SnapshotFile>, String> {
+    find_snapshot(db, profile_id, snapshot_id).await?;
+    db.call(move |conn| DbOperations::get_snapshot_files(conn, snapshot_id))
+        .await
+        .map_err(|e| format!("Failed to load snapshot: {}", e))
+}
@@ -1,31 +1,76 @@
-use crate::core::sync_engine::{SyncEngine, SyncResult};
-use crate::db::{models::DbOperations, schema::Database};
+use crate::commands::{managed_policy, notifications, publishing};
+use crate::commands::pauses::ensure_not_paused;
+use crate::core::archive_inspector;
+use crate::core::cad_normalizer::HashPolicy;
+use crate::core::concurrency::{MAX_DRIVE_TRANSFERS, MAX_SMB_TRANSFERS};
+use crate::core::conflict_resolver::{Conflict, ConflictResolver, ResolutionAction, ResolutionPreview, ResolutionStep};
+use crate::core::drive_encryption;
+use crate::core::estimate::SyncEstimate;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::legal_hold::LegalHolds;
+use crate::core::media_exchange::MEDIA_PROFILE_NAME;
+use crate::core::notifications::{Notification, NotificationKind, Severity};
+use crate::core::naming::NamingConvention;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::plan_approval;
+use crate::core::previews;
+use crate::core::progress::{self, ProgressThrottle};
+use crate::core::quarantine::{self, ScanPolicy};
+use crate::core::quick_hash;
+use crate::core::references;
+use crate::core::revisions;
+use crate::core::selective_sync::SelectiveSync;
+use crate::core::signing::DigestSigner;
+use crate::core::stale_files;
+use crate::core::sync_engine::{self, SyncEngine, SyncResult};
+use crate::core::sync_policy::SyncPolicy;
+use crate::core::working_set::{self, WorkingFolder};
+use crate::db::{handle::DbHandle, models::DbOperations, schema::Database};
+use crate::models::conflict::{Conflict as RecordedConflict, ConflictResolution};
+use crate::models::file_event::{FileEvent, FileEventKind};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_phase::SyncPhase;
+use crate::models::sync_plan::{PlanStep, SyncPlan};
 use crate::models::sync_profile::SyncProfile;
 use crate::providers::{
-    google_drive::GoogleDriveProvider,
+    google_drive::{GoogleDriveProvider, UploadProgressCallback},
     local_fs::LocalFsProvider,
-    samba::SambaProvider,
-    traits::StorageProvider,
+    samba::{self, SambaProvider},
+    traits::{FileMetadata, StorageProvider},
 };
+use crate::utils::i18n;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::Manager;
+use tokio_util::sync::CancellationToken;
 
-static SYNC_STATE: Lazy<Arc<std::sync::Mutex<SyncStateTracker>>> = Lazy::new(|| {
-    Arc::new(std::sync::Mutex::new(SyncStateTracker {
-        is_syncing: false,
-        last_sync: None,
-        last_result: None,
-    }))
+/// Sync state per profile id, so several profiles can sync at the same time.
+static SYNC_STATE: Lazy<Arc<std::sync::Mutex<HashMap<i64, SyncStateTracker>>>> = Lazy::new(|| {
+    Arc::new(std::sync::Mutex::new(HashMap::new()))
 });
 
+#[derive(Default)]
 struct SyncStateTracker {
     is_syncing: bool,
     last_sync: Option<String>,
     last_result: Option<SyncResult>,
+    current_plan: Option<CurrentPlan>,
+    /// Cancels the running sync; see `cancel_sync`.
+    cancellation: Option<CancellationToken>,
+}
+
+/// The plan of the running sync or preview, or of the last one.
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrentPlan {
+    pub run_id: String,
+    /// Made by `estimate_sync`; nothing is carried out.
+    pub preview: bool,
+    pub steps: Vec<PlanStep>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,6 +88,10 @@ pub struct FileInfo {
     pub size: u64,
     pub modified: String,
     pub status: String,
+    /// The file name does not match the profile's naming convention.
+    pub naming_violation: bool,
+    /// Changes are detected from sampled blocks only, not the whole content.
+    pub sampled_hash: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,6 +100,68 @@ pub struct SyncResultDto {
     pub files_synced: usize,
     pub conflicts: Vec<String>,
     pub errors: Vec<String>,
+    /// Files not synced to released locations because their name violates the convention.
+    pub blocked: Vec<String>,
+    /// Files not deleted or overwritten because they are under legal hold.
+    pub held: Vec<String>,
+    pub warnings: Vec<String>,
+    pub plan: Vec<PlanStep>,
+    /// Set when the plan was stored for approval instead of being carried out.
+    pub pending_plan_id: Option<i64>,
+    /// Paths not synced because Google Drive has several files under them; see `dedupe_remote`.
+    pub duplicates: Vec<String>,
+    /// The sync run, for `get_sync_run`; None for operations outside the engine.
+    pub run_id: Option<String>,
+    /// Files downloaded although their signature showed a change made outside UVCAD.
+    pub tampered: Vec<String>,
+    /// Files synced by this run; with `cancelled`, those done before it stopped.
+    pub completed: Vec<String>,
+    /// The sync was cancelled with `cancel_sync` before it finished.
+    pub cancelled: bool,
+    /// Files whose changes wait for a location paused with `pause_provider`.
+    pub paused: Vec<String>,
+    /// Locations skipped for the rest of the sync after failing repeatedly.
+    pub degraded: Vec<FileLocation>,
+}
+
+impl SyncResultDto {
+    pub(crate) fn from_result(result: &SyncResult) -> Self {
+        Self {
+            actions_performed: result.files_synced,
+            files_synced: result.files_synced,
+            conflicts: result.conflicts.iter()
+                .map(|conflict| conflict.file_path.clone())
+                .collect(),
+            errors: vec![], // No errors field in SyncResult, using empty vec
+            blocked: result.blocked.clone(),
+            held: result.held.clone(),
+            warnings: result.warnings.clone(),
+            plan: result.plan.clone(),
+            pending_plan_id: None,
+            duplicates: result.duplicates.clone(),
+            run_id: Some(result.run_id.clone()),
+            tampered: result.tampered.clone(),
+            completed: result.completed.clone(),
+            cancelled: result.cancelled,
+            paused: result.paused.clone(),
+            degraded: result.degraded.clone(),
+        }
+    }
+}
+
+/// What resolving a conflict changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictResolutionResult {
+    pub file_path: String,
+    pub resolution: ConflictResolution,
+    /// The changes made, in the order they were made.
+    pub steps: Vec<ResolutionStep>,
+    /// Locations whose copy already was the one kept.
+    pub unchanged: Vec<FileLocation>,
+    /// Where the versions set aside by keeping both are now.
+    pub conflict_copies: Vec<String>,
+    /// The run the changes were made in, for `get_sync_run`.
+    pub run_id: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -58,95 +169,392 @@ pub struct SyncProgress {
     pub current_file: String,
     pub total_files: usize,
     pub processed_files: usize,
-    pub operation: String,
+    pub operation: SyncPhase,
+    /// `operation` as a localized sentence.
+    pub description: String,
     pub percentage: f32,
+    /// Updates held back by throttling since the previous event; this one stands in for them.
+    pub batched_updates: usize,
+    /// The sync run the event belongs to.
+    pub run_id: Option<String>,
 }
 
-fn create_database() -> Result<Arc<std::sync::Mutex<Database>>, String> {
-    let db = Database::new().map_err(|e| format!("Failed to create database: {}", e))?;
-    db.initialize().map_err(|e| format!("Failed to initialize database: {}", e))?;
-    Ok(Arc::new(std::sync::Mutex::new(db)))
-}
+impl SyncProgress {
+    fn new(current_file: String, processed_files: usize, total_files: usize, operation: SyncPhase, percentage: f32) -> Self {
+        let description = operation.describe(&current_file);
+        Self { current_file, total_files, processed_files, operation, description, percentage, batched_updates: 0, run_id: None }
+    }
 
-async fn get_or_create_default_profile() -> Result<(SyncProfile, Arc<std::sync::Mutex<Database>>), String> {
-    let db_arc = create_database()?;
+    fn with_batched_updates(mut self, count: usize) -> Self {
+        self.batched_updates = count;
+        self
+    }
 
-    let profile = {
-        let db_guard = db_arc.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-        let conn = db_guard.get_connection();
+    fn with_run_id(mut self, run_id: &str) -> Self {
+        self.run_id = Some(run_id.to_string());
+        self
+    }
+}
 
-        // Try to get existing profile with id=1
-        if let Some(profile) = DbOperations::get_sync_profile(conn, 1)
-            .map_err(|e| format!("Failed to get sync profile: {}", e))? {
-            profile
+pub(crate) async fn create_database() -> Result<DbHandle, String> {
+    let db = tokio::task::spawn_blocking(|| {
+        let db = Database::new().map_err(|e| format!("Failed to create database: {}", e))?;
+        db.initialize().map_err(|e| format!("Failed to initialize database: {}", e))?;
+        Ok::<_, String>(db)
+    })
+    .await
+    .map_err(|e| format!("Failed to create database: {}", e))??;
+    Ok(DbHandle::new(db))
+}
+
+/// The active profile (see `set_active_profile`), which commands work on unless
+/// they are given another one. Before any profile was activated that is the
+/// first one; a default profile is created when there is none.
+pub(crate) async fn get_or_create_default_profile() -> Result<(SyncProfile, DbHandle), String> {
+    let db = create_database().await?;
+
+    let profile = db.call(|conn| {
+        let active = match DbOperations::get_active_sync_profile_id(conn)? {
+            Some(id) => DbOperations::get_sync_profile(conn, id)?,
+            None => DbOperations::list_sync_profiles(conn)?
+                .into_iter()
+                .find(|p| p.name != MEDIA_PROFILE_NAME),
+        };
+        if let Some(profile) = active {
+            Ok(profile)
         } else {
             // Create a default profile if none exists
-            let default_profile = SyncProfile {
-                id: None,
-                name: "Default".to_string(),
-                local_path: std::env::current_dir()
+            let default_profile = SyncProfile::new(
+                "Default".to_string(),
+                std::env::current_dir()
                     .unwrap_or_else(|_| PathBuf::from("."))
                     .to_string_lossy()
                     .to_string(),
-                gdrive_folder_id: None,
-                smb_share_path: None,
-                created_at: chrono::Utc::now(),
-                last_sync_at: None,
-            };
+            );
 
-            let id = DbOperations::create_sync_profile(conn, &default_profile)
-                .map_err(|e| format!("Failed to create sync profile: {}", e))?;
+            let id = DbOperations::create_sync_profile(conn, &default_profile)?;
+            DbOperations::set_active_sync_profile(conn, id)?;
 
             let mut profile = default_profile;
             profile.id = Some(id);
-            profile
+            Ok(profile)
         }
-    }; // db_guard is dropped here
+    })
+    .await
+    .map_err(|e| format!("Failed to get sync profile: {}", e))?;
+
+    Ok((profile, db))
+}
 
-    Ok((profile, db_arc))
+/// The profile with `profile_id`, or the active one when no id is given.
+pub(crate) async fn get_profile(profile_id: Option<i64>) -> Result<(SyncProfile, DbHandle), String> {
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile = match profile_id {
+        Some(id) if profile.id != Some(id) => db.call(move |conn| DbOperations::get_sync_profile(conn, id))
+            .await
+            .map_err(|e| format!("Failed to get sync profile: {}", e))?
+            .ok_or_else(|| format!("Sync profile not found: {}", id))?,
+        _ => profile,
+    };
+    Ok((profile, db))
 }
 
+/// How much of a large upload to Google Drive has arrived, emitted as
+/// `upload-progress` after each chunk.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadProgress {
+    pub path: String,
+    pub bytes_sent: u64,
+    pub bytes_total: u64,
+    pub run_id: String,
+}
+
+/// Sync the profile with `profile_id`, or the active one.
 #[tauri::command]
-pub async fn start_sync(app: tauri::AppHandle) -> Result<SyncResultDto, String> {
+pub async fn start_sync(app: tauri::AppHandle, profile_id: Option<i64>) -> Result<SyncResultDto, String> {
     tracing::info!("Start sync command called");
 
-    // Check if already syncing
-    {
-        let mut state = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-        if state.is_syncing {
-            return Err("Sync already in progress".to_string());
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+
+    let result = run_sync_inner(&app, profile, db, None, None, &sync_engine::new_run_id()).await;
+    finish_sync(profile_id, result)
+}
+
+/// How much a sync of the profile (the active one unless `profile_id` is
+/// given) would upload and download per location, and how long it would take
+/// at the throughput of recent transfers. Nothing is transferred.
+#[tauri::command]
+pub async fn estimate_sync(app: tauri::AppHandle, profile_id: Option<i64>) -> Result<SyncEstimate, String> {
+    tracing::info!("Estimate sync command called");
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+
+    begin_sync(profile_id)?;
+    let result = async {
+        build_sync_engine(&app, &profile, db, &sync_engine::new_run_id())?
+            .estimate()
+            .await
+            .map_err(|e| format!("Estimate failed: {}", e))
+    }.await;
+    end_sync(profile_id);
+    result
+}
+
+/// Sync only the given files, automatically including every file they reference
+/// (assembly components, xrefs) so an assembly is never pulled with missing parts.
+#[tauri::command]
+pub async fn sync_files(app: tauri::AppHandle, paths: Vec<String>) -> Result<SyncResultDto, String> {
+    tracing::info!("Sync files command called for {} paths", paths.len());
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+
+    let result = sync_files_inner(&app, profile, db, paths).await;
+    finish_sync(profile_id, result)
+}
+
+/// Maximum number of dependency expansion passes for on-demand syncs. Each pass can
+/// discover new references once freshly downloaded assemblies have been scanned.
+const MAX_DEPENDENCY_PASSES: usize = 5;
+
+pub(crate) async fn sync_files_inner(
+    app: &tauri::AppHandle,
+    profile: SyncProfile,
+    db: DbHandle,
+    paths: Vec<String>,
+) -> Result<(SyncResultDto, SyncResult), String> {
+    let profile_id = profile.id.unwrap();
+
+    // Files fetched or saved on demand show where the user is working
+    let opened = paths.clone();
+    let recorded = db.call(move |conn| {
+        working_set::record_opened(conn, profile_id, &plan_approval::current_user(), &opened, chrono::Utc::now())
+    }).await;
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record opened folders: {}", e);
+    }
+
+    // Every dependency pass belongs to the same run
+    let run_id = sync_engine::new_run_id();
+    let mut total = SyncResult { run_id: run_id.clone(), ..SyncResult::default() };
+    let mut synced: HashSet<PathBuf> = HashSet::new();
+
+    for pass in 0..MAX_DEPENDENCY_PASSES {
+        let roots = paths.clone();
+        let closure = db.call(move |conn| references::collect_dependencies(conn, profile_id, &roots))
+            .await
+            .map_err(|e| format!("Failed to resolve dependencies: {}", e))?;
+
+        let pending: HashSet<PathBuf> = closure.into_iter()
+            .map(PathBuf::from)
+            .filter(|p| !synced.contains(p))
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+
+        tracing::info!("Dependency pass {}: syncing {} files", pass + 1, pending.len());
+        synced.extend(pending.iter().cloned());
+
+        let (dto, result) = run_sync_inner(app, profile.clone(), db.clone(), Some(pending), None, &run_id).await?;
+        if dto.pending_plan_id.is_some() {
+            return Ok((dto, result));
+        }
+        total.files_synced += result.files_synced;
+        total.files_failed += result.files_failed;
+        total.files_conflict += result.files_conflict;
+        total.conflicts.extend(result.conflicts);
+        total.blocked.extend(result.blocked);
+        total.held.extend(result.held);
+        total.warnings.extend(result.warnings);
+        total.tampered.extend(result.tampered);
+        total.completed.extend(result.completed);
+        total.paused.extend(result.paused);
+        for location in result.degraded {
+            if !total.degraded.contains(&location) {
+                total.degraded.push(location);
+            }
+        }
+        if result.cancelled {
+            total.cancelled = true;
+            break;
         }
-        state.is_syncing = true;
     }
 
-    // Emit initial progress
-    let _ = app.emit_all("sync-progress", SyncProgress {
-        current_file: "Starting sync...".to_string(),
-        total_files: 0,
-        processed_files: 0,
-        operation: "initializing".to_string(),
-        percentage: 0.0,
-    });
+    Ok((SyncResultDto::from_result(&total), total))
+}
 
-    // Get or create sync profile and database
-    let (profile, db_arc) = get_or_create_default_profile().await?;
+/// Mark a sync of the profile as running, failing if one already is.
+pub(crate) fn begin_sync(profile_id: i64) -> Result<(), String> {
+    let mut states = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let state = states.entry(profile_id).or_default();
+    if state.is_syncing {
+        return Err("Sync already in progress".to_string());
+    }
+    state.is_syncing = true;
+    state.cancellation = Some(CancellationToken::new());
+    Ok(())
+}
 
-    tracing::info!("Using sync profile: {:?}", profile);
+/// Clear the running flag set by `begin_sync` without recording a sync result.
+pub(crate) fn end_sync(profile_id: i64) {
+    if let Ok(mut states) = SYNC_STATE.lock() {
+        let state = states.entry(profile_id).or_default();
+        state.is_syncing = false;
+        state.cancellation = None;
+    }
+}
 
-    // Validate configuration
-    if profile.local_path.is_empty() {
-        SYNC_STATE.lock().unwrap().is_syncing = false;
-        return Err("Local path not configured".to_string());
+/// The token `cancel_sync` cancels for the running sync of the profile.
+fn cancellation_token(profile_id: i64) -> CancellationToken {
+    SYNC_STATE.lock()
+        .ok()
+        .and_then(|states| states.get(&profile_id).and_then(|state| state.cancellation.clone()))
+        .unwrap_or_default()
+}
+
+/// Stop the running sync of the profile (the active one unless `profile_id` is
+/// given). Transfers under way finish and are recorded; the rest are left for
+/// the next sync. The sync's result lists the files completed before it stopped.
+#[tauri::command]
+pub async fn cancel_sync(profile_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("Cancel sync command called");
+
+    let (profile, _) = get_profile(profile_id).await?;
+    let states = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    match states.get(&profile.id.unwrap()).and_then(|state| state.cancellation.as_ref()) {
+        Some(token) => {
+            token.cancel();
+            Ok(())
+        }
+        None => Err("No sync in progress".to_string()),
+    }
+}
+
+/// Whether a sync of the profile is running.
+pub(crate) fn is_syncing(profile_id: i64) -> bool {
+    SYNC_STATE.lock().is_ok_and(|states| states.get(&profile_id).is_some_and(|state| state.is_syncing))
+}
+
+/// Record the outcome of a sync run of the profile in the state tracker.
+pub(crate) fn finish_sync(profile_id: i64, result: Result<(SyncResultDto, SyncResult), String>) -> Result<SyncResultDto, String> {
+    let mut states = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let state = states.entry(profile_id).or_default();
+    state.is_syncing = false;
+    state.cancellation = None;
+
+    match &result {
+        // Plans awaiting approval were announced when they were stored
+        Ok((dto, _)) if dto.pending_plan_id.is_some() => {}
+        Ok((_, result)) if result.cancelled => notifications::notify(Notification::new(
+            NotificationKind::SyncCompleted,
+            Severity::Info,
+            i18n::tr("notification.sync_cancelled", &[("count", result.completed.len().to_string())]),
+        ).with_run_id(&result.run_id)),
+        Ok((_, result)) => notifications::notify(sync_completed(result)),
+        Err(e) => notifications::notify(Notification::new(
+            NotificationKind::SyncFailed,
+            Severity::Error,
+            i18n::tr("notification.sync_failed", &[("detail", e.clone())]),
+        )),
     }
 
+    let (dto, result) = result?;
+    state.last_sync = Some(chrono::Utc::now().to_rfc3339());
+    state.last_result = Some(result);
+    Ok(dto)
+}
+
+/// A warning when files failed or conflicted, so channels can skip clean runs.
+fn sync_completed(result: &SyncResult) -> Notification {
+    let problems = result.files_failed + result.conflicts.len();
+    let notification = if !result.degraded.is_empty() {
+        let locations: Vec<&str> = result.degraded.iter().map(FileLocation::as_str).collect();
+        Notification::new(
+            NotificationKind::SyncCompleted,
+            Severity::Warning,
+            i18n::tr("notification.sync_degraded", &[
+                ("locations", locations.join(", ")),
+                ("failed", result.files_failed.to_string()),
+            ]),
+        )
+    } else if problems > 0 {
+        Notification::new(
+            NotificationKind::SyncCompleted,
+            Severity::Warning,
+            i18n::tr("notification.sync_completed_with_problems", &[
+                ("count", result.files_synced.to_string()),
+                ("failed", result.files_failed.to_string()),
+                ("conflicts", result.conflicts.len().to_string()),
+            ]),
+        )
+    } else {
+        Notification::new(
+            NotificationKind::SyncCompleted,
+            Severity::Info,
+            i18n::tr("notification.sync_completed", &[("count", result.files_synced.to_string())]),
+        )
+    };
+    notification.with_run_id(&result.run_id).with_details(serde_json::json!({
+        "files_synced": result.files_synced,
+        "files_failed": result.files_failed,
+        "conflicts": result.conflicts.len(),
+        "degraded": result.degraded,
+    }))
+}
+
+/// Set up providers and a sync engine for the profile, reporting progress to the UI.
+pub(crate) fn build_sync_engine(
+    app: &tauri::AppHandle,
+    profile: &SyncProfile,
+    db: DbHandle,
+    run_id: &str,
+) -> Result<SyncEngine, String> {
+    managed_policy::check_locations(profile)?;
+    let hash_policy = HashPolicy::from_config(profile.normalized_extensions.as_deref())
+        .with_sampling_above(profile.sampled_hash_min_mb)
+        .with_known_hashes(db.clone());
+    let policy = SyncPolicy::from_profile(profile).compile()
+        .map_err(|e| format!("Invalid sync policy: {}", e))?;
+    let signer = if profile.sign_digests {
+        Some(Arc::new(DigestSigner::for_this_machine(plan_approval::current_user(), profile.trusted_signing_keys.as_deref())
+            .map_err(|e| format!("Invalid signing settings: {}", e))?))
+    } else {
+        None
+    };
+
+    let scanner = ScanPolicy::from_config(
+        profile.scan_command.as_deref(),
+        quarantine::quarantine_root(profile.id.unwrap_or_default()).map_err(|e| e.to_string())?,
+    );
+    let drive_keys = drive_encryption::for_profile(profile)
+        .map_err(|e| format!("Failed to load the Drive encryption key: {}", e))?;
+
     // Initialize providers
     let local_provider: Arc<Mutex<dyn StorageProvider>> = Arc::new(Mutex::new(
         LocalFsProvider::new(PathBuf::from(&profile.local_path))
+            .with_hash_policy(hash_policy.clone())
     ));
 
+    // Report how far large uploads to Google Drive are
+    let app_handle = app.clone();
+    let upload_run_id = run_id.to_string();
+    let upload_progress: UploadProgressCallback = Arc::new(move |path: &Path, bytes_sent: u64, bytes_total: u64| {
+        let _ = app_handle.emit_all("upload-progress", UploadProgress {
+            path: path.to_string_lossy().to_string(),
+            bytes_sent,
+            bytes_total,
+            run_id: upload_run_id.clone(),
+        });
+    });
+
     // Initialize Google Drive provider if configured
     let gdrive_provider: Option<Arc<Mutex<dyn StorageProvider>>> = if let Some(ref folder_id) = profile.gdrive_folder_id {
-        match GoogleDriveProvider::new(folder_id.clone()) {
+        match GoogleDriveProvider::for_profile(profile, folder_id.clone()).map(|p| p.with_upload_progress(upload_progress.clone())) {
             Ok(provider) => {
                 if provider.is_authenticated() {
                     tracing::info!("Google Drive authenticated, initializing provider");
@@ -167,105 +575,314 @@ pub async fn start_sync(app: tauri::AppHandle) -> Result<SyncResultDto, String>
     };
 
     // Initialize Samba provider if configured
+    let new_samba = |share_path: &str| -> Arc<Mutex<dyn StorageProvider>> {
+        Arc::new(Mutex::new(
+            SambaProvider::new(PathBuf::from(share_path))
+                .with_fallbacks(samba::fallback_paths(profile.smb_fallback_paths.as_deref()))
+                .with_hash_policy(hash_policy.clone())
+        ))
+    };
     let samba_provider: Option<Arc<Mutex<dyn StorageProvider>>> = if let Some(ref share_path) = profile.smb_share_path {
         tracing::info!("Samba share configured: {}", share_path);
-        Some(Arc::new(Mutex::new(SambaProvider::new(PathBuf::from(share_path)))))
+        Some(new_samba(share_path))
     } else {
         tracing::info!("Samba not configured");
         None
     };
 
+    // Extra connections so transfers to a location can run in parallel
+    type Connections = Vec<(FileLocation, Vec<Arc<Mutex<dyn StorageProvider>>>)>;
+    let mut connections: Connections = Vec::new();
+    if let (Some(folder_id), Some(_)) = (&profile.gdrive_folder_id, &gdrive_provider) {
+        let drive_connections = (1..MAX_DRIVE_TRANSFERS)
+            .filter_map(|_| GoogleDriveProvider::for_profile(profile, folder_id.clone()).ok())
+            .map(|provider| Arc::new(Mutex::new(provider.with_upload_progress(upload_progress.clone()))) as Arc<Mutex<dyn StorageProvider>>)
+            .collect();
+        connections.push((FileLocation::GoogleDrive, drive_connections));
+    }
+    if let Some(ref share_path) = profile.smb_share_path {
+        connections.push((FileLocation::Smb, (1..MAX_SMB_TRANSFERS).map(|_| new_samba(share_path)).collect()));
+    }
+
     // Create progress callback
     let app_handle = app.clone();
-    let progress_callback = Arc::new(move |processed: usize, total: usize, filename: String, operation: String| {
+    let callback_run_id = run_id.to_string();
+    let throttle = std::sync::Mutex::new(ProgressThrottle::new(progress::MAX_EVENTS_PER_SECOND));
+    let progress_callback = Arc::new(move |processed: usize, total: usize, filename: String, operation: SyncPhase| {
+        let Some(batched) = throttle.lock().unwrap().admit(&operation, processed, total, Instant::now()) else {
+            return;
+        };
         let percentage = if total > 0 {
             (processed as f32 / total as f32) * 100.0
         } else {
             0.0
         };
 
-        let _ = app_handle.emit_all("sync-progress", SyncProgress {
-            current_file: filename,
-            total_files: total,
-            processed_files: processed,
+        let _ = app_handle.emit_all("sync-progress", SyncProgress::new(
+            filename,
+            processed,
+            total,
             operation,
             percentage,
-        });
+        ).with_batched_updates(batched).with_run_id(&callback_run_id));
+    });
+
+    // Keep each plan for get_current_plan and show it while the sync runs
+    let app_handle = app.clone();
+    let plan_run_id = run_id.to_string();
+    let plan_profile_id = profile.id.unwrap();
+    let plan_callback = Arc::new(move |steps: &[PlanStep], preview: bool| {
+        let plan = CurrentPlan { run_id: plan_run_id.clone(), preview, steps: steps.to_vec() };
+        let _ = app_handle.emit_all("sync-plan", &plan);
+        if let Ok(mut states) = SYNC_STATE.lock() {
+            states.entry(plan_profile_id).or_default().current_plan = Some(plan);
+        }
     });
 
     // Create sync engine with progress callback
-    let mut sync_engine = SyncEngine::new(
+    let sync_engine = SyncEngine::new(
         profile.id.unwrap(),
         local_provider,
         gdrive_provider,
         samba_provider,
-        db_arc,
-    ).with_progress_callback(progress_callback);
+        db,
+    )
+    .with_progress_callback(progress_callback)
+    .with_plan_callback(plan_callback)
+    .with_policy(policy)
+    .with_scan_cache()
+    .with_cancellation(cancellation_token(profile.id.unwrap()))
+    .with_run_id(run_id);
+    let sync_engine = connections.into_iter()
+        .fold(sync_engine, |engine, (location, providers)| engine.with_connections(location, providers));
+    let sync_engine = match signer {
+        Some(signer) => sync_engine.with_signing(signer),
+        None => sync_engine,
+    };
+    let sync_engine = match drive_keys {
+        Some(keys) => sync_engine.with_drive_encryption(Arc::new(keys)),
+        None => sync_engine,
+    };
+    Ok(match scanner {
+        Some(scanner) => sync_engine.with_scanner(Arc::new(scanner)),
+        None => sync_engine,
+    })
+}
+
+/// A standalone provider for one location of the profile.
+pub(crate) fn build_provider(profile: &SyncProfile, location: &FileLocation) -> Result<Box<dyn StorageProvider>, String> {
+    if *location != FileLocation::Local {
+        managed_policy::check_locations(profile)?;
+    }
+    let hash_policy = HashPolicy::from_config(profile.normalized_extensions.as_deref())
+        .with_sampling_above(profile.sampled_hash_min_mb);
+
+    match location {
+        FileLocation::Local => Ok(Box::new(
+            LocalFsProvider::new(PathBuf::from(&profile.local_path)).with_hash_policy(hash_policy)
+        )),
+        FileLocation::GoogleDrive => {
+            let folder_id = profile.gdrive_folder_id.clone()
+                .ok_or_else(|| "Google Drive folder not configured".to_string())?;
+            let provider = GoogleDriveProvider::for_profile(profile, folder_id)
+                .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+            if !provider.is_authenticated() {
+                return Err("Google Drive not authenticated".to_string());
+            }
+            Ok(Box::new(provider))
+        }
+        FileLocation::Smb => {
+            let share_path = profile.smb_share_path.clone()
+                .ok_or_else(|| "SMB share not configured".to_string())?;
+            Ok(Box::new(
+                SambaProvider::new(PathBuf::from(share_path))
+                    .with_fallbacks(samba::fallback_paths(profile.smb_fallback_paths.as_deref()))
+                    .with_hash_policy(hash_policy)
+            ))
+        }
+    }
+}
+
+/// Cache previews of local files covered by the thumbnail extension policy.
+fn refresh_thumbnails(conn: &rusqlite::Connection, profile: &SyncProfile) -> crate::utils::error::Result<()> {
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())?;
+    let profile_id = profile.id.unwrap();
+    let states = DbOperations::get_file_states(conn, profile_id)?;
+    let local_paths = states.iter()
+        .filter(|s| s.location == FileLocation::Local)
+        .map(|s| s.file_path.as_str());
+
+    let written = previews::refresh_thumbnails(
+        &previews::thumbnail_root(profile_id)?,
+        Path::new(&profile.local_path),
+        &policies,
+        local_paths,
+    )?;
+    if written > 0 {
+        tracing::info!("Cached {} thumbnails", written);
+    }
+    Ok(())
+}
+
+/// Run a sync of `profile`. With plan approval enabled, a plan not
+/// covered by `approved_plan`, with the user who approved it, is stored for
+/// approval and nothing is transferred.
+pub(crate) async fn run_sync_inner(
+    app: &tauri::AppHandle,
+    profile: SyncProfile,
+    db: DbHandle,
+    path_filter: Option<HashSet<PathBuf>>,
+    approved_plan: Option<(Vec<PlanStep>, String)>,
+    run_id: &str,
+) -> Result<(SyncResultDto, SyncResult), String> {
+    // Emit initial progress
+    let _ = app.emit_all("sync-progress", SyncProgress::new(
+        "Starting sync...".to_string(),
+        0,
+        0,
+        SyncPhase::Initializing,
+        0.0,
+    ).with_run_id(run_id));
+
+    tracing::info!("Using sync profile: {:?}", profile);
+
+    // Validate configuration
+    if profile.local_path.is_empty() {
+        return Err("Local path not configured".to_string());
+    }
+
+    let mut sync_engine = build_sync_engine(app, &profile, db.clone(), run_id)?;
+    match load_working_set(&profile, &db).await {
+        Ok(folders) => sync_engine = sync_engine.with_working_set(working_set::folder_paths(&folders)),
+        Err(e) => tracing::warn!("Failed to update the working set: {}", e),
+    }
+
+    if let Some(paths) = path_filter {
+        sync_engine = sync_engine.with_path_filter(paths);
+    }
+    if let Some((steps, approved_by)) = approved_plan {
+        sync_engine = sync_engine.with_approved_plan(steps, &approved_by);
+    }
 
     // Run sync
     tracing::info!("Starting sync operation...");
     let result = sync_engine.start_sync()
         .await
-        .map_err(|e| {
-            SYNC_STATE.lock().unwrap().is_syncing = false;
-            format!("Sync failed: {}", e)
-        })?;
+        .map_err(|e| format!("Sync failed: {}", e))?;
 
     tracing::info!("Sync completed: {:?}", result);
 
-    // Emit completion progress
-    let _ = app.emit_all("sync-progress", SyncProgress {
-        current_file: "Sync complete!".to_string(),
-        total_files: result.files_synced + result.files_failed + result.files_conflict,
-        processed_files: result.files_synced + result.files_failed + result.files_conflict,
-        operation: "completed".to_string(),
-        percentage: 100.0,
-    });
+    if result.awaiting_approval {
+        let plan = SyncPlan::new(profile.id.unwrap(), result.plan.clone());
+        let plan_id = db.call(move |conn| DbOperations::create_sync_plan(conn, &plan))
+            .await
+            .map_err(|e| format!("Failed to store sync plan: {}", e))?;
+
+        let _ = app.emit_all("sync-progress", SyncProgress::new(
+            "Awaiting approval".to_string(),
+            0,
+            0,
+            SyncPhase::Completed,
+            100.0,
+        ).with_run_id(run_id));
+        let _ = app.emit_all("plan-awaiting-approval", plan_id);
+        notifications::notify(Notification::new(
+            NotificationKind::PlanAwaitingApproval,
+            Severity::Warning,
+            i18n::tr("notification.plan_awaiting_approval", &[("count", result.plan.len().to_string())]),
+        ).with_run_id(run_id));
+
+        let mut dto = SyncResultDto::from_result(&result);
+        dto.pending_plan_id = Some(plan_id);
+        return Ok((dto, result));
+    }
 
-    // Convert conflicts to strings
-    let conflict_paths: Vec<String> = result.conflicts.iter()
-        .map(|conflict| conflict.file_path.clone())
-        .collect();
+    // Refresh derived indexes (archive contents, assembly references, part revisions) for changed files
+    let indexed_profile = profile.clone();
+    let indexed = db.call(move |conn| {
+        let profile_id = indexed_profile.id.unwrap();
+        let local_root = Path::new(&indexed_profile.local_path);
 
-    let dto = SyncResultDto {
-        actions_performed: result.files_synced,
-        files_synced: result.files_synced,
-        conflicts: conflict_paths,
-        errors: vec![], // No errors field in SyncResult, using empty vec
-    };
+        if indexed_profile.inspect_archives {
+            if let Err(e) = archive_inspector::index_profile_archives(conn, profile_id, local_root) {
+                tracing::warn!("Archive indexing failed: {}", e);
+            }
+        }
+        if let Err(e) = references::rebuild_reference_graph(conn, profile_id, local_root) {
+            tracing::warn!("Reference graph update failed: {}", e);
+        }
+        if let Err(e) = revisions::record_revisions(conn, profile_id) {
+            tracing::warn!("Revision tracking failed: {}", e);
+        }
+        if let Err(e) = refresh_thumbnails(conn, &indexed_profile) {
+            tracing::warn!("Thumbnail refresh failed: {}", e);
+        }
+        Ok(())
+    }).await;
+    if let Err(e) = indexed {
+        tracing::warn!("Index refresh failed: {}", e);
+    }
 
-    // Update state
-    {
-        let mut state = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-        state.is_syncing = false;
-        state.last_sync = Some(chrono::Utc::now().to_rfc3339());
-        state.last_result = Some(result);
+    // Publish updated drawings for viewers
+    if let Err(e) = publishing::publish_profile(&profile, &db).await {
+        tracing::warn!("Publishing failed: {}", e);
     }
 
-    Ok(dto)
+    // Emit completion progress
+    let message = if result.cancelled { "Sync cancelled" } else { "Sync complete!" };
+    let _ = app.emit_all("sync-progress", SyncProgress::new(
+        message.to_string(),
+        result.files_synced + result.files_failed + result.files_conflict,
+        result.files_synced + result.files_failed + result.files_conflict,
+        SyncPhase::Completed,
+        100.0,
+    ).with_run_id(run_id));
+
+    Ok((SyncResultDto::from_result(&result), result))
+}
+
+/// The current user's working set, after learning from the local access times
+/// what they opened since the last sync.
+async fn load_working_set(profile: &SyncProfile, db: &DbHandle) -> Result<Vec<WorkingFolder>, String> {
+    let profile_id = profile.id.unwrap();
+    let local: Vec<String> = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get file states: {}", e))?
+        .into_iter()
+        .filter(|state| state.location == FileLocation::Local)
+        .map(|state| state.file_path)
+        .collect();
+
+    let root = PathBuf::from(&profile.local_path);
+    let accessed = tokio::task::spawn_blocking(move || stale_files::local_access_times(&root, &local))
+        .await
+        .map_err(|e| format!("Failed to read access times: {}", e))?;
+
+    let user = plan_approval::current_user();
+    db.call(move |conn| working_set::refresh(conn, profile_id, &user, &accessed, chrono::Utc::now()))
+        .await
+        .map_err(|e| format!("Failed to update the working set: {}", e))
 }
 
 #[tauri::command]
 pub async fn pull_from_gdrive(app: tauri::AppHandle) -> Result<SyncResultDto, String> {
     tracing::info!("Pull from Google Drive command called");
 
-    // Check if already syncing
-    {
-        let mut state = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-        if state.is_syncing {
-            return Err("Sync already in progress".to_string());
-        }
-        state.is_syncing = true;
-    }
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+    ensure_not_paused(&db, profile_id, &FileLocation::GoogleDrive).await?;
+    begin_sync(profile_id)?;
 
-    let result = pull_from_gdrive_inner(&app).await;
+    let result = pull_from_gdrive_inner(&app, profile, db).await;
 
     // Always clear syncing flag
-    SYNC_STATE.lock().unwrap().is_syncing = false;
+    let mut states = SYNC_STATE.lock().unwrap();
+    let state = states.entry(profile_id).or_default();
+    state.is_syncing = false;
+    state.cancellation = None;
 
     match result {
         Ok(dto) => {
-            let mut state = SYNC_STATE.lock().unwrap();
             state.last_sync = Some(chrono::Utc::now().to_rfc3339());
             Ok(dto)
         }
@@ -273,17 +890,15 @@ pub async fn pull_from_gdrive(app: tauri::AppHandle) -> Result<SyncResultDto, St
     }
 }
 
-async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto, String> {
+async fn pull_from_gdrive_inner(app: &tauri::AppHandle, profile: SyncProfile, db: DbHandle) -> Result<SyncResultDto, String> {
     // Emit initial progress
-    let _ = app.emit_all("sync-progress", SyncProgress {
-        current_file: "Connecting to Google Drive...".to_string(),
-        total_files: 0,
-        processed_files: 0,
-        operation: "initializing".to_string(),
-        percentage: 0.0,
-    });
-
-    let (profile, db_arc) = get_or_create_default_profile().await?;
+    let _ = app.emit_all("sync-progress", SyncProgress::new(
+        "Connecting to Google Drive...".to_string(),
+        0,
+        0,
+        SyncPhase::Initializing,
+        0.0,
+    ));
 
     // Validate local path
     if profile.local_path.is_empty() {
@@ -291,6 +906,9 @@ async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto,
     }
 
     let local_path = PathBuf::from(&profile.local_path);
+    let hash_policy = HashPolicy::from_config(profile.normalized_extensions.as_deref())
+        .with_sampling_above(profile.sampled_hash_min_mb)
+        .with_known_hashes(db.clone());
     if !local_path.exists() {
         tokio::fs::create_dir_all(&local_path).await
             .map_err(|e| format!("Failed to create local directory: {}", e))?;
@@ -299,42 +917,67 @@ async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto,
     // Validate Google Drive config
     let folder_id = profile.gdrive_folder_id.as_ref()
         .ok_or_else(|| "Google Drive folder not configured".to_string())?;
+    managed_policy::check_locations(&profile)?;
 
-    let gdrive = GoogleDriveProvider::new(folder_id.clone())
+    let gdrive = GoogleDriveProvider::for_profile(&profile, folder_id.clone())
         .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
 
     if !gdrive.is_authenticated() {
         return Err("Not authenticated with Google Drive. Please sign in first.".to_string());
     }
+    let drive_keys = drive_encryption::for_profile(&profile)
+        .map_err(|e| format!("Failed to load the Drive encryption key: {}", e))?;
 
     // List all files on Google Drive
-    let _ = app.emit_all("sync-progress", SyncProgress {
-        current_file: "Listing files on Google Drive...".to_string(),
-        total_files: 0,
-        processed_files: 0,
-        operation: "scanning".to_string(),
-        percentage: 5.0,
-    });
+    let _ = app.emit_all("sync-progress", SyncProgress::new(
+        "Listing files on Google Drive...".to_string(),
+        0,
+        0,
+        SyncPhase::Scanning { provider: FileLocation::GoogleDrive },
+        5.0,
+    ));
 
-    let files = gdrive.list_files(std::path::Path::new(""))
+    // Files outside the Drive prefix folder or left out by the sync patterns
+    // are not part of the profile
+    let prefixes = PathPrefixes::from_config(profile.path_prefixes.as_deref())
+        .map_err(|e| format!("Invalid path prefixes: {}", e))?;
+    let selection = SelectiveSync::from_config(profile.include_patterns.as_deref(), profile.exclude_patterns.as_deref())
+        .map_err(|e| format!("Invalid sync patterns: {}", e))?;
+    let files: Vec<(PathBuf, FileMetadata)> = gdrive.list_files(std::path::Path::new(""))
         .await
-        .map_err(|e| format!("Failed to list Google Drive files: {}", e))?;
+        .map_err(|e| format!("Failed to list Google Drive files: {}", e))?
+        .into_iter()
+        .filter_map(|meta| prefixes.logical_path(&meta.path, &FileLocation::GoogleDrive).map(|path| (path, meta)))
+        .filter(|(path, _)| selection.selects(path))
+        .collect();
 
     let total = files.len();
     if total == 0 {
-        let _ = app.emit_all("sync-progress", SyncProgress {
-            current_file: "No files found on Google Drive".to_string(),
-            total_files: 0,
-            processed_files: 0,
-            operation: "completed".to_string(),
-            percentage: 100.0,
-        });
+        let _ = app.emit_all("sync-progress", SyncProgress::new(
+            "No files found on Google Drive".to_string(),
+            0,
+            0,
+            SyncPhase::Completed,
+            100.0,
+        ));
 
         return Ok(SyncResultDto {
             actions_performed: 0,
             files_synced: 0,
             conflicts: vec![],
             errors: vec![],
+            blocked: vec![],
+            held: vec![],
+            warnings: vec![],
+            plan: vec![],
+            pending_plan_id: None,
+            duplicates: vec![],
+            run_id: None,
+            tampered: vec![],
+            completed: vec![],
+            cancelled: false,
+            paused: vec![],
+            degraded: vec![],
         });
     }
 
@@ -342,20 +985,43 @@ async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto,
 
     let mut downloaded = 0;
     let mut errors = Vec::new();
+    let mut states = Vec::new();
+    let mut held = Vec::new();
+    let mut completed = Vec::new();
+    let mut cancelled = false;
+    let profile_id = profile.id.unwrap();
+    let holds = db.call(move |conn| LegalHolds::load(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load legal holds: {}", e))?;
+    let cancellation = cancellation_token(profile_id);
+
+    let mut throttle = ProgressThrottle::new(progress::MAX_EVENTS_PER_SECOND);
+    for (i, (path, file_meta)) in files.iter().enumerate() {
+        if cancellation.is_cancelled() {
+            tracing::info!("Pull cancelled after {} files", downloaded);
+            cancelled = true;
+            break;
+        }
+        let filename = path.to_string_lossy().to_string();
+
+        let phase = SyncPhase::Transferring { from: Some(FileLocation::GoogleDrive), to: FileLocation::Local };
+        if let Some(batched) = throttle.admit(&phase, i, total, Instant::now()) {
+            let percentage = 10.0 + (i as f32 / total as f32) * 85.0; // 10-95% range
+            let _ = app.emit_all("sync-progress", SyncProgress::new(
+                filename.clone(),
+                i,
+                total,
+                phase,
+                percentage,
+            ).with_batched_updates(batched));
+        }
 
-    for (i, file_meta) in files.iter().enumerate() {
-        let filename = file_meta.path.to_string_lossy().to_string();
-
-        let percentage = 10.0 + (i as f32 / total as f32) * 85.0; // 10-95% range
-        let _ = app.emit_all("sync-progress", SyncProgress {
-            current_file: filename.clone(),
-            total_files: total,
-            processed_files: i,
-            operation: "downloading".to_string(),
-            percentage,
-        });
-
-        let dest_path = local_path.join(&file_meta.path);
+        let dest_path = local_path.join(path);
+        if holds.covering(path).is_some() && dest_path.exists() {
+            tracing::warn!("Not overwriting {}: under legal hold", filename);
+            held.push(filename);
+            continue;
+        }
 
         // Create parent directories if needed
         if let Some(parent) = dest_path.parent() {
@@ -369,50 +1035,55 @@ async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto,
         }
 
         // Download file
-        match gdrive.download(&file_meta.path, &dest_path).await {
+        let pulled = async {
+            gdrive.download(&file_meta.path, &dest_path).await?;
+            match &drive_keys {
+                Some(keys) => keys.decrypt_file(&dest_path, &dest_path),
+                None => Ok(()),
+            }
+        }.await;
+        match pulled {
             Ok(_) => {
                 downloaded += 1;
                 tracing::info!("Downloaded: {}", filename);
 
-                // Update DB state for both locations
+                // Record DB state for both locations
                 let profile_id = profile.id.unwrap();
                 let now = chrono::Utc::now();
 
                 // Compute local hash after download
-                let local_hash = crate::core::file_hasher::compute_file_hash(&dest_path).ok();
-
-                let db_guard = db_arc.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-                let conn = db_guard.get_connection();
+                let local_hash = hash_policy.hash_file(&dest_path).ok();
 
                 // Record local file state
-                let local_state = crate::models::file_state::FileState {
+                states.push(crate::models::file_state::FileState {
                     id: None,
                     profile_id,
                     file_path: filename.clone(),
-                    location: crate::models::file_state::FileLocation::Local,
-                    content_hash: local_hash,
+                    location: FileLocation::Local,
+                    content_hash: local_hash.as_ref().map(|h| h.hash.clone()),
+                    hash_fingerprint: local_hash.and_then(|h| h.fingerprint),
                     size_bytes: Some(file_meta.size as i64),
                     modified_at: Some(now),
                     synced_at: Some(now),
                     status: crate::models::file_state::SyncStatus::Synced,
                     metadata: None,
-                };
-                let _ = DbOperations::upsert_file_state(conn, &local_state);
+                });
 
                 // Record Google Drive file state
-                let gdrive_state = crate::models::file_state::FileState {
+                states.push(crate::models::file_state::FileState {
                     id: None,
                     profile_id,
                     file_path: filename.clone(),
-                    location: crate::models::file_state::FileLocation::GoogleDrive,
+                    location: FileLocation::GoogleDrive,
                     content_hash: file_meta.hash.clone(),
+                    hash_fingerprint: None,
                     size_bytes: Some(file_meta.size as i64),
                     modified_at: Some(file_meta.modified),
                     synced_at: Some(now),
                     status: crate::models::file_state::SyncStatus::Synced,
                     metadata: None,
-                };
-                let _ = DbOperations::upsert_file_state(conn, &gdrive_state);
+                });
+                completed.push(filename);
             }
             Err(e) => {
                 tracing::error!("Failed to download {}: {}", filename, e);
@@ -421,14 +1092,37 @@ async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto,
         }
     }
 
+    // Save the state of every downloaded file in one transaction
+    let saved = db.call(move |conn| {
+        let tx = conn.unchecked_transaction()?;
+        let mut events = Vec::new();
+        for state in &states {
+            let kind = if DbOperations::file_state_exists(&tx, state.profile_id, &state.file_path, &state.location)? {
+                FileEventKind::Modified
+            } else {
+                FileEventKind::Added
+            };
+            events.push(FileEvent::new(
+                state.profile_id, state.file_path.clone(), state.location.clone(), kind, state.content_hash.clone(),
+            ));
+        }
+        DbOperations::insert_file_events(&tx, &events)?;
+        DbOperations::upsert_file_states(&tx, &states)?;
+        tx.commit()?;
+        Ok(())
+    }).await;
+    if let Err(e) = saved {
+        tracing::error!("Failed to save file states after pull: {}", e);
+    }
+
     // Emit completion
-    let _ = app.emit_all("sync-progress", SyncProgress {
-        current_file: format!("Pull complete! Downloaded {} files", downloaded),
-        total_files: total,
-        processed_files: total,
-        operation: "completed".to_string(),
-        percentage: 100.0,
-    });
+    let _ = app.emit_all("sync-progress", SyncProgress::new(
+        format!("Pull complete! Downloaded {} files", downloaded),
+        total,
+        total,
+        SyncPhase::Completed,
+        100.0,
+    ));
 
     tracing::info!("Pull from Google Drive complete: {}/{} files downloaded", downloaded, total);
 
@@ -437,14 +1131,39 @@ async fn pull_from_gdrive_inner(app: &tauri::AppHandle) -> Result<SyncResultDto,
         files_synced: downloaded,
         conflicts: vec![],
         errors,
+        blocked: vec![],
+        held,
+        warnings: vec![],
+        plan: vec![],
+        pending_plan_id: None,
+        duplicates: vec![],
+        run_id: None,
+        tampered: vec![],
+        completed,
+        cancelled,
+        paused: vec![],
+        degraded: vec![],
     })
 }
 
+/// The plan of the running sync or preview of the profile (the active one
+/// unless `profile_id` is given), or of the last one, for review.
 #[tauri::command]
-pub async fn get_sync_status() -> Result<SyncStatus, String> {
+pub async fn get_current_plan(profile_id: Option<i64>) -> Result<Option<CurrentPlan>, String> {
+    let (profile, _) = get_profile(profile_id).await?;
+    let states = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    Ok(states.get(&profile.id.unwrap()).and_then(|state| state.current_plan.clone()))
+}
+
+/// Whether the profile (the active one unless `profile_id` is given) is syncing
+/// and how its last sync went.
+#[tauri::command]
+pub async fn get_sync_status(profile_id: Option<i64>) -> Result<SyncStatus, String> {
     tracing::info!("Get sync status command called");
 
-    let state = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let (profile, _) = get_profile(profile_id).await?;
+    let mut states = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let state = states.entry(profile.id.unwrap()).or_default();
 
     let (files_synced, files_pending, conflicts) = if let Some(ref result) = state.last_result {
         (
@@ -470,19 +1189,23 @@ pub async fn get_file_list() -> Result<Vec<FileInfo>, String> {
     tracing::info!("Get file list command called");
 
     // Get or create sync profile and database
-    let (profile, db_arc) = get_or_create_default_profile().await?;
-
-    let db_guard = db_arc.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = db_guard.get_connection();
+    let (profile, db) = get_or_create_default_profile().await?;
 
     // Query file states from database
-    let file_states = DbOperations::get_file_states(conn, profile.id.unwrap())
+    let profile_id = profile.id.unwrap();
+    let file_states = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+        .await
         .map_err(|e| format!("Failed to get file states: {}", e))?;
 
+    let naming = NamingConvention::from_config(
+        profile.naming_rules.as_deref(),
+        profile.naming_blocked_locations.as_deref(),
+    ).map_err(|e| format!("Invalid naming convention: {}", e))?;
+
     // Convert to FileInfo and sort by modified date (most recent first)
     let mut files: Vec<FileInfo> = file_states
         .into_iter()
-        .filter(|state| state.location == crate::models::file_state::FileLocation::Local)
+        .filter(|state| state.location == FileLocation::Local)
         .map(|state| FileInfo {
             path: state.file_path.clone(),
             size: state.size_bytes.unwrap_or(0) as u64,
@@ -490,6 +1213,8 @@ pub async fn get_file_list() -> Result<Vec<FileInfo>, String> {
                 .map(|dt| dt.to_rfc3339())
                 .unwrap_or_else(|| "Unknown".to_string()),
             status: state.status.as_str().to_string(),
+            naming_violation: !naming.is_compliant(Path::new(&state.file_path)),
+            sampled_hash: state.content_hash.as_deref().is_some_and(quick_hash::is_sampled),
         })
         .collect();
 
@@ -503,16 +1228,130 @@ pub async fn get_file_list() -> Result<Vec<FileInfo>, String> {
     Ok(files)
 }
 
+/// The conflicts recorded for the profile (the active one unless `profile_id`
+/// is given), oldest first: those awaiting resolution, and with
+/// `include_resolved` also the ones resolved since.
+#[tauri::command]
+pub async fn get_conflicts(profile_id: Option<i64>, include_resolved: Option<bool>) -> Result<Vec<RecordedConflict>, String> {
+    tracing::info!("Get conflicts command called");
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    let include_resolved = include_resolved.unwrap_or(false);
+    Ok(db.call(move |conn| DbOperations::get_conflicts(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load conflicts: {}", e))?
+        .into_iter()
+        .filter(|c| include_resolved || !c.resolved)
+        .collect())
+}
+
+/// What resolving the conflict on `file_path` with `resolution` would do: which
+/// copies would be overwritten, renamed, copied or deleted at which location.
+/// Nothing is changed.
 #[tauri::command]
-pub async fn resolve_conflict(file_path: String, resolution: String) -> Result<String, String> {
+pub async fn resolve_conflict_preview(file_path: String, resolution: String, profile_id: Option<i64>) -> Result<ResolutionPreview, String> {
+    tracing::info!("Resolve conflict preview for: {} with {}", file_path, resolution);
+
+    let resolution = ConflictResolution::from_str(&resolution)
+        .ok_or_else(|| format!("Invalid resolution: {}", resolution))?;
+    let (profile, db) = get_profile(profile_id).await?;
+    let conflict = find_conflict(&db, profile.id.unwrap(), &file_path).await?;
+    Ok(ConflictResolver::new().preview(&conflict, resolution, &conflict_locations(&profile), chrono::Local::now().date_naive()))
+}
+
+/// Resolve the conflict on `file_path` by carrying out the steps
+/// `resolve_conflict_preview` shows for `resolution`. Keeping one copy
+/// overwrites, copies or deletes the others to match it; keeping both sets
+/// every other version aside as `name (conflict from <location> <date>).ext`
+/// at every location. The file's state is recorded and the conflict marked
+/// resolved only when every step succeeded.
+#[tauri::command]
+pub async fn resolve_conflict(
+    app: tauri::AppHandle,
+    file_path: String,
+    resolution: String,
+    profile_id: Option<i64>,
+) -> Result<ConflictResolutionResult, String> {
     tracing::info!("Resolve conflict for: {} with {}", file_path, resolution);
 
-    // TODO: Implement conflict resolution
-    // This would involve:
-    // 1. Get conflict from database
-    // 2. Apply resolution (keep local, keep gdrive, keep samba, keep all)
-    // 3. Update file states
-    // 4. Mark conflict as resolved
+    let resolution = ConflictResolution::from_str(&resolution)
+        .ok_or_else(|| format!("Invalid resolution: {}", resolution))?;
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    let conflict = find_conflict(&db, profile_id, &file_path).await?;
+    let preview = ConflictResolver::new()
+        .preview(&conflict, resolution, &conflict_locations(&profile), chrono::Local::now().date_naive());
+    for location in conflict_locations(&profile).iter().filter(|l| preview.steps.iter().any(|step| &step.location == *l)) {
+        ensure_not_paused(&db, profile_id, location).await?;
+    }
+
+    begin_sync(profile_id)?;
+    let result = async {
+        build_sync_engine(&app, &profile, db, &sync_engine::new_run_id())?
+            .resolve_conflict(&conflict, &preview, &plan_approval::current_user())
+            .await
+            .map_err(|e| format!("Failed to resolve conflict: {}", e))
+    }.await;
+    end_sync(profile_id);
+
+    let result = result?;
+    if let Some(warning) = result.warnings.first() {
+        return Err(warning.clone());
+    }
+    if let Ok(mut states) = SYNC_STATE.lock() {
+        if let Some(last) = states.get_mut(&profile_id).and_then(|state| state.last_result.as_mut()) {
+            last.conflicts.retain(|c| c.file_path != file_path);
+        }
+    }
+    let conflict_copies: Vec<String> = preview.steps.iter()
+        .filter_map(|step| match &step.action {
+            ResolutionAction::Rename { to } => Some(to.clone()),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    tracing::info!("Resolved the conflict on {} with {} steps", file_path, preview.steps.len());
+    Ok(ConflictResolutionResult {
+        file_path,
+        resolution: preview.resolution,
+        steps: preview.steps,
+        unchanged: preview.unchanged,
+        conflict_copies,
+        run_id: result.run_id,
+    })
+}
+
+/// The unresolved conflict on `file_path`: the latest one recorded, or the
+/// one the profile's last sync found.
+async fn find_conflict(db: &DbHandle, profile_id: i64, file_path: &str) -> Result<Conflict, String> {
+    let recorded = db.call(move |conn| DbOperations::get_conflicts(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to load conflicts: {}", e))?
+        .into_iter()
+        .rev()
+        .find(|c| !c.resolved && c.file_path == file_path);
+    if let Some(recorded) = recorded {
+        return Ok(Conflict::from(&recorded));
+    }
+
+    let states = SYNC_STATE.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    states.get(&profile_id)
+        .and_then(|state| state.last_result.as_ref())
+        .and_then(|result| result.conflicts.iter().find(|c| c.file_path == file_path).cloned())
+        .ok_or_else(|| format!("No unresolved conflict on {}", file_path))
+}
 
-    Ok(format!("Conflict resolved: {}", file_path))
+/// The locations a conflict can involve: the local folder and whichever
+/// remotes the profile has.
+fn conflict_locations(profile: &SyncProfile) -> Vec<FileLocation> {
+    let mut locations = vec![FileLocation::Local];
+    if profile.gdrive_folder_id.is_some() {
+        locations.push(FileLocation::GoogleDrive);
+    }
+    if profile.smb_share_path.is_some() {
+        locations.push(FileLocation::Smb);
+    }
+    locations
 }
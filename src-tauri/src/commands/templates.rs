@@ -0,0 +1,29 @@
+use crate::commands::sync::{get_or_create_default_profile, sync_files, SyncResultDto};
+use crate::core::project_template;
+use std::path::{Path, PathBuf};
+
+/// Create a new project folder from the configured template and sync it out
+/// to every configured endpoint.
+#[tauri::command]
+pub async fn create_project_from_template(app: tauri::AppHandle, name: String) -> Result<SyncResultDto, String> {
+    tracing::info!("Create project from template: {}", name);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let template_path = profile.project_template_path.as_ref()
+        .ok_or_else(|| "Project template folder not configured".to_string())?;
+    if profile.local_path.is_empty() {
+        return Err("Local path not configured".to_string());
+    }
+    let local_root = PathBuf::from(&profile.local_path);
+
+    let files = project_template::instantiate_template(Path::new(template_path), &local_root, &name)
+        .map_err(|e| format!("Failed to create project: {}", e))?;
+
+    let profile_id = profile.id.unwrap();
+    let created = files.clone();
+    db.call(move |conn| project_template::register_files(conn, profile_id, &local_root, &created))
+        .await
+        .map_err(|e| format!("Failed to register project files: {}", e))?;
+
+    sync_files(app, files).await
+}
@@ -0,0 +1,179 @@
+use crate::commands::sync::get_or_create_default_profile;
+use crate::core::file_hasher;
+use crate::core::plan_approval;
+use crate::core::scan_cache;
+use crate::core::stubs::{self, StubFile};
+use crate::core::tiering::{self, TieringCandidate, TieringRule};
+use crate::core::working_set;
+use crate::db::models::DbOperations;
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::{google_drive::GoogleDriveProvider, traits::StorageProvider};
+use crate::utils::unc;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct TieringResult {
+    pub offloaded: Vec<String>,
+    pub bytes_freed: i64,
+    pub errors: Vec<String>,
+}
+
+fn tiering_rules(profile: &SyncProfile) -> Result<Vec<TieringRule>, String> {
+    TieringRule::parse_rules(profile.tiering_rules.as_deref().unwrap_or(""))
+        .map_err(|e| format!("Invalid tiering rules: {}", e))
+}
+
+/// List the local files the tiering rules would offload right now, oldest first.
+#[tauri::command]
+pub async fn preview_tiering() -> Result<Vec<TieringCandidate>, String> {
+    tracing::info!("Preview tiering command called");
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let rules = tiering_rules(&profile)?;
+
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| tiering::plan_tiering(conn, profile_id, &rules, chrono::Utc::now()))
+        .await
+        .map_err(|e| format!("Failed to plan tiering: {}", e))
+}
+
+/// Archive every file selected by the tiering rules to the Drive archive folder,
+/// verify the upload, then remove the local copy.
+#[tauri::command]
+pub async fn apply_tiering() -> Result<TieringResult, String> {
+    tracing::info!("Apply tiering command called");
+
+    let candidates = preview_tiering().await?;
+    let (profile, db) = get_or_create_default_profile().await?;
+
+    let archive_folder_id = profile.tiering_archive_folder_id.clone()
+        .ok_or_else(|| "Tiering archive folder not configured".to_string())?;
+    let archive = GoogleDriveProvider::for_profile(&profile, archive_folder_id.clone())
+        .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+    if !archive.is_authenticated() {
+        return Err("Google Drive not authenticated".to_string());
+    }
+
+    let local_root = PathBuf::from(&profile.local_path);
+    let mut result = TieringResult {
+        offloaded: Vec::new(),
+        bytes_freed: 0,
+        errors: Vec::new(),
+    };
+
+    for candidate in candidates {
+        let path = Path::new(&candidate.path);
+        if let Err(e) = archive_file(&archive, &local_root, path).await {
+            tracing::error!("Failed to archive {}: {}", candidate.path, e);
+            result.errors.push(format!("{}: {}", candidate.path, e));
+            continue;
+        }
+
+        let profile_id = profile.id.unwrap();
+        let (offloaded_path, folder_id) = (candidate.path.clone(), archive_folder_id.clone());
+        let marked = db.call(move |conn| tiering::mark_offloaded(conn, profile_id, &offloaded_path, &folder_id)).await;
+        if let Err(e) = marked {
+            result.errors.push(format!("{}: {}", candidate.path, e));
+            continue;
+        }
+
+        let removed = tokio::fs::remove_file(local_root.join(path)).await;
+        scan_cache::invalidate(profile_id, Some(&FileLocation::Local), path);
+        if let Err(e) = removed {
+            result.errors.push(format!("{}: archived but not removed locally: {}", candidate.path, e));
+            continue;
+        }
+
+        let stub = StubFile {
+            path: candidate.path.clone(),
+            original_name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            size: Some(candidate.size),
+            content_hash: candidate.content_hash.clone(),
+            location: "gdrive-archive".to_string(),
+            archive_folder_id: archive_folder_id.clone(),
+            offloaded_at: chrono::Utc::now(),
+        };
+        if let Err(e) = stubs::write_stub(&local_root, &stub) {
+            tracing::warn!("Failed to write placeholder for {}: {}", candidate.path, e);
+        }
+
+        result.bytes_freed += candidate.size;
+        result.offloaded.push(candidate.path);
+    }
+
+    tracing::info!("Tiering offloaded {} files ({} bytes)", result.offloaded.len(), result.bytes_freed);
+    Ok(result)
+}
+
+/// Upload to the archive and confirm Drive holds identical content (MD5).
+async fn archive_file(archive: &GoogleDriveProvider, local_root: &Path, path: &Path) -> Result<(), String> {
+    let source = local_root.join(path);
+    let local_md5 = file_hasher::compute_file_md5(&source).map_err(|e| e.to_string())?;
+
+    archive.upload(&source, path).await.map_err(|e| e.to_string())?;
+
+    let uploaded = archive.get_metadata(path).await.map_err(|e| e.to_string())?;
+    match uploaded.and_then(|m| m.hash) {
+        Some(md5) if md5.eq_ignore_ascii_case(&local_md5) => Ok(()),
+        _ => Err("archive copy could not be verified".to_string()),
+    }
+}
+
+/// Download an offloaded file back from the archive. Accepts the file's relative
+/// path or the path of its `.uvcadstub` placeholder (e.g. from a double-click).
+#[tauri::command]
+pub async fn restore_offloaded(path: String) -> Result<String, String> {
+    tracing::info!("Restore offloaded file: {}", path);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let local_root = PathBuf::from(&profile.local_path);
+
+    // Absolute paths may name the root by drive letter or UNC path
+    let path = match unc::strip_root(Path::new(&path), &profile.local_path) {
+        Some(relative) if Path::new(&path).is_absolute() => relative.to_string_lossy().to_string(),
+        _ => path,
+    };
+
+    let (relative, archive_folder_id) = if stubs::is_stub(Path::new(&path)) {
+        let stub_path = local_root.join(&path);
+        let stub = stubs::read_stub(&stub_path)
+            .map_err(|e| format!("Failed to read placeholder: {}", e))?;
+        (stub.path, stub.archive_folder_id)
+    } else {
+        let profile_id = profile.id.unwrap();
+        let state = db.call(move |conn| DbOperations::get_file_states(conn, profile_id))
+            .await
+            .map_err(|e| format!("Failed to get file states: {}", e))?
+            .into_iter()
+            .find(|s| s.location == FileLocation::Local && s.file_path == path)
+            .ok_or_else(|| format!("File is not tracked: {}", path))?;
+        let record = tiering::read_archive_record(state.metadata.as_deref())
+            .ok_or_else(|| format!("File is not offloaded: {}", path))?;
+        (path, record.archive_folder_id)
+    };
+
+    let archive = GoogleDriveProvider::for_profile(&profile, archive_folder_id)
+        .map_err(|e| format!("Failed to initialize Google Drive: {}", e))?;
+    let dest = local_root.join(&relative);
+    archive.download(Path::new(&relative), &dest)
+        .await
+        .map_err(|e| format!("Failed to restore {}: {}", relative, e))?;
+
+    let profile_id = profile.id.unwrap();
+    scan_cache::invalidate(profile_id, Some(&FileLocation::Local), Path::new(&relative));
+    let restored = relative.clone();
+    db.call(move |conn| {
+        tiering::mark_restored(conn, profile_id, &restored)?;
+        // Opening a placeholder shows where the user is working
+        working_set::record_opened(conn, profile_id, &plan_approval::current_user(), &[restored], chrono::Utc::now())
+    })
+        .await
+        .map_err(|e| format!("Failed to update file state: {}", e))?;
+
+    let _ = tokio::fs::remove_file(stubs::stub_path_for(&dest)).await;
+
+    tracing::info!("Restored {} from archive", relative);
+    Ok(relative)
+}
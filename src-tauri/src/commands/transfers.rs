@@ -0,0 +1,150 @@
+use crate::commands::pauses::ensure_not_paused;
+use crate::commands::sync::{begin_sync, build_sync_engine, finish_sync, get_or_create_default_profile, SyncResultDto};
+use crate::core::bootstrap::{self, BootstrapProgress};
+use crate::core::sync_engine;
+use crate::db::models::DbOperations;
+use crate::models::file_state::FileLocation;
+use crate::models::transfer::{Transfer, TransferStatus};
+use std::path::PathBuf;
+use tauri::Manager;
+
+/// Pending, running and failed transfers in the order they will run.
+#[tauri::command]
+pub async fn get_transfer_queue() -> Result<Vec<Transfer>, String> {
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to get transfers: {}", e))
+}
+
+/// Move a transfer to `position` in the queue (0 runs next). Takes effect
+/// immediately, including for a sync that is already running.
+#[tauri::command]
+pub async fn reorder_transfer(id: i64, position: usize) -> Result<Vec<Transfer>, String> {
+    tracing::info!("Reorder transfer {} to position {}", id, position);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| {
+        let mut queue = DbOperations::get_transfers(conn, profile_id)?;
+        let Some(index) = queue.iter().position(|t| t.id == Some(id)) else {
+            return Ok(Err(format!("Transfer not found: {}", id)));
+        };
+
+        let transfer = queue.remove(index);
+        queue.insert(position.min(queue.len()), transfer);
+
+        for (priority, transfer) in queue.iter_mut().enumerate() {
+            transfer.priority = priority as i64;
+            DbOperations::set_transfer_priority(conn, transfer.id.unwrap(), transfer.priority)?;
+        }
+        Ok(Ok(queue))
+    })
+    .await
+    .map_err(|e| format!("Failed to reorder transfers: {}", e))?
+}
+
+/// Remove a queued or failed transfer. A running transfer cannot be cancelled.
+#[tauri::command]
+pub async fn cancel_transfer(id: i64) -> Result<(), String> {
+    tracing::info!("Cancel transfer {}", id);
+
+    let (_, db) = get_or_create_default_profile().await?;
+
+    db.call(move |conn| {
+        let Some(transfer) = DbOperations::get_transfer(conn, id)? else {
+            return Ok(Err(format!("Transfer not found: {}", id)));
+        };
+        if transfer.status == TransferStatus::InProgress {
+            return Ok(Err("Transfer is already running".to_string()));
+        }
+
+        DbOperations::delete_transfer(conn, id)?;
+        Ok(Ok(()))
+    })
+    .await
+    .map_err(|e| format!("Failed to cancel transfer: {}", e))?
+}
+
+/// Run a failed transfer again.
+#[tauri::command]
+pub async fn retry_transfer(app: tauri::AppHandle, id: i64) -> Result<SyncResultDto, String> {
+    tracing::info!("Retry transfer {}", id);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let transfer = db.call(move |conn| DbOperations::get_transfer(conn, id))
+        .await
+        .map_err(|e| format!("Failed to get transfer: {}", e))?
+        .ok_or_else(|| format!("Transfer not found: {}", id))?;
+    if transfer.status != TransferStatus::Failed {
+        return Err("Only failed transfers can be retried".to_string());
+    }
+
+    let profile_id = profile.id.unwrap();
+    begin_sync(profile_id)?;
+
+    let result = async {
+        let sync_engine = build_sync_engine(&app, &profile, db, &sync_engine::new_run_id())?;
+        let result = sync_engine.retry_transfers(&[id])
+            .await
+            .map_err(|e| format!("Retry failed: {}", e))?;
+        Ok((SyncResultDto::from_result(&result), result))
+    }.await;
+    finish_sync(profile_id, result)
+}
+
+/// Push every local file missing from Google Drive with several uploads at once,
+/// for the first upload of a large vault. Files go folder by folder, those under
+/// `deprioritized` folders last. Running it again after the app was closed picks
+/// up the queued uploads where they stopped. Progress with an estimate of the
+/// time left is sent as `bootstrap-progress` events.
+#[tauri::command]
+pub async fn start_initial_upload(
+    app: tauri::AppHandle,
+    deprioritized: Option<Vec<String>>,
+) -> Result<SyncResultDto, String> {
+    tracing::info!("Initial upload command called");
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let deprioritized: Vec<PathBuf> = deprioritized.unwrap_or_default().iter().map(PathBuf::from).collect();
+
+    let profile_id = profile.id.unwrap();
+    ensure_not_paused(&db, profile_id, &FileLocation::GoogleDrive).await?;
+    begin_sync(profile_id)?;
+
+    let result = async {
+        let sync_engine = build_sync_engine(&app, &profile, db, &sync_engine::new_run_id())?;
+        let on_progress = |progress: &BootstrapProgress| {
+            let _ = app.emit_all("bootstrap-progress", progress);
+        };
+        let result = sync_engine.bootstrap_upload(&deprioritized, &on_progress)
+            .await
+            .map_err(|e| format!("Initial upload failed: {}", e))?;
+        Ok((SyncResultDto::from_result(&result), result))
+    }.await;
+    finish_sync(profile_id, result)
+}
+
+/// Move the queued transfers under `folder` to the end of the queue, e.g. to
+/// get the rest of the vault onto Drive first. Takes effect immediately,
+/// including for an initial upload that is already running.
+#[tauri::command]
+pub async fn deprioritize_folder(folder: String) -> Result<Vec<Transfer>, String> {
+    tracing::info!("Deprioritize transfers under {}", folder);
+
+    let (profile, db) = get_or_create_default_profile().await?;
+    let profile_id = profile.id.unwrap();
+
+    db.call(move |conn| {
+        let queue = bootstrap::deprioritize(DbOperations::get_transfers(conn, profile_id)?, &PathBuf::from(&folder));
+        for transfer in &queue {
+            DbOperations::set_transfer_priority(conn, transfer.id.unwrap(), transfer.priority)?;
+        }
+        Ok(queue)
+    })
+    .await
+    .map_err(|e| format!("Failed to reorder transfers: {}", e))
+}
@@ -0,0 +1,47 @@
+use crate::commands::sync::{begin_sync, build_sync_engine, end_sync, get_profile};
+use crate::core::sync_engine::{self, RestoredFile};
+use crate::db::models::DbOperations;
+use crate::models::trashed_file::TrashedFile;
+
+/// The copies of the profile's files that syncs moved to the trash, most
+/// recently deleted first. Empty unless soft deletion is or was turned on.
+#[tauri::command]
+pub async fn list_deleted_files(profile_id: Option<i64>) -> Result<Vec<TrashedFile>, String> {
+    tracing::info!("List deleted files command called");
+
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+    db.call(move |conn| DbOperations::get_trashed_files(conn, profile_id))
+        .await
+        .map_err(|e| format!("Failed to list deleted files: {}", e))
+}
+
+/// Bring back a file a sync deleted: the copies its latest deletion moved to
+/// the trash are put back where they were, and the next sync copies it on to
+/// the other locations. Fails when none could be restored.
+#[tauri::command]
+pub async fn restore_deleted(app: tauri::AppHandle, file_path: String, profile_id: Option<i64>) -> Result<RestoredFile, String> {
+    tracing::info!("Restore deleted command called: {}", file_path);
+
+    let file_path = file_path.trim().replace('\\', "/").trim_matches('/').to_string();
+    let (profile, db) = get_profile(profile_id).await?;
+    let profile_id = profile.id.unwrap();
+
+    begin_sync(profile_id)?;
+    let result = async {
+        build_sync_engine(&app, &profile, db, &sync_engine::new_run_id())?
+            .restore_deleted(&file_path)
+            .await
+            .map_err(|e| format!("Failed to restore {}: {}", file_path, e))
+    }.await;
+    end_sync(profile_id);
+
+    let restored = result?;
+    if restored.restored.is_empty() {
+        let reasons: Vec<String> = restored.failed.iter()
+            .map(|(location, error)| format!("{}: {}", location.as_str(), error))
+            .collect();
+        return Err(format!("Failed to restore {}: {}", file_path, reasons.join("; ")));
+    }
+    Ok(restored)
+}
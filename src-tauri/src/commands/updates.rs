@@ -0,0 +1,141 @@
+use serde::Serialize;
+#[cfg(feature = "updater")]
+use {
+    crate::commands::notifications,
+    crate::commands::sync::create_database,
+    crate::core::notifications::{Notification, NotificationKind, Severity},
+    crate::core::updates::{self, UpdateChannel},
+    crate::db::models::DbOperations,
+    crate::utils::i18n,
+    tauri::{updater, Manager, Wry},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub channel: String,
+    pub current_version: String,
+    pub latest_version: String,
+    pub available: bool,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+    /// False where updates come from the package manager instead.
+    pub can_install: bool,
+}
+
+#[cfg(feature = "updater")]
+async fn configured_channel() -> Result<UpdateChannel, String> {
+    let db = create_database().await?;
+    let channel = db.call(|conn| DbOperations::get_app_setting(conn, updates::CHANNEL_SETTING))
+        .await
+        .map_err(|e| format!("Failed to read the update channel: {}", e))?;
+    UpdateChannel::from_config(channel.as_deref())
+        .map_err(|e| format!("Invalid update channel: {}", e))
+}
+
+/// Returned by builds without the `updater` feature, which can't verify releases.
+#[cfg(not(feature = "updater"))]
+const UPDATES_DISABLED: &str = "Updates are not enabled in this build";
+
+#[cfg(feature = "updater")]
+async fn check(app: &tauri::AppHandle, channel: UpdateChannel) -> updater::Result<updater::UpdateResponse<Wry>> {
+    updater::builder(app.clone())
+        .endpoints(&[channel.feed_url()])
+        .skip_events()
+        .check()
+        .await
+}
+
+#[cfg(feature = "updater")]
+#[tauri::command]
+pub async fn check_for_update(app: tauri::AppHandle) -> Result<UpdateInfo, String> {
+    let channel = configured_channel().await?;
+    tracing::info!("Checking for updates on the {} channel", channel.as_str());
+
+    let current_version = app.package_info().version.to_string();
+    let update = match check(&app, channel).await {
+        Ok(update) => update,
+        // Returned when the feed has nothing newer than this build
+        Err(updater::Error::UpToDate) => {
+            return Ok(UpdateInfo {
+                channel: channel.as_str().to_string(),
+                latest_version: current_version.clone(),
+                current_version,
+                available: false,
+                notes: None,
+                date: None,
+                can_install: updates::can_self_update(),
+            });
+        }
+        Err(e) => return Err(format!("Update check failed: {}", e)),
+    };
+
+    Ok(UpdateInfo {
+        channel: channel.as_str().to_string(),
+        current_version,
+        latest_version: update.latest_version().to_string(),
+        available: update.is_update_available(),
+        notes: update.body().cloned(),
+        date: update.date().map(|d| d.to_string()),
+        can_install: updates::can_self_update(),
+    })
+}
+
+#[cfg(not(feature = "updater"))]
+#[tauri::command]
+pub async fn check_for_update(_app: tauri::AppHandle) -> Result<UpdateInfo, String> {
+    Err(UPDATES_DISABLED.to_string())
+}
+
+/// Download and install the latest release on the configured channel, then restart.
+#[cfg(feature = "updater")]
+#[tauri::command]
+pub async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
+    if !updates::can_self_update() {
+        return Err("This installation is updated by the system package manager".to_string());
+    }
+
+    let channel = configured_channel().await?;
+    let update = match check(&app, channel).await {
+        Ok(update) => update,
+        Err(updater::Error::UpToDate) => return Err("UVCAD is already up to date".to_string()),
+        Err(e) => return Err(format!("Update check failed: {}", e)),
+    };
+    if !update.is_update_available() {
+        return Err("UVCAD is already up to date".to_string());
+    }
+
+    tracing::info!("Installing UVCAD {}", update.latest_version());
+    update.download_and_install()
+        .await
+        .map_err(|e| format!("Failed to install update: {}", e))?;
+
+    app.restart();
+    Ok(())
+}
+
+#[cfg(not(feature = "updater"))]
+#[tauri::command]
+pub async fn install_update(_app: tauri::AppHandle) -> Result<(), String> {
+    Err(UPDATES_DISABLED.to_string())
+}
+
+/// Check once at startup and tell the frontend when a newer version exists.
+#[cfg(feature = "updater")]
+pub async fn notify_if_update_available(app: tauri::AppHandle) {
+    match check_for_update(app.clone()).await {
+        Ok(info) if info.available => {
+            tracing::info!("UVCAD {} is available", info.latest_version);
+            notifications::notify(Notification::new(
+                NotificationKind::UpdateAvailable,
+                Severity::Info,
+                i18n::tr("notification.update_available", &[("version", info.latest_version.clone())]),
+            ));
+            let _ = app.emit_all("update-available", info);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("{}", e),
+    }
+}
+
+#[cfg(not(feature = "updater"))]
+pub async fn notify_if_update_available(_app: tauri::AppHandle) {}
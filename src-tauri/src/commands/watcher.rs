@@ -0,0 +1,178 @@
+use crate::commands::sync::{begin_sync, finish_sync, get_or_create_default_profile, get_profile, sync_files_inner};
+use crate::core::cad_temp;
+use crate::core::watcher::{Debouncer, FolderWatcher};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_plan::PlannedOperation;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// How often pending changes are checked for having settled.
+const TICK: Duration = Duration::from_millis(500);
+
+struct Running {
+    _watcher: FolderWatcher,
+    handle: tauri::async_runtime::JoinHandle<()>,
+    profile_id: i64,
+    folder: String,
+    started_at: DateTime<Utc>,
+}
+
+impl Running {
+    fn status(&self) -> WatcherStatus {
+        WatcherStatus {
+            running: true,
+            profile_id: Some(self.profile_id),
+            folder: Some(self.folder.clone()),
+            started_at: Some(self.started_at),
+        }
+    }
+}
+
+static WATCHER: Lazy<Mutex<Option<Running>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherStatus {
+    pub running: bool,
+    /// The profile whose changes are synced.
+    pub profile_id: Option<i64>,
+    /// The watched local folder.
+    pub folder: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Watch the local folder of the active profile and sync changed CAD files
+/// once their saves have settled. Restarts a running watcher, so it follows a
+/// changed local folder or another active profile.
+#[tauri::command]
+pub async fn start_watcher(app: tauri::AppHandle) -> Result<WatcherStatus, String> {
+    tracing::info!("Start watcher command called");
+
+    let (profile, _) = get_or_create_default_profile().await?;
+    if profile.local_path.is_empty() {
+        return Err("Local path not configured".to_string());
+    }
+    let settle = cad_temp::settle_delay(profile.save_settle_secs)
+        .map_err(|e| format!("Invalid save settings: {}", e))?;
+
+    let (sender, changes) = mpsc::unbounded_channel();
+    let watcher = FolderWatcher::start(&PathBuf::from(&profile.local_path), sender)
+        .map_err(|e| e.to_string())?;
+    let profile_id = profile.id.unwrap();
+    let handle = tauri::async_runtime::spawn(watch(app, profile_id, changes, Debouncer::new(settle)));
+
+    let running = Running { _watcher: watcher, handle, profile_id, folder: profile.local_path.clone(), started_at: Utc::now() };
+    let status = running.status();
+    if let Some(previous) = WATCHER.lock().unwrap().replace(running) {
+        previous.handle.abort();
+    }
+    tracing::info!("Watching {} for changes", profile.local_path);
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn stop_watcher() -> Result<(), String> {
+    tracing::info!("Stop watcher command called");
+
+    if let Some(running) = WATCHER.lock().unwrap().take() {
+        running.handle.abort();
+        tracing::info!("Stopped watching {}", running.folder);
+    }
+    Ok(())
+}
+
+/// Stop the watcher if it syncs the profile, e.g. once the profile is deleted.
+pub(crate) fn stop_watching(profile_id: i64) {
+    let mut watcher = WATCHER.lock().unwrap();
+    if watcher.as_ref().is_some_and(|running| running.profile_id == profile_id) {
+        if let Some(running) = watcher.take() {
+            running.handle.abort();
+            tracing::info!("Stopped watching {}", running.folder);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_watcher_status() -> Result<WatcherStatus, String> {
+    Ok(match WATCHER.lock().unwrap().as_ref() {
+        Some(running) => running.status(),
+        None => WatcherStatus { running: false, profile_id: None, folder: None, started_at: None },
+    })
+}
+
+async fn watch(app: tauri::AppHandle, profile_id: i64, mut changes: UnboundedReceiver<PathBuf>, mut debouncer: Debouncer) {
+    let mut tick = tokio::time::interval(TICK);
+    loop {
+        tokio::select! {
+            change = changes.recv() => match change {
+                Some(path) => debouncer.record(path, Instant::now()),
+                None => return,
+            },
+            _ = tick.tick() => {
+                if let Some(paths) = debouncer.take_ready(Instant::now()) {
+                    sync_changes(&app, profile_id, paths, &mut changes, &mut debouncer).await;
+                }
+            }
+        }
+    }
+}
+
+/// Sync the changed files. While another sync runs they wait for the next
+/// quiet period. Changes the sync itself made to the local folder are dropped,
+/// so downloads don't start another sync.
+async fn sync_changes(
+    app: &tauri::AppHandle,
+    profile_id: i64,
+    paths: Vec<PathBuf>,
+    changes: &mut UnboundedReceiver<PathBuf>,
+    debouncer: &mut Debouncer,
+) {
+    let (profile, db) = match get_profile(Some(profile_id)).await {
+        Ok(profile) => profile,
+        Err(e) => {
+            tracing::warn!("Sync of changed files failed: {}", e);
+            return;
+        }
+    };
+    if begin_sync(profile_id).is_err() {
+        for path in paths {
+            debouncer.record(path, Instant::now());
+        }
+        return;
+    }
+
+    tracing::info!("Syncing {} changed files", paths.len());
+    let roots = paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+    let result = sync_files_inner(app, profile, db, roots).await;
+    let written: HashSet<PathBuf> = match &result {
+        Ok((_, result)) => result.plan.iter()
+            .flat_map(|step| match &step.operation {
+                PlannedOperation::Copy { to: FileLocation::Local, .. } | PlannedOperation::Delete { at: FileLocation::Local } => {
+                    vec![PathBuf::from(&step.path)]
+                }
+                PlannedOperation::Move { at: FileLocation::Local, from } => vec![PathBuf::from(&step.path), PathBuf::from(from)],
+                _ => Vec::new(),
+            })
+            .collect(),
+        Err(_) => HashSet::new(),
+    };
+
+    match finish_sync(profile_id, result) {
+        Ok(dto) => {
+            let _ = app.emit_all("watcher-sync", &dto);
+        }
+        Err(e) => tracing::warn!("Sync of changed files failed: {}", e),
+    }
+
+    while let Ok(path) = changes.try_recv() {
+        if !written.contains(&path) {
+            debouncer.record(path, Instant::now());
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::commands::compare::stage_version;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::webdav::{self, DavContent, DavFile, DavSource};
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::error::{self, UvcadError};
+use async_trait::async_trait;
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::net::TcpListener;
+
+/// Where copies that aren't on this computer are tried, in order.
+const REMOTE_LOCATIONS: [FileLocation; 2] = [FileLocation::Smb, FileLocation::GoogleDrive];
+
+struct Server {
+    port: u16,
+    handle: tauri::async_runtime::JoinHandle<()>,
+}
+
+static SERVER: Lazy<Mutex<Option<Server>>> = Lazy::new(|| Mutex::new(None));
+/// The profile being served. Swapped when the settings change, so a running
+/// server keeps its port and mapped drives stay connected.
+static PROFILE: Lazy<RwLock<Option<Arc<ProfileSource>>>> = Lazy::new(|| RwLock::new(None));
+
+/// The active profile's files: listed from the sync state, read from the
+/// local folder or, for files not there, fetched from the share or Drive.
+struct ProfileSource {
+    profile: SyncProfile,
+    policies: ExtensionPolicies,
+    db: DbHandle,
+    staging: PathBuf,
+}
+
+#[async_trait]
+impl DavSource for ProfileSource {
+    async fn files(&self) -> error::Result<Vec<DavFile>> {
+        let profile_id = self.profile.id.unwrap_or(1);
+        let states = self.db.call(move |conn| DbOperations::get_file_states(conn, profile_id)).await?;
+        Ok(webdav::merged_files(&states))
+    }
+
+    async fn open(&self, path: &str) -> error::Result<DavContent> {
+        let local = Path::new(&self.profile.local_path).join(path);
+        if local.is_file() {
+            return Ok(DavContent { path: local, temporary: false });
+        }
+
+        let request_dir = self.staging.join(uuid::Uuid::new_v4().simple().to_string());
+        tokio::fs::create_dir_all(&request_dir).await?;
+        let mut last_error = format!("{} is not available at any location", path);
+        for location in &REMOTE_LOCATIONS {
+            match stage_version(&self.profile, &self.policies, Path::new(path), location, &request_dir).await {
+                Ok(staged) => {
+                    // Flatten into the staging folder so removing the file cleans up fully
+                    let file_name = staged.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    let dest = self.staging.join(format!("{}-{}", uuid::Uuid::new_v4().simple(), file_name));
+                    tokio::fs::rename(&staged, &dest).await?;
+                    let _ = tokio::fs::remove_dir_all(&request_dir).await;
+                    return Ok(DavContent { path: dest, temporary: true });
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        let _ = tokio::fs::remove_dir_all(&request_dir).await;
+        Err(UvcadError::ProviderError(last_error))
+    }
+}
+
+/// Delegates to the profile currently being served.
+struct CurrentProfile;
+
+impl CurrentProfile {
+    fn get(&self) -> error::Result<Arc<ProfileSource>> {
+        PROFILE.read().unwrap().clone()
+            .ok_or_else(|| UvcadError::InvalidConfig("No profile is being served".to_string()))
+    }
+}
+
+#[async_trait]
+impl DavSource for CurrentProfile {
+    async fn files(&self) -> error::Result<Vec<DavFile>> {
+        self.get()?.files().await
+    }
+
+    async fn open(&self, path: &str) -> error::Result<DavContent> {
+        self.get()?.open(path).await
+    }
+}
+
+/// Start, move or stop the WebDAV server to match the profile's `webdav_port`.
+pub async fn configure(profile: SyncProfile, db: DbHandle) -> Result<(), String> {
+    let Some(port) = profile.webdav_port else {
+        if let Some(server) = SERVER.lock().unwrap().take() {
+            server.handle.abort();
+            tracing::info!("Stopped the WebDAV server");
+        }
+        return Ok(());
+    };
+    let port = validate_port(port)?;
+
+    let policies = ExtensionPolicies::from_config(profile.extension_policies.as_deref())
+        .map_err(|e| format!("Invalid extension policies: {}", e))?;
+    let staging = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| "Failed to get project directory".to_string())?
+        .data_dir()
+        .join("webdav");
+    *PROFILE.write().unwrap() = Some(Arc::new(ProfileSource { profile, policies, db, staging }));
+
+    if SERVER.lock().unwrap().as_ref().is_some_and(|server| server.port == port) {
+        return Ok(());
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Failed to start the WebDAV server on port {}: {}", port, e))?;
+    let handle = tauri::async_runtime::spawn(webdav::serve(listener, Arc::new(CurrentProfile)));
+    if let Some(previous) = SERVER.lock().unwrap().replace(Server { port, handle }) {
+        previous.handle.abort();
+    }
+    tracing::info!("Serving synced files over WebDAV at {}", url(port));
+    Ok(())
+}
+
+pub fn validate_port(port: i64) -> Result<u16, String> {
+    u16::try_from(port).ok()
+        .filter(|port| *port > 0)
+        .ok_or_else(|| format!("Invalid WebDAV port: {}", port))
+}
+
+fn url(port: u16) -> String {
+    format!("http://127.0.0.1:{}/", port)
+}
+
+/// The address to map a drive to, or None while the server is off.
+#[tauri::command]
+pub async fn get_webdav_url() -> Result<Option<String>, String> {
+    Ok(SERVER.lock().unwrap().as_ref().map(|server| url(server.port)))
+}
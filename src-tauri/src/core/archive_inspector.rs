@@ -0,0 +1,133 @@
+use crate::db::models::DbOperations;
+use crate::models::file_state::FileLocation;
+use crate::utils::error::{Result, UvcadError};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const BUFFER_SIZE: usize = 8192;
+
+/// Key under which the archive index is stored in `file_states.metadata`.
+const METADATA_KEY: &str = "archive";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveMember {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    pub crc32: u32,
+    /// SHA-256 of the uncompressed member; `None` for directory entries.
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveIndex {
+    /// Content hash of the archive the index was built from, used to detect staleness.
+    pub source_hash: Option<String>,
+    pub members: Vec<ArchiveMember>,
+}
+
+/// Whether a file is a container format we know how to index.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+/// List the members of a zip archive with per-member hashes, without extracting to disk.
+pub fn inspect_zip(path: &Path) -> Result<Vec<ArchiveMember>> {
+    let file = File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| UvcadError::ArchiveError(format!("{}: {}", path.display(), e)))?;
+
+    let mut members = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .map_err(|e| UvcadError::ArchiveError(format!("{}: {}", path.display(), e)))?;
+
+        let sha256 = if entry.is_dir() {
+            None
+        } else {
+            let mut hasher = Sha256::new();
+            let mut buffer = vec![0u8; BUFFER_SIZE];
+            loop {
+                let count = entry.read(&mut buffer)?;
+                if count == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..count]);
+            }
+            Some(hex::encode(hasher.finalize()))
+        };
+
+        members.push(ArchiveMember {
+            name: entry.name().to_string(),
+            size: entry.size(),
+            compressed_size: entry.compressed_size(),
+            crc32: entry.crc32(),
+            sha256,
+        });
+    }
+
+    Ok(members)
+}
+
+/// Extract the archive index from a `file_states.metadata` JSON blob, if present.
+pub fn read_index(metadata: Option<&str>) -> Option<ArchiveIndex> {
+    let value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    serde_json::from_value(value.get(METADATA_KEY)?.clone()).ok()
+}
+
+/// Store the archive index into a metadata JSON blob, preserving any other keys.
+fn write_index(metadata: Option<&str>, index: &ArchiveIndex) -> Result<String> {
+    let mut value = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+
+    value[METADATA_KEY] = serde_json::to_value(index)?;
+    Ok(value.to_string())
+}
+
+/// (Re)index every local archive of a profile whose index is missing or stale.
+/// Returns the number of archives indexed.
+pub fn index_profile_archives(conn: &Connection, profile_id: i64, local_root: &Path) -> Result<usize> {
+    let states = DbOperations::get_file_states(conn, profile_id)?;
+    let mut indexed = 0;
+
+    for mut state in states {
+        if state.location != FileLocation::Local || !is_archive(Path::new(&state.file_path)) {
+            continue;
+        }
+
+        let up_to_date = read_index(state.metadata.as_deref())
+            .map(|index| index.source_hash == state.content_hash)
+            .unwrap_or(false);
+        if up_to_date {
+            continue;
+        }
+
+        let members = match inspect_zip(&local_root.join(&state.file_path)) {
+            Ok(members) => members,
+            Err(e) => {
+                tracing::warn!("Failed to inspect archive {}: {}", state.file_path, e);
+                continue;
+            }
+        };
+
+        let index = ArchiveIndex {
+            source_hash: state.content_hash.clone(),
+            members,
+        };
+        state.metadata = Some(write_index(state.metadata.as_deref(), &index)?);
+        DbOperations::upsert_file_state(conn, &state)?;
+        indexed += 1;
+    }
+
+    tracing::info!("Indexed {} archives for profile {}", indexed, profile_id);
+    Ok(indexed)
+}
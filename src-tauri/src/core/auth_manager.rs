@@ -7,6 +7,56 @@ use oauth2::{
     PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RefreshToken, Scope, TokenResponse, TokenUrl,
 };
 use oauth2::reqwest::async_http_client;
+use serde::Serialize;
+
+const FULL_SCOPE: &str = "https://www.googleapis.com/auth/drive";
+const FILE_SCOPE: &str = "https://www.googleapis.com/auth/drive.file";
+
+/// How much of a user's Google Drive UVCAD asks to access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriveScope {
+    /// Every file (`drive`).
+    #[default]
+    Full,
+    /// Only files UVCAD created or was given (`drive.file`). Files others add
+    /// in the web UI are hidden, so the synced folder must be one UVCAD created.
+    File,
+}
+
+impl DriveScope {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DriveScope::Full => "full",
+            DriveScope::File => "file",
+        }
+    }
+
+    /// Parse the profile setting; unset means the full scope.
+    pub fn from_config(config: Option<&str>) -> Result<Self> {
+        match config.map(|c| c.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("full") | Some("drive") => Ok(DriveScope::Full),
+            Some("file") | Some("drive.file") => Ok(DriveScope::File),
+            Some(other) => Err(UvcadError::InvalidConfig(format!("Unknown Google Drive scope: {}", other))),
+        }
+    }
+
+    /// The scope tokens were granted, from the space-separated scopes Google
+    /// returned. Tokens from before the scope was recorded asked for `drive`.
+    pub fn granted(scopes: Option<&str>) -> Self {
+        match scopes {
+            Some(scopes) if !scopes.split_whitespace().any(|scope| scope == FULL_SCOPE) => DriveScope::File,
+            _ => DriveScope::Full,
+        }
+    }
+
+    pub(crate) fn url(&self) -> &'static str {
+        match self {
+            DriveScope::Full => FULL_SCOPE,
+            DriveScope::File => FILE_SCOPE,
+        }
+    }
+}
 
 pub struct AuthManager {
     token_manager: TokenManager,
@@ -78,7 +128,7 @@ impl AuthManager {
     /// 5. Wait for callback (5 min timeout)
     /// 6. Verify CSRF, exchange code for tokens
     /// 7. Store tokens + credentials in keyring
-    pub async fn authenticate(&mut self) -> Result<OAuthTokens> {
+    pub async fn authenticate(&mut self, scope: DriveScope) -> Result<OAuthTokens> {
         let client_id = credentials::default_client_id().to_string();
         let client_secret = credentials::default_client_secret().to_string();
 
@@ -90,7 +140,7 @@ impl AuthManager {
         // Generate auth URL
         let (auth_url, csrf_token) = client
             .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new("https://www.googleapis.com/auth/drive".to_string()))
+            .add_scope(Scope::new(scope.url().to_string()))
             .set_pkce_challenge(pkce_challenge)
             .url();
 
@@ -130,6 +180,7 @@ impl AuthManager {
             expires_at: token_result.expires_in().map(|d| {
                 (chrono::Utc::now() + chrono::Duration::seconds(d.as_secs() as i64)).timestamp()
             }),
+            scope: Some(granted_scopes(&token_result).unwrap_or_else(|| scope.url().to_string())),
         };
 
         // Store tokens in keyring
@@ -189,6 +240,8 @@ impl AuthManager {
             expires_at: token_result.expires_in().map(|d| {
                 (chrono::Utc::now() + chrono::Duration::seconds(d.as_secs() as i64)).timestamp()
             }),
+            // A refresh never widens the scope
+            scope: granted_scopes(&token_result).or_else(|| tokens.scope.clone()),
         };
 
         self.token_manager.store_tokens(&new_tokens)?;
@@ -200,9 +253,37 @@ impl AuthManager {
         self.token_manager.has_tokens()
     }
 
+    /// The scope the stored tokens carry; None when signed out.
+    pub fn granted_scope(&self) -> Option<DriveScope> {
+        let tokens = self.token_manager.get_tokens().ok()?;
+        Some(DriveScope::granted(tokens.scope.as_deref()))
+    }
+
     pub fn logout(&self) -> Result<()> {
         self.token_manager.delete_tokens()?;
         let _ = self.credential_manager.delete_credentials();
         Ok(())
     }
 }
+
+/// The scopes Google granted, space-separated, when it said.
+fn granted_scopes(token_result: &impl TokenResponse<oauth2::basic::BasicTokenType>) -> Option<String> {
+    let scopes = token_result.scopes()?;
+    Some(scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_from_config_and_grant() {
+        assert_eq!(DriveScope::from_config(None).unwrap(), DriveScope::Full);
+        assert_eq!(DriveScope::from_config(Some(" drive.file ")).unwrap(), DriveScope::File);
+        assert!(DriveScope::from_config(Some("drive.readonly")).is_err());
+
+        assert_eq!(DriveScope::granted(None), DriveScope::Full);
+        assert_eq!(DriveScope::granted(Some(FILE_SCOPE)), DriveScope::File);
+        assert_eq!(DriveScope::granted(Some(&format!("openid {}", FULL_SCOPE))), DriveScope::Full);
+    }
+}
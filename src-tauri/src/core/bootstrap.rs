@@ -0,0 +1,90 @@
+use crate::models::transfer::Transfer;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+fn is_under(path: &Path, folder: &Path) -> bool {
+    path.starts_with(folder) && path != folder
+}
+
+/// Order files for the initial upload: folder by folder, so finished folders
+/// are complete on Drive early, with files under `deprioritized` folders last
+/// in the order the folders are given.
+pub fn upload_order(mut paths: Vec<PathBuf>, deprioritized: &[PathBuf]) -> Vec<PathBuf> {
+    let rank = |path: &Path| deprioritized.iter()
+        .position(|folder| is_under(path, folder))
+        .map_or(0, |i| i + 1);
+    paths.sort_by_cached_key(|path| (rank(path), path.parent().map(Path::to_path_buf), path.file_name().map(|n| n.to_os_string())));
+    paths
+}
+
+/// Move the queued transfers under `folder` behind all others, keeping their
+/// order. Returns the queue in its new order with updated priorities.
+pub fn deprioritize(queue: Vec<Transfer>, folder: &Path) -> Vec<Transfer> {
+    let (later, mut queue): (Vec<_>, Vec<_>) = queue.into_iter()
+        .partition(|t| is_under(Path::new(&t.file_path), folder));
+    queue.extend(later);
+    for (priority, transfer) in queue.iter_mut().enumerate() {
+        transfer.priority = priority as i64;
+    }
+    queue
+}
+
+/// How far the initial upload has got, sent to the UI after each file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BootstrapProgress {
+    pub files_total: usize,
+    pub files_done: usize,
+    pub files_failed: usize,
+    pub bytes_total: u64,
+    pub bytes_done: u64,
+    pub current_file: String,
+    pub elapsed_secs: u64,
+    /// Estimated seconds left at the throughput so far; None until a file has finished.
+    pub eta_secs: Option<u64>,
+}
+
+impl BootstrapProgress {
+    pub fn new(files_total: usize, bytes_total: u64) -> Self {
+        Self { files_total, bytes_total, ..Self::default() }
+    }
+
+    /// Count a finished file; failed files count as done so the estimate
+    /// only covers what is still to be uploaded.
+    pub fn record(&mut self, file: &str, bytes: u64, succeeded: bool, elapsed: Duration) {
+        self.files_done += 1;
+        if !succeeded {
+            self.files_failed += 1;
+        }
+        self.bytes_done = (self.bytes_done + bytes).min(self.bytes_total);
+        self.current_file = file.to_string();
+        self.elapsed_secs = elapsed.as_secs();
+        self.eta_secs = if self.bytes_done > 0 {
+            let rate = self.bytes_done as f64 / elapsed.as_secs_f64().max(1.0);
+            Some(((self.bytes_total - self.bytes_done) as f64 / rate).ceil() as u64)
+        } else {
+            None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orders_by_folder_with_deprioritized_folders_last() {
+        let paths = ["b/2.dwg", "archive/old.dwg", "a/z.dwg", "renders/x.png", "b/1.dwg", "a/sub/y.dwg", "top.dwg"]
+            .iter().map(PathBuf::from).collect();
+        let order = upload_order(paths, &[PathBuf::from("renders"), PathBuf::from("archive")]);
+        let order: Vec<_> = order.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        assert_eq!(order, ["top.dwg", "a/z.dwg", "a/sub/y.dwg", "b/1.dwg", "b/2.dwg", "renders/x.png", "archive/old.dwg"]);
+
+        let mut progress = BootstrapProgress::new(4, 400);
+        assert_eq!(progress.eta_secs, None);
+        progress.record("a/z.dwg", 100, true, Duration::from_secs(10));
+        assert_eq!(progress.eta_secs, Some(30));
+        progress.record("b/1.dwg", 100, false, Duration::from_secs(20));
+        assert_eq!((progress.files_done, progress.files_failed, progress.eta_secs), (2, 1, Some(20)));
+    }
+}
@@ -0,0 +1,308 @@
+use crate::core::file_hasher;
+use crate::core::quick_hash;
+use crate::db::{handle::DbHandle, models::DbOperations};
+use crate::utils::error::Result;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Neutral CAD formats whose exported files carry volatile header data
+/// (export timestamps, author, originating system) unrelated to geometry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalizedFormat {
+    Step,
+    Iges,
+}
+
+impl NormalizedFormat {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "step" | "stp" | "p21" => Some(NormalizedFormat::Step),
+            "iges" | "igs" => Some(NormalizedFormat::Iges),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NormalizedFormat::Step => "step",
+            NormalizedFormat::Iges => "iges",
+        }
+    }
+}
+
+/// Decides how each file is hashed for change detection.
+/// Files with a configured extension get a normalized hash that ignores
+/// header sections; everything else uses the plain SHA-256 content hash.
+/// Files above the sampling size only get a sampled hash, see `quick_hash::sampled_content_hash`.
+#[derive(Debug, Clone, Default)]
+pub struct HashPolicy {
+    normalized_extensions: Vec<String>,
+    sample_above: Option<u64>,
+    /// Where hashes of unchanged files are looked up by fingerprint.
+    known_hashes: Option<DbHandle>,
+}
+
+/// A content hash and the quick-hash fingerprint of the file it was computed
+/// from, if any.
+#[derive(Debug, Clone)]
+pub struct FileHash {
+    pub hash: String,
+    pub fingerprint: Option<String>,
+}
+
+impl HashPolicy {
+    /// Build from the profile's comma-separated extension list (e.g. "step, .stp,IGS").
+    pub fn from_config(extensions: Option<&str>) -> Self {
+        let normalized_extensions = extensions
+            .unwrap_or("")
+            .split(',')
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+
+        Self { normalized_extensions, sample_above: None, known_hashes: None }
+    }
+
+    /// Reuse the hashes recorded in `file_states` for files whose fingerprint
+    /// hasn't changed.
+    pub fn with_known_hashes(mut self, db: DbHandle) -> Self {
+        self.known_hashes = Some(db);
+        self
+    }
+
+    /// Hash files of at least `min_mb` megabytes by size, modification time and sampled blocks only.
+    pub fn with_sampling_above(mut self, min_mb: Option<i64>) -> Self {
+        self.sample_above = min_mb.filter(|mb| *mb > 0).map(|mb| mb as u64 * 1024 * 1024);
+        self
+    }
+
+    fn format_for(&self, path: &Path) -> Option<NormalizedFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        if self.normalized_extensions.contains(&ext) {
+            NormalizedFormat::from_extension(&ext)
+        } else {
+            None
+        }
+    }
+
+    /// Hash a file according to this policy. Files whose fingerprint matches
+    /// the one recorded with their last hash are not read in full again.
+    pub fn hash_file(&self, path: &Path) -> Result<FileHash> {
+        if let Some(threshold) = self.sample_above {
+            if std::fs::metadata(path)?.len() >= threshold {
+                let hash = quick_hash::sampled_content_hash(path)?;
+                return Ok(FileHash { hash, fingerprint: None });
+            }
+        }
+        let (hash, fingerprint) = match self.format_for(path) {
+            Some(format) => quick_hash::hash_with_prefilter(
+                path, format.as_str(), |f| self.known_hash(f), |p| compute_normalized_hash(p, format),
+            )?,
+            None => quick_hash::hash_with_prefilter(path, "sha256", |f| self.known_hash(f), file_hasher::compute_file_hash)?,
+        };
+        Ok(FileHash { hash, fingerprint: Some(fingerprint) })
+    }
+
+    fn known_hash(&self, fingerprint: &str) -> Option<String> {
+        let db = self.known_hashes.as_ref()?;
+        db.call_blocking(|conn| DbOperations::find_hash_by_fingerprint(conn, fingerprint))
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to look up a recorded hash: {}", e);
+                None
+            })
+    }
+}
+
+/// Compute a SHA-256 hash over the semantically relevant part of a STEP/IGES file.
+///
+/// - STEP: the `HEADER; ... ENDSEC;` section is skipped.
+/// - IGES: Start (S), Global (G) and Terminate (T) section lines are skipped,
+///   leaving only the Directory Entry and Parameter Data sections.
+///
+/// Line endings are normalized so CRLF/LF differences between exporters
+/// don't register as changes.
+pub fn compute_normalized_hash(path: &Path, format: NormalizedFormat) -> Result<String> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut line = Vec::new();
+    let mut in_step_header = false;
+    let mut step_header_done = false;
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let content = trim_line_ending(&line);
+
+        let skip = match format {
+            NormalizedFormat::Step => {
+                if !step_header_done && !in_step_header && trim_ascii(content).starts_with(b"HEADER;") {
+                    in_step_header = true;
+                }
+                if in_step_header {
+                    if contains(content, b"ENDSEC;") {
+                        in_step_header = false;
+                        step_header_done = true;
+                    }
+                    true
+                } else {
+                    false
+                }
+            }
+            NormalizedFormat::Iges => matches!(content.get(72), Some(b'S' | b'G' | b'T')),
+        };
+
+        if !skip {
+            hasher.update(content);
+            hasher.update(b"\n");
+        }
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Replace identifying STEP header data (author, organization, originating system,
+/// description) with neutral values before a file leaves the company.
+/// FILE_SCHEMA and any other header statements are kept; the DATA section is untouched.
+pub fn strip_step_metadata(content: &[u8], file_name: &str) -> Vec<u8> {
+    let Some(header_start) = find(content, b"HEADER;") else {
+        return content.to_vec();
+    };
+    let body_start = header_start + b"HEADER;".len();
+    let Some(header_len) = find(&content[body_start..], b"ENDSEC;") else {
+        return content.to_vec();
+    };
+    let body_end = body_start + header_len;
+
+    let header = String::from_utf8_lossy(&content[body_start..body_end]);
+    let mut statements = Vec::new();
+    for statement in split_step_statements(&header) {
+        let statement = statement.trim();
+        let keyword = statement.to_ascii_uppercase();
+        if keyword.starts_with("FILE_DESCRIPTION") {
+            statements.push("FILE_DESCRIPTION((''),'2;1');".to_string());
+        } else if keyword.starts_with("FILE_NAME") {
+            statements.push(format!(
+                "FILE_NAME('{}','',(''),(''),'','','');",
+                file_name.replace('\'', "''")
+            ));
+        } else if !statement.is_empty() {
+            statements.push(format!("{};", statement));
+        }
+    }
+
+    let mut output = Vec::with_capacity(content.len());
+    output.extend_from_slice(&content[..body_start]);
+    output.extend_from_slice(b"\n");
+    output.extend_from_slice(statements.join("\n").as_bytes());
+    output.extend_from_slice(b"\n");
+    output.extend_from_slice(&content[body_end..]);
+    output
+}
+
+/// Split STEP header text on `;` outside of quoted strings (`''` escapes a quote).
+fn split_step_statements(text: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut in_string = false;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            ';' if !in_string => {
+                statements.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let mut end = line.len();
+    while end > 0 && (line[end - 1] == b'\n' || line[end - 1] == b'\r') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+fn trim_ascii(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(s.len());
+    &s[start..]
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn step_file(timestamp: &str, data: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "ISO-10303-21;\r\nHEADER;\r\nFILE_DESCRIPTION(('part'),'2;1');\r\n\
+             FILE_NAME('part.stp','{}',(''),(''),'','','');\r\nFILE_SCHEMA(('AP214'));\r\nENDSEC;\r\n\
+             DATA;\r\n{}\r\nENDSEC;\r\nEND-ISO-10303-21;\r\n",
+            timestamp, data
+        )
+        .unwrap();
+        file
+    }
+
+    #[test]
+    fn test_step_header_changes_are_ignored() {
+        let a = step_file("2024-01-01T10:00:00", "#1=CARTESIAN_POINT('',(0.,0.,0.));");
+        let b = step_file("2024-06-01T12:30:00", "#1=CARTESIAN_POINT('',(0.,0.,0.));");
+
+        let hash_a = compute_normalized_hash(a.path(), NormalizedFormat::Step).unwrap();
+        let hash_b = compute_normalized_hash(b.path(), NormalizedFormat::Step).unwrap();
+        assert_eq!(hash_a, hash_b);
+    }
+
+    #[test]
+    fn test_step_data_changes_are_detected() {
+        let a = step_file("2024-01-01T10:00:00", "#1=CARTESIAN_POINT('',(0.,0.,0.));");
+        let b = step_file("2024-01-01T10:00:00", "#1=CARTESIAN_POINT('',(1.,0.,0.));");
+
+        let hash_a = compute_normalized_hash(a.path(), NormalizedFormat::Step).unwrap();
+        let hash_b = compute_normalized_hash(b.path(), NormalizedFormat::Step).unwrap();
+        assert_ne!(hash_a, hash_b);
+    }
+
+    #[test]
+    fn test_strip_step_metadata_keeps_schema_and_data() {
+        let content = b"ISO-10303-21;\nHEADER;\nFILE_DESCRIPTION(('Bob''s part'),'2;1');\n\
+            FILE_NAME('part.stp','2024-01-01',('Bob'),('ACME'),'SW','SW','');\n\
+            FILE_SCHEMA(('AP214'));\nENDSEC;\nDATA;\n#1=POINT('',(0.,0.,0.));\nENDSEC;\n";
+
+        let stripped = String::from_utf8(strip_step_metadata(content, "part.stp")).unwrap();
+        assert!(!stripped.contains("Bob"));
+        assert!(!stripped.contains("ACME"));
+        assert!(stripped.contains("FILE_SCHEMA(('AP214'));"));
+        assert!(stripped.contains("FILE_NAME('part.stp','',(''),(''),'','','');"));
+        assert!(stripped.contains("DATA;\n#1=POINT('',(0.,0.,0.));\nENDSEC;\n"));
+    }
+
+    #[test]
+    fn test_policy_only_applies_to_configured_extensions() {
+        let policy = HashPolicy::from_config(Some(" .STEP, igs "));
+        assert_eq!(policy.format_for(Path::new("a/part.step")), Some(NormalizedFormat::Step));
+        assert_eq!(policy.format_for(Path::new("a/part.IGS")), Some(NormalizedFormat::Iges));
+        assert_eq!(policy.format_for(Path::new("a/part.stp")), None);
+        assert_eq!(policy.format_for(Path::new("a/part.dwg")), None);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::utils::error::{Result, UvcadError};
+use std::path::{Component, Path};
+use std::time::Duration;
+
+/// How long a recently written file must stay unchanged before it is synced,
+/// unless the profile sets `save_settle_secs`.
+pub const DEFAULT_SAVE_SETTLE: Duration = Duration::from_secs(5);
+
+/// Extensions of autosaves, lock files and journals CAD programs write next to
+/// the drawings they have open.
+const TEMP_EXTENSIONS: [&str; 6] = [
+    "sv$",  // AutoCAD autosave
+    "ac$",  // AutoCAD temporary
+    "dwl",  // AutoCAD lock
+    "dwl2", // AutoCAD lock
+    "swj",  // SolidWorks journal
+    "lck",  // Inventor and others: lock file
+];
+
+/// Folders holding older copies the CAD program maintains itself.
+const TEMP_FOLDERS: [&str; 1] = [
+    "oldversions", // Inventor
+];
+
+/// Whether `path` is a CAD program's temporary or bookkeeping file, never worth syncing.
+pub fn is_cad_temp(path: &Path) -> bool {
+    let in_temp_folder = path.parent().is_some_and(|parent| parent.components().any(|c| match c {
+        Component::Normal(name) => TEMP_FOLDERS.contains(&name.to_string_lossy().to_lowercase().as_str()),
+        _ => false,
+    }));
+    if in_temp_folder {
+        return true;
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    // `~$part.sldprt` lock files, `swx1234.tmp` SolidWorks scratch files
+    name.starts_with("~$")
+        || (name.starts_with("swx") && extension == "tmp")
+        || TEMP_EXTENSIONS.contains(&extension.as_str())
+}
+
+/// The settle delay for a profile's `save_settle_secs`; 0 turns the check off.
+pub fn settle_delay(seconds: Option<i64>) -> Result<Duration> {
+    match seconds {
+        None => Ok(DEFAULT_SAVE_SETTLE),
+        Some(seconds) if seconds >= 0 => Ok(Duration::from_secs(seconds as u64)),
+        Some(seconds) => Err(UvcadError::InvalidConfig(format!("Invalid settle delay: {} seconds", seconds))),
+    }
+}
+
+/// The quiet period for a profile's `quiet_period_secs`; None or 0 means none.
+pub fn quiet_period(seconds: Option<i64>) -> Result<Duration> {
+    match seconds {
+        None => Ok(Duration::ZERO),
+        Some(seconds) if seconds >= 0 => Ok(Duration::from_secs(seconds as u64)),
+        Some(seconds) => Err(UvcadError::InvalidConfig(format!("Invalid quiet period: {} seconds", seconds))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognizes_cad_temp_files() {
+        assert!(is_cad_temp(Path::new("site/plan_1_1_1234.sv$")));
+        assert!(is_cad_temp(Path::new("site/plan.DWL")));
+        assert!(is_cad_temp(Path::new("parts/~$bracket.SLDPRT")));
+        assert!(is_cad_temp(Path::new("parts/swx2840.tmp")));
+        assert!(is_cad_temp(Path::new("assy/OldVersions/frame.0003.iam")));
+        assert!(!is_cad_temp(Path::new("site/plan.dwg")));
+        assert!(!is_cad_temp(Path::new("OldVersions.txt")));
+        assert!(!is_cad_temp(Path::new("parts/bracket.sldprt")));
+
+        assert_eq!(settle_delay(None).unwrap(), DEFAULT_SAVE_SETTLE);
+        assert!(settle_delay(Some(0)).unwrap().is_zero());
+        assert!(settle_delay(Some(-1)).is_err());
+        assert!(quiet_period(None).unwrap().is_zero());
+        assert_eq!(quiet_period(Some(120)).unwrap(), Duration::from_secs(120));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::db::models::DbOperations;
+use crate::models::file_event::{FileEvent, FileEventKind};
+use crate::models::file_state::FileLocation;
+use crate::utils::error::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Net changes at one location over a period. Paths are sorted.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LocationChanges {
+    pub location: FileLocation,
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// What changed at each location since `since`, from the recorded file events.
+pub fn changes_since(conn: &Connection, profile_id: i64, since: DateTime<Utc>) -> Result<Vec<LocationChanges>> {
+    let events = DbOperations::get_file_events_since(conn, profile_id, since)?;
+    Ok(summarize(&events))
+}
+
+/// Collapse each file's events (oldest first) into one net change. A file that
+/// was added and deleted again within the period is left out.
+fn summarize(events: &[FileEvent]) -> Vec<LocationChanges> {
+    let mut net: BTreeMap<(&str, &str), (FileEventKind, FileEventKind)> = BTreeMap::new();
+    for event in events {
+        net.entry((event.location.as_str(), event.file_path.as_str()))
+            .and_modify(|(_, last)| *last = event.kind)
+            .or_insert((event.kind, event.kind));
+    }
+
+    [FileLocation::Local, FileLocation::GoogleDrive, FileLocation::Smb]
+        .into_iter()
+        .filter_map(|location| {
+            let mut changes = LocationChanges {
+                location: location.clone(),
+                added: Vec::new(),
+                modified: Vec::new(),
+                deleted: Vec::new(),
+            };
+            for ((_, path), (first, last)) in net.iter().filter(|((l, _), _)| *l == location.as_str()) {
+                let list = match (first, last) {
+                    (FileEventKind::Added, FileEventKind::Deleted) => continue,
+                    (FileEventKind::Added, _) => &mut changes.added,
+                    (_, FileEventKind::Deleted) => &mut changes.deleted,
+                    _ => &mut changes.modified,
+                };
+                list.push(path.to_string());
+            }
+
+            let empty = changes.added.is_empty() && changes.modified.is_empty() && changes.deleted.is_empty();
+            (!empty).then_some(changes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(path: &str, location: FileLocation, kind: FileEventKind) -> FileEvent {
+        FileEvent::new(1, path.to_string(), location, kind, None)
+    }
+
+    #[test]
+    fn test_summarize_collapses_events() {
+        let events = vec![
+            event("new.sldprt", FileLocation::Local, FileEventKind::Added),
+            event("new.sldprt", FileLocation::Local, FileEventKind::Modified),
+            event("temp.bak", FileLocation::Local, FileEventKind::Added),
+            event("temp.bak", FileLocation::Local, FileEventKind::Deleted),
+            event("old.step", FileLocation::Local, FileEventKind::Modified),
+            event("old.step", FileLocation::Local, FileEventKind::Deleted),
+            event("recreated.dwg", FileLocation::Local, FileEventKind::Deleted),
+            event("recreated.dwg", FileLocation::Local, FileEventKind::Added),
+            event("new.sldprt", FileLocation::Smb, FileEventKind::Added),
+        ];
+
+        let changes = summarize(&events);
+        assert_eq!(changes.len(), 2);
+
+        assert_eq!(changes[0].location, FileLocation::Local);
+        assert_eq!(changes[0].added, vec!["new.sldprt"]);
+        assert_eq!(changes[0].modified, vec!["recreated.dwg"]);
+        assert_eq!(changes[0].deleted, vec!["old.step"]);
+
+        assert_eq!(changes[1].location, FileLocation::Smb);
+        assert_eq!(changes[1].added, vec!["new.sldprt"]);
+    }
+}
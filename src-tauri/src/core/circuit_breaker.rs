@@ -0,0 +1,132 @@
+use crate::models::file_state::FileLocation;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Failed transfers in a row after which a location is skipped for the rest of a run.
+pub const TRIP_AFTER_FAILURES: usize = 5;
+
+/// Wait before the transfer after a failure; doubled with every further failure.
+pub const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+
+/// A location skipped for the rest of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trip {
+    pub location: FileLocation,
+    /// The error of the failure that tripped the breaker.
+    pub last_error: String,
+    /// Transfers not attempted since.
+    pub skipped: usize,
+}
+
+/// Counts the failed transfers in a row at each remote location during a run,
+/// so a share that went away, e.g. a rebooting NAS, fails a handful of
+/// transfers instead of every one. Each failure is followed by a growing wait;
+/// after `threshold` of them the location is skipped until the next run. A
+/// failure is put down to the remote location written to, or for downloads to
+/// the one read from. The local folder is never skipped.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    threshold: usize,
+    backoff: Duration,
+    failures: HashMap<FileLocation, usize>,
+    tripped: Vec<Trip>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(TRIP_AFTER_FAILURES, FIRST_BACKOFF)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: usize, backoff: Duration) -> Self {
+        Self { threshold: threshold.max(1), backoff, failures: HashMap::new(), tripped: Vec::new() }
+    }
+
+    /// The tripped location a transfer from `source` to `destination` involves,
+    /// counting the transfer as skipped there.
+    pub fn skip(&mut self, source: Option<&FileLocation>, destination: &FileLocation) -> Option<FileLocation> {
+        let trip = self.tripped.iter_mut()
+            .find(|trip| Some(&trip.location) == source || trip.location == *destination)?;
+        trip.skipped += 1;
+        Some(trip.location.clone())
+    }
+
+    /// How long to wait before a transfer from `source` to `destination`, after
+    /// the failures in a row at its location.
+    pub fn backoff(&self, source: Option<&FileLocation>, destination: &FileLocation) -> Duration {
+        match blamed(source, destination).and_then(|location| self.failures.get(location)) {
+            Some(&failures) if failures > 0 => self.backoff.saturating_mul(1 << (failures - 1).min(16) as u32),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn succeeded(&mut self, source: Option<&FileLocation>, destination: &FileLocation) {
+        if let Some(location) = blamed(source, destination) {
+            self.failures.remove(location);
+        }
+    }
+
+    /// Count a failed transfer. Returns the location when this failure trips the breaker.
+    pub fn failed(&mut self, source: Option<&FileLocation>, destination: &FileLocation, error: &str) -> Option<FileLocation> {
+        let location = blamed(source, destination)?;
+        if self.tripped.iter().any(|trip| trip.location == *location) {
+            return None;
+        }
+        let failures = self.failures.entry(location.clone()).or_default();
+        *failures += 1;
+        if *failures < self.threshold {
+            return None;
+        }
+
+        self.tripped.push(Trip { location: location.clone(), last_error: error.to_string(), skipped: 0 });
+        Some(location.clone())
+    }
+
+    /// The trips so far, resetting the breaker for the next run.
+    pub fn take_tripped(&mut self) -> Vec<Trip> {
+        self.failures.clear();
+        std::mem::take(&mut self.tripped)
+    }
+}
+
+/// The remote location a failed transfer is put down to.
+fn blamed<'a>(source: Option<&'a FileLocation>, destination: &'a FileLocation) -> Option<&'a FileLocation> {
+    match destination {
+        FileLocation::Local => source.filter(|s| **s != FileLocation::Local),
+        _ => Some(destination),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FileLocation::*;
+
+    #[test]
+    fn test_trips_after_failures_in_a_row_at_one_location() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_millis(100));
+        assert_eq!(breaker.failed(Some(&Local), &Smb, "offline"), None);
+        assert_eq!(breaker.failed(Some(&Smb), &Local, "offline"), None);
+        assert_eq!(breaker.backoff(Some(&Local), &Smb), Duration::from_millis(200));
+        // Drive failures and successes don't count towards the share
+        breaker.failed(Some(&Local), &GoogleDrive, "rate limited");
+        breaker.succeeded(Some(&Local), &GoogleDrive);
+        assert_eq!(breaker.backoff(Some(&Local), &GoogleDrive), Duration::ZERO);
+
+        assert_eq!(breaker.failed(None, &Smb, "still offline"), Some(Smb));
+        assert_eq!(breaker.skip(Some(&Smb), &GoogleDrive), Some(Smb));
+        assert_eq!(breaker.skip(Some(&Local), &Smb), Some(Smb));
+        assert_eq!(breaker.skip(Some(&Local), &GoogleDrive), None);
+
+        let trips = breaker.take_tripped();
+        assert_eq!(trips, [Trip { location: Smb, last_error: "still offline".to_string(), skipped: 2 }]);
+        assert_eq!(breaker.skip(Some(&Local), &Smb), None);
+
+        // A success resets the count
+        let mut breaker = CircuitBreaker::new(2, Duration::ZERO);
+        breaker.failed(Some(&Local), &Smb, "offline");
+        breaker.succeeded(Some(&Local), &Smb);
+        assert_eq!(breaker.failed(Some(&Local), &Smb, "offline"), None);
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+/// Most transfers to Drive at once; more run into Drive's per-user request rate limit.
+pub const MAX_DRIVE_TRANSFERS: usize = 8;
+
+/// Most transfers to a file share at once.
+pub const MAX_SMB_TRANSFERS: usize = 4;
+
+/// Transfers per running worker that make up one measurement period.
+const TRANSFERS_PER_PERIOD: usize = 2;
+
+/// Throughput changes smaller than this fraction are treated as noise.
+const SIGNIFICANT_CHANGE: f64 = 0.1;
+
+/// Periods a settled limit is kept before trying one more transfer at a time again.
+const PROBE_AFTER_PERIODS: usize = 10;
+
+/// How many transfers to one location run at once, found by measuring. The limit
+/// is raised while throughput improves and lowered while fewer transfers do as
+/// well; when a change makes things worse it goes back and settles, trying again
+/// now and then. Latency bound uploads, e.g. many small files to Drive, end up
+/// with many parallel transfers; a saturated link, e.g. to a file server, at one.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrency {
+    limit: usize,
+    max: usize,
+    /// Direction of the change being tried; None while settled.
+    step: Option<isize>,
+    settled_periods: usize,
+    period_started: Option<Instant>,
+    period_bytes: u64,
+    period_transfers: usize,
+    last_rate: Option<f64>,
+    bytes_total: u64,
+    busy: Duration,
+}
+
+impl AdaptiveConcurrency {
+    /// Start with one transfer at a time and probe upwards, never past `max`.
+    pub fn new(max: usize) -> Self {
+        Self {
+            limit: 1,
+            max: max.max(1),
+            step: Some(1),
+            settled_periods: 0,
+            period_started: None,
+            period_bytes: 0,
+            period_transfers: 0,
+            last_rate: None,
+            bytes_total: 0,
+            busy: Duration::ZERO,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Note that a transfer started at `now`, so the first period is timed from it.
+    pub fn started(&mut self, now: Instant) {
+        self.period_started.get_or_insert(now);
+    }
+
+    /// Count a finished transfer of `bytes` and adjust the limit at the end of a period.
+    pub fn finished(&mut self, bytes: u64, now: Instant) {
+        let started = *self.period_started.get_or_insert(now);
+        self.period_bytes += bytes;
+        self.period_transfers += 1;
+        self.bytes_total += bytes;
+        if self.period_transfers < TRANSFERS_PER_PERIOD * self.limit {
+            return;
+        }
+
+        let elapsed = now.duration_since(started);
+        self.busy += elapsed;
+        let rate = self.period_bytes as f64 / elapsed.as_secs_f64().max(0.001);
+        let better = self.last_rate.is_none_or(|last| rate > last * (1.0 + SIGNIFICANT_CHANGE));
+        let worse = self.last_rate.is_some_and(|last| rate < last * (1.0 - SIGNIFICANT_CHANGE));
+        match self.step {
+            None => {
+                self.settled_periods += 1;
+                if self.settled_periods >= PROBE_AFTER_PERIODS {
+                    self.try_step(1);
+                }
+            }
+            Some(step) if better => self.try_step(step),
+            // More transfers didn't help, or fewer made it worse: go back
+            Some(step) if step > 0 || worse => {
+                self.limit = (self.limit as isize - step) as usize;
+                self.settle();
+            }
+            // Fewer transfers do as well
+            Some(step) => self.try_step(step),
+        }
+        tracing::debug!("Transfer throughput {:.0} bytes/s, now {} at a time", rate, self.limit);
+        self.last_rate = Some(rate);
+        self.period_started = Some(now);
+        self.period_bytes = 0;
+        self.period_transfers = 0;
+    }
+
+    /// A transfer failed: halve the limit, e.g. to get back under a rate limit.
+    pub fn failed(&mut self) {
+        self.limit = (self.limit / 2).max(1);
+        self.settle();
+    }
+
+    /// Average bytes per second over the measured periods, if any has ended.
+    pub fn throughput(&self) -> Option<u64> {
+        (!self.busy.is_zero()).then(|| (self.bytes_total as f64 / self.busy.as_secs_f64()) as u64)
+    }
+
+    /// Move the limit by `step`, settling where it can't move further.
+    fn try_step(&mut self, step: isize) {
+        let next = (self.limit as isize + step).clamp(1, self.max as isize) as usize;
+        if next == self.limit {
+            self.settle();
+        } else {
+            self.limit = next;
+            self.step = Some(step);
+        }
+    }
+
+    fn settle(&mut self) {
+        self.step = None;
+        self.settled_periods = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `periods` measurement periods where the link moves `rate(limit)` bytes per second.
+    fn settle(control: &mut AdaptiveConcurrency, periods: usize, rate: impl Fn(usize) -> u64) {
+        let mut now = Instant::now();
+        control.started(now);
+        for _ in 0..periods {
+            let limit = control.limit();
+            for _ in 0..TRANSFERS_PER_PERIOD * limit {
+                now += Duration::from_millis(100);
+                control.finished(rate(limit) / 10, now);
+            }
+        }
+    }
+
+    #[test]
+    fn test_limit_follows_throughput() {
+        // Small uploads: each extra transfer adds throughput up to the maximum
+        let mut latency_bound = AdaptiveConcurrency::new(6);
+        settle(&mut latency_bound, 20, |limit| 1000 * limit as u64);
+        assert_eq!(latency_bound.limit(), 6);
+
+        // Saturated link: a second stream adds nothing, so it goes back to one
+        let mut saturated = AdaptiveConcurrency::new(6);
+        settle(&mut saturated, 25, |_| 1_000_000);
+        assert_eq!(saturated.limit(), 1);
+        assert!(saturated.throughput().is_some());
+
+        latency_bound.failed();
+        assert_eq!(latency_bound.limit(), 3);
+    }
+}
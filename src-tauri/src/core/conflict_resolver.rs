@@ -1,6 +1,9 @@
-use crate::models::conflict::ConflictResolution;
-use crate::utils::error::Result;
+use crate::core::planner::location_rank;
+use crate::models::conflict::{Conflict as RecordedConflict, ConflictResolution};
+use crate::models::file_state::FileLocation;
+use chrono::NaiveDate;
 use serde::Serialize;
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Conflict {
@@ -10,6 +13,59 @@ pub struct Conflict {
     pub smb_hash: Option<String>,
 }
 
+impl From<&RecordedConflict> for Conflict {
+    fn from(recorded: &RecordedConflict) -> Self {
+        Self {
+            file_path: recorded.file_path.clone(),
+            local_hash: recorded.local_hash.clone(),
+            gdrive_hash: recorded.gdrive_hash.clone(),
+            smb_hash: recorded.smb_hash.clone(),
+        }
+    }
+}
+
+impl Conflict {
+    /// The content of the copy at `location`; None where there is no copy.
+    pub fn hash_at(&self, location: &FileLocation) -> Option<&str> {
+        match location {
+            FileLocation::Local => self.local_hash.as_deref(),
+            FileLocation::GoogleDrive => self.gdrive_hash.as_deref(),
+            FileLocation::Smb => self.smb_hash.as_deref(),
+        }
+    }
+}
+
+/// What applying a resolution would do to one copy.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ResolutionAction {
+    /// Replace the copy with the one at `from`.
+    Overwrite { from: FileLocation },
+    /// Create the file from the copy at `from`, where it is missing.
+    Copy { from: FileLocation },
+    /// Move the copy aside under `to`, keeping its content.
+    Rename { to: String },
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolutionStep {
+    pub location: FileLocation,
+    pub path: String,
+    #[serde(flatten)]
+    pub action: ResolutionAction,
+}
+
+/// Every change a resolution would make, in the order it would make them, and
+/// the copies it leaves as they are.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolutionPreview {
+    pub file_path: String,
+    pub resolution: ConflictResolution,
+    pub steps: Vec<ResolutionStep>,
+    pub unchanged: Vec<FileLocation>,
+}
+
 pub struct ConflictResolver {}
 
 impl ConflictResolver {
@@ -17,67 +73,171 @@ impl ConflictResolver {
         Self {}
     }
 
-    pub fn resolve_conflict(
+    /// What applying `resolution` to `conflict` would do at each of `locations`,
+    /// without doing it. Keeping one copy overwrites the differing copies with
+    /// it, copies it where the file is missing and, when that copy was deleted,
+    /// deletes the others. Keeping both leaves the local copy (or the first
+    /// remaining one) under the file's name and renames every other version to
+    /// `name (conflict from <location> <date>).ext` after the first location
+    /// holding it and the day of the resolution; each renamed version is then
+    /// copied to every location.
+    pub fn preview(
         &self,
         conflict: &Conflict,
         resolution: ConflictResolution,
-    ) -> Result<ResolvedConflict> {
-        // Determine which version to keep based on resolution strategy
-        let source = match resolution {
-            ConflictResolution::KeepLocal => ConflictSource::Local,
-            ConflictResolution::KeepGoogleDrive => ConflictSource::GoogleDrive,
-            ConflictResolution::KeepSmb => ConflictSource::Smb,
-            ConflictResolution::KeepBoth => ConflictSource::KeepAll,
-        };
+        locations: &[FileLocation],
+        date: NaiveDate,
+    ) -> ResolutionPreview {
+        let mut locations = locations.to_vec();
+        locations.sort_by_key(location_rank);
+        let path = conflict.file_path.clone();
+        let mut steps = Vec::new();
+        let mut unchanged = Vec::new();
 
-        Ok(ResolvedConflict {
-            file_path: conflict.file_path.clone(),
-            source,
-            resolution,
-        })
-    }
+        let kept = match resolution {
+            ConflictResolution::KeepLocal => Some(FileLocation::Local),
+            ConflictResolution::KeepGoogleDrive => Some(FileLocation::GoogleDrive),
+            ConflictResolution::KeepSmb => Some(FileLocation::Smb),
+            ConflictResolution::KeepBoth => None,
+        };
+        let (kept, kept_hash) = match kept {
+            Some(kept) => {
+                let hash = conflict.hash_at(&kept);
+                (kept, hash)
+            }
+            None => {
+                // Keep both: the first copy keeps the name, other versions are set aside
+                let Some(kept) = locations.iter().find(|l| conflict.hash_at(l).is_some()).cloned() else {
+                    return ResolutionPreview { file_path: path, resolution, steps, unchanged: locations };
+                };
+                let kept_hash = conflict.hash_at(&kept);
+                let mut versions: Vec<(&str, String)> = Vec::new();
+                for location in &locations {
+                    let Some(hash) = conflict.hash_at(location).filter(|h| Some(*h) != kept_hash) else {
+                        continue;
+                    };
+                    let renamed = match versions.iter().find(|(h, _)| *h == hash) {
+                        Some((_, renamed)) => renamed.clone(),
+                        None => {
+                            let renamed = conflict_copy(&path, location, date);
+                            versions.push((hash, renamed.clone()));
+                            renamed
+                        }
+                    };
+                    steps.push(ResolutionStep {
+                        location: location.clone(),
+                        path: path.clone(),
+                        action: ResolutionAction::Rename { to: renamed },
+                    });
+                }
+                for (hash, renamed) in &versions {
+                    let holder = locations.iter().find(|l| conflict.hash_at(l) == Some(*hash)).cloned().unwrap();
+                    for location in locations.iter().filter(|l| conflict.hash_at(l) != Some(*hash)) {
+                        steps.push(ResolutionStep {
+                            location: location.clone(),
+                            path: renamed.clone(),
+                            action: ResolutionAction::Copy { from: holder.clone() },
+                        });
+                    }
+                }
+                (kept, kept_hash)
+            }
+        };
 
-    pub fn detect_conflicts(
-        &self,
-        local_hash: Option<&str>,
-        gdrive_hash: Option<&str>,
-        smb_hash: Option<&str>,
-    ) -> Option<Conflict> {
-        let hashes: Vec<Option<&str>> = vec![local_hash, gdrive_hash, smb_hash];
-
-        // If all hashes that exist are the same, no conflict
-        let unique_hashes: Vec<&&str> = hashes
-            .iter()
-            .filter_map(|h| h.as_ref())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
+        let set_aside: Vec<FileLocation> = steps.iter()
+            .filter(|step| matches!(step.action, ResolutionAction::Rename { .. }))
+            .map(|step| step.location.clone())
             .collect();
-
-        if unique_hashes.len() <= 1 {
-            return None;
+        for location in locations.iter().filter(|l| **l != kept) {
+            let hash = conflict.hash_at(location);
+            let action = match (kept_hash, hash) {
+                (Some(_), _) if set_aside.contains(location) => ResolutionAction::Copy { from: kept.clone() },
+                (Some(k), Some(h)) if k == h => {
+                    unchanged.push(location.clone());
+                    continue;
+                }
+                (Some(_), Some(_)) => ResolutionAction::Overwrite { from: kept.clone() },
+                (Some(_), None) => ResolutionAction::Copy { from: kept.clone() },
+                (None, Some(_)) => ResolutionAction::Delete,
+                (None, None) => continue,
+            };
+            steps.push(ResolutionStep { location: location.clone(), path: path.clone(), action });
+        }
+        if kept_hash.is_some() {
+            unchanged.push(kept);
         }
+        unchanged.sort_by_key(location_rank);
 
-        // Multiple different hashes = conflict
-        Some(Conflict {
-            file_path: String::new(),
-            local_hash: local_hash.map(String::from),
-            gdrive_hash: gdrive_hash.map(String::from),
-            smb_hash: smb_hash.map(String::from),
-        })
+        ResolutionPreview { file_path: path, resolution, steps, unchanged }
     }
 }
 
-#[derive(Debug)]
-pub enum ConflictSource {
-    Local,
-    GoogleDrive,
-    Smb,
-    KeepAll,
+/// `path` with the location a conflicting version came from and the day it
+/// was set aside added to the name, e.g.
+/// `asm/frame (conflict from gdrive 2024-05-01).sldasm`.
+fn conflict_copy(path: &str, from: &FileLocation, date: NaiveDate) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let origin = format!("conflict from {} {}", from.as_str(), date.format("%Y-%m-%d"));
+    let name = match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, origin, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, origin),
+    };
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => format!("{}/{}", parent.to_string_lossy().replace('\\', "/"), name),
+        None => name,
+    }
 }
 
-#[derive(Debug)]
-pub struct ResolvedConflict {
-    pub file_path: String,
-    pub source: ConflictSource,
-    pub resolution: ConflictResolution,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FileLocation::*;
+
+    fn conflict(local: Option<&str>, gdrive: Option<&str>, smb: Option<&str>) -> Conflict {
+        Conflict {
+            file_path: "asm/frame.sldasm".to_string(),
+            local_hash: local.map(String::from),
+            gdrive_hash: gdrive.map(String::from),
+            smb_hash: smb.map(String::from),
+        }
+    }
+
+    fn step(location: FileLocation, path: &str, action: ResolutionAction) -> ResolutionStep {
+        ResolutionStep { location, path: path.to_string(), action }
+    }
+
+    #[test]
+    fn test_previews_what_each_resolution_changes() {
+        let resolver = ConflictResolver::new();
+        let all = [Local, GoogleDrive, Smb];
+        let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let edited_twice = conflict(Some("l"), Some("g"), Some("l"));
+
+        let keep_drive = resolver.preview(&edited_twice, ConflictResolution::KeepGoogleDrive, &all, today);
+        assert_eq!(keep_drive.steps, [
+            step(Local, "asm/frame.sldasm", ResolutionAction::Overwrite { from: GoogleDrive }),
+            step(Smb, "asm/frame.sldasm", ResolutionAction::Overwrite { from: GoogleDrive }),
+        ]);
+        assert_eq!(keep_drive.unchanged, [GoogleDrive]);
+
+        // Keeping both sets the Drive version aside everywhere next to the local one
+        let keep_both = resolver.preview(&edited_twice, ConflictResolution::KeepBoth, &all, today);
+        assert_eq!(keep_both.steps, [
+            step(GoogleDrive, "asm/frame.sldasm", ResolutionAction::Rename { to: "asm/frame (conflict from gdrive 2024-05-01).sldasm".to_string() }),
+            step(Local, "asm/frame (conflict from gdrive 2024-05-01).sldasm", ResolutionAction::Copy { from: GoogleDrive }),
+            step(Smb, "asm/frame (conflict from gdrive 2024-05-01).sldasm", ResolutionAction::Copy { from: GoogleDrive }),
+            step(GoogleDrive, "asm/frame.sldasm", ResolutionAction::Copy { from: Local }),
+        ]);
+        assert_eq!(keep_both.unchanged, [Local, Smb]);
+
+        // Keeping a deletion deletes the edited copies
+        let deleted_locally = conflict(None, Some("g"), Some("s"));
+        let keep_local = resolver.preview(&deleted_locally, ConflictResolution::KeepLocal, &all, today);
+        assert_eq!(keep_local.steps, [
+            step(GoogleDrive, "asm/frame.sldasm", ResolutionAction::Delete),
+            step(Smb, "asm/frame.sldasm", ResolutionAction::Delete),
+        ]);
+        assert!(keep_local.unchanged.is_empty());
+    }
 }
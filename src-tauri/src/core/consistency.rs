@@ -0,0 +1,73 @@
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::glob;
+use std::path::Path;
+
+/// Files that must reach other locations together, e.g. an assembly and its drawings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsistencyGroup {
+    pub name: String,
+    /// Globs on the relative path; a file matching any of them is a member.
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyGroups {
+    groups: Vec<ConsistencyGroup>,
+}
+
+impl ConsistencyGroups {
+    /// Parse groups written one per line as `name = pattern[, pattern]`, e.g.
+    /// `bracket = asm/bracket.sldasm, drawings/bracket*.slddrw`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn from_config(config: Option<&str>) -> Result<Self> {
+        let groups = config.unwrap_or("")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let invalid = || UvcadError::InvalidConfig(format!("Invalid consistency group: {}", line));
+                let (name, patterns) = line.split_once('=').ok_or_else(invalid)?;
+                let patterns: Vec<String> = patterns.split(',')
+                    .map(|p| p.trim().replace('\\', "/"))
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                if name.trim().is_empty() || patterns.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(ConsistencyGroup {
+                    name: name.trim().to_string(),
+                    patterns,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { groups })
+    }
+
+    /// The first group `path` belongs to.
+    pub fn group_of(&self, path: &Path) -> Option<&ConsistencyGroup> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.groups.iter()
+            .find(|group| group.patterns.iter().any(|pattern| glob::matches(pattern, &path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_membership() {
+        let groups = ConsistencyGroups::from_config(Some(
+            "# assemblies travel with their drawings\nbracket = asm/bracket.sldasm, drawings/bracket*.slddrw\n"
+        )).unwrap();
+
+        let name = |path: &str| groups.group_of(Path::new(path)).map(|g| g.name.clone());
+        assert_eq!(name("asm/bracket.sldasm"), Some("bracket".to_string()));
+        assert_eq!(name("drawings/Bracket-sheet2.SLDDRW"), Some("bracket".to_string()));
+        assert_eq!(name("asm/frame.sldasm"), None);
+
+        assert!(ConsistencyGroups::from_config(Some("bracket")).is_err());
+        assert!(ConsistencyGroups::from_config(Some("= a.sldasm")).is_err());
+    }
+}
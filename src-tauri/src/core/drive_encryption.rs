@@ -0,0 +1,390 @@
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::crypto;
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::secrets;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, pbkdf2};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+/// Start of every file this module encrypted, followed by the fingerprint of
+/// the key, the nonce prefix and the sealed chunks.
+const FILE_MAGIC: &[u8] = b"UVCE1";
+const FINGERPRINT_LEN: usize = 8;
+/// Random for each file. A chunk's nonce is the prefix, the chunk's index and
+/// whether it is the last one, so chunks can't be reordered or cut off.
+const NONCE_PREFIX_LEN: usize = 7;
+const HEADER_LEN: usize = FILE_MAGIC.len() + FINGERPRINT_LEN + NONCE_PREFIX_LEN;
+/// Plaintext sealed at a time; only one chunk is held in memory.
+const CHUNK_LEN: usize = 64 * 1024;
+/// AES-GCM tag following each chunk.
+const TAG_LEN: usize = 16;
+
+/// Start of an exported key file, followed by the salt and the encrypted keys.
+const EXPORT_MAGIC: &[u8] = b"UVCADKEY1";
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Secret holding a profile's keys, as JSON, followed by the profile id.
+const KEY_SECRET_PREFIX: &str = "drive_encryption_key_";
+
+/// An AES-256 key.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Key([u8; 32]);
+
+impl Key {
+    /// The first bytes of the key's SHA-256, stored with each file so the key
+    /// it was encrypted with can be found again.
+    pub fn fingerprint(&self) -> [u8; FINGERPRINT_LEN] {
+        let digest = Sha256::digest(self.0);
+        let mut fingerprint = [0; FINGERPRINT_LEN];
+        fingerprint.copy_from_slice(&digest[..FINGERPRINT_LEN]);
+        fingerprint
+    }
+
+    fn aead_key(&self) -> Result<aead::LessSafeKey> {
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &self.0)
+            .map_err(|_| UvcadError::InvalidConfig("Invalid encryption key".to_string()))?;
+        Ok(aead::LessSafeKey::new(key))
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, String> {
+        let bytes = STANDARD.decode(value).map_err(|e| e.to_string())?;
+        bytes.try_into().map(Key).map_err(|_| "An encryption key must be 32 bytes".to_string())
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        STANDARD.encode(key.0)
+    }
+}
+
+/// A rotation to a new key that has not re-encrypted every file yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rotation {
+    pub started_at: DateTime<Utc>,
+    /// The last path re-encrypted so far; files are taken in path order.
+    pub after: Option<String>,
+}
+
+/// The keys of a profile whose files on Google Drive are encrypted: the
+/// current one for everything written, and earlier ones kept so files not
+/// re-encrypted since a rotation can still be read.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProfileKeys {
+    pub current: Key,
+    #[serde(default)]
+    pub previous: Vec<Key>,
+    #[serde(default)]
+    pub rotation: Option<Rotation>,
+}
+
+impl ProfileKeys {
+    pub fn generate() -> Result<Self> {
+        Ok(Self { current: Key(crypto::generate_key()?), previous: Vec::new(), rotation: None })
+    }
+
+    /// Hex fingerprint of the current key, to compare between computers.
+    pub fn fingerprint(&self) -> String {
+        hex::encode(self.current.fingerprint())
+    }
+
+    /// Switch to a new key. Files stay encrypted with the old one, which is
+    /// kept for reading them, until `reencrypt` rewrites them.
+    pub fn rotate(&mut self) -> Result<()> {
+        let old = std::mem::replace(&mut self.current, Key(crypto::generate_key()?));
+        self.previous.insert(0, old);
+        self.rotation = Some(Rotation { started_at: Utc::now(), after: None });
+        Ok(())
+    }
+
+    /// Encrypt everything read from `input` with the current key into `output`.
+    pub fn encrypt_to(&self, input: impl Read, mut output: impl Write) -> Result<()> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        SystemRandom::new().fill(&mut prefix)
+            .map_err(|_| UvcadError::InvalidConfig("Random generation failed".to_string()))?;
+        output.write_all(FILE_MAGIC)?;
+        output.write_all(&self.current.fingerprint())?;
+        output.write_all(&prefix)?;
+
+        let key = self.current.aead_key()?;
+        let mut input = BufReader::new(input);
+        let mut chunk = Vec::with_capacity(CHUNK_LEN + TAG_LEN);
+        for index in 0..=u32::MAX {
+            chunk.clear();
+            (&mut input).take(CHUNK_LEN as u64).read_to_end(&mut chunk)?;
+            let last = input.fill_buf()?.is_empty();
+            key.seal_in_place_append_tag(chunk_nonce(&prefix, index, last), aead::Aad::empty(), &mut chunk)
+                .map_err(|_| UvcadError::InvalidConfig("Encryption failed".to_string()))?;
+            output.write_all(&chunk)?;
+            if last {
+                return Ok(());
+            }
+        }
+        Err(UvcadError::InvalidConfig("The file is too large to encrypt".to_string()))
+    }
+
+    /// Write the content of a stored file read from `input` to `output`. Files
+    /// stored before encryption was turned on are copied as they are.
+    pub fn decrypt_to(&self, input: impl Read, mut output: impl Write) -> Result<()> {
+        let mut input = BufReader::new(input);
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        (&mut input).take(HEADER_LEN as u64).read_to_end(&mut header)?;
+        let Some(fingerprint) = key_fingerprint(&header) else {
+            output.write_all(&header)?;
+            std::io::copy(&mut input, &mut output)?;
+            return Ok(());
+        };
+        let corrupt = || UvcadError::InvalidConfig("The encrypted file is damaged or incomplete".to_string());
+        let prefix: [u8; NONCE_PREFIX_LEN] = header[FILE_MAGIC.len() + FINGERPRINT_LEN..]
+            .try_into()
+            .map_err(|_| corrupt())?;
+
+        let key = std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.fingerprint() == fingerprint)
+            .ok_or_else(|| UvcadError::InvalidConfig(format!(
+                "The file was encrypted with key {}, which this computer does not have; import it first",
+                hex::encode(fingerprint)
+            )))?
+            .aead_key()?;
+        let mut chunk = Vec::with_capacity(CHUNK_LEN + TAG_LEN);
+        for index in 0..=u32::MAX {
+            chunk.clear();
+            (&mut input).take((CHUNK_LEN + TAG_LEN) as u64).read_to_end(&mut chunk)?;
+            let last = input.fill_buf()?.is_empty();
+            let plaintext = key.open_in_place(chunk_nonce(&prefix, index, last), aead::Aad::empty(), &mut chunk)
+                .map_err(|_| corrupt())?;
+            output.write_all(plaintext)?;
+            if last {
+                return Ok(());
+            }
+        }
+        Err(corrupt())
+    }
+
+    #[cfg(test)]
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut stored = Vec::new();
+        self.encrypt_to(plaintext, &mut stored)?;
+        Ok(stored)
+    }
+
+    #[cfg(test)]
+    pub fn decrypt(&self, stored: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        self.decrypt_to(stored, &mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    /// Whether the stored file at `path` is encrypted with the current key.
+    pub fn is_current(&self, path: &Path) -> Result<bool> {
+        let mut header = Vec::with_capacity(FILE_MAGIC.len() + FINGERPRINT_LEN);
+        File::open(path)?.take((FILE_MAGIC.len() + FINGERPRINT_LEN) as u64).read_to_end(&mut header)?;
+        Ok(key_fingerprint(&header) == Some(self.current.fingerprint()))
+    }
+
+    /// Encrypt `source` into `dest`, which may be the same file.
+    pub fn encrypt_file(&self, source: &Path, dest: &Path) -> Result<()> {
+        write_replacing(dest, |output| self.encrypt_to(File::open(source)?, output))
+    }
+
+    /// Decrypt `source` into `dest`, which may be the same file.
+    pub fn decrypt_file(&self, source: &Path, dest: &Path) -> Result<()> {
+        write_replacing(dest, |output| self.decrypt_to(File::open(source)?, output))
+    }
+
+    /// The keys, protected with `passphrase`, for a colleague's computer to
+    /// read the profile's files with. A rotation in progress is not included.
+    pub fn export(&self, passphrase: &str) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_LEN];
+        SystemRandom::new().fill(&mut salt)
+            .map_err(|_| UvcadError::InvalidConfig("Random generation failed".to_string()))?;
+        let keys = Self { rotation: None, ..self.clone() };
+        let mut exported = EXPORT_MAGIC.to_vec();
+        exported.extend(salt);
+        exported.extend(crypto::encrypt(&serde_json::to_vec(&keys)?, &derive_key(passphrase, &salt))?);
+        Ok(exported)
+    }
+
+    /// Read keys exported with `export`.
+    pub fn import(exported: &[u8], passphrase: &str) -> Result<Self> {
+        let Some(rest) = exported.strip_prefix(EXPORT_MAGIC).filter(|rest| rest.len() > SALT_LEN) else {
+            return Err(UvcadError::InvalidConfig("Not an exported UVCAD key file".to_string()));
+        };
+        let (salt, encrypted) = rest.split_at(SALT_LEN);
+        let json = crypto::decrypt(encrypted, &derive_key(passphrase, salt))
+            .map_err(|_| UvcadError::InvalidConfig("Wrong passphrase for the key file".to_string()))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// Take over imported keys: their current key encrypts from now on, and
+    /// every key known here before is kept for reading.
+    pub fn merge(&mut self, imported: ProfileKeys) {
+        let old = std::mem::replace(&mut self.current, imported.current);
+        for key in std::iter::once(old).chain(imported.previous) {
+            if key != self.current && !self.previous.contains(&key) {
+                self.previous.push(key);
+            }
+        }
+    }
+}
+
+fn key_fingerprint(stored: &[u8]) -> Option<[u8; FINGERPRINT_LEN]> {
+    stored.strip_prefix(FILE_MAGIC)?.get(..FINGERPRINT_LEN)?.try_into().ok()
+}
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_LEN], index: u32, last: bool) -> aead::Nonce {
+    let mut nonce = [0u8; aead::NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..aead::NONCE_LEN - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[aead::NONCE_LEN - 1] = u8::from(last);
+    aead::Nonce::assume_unique_for_key(nonce)
+}
+
+/// Write `dest` through a file next to it that replaces it once complete, so
+/// `dest` can also be the file being read.
+fn write_replacing(dest: &Path, write: impl FnOnce(&mut BufWriter<File>) -> Result<()>) -> Result<()> {
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let written = File::create(&partial).map_err(UvcadError::from).and_then(|file| {
+        let mut output = BufWriter::new(file);
+        write(&mut output)?;
+        output.flush()?;
+        Ok(())
+    });
+    match written {
+        Ok(()) => Ok(std::fs::rename(&partial, dest)?),
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    key
+}
+
+/// The keys to encrypt the profile's files on Google Drive with, or None
+/// when it stores them as they are.
+pub fn for_profile(profile: &SyncProfile) -> Result<Option<ProfileKeys>> {
+    if !profile.encrypt_drive {
+        return Ok(None);
+    }
+    load_or_create(profile.id.unwrap_or_default()).map(Some)
+}
+
+/// The keys of a profile; None when it never had any.
+pub fn load(profile_id: i64) -> Result<Option<ProfileKeys>> {
+    let Some(json) = secrets::store()?.get(&format!("{}{}", KEY_SECRET_PREFIX, profile_id))? else {
+        return Ok(None);
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|_| UvcadError::InvalidConfig("The stored Drive encryption key is corrupt".to_string()))
+}
+
+/// The keys of a profile, generating the first one when there is none.
+pub fn load_or_create(profile_id: i64) -> Result<ProfileKeys> {
+    if let Some(keys) = load(profile_id)? {
+        return Ok(keys);
+    }
+    let keys = ProfileKeys::generate()?;
+    save(profile_id, &keys)?;
+    tracing::info!("Created Drive encryption key {} for profile {}", keys.fingerprint(), profile_id);
+    Ok(keys)
+}
+
+pub fn save(profile_id: i64, keys: &ProfileKeys) -> Result<()> {
+    secrets::store()?.set(&format!("{}{}", KEY_SECRET_PREFIX, profile_id), &serde_json::to_string(keys)?)
+}
+
+/// Forget the keys of a deleted profile.
+pub fn delete(profile_id: i64) -> Result<()> {
+    secrets::store()?.delete(&format!("{}{}", KEY_SECRET_PREFIX, profile_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotated_keys_still_read_older_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("part.step");
+        std::fs::write(&path, "ISO-10303-21;").unwrap();
+        let mut keys = ProfileKeys::generate().unwrap();
+        keys.encrypt_file(&path, &path).unwrap();
+        assert!(keys.is_current(&path).unwrap());
+        assert_eq!(keys.decrypt(b"stored before encryption").unwrap(), b"stored before encryption");
+
+        keys.rotate().unwrap();
+        assert!(!keys.is_current(&path).unwrap());
+        keys.decrypt_file(&path, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"ISO-10303-21;");
+        let new = keys.encrypt(b"ISO-10303-21;").unwrap();
+
+        // A colleague with only the old key can't read what was written since
+        let colleague = ProfileKeys { current: keys.previous[0].clone(), previous: Vec::new(), rotation: None };
+        assert!(colleague.decrypt(&new).is_err());
+    }
+
+    #[test]
+    fn test_chunks_cannot_be_cut_off_or_altered() {
+        let keys = ProfileKeys::generate().unwrap();
+        for len in [0, 1, CHUNK_LEN, 2 * CHUNK_LEN + 5] {
+            let content: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            assert_eq!(keys.decrypt(&keys.encrypt(&content).unwrap()).unwrap(), content);
+        }
+
+        let stored = keys.encrypt(&vec![7u8; 2 * CHUNK_LEN]).unwrap();
+        assert!(keys.decrypt(&stored[..HEADER_LEN + CHUNK_LEN + TAG_LEN]).is_err());
+        let mut altered = stored.clone();
+        altered[HEADER_LEN + 10] ^= 1;
+        assert!(keys.decrypt(&altered).is_err());
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let mut keys = ProfileKeys::generate().unwrap();
+        keys.rotate().unwrap();
+        let exported = keys.export("correct horse").unwrap();
+        assert!(ProfileKeys::import(&exported, "wrong").is_err());
+        assert!(ProfileKeys::import(b"not a key file", "correct horse").is_err());
+
+        let imported = ProfileKeys::import(&exported, "correct horse").unwrap();
+        assert_eq!(imported.fingerprint(), keys.fingerprint());
+        assert_eq!(imported.previous.len(), 1);
+        assert!(imported.rotation.is_none());
+
+        let mut own = ProfileKeys::generate().unwrap();
+        let written_here = own.encrypt(b"local").unwrap();
+        own.merge(imported);
+        assert_eq!(own.fingerprint(), keys.fingerprint());
+        assert_eq!(own.decrypt(&written_here).unwrap(), b"local");
+        assert_eq!(own.previous.len(), 2);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::models::file_state::FileLocation;
+use crate::models::file_timing::FileTiming;
+use crate::models::sync_plan::{PlanStep, PlannedOperation};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Recent transfers the throughput between two locations is measured over.
+pub const SAMPLE_TRANSFERS: usize = 500;
+
+/// What a sync would move to and from one remote location.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationVolume {
+    pub location: FileLocation,
+    pub upload_files: usize,
+    pub upload_bytes: u64,
+    pub download_files: usize,
+    pub download_bytes: u64,
+}
+
+impl LocationVolume {
+    fn new(location: &FileLocation) -> Self {
+        Self { location: location.clone(), upload_files: 0, upload_bytes: 0, download_files: 0, download_bytes: 0 }
+    }
+}
+
+/// How much a sync would transfer and roughly how long it would take.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncEstimate {
+    pub locations: Vec<LocationVolume>,
+    pub files_to_copy: usize,
+    pub bytes_to_copy: u64,
+    pub files_to_delete: usize,
+    pub conflicts: usize,
+    /// From the throughput of recent transfers between the same locations; None
+    /// when none of the planned copies has been measured yet.
+    pub estimated_secs: Option<u64>,
+    /// Bytes between locations without measured transfers, not in `estimated_secs`.
+    pub unmeasured_bytes: u64,
+}
+
+/// Bytes per millisecond between each pair of locations over `timings`.
+fn throughput(timings: &[FileTiming]) -> HashMap<(FileLocation, FileLocation), f64> {
+    let mut totals: HashMap<(FileLocation, FileLocation), (u64, u64)> = HashMap::new();
+    for timing in timings {
+        let (Some(size), true) = (timing.size_bytes, timing.total_ms > 0) else {
+            continue;
+        };
+        let total = totals.entry((timing.source.clone(), timing.destination.clone())).or_default();
+        total.0 += size.max(0) as u64;
+        total.1 += timing.total_ms as u64;
+    }
+    totals.into_iter()
+        .filter(|(_, (bytes, _))| *bytes > 0)
+        .map(|(pair, (bytes, millis))| (pair, bytes as f64 / millis as f64))
+        .collect()
+}
+
+/// Estimate a sync plan from the recent transfers of the profile. Transfers are
+/// counted one after another, so their durations add up.
+pub fn estimate(plan: &[PlanStep], timings: &[FileTiming]) -> SyncEstimate {
+    let rates = throughput(timings);
+    let mut volumes: HashMap<FileLocation, LocationVolume> = HashMap::new();
+    let mut millis = 0.0;
+    let mut measured = false;
+    let mut unmeasured_bytes = 0;
+    let mut files_to_copy = 0;
+    let mut bytes_to_copy = 0;
+
+    for step in plan {
+        let PlannedOperation::Copy { from, to } = &step.operation else {
+            continue;
+        };
+        files_to_copy += 1;
+        bytes_to_copy += step.size_bytes;
+        if *to != FileLocation::Local {
+            let volume = volumes.entry(to.clone()).or_insert_with(|| LocationVolume::new(to));
+            volume.upload_files += 1;
+            volume.upload_bytes += step.size_bytes;
+        }
+        if *from != FileLocation::Local {
+            let volume = volumes.entry(from.clone()).or_insert_with(|| LocationVolume::new(from));
+            volume.download_files += 1;
+            volume.download_bytes += step.size_bytes;
+        }
+        match rates.get(&(from.clone(), to.clone())) {
+            Some(rate) => {
+                millis += step.size_bytes as f64 / rate;
+                measured = true;
+            }
+            None => unmeasured_bytes += step.size_bytes,
+        }
+    }
+
+    let mut locations: Vec<LocationVolume> = volumes.into_values().collect();
+    locations.sort_by_key(|v| v.location.as_str().to_string());
+
+    SyncEstimate {
+        locations,
+        files_to_copy,
+        bytes_to_copy,
+        files_to_delete: plan.iter().filter(|s| matches!(s.operation, PlannedOperation::Delete { .. })).count(),
+        conflicts: plan.iter().filter(|s| s.operation == PlannedOperation::Conflict).count(),
+        estimated_secs: measured.then(|| (millis / 1000.0).ceil() as u64),
+        unmeasured_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn timing(source: FileLocation, destination: FileLocation, size: i64, total_ms: i64) -> FileTiming {
+        FileTiming {
+            id: None,
+            profile_id: 1,
+            run_id: "run".to_string(),
+            file_path: "part.step".to_string(),
+            source,
+            destination,
+            size_bytes: Some(size),
+            download_ms: 0,
+            hash_ms: 0,
+            upload_ms: total_ms,
+            total_ms,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    fn step(operation: PlannedOperation, size_bytes: u64) -> PlanStep {
+        PlanStep { index: 0, path: "part.step".to_string(), operation, size_bytes, reason: None }
+    }
+
+    #[test]
+    fn test_estimates_from_measured_throughput() {
+        use FileLocation::*;
+        // 1 MB/s up to Drive, 10 MB/s down from the share
+        let timings = [timing(Local, GoogleDrive, 2_000_000, 2000), timing(Smb, Local, 10_000_000, 1000)];
+        let plan = [
+            step(PlannedOperation::Copy { from: Local, to: GoogleDrive }, 30_000_000),
+            step(PlannedOperation::Copy { from: Smb, to: Local }, 50_000_000),
+            step(PlannedOperation::Copy { from: GoogleDrive, to: Smb }, 7),
+            step(PlannedOperation::Delete { at: Smb }, 0),
+        ];
+
+        let estimate = estimate(&plan, &timings);
+        assert_eq!(estimate.estimated_secs, Some(35));
+        assert_eq!(estimate.unmeasured_bytes, 7);
+        assert_eq!((estimate.files_to_copy, estimate.files_to_delete, estimate.conflicts), (3, 1, 0));
+
+        let drive = estimate.locations.iter().find(|v| v.location == GoogleDrive).unwrap();
+        assert_eq!((drive.upload_bytes, drive.download_bytes), (30_000_000, 7));
+        let smb = estimate.locations.iter().find(|v| v.location == Smb).unwrap();
+        assert_eq!((smb.upload_files, smb.download_bytes), (1, 50_000_000));
+
+        assert_eq!(super::estimate(&plan, &[]).estimated_secs, None);
+    }
+}
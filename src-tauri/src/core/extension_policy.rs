@@ -0,0 +1,168 @@
+use crate::models::file_state::FileLocation;
+use crate::utils::error::{Result, UvcadError};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Suffix appended to files stored compressed on remote locations.
+pub const COMPRESSED_SUFFIX: &str = "gz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionBehavior {
+    /// Never planned for sync at all (e.g. `.bak`).
+    NeverSync,
+    /// Kept on the local disk only; never pushed to remote locations.
+    LocalOnly,
+    /// Gzip-compressed on remote locations, stored as `<name>.gz`.
+    Compress,
+    /// Cache a preview image after sync.
+    Thumbnail,
+}
+
+impl ExtensionBehavior {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "never-sync" => Some(ExtensionBehavior::NeverSync),
+            "local-only" => Some(ExtensionBehavior::LocalOnly),
+            "compress" => Some(ExtensionBehavior::Compress),
+            "thumbnail" => Some(ExtensionBehavior::Thumbnail),
+            _ => None,
+        }
+    }
+}
+
+/// Behaviors keyed by lower-case file extension.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionPolicies {
+    rules: HashMap<String, Vec<ExtensionBehavior>>,
+}
+
+impl ExtensionPolicies {
+    /// Parse policies written one per line as `ext = behavior[, behavior]`, e.g.
+    /// `.stl = compress, thumbnail`. Blank lines and lines starting with `#` are ignored.
+    pub fn from_config(config: Option<&str>) -> Result<Self> {
+        let mut rules: HashMap<String, Vec<ExtensionBehavior>> = HashMap::new();
+
+        for line in config.unwrap_or("").lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || UvcadError::InvalidConfig(format!("Invalid extension policy: {}", line));
+
+            let (extension, behaviors) = line.split_once('=').ok_or_else(invalid)?;
+            let extension = extension.trim().trim_start_matches('.').to_lowercase();
+            if extension.is_empty() {
+                return Err(invalid());
+            }
+            for behavior in behaviors.split(',') {
+                let behavior = ExtensionBehavior::parse(behavior).ok_or_else(invalid)?;
+                let entry = rules.entry(extension.clone()).or_default();
+                if !entry.contains(&behavior) {
+                    entry.push(behavior);
+                }
+            }
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn applies(&self, path: &Path, behavior: ExtensionBehavior) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| self.rules.get(&e.to_lowercase()))
+            .is_some_and(|behaviors| behaviors.contains(&behavior))
+    }
+
+    /// Whether the copy at `location` is stored compressed.
+    pub fn is_compressed_at(&self, path: &Path, location: &FileLocation) -> bool {
+        *location != FileLocation::Local && self.applies(path, ExtensionBehavior::Compress)
+    }
+
+    /// Name a file is stored under at `location`: `part.stl` -> `part.stl.gz` on
+    /// remote locations when the extension is compressed.
+    pub fn stored_path(&self, path: &Path, location: &FileLocation) -> PathBuf {
+        if !self.is_compressed_at(path, location) {
+            return path.to_path_buf();
+        }
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(COMPRESSED_SUFFIX);
+        path.with_file_name(name)
+    }
+
+    /// Inverse of `stored_path`: the logical path of a file listed at `location`.
+    pub fn logical_path(&self, stored: &Path, location: &FileLocation) -> PathBuf {
+        let is_compressed = stored.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case(COMPRESSED_SUFFIX));
+        if is_compressed {
+            let inner = stored.with_extension("");
+            if self.is_compressed_at(&inner, location) {
+                return inner;
+            }
+        }
+        stored.to_path_buf()
+    }
+}
+
+pub fn compress_file(source: &Path, dest: &Path) -> Result<()> {
+    let mut input = std::fs::File::open(source)?;
+    let mut encoder = flate2::write::GzEncoder::new(std::fs::File::create(dest)?, flate2::Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+pub fn decompress_file(source: &Path, dest: &Path) -> Result<()> {
+    let mut decoder = flate2::read::GzDecoder::new(std::fs::File::open(source)?);
+    let mut output = std::fs::File::create(dest)?;
+    std::io::copy(&mut decoder, &mut output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply() {
+        let policies = ExtensionPolicies::from_config(Some(
+            "# housekeeping\n.bak = never-sync\nSTL = compress, thumbnail\nlog = local-only\n"
+        )).unwrap();
+
+        assert!(policies.applies(Path::new("a/part.BAK"), ExtensionBehavior::NeverSync));
+        assert!(policies.applies(Path::new("mesh.stl"), ExtensionBehavior::Compress));
+        assert!(policies.applies(Path::new("mesh.stl"), ExtensionBehavior::Thumbnail));
+        assert!(policies.applies(Path::new("run.log"), ExtensionBehavior::LocalOnly));
+        assert!(!policies.applies(Path::new("part.sldprt"), ExtensionBehavior::NeverSync));
+
+        assert!(ExtensionPolicies::from_config(Some("stl")).is_err());
+        assert!(ExtensionPolicies::from_config(Some("stl = shrink")).is_err());
+    }
+
+    #[test]
+    fn test_stored_path_round_trip() {
+        let policies = ExtensionPolicies::from_config(Some("stl = compress")).unwrap();
+        let path = Path::new("prints/mesh.stl");
+
+        assert_eq!(policies.stored_path(path, &FileLocation::Local), path);
+        let stored = policies.stored_path(path, &FileLocation::GoogleDrive);
+        assert_eq!(stored, Path::new("prints/mesh.stl.gz"));
+        assert_eq!(policies.logical_path(&stored, &FileLocation::GoogleDrive), path);
+
+        // Plain .gz files and local copies keep their names
+        assert_eq!(policies.logical_path(Path::new("data.csv.gz"), &FileLocation::Smb), Path::new("data.csv.gz"));
+        assert_eq!(policies.logical_path(&stored, &FileLocation::Local), stored);
+    }
+
+    #[test]
+    fn test_compression_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("mesh.stl");
+        std::fs::write(&source, "solid mesh\nendsolid mesh\n".repeat(100)).unwrap();
+
+        compress_file(&source, &dir.path().join("mesh.stl.gz")).unwrap();
+        decompress_file(&dir.path().join("mesh.stl.gz"), &dir.path().join("restored.stl")).unwrap();
+
+        assert_eq!(std::fs::read(&source).unwrap(), std::fs::read(dir.path().join("restored.stl")).unwrap());
+    }
+}
@@ -44,6 +44,12 @@ pub fn compute_file_md5(path: &Path) -> Result<String> {
     Ok(format!("{:x}", digest))
 }
 
+/// Compute MD5 hash of bytes (for Google Drive compatibility)
+#[cfg(test)]
+pub fn compute_bytes_md5(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
 /// Compute SHA-256 hash of bytes
 pub fn compute_bytes_hash(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -80,6 +86,14 @@ mod tests {
         assert_eq!(hash.len(), 64);
     }
 
+    #[test]
+    fn test_bytes_md5_matches_file_md5() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "test content").unwrap();
+
+        assert_eq!(compute_bytes_md5(b"test content"), compute_file_md5(temp_file.path()).unwrap());
+    }
+
     #[test]
     fn test_verify_file_hash() {
         let mut temp_file = NamedTempFile::new().unwrap();
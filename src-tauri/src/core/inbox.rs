@@ -0,0 +1,327 @@
+use crate::core::file_hasher;
+use crate::db::models::DbOperations;
+use crate::models::inbox_entry::{InboxEntry, InboxStatus};
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::command;
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::glob;
+use chrono::Utc;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Subfolder of the inbox that rejected deliveries are moved to, so they
+/// are not picked up again on the next pass.
+pub const REJECTED_FOLDER: &str = "_rejected";
+
+/// Files modified more recently than this are assumed to still be copying in.
+pub const SETTLE_TIME: Duration = Duration::from_secs(10);
+
+/// Sidecar extension suppliers can use to send the expected SHA-256 of a file.
+const CHECKSUM_EXTENSION: &str = "sha256";
+
+/// Routes a delivered file name to a project folder.
+///
+/// `pattern` is a case-insensitive glob (`*` and `?`); the text matched by each `*`
+/// can be used in `target` as `{1}`, `{2}`, ...
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingRule {
+    pub pattern: String,
+    pub target: String,
+}
+
+impl RoutingRule {
+    /// Parse rules written one per line as `pattern = target folder`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse_rules(text: &str) -> Result<Vec<RoutingRule>> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (pattern, target) = line.split_once('=').ok_or_else(|| {
+                    UvcadError::InvalidConfig(format!("Inbox rule is missing '=': {}", line))
+                })?;
+                Ok(RoutingRule {
+                    pattern: pattern.trim().to_string(),
+                    target: target.trim().trim_matches('/').to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// The target folder for `file_name`, if this rule matches it.
+    pub fn apply(&self, file_name: &str) -> Option<String> {
+        let captures = glob::captures(&self.pattern, file_name)?;
+
+        let mut target = self.target.clone();
+        for (i, text) in captures.iter().enumerate() {
+            target = target.replace(&format!("{{{}}}", i + 1), text);
+        }
+        Some(target)
+    }
+}
+
+/// Validation and routing settings for the supplier inbox, taken from the profile.
+#[derive(Debug, Clone, Default)]
+pub struct InboxPolicy {
+    allowed_extensions: Vec<String>,
+    scan_command: Option<String>,
+    rules: Vec<RoutingRule>,
+}
+
+impl InboxPolicy {
+    pub fn from_profile(profile: &SyncProfile) -> Result<Self> {
+        let allowed_extensions = profile.inbox_extensions.as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+
+        Ok(Self {
+            allowed_extensions,
+            scan_command: profile.inbox_scan_command.clone()
+                .filter(|c| !c.trim().is_empty()),
+            rules: RoutingRule::parse_rules(profile.inbox_rules.as_deref().unwrap_or(""))?,
+        })
+    }
+
+    fn extension_allowed(&self, file_name: &str) -> bool {
+        if self.allowed_extensions.is_empty() {
+            return true;
+        }
+        Path::new(file_name).extension()
+            .and_then(|e| e.to_str())
+            .map(|e| self.allowed_extensions.contains(&e.to_ascii_lowercase()))
+            .unwrap_or(false)
+    }
+
+    /// First matching rule wins.
+    fn route(&self, file_name: &str) -> Option<String> {
+        self.rules.iter().find_map(|rule| rule.apply(file_name))
+    }
+
+    /// Run the configured scanner. `{file}` in the command is replaced with the
+    /// file path; without a placeholder the path is appended as the last argument.
+    fn scan(&self, path: &Path) -> std::result::Result<(), String> {
+        let Some(template) = &self.scan_command else {
+            return Ok(());
+        };
+
+        let output = command::build(template, &[("file", &path.to_string_lossy())])
+            .map_err(|e| format!("Virus scan could not be run: {}", e))?
+            .output()
+            .map_err(|e| format!("Virus scan could not be run: {}", e))?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Err(format!("Virus scan flagged the file: {}", stdout.trim()))
+        }
+    }
+}
+
+/// Validate and route every settled file in `inbox_dir`, moving accepted files
+/// under `local_root` and rejected ones to the inbox's rejected folder.
+/// Every processed file is written to the inbox log.
+///
+/// Files modified within `settle_time` are left for a later pass.
+pub fn process_inbox(
+    conn: &Connection,
+    profile_id: i64,
+    policy: &InboxPolicy,
+    inbox_dir: &Path,
+    local_root: &Path,
+    settle_time: Duration,
+) -> Result<Vec<InboxEntry>> {
+    let mut entries = Vec::new();
+    let now = SystemTime::now();
+
+    for dir_entry in std::fs::read_dir(inbox_dir)? {
+        let path = dir_entry?.path();
+        if !path.is_file() || is_checksum_file(&path) {
+            continue;
+        }
+
+        let modified = path.metadata()?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() < settle_time {
+            continue;
+        }
+
+        let entry = process_file(conn, profile_id, policy, &path, inbox_dir, local_root)?;
+        DbOperations::insert_inbox_entry(conn, &entry)?;
+        entries.push(entry);
+    }
+
+    if !entries.is_empty() {
+        tracing::info!("Processed {} inbox deliveries", entries.len());
+    }
+    Ok(entries)
+}
+
+fn process_file(
+    conn: &Connection,
+    profile_id: i64,
+    policy: &InboxPolicy,
+    path: &Path,
+    inbox_dir: &Path,
+    local_root: &Path,
+) -> Result<InboxEntry> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+    let hash = file_hasher::compute_file_hash(path)?;
+    let size = path.metadata()?.len() as i64;
+
+    let mut entry = InboxEntry {
+        id: None,
+        profile_id,
+        file_name: file_name.clone(),
+        content_hash: Some(hash.clone()),
+        size_bytes: Some(size),
+        status: InboxStatus::Rejected,
+        message: None,
+        target_path: None,
+        received_at: Utc::now(),
+    };
+
+    let target = validate(conn, profile_id, policy, path, &file_name, &hash, size)
+        .and_then(|target| {
+            if local_root.join(&target).join(&file_name).exists() {
+                Err(format!("{} already exists in {}", file_name, target))
+            } else {
+                Ok(target)
+            }
+        });
+
+    let target = match target {
+        Ok(target) => target,
+        Err(reason) => {
+            tracing::warn!("Rejected inbox delivery {}: {}", file_name, reason);
+            entry.message = Some(reason);
+            // Timestamp prefix keeps repeated rejections of the same name apart
+            let rejected_dir = inbox_dir.join(REJECTED_FOLDER);
+            let stamp = entry.received_at.format("%Y%m%d-%H%M%S");
+            move_file(path, &rejected_dir.join(format!("{}_{}", stamp, file_name)))?;
+            move_checksum_file(path, &rejected_dir, &stamp.to_string())?;
+            return Ok(entry);
+        }
+    };
+
+    let relative = Path::new(&target).join(&file_name);
+    move_file(path, &local_root.join(&relative))?;
+    if let Some(checksum) = checksum_file(path).filter(|c| c.exists()) {
+        std::fs::remove_file(checksum)?;
+    }
+
+    tracing::info!("Accepted inbox delivery {} into {}", file_name, target);
+    entry.status = InboxStatus::Accepted;
+    entry.target_path = Some(relative.to_string_lossy().replace('\\', "/"));
+    Ok(entry)
+}
+
+/// Returns the target folder, or the reason the delivery is rejected.
+fn validate(
+    conn: &Connection,
+    profile_id: i64,
+    policy: &InboxPolicy,
+    path: &Path,
+    file_name: &str,
+    hash: &str,
+    size: i64,
+) -> std::result::Result<String, String> {
+    if size == 0 {
+        return Err("File is empty".to_string());
+    }
+    if !policy.extension_allowed(file_name) {
+        return Err("File extension is not on the inbox whitelist".to_string());
+    }
+
+    if let Some(checksum) = checksum_file(path).filter(|c| c.exists()) {
+        let expected = std::fs::read_to_string(&checksum).map_err(|e| e.to_string())?;
+        let expected = expected.split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+        if expected != hash {
+            return Err(format!("Checksum mismatch (expected {}, got {})", expected, hash));
+        }
+    }
+
+    match DbOperations::inbox_hash_accepted(conn, profile_id, hash) {
+        Ok(true) => return Err("Identical file was already delivered".to_string()),
+        Ok(false) => {}
+        Err(e) => return Err(e.to_string()),
+    }
+
+    policy.scan(path)?;
+
+    policy.route(file_name).ok_or_else(|| "No routing rule matches the file name".to_string())
+}
+
+fn is_checksum_file(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case(CHECKSUM_EXTENSION))
+        .unwrap_or(false)
+}
+
+fn checksum_file(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    Some(path.with_file_name(format!("{}.{}", name, CHECKSUM_EXTENSION)))
+}
+
+fn move_checksum_file(path: &Path, dest_dir: &Path, prefix: &str) -> Result<()> {
+    if let Some(checksum) = checksum_file(path).filter(|c| c.exists()) {
+        let name = checksum.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        move_file(&checksum, &dest_dir.join(format!("{}_{}", prefix, name)))?;
+    }
+    Ok(())
+}
+
+/// Move without overwriting; falls back to copy + delete across volumes.
+fn move_file(source: &Path, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        return Err(UvcadError::ProviderError(format!(
+            "Inbox target already exists: {}",
+            dest.display()
+        )));
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(source, dest).is_err() {
+        std::fs::copy(source, dest)?;
+        std::fs::remove_file(source)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_route_with_captures() {
+        let rules = RoutingRule::parse_rules(
+            "# supplier drops\nPRJ-*_*.step = Projects/{1}/Supplier/{2}\n*.pdf = Documents\n",
+        ).unwrap();
+        assert_eq!(rules.len(), 2);
+
+        let policy = InboxPolicy { rules, ..Default::default() };
+        assert_eq!(policy.route("prj-1042_housing.STEP"), Some("Projects/1042/Supplier/housing".to_string()));
+        assert_eq!(policy.route("drawing.pdf"), Some("Documents".to_string()));
+        assert_eq!(policy.route("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_rule_without_separator_is_rejected() {
+        assert!(RoutingRule::parse_rules("*.step Projects").is_err());
+    }
+
+    #[test]
+    fn test_extension_whitelist() {
+        let policy = InboxPolicy {
+            allowed_extensions: vec!["step".to_string(), "pdf".to_string()],
+            ..Default::default()
+        };
+        assert!(policy.extension_allowed("part.STEP"));
+        assert!(!policy.extension_allowed("setup.exe"));
+        assert!(InboxPolicy::default().extension_allowed("anything.bin"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::db::models::DbOperations;
+use crate::models::legal_hold::LegalHold;
+use crate::utils::error::{Result, UvcadError};
+use rusqlite::Connection;
+use std::path::{Component, Path, PathBuf};
+
+/// The active legal holds of a profile. Held files are never deleted or
+/// overwritten at any location, whatever the other policies say; copies to
+/// locations that don't have them yet are still made.
+#[derive(Debug, Clone, Default)]
+pub struct LegalHolds {
+    holds: Vec<(PathBuf, LegalHold)>,
+}
+
+impl LegalHolds {
+    /// Keeps the active ones of `holds`.
+    pub fn new(holds: Vec<LegalHold>) -> Self {
+        Self {
+            holds: holds.into_iter()
+                .filter(LegalHold::is_active)
+                .map(|hold| (PathBuf::from(&hold.path), hold))
+                .collect(),
+        }
+    }
+
+    pub fn load(conn: &Connection, profile_id: i64) -> Result<Self> {
+        Ok(Self::new(DbOperations::get_legal_holds(conn, profile_id)?))
+    }
+
+    /// The hold on `path` or on a folder above it.
+    pub fn covering(&self, path: &Path) -> Option<&LegalHold> {
+        self.holds.iter()
+            .find(|(held, _)| path.starts_with(held))
+            .map(|(_, hold)| hold)
+    }
+}
+
+/// `path` as stored on a hold: relative to the profile's root, with forward
+/// slashes and without leading or trailing ones.
+pub fn normalize_path(path: &str) -> Result<String> {
+    let normalized = path.trim().replace('\\', "/").trim_matches('/').to_string();
+    if normalized.is_empty() || !Path::new(&normalized).components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(UvcadError::InvalidConfig(format!("Invalid path for a legal hold: {}", path)));
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn hold(path: &str) -> LegalHold {
+        LegalHold {
+            id: None,
+            profile_id: 1,
+            path: path.to_string(),
+            reason: "Dispute with customer".to_string(),
+            placed_by: "anna".to_string(),
+            placed_at: Utc::now(),
+            released_by: None,
+            released_at: None,
+            release_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_holds_cover_files_below_held_folders() {
+        let released = LegalHold { released_at: Some(Utc::now()), ..hold("Released") };
+        let holds = LegalHolds::new(vec![hold("ProjectX"), hold("shared/frame.dwg"), released]);
+
+        assert!(holds.covering(Path::new("ProjectX/asm/frame.sldasm")).is_some());
+        assert!(holds.covering(Path::new("shared/frame.dwg")).is_some());
+        assert!(holds.covering(Path::new("ProjectXY/part.sldprt")).is_none());
+        assert!(holds.covering(Path::new("Released/part.sldprt")).is_none());
+
+        assert_eq!(normalize_path("\\ProjectX\\asm\\").unwrap(), "ProjectX/asm");
+        assert!(normalize_path("/").is_err());
+        assert!(normalize_path("../other").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::core::sync_policy::SyncPolicy;
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Environment variable naming the managed policy file or https URL. Wins over the
+/// profile setting, so deployment tooling can point every computer at it.
+pub const SOURCE_ENV_VAR: &str = "UVCAD_MANAGED_POLICY";
+
+/// The copy of the last policy fetched, used while the source can't be reached.
+const KEPT_COPY: &str = "managed-policy.json";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A policy file the company keeps on the share or a web server, applied to
+/// every computer:
+///
+/// ```json
+/// {
+///   "description": "Engineering, rev 4",
+///   "policy": { "exclude_patterns": "*.bak, ~$*", "max_deletion_count": 50 },
+///   "profiles": [{ "name": "Projects", "local_path": "C:\\CAD\\Projects", "smb_share_path": "\\\\nas\\eng" }],
+///   "allowed_locations": ["smb"]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ManagedPolicy {
+    pub description: Option<String>,
+    /// `SyncPolicy` settings every profile gets; settings left out stay local.
+    pub policy: Map<String, Value>,
+    /// Profiles created on every computer without one of that name.
+    pub profiles: Vec<ManagedProfile>,
+    /// The remote locations profiles may sync with (`gdrive`, `smb`); None
+    /// allows both. The local folder is always allowed.
+    pub allowed_locations: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManagedProfile {
+    pub name: String,
+    /// Skipped on computers where this folder does not exist.
+    pub local_path: String,
+    #[serde(default)]
+    pub gdrive_folder_id: Option<String>,
+    #[serde(default)]
+    pub smb_share_path: Option<String>,
+}
+
+impl ManagedPolicy {
+    /// Parse and check a policy file.
+    pub fn parse(json: &str) -> Result<Self> {
+        let managed: Self = serde_json::from_str(json)
+            .map_err(|e| UvcadError::InvalidConfig(format!("Invalid managed policy: {}", e)))?;
+
+        let known = serde_json::to_value(SyncPolicy::default())?;
+        if let Some(unknown) = managed.policy.keys().find(|key| known.get(key.as_str()).is_none()) {
+            return Err(UvcadError::InvalidConfig(format!("Unknown setting in the managed policy: {}", unknown)));
+        }
+        managed.apply(&SyncPolicy::default())?.compile()?;
+
+        for location in managed.allowed_locations.iter().flatten() {
+            if FileLocation::from_str_opt(location).is_none_or(|l| l == FileLocation::Local) {
+                return Err(UvcadError::InvalidConfig(format!("Unknown location in the managed policy: {}", location)));
+            }
+        }
+        for profile in &managed.profiles {
+            if profile.name.trim().is_empty() || profile.local_path.trim().is_empty() {
+                return Err(UvcadError::InvalidConfig("Managed profiles need a name and a local path".to_string()));
+            }
+            managed.check_locations(&profile.gdrive_folder_id, &profile.smb_share_path)?;
+        }
+        Ok(managed)
+    }
+
+    /// The settings the policy decides, sorted.
+    pub fn managed_settings(&self) -> Vec<String> {
+        let mut settings: Vec<String> = self.policy.keys().cloned().collect();
+        settings.sort();
+        settings
+    }
+
+    /// `local` with the managed settings put in.
+    pub fn apply(&self, local: &SyncPolicy) -> Result<SyncPolicy> {
+        let mut merged = serde_json::to_value(local)?;
+        for (key, value) in &self.policy {
+            merged[key] = value.clone();
+        }
+        serde_json::from_value(merged)
+            .map_err(|e| UvcadError::InvalidConfig(format!("Invalid managed policy: {}", e)))
+    }
+
+    /// The managed settings `proposed` sets differently, sorted.
+    pub fn deviations(&self, proposed: &SyncPolicy) -> Result<Vec<String>> {
+        let proposed = serde_json::to_value(proposed)?;
+        let managed = serde_json::to_value(self.apply(&SyncPolicy::default())?)?;
+        Ok(self.managed_settings().into_iter().filter(|key| proposed[key] != managed[key]).collect())
+    }
+
+    pub fn allows(&self, location: &FileLocation) -> bool {
+        *location == FileLocation::Local
+            || self.allowed_locations.as_ref().is_none_or(|allowed| allowed.iter().any(|l| l == location.as_str()))
+    }
+
+    /// Fail when a profile with these remote locations would sync somewhere
+    /// the policy does not allow.
+    pub fn check_locations(&self, gdrive_folder_id: &Option<String>, smb_share_path: &Option<String>) -> Result<()> {
+        let configured = [
+            (gdrive_folder_id, FileLocation::GoogleDrive, "Google Drive"),
+            (smb_share_path, FileLocation::Smb, "the network share"),
+        ];
+        for (setting, location, name) in configured {
+            if setting.as_deref().is_some_and(|s| !s.trim().is_empty()) && !self.allows(&location) {
+                return Err(UvcadError::PermissionDenied(format!("the managed policy does not allow syncing with {}", name)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check_profile(&self, profile: &SyncProfile) -> Result<()> {
+        self.check_locations(&profile.gdrive_folder_id, &profile.smb_share_path)
+    }
+}
+
+/// A managed policy as loaded on this computer.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedPolicy {
+    /// The file or URL it came from.
+    pub source: String,
+    pub policy: ManagedPolicy,
+    pub fetched_at: DateTime<Utc>,
+    /// Why the source could not be read, when the copy kept from the last time
+    /// it could is in use instead.
+    pub stale: Option<String>,
+}
+
+static CURRENT: Lazy<RwLock<Option<LoadedPolicy>>> = Lazy::new(|| RwLock::new(None));
+
+/// The managed policy in force; None when this computer has none.
+pub fn current() -> Option<LoadedPolicy> {
+    CURRENT.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn set_current(loaded: Option<LoadedPolicy>) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = loaded;
+}
+
+/// Where to load the managed policy from: the environment override, else the
+/// profile setting; None when neither is set.
+pub fn source(configured: Option<&str>) -> Option<String> {
+    std::env::var(SOURCE_ENV_VAR).ok()
+        .or_else(|| configured.map(str::to_string))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Fail for a plain http URL: anyone on the way could hand out their own
+/// policy, e.g. one allowing other locations or no deletion limit.
+fn check_transport(source: &str) -> Result<()> {
+    if has_scheme(source, "http://") {
+        return Err(UvcadError::InvalidConfig(format!(
+            "The managed policy must come from a file or an https URL, not over plain http: {}", source
+        )));
+    }
+    Ok(())
+}
+
+fn has_scheme(source: &str, scheme: &str) -> bool {
+    source.get(..scheme.len()).is_some_and(|s| s.eq_ignore_ascii_case(scheme))
+}
+
+/// Read the policy file at `source`, a path (e.g. on the share) or an https URL.
+pub async fn fetch(source: &str) -> Result<ManagedPolicy> {
+    check_transport(source)?;
+    let json = if has_scheme(source, "https://") {
+        let response = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            // Nor may a redirect take it to plain http
+            .redirect(reqwest::redirect::Policy::custom(|attempt| match attempt.url().scheme() {
+                "https" if attempt.previous().len() < 10 => attempt.follow(),
+                _ => attempt.stop(),
+            }))
+            .build()?
+            .get(source)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(UvcadError::InvalidConfig(format!(
+                "Failed to fetch the managed policy from {}: {}", source, response.status()
+            )));
+        }
+        response.text().await?
+    } else {
+        tokio::fs::read_to_string(source).await?
+    };
+    ManagedPolicy::parse(&json)
+}
+
+/// Fetch the policy, keeping a copy in the app's data folder to fall back on
+/// while the source can't be reached.
+pub async fn load(source: &str) -> Result<LoadedPolicy> {
+    let project_dirs = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| UvcadError::InvalidConfig("Failed to get project directory".to_string()))?;
+    load_in(source, project_dirs.data_dir()).await
+}
+
+/// Like `load`, keeping the copy in `dir`.
+pub async fn load_in(source: &str, dir: &Path) -> Result<LoadedPolicy> {
+    // Nor is a copy kept from such a source used
+    check_transport(source)?;
+    let kept_copy = dir.join(KEPT_COPY);
+    match fetch(source).await {
+        Ok(policy) => {
+            let loaded = LoadedPolicy { source: source.to_string(), policy, fetched_at: Utc::now(), stale: None };
+            if let Err(e) = keep(&loaded, &kept_copy).await {
+                tracing::warn!("Failed to keep a copy of the managed policy: {}", e);
+            }
+            Ok(loaded)
+        }
+        Err(e) => {
+            let kept = tokio::fs::read_to_string(&kept_copy).await.ok()
+                .and_then(|json| serde_json::from_str::<KeptPolicy>(&json).ok())
+                .filter(|kept| kept.source == source);
+            let Some(kept) = kept else {
+                return Err(e);
+            };
+            tracing::warn!("Using the managed policy fetched at {}: {}", kept.fetched_at, e);
+            Ok(LoadedPolicy { source: kept.source, policy: kept.policy, fetched_at: kept.fetched_at, stale: Some(e.to_string()) })
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeptPolicy {
+    source: String,
+    policy: ManagedPolicy,
+    fetched_at: DateTime<Utc>,
+}
+
+async fn keep(loaded: &LoadedPolicy, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let kept = KeptPolicy { source: loaded.source.clone(), policy: loaded.policy.clone(), fetched_at: loaded.fetched_at };
+    tokio::fs::write(path, serde_json::to_string_pretty(&kept)?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"{
+        "description": "Engineering",
+        "policy": { "exclude_patterns": "*.bak", "max_deletion_count": 50 },
+        "profiles": [{ "name": "Projects", "local_path": "/cad/projects", "smb_share_path": "//nas/eng" }],
+        "allowed_locations": ["smb"]
+    }"#;
+
+    #[test]
+    fn test_managed_settings_override_local_ones() {
+        let managed = ManagedPolicy::parse(POLICY).unwrap();
+        assert_eq!(managed.managed_settings(), vec!["exclude_patterns", "max_deletion_count"]);
+
+        let local = SyncPolicy {
+            include_patterns: Some("*.dwg".to_string()),
+            max_deletion_count: Some(5),
+            ..Default::default()
+        };
+        let applied = managed.apply(&local).unwrap();
+        assert_eq!(applied.include_patterns.as_deref(), Some("*.dwg"));
+        assert_eq!(applied.exclude_patterns.as_deref(), Some("*.bak"));
+        assert_eq!(applied.max_deletion_count, Some(50));
+        assert!(managed.deviations(&applied).unwrap().is_empty());
+        assert_eq!(managed.deviations(&local).unwrap(), vec!["exclude_patterns", "max_deletion_count"]);
+
+        assert!(managed.allows(&FileLocation::Smb) && !managed.allows(&FileLocation::GoogleDrive));
+        assert!(managed.check_locations(&Some("folder-id".to_string()), &None).is_err());
+        assert!(managed.check_locations(&Some(" ".to_string()), &Some("//nas/eng".to_string())).is_ok());
+
+        assert!(ManagedPolicy::parse(r#"{"policy": {"max_deletions": 5}}"#).is_err());
+        assert!(ManagedPolicy::parse(r#"{"policy": {"max_deletion_percentage": 150}}"#).is_err());
+        assert!(ManagedPolicy::parse(r#"{"allowed_locations": ["ftp"]}"#).is_err());
+
+        assert!(check_transport("HTTP://intranet/uvcad-policy.json").is_err());
+        assert!(check_transport("https://intranet/uvcad-policy.json").is_ok());
+        assert!(check_transport(r"\\nas\eng\uvcad-policy.json").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_kept_copy_is_used_while_the_source_is_unreachable() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("share").join("uvcad-policy.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, POLICY).unwrap();
+        let source = source.to_string_lossy().to_string();
+
+        let loaded = load_in(&source, dir.path()).await.unwrap();
+        assert!(loaded.stale.is_none());
+
+        std::fs::remove_file(&source).unwrap();
+        let kept = load_in(&source, dir.path()).await.unwrap();
+        assert!(kept.stale.is_some());
+        assert_eq!(kept.policy, loaded.policy);
+        // A copy kept from another source is not used
+        assert!(load_in("/elsewhere/uvcad-policy.json", dir.path()).await.is_err());
+    }
+}
@@ -0,0 +1,499 @@
+use crate::core::extension_policy::{ExtensionBehavior, ExtensionPolicies};
+use crate::core::file_hasher;
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use crate::providers::traits::{FileMetadata, StorageProvider};
+use crate::utils::crypto;
+use crate::utils::error::{Result, UvcadError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const EXTENSION: &str = "uvcadmedia";
+/// Profile holding what the other site is known to have: its `Smb` states
+/// stand for the media, its `Local` states for this computer at the last exchange.
+pub const MEDIA_PROFILE_NAME: &str = "Removable media";
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+const FORMAT_VERSION: u32 = 1;
+const HEADER_MEMBER: &str = "uvcadmedia.json";
+const MANIFEST_MEMBER: &str = "manifest";
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// Keeps tests fast; the count is stored in each archive.
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+
+/// Unencrypted, so the key can be derived before anything is decrypted.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    salt: String,
+    iterations: u32,
+}
+
+/// A file at the exporting site when the archive was written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaEntry {
+    pub path: String,
+    pub hash: Option<String>,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    /// Archive member with the content; None when the other site already has it.
+    pub member: Option<String>,
+}
+
+/// Every file of the exporting site, so files missing from it are deletions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaManifest {
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<MediaEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaExportSummary {
+    pub archive_path: String,
+    pub files_listed: usize,
+    /// New or changed since the last exchange, so their content is in the archive.
+    pub files_included: usize,
+    pub files_deleted: usize,
+    pub bytes_included: u64,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<[u8; 32]> {
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| UvcadError::ArchiveError("Invalid key derivation settings".to_string()))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+    Ok(key)
+}
+
+fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(UvcadError::InvalidConfig(format!(
+            "The passphrase must have at least {} characters", MIN_PASSPHRASE_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Writes an archive: a zip of separately AES-GCM encrypted members under
+/// opaque names, so neither contents nor paths can be read without the passphrase.
+struct MediaWriter {
+    zip: ZipWriter<File>,
+    key: [u8; 32],
+    members: usize,
+}
+
+impl MediaWriter {
+    fn create(output: &Path, passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        SystemRandom::new().fill(&mut salt)
+            .map_err(|_| UvcadError::ArchiveError("Random generation failed".to_string()))?;
+        let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+
+        let mut zip = ZipWriter::new(File::create(output)?);
+        let header = Header { version: FORMAT_VERSION, salt: hex::encode(salt), iterations: PBKDF2_ITERATIONS };
+        zip.start_file(HEADER_MEMBER, Self::options()).map_err(zip_error)?;
+        zip.write_all(&serde_json::to_vec(&header)?)?;
+        Ok(Self { zip, key, members: 0 })
+    }
+
+    /// Encrypted data doesn't compress, so members are stored.
+    fn options() -> FileOptions {
+        FileOptions::default().compression_method(CompressionMethod::Stored).large_file(true)
+    }
+
+    fn add(&mut self, content: &[u8]) -> Result<String> {
+        self.members += 1;
+        let name = format!("files/{:06}", self.members);
+        self.write_member(&name, content)?;
+        Ok(name)
+    }
+
+    fn write_member(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        self.zip.start_file(name, Self::options()).map_err(zip_error)?;
+        self.zip.write_all(&crypto::encrypt(content, &self.key)?)?;
+        Ok(())
+    }
+
+    fn finish(mut self, manifest: &MediaManifest) -> Result<()> {
+        self.write_member(MANIFEST_MEMBER, &serde_json::to_vec(manifest)?)?;
+        self.zip.finish().map_err(zip_error)?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// An archive opened with its passphrase.
+pub struct MediaArchive {
+    zip: Mutex<ZipArchive<File>>,
+    key: [u8; 32],
+    pub manifest: MediaManifest,
+}
+
+impl MediaArchive {
+    pub fn open(path: &Path, passphrase: &str) -> Result<Self> {
+        let mut zip = ZipArchive::new(File::open(path)?)
+            .map_err(|e| UvcadError::ArchiveError(format!("Not a UVCAD media archive: {}", e)))?;
+        let header: Header = {
+            let mut member = zip.by_name(HEADER_MEMBER)
+                .map_err(|_| UvcadError::ArchiveError("Not a UVCAD media archive".to_string()))?;
+            let mut json = Vec::new();
+            member.read_to_end(&mut json)?;
+            serde_json::from_slice(&json)?
+        };
+        if header.version != FORMAT_VERSION {
+            return Err(UvcadError::ArchiveError(format!("Unsupported media archive version {}", header.version)));
+        }
+        let salt = hex::decode(&header.salt)
+            .map_err(|_| UvcadError::ArchiveError("Damaged media archive header".to_string()))?;
+        let key = derive_key(passphrase, &salt, header.iterations)?;
+
+        let archive = Self {
+            zip: Mutex::new(zip),
+            key,
+            manifest: MediaManifest { source: String::new(), created_at: Utc::now(), files: Vec::new() },
+        };
+        let manifest = archive.read_member(MANIFEST_MEMBER)
+            .map_err(|_| UvcadError::ArchiveError("Wrong passphrase or damaged archive".to_string()))?;
+        Ok(Self { manifest: serde_json::from_slice(&manifest)?, ..archive })
+    }
+
+    pub fn read_member(&self, name: &str) -> Result<Vec<u8>> {
+        let mut zip = self.zip.lock().unwrap();
+        let mut member = zip.by_name(name)
+            .map_err(|_| UvcadError::ArchiveError(format!("Archive member {} is missing", name)))?;
+        let mut encrypted = Vec::with_capacity(member.size() as usize);
+        member.read_to_end(&mut encrypted)?;
+        crypto::decrypt(&encrypted, &self.key)
+            .map_err(|_| UvcadError::ArchiveError(format!("Archive member {} is damaged", name)))
+    }
+}
+
+fn zip_error(e: zip::result::ZipError) -> UvcadError {
+    UvcadError::ArchiveError(e.to_string())
+}
+
+/// Write every file at `local` to an encrypted archive at `output`, with the
+/// content of those that differ from `baseline` (path -> hash the other site
+/// has) or of all files with `full`. Local-only files are left out.
+pub async fn export(
+    local: &dyn StorageProvider,
+    baseline: &HashMap<String, Option<String>>,
+    policies: &ExtensionPolicies,
+    source: &str,
+    output: &Path,
+    passphrase: &str,
+    full: bool,
+) -> Result<(MediaManifest, MediaExportSummary)> {
+    check_passphrase(passphrase)?;
+    let mut listed: Vec<FileMetadata> = local.list_files(Path::new("")).await?
+        .into_iter()
+        .filter(|f| !policies.applies(&f.path, ExtensionBehavior::LocalOnly))
+        .collect();
+    listed.sort_by(|a, b| a.path.cmp(&b.path));
+
+    // Written next to the target and renamed at the end, so a pulled stick never holds half an archive
+    let partial = output.with_extension(format!("{}.partial", EXTENSION));
+    let mut writer = MediaWriter::create(&partial, passphrase)?;
+    let staging = tempfile_path(output);
+    let mut files = Vec::with_capacity(listed.len());
+    let mut summary = MediaExportSummary {
+        archive_path: output.to_string_lossy().to_string(),
+        files_listed: listed.len(),
+        files_included: 0,
+        files_deleted: 0,
+        bytes_included: 0,
+    };
+
+    let written: Result<()> = async {
+        for file in &listed {
+            let path = file.path.to_string_lossy().replace('\\', "/");
+            let unchanged = baseline.get(&path).is_some_and(|hash| hash.is_some() && *hash == file.hash);
+            let member = if full || !unchanged {
+                local.download(&file.path, &staging).await?;
+                let content = std::fs::read(&staging)?;
+                summary.files_included += 1;
+                summary.bytes_included += content.len() as u64;
+                Some(writer.add(&content)?)
+            } else {
+                None
+            };
+            files.push(MediaEntry { path, hash: file.hash.clone(), size: file.size, modified: file.modified, member });
+        }
+        Ok(())
+    }.await;
+    let _ = std::fs::remove_file(&staging);
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    let manifest = MediaManifest { source: source.to_string(), created_at: Utc::now(), files };
+    summary.files_deleted = baseline.keys()
+        .filter(|path| !manifest.files.iter().any(|f| &f.path == *path))
+        .count();
+    writer.finish(&manifest)?;
+    std::fs::rename(&partial, output)?;
+    Ok((manifest, summary))
+}
+
+fn tempfile_path(output: &Path) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "uvcad_media_{}_{}",
+        std::process::id(),
+        output.file_stem().unwrap_or_default().to_string_lossy()
+    ))
+}
+
+/// The hashes the other site has, from the media profile's `Smb` states.
+pub fn baseline(conn: &Connection, media_profile_id: i64) -> Result<HashMap<String, Option<String>>> {
+    Ok(DbOperations::get_file_states(conn, media_profile_id)?
+        .into_iter()
+        .filter(|s| s.location == FileLocation::Smb)
+        .map(|s| (s.file_path, s.content_hash))
+        .collect())
+}
+
+/// Make the media profile's states at `locations` match `manifest`, the files
+/// both sites have after an exchange.
+pub fn record_exchanged(
+    conn: &Connection,
+    media_profile_id: i64,
+    manifest: &MediaManifest,
+    locations: &[FileLocation],
+) -> Result<()> {
+    let existing = DbOperations::get_file_states(conn, media_profile_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let removed: Vec<(String, FileLocation)> = existing.into_iter()
+        .filter(|s| locations.contains(&s.location))
+        .map(|s| (s.file_path, s.location))
+        .collect();
+    DbOperations::delete_file_states(&tx, media_profile_id, &removed)?;
+
+    let now = Utc::now();
+    let states: Vec<FileState> = locations.iter()
+        .flat_map(|location| manifest.files.iter().map(move |entry| FileState {
+            id: None,
+            profile_id: media_profile_id,
+            file_path: entry.path.clone(),
+            location: location.clone(),
+            content_hash: entry.hash.clone(),
+            hash_fingerprint: None,
+            size_bytes: Some(entry.size as i64),
+            modified_at: Some(entry.modified),
+            synced_at: Some(now),
+            status: SyncStatus::Synced,
+            metadata: None,
+        }))
+        .collect();
+    DbOperations::upsert_file_states(&tx, &states)?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum Content {
+    Member(String),
+    /// Written during an import; kept only so the engine can read it back.
+    Written(Vec<u8>),
+    /// Unchanged at the exporting site, so not in the archive.
+    NotIncluded,
+}
+
+#[derive(Debug, Clone)]
+struct ArchiveFile {
+    metadata: FileMetadata,
+    content: Content,
+}
+
+/// An opened archive as a storage location, so an import is planned like any
+/// sync. Lists the exporting site's files; writes go to memory and are dropped.
+pub struct ArchiveProvider {
+    archive: Arc<MediaArchive>,
+    files: Mutex<HashMap<PathBuf, ArchiveFile>>,
+}
+
+impl ArchiveProvider {
+    pub fn new(archive: Arc<MediaArchive>) -> Self {
+        let files = archive.manifest.files.iter()
+            .map(|entry| {
+                let path = PathBuf::from(&entry.path);
+                let metadata = FileMetadata {
+                    path: path.clone(),
+                    size: entry.size,
+                    modified: entry.modified,
+                    hash: entry.hash.clone(),
+                    fingerprint: None,
+                    exists: true,
+                };
+                let content = entry.member.clone().map_or(Content::NotIncluded, Content::Member);
+                (path, ArchiveFile { metadata, content })
+            })
+            .collect();
+        Self { archive, files: Mutex::new(files) }
+    }
+}
+
+#[async_trait]
+impl StorageProvider for ArchiveProvider {
+    fn name(&self) -> &str {
+        "removable media"
+    }
+
+    async fn list_files(&self, path: &Path) -> Result<Vec<FileMetadata>> {
+        Ok(self.files.lock().unwrap().iter()
+            .filter(|(p, _)| p.starts_with(path))
+            .map(|(_, f)| f.metadata.clone())
+            .collect())
+    }
+
+    async fn get_metadata(&self, path: &Path) -> Result<Option<FileMetadata>> {
+        Ok(self.files.lock().unwrap().get(path).map(|f| f.metadata.clone()))
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        Ok(self.files.lock().unwrap().contains_key(path))
+    }
+
+    async fn download(&self, path: &Path, dest: &Path) -> Result<PathBuf> {
+        let content = self.files.lock().unwrap().get(path)
+            .map(|f| f.content.clone())
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.display().to_string() })?;
+        let bytes = match content {
+            Content::Member(member) => self.archive.read_member(&member)?,
+            Content::Written(bytes) => bytes,
+            Content::NotIncluded => {
+                return Err(UvcadError::ArchiveError(format!(
+                    "{} is not in this archive; export again with all files included", path.display()
+                )));
+            }
+        };
+        tokio::fs::write(dest, bytes).await?;
+        Ok(dest.to_path_buf())
+    }
+
+    async fn upload(&self, source: &Path, dest: &Path) -> Result<()> {
+        let bytes = tokio::fs::read(source).await?;
+        let metadata = FileMetadata {
+            path: dest.to_path_buf(),
+            size: bytes.len() as u64,
+            modified: Utc::now(),
+            hash: Some(file_hasher::compute_bytes_hash(&bytes)),
+            fingerprint: None,
+            exists: true,
+        };
+        self.files.lock().unwrap().insert(dest.to_path_buf(), ArchiveFile { metadata, content: Content::Written(bytes) });
+        Ok(())
+    }
+
+    async fn delete(&self, path: &Path) -> Result<()> {
+        self.files.lock().unwrap().remove(path)
+            .map(|_| ())
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.display().to_string() })
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let mut file = files.remove(from)
+            .ok_or_else(|| UvcadError::FileNotFound { path: from.display().to_string() })?;
+        file.metadata.path = to.to_path_buf();
+        files.insert(to.to_path_buf(), file);
+        Ok(())
+    }
+
+    async fn initialize(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn test_connection(&self) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sync_engine::SyncEngine;
+    use crate::db::{handle::DbHandle, schema::Database};
+    use crate::models::sync_profile::SyncProfile;
+    use crate::providers::mock::MockProvider;
+    use tokio::sync::Mutex as AsyncMutex;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    async fn media_profile(db: &DbHandle) -> i64 {
+        db.call(|conn| DbOperations::create_sync_profile(conn, &SyncProfile::new(MEDIA_PROFILE_NAME.to_string(), String::new())))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exchange_carries_changes_and_deletions() {
+        let dir = tempfile::tempdir().unwrap();
+        let policies = ExtensionPolicies::default();
+
+        // Site A and site B start out with the same two files
+        let (site_a, site_b) = (MockProvider::new(), MockProvider::new());
+        for site in [&site_a, &site_b] {
+            site.put("bracket.step", "v1");
+            site.put("old.dwg", "old");
+        }
+        let database = || {
+            let database = Database::in_memory().unwrap();
+            database.initialize().unwrap();
+            DbHandle::new(database)
+        };
+        let (db_a, db_b) = (database(), database());
+        let (media_a, media_b) = (media_profile(&db_a).await, media_profile(&db_b).await);
+
+        // First trip: everything goes, the second only what changed since
+        let first = dir.path().join(format!("first.{}", EXTENSION));
+        let (manifest, summary) = export(&site_a, &HashMap::new(), &policies, "A", &first, PASSPHRASE, false).await.unwrap();
+        assert_eq!(summary.files_included, 2);
+        db_a.call(move |conn| record_exchanged(conn, media_a, &manifest, &[FileLocation::Local, FileLocation::Smb])).await.unwrap();
+
+        site_a.put("bracket.step", "v2");
+        site_a.remove(Path::new("old.dwg"));
+        site_a.put("new.sldprt", "new");
+        site_b.put("notes.txt", "from B");
+        let known = db_a.call(move |conn| baseline(conn, media_a)).await.unwrap();
+        let second = dir.path().join(format!("second.{}", EXTENSION));
+        let (_, summary) = export(&site_a, &known, &policies, "A", &second, PASSPHRASE, false).await.unwrap();
+        assert_eq!((summary.files_listed, summary.files_included, summary.files_deleted), (2, 2, 1));
+
+        assert!(MediaArchive::open(&second, "wrong passphrase").is_err());
+        let archive = Arc::new(MediaArchive::open(&first, PASSPHRASE).unwrap());
+        let import = |archive: Arc<MediaArchive>| {
+            let local: Arc<AsyncMutex<dyn StorageProvider>> = Arc::new(AsyncMutex::new(site_b.clone()));
+            let media: Arc<AsyncMutex<dyn StorageProvider>> = Arc::new(AsyncMutex::new(ArchiveProvider::new(archive)));
+            SyncEngine::new(media_b, local, None, Some(media), db_b.clone())
+        };
+        import(archive.clone()).start_sync().await.unwrap();
+        db_b.call(move |conn| record_exchanged(conn, media_b, &archive.manifest, &[FileLocation::Smb])).await.unwrap();
+
+        let archive = Arc::new(MediaArchive::open(&second, PASSPHRASE).unwrap());
+        let result = import(archive.clone()).start_sync().await.unwrap();
+        db_b.call(move |conn| record_exchanged(conn, media_b, &archive.manifest, &[FileLocation::Smb])).await.unwrap();
+        assert!(result.conflicts.is_empty());
+        assert_eq!(site_b.content(Path::new("bracket.step")), Some(b"v2".to_vec()));
+        assert_eq!(site_b.content(Path::new("new.sldprt")), Some(b"new".to_vec()));
+        assert_eq!(site_b.content(Path::new("old.dwg")), None);
+
+        // B's own change is still pending for its next export
+        let known = db_b.call(move |conn| baseline(conn, media_b)).await.unwrap();
+        assert!(!known.contains_key("notes.txt"));
+    }
+}
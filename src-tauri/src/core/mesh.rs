@@ -0,0 +1,359 @@
+use crate::utils::error::{Result, UvcadError};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Edge length of rendered mesh thumbnails, in pixels.
+pub const THUMBNAIL_SIZE: usize = 128;
+
+/// Extensions of the mesh formats we can read.
+pub const MESH_EXTENSIONS: &[&str] = &["stl", "obj", "3mf"];
+
+type Vertex = [f32; 3];
+type Triangle = [Vertex; 3];
+
+/// Axis-aligned bounds in the file's own units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+/// Summary of a mesh file shown next to its thumbnail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshInfo {
+    pub triangle_count: usize,
+    /// `None` for a mesh without triangles.
+    pub bounding_box: Option<BoundingBox>,
+}
+
+pub struct Mesh {
+    triangles: Vec<Triangle>,
+}
+
+impl Mesh {
+    /// Read an STL (binary or ASCII), OBJ or 3MF file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let extension = path.extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        let malformed = |reason: &str| UvcadError::MalformedFile(format!("{}: {}", path.display(), reason));
+
+        let triangles = match extension.as_str() {
+            "stl" => parse_stl(&std::fs::read(path)?).ok_or_else(|| malformed("invalid STL"))?,
+            "obj" => parse_obj(&std::fs::read_to_string(path)?).ok_or_else(|| malformed("invalid OBJ"))?,
+            "3mf" => parse_3mf(path)?.ok_or_else(|| malformed("invalid 3MF model"))?,
+            _ => return Err(malformed("not a mesh format")),
+        };
+        Ok(Self { triangles })
+    }
+
+    pub fn info(&self) -> MeshInfo {
+        let bounding_box = self.triangles.iter().flatten().fold(None, |bounds: Option<BoundingBox>, v| {
+            let mut b = bounds.unwrap_or(BoundingBox { min: *v, max: *v });
+            for (axis, &value) in v.iter().enumerate() {
+                b.min[axis] = b.min[axis].min(value);
+                b.max[axis] = b.max[axis].max(value);
+            }
+            Some(b)
+        });
+
+        MeshInfo {
+            triangle_count: self.triangles.len(),
+            bounding_box,
+        }
+    }
+
+    /// Flat-shaded isometric view as a 24-bit BMP of `THUMBNAIL_SIZE` pixels square.
+    pub fn render_thumbnail(&self) -> Vec<u8> {
+        let size = THUMBNAIL_SIZE;
+        let mut pixels = vec![[0xF0u8; 3]; size * size];
+        let mut depth = vec![f32::NEG_INFINITY; size * size];
+
+        // Rotate 45 degrees about Z, then tilt so the top faces are visible
+        let (yaw_sin, yaw_cos) = std::f32::consts::FRAC_PI_4.sin_cos();
+        let (tilt_sin, tilt_cos) = 35.264f32.to_radians().sin_cos();
+        let view = |v: &Vertex| -> Vertex {
+            let x = v[0] * yaw_cos - v[1] * yaw_sin;
+            let y = v[0] * yaw_sin + v[1] * yaw_cos;
+            [x, v[2] * tilt_cos - y * tilt_sin, v[2] * tilt_sin + y * tilt_cos]
+        };
+        let projected: Vec<Triangle> = self.triangles.iter()
+            .map(|t| [view(&t[0]), view(&t[1]), view(&t[2])])
+            .collect();
+
+        let Some((min, max)) = projected.iter().flatten().fold(None, |acc: Option<([f32; 2], [f32; 2])>, v| {
+            let (mut min, mut max) = acc.unwrap_or(([v[0], v[1]], [v[0], v[1]]));
+            min = [min[0].min(v[0]), min[1].min(v[1])];
+            max = [max[0].max(v[0]), max[1].max(v[1])];
+            Some((min, max))
+        }) else {
+            return encode_bmp(&pixels, size);
+        };
+
+        let margin = size as f32 * 0.05;
+        let extent = (max[0] - min[0]).max(max[1] - min[1]).max(f32::EPSILON);
+        let scale = (size as f32 - 2.0 * margin) / extent;
+        let offset = [
+            (size as f32 - (max[0] - min[0]) * scale) / 2.0,
+            (size as f32 - (max[1] - min[1]) * scale) / 2.0,
+        ];
+        let to_screen = |v: &Vertex| -> Vertex {
+            [(v[0] - min[0]) * scale + offset[0], size as f32 - ((v[1] - min[1]) * scale + offset[1]), v[2]]
+        };
+
+        for triangle in &projected {
+            // Light from the viewer, slightly above; back faces are lit the same
+            let normal = cross(sub(triangle[1], triangle[0]), sub(triangle[2], triangle[0]));
+            let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let light = (0.3 * normal[1] + 0.95 * normal[2]).abs() / length;
+            let shade = (60.0 + 180.0 * light.min(1.0)) as u8;
+            let color = [shade, (shade as f32 * 0.9) as u8, (shade as f32 * 0.75) as u8];
+
+            let [a, b, c] = [to_screen(&triangle[0]), to_screen(&triangle[1]), to_screen(&triangle[2])];
+            let area = edge(a, b, c);
+            if area.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let x_range = a[0].min(b[0]).min(c[0]).floor().max(0.0) as usize
+                ..=(a[0].max(b[0]).max(c[0]).ceil() as usize).min(size - 1);
+            let y_range = a[1].min(b[1]).min(c[1]).floor().max(0.0) as usize
+                ..=(a[1].max(b[1]).max(c[1]).ceil() as usize).min(size - 1);
+            for y in y_range {
+                for x in x_range.clone() {
+                    let p = [x as f32 + 0.5, y as f32 + 0.5, 0.0];
+                    let (w0, w1, w2) = (edge(b, c, p) / area, edge(c, a, p) / area, edge(a, b, p) / area);
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+                    let z = w0 * a[2] + w1 * b[2] + w2 * c[2];
+                    let index = y * size + x;
+                    if z > depth[index] {
+                        depth[index] = z;
+                        pixels[index] = color;
+                    }
+                }
+            }
+        }
+
+        encode_bmp(&pixels, size)
+    }
+}
+
+fn sub(a: Vertex, b: Vertex) -> Vertex {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: Vertex, b: Vertex) -> Vertex {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Twice the signed area of the screen-space triangle (a, b, p).
+fn edge(a: Vertex, b: Vertex, p: Vertex) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Binary STL when the size matches the triangle count in the header, ASCII otherwise.
+fn parse_stl(data: &[u8]) -> Option<Vec<Triangle>> {
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes(data[80..84].try_into().ok()?) as usize;
+        if count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(data.len()) {
+            let read_vertex = |offset: usize| -> Vertex {
+                let f = |i: usize| f32::from_le_bytes(data[offset + i * 4..offset + i * 4 + 4].try_into().unwrap());
+                [f(0), f(1), f(2)]
+            };
+            return Some((0..count)
+                .map(|i| {
+                    // Skip the 12-byte facet normal; it is recomputed for shading
+                    let record = 84 + i * 50 + 12;
+                    [read_vertex(record), read_vertex(record + 12), read_vertex(record + 24)]
+                })
+                .collect());
+        }
+    }
+
+    let text = std::str::from_utf8(data).ok()?;
+    if !text.trim_start().starts_with("solid") {
+        return None;
+    }
+    let vertices = text.lines()
+        .filter_map(|line| line.trim().strip_prefix("vertex"))
+        .map(parse_coordinates)
+        .collect::<Option<Vec<Vertex>>>()?;
+    Some(vertices.chunks_exact(3).map(|v| [v[0], v[1], v[2]]).collect())
+}
+
+fn parse_coordinates(text: &str) -> Option<Vertex> {
+    let mut values = text.split_whitespace().map(|v| v.parse::<f32>().ok());
+    Some([values.next()??, values.next()??, values.next()??])
+}
+
+/// Faces with more than three corners are split into a fan.
+fn parse_obj(text: &str) -> Option<Vec<Triangle>> {
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut triangles = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if let Some(coordinates) = line.strip_prefix("v ") {
+            vertices.push(parse_coordinates(coordinates)?);
+        } else if let Some(corners) = line.strip_prefix("f ") {
+            // Corners are `v`, `v/vt`, `v//vn` or `v/vt/vn`; negative indices count from the end
+            let corners = corners.split_whitespace()
+                .map(|corner| {
+                    let index: i64 = corner.split('/').next()?.parse().ok()?;
+                    let index = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+                    vertices.get(usize::try_from(index).ok()?).copied()
+                })
+                .collect::<Option<Vec<Vertex>>>()?;
+            for i in 1..corners.len().saturating_sub(1) {
+                triangles.push([corners[0], corners[i], corners[i + 1]]);
+            }
+        }
+    }
+
+    Some(triangles)
+}
+
+/// Meshes of every object in the package's model parts. Build transforms and
+/// component references are not applied.
+fn parse_3mf(path: &Path) -> Result<Option<Vec<Triangle>>> {
+    let archive_error = |e: zip::result::ZipError| UvcadError::ArchiveError(format!("{}: {}", path.display(), e));
+    let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(archive_error)?;
+
+    let mut triangles = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(archive_error)?;
+        if !entry.name().to_lowercase().ends_with(".model") {
+            continue;
+        }
+        let mut model = String::new();
+        entry.read_to_string(&mut model)?;
+        match parse_3mf_model(&model) {
+            Some(mut parsed) => triangles.append(&mut parsed),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(triangles))
+}
+
+static MODEL_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<(?:\w+:)?(vertex|triangle|mesh)\b[^>]*>").unwrap());
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).unwrap());
+
+fn parse_3mf_model(model: &str) -> Option<Vec<Triangle>> {
+    let mut triangles = Vec::new();
+    let mut vertices: Vec<Vertex> = Vec::new();
+
+    for tag in MODEL_TAG.captures_iter(model) {
+        let attribute = |name: &str| -> Option<&str> {
+            ATTRIBUTE.captures_iter(tag.get(0)?.as_str())
+                .find(|a| &a[1] == name)
+                .and_then(|a| a.get(2))
+                .map(|value| value.as_str())
+        };
+
+        match &tag[1] {
+            // Vertex indices are local to each mesh
+            "mesh" => vertices.clear(),
+            "vertex" => vertices.push([
+                attribute("x")?.parse().ok()?,
+                attribute("y")?.parse().ok()?,
+                attribute("z")?.parse().ok()?,
+            ]),
+            _ => {
+                let corner = |name: &str| -> Option<Vertex> {
+                    vertices.get(attribute(name)?.parse::<usize>().ok()?).copied()
+                };
+                triangles.push([corner("v1")?, corner("v2")?, corner("v3")?]);
+            }
+        }
+    }
+
+    Some(triangles)
+}
+
+/// Bottom-up 24-bit BMP; rows are padded to four bytes.
+fn encode_bmp(pixels: &[[u8; 3]], size: usize) -> Vec<u8> {
+    let row_size = (size * 3 + 3) & !3;
+    let image_size = row_size * size;
+    let file_size = 54 + image_size;
+
+    let mut bmp = Vec::with_capacity(file_size);
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(file_size as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&54u32.to_le_bytes());
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.extend_from_slice(&(size as i32).to_le_bytes());
+    bmp.extend_from_slice(&(size as i32).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&24u16.to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&(image_size as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 16]);
+
+    for row in pixels.chunks(size).rev() {
+        for [r, g, b] in row {
+            bmp.extend_from_slice(&[*b, *g, *r]);
+        }
+        bmp.resize(bmp.len() + row_size - size * 3, 0);
+    }
+    bmp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE_OBJ: &str = "# unit cube\n\
+        v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+        f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4/1 1/1 5//1 8/1/1\n";
+
+    #[test]
+    fn test_obj_info_and_thumbnail() {
+        let mesh = Mesh { triangles: parse_obj(CUBE_OBJ).unwrap() };
+        let info = mesh.info();
+        assert_eq!(info.triangle_count, 12);
+        assert_eq!(info.bounding_box, Some(BoundingBox { min: [0.0; 3], max: [1.0; 3] }));
+
+        let bmp = mesh.render_thumbnail();
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(bmp.len(), 54 + THUMBNAIL_SIZE * THUMBNAIL_SIZE * 3);
+        // The cube fills the centre of the image
+        let centre = 54 + (THUMBNAIL_SIZE / 2 * THUMBNAIL_SIZE + THUMBNAIL_SIZE / 2) * 3;
+        assert_ne!(&bmp[centre..centre + 3], &[0xF0; 3]);
+    }
+
+    #[test]
+    fn test_stl_binary_and_ascii() {
+        let mut binary = vec![0u8; 80];
+        binary.extend_from_slice(&1u32.to_le_bytes());
+        binary.extend_from_slice(&[0; 12]);
+        for value in [0.0f32, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 3.0, 0.0] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        binary.extend_from_slice(&[0; 2]);
+        assert_eq!(parse_stl(&binary).unwrap(), vec![[[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 3.0, 0.0]]]);
+
+        let ascii = "solid part\n facet normal 0 0 1\n  outer loop\n   vertex 0 0 0\n   vertex 2 0 0\n   vertex 0 3 0\n  endloop\n endfacet\nendsolid part\n";
+        assert_eq!(parse_stl(ascii.as_bytes()), parse_stl(&binary));
+    }
+
+    #[test]
+    fn test_3mf_model() {
+        let model = r#"<model unit="millimeter"><resources><object id="1"><mesh>
+            <vertices><vertex x="0" y="0" z="0"/><vertex x="4" y="0" z="0"/><vertex x="0" y="5" z="6"/></vertices>
+            <triangles><triangle v1="0" v2="1" v3="2"/></triangles>
+            </mesh></object></resources></model>"#;
+        let triangles = parse_3mf_model(model).unwrap();
+        assert_eq!(triangles, vec![[[0.0, 0.0, 0.0], [4.0, 0.0, 0.0], [0.0, 5.0, 6.0]]]);
+    }
+}
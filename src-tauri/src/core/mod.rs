@@ -1,6 +1,60 @@
+pub mod archive_inspector;
 pub mod auth_manager;
+pub mod bootstrap;
+pub mod cad_normalizer;
+pub mod cad_temp;
+pub mod changes;
+pub mod circuit_breaker;
+pub mod concurrency;
 pub mod conflict_resolver;
+pub mod consistency;
 pub mod credentials;
+pub mod drive_encryption;
+pub mod estimate;
+pub mod extension_policy;
 pub mod file_hasher;
+pub mod inbox;
+pub mod legal_hold;
+pub mod managed_policy;
+pub mod media_exchange;
+pub mod mesh;
+pub mod mqtt;
+pub mod naming;
+pub mod path_prefix;
+pub mod nas_discovery;
+pub mod notifications;
 pub mod oauth_server;
+pub mod package_export;
+pub mod plan_approval;
+pub mod planner;
+pub mod previews;
+pub mod quarantine;
+pub mod progress;
+pub mod project_template;
+pub mod publishing;
+pub mod quick_hash;
+pub mod references;
+pub mod relocation;
+pub mod release;
+pub mod revisions;
+pub mod roles;
+pub mod scan_cache;
+pub mod selective_sync;
+pub mod service_account;
+pub mod shell_integration;
+pub mod signing;
+pub mod simulation;
+pub mod size_report;
+pub mod smb_mounts;
+pub mod snapshots;
+pub mod soak;
+pub mod stale_files;
+pub mod stubs;
 pub mod sync_engine;
+pub mod sync_policy;
+pub mod tiering;
+pub mod trash;
+pub mod updates;
+pub mod watcher;
+pub mod webdav;
+pub mod working_set;
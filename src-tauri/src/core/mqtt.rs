@@ -0,0 +1,270 @@
+use crate::core::notifications::{Notification, NotificationKind, NotificationSink, Severity};
+use crate::utils::error::{Result, UvcadError};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const DEFAULT_PORT: u16 = 1883;
+/// Connecting, the handshake and publishing together may take no longer.
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
+const KEEP_ALIVE_SECS: u16 = 30;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const DISCONNECT: u8 = 0xE0;
+const RETAIN: u8 = 0x01;
+
+const FLAG_USERNAME: u8 = 0x80;
+const FLAG_PASSWORD: u8 = 0x40;
+const FLAG_CLEAN_SESSION: u8 = 0x02;
+
+/// Publishes to an MQTT 3.1.1 broker, e.g. for a Node-RED dashboard:
+/// - `<topic>/status` holds the outcome of the last sync, retained so new
+///   subscribers see it right away
+/// - `<topic>/events` receives every notification, including per-run summaries
+///
+/// Messages are sent at QoS 0 over a short-lived plain TCP connection each.
+pub struct MqttSink {
+    host: String,
+    port: u16,
+    topic: String,
+    username: Option<String>,
+    password: Option<String>,
+    client_id: String,
+}
+
+/// What `<topic>/status` holds.
+#[derive(Debug, Serialize)]
+struct Status<'a> {
+    /// `current`, `problems` or `failed`.
+    state: &'a str,
+    message: &'a str,
+    run_id: Option<&'a str>,
+    details: Option<&'a serde_json::Value>,
+    updated_at: String,
+}
+
+impl MqttSink {
+    /// `broker` is `host`, `host:port` or `mqtt://host:port`.
+    pub fn new(broker: &str, topic: &str, username: Option<String>, password: Option<String>) -> Result<Self> {
+        let (host, port) = parse_broker(broker)?;
+        let topic = topic.trim().trim_end_matches('/');
+        if topic.is_empty() || topic.contains(['+', '#']) {
+            return Err(UvcadError::InvalidConfig(format!("Invalid MQTT topic: {}", topic)));
+        }
+        Ok(Self {
+            host,
+            port,
+            topic: topic.to_string(),
+            username,
+            password,
+            client_id: format!("uvcad-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]),
+        })
+    }
+
+    async fn publish_all(&self, messages: &[(String, Vec<u8>, bool)]) -> Result<()> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        stream.write_all(&connect_packet(&self.client_id, self.username.as_deref(), self.password.as_deref())).await?;
+
+        let mut connack = [0u8; 4];
+        stream.read_exact(&mut connack).await?;
+        if connack[0] != CONNACK || connack[1] != 2 {
+            return Err(UvcadError::ProviderError("MQTT broker sent an unexpected reply".to_string()));
+        }
+        if connack[3] != 0 {
+            return Err(match connack[3] {
+                4 | 5 => UvcadError::AuthenticationFailed("MQTT broker refused the credentials".to_string()),
+                code => UvcadError::ProviderError(format!("MQTT broker refused the connection (code {})", code)),
+            });
+        }
+
+        for (topic, payload, retain) in messages {
+            stream.write_all(&publish_packet(topic, payload, *retain)).await?;
+        }
+        stream.write_all(&[DISCONNECT, 0]).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for MqttSink {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let mut messages = vec![(format!("{}/events", self.topic), serde_json::to_vec(notification)?, false)];
+        if matches!(notification.kind, NotificationKind::SyncCompleted | NotificationKind::SyncFailed) {
+            let status = Status {
+                state: match notification.severity {
+                    Severity::Info => "current",
+                    Severity::Warning => "problems",
+                    Severity::Error => "failed",
+                },
+                message: &notification.message,
+                run_id: notification.run_id.as_deref(),
+                details: notification.details.as_ref(),
+                updated_at: notification.created_at.to_rfc3339(),
+            };
+            messages.push((format!("{}/status", self.topic), serde_json::to_vec(&status)?, true));
+        }
+
+        tokio::time::timeout(PUBLISH_TIMEOUT, self.publish_all(&messages))
+            .await
+            .map_err(|_| UvcadError::ProviderError(format!("MQTT broker {}:{} did not answer", self.host, self.port)))?
+    }
+}
+
+fn parse_broker(broker: &str) -> Result<(String, u16)> {
+    let broker = broker.trim();
+    let address = match broker.split_once("://") {
+        Some(("mqtt" | "tcp", rest)) => rest,
+        Some((scheme, _)) => {
+            return Err(UvcadError::InvalidConfig(format!("Unsupported MQTT broker scheme: {}", scheme)));
+        }
+        None => broker,
+    }.trim_end_matches('/');
+
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse()
+                .map_err(|_| UvcadError::InvalidConfig(format!("Invalid MQTT broker port: {}", port)))?;
+            (host, port)
+        }
+        None => (address, DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return Err(UvcadError::InvalidConfig(format!("Invalid MQTT broker: {}", broker)));
+    }
+    Ok((host.to_string(), port))
+}
+
+fn connect_packet(client_id: &str, username: Option<&str>, password: Option<&str>) -> Vec<u8> {
+    let mut flags = FLAG_CLEAN_SESSION;
+    let mut body = Vec::new();
+    put_string(&mut body, "MQTT");
+    body.push(4); // Protocol level of MQTT 3.1.1
+    let flags_at = body.len();
+    body.push(0);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+
+    put_string(&mut body, client_id);
+    if let Some(username) = username {
+        flags |= FLAG_USERNAME;
+        put_string(&mut body, username);
+        if let Some(password) = password {
+            flags |= FLAG_PASSWORD;
+            put_string(&mut body, password);
+        }
+    }
+    body[flags_at] = flags;
+    packet(CONNECT, body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(if retain { PUBLISH | RETAIN } else { PUBLISH }, body)
+}
+
+/// A control packet: type byte, remaining length as a variable-length integer, body.
+fn packet(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut remaining = body.len();
+    loop {
+        let mut byte = (remaining % 128) as u8;
+        remaining /= 128;
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if remaining == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+fn put_string(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Read one control packet from a client: type byte and body.
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let (mut length, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            length += ((byte & 0x7F) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    #[test]
+    fn test_remaining_length_and_broker() {
+        assert_eq!(&packet(PUBLISH, vec![0; 127])[..2], &[PUBLISH, 0x7F]);
+        assert_eq!(&packet(PUBLISH, vec![0; 321])[..3], &[PUBLISH, 0xC1, 0x02]);
+        assert_eq!(parse_broker("mqtt://nodered.local:1884").unwrap(), ("nodered.local".to_string(), 1884));
+        assert_eq!(parse_broker("10.0.0.5").unwrap(), ("10.0.0.5".to_string(), DEFAULT_PORT));
+        assert!(parse_broker("mqtts://broker").is_err());
+        assert!(MqttSink::new("broker", "factory/#", None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publishes_retained_status_after_sync() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (header, connect) = read_packet(&mut stream).await;
+            assert_eq!(header, CONNECT);
+            assert_eq!(connect[7], FLAG_CLEAN_SESSION | FLAG_USERNAME | FLAG_PASSWORD);
+            stream.write_all(&[CONNACK, 2, 0, 0]).await.unwrap();
+
+            let mut published = Vec::new();
+            loop {
+                match read_packet(&mut stream).await {
+                    (DISCONNECT, _) => break,
+                    (header, body) => {
+                        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                        let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+                        let payload: serde_json::Value = serde_json::from_slice(&body[2 + topic_len..]).unwrap();
+                        published.push((header, topic, payload));
+                    }
+                }
+            }
+            published
+        });
+
+        let sink = MqttSink::new(&format!("127.0.0.1:{}", port), "factory/cam1/", Some("cam".into()), Some("secret".into())).unwrap();
+        let notification = Notification::new(NotificationKind::SyncCompleted, Severity::Warning, "1 failed".to_string())
+            .with_run_id("run-1");
+        sink.send(&notification).await.unwrap();
+
+        let published = broker.await.unwrap();
+        assert_eq!(published.len(), 2);
+        assert_eq!((published[0].0, published[0].1.as_str()), (PUBLISH, "factory/cam1/events"));
+        assert_eq!(published[0].2["kind"], "sync_completed");
+        assert_eq!((published[1].0, published[1].1.as_str()), (PUBLISH | RETAIN, "factory/cam1/status"));
+        assert_eq!(published[1].2["state"], "problems");
+        assert_eq!(published[1].2["run_id"], "run-1");
+    }
+}
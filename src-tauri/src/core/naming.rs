@@ -0,0 +1,168 @@
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, SyncStatus};
+use crate::utils::error::{Result, UvcadError};
+use regex::Regex;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Prefix marking a rule line as a raw regular expression rather than a template.
+const REGEX_PREFIX: &str = "regex:";
+
+/// Per-profile file naming convention.
+///
+/// Rules are written one per line and matched against the file name (not the folder).
+/// A file complies if it matches any rule. Template rules support:
+/// - `#` one digit
+/// - `{text}` letters, digits and hyphens
+/// - `{rev}` a revision tag (letters/digits)
+/// - `{ext}` a file extension
+/// - `*` anything
+///
+/// Everything else is literal, e.g. `PRJ-####_{text}_Rev{rev}.{ext}`.
+/// Lines starting with `regex:` are used as-is (anchored to the whole name),
+/// and lines starting with `//` are comments.
+#[derive(Debug, Clone, Default)]
+pub struct NamingConvention {
+    rules: Vec<Regex>,
+    blocked_locations: Vec<FileLocation>,
+}
+
+impl NamingConvention {
+    /// Build from the profile's rule text and comma-separated list of locations
+    /// ("gdrive", "smb") that non-compliant files must not be synced to.
+    pub fn from_config(rules: Option<&str>, blocked_locations: Option<&str>) -> Result<Self> {
+        let rules = rules.unwrap_or("")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .map(compile_rule)
+            .collect::<Result<Vec<_>>>()?;
+
+        let blocked_locations = blocked_locations.unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| l.parse::<FileLocation>().map_err(UvcadError::InvalidConfig))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { rules, blocked_locations })
+    }
+
+    /// Whether any rules are configured. Without rules every name complies.
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    pub fn is_compliant(&self, path: &Path) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        self.rules.iter().any(|rule| rule.is_match(name))
+    }
+
+    /// Whether syncing `path` to `location` must be refused.
+    pub fn blocks(&self, path: &Path, location: &FileLocation) -> bool {
+        self.blocked_locations.contains(location) && !self.is_compliant(path)
+    }
+}
+
+/// A tracked file whose name breaks the convention.
+#[derive(Debug, Clone, Serialize)]
+pub struct NamingViolation {
+    pub path: String,
+    /// Locations the file currently exists on.
+    pub locations: Vec<String>,
+}
+
+/// List every tracked file violating the convention, with where it currently lives.
+pub fn find_violations(conn: &Connection, profile_id: i64, convention: &NamingConvention) -> Result<Vec<NamingViolation>> {
+    if !convention.is_enabled() {
+        return Ok(Vec::new());
+    }
+
+    let mut violations: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for state in DbOperations::get_file_states(conn, profile_id)? {
+        if state.status == SyncStatus::Deleted || convention.is_compliant(Path::new(&state.file_path)) {
+            continue;
+        }
+        violations.entry(state.file_path).or_default().push(state.location.as_str().to_string());
+    }
+
+    Ok(violations.into_iter()
+        .map(|(path, locations)| NamingViolation { path, locations })
+        .collect())
+}
+
+fn compile_rule(line: &str) -> Result<Regex> {
+    let pattern = match line.strip_prefix(REGEX_PREFIX) {
+        Some(raw) => format!("^(?:{})$", raw.trim()),
+        None => format!("^{}$", template_to_regex(line)),
+    };
+    Regex::new(&pattern)
+        .map_err(|e| UvcadError::InvalidConfig(format!("Invalid naming rule '{}': {}", line, e)))
+}
+
+fn template_to_regex(template: &str) -> String {
+    let mut pattern = String::new();
+    let mut rest = template;
+
+    while let Some(c) = rest.chars().next() {
+        let placeholder = [
+            ("{text}", "[A-Za-z0-9-]+"),
+            ("{rev}", "[A-Za-z0-9]+"),
+            ("{ext}", "[A-Za-z0-9]+"),
+        ]
+        .into_iter()
+        .find(|(token, _)| rest.starts_with(token));
+
+        if let Some((token, regex)) = placeholder {
+            pattern.push_str(regex);
+            rest = &rest[token.len()..];
+            continue;
+        }
+
+        match c {
+            '#' => pattern.push_str("[0-9]"),
+            '*' => pattern.push_str(".*"),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_rules() {
+        let convention = NamingConvention::from_config(
+            Some("PRJ-####_{text}_Rev{rev}.{ext}\n*.pdf"),
+            Some("smb"),
+        ).unwrap();
+
+        assert!(convention.is_compliant(Path::new("jobs/PRJ-1042_Housing-Left_RevB.step")));
+        assert!(convention.is_compliant(Path::new("docs/anything.pdf")));
+        assert!(!convention.is_compliant(Path::new("PRJ-12_Housing_RevB.step")));
+        assert!(!convention.is_compliant(Path::new("housing final v2.step")));
+
+        assert!(convention.blocks(Path::new("housing.step"), &FileLocation::Smb));
+        assert!(!convention.blocks(Path::new("housing.step"), &FileLocation::GoogleDrive));
+    }
+
+    #[test]
+    fn test_regex_rules_and_empty_config() {
+        let convention = NamingConvention::from_config(Some(r"regex:[A-Z]{3}\d+\.dwg"), None).unwrap();
+        assert!(convention.is_compliant(Path::new("ABC123.dwg")));
+        assert!(!convention.is_compliant(Path::new("xABC123.dwg")));
+
+        assert!(NamingConvention::default().is_compliant(Path::new("whatever.txt")));
+        assert!(NamingConvention::from_config(Some("regex:("), None).is_err());
+    }
+}
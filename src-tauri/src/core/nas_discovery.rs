@@ -0,0 +1,367 @@
+use crate::utils::error::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+/// How long to wait for answers to each discovery probe.
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+const MDNS_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+const NETBIOS_PORT: u16 = 137;
+const WSD_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 3702);
+
+const SMB_SERVICE: &str = "_smb._tcp.local";
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_PTR: u16 = 12;
+const DNS_TYPE_SRV: u16 = 33;
+const NETBIOS_TYPE_NBSTAT: u16 = 0x21;
+/// NetBIOS name suffix of the file server service.
+const NETBIOS_FILE_SERVER: u8 = 0x20;
+
+/// A file server found on the local network.
+#[derive(Debug, Clone, Serialize)]
+pub struct NasServer {
+    pub name: String,
+    /// Host name or address to build share paths from (`//host/share`).
+    pub host: String,
+    pub address: Option<String>,
+    /// Protocols the server answered: "mdns", "netbios", "wsd".
+    pub sources: Vec<String>,
+    pub shares: Vec<String>,
+}
+
+/// Probe the LAN with mDNS (`_smb._tcp`), a NetBIOS node status broadcast and
+/// WS-Discovery, merging servers that answered more than one.
+pub async fn discover_servers() -> Result<Vec<NasServer>> {
+    let (mdns, netbios, wsd) = tokio::join!(discover_mdns(), discover_netbios(), discover_wsd());
+
+    let mut servers: Vec<NasServer> = Vec::new();
+    for (source, found) in [("mdns", mdns), ("netbios", netbios), ("wsd", wsd)] {
+        let found = match found {
+            Ok(found) => found,
+            Err(e) => {
+                tracing::warn!("{} discovery failed: {}", source, e);
+                continue;
+            }
+        };
+        for server in found {
+            let existing = servers.iter_mut().find(|s| {
+                (s.address.is_some() && s.address == server.address) || s.host.eq_ignore_ascii_case(&server.host)
+            });
+            match existing {
+                Some(existing) => {
+                    existing.sources.extend(server.sources);
+                    existing.address = existing.address.take().or(server.address);
+                }
+                None => servers.push(server),
+            }
+        }
+    }
+    servers.sort_by_key(|server| server.name.to_lowercase());
+    Ok(servers)
+}
+
+/// Collect datagrams answering a probe until the timeout passes.
+async fn collect_replies(socket: &UdpSocket) -> Vec<(Vec<u8>, SocketAddr)> {
+    let deadline = Instant::now() + DISCOVERY_TIMEOUT;
+    let mut replies = Vec::new();
+    let mut buffer = vec![0u8; 9000];
+    while let Ok(Ok((len, from))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+        replies.push((buffer[..len].to_vec(), from));
+    }
+    replies
+}
+
+fn server(name: &str, host: &str, address: Option<String>, source: &str) -> NasServer {
+    NasServer {
+        name: name.to_string(),
+        host: host.to_string(),
+        address,
+        sources: vec![source.to_string()],
+        shares: Vec::new(),
+    }
+}
+
+async fn discover_mdns() -> Result<Vec<NasServer>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.send_to(&mdns_query(), MDNS_ADDR).await?;
+
+    let mut records = MdnsRecords::default();
+    for (packet, _) in collect_replies(&socket).await {
+        records.add_packet(&packet);
+    }
+    Ok(records.servers())
+}
+
+/// A PTR query for SMB services asking for unicast replies.
+fn mdns_query() -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    encode_name(&mut packet, SMB_SERVICE);
+    packet.extend_from_slice(&DNS_TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&0x8001u16.to_be_bytes()); // QU bit + class IN
+    packet
+}
+
+fn encode_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+}
+
+/// Records gathered from mDNS answers, possibly spread over several packets.
+#[derive(Default)]
+struct MdnsRecords {
+    instances: Vec<String>,
+    /// Service instance -> target host.
+    targets: HashMap<String, String>,
+    /// Host -> IPv4 address.
+    addresses: HashMap<String, Ipv4Addr>,
+}
+
+impl MdnsRecords {
+    fn add_packet(&mut self, packet: &[u8]) {
+        let Some(records) = parse_dns_records(packet) else {
+            return;
+        };
+        for record in records {
+            match record.rtype {
+                DNS_TYPE_PTR if record.name.eq_ignore_ascii_case(SMB_SERVICE) => {
+                    if let Some((instance, _)) = read_name(packet, record.rdata_offset) {
+                        if !self.instances.contains(&instance) {
+                            self.instances.push(instance);
+                        }
+                    }
+                }
+                DNS_TYPE_SRV if record.rdata.len() > 6 => {
+                    if let Some((target, _)) = read_name(packet, record.rdata_offset + 6) {
+                        self.targets.insert(record.name.to_lowercase(), target);
+                    }
+                }
+                DNS_TYPE_A if record.rdata.len() == 4 => {
+                    let address = Ipv4Addr::new(record.rdata[0], record.rdata[1], record.rdata[2], record.rdata[3]);
+                    self.addresses.insert(record.name.to_lowercase(), address);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn servers(&self) -> Vec<NasServer> {
+        self.instances.iter()
+            .map(|instance| {
+                let name = instance.split("._smb.").next().unwrap_or(instance);
+                let host = self.targets.get(&instance.to_lowercase())
+                    .map(|t| t.trim_end_matches('.').to_string())
+                    .unwrap_or_else(|| format!("{}.local", name));
+                let address = self.addresses.get(&host.to_lowercase()).map(|a| a.to_string());
+                server(name, &host, address, "mdns")
+            })
+            .collect()
+    }
+}
+
+struct DnsRecord<'a> {
+    name: String,
+    rtype: u16,
+    rdata: &'a [u8],
+    rdata_offset: usize,
+}
+
+/// Answer, authority and additional records of a DNS message.
+fn parse_dns_records(packet: &[u8]) -> Option<Vec<DnsRecord<'_>>> {
+    let count = |at: usize| Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]) as usize);
+    let questions = count(4)?;
+    let records = count(6)? + count(8)? + count(10)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = read_name(packet, offset)?.1 + 4;
+    }
+
+    let mut parsed = Vec::with_capacity(records);
+    for _ in 0..records {
+        let (name, next) = read_name(packet, offset)?;
+        let rtype = count(next)? as u16;
+        let rdlength = count(next + 8)?;
+        let rdata_offset = next + 10;
+        let rdata = packet.get(rdata_offset..rdata_offset + rdlength)?;
+        parsed.push(DnsRecord { name, rtype, rdata, rdata_offset });
+        offset = rdata_offset + rdlength;
+    }
+    Some(parsed)
+}
+
+/// Read a possibly compressed name at `offset`; returns it and the offset after it.
+fn read_name(packet: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut position = offset;
+    let mut end = None;
+    // Bounds the number of pointers followed, so loops cannot hang
+    for _ in 0..64 {
+        let len = *packet.get(position)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(position + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let target = ((len & 0x3F) << 8) | *packet.get(position + 1)? as usize;
+            end.get_or_insert(position + 2);
+            position = target;
+            continue;
+        }
+        let label = packet.get(position + 1..position + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        position += 1 + len;
+    }
+    None
+}
+
+async fn discover_netbios() -> Result<Vec<NasServer>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&netbios_status_query(), (Ipv4Addr::BROADCAST, NETBIOS_PORT)).await?;
+
+    Ok(collect_replies(&socket).await.into_iter()
+        .filter_map(|(packet, from)| {
+            let name = parse_netbios_status(&packet)?;
+            let address = from.ip().to_string();
+            Some(server(&name, &name, Some(address), "netbios"))
+        })
+        .collect())
+}
+
+/// A node status request for the wildcard name `*`.
+fn netbios_status_query() -> Vec<u8> {
+    let mut packet = vec![0x55, 0xAA, 0x00, 0x10, 0, 1, 0, 0, 0, 0, 0, 0];
+    let mut name = [0u8; 16];
+    name[0] = b'*';
+    packet.push(32);
+    for byte in name {
+        packet.push(b'A' + (byte >> 4));
+        packet.push(b'A' + (byte & 0x0F));
+    }
+    packet.push(0);
+    packet.extend_from_slice(&NETBIOS_TYPE_NBSTAT.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet
+}
+
+/// The file server name in a node status response, if the host runs one.
+fn parse_netbios_status(packet: &[u8]) -> Option<String> {
+    let (_, next) = read_name(packet, 12)?;
+    let table = next + 10;
+    let count = *packet.get(table)? as usize;
+    (0..count)
+        .filter_map(|i| packet.get(table + 1 + i * 18..table + 1 + (i + 1) * 18))
+        .find(|entry| entry[15] == NETBIOS_FILE_SERVER && entry[16] & 0x80 == 0)
+        .map(|entry| String::from_utf8_lossy(&entry[..15]).trim_end().to_string())
+}
+
+async fn discover_wsd() -> Result<Vec<NasServer>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let message_id = format!("{:032x}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
+    socket.send_to(wsd_probe(&message_id).as_bytes(), WSD_ADDR).await?;
+
+    Ok(collect_replies(&socket).await.into_iter()
+        .filter_map(|(packet, from)| {
+            let reply = String::from_utf8_lossy(&packet);
+            if !reply.contains("pub:Computer") {
+                return None;
+            }
+            let address = from.ip().to_string();
+            Some(server(&address, &address, Some(address.clone()), "wsd"))
+        })
+        .collect())
+}
+
+fn wsd_probe(message_id: &str) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" "#,
+            r#"xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" "#,
+            r#"xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery" "#,
+            r#"xmlns:wsdp="http://schemas.xmlsoap.org/ws/2006/02/devprof" "#,
+            r#"xmlns:pub="http://schemas.microsoft.com/windows/pub/2005/07">"#,
+            r#"<soap:Header><wsa:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</wsa:To>"#,
+            r#"<wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</wsa:Action>"#,
+            r#"<wsa:MessageID>urn:uuid:{}-{}-{}-{}-{}</wsa:MessageID></soap:Header>"#,
+            r#"<soap:Body><wsd:Probe><wsd:Types>wsdp:Device pub:Computer</wsd:Types></wsd:Probe></soap:Body>"#,
+            r#"</soap:Envelope>"#,
+        ),
+        &message_id[..8], &message_id[8..12], &message_id[12..16], &message_id[16..20], &message_id[20..32],
+    )
+}
+
+/// Disk shares a server offers to guests, listed with the platform's SMB tools.
+pub fn list_shares(host: &str) -> Result<Vec<String>> {
+    #[cfg(target_os = "macos")]
+    let output = std::process::Command::new("smbutil").args(["view", "-G", &format!("//{}", host)]).output()?;
+    #[cfg(target_os = "windows")]
+    let output = std::process::Command::new("net").args(["view", &format!(r"\\{}", host)]).output()?;
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let output = std::process::Command::new("smbclient").args(["-L", host, "-N", "-g"]).output()?;
+
+    Ok(parse_share_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Disk shares in `smbutil view` / `net view` tables or `smbclient -g` output.
+/// Hidden administrative shares (ending in `$`) are skipped.
+fn parse_share_list(output: &str) -> Vec<String> {
+    let table_row = Regex::new(r"^(.+?)\s{2,}Disk\b").unwrap();
+    let shares: BTreeSet<String> = output.lines()
+        .filter_map(|line| match line.strip_prefix("Disk|") {
+            Some(grepable) => grepable.split('|').next().map(str::to_string),
+            None => table_row.captures(line).map(|c| c[1].trim().to_string()),
+        })
+        .filter(|share| !share.ends_with('$'))
+        .collect();
+    shares.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mdns_answer() {
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 3, 0, 0, 0, 0];
+        let record = |packet: &mut Vec<u8>, owner: &str, rtype: u16, rdata: Vec<u8>| {
+            encode_name(packet, owner);
+            packet.extend_from_slice(&rtype.to_be_bytes());
+            packet.extend_from_slice(&[0, 1, 0, 0, 0, 120]);
+            packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            packet.extend(rdata);
+        };
+        let mut instance = Vec::new();
+        encode_name(&mut instance, "Eng NAS._smb._tcp.local");
+        record(&mut packet, SMB_SERVICE, DNS_TYPE_PTR, instance);
+        let mut srv = vec![0, 0, 0, 0, 1, 189];
+        encode_name(&mut srv, "engnas.local");
+        record(&mut packet, "Eng NAS._smb._tcp.local", DNS_TYPE_SRV, srv);
+        record(&mut packet, "engnas.local", DNS_TYPE_A, vec![192, 168, 1, 20]);
+
+        let mut records = MdnsRecords::default();
+        records.add_packet(&packet);
+        let servers = records.servers();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].name, "Eng NAS");
+        assert_eq!(servers[0].host, "engnas.local");
+        assert_eq!(servers[0].address.as_deref(), Some("192.168.1.20"));
+    }
+
+    #[test]
+    fn test_parse_share_lists() {
+        let net_view = "Shared resources at \\\\NAS\n\nShare name   Type  Used as  Comment\n\
+                        -----------------------------------------\n\
+                        Eng Data     Disk           Engineering\nPrinter      Print\n\
+                        The command completed successfully.\n";
+        assert_eq!(parse_share_list(net_view), vec!["Eng Data"]);
+        assert_eq!(parse_share_list("Disk|eng|\nDisk|IPC$|\nPrinter|hp|\n"), vec!["eng"]);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::core::mqtt::MqttSink;
+use crate::utils::error::{Result, UvcadError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// What happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    SyncCompleted,
+    SyncFailed,
+    PlanAwaitingApproval,
+    UpdateAvailable,
+    InboxProcessed,
+    FileRestored,
+    /// A downloaded file did not match its signature; see `signing`.
+    TamperDetected,
+    /// The malware scanner flagged a download; see `quarantine`.
+    FileQuarantined,
+    /// Sent on request to check the configured channels.
+    Test,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    /// Localized text for people; channels may add their own title.
+    pub message: String,
+    pub run_id: Option<String>,
+    /// Machine-readable facts, e.g. the counts of a sync run.
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn new(kind: NotificationKind, severity: Severity, message: String) -> Self {
+        Self { kind, severity, message, run_id: None, details: None, created_at: Utc::now() }
+    }
+
+    pub fn with_run_id(mut self, run_id: &str) -> Self {
+        self.run_id = Some(run_id.to_string());
+        self
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// A channel notifications are delivered through, e.g. the app window or a webhook.
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Which notifications a channel receives.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SinkFilter {
+    /// Empty receives every kind.
+    #[serde(default)]
+    pub kinds: Vec<NotificationKind>,
+    #[serde(default)]
+    pub min_severity: Severity,
+}
+
+impl SinkFilter {
+    pub fn accepts(&self, notification: &Notification) -> bool {
+        notification.severity >= self.min_severity
+            && (self.kinds.is_empty() || self.kinds.contains(&notification.kind))
+    }
+}
+
+/// One configured channel from the profile's `notification_channels`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelConfig {
+    /// The UVCAD window.
+    App {
+        #[serde(flatten)]
+        filter: SinkFilter,
+    },
+    /// A JSON POST of each notification to `url`.
+    Webhook {
+        url: String,
+        #[serde(flatten)]
+        filter: SinkFilter,
+    },
+    /// Sync status and notifications published to an MQTT broker; see `MqttSink`.
+    Mqtt {
+        broker: String,
+        topic: String,
+        username: Option<String>,
+        password: Option<String>,
+        #[serde(flatten)]
+        filter: SinkFilter,
+    },
+}
+
+impl ChannelConfig {
+    /// Parse the profile setting. Unset notifies the app window of everything.
+    pub fn parse_all(config: Option<&str>) -> Result<Vec<ChannelConfig>> {
+        let Some(json) = config.map(str::trim).filter(|c| !c.is_empty()) else {
+            return Ok(vec![ChannelConfig::App { filter: SinkFilter::default() }]);
+        };
+        let channels: Vec<ChannelConfig> = serde_json::from_str(json)
+            .map_err(|e| UvcadError::InvalidConfig(e.to_string()))?;
+        for channel in &channels {
+            channel.sink()?;
+        }
+        Ok(channels)
+    }
+
+    /// The sink delivering to this channel; None for the app window, which
+    /// only the app can reach.
+    pub fn sink(&self) -> Result<Option<(Arc<dyn NotificationSink>, SinkFilter)>> {
+        match self {
+            ChannelConfig::App { .. } => Ok(None),
+            ChannelConfig::Webhook { url, filter } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(UvcadError::InvalidConfig(format!("Webhook URL must use http or https: {}", url)));
+                }
+                Ok(Some((Arc::new(WebhookSink::new(url.clone())), filter.clone())))
+            }
+            ChannelConfig::Mqtt { broker, topic, username, password, filter } => {
+                let sink = MqttSink::new(broker, topic, username.clone(), password.clone())?;
+                Ok(Some((Arc::new(sink), filter.clone())))
+            }
+        }
+    }
+}
+
+/// Posts each notification as JSON.
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: String) -> Self {
+        Self { url, client: reqwest::Client::new() }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let response = self.client.post(&self.url).json(notification).send().await?;
+        if !response.status().is_success() {
+            return Err(UvcadError::ProviderError(format!("Webhook {} answered {}", self.url, response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Hands each notification to the channels whose filter accepts it.
+#[derive(Default)]
+pub struct Dispatcher {
+    sinks: Vec<(Arc<dyn NotificationSink>, SinkFilter)>,
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn NotificationSink>, filter: SinkFilter) -> Self {
+        self.sinks.push((sink, filter));
+        self
+    }
+
+    /// Deliver to every accepting channel. A failing channel is logged and does
+    /// not keep the others from receiving it. Returns how many delivered it.
+    pub async fn dispatch(&self, notification: &Notification) -> usize {
+        let mut delivered = 0;
+        for (sink, _) in self.sinks.iter().filter(|(_, filter)| filter.accepts(notification)) {
+            match sink.send(notification).await {
+                Ok(()) => delivered += 1,
+                Err(e) => tracing::warn!("Failed to notify through {}: {}", sink.name(), e),
+            }
+        }
+        delivered
+    }
+}
+
+static DISPATCHER: Lazy<RwLock<Arc<Dispatcher>>> = Lazy::new(|| RwLock::new(Arc::new(Dispatcher::new())));
+
+/// Replace the process-wide channels, e.g. after the settings changed.
+pub fn install(dispatcher: Dispatcher) {
+    *DISPATCHER.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(dispatcher);
+}
+
+/// Deliver through the installed channels.
+pub async fn notify(notification: Notification) -> usize {
+    let dispatcher = DISPATCHER.read().unwrap_or_else(|e| e.into_inner()).clone();
+    dispatcher.dispatch(&notification).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<NotificationKind>>);
+
+    #[async_trait]
+    impl NotificationSink for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        async fn send(&self, notification: &Notification) -> Result<()> {
+            self.0.lock().unwrap().push(notification.kind);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_filters_by_kind_and_severity() {
+        let everything = Arc::new(Recorder::default());
+        let failures = Arc::new(Recorder::default());
+        let dispatcher = Dispatcher::new()
+            .with_sink(everything.clone(), SinkFilter::default())
+            .with_sink(failures.clone(), SinkFilter { kinds: vec![NotificationKind::SyncFailed], min_severity: Severity::Warning });
+
+        let completed = Notification::new(NotificationKind::SyncCompleted, Severity::Warning, "2 failed".to_string());
+        let failed = Notification::new(NotificationKind::SyncFailed, Severity::Error, "Sync failed".to_string());
+        assert_eq!(dispatcher.dispatch(&completed).await, 1);
+        assert_eq!(dispatcher.dispatch(&failed).await, 2);
+        assert_eq!(*failures.0.lock().unwrap(), vec![NotificationKind::SyncFailed]);
+        assert_eq!(everything.0.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_channels() {
+        let channels = ChannelConfig::parse_all(Some(
+            r#"[{"type": "webhook", "url": "https://example.com/hook", "kinds": ["sync_failed"], "min_severity": "error"}]"#,
+        )).unwrap();
+        assert_eq!(channels, vec![ChannelConfig::Webhook {
+            url: "https://example.com/hook".to_string(),
+            filter: SinkFilter { kinds: vec![NotificationKind::SyncFailed], min_severity: Severity::Error },
+        }]);
+        assert_eq!(ChannelConfig::parse_all(None).unwrap(), vec![ChannelConfig::App { filter: SinkFilter::default() }]);
+        assert!(ChannelConfig::parse_all(Some(r#"[{"type": "webhook", "url": "ftp://x"}]"#)).is_err());
+        assert!(ChannelConfig::parse_all(Some(r#"[{"type": "pager"}]"#)).is_err());
+    }
+}
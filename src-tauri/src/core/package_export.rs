@@ -0,0 +1,117 @@
+use crate::core::cad_normalizer::{self, NormalizedFormat};
+use crate::core::file_hasher;
+use crate::core::references;
+use crate::db::models::DbOperations;
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageManifestEntry {
+    pub path: String,
+    pub size: u64,
+    /// SHA-256 of the file as packaged (after metadata stripping, if applied).
+    pub sha256: String,
+    pub metadata_stripped: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageManifest {
+    pub root_assembly: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<PackageManifestEntry>,
+    /// References inside packaged assemblies that could not be resolved to a file.
+    pub unresolved_references: Vec<String>,
+}
+
+/// Build a zip package containing `assembly_path` and everything it references,
+/// plus a `manifest.json` listing every member with its hash.
+///
+/// All files must be present in the local root; missing ones abort the export so
+/// an incomplete package is never sent to a supplier.
+pub fn build_package(
+    conn: &Connection,
+    profile_id: i64,
+    local_root: &Path,
+    assembly_path: &str,
+    strip_metadata: bool,
+    output: &Path,
+) -> Result<PackageManifest> {
+    let files = references::collect_dependencies(conn, profile_id, &[assembly_path.to_string()])?;
+
+    let missing: Vec<&String> = files.iter()
+        .filter(|f| !local_root.join(f).is_file())
+        .collect();
+    if !missing.is_empty() {
+        return Err(UvcadError::ArchiveError(format!(
+            "Cannot build package, files missing locally: {}",
+            missing.iter().map(|f| f.as_str()).collect::<Vec<_>>().join(", ")
+        )));
+    }
+
+    let included: HashSet<&String> = files.iter().collect();
+    let unresolved_references = DbOperations::get_file_references(conn, profile_id)?
+        .into_iter()
+        .filter(|r| r.resolved_path.is_none() && included.contains(&r.parent_path))
+        .map(|r| format!("{} -> {}", r.parent_path, r.reference))
+        .collect();
+
+    let archive_error = |e: zip::result::ZipError| UvcadError::ArchiveError(e.to_string());
+    let mut writer = zip::ZipWriter::new(File::create(output)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut entries = Vec::with_capacity(files.len());
+    for file in &files {
+        let source = local_root.join(file);
+        let member_name = file.replace('\\', "/");
+        writer.start_file(member_name.as_str(), options).map_err(archive_error)?;
+
+        let is_step = Path::new(file).extension()
+            .and_then(|e| e.to_str())
+            .and_then(NormalizedFormat::from_extension)
+            == Some(NormalizedFormat::Step);
+
+        let entry = if strip_metadata && is_step {
+            let file_name = Path::new(file).file_name().and_then(|n| n.to_str()).unwrap_or(file);
+            let content = cad_normalizer::strip_step_metadata(&std::fs::read(&source)?, file_name);
+            writer.write_all(&content)?;
+            PackageManifestEntry {
+                path: member_name,
+                size: content.len() as u64,
+                sha256: file_hasher::compute_bytes_hash(&content),
+                metadata_stripped: true,
+            }
+        } else {
+            let size = std::io::copy(&mut File::open(&source)?, &mut writer)?;
+            PackageManifestEntry {
+                path: member_name,
+                size,
+                sha256: file_hasher::compute_file_hash(&source)?,
+                metadata_stripped: false,
+            }
+        };
+        entries.push(entry);
+    }
+
+    let manifest = PackageManifest {
+        root_assembly: assembly_path.to_string(),
+        created_at: Utc::now(),
+        files: entries,
+        unresolved_references,
+    };
+
+    writer.start_file(MANIFEST_NAME, options).map_err(archive_error)?;
+    writer.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    writer.finish().map_err(archive_error)?;
+
+    tracing::info!("Built package for {} with {} files", assembly_path, manifest.files.len());
+    Ok(manifest)
+}
@@ -0,0 +1,93 @@
+use crate::models::file_state::FileLocation;
+use crate::utils::error::{Result, UvcadError};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Folders that hold the synced files at remote locations, e.g. an extra
+/// `Engineering` level on Drive that the share doesn't have. Files are planned
+/// under their path below the prefix, so `Engineering/ProjectX/a.dwg` on Drive
+/// pairs with `ProjectX/a.dwg` locally.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathPrefixes {
+    prefixes: HashMap<FileLocation, PathBuf>,
+}
+
+impl PathPrefixes {
+    /// Parse prefixes written one per line as `location = folder`, e.g.
+    /// `gdrive = Engineering`. Blank lines and lines starting with `#` are ignored.
+    pub fn from_config(config: Option<&str>) -> Result<Self> {
+        let mut prefixes = HashMap::new();
+
+        for line in config.unwrap_or("").lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || UvcadError::InvalidConfig(format!("Invalid path prefix: {}", line));
+
+            let (location, folder) = line.split_once('=').ok_or_else(invalid)?;
+            let location: FileLocation = location.trim().parse().map_err(UvcadError::InvalidConfig)?;
+            if location == FileLocation::Local {
+                return Err(UvcadError::InvalidConfig(
+                    "The local folder has no path prefix; point the profile at the subfolder instead".to_string()
+                ));
+            }
+            let folder = PathBuf::from(folder.trim().replace('\\', "/").trim_matches('/'));
+            if folder.as_os_str().is_empty() || !folder.components().all(|c| matches!(c, Component::Normal(_))) {
+                return Err(invalid());
+            }
+            if prefixes.insert(location.clone(), folder).is_some() {
+                return Err(UvcadError::InvalidConfig(format!("Several path prefixes for {}", location.as_str())));
+            }
+        }
+
+        Ok(Self { prefixes })
+    }
+
+    /// The folder files are kept in at `location`, if it has one.
+    pub fn at(&self, location: &FileLocation) -> Option<&Path> {
+        self.prefixes.get(location).map(PathBuf::as_path)
+    }
+
+    /// Where a file is kept at `location`.
+    pub fn stored_path(&self, path: &Path, location: &FileLocation) -> PathBuf {
+        match self.at(location) {
+            Some(prefix) => prefix.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Inverse of `stored_path`: the planned path of a file listed at `location`,
+    /// or None for files outside its prefix, which are not part of the sync.
+    pub fn logical_path(&self, stored: &Path, location: &FileLocation) -> Option<PathBuf> {
+        match self.at(location) {
+            Some(prefix) => stored.strip_prefix(prefix).ok()
+                .filter(|path| !path.as_os_str().is_empty())
+                .map(Path::to_path_buf),
+            None => Some(stored.to_path_buf()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefixes_map_paths_per_location() {
+        let prefixes = PathPrefixes::from_config(Some("# Drive has an extra level\ngdrive = /Engineering/\n")).unwrap();
+        let path = Path::new("ProjectX/a.dwg");
+
+        let stored = prefixes.stored_path(path, &FileLocation::GoogleDrive);
+        assert_eq!(stored, Path::new("Engineering/ProjectX/a.dwg"));
+        assert_eq!(prefixes.logical_path(&stored, &FileLocation::GoogleDrive).as_deref(), Some(path));
+        assert_eq!(prefixes.logical_path(Path::new("Sales/b.pdf"), &FileLocation::GoogleDrive), None);
+        assert_eq!(prefixes.logical_path(Path::new("Engineering"), &FileLocation::GoogleDrive), None);
+        assert_eq!(prefixes.stored_path(path, &FileLocation::Smb), path);
+
+        assert!(PathPrefixes::from_config(Some("gdrive")).is_err());
+        assert!(PathPrefixes::from_config(Some("dropbox = Engineering")).is_err());
+        assert!(PathPrefixes::from_config(Some("local = Engineering")).is_err());
+        assert!(PathPrefixes::from_config(Some("smb = ../Engineering")).is_err());
+        assert!(PathPrefixes::from_config(Some("smb = A\nsmb = B")).is_err());
+    }
+}
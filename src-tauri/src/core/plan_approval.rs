@@ -0,0 +1,103 @@
+use crate::models::sync_plan::{PlanStep, PlannedOperation};
+use crate::utils::error::{Result, UvcadError};
+use std::collections::HashSet;
+
+/// Operation types that can be approved in advance.
+const OPERATION_KINDS: &[&str] = &["copy", "move", "delete"];
+
+/// Who may approve sync plans, and which operations need no approval.
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalPolicy {
+    approvers: Vec<String>,
+    auto_approved: HashSet<String>,
+}
+
+impl ApprovalPolicy {
+    /// Parse the profile settings: comma-separated approver names and operation types.
+    pub fn from_config(approvers: Option<&str>, auto_approved: Option<&str>) -> Result<Self> {
+        let approvers: Vec<String> = split_list(approvers).collect();
+        let auto_approved: HashSet<String> = split_list(auto_approved).map(|k| k.to_lowercase()).collect();
+
+        if let Some(kind) = auto_approved.iter().find(|k| !OPERATION_KINDS.contains(&k.as_str())) {
+            return Err(UvcadError::InvalidConfig(format!(
+                "Unknown operation type: {} (expected {})", kind, OPERATION_KINDS.join(", ")
+            )));
+        }
+        if approvers.is_empty() && auto_approved.len() < OPERATION_KINDS.len() {
+            return Err(UvcadError::InvalidConfig("Plan approval requires at least one approver".to_string()));
+        }
+        Ok(Self { approvers, auto_approved })
+    }
+
+    /// Whether `user` holds the approver role.
+    pub fn can_approve(&self, user: &str) -> bool {
+        self.approvers.iter().any(|a| a.eq_ignore_ascii_case(user))
+    }
+
+    /// Steps that must be approved before they run. Conflicts are never carried
+    /// out by a sync, so they need no approval.
+    pub fn steps_needing_approval<'a>(&'a self, plan: &'a [PlanStep]) -> impl Iterator<Item = &'a PlanStep> {
+        plan.iter().filter(|step| OPERATION_KINDS.contains(&step.operation.kind()) && !self.auto_approves(&step.operation))
+    }
+
+    /// Whether operations like `operation` run without approval.
+    pub fn auto_approves(&self, operation: &PlannedOperation) -> bool {
+        self.auto_approved.contains(operation.kind())
+    }
+
+    /// Whether `plan` may run given the steps of an approved plan. Approval covers
+    /// a step with the same path and operation; indices may shift between runs.
+    pub fn is_covered(&self, plan: &[PlanStep], approved: &[PlanStep]) -> bool {
+        self.steps_needing_approval(plan).all(|step| {
+            approved.iter().any(|a| a.path == step.path && a.operation == step.operation)
+        })
+    }
+}
+
+fn split_list(value: Option<&str>) -> impl Iterator<Item = String> + '_ {
+    value.unwrap_or("").split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+/// The name of the user running the app.
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::file_state::FileLocation;
+
+    fn step(index: usize, path: &str, operation: PlannedOperation) -> PlanStep {
+        PlanStep { index, path: path.to_string(), operation, size_bytes: 0, reason: None }
+    }
+
+    #[test]
+    fn test_auto_approved_operations_skip_approval() {
+        let policy = ApprovalPolicy::from_config(Some("alice, Bob"), Some("copy")).unwrap();
+        let copy = PlannedOperation::Copy { from: FileLocation::Local, to: FileLocation::Smb };
+        let delete = PlannedOperation::Delete { at: FileLocation::GoogleDrive };
+        let plan = vec![
+            step(0, "a.step", copy.clone()),
+            step(1, "b.step", delete.clone()),
+            step(2, "c.step", PlannedOperation::Conflict),
+        ];
+
+        let pending: Vec<_> = policy.steps_needing_approval(&plan).map(|s| s.path.as_str()).collect();
+        assert_eq!(pending, vec!["b.step"]);
+        assert!(policy.is_covered(&plan, &[step(7, "b.step", delete)]));
+        assert!(!policy.is_covered(&plan, &[step(0, "a.step", copy)]));
+        assert!(policy.can_approve("bob"));
+        assert!(!policy.can_approve("mallory"));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(ApprovalPolicy::from_config(Some("alice"), Some("rename")).is_err());
+        assert!(ApprovalPolicy::from_config(None, Some("copy")).is_err());
+        assert!(ApprovalPolicy::from_config(None, Some("copy, delete")).is_err());
+        assert!(ApprovalPolicy::from_config(None, Some("copy, move, delete")).is_ok());
+    }
+}
@@ -0,0 +1,993 @@
+use crate::core::conflict_resolver::Conflict as ConflictInfo;
+use crate::core::extension_policy::{ExtensionBehavior, ExtensionPolicies};
+use crate::core::sync_engine::FileSnapshot;
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use crate::models::sync_plan::{ChangeReason, PlannedOperation};
+use crate::utils::error::Result;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Paths read back from `Snapshots` at a time.
+pub const PAGE_SIZE: usize = 5_000;
+
+const SNAPSHOT_TABLES: &str = "
+    CREATE TABLE files (
+        sort_key TEXT NOT NULL,
+        location TEXT NOT NULL,
+        path TEXT NOT NULL,
+        name_key TEXT NOT NULL,
+        hash TEXT,
+        fingerprint TEXT,
+        size INTEGER NOT NULL,
+        modified TEXT NOT NULL,
+        PRIMARY KEY (sort_key, location)
+    ) WITHOUT ROWID;
+    CREATE INDEX files_by_location ON files(location, sort_key);
+    CREATE INDEX files_by_name ON files(location, name_key);
+    CREATE TABLE duplicates (sort_key TEXT PRIMARY KEY, path TEXT NOT NULL) WITHOUT ROWID;
+    CREATE TABLE last_known (
+        sort_key TEXT PRIMARY KEY,
+        local TEXT,
+        gdrive TEXT,
+        smb TEXT,
+        local_offloaded BOOLEAN NOT NULL DEFAULT FALSE
+    ) WITHOUT ROWID;";
+
+/// What the scan phase found: the files at every location, and the state
+/// recorded for them at the last sync. Kept in a private temporary SQLite
+/// database, which spills to disk, so a sync doesn't hold every path of the
+/// repository in memory; planning reads it back `PAGE_SIZE` paths at a time.
+#[derive(Debug)]
+pub struct Snapshots {
+    conn: Mutex<Connection>,
+}
+
+/// A scanned path with its copy at every location.
+#[derive(Debug)]
+pub struct ScannedPath {
+    pub path: PathBuf,
+    pub local: Option<FileSnapshot>,
+    pub gdrive: Option<FileSnapshot>,
+    pub smb: Option<FileSnapshot>,
+}
+
+/// Orders paths the way `Path` does, component by component: the separator
+/// sorts before every other character.
+fn sort_key(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\u{1}")
+}
+
+/// File names compared case-insensitively, for revision lookups.
+fn name_key(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_ascii_lowercase()
+}
+
+fn snapshot_from_row(row: &rusqlite::Row, offset: usize) -> rusqlite::Result<FileSnapshot> {
+    Ok(FileSnapshot {
+        location: row.get::<_, String>(offset)?.parse().unwrap_or(FileLocation::Local),
+        hash: row.get(offset + 1)?,
+        fingerprint: row.get(offset + 2)?,
+        size: row.get::<_, i64>(offset + 3)? as u64,
+        modified: row.get(offset + 4)?,
+    })
+}
+
+impl Snapshots {
+    pub fn new() -> Result<Self> {
+        // An empty name opens a temporary database that is deleted when closed
+        let conn = Connection::open("")?;
+        conn.execute_batch(SNAPSHOT_TABLES)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add the files scanned at one location. A path listed twice is recorded
+    /// as a duplicate. Returns the number of distinct paths added.
+    pub fn insert_all(&self, files: impl IntoIterator<Item = (PathBuf, FileSnapshot)>) -> Result<usize> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        let mut count = 0;
+        {
+            let mut exists = tx.prepare("SELECT 1 FROM files WHERE sort_key = ?1 AND location = ?2")?;
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO files (sort_key, location, path, name_key, hash, fingerprint, size, modified)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+            )?;
+            let mut duplicate = tx.prepare("INSERT OR IGNORE INTO duplicates (sort_key, path) VALUES (?1, ?2)")?;
+            for (path, snapshot) in files {
+                let key = sort_key(&path);
+                let path_str = path.to_string_lossy();
+                if exists.exists(rusqlite::params![key, snapshot.location.as_str()])? {
+                    duplicate.execute(rusqlite::params![key, path_str])?;
+                } else {
+                    count += 1;
+                }
+                insert.execute(rusqlite::params![
+                    key, snapshot.location.as_str(), path_str, name_key(&path),
+                    snapshot.hash, snapshot.fingerprint, snapshot.size as i64, snapshot.modified,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Record the last known state from rows of `file_states`.
+    pub fn record_last_known(&self, states: &[FileState]) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        for state in states {
+            let offloaded = state.location == FileLocation::Local && state.status == SyncStatus::Offloaded;
+            let (column, value): (&str, &dyn rusqlite::ToSql) = match state.location {
+                FileLocation::Local if offloaded => ("local_offloaded", &true),
+                FileLocation::Local => ("local", &state.content_hash),
+                FileLocation::GoogleDrive => ("gdrive", &state.content_hash),
+                FileLocation::Smb => ("smb", &state.content_hash),
+            };
+            tx.prepare_cached(&format!(
+                "INSERT INTO last_known (sort_key, {0}) VALUES (?1, ?2)
+                 ON CONFLICT(sort_key) DO UPDATE SET {0} = excluded.{0}",
+                column
+            ))?
+            .execute(rusqlite::params![sort_key(Path::new(&state.file_path)), value])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get(&self, location: &FileLocation, path: &Path) -> Result<Option<FileSnapshot>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT location, hash, fingerprint, size, modified FROM files WHERE sort_key = ?1 AND location = ?2"
+        )?;
+        Ok(stmt.query_row(rusqlite::params![sort_key(path), location.as_str()], |row| snapshot_from_row(row, 0)).optional()?)
+    }
+
+    pub fn contains(&self, location: &FileLocation, path: &Path) -> Result<bool> {
+        Ok(self.get(location, path)?.is_some())
+    }
+
+    pub fn last_known(&self, path: &Path) -> Result<Option<LastKnownState>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached("SELECT local, gdrive, smb, local_offloaded FROM last_known WHERE sort_key = ?1")?;
+        Ok(stmt.query_row([sort_key(path)], |row| Ok(LastKnownState {
+            local: row.get(0)?,
+            gdrive: row.get(1)?,
+            smb: row.get(2)?,
+            local_offloaded: row.get(3)?,
+        })).optional()?)
+    }
+
+    /// Paths listed more than once by a provider, e.g. same-named files in a Drive folder.
+    pub fn duplicates(&self) -> Result<Vec<PathBuf>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached("SELECT path FROM duplicates ORDER BY sort_key")?;
+        let paths = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .map(|path| path.map(PathBuf::from))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    pub fn is_duplicate(&self, path: &Path) -> Result<bool> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached("SELECT 1 FROM duplicates WHERE sort_key = ?1")?;
+        Ok(stmt.exists([sort_key(path)])?)
+    }
+
+    /// Up to `PAGE_SIZE` scanned paths after `after`, in path order.
+    pub fn page(&self, after: Option<&Path>) -> Result<Vec<ScannedPath>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "WITH page AS (SELECT DISTINCT sort_key FROM files WHERE sort_key > ?1 ORDER BY sort_key LIMIT ?2)
+             SELECT files.sort_key, files.path, files.location, files.hash, files.fingerprint, files.size, files.modified
+             FROM page JOIN files ON files.sort_key = page.sort_key
+             ORDER BY files.sort_key"
+        )?;
+        let mut rows = stmt.query(rusqlite::params![after.map(sort_key).unwrap_or_default(), PAGE_SIZE as i64])?;
+
+        let mut page: Vec<ScannedPath> = Vec::new();
+        let mut last_key = None;
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            if last_key.as_ref() != Some(&key) {
+                page.push(ScannedPath { path: PathBuf::from(row.get::<_, String>(1)?), local: None, gdrive: None, smb: None });
+                last_key = Some(key);
+            }
+            let snapshot = snapshot_from_row(row, 2)?;
+            let scanned = page.last_mut().expect("a path was just added");
+            match snapshot.location {
+                FileLocation::Local => scanned.local = Some(snapshot),
+                FileLocation::GoogleDrive => scanned.gdrive = Some(snapshot),
+                FileLocation::Smb => scanned.smb = Some(snapshot),
+            }
+        }
+        Ok(page)
+    }
+
+    /// Up to `PAGE_SIZE` files at `location` after `after`, in path order.
+    pub fn page_at(&self, location: &FileLocation, after: Option<&Path>) -> Result<Vec<(PathBuf, FileSnapshot)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT path, location, hash, fingerprint, size, modified FROM files
+             WHERE location = ?1 AND sort_key > ?2 ORDER BY sort_key LIMIT ?3"
+        )?;
+        let files = stmt.query_map(
+            rusqlite::params![location.as_str(), after.map(sort_key).unwrap_or_default(), PAGE_SIZE as i64],
+            |row| Ok((PathBuf::from(row.get::<_, String>(0)?), snapshot_from_row(row, 1)?)),
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(files)
+    }
+
+    /// Files at `location` whose name starts with `prefix`, ignoring ASCII case.
+    pub fn named_like(&self, location: &FileLocation, prefix: &str) -> Result<Vec<PathBuf>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT path FROM files WHERE location = ?1 AND name_key >= ?2 AND name_key < ?3"
+        )?;
+        let prefix = prefix.to_ascii_lowercase();
+        let paths = stmt.query_map(
+            rusqlite::params![location.as_str(), prefix, format!("{}{}", prefix, char::MAX)],
+            |row| row.get::<_, String>(0),
+        )?
+        .map(|path| path.map(PathBuf::from))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+}
+
+/// The content hash of a file at each location as of the last sync.
+#[derive(Debug, Clone, Default)]
+pub struct LastKnownState {
+    pub local: Option<String>,
+    pub gdrive: Option<String>,
+    pub smb: Option<String>,
+    /// Removed locally on purpose by tiering
+    pub local_offloaded: bool,
+}
+
+impl LastKnownState {
+    pub fn at(&self, location: &FileLocation) -> Option<&String> {
+        match location {
+            FileLocation::Local => self.local.as_ref(),
+            FileLocation::GoogleDrive => self.gdrive.as_ref(),
+            FileLocation::Smb => self.smb.as_ref(),
+        }
+    }
+}
+
+/// What the planner needs to know besides the files.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanPolicies<'a> {
+    /// Whether Google Drive and the file share are configured; nothing is copied
+    /// to a location that isn't.
+    pub gdrive: bool,
+    pub smb: bool,
+    pub extensions: &'a ExtensionPolicies,
+    /// Only these paths are planned, if given.
+    pub scope: Option<&'a HashSet<PathBuf>>,
+    /// Whether files moved at one location are moved the same way at the
+    /// others, rather than deleted there and copied again.
+    pub moves: bool,
+    /// Whether a file gone from Google Drive was deleted there. Not with the
+    /// `drive.file` scope, which hides files UVCAD didn't create; they are
+    /// left alone elsewhere and copied to Drive again when next changed.
+    pub gdrive_deletions: bool,
+}
+
+/// Every path that needs something done, in path order.
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub actions: Vec<(PathBuf, SyncAction)>,
+    /// Paths in scope, including those already in sync.
+    pub total_files: usize,
+    /// Paths already in sync.
+    pub in_sync: usize,
+    /// Paths left alone because a provider listed them more than once.
+    pub duplicates: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum SyncAction {
+    NoAction,
+    Sync {
+        operations: Vec<SyncOperation>,
+        reason: ChangeReason,
+    },
+    Conflict(ConflictInfo),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOperation {
+    Upload {
+        from: FileLocation,
+        to: FileLocation,
+        path: PathBuf,
+    },
+    Delete {
+        location: FileLocation,
+        path: PathBuf,
+    },
+    /// Move the copy of `from` at `location` to `path`, following a move at
+    /// another location.
+    Move {
+        location: FileLocation,
+        from: PathBuf,
+        path: PathBuf,
+    },
+}
+
+impl SyncOperation {
+    /// Copies and moves before deletions, then by location.
+    pub fn order_key(&self) -> (u8, u8) {
+        match self {
+            SyncOperation::Upload { to, .. } => (0, location_rank(to)),
+            SyncOperation::Move { location, .. } => (0, location_rank(location)),
+            SyncOperation::Delete { location, .. } => (1, location_rank(location)),
+        }
+    }
+
+    pub fn planned(&self) -> PlannedOperation {
+        match self {
+            SyncOperation::Upload { from, to, .. } => PlannedOperation::Copy { from: from.clone(), to: to.clone() },
+            SyncOperation::Delete { location, .. } => PlannedOperation::Delete { at: location.clone() },
+            SyncOperation::Move { location, from, .. } => PlannedOperation::Move {
+                at: location.clone(),
+                from: from.to_string_lossy().to_string(),
+            },
+        }
+    }
+}
+
+pub fn location_rank(location: &FileLocation) -> u8 {
+    match location {
+        FileLocation::Local => 0,
+        FileLocation::GoogleDrive => 1,
+        FileLocation::Smb => 2,
+    }
+}
+
+/// Decide what to do with every scanned path by comparing each location with the
+/// last known state, a page of paths at a time. Depends on nothing but its arguments.
+pub fn plan(snapshots: &Snapshots, policies: &PlanPolicies) -> Result<Plan> {
+    let mut plan = Plan::default();
+
+    let mut after: Option<PathBuf> = None;
+    loop {
+        let page = snapshots.page(after.as_deref())?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = Some(last.path.clone());
+
+        for scanned in page {
+            let path = scanned.path.as_path();
+            if !policies.scope.is_none_or(|scope| scope.contains(path)) {
+                continue;
+            }
+            plan.total_files += 1;
+            // Which copy a duplicated path refers to is ambiguous; leave it alone until resolved
+            if snapshots.is_duplicate(path)? {
+                plan.duplicates.push(path.to_path_buf());
+                continue;
+            }
+
+            let local = scanned.local.as_ref();
+            let last_known = snapshots.last_known(path)?;
+            let last_known = last_known.as_ref();
+            let mut action = determine_sync_action(path, local, scanned.gdrive.as_ref(), scanned.smb.as_ref(), last_known, policies);
+
+            // Local-only files are never propagated in either direction
+            if policies.extensions.applies(path, ExtensionBehavior::LocalOnly) {
+                action = SyncAction::NoAction;
+            }
+
+            // Offloaded files stay online-only: never pull them back down
+            if local.is_none() && last_known.is_some_and(|s| s.local_offloaded) {
+                if let SyncAction::Sync { operations, .. } = &mut action {
+                    operations.retain(|op| !matches!(op, SyncOperation::Upload { to: FileLocation::Local, .. }));
+                    if operations.is_empty() {
+                        action = SyncAction::NoAction;
+                    }
+                }
+            }
+            if let SyncAction::Sync { operations, .. } = &mut action {
+                operations.sort_by_key(SyncOperation::order_key);
+            }
+            if matches!(action, SyncAction::NoAction) {
+                tracing::debug!("No action needed for: {}", path.display());
+                plan.in_sync += 1;
+                continue;
+            }
+            plan.actions.push((scanned.path, action));
+        }
+    }
+
+    if policies.moves {
+        pair_moves(&mut plan.actions, snapshots, policies)?;
+    }
+    Ok(plan)
+}
+
+/// Turn a file deleted at one location and a new one with the same content
+/// there into moves at the other locations: the old copy is moved to the new
+/// path instead of being deleted and the file copied again, so renaming a
+/// folder neither re-uploads it nor counts towards the deletion limits.
+/// Content is only compared within a location, as locations hash differently.
+/// Where several deleted and new files share content, they are paired by name.
+fn pair_moves(
+    actions: &mut Vec<(PathBuf, SyncAction)>,
+    snapshots: &Snapshots,
+    policies: &PlanPolicies,
+) -> Result<()> {
+    type Candidates = HashMap<(FileLocation, String), Vec<usize>>;
+    let mut deleted: Candidates = HashMap::new();
+    let mut added: Candidates = HashMap::new();
+    for (index, (path, action)) in actions.iter().enumerate() {
+        let SyncAction::Sync { reason, .. } = action else {
+            continue;
+        };
+        let (candidates, at, hash) = match reason {
+            ChangeReason::Deleted { at } => (&mut deleted, at, snapshots.last_known(path)?.and_then(|s| s.at(at).cloned())),
+            ChangeReason::Added { at } => (&mut added, at, snapshots.get(at, path)?.and_then(|s| s.hash)),
+            _ => continue,
+        };
+        if let Some(hash) = hash {
+            candidates.entry((at.clone(), hash)).or_default().push(index);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (key, new_files) in &added {
+        let Some(old_files) = deleted.get(key) else {
+            continue;
+        };
+        if let ([old], [new]) = (old_files.as_slice(), new_files.as_slice()) {
+            pairs.push((key.0.clone(), *old, *new));
+            continue;
+        }
+        let name = |index: usize| actions[index].0.file_name();
+        let unique = |files: &[usize], index: usize| files.iter().filter(|&&other| name(other) == name(index)).count() == 1;
+        for &new in new_files.iter().filter(|&&new| unique(new_files, new)) {
+            if let Some(&old) = old_files.iter().find(|&&old| name(old) == name(new) && unique(old_files, old)) {
+                pairs.push((key.0.clone(), old, new));
+            }
+        }
+    }
+
+    for (at, old, new) in pairs {
+        let old_path = actions[old].0.clone();
+        let new_path = actions[new].0.clone();
+        let mut moved = Vec::new();
+        if let SyncAction::Sync { operations, reason } = &mut actions[new].1 {
+            for operation in operations.iter_mut() {
+                let SyncOperation::Upload { to, .. } = operation else {
+                    continue;
+                };
+                let to = to.clone();
+                let movable = snapshots.contains(&to, &old_path)?
+                    && !snapshots.contains(&to, &new_path)?
+                    && policies.extensions.is_compressed_at(&old_path, &to) == policies.extensions.is_compressed_at(&new_path, &to);
+                if movable {
+                    *operation = SyncOperation::Move { location: to.clone(), from: old_path.clone(), path: new_path.clone() };
+                    moved.push(to);
+                }
+            }
+            if !moved.is_empty() {
+                *reason = ChangeReason::Moved { at: at.clone(), from: old_path.to_string_lossy().to_string() };
+            }
+        }
+        if let SyncAction::Sync { operations, .. } = &mut actions[old].1 {
+            operations.retain(|op| !matches!(op, SyncOperation::Delete { location, .. } if moved.contains(location)));
+        }
+    }
+
+    // Files whose every copy was moved have nothing left to do themselves
+    actions.retain(|(_, action)| !matches!(action, SyncAction::Sync { operations, .. } if operations.is_empty()));
+    Ok(())
+}
+
+fn determine_sync_action(
+    path: &Path,
+    local: Option<&FileSnapshot>,
+    gdrive: Option<&FileSnapshot>,
+    smb: Option<&FileSnapshot>,
+    last_known: Option<&LastKnownState>,
+    policies: &PlanPolicies,
+) -> SyncAction {
+    // Three-way merge logic
+    // Compare current state with last known state to detect changes
+
+    let local_changed = has_changed(local, last_known.and_then(|s| s.local.as_ref()));
+    let gdrive_changed = has_changed(gdrive, last_known.and_then(|s| s.gdrive.as_ref()))
+        && (gdrive.is_some() || policies.gdrive_deletions);
+    let smb_changed = has_changed(smb, last_known.and_then(|s| s.smb.as_ref()));
+
+    tracing::debug!("File: {} - local_changed={}, gdrive_changed={}, smb_changed={}",
+                   path.display(), local_changed, gdrive_changed, smb_changed);
+
+    // No changes anywhere
+    if !local_changed && !gdrive_changed && !smb_changed {
+        return SyncAction::NoAction;
+    }
+
+    // Conflict: Multiple locations changed
+    let change_count = [local_changed, gdrive_changed, smb_changed].iter().filter(|&&c| c).count();
+    if change_count > 1 {
+        // Check if changes are identical by comparing all changed locations' hashes.
+        // Note: Cross-provider hash comparison (e.g. SHA-256 vs MD5) will never match,
+        // so we compare modification times as a fallback heuristic for cross-provider
+        // "same content" detection. If only one hash algorithm is used across all
+        // providers, hash comparison works directly.
+        let changed_snapshots: Vec<&FileSnapshot> = [
+            local.filter(|_| local_changed),
+            gdrive.filter(|_| gdrive_changed),
+            smb.filter(|_| smb_changed),
+        ].into_iter().flatten().collect();
+
+        let all_same = if changed_snapshots.len() >= 2 {
+            let first = &changed_snapshots[0];
+            changed_snapshots[1..].iter().all(|s| {
+                // Compare hashes if both are Some and non-empty
+                match (&first.hash, &s.hash) {
+                    (Some(h1), Some(h2)) if h1.len() == h2.len() => h1 == h2,
+                    _ => false, // Different hash algorithms or missing hashes — treat as conflict
+                }
+            })
+        } else {
+            false
+        };
+
+        if all_same {
+            // Same content across all changed locations, not a conflict
+            return sync_action(sync_to_missing(path, local, gdrive, smb, policies), ChangeReason::SameChange);
+        }
+
+        return SyncAction::Conflict(ConflictInfo {
+            file_path: path.to_string_lossy().to_string(),
+            local_hash: local.and_then(|f| f.hash.clone()),
+            gdrive_hash: gdrive.and_then(|f| f.hash.clone()),
+            smb_hash: smb.and_then(|f| f.hash.clone()),
+        });
+    }
+
+    // Single location changed - propagate to others
+    let (operations, at, current, known) = if local_changed {
+        (sync_from_local(path, local, gdrive, smb, policies), FileLocation::Local, local, last_known.and_then(|s| s.local.as_ref()))
+    } else if gdrive_changed {
+        (sync_from_gdrive(path, local, gdrive, smb, policies), FileLocation::GoogleDrive, gdrive, last_known.and_then(|s| s.gdrive.as_ref()))
+    } else {
+        (sync_from_smb(path, local, gdrive, smb, policies), FileLocation::Smb, smb, last_known.and_then(|s| s.smb.as_ref()))
+    };
+    let reason = match (current, known) {
+        (Some(_), None) => ChangeReason::Added { at },
+        (Some(_), Some(_)) => ChangeReason::Modified { at },
+        (None, _) => ChangeReason::Deleted { at },
+    };
+    sync_action(operations, reason)
+}
+
+fn sync_action(operations: Vec<SyncOperation>, reason: ChangeReason) -> SyncAction {
+    if operations.is_empty() {
+        SyncAction::NoAction
+    } else {
+        SyncAction::Sync { operations, reason }
+    }
+}
+
+fn has_changed(current: Option<&FileSnapshot>, last_known: Option<&String>) -> bool {
+    match (current, last_known) {
+        (Some(curr), Some(known)) => {
+            // File exists now and existed before - check if hash changed
+            curr.hash.as_ref() != Some(known)
+        }
+        (Some(_), None) => {
+            // File exists now but didn't before - it's new
+            true
+        }
+        (None, Some(_)) => {
+            // File doesn't exist now but did before - it was deleted
+            true
+        }
+        (None, None) => {
+            // File doesn't exist now and didn't before - no change
+            false
+        }
+    }
+}
+
+/// Check if the destination is missing or has a different hash than the source.
+fn needs_update(source: &FileSnapshot, dest: Option<&FileSnapshot>) -> bool {
+    match dest {
+        None => true, // destination missing
+        Some(d) => source.hash != d.hash, // destination exists but hash differs
+    }
+}
+
+fn sync_from_local(path: &Path, local: Option<&FileSnapshot>,
+                   gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>, policies: &PlanPolicies) -> Vec<SyncOperation> {
+    let mut operations = Vec::new();
+
+    if let Some(local_file) = local {
+        // Local file exists - sync to other locations (missing OR stale)
+        if policies.gdrive && needs_update(local_file, gdrive) {
+            operations.push(SyncOperation::Upload {
+                from: FileLocation::Local,
+                to: FileLocation::GoogleDrive,
+                path: path.to_path_buf(),
+            });
+        }
+        if policies.smb && needs_update(local_file, smb) {
+            operations.push(SyncOperation::Upload {
+                from: FileLocation::Local,
+                to: FileLocation::Smb,
+                path: path.to_path_buf(),
+            });
+        }
+    } else {
+        // Local file deleted - delete from other locations
+        if gdrive.is_some() {
+            operations.push(SyncOperation::Delete {
+                location: FileLocation::GoogleDrive,
+                path: path.to_path_buf(),
+            });
+        }
+        if smb.is_some() {
+            operations.push(SyncOperation::Delete {
+                location: FileLocation::Smb,
+                path: path.to_path_buf(),
+            });
+        }
+    }
+
+    operations
+}
+
+fn sync_from_gdrive(path: &Path, local: Option<&FileSnapshot>,
+                    gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>, policies: &PlanPolicies) -> Vec<SyncOperation> {
+    let mut operations = Vec::new();
+
+    if let Some(gdrive_file) = gdrive {
+        // Google Drive file exists - sync to other locations (missing OR stale)
+        if needs_update(gdrive_file, local) {
+            operations.push(SyncOperation::Upload {
+                from: FileLocation::GoogleDrive,
+                to: FileLocation::Local,
+                path: path.to_path_buf(),
+            });
+        }
+        if policies.smb && needs_update(gdrive_file, smb) {
+            operations.push(SyncOperation::Upload {
+                from: FileLocation::GoogleDrive,
+                to: FileLocation::Smb,
+                path: path.to_path_buf(),
+            });
+        }
+    } else {
+        // Google Drive file deleted - delete from other locations
+        if local.is_some() {
+            operations.push(SyncOperation::Delete {
+                location: FileLocation::Local,
+                path: path.to_path_buf(),
+            });
+        }
+        if smb.is_some() {
+            operations.push(SyncOperation::Delete {
+                location: FileLocation::Smb,
+                path: path.to_path_buf(),
+            });
+        }
+    }
+
+    operations
+}
+
+fn sync_from_smb(path: &Path, local: Option<&FileSnapshot>,
+                 gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>, policies: &PlanPolicies) -> Vec<SyncOperation> {
+    let mut operations = Vec::new();
+
+    if let Some(smb_file) = smb {
+        // Samba file exists - sync to other locations (missing OR stale)
+        if needs_update(smb_file, local) {
+            operations.push(SyncOperation::Upload {
+                from: FileLocation::Smb,
+                to: FileLocation::Local,
+                path: path.to_path_buf(),
+            });
+        }
+        if policies.gdrive && needs_update(smb_file, gdrive) {
+            operations.push(SyncOperation::Upload {
+                from: FileLocation::Smb,
+                to: FileLocation::GoogleDrive,
+                path: path.to_path_buf(),
+            });
+        }
+    } else {
+        // Samba file deleted - delete from other locations
+        if local.is_some() {
+            operations.push(SyncOperation::Delete {
+                location: FileLocation::Local,
+                path: path.to_path_buf(),
+            });
+        }
+        if gdrive.is_some() {
+            operations.push(SyncOperation::Delete {
+                location: FileLocation::GoogleDrive,
+                path: path.to_path_buf(),
+            });
+        }
+    }
+
+    operations
+}
+
+fn sync_to_missing(path: &Path, local: Option<&FileSnapshot>,
+                   gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>, policies: &PlanPolicies) -> Vec<SyncOperation> {
+    let mut operations = Vec::new();
+
+    // If we have the file in at least one location, sync to missing locations
+    let Some(source) = local.or(gdrive).or(smb) else {
+        return operations;
+    };
+    let source_location = source.location.clone();
+
+    if local.is_none() && source_location != FileLocation::Local {
+        operations.push(SyncOperation::Upload {
+            from: source_location.clone(),
+            to: FileLocation::Local,
+            path: path.to_path_buf(),
+        });
+    }
+
+    if policies.gdrive && gdrive.is_none() && source_location != FileLocation::GoogleDrive {
+        operations.push(SyncOperation::Upload {
+            from: source_location.clone(),
+            to: FileLocation::GoogleDrive,
+            path: path.to_path_buf(),
+        });
+    }
+
+    if policies.smb && smb.is_none() && source_location != FileLocation::Smb {
+        operations.push(SyncOperation::Upload {
+            from: source_location.clone(),
+            to: FileLocation::Smb,
+            path: path.to_path_buf(),
+        });
+    }
+
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FileLocation::*;
+
+    /// A path at one location compared with the last sync.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Change {
+        Absent,
+        Unchanged,
+        Added,
+        Modified,
+        Deleted,
+    }
+
+    const CHANGES: [Change; 5] = [Change::Absent, Change::Unchanged, Change::Added, Change::Modified, Change::Deleted];
+    const LOCATIONS: [FileLocation; 3] = [Local, GoogleDrive, Smb];
+
+    fn snapshot(location: &FileLocation, hash: &str) -> FileSnapshot {
+        FileSnapshot { hash: Some(hash.to_string()), fingerprint: None, size: 1, modified: chrono::Utc::now(), location: location.clone() }
+    }
+
+    fn insert(snapshots: &Snapshots, path: &str, location: &FileLocation, hash: &str) {
+        snapshots.insert_all([(PathBuf::from(path), snapshot(location, hash))]).unwrap();
+    }
+
+    fn known(path: &str, location: &FileLocation, hash: &str) -> FileState {
+        FileState { content_hash: Some(hash.to_string()), ..FileState::new(1, path.to_string(), location.clone()) }
+    }
+
+    /// Snapshots and last known state of `part.step` with the given change at
+    /// each location. New content differs between locations.
+    fn scenario(changes: [Change; 3]) -> Snapshots {
+        let snapshots = Snapshots::new().unwrap();
+        for (location, change) in LOCATIONS.iter().zip(changes) {
+            let current = match change {
+                Change::Unchanged => Some("old".to_string()),
+                Change::Added | Change::Modified => Some(format!("new at {}", location.as_str())),
+                Change::Absent | Change::Deleted => None,
+            };
+            if let Some(hash) = current {
+                insert(&snapshots, "part.step", location, &hash);
+            }
+            if matches!(change, Change::Unchanged | Change::Modified | Change::Deleted) {
+                snapshots.record_last_known(&[known("part.step", location, "old")]).unwrap();
+            }
+        }
+        snapshots
+    }
+
+    fn policies(extensions: &ExtensionPolicies) -> PlanPolicies<'_> {
+        PlanPolicies { gdrive: true, smb: true, extensions, scope: None, moves: true, gdrive_deletions: true }
+    }
+
+    /// The operations planned for the single path, or None for a conflict.
+    fn operations(plan: &Plan) -> Option<Vec<SyncOperation>> {
+        match plan.actions.first() {
+            None => Some(Vec::new()),
+            Some((_, SyncAction::Sync { operations, .. })) => Some(operations.clone()),
+            Some((_, SyncAction::Conflict(_))) => None,
+            Some((_, SyncAction::NoAction)) => unreachable!("paths in sync are only counted"),
+        }
+    }
+
+    fn upload(from: FileLocation, to: FileLocation) -> SyncOperation {
+        SyncOperation::Upload { from, to, path: PathBuf::from("part.step") }
+    }
+
+    fn delete(location: FileLocation) -> SyncOperation {
+        SyncOperation::Delete { location, path: PathBuf::from("part.step") }
+    }
+
+    #[test]
+    fn test_every_combination_of_changes() {
+        let extensions = ExtensionPolicies::default();
+        for local in CHANGES {
+            for gdrive in CHANGES {
+                for smb in CHANGES {
+                    let changes = [local, gdrive, smb];
+                    let plan = plan(&scenario(changes), &policies(&extensions)).unwrap();
+                    // Only scanned paths are planned, so one gone everywhere is not seen
+                    if changes.iter().all(|c| matches!(c, Change::Absent | Change::Deleted)) {
+                        assert_eq!(plan.total_files, 0);
+                        continue;
+                    }
+                    assert_eq!(plan.total_files, 1);
+
+                    let changed: Vec<usize> = (0..3)
+                        .filter(|&i| !matches!(changes[i], Change::Absent | Change::Unchanged))
+                        .collect();
+                    let expected = match changed[..] {
+                        [] => Some(Vec::new()),
+                        // A change at one location goes to the others
+                        [from] if changes[from] == Change::Deleted => Some((0..3)
+                            .filter(|&i| changes[i] == Change::Unchanged)
+                            .map(|i| delete(LOCATIONS[i].clone()))
+                            .collect()),
+                        [from] => Some((0..3)
+                            .filter(|&i| i != from)
+                            .map(|i| upload(LOCATIONS[from].clone(), LOCATIONS[i].clone()))
+                            .collect()),
+                        // Different changes at several locations always conflict
+                        _ => None,
+                    };
+                    assert_eq!(operations(&plan), expected, "changes {:?}", changes);
+                    assert_eq!(plan.in_sync, usize::from(expected == Some(Vec::new())), "changes {:?}", changes);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_policies_and_special_cases() {
+        use Change::*;
+        let extensions = ExtensionPolicies::from_config(Some(".step = local-only")).unwrap();
+        let default = ExtensionPolicies::default();
+        let unconfigured_smb = PlanPolicies { smb: false, ..policies(&default) };
+        let drive_file_scope = PlanPolicies { gdrive_deletions: false, ..policies(&default) };
+
+        type Case<'a> = (&'a str, [Change; 3], PlanPolicies<'a>, Option<Vec<SyncOperation>>);
+        let cases: Vec<Case> = vec![
+            ("nothing goes to an unconfigured share", [Added, Absent, Absent], unconfigured_smb, Some(vec![upload(Local, GoogleDrive)])),
+            ("a drive change still reaches the local folder", [Unchanged, Modified, Absent], unconfigured_smb, Some(vec![upload(GoogleDrive, Local)])),
+            ("local-only files are not propagated", [Added, Absent, Absent], policies(&extensions), Some(Vec::new())),
+            ("local-only files are not pulled down", [Absent, Added, Absent], policies(&extensions), Some(Vec::new())),
+            ("files hidden from drive.file are not deleted", [Unchanged, Deleted, Unchanged], drive_file_scope, Some(Vec::new())),
+            ("a file hidden from drive.file is copied there when changed", [Modified, Deleted, Unchanged], drive_file_scope, Some(vec![upload(Local, GoogleDrive), upload(Local, Smb)])),
+        ];
+        for (name, changes, policies, expected) in cases {
+            assert_eq!(operations(&plan(&scenario(changes), &policies).unwrap()), expected, "{}", name);
+        }
+
+        // The same new content in two places is copied to the third, not a conflict
+        let snapshots = scenario([Added, Absent, Absent]);
+        insert(&snapshots, "part.step", &GoogleDrive, "new at local");
+        assert_eq!(operations(&plan(&snapshots, &policies(&default)).unwrap()), Some(vec![upload(Local, Smb)]));
+
+        // Each plan says which change it follows from
+        let reason = |changes| {
+            match plan(&scenario(changes), &policies(&default)).unwrap().actions.first() {
+                Some((_, SyncAction::Sync { reason, .. })) => reason.clone(),
+                other => panic!("expected a sync, got {:?}", other),
+            }
+        };
+        assert_eq!(reason([Unchanged, Deleted, Unchanged]), ChangeReason::Deleted { at: GoogleDrive });
+        assert_eq!(reason([Modified, Unchanged, Unchanged]), ChangeReason::Modified { at: Local });
+        assert_eq!(reason([Absent, Absent, Added]), ChangeReason::Added { at: Smb });
+
+        // Offloaded files stay online-only
+        let snapshots = scenario([Absent, Modified, Unchanged]);
+        let offloaded = FileState { status: SyncStatus::Offloaded, ..FileState::new(1, "part.step".to_string(), Local) };
+        snapshots.record_last_known(&[offloaded]).unwrap();
+        assert_eq!(operations(&plan(&snapshots, &policies(&default)).unwrap()), Some(vec![upload(GoogleDrive, Smb)]));
+
+        // Duplicated and out-of-scope paths are not planned
+        let snapshots = scenario([Added, Absent, Absent]);
+        insert(&snapshots, "part.step", &Local, "another part");
+        let duplicated = plan(&snapshots, &policies(&default)).unwrap();
+        assert_eq!((duplicated.actions.len(), duplicated.duplicates.len()), (0, 1));
+        let scope = HashSet::from([PathBuf::from("other.step")]);
+        let scoped = plan(&snapshots, &PlanPolicies { scope: Some(&scope), ..policies(&default) }).unwrap();
+        assert_eq!((scoped.total_files, scoped.actions.len()), (0, 0));
+    }
+
+    #[test]
+    fn test_pages_cover_every_path_in_path_order() {
+        let snapshots = Snapshots::new().unwrap();
+        let mut paths: Vec<PathBuf> = (0..PAGE_SIZE + 10).map(|i| PathBuf::from(format!("parts/{:05}.step", i))).collect();
+        // A folder comes before names that merely start with it
+        paths.extend([PathBuf::from("a-b.step"), PathBuf::from("a/b.step")]);
+        snapshots.insert_all(paths.iter().map(|path| (path.clone(), snapshot(&Local, "h")))).unwrap();
+
+        let plan = plan(&snapshots, &policies(&ExtensionPolicies::default())).unwrap();
+        paths.sort();
+        assert_eq!(plan.total_files, paths.len());
+        assert!(plan.actions.iter().map(|(path, _)| path).eq(paths.iter()));
+    }
+
+    #[test]
+    fn test_renamed_folder_is_moved_at_the_other_locations() {
+        // Two identical parts, renamed from asm/ to assembly/ locally; the share
+        // never got the second one
+        let snapshots = Snapshots::new().unwrap();
+        for name in ["a.step", "b.step"] {
+            let (old, new) = (format!("asm/{}", name), format!("assembly/{}", name));
+            insert(&snapshots, &new, &Local, "h");
+            insert(&snapshots, &old, &GoogleDrive, "g");
+            snapshots.record_last_known(&[known(&old, &Local, "h"), known(&old, &GoogleDrive, "g")]).unwrap();
+            if name == "a.step" {
+                insert(&snapshots, &old, &Smb, "s");
+                snapshots.record_last_known(&[known(&old, &Smb, "s")]).unwrap();
+            }
+        }
+        let extensions = ExtensionPolicies::default();
+
+        let moved = plan(&snapshots, &policies(&extensions)).unwrap();
+        let planned: Vec<(&Path, &[SyncOperation], &ChangeReason)> = moved.actions.iter()
+            .map(|(path, action)| match action {
+                SyncAction::Sync { operations, reason } => (path.as_path(), operations.as_slice(), reason),
+                other => panic!("expected a sync, got {:?}", other),
+            })
+            .collect();
+        let movement = |location: FileLocation, name: &str| SyncOperation::Move {
+            location,
+            from: PathBuf::from("asm").join(name),
+            path: PathBuf::from("assembly").join(name),
+        };
+        let reason = |name: &str| ChangeReason::Moved { at: Local, from: format!("asm/{}", name) };
+        assert_eq!(planned, [
+            (Path::new("assembly/a.step"), &[movement(GoogleDrive, "a.step"), movement(Smb, "a.step")][..], &reason("a.step")),
+            (
+                Path::new("assembly/b.step"),
+                &[movement(GoogleDrive, "b.step"), SyncOperation::Upload { from: Local, to: Smb, path: PathBuf::from("assembly/b.step") }][..],
+                &reason("b.step"),
+            ),
+        ]);
+
+        // Without moves the old copies are deleted and the files copied again
+        let copied = plan(&snapshots, &PlanPolicies { moves: false, ..policies(&extensions) }).unwrap();
+        let deletions = copied.actions.iter()
+            .filter(|(_, action)| matches!(action, SyncAction::Sync { reason: ChangeReason::Deleted { .. }, .. }))
+            .count();
+        assert_eq!((copied.actions.len(), deletions), (4, 2));
+    }
+}
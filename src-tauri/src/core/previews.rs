@@ -0,0 +1,205 @@
+use crate::core::extension_policy::{ExtensionBehavior, ExtensionPolicies};
+use crate::core::mesh::{self, Mesh, MeshInfo};
+use crate::utils::error::{Result, UvcadError};
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+/// Image formats a preview can be cached as.
+const PREVIEW_FORMATS: &[&str] = &["png", "bmp"];
+
+/// Suffix of the cached mesh summary stored next to a mesh thumbnail.
+const MESH_INFO_SUFFIX: &str = "mesh.json";
+
+/// Marks the start of the preview section in a DWG file (R13 and later).
+const DWG_IMAGE_SENTINEL: [u8; 16] = [
+    0x1F, 0x25, 0x6D, 0x07, 0xD4, 0x36, 0x28, 0x28, 0x9D, 0x57, 0xCA, 0x3F, 0x9D, 0x44, 0x10, 0x2B,
+];
+
+/// An image ready to be written to the thumbnail cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    /// File extension of the image, one of `PREVIEW_FORMATS`.
+    pub format: &'static str,
+    pub data: Vec<u8>,
+    /// Triangle count and bounds, for mesh files.
+    pub mesh: Option<MeshInfo>,
+}
+
+/// Extract a preview image from a file, if its format embeds or supports one.
+pub fn extract_preview(path: &Path) -> Result<Option<Preview>> {
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "dwg" => Ok(parse_dwg_preview(&std::fs::read(path)?)),
+        e if mesh::MESH_EXTENSIONS.contains(&e) => {
+            let mesh = Mesh::load(path)?;
+            Ok(Some(Preview { format: "bmp", data: mesh.render_thumbnail(), mesh: Some(mesh.info()) }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Read the thumbnail AutoCAD embeds in DWG files. Prefers PNG (2013+) over BMP;
+/// WMF previews are ignored.
+fn parse_dwg_preview(data: &[u8]) -> Option<Preview> {
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    };
+
+    let image_start = read_u32(0x0D)?;
+    if data.get(image_start..image_start + 16)? != DWG_IMAGE_SENTINEL {
+        return None;
+    }
+
+    // Sentinel, overall size (4 bytes), entry count (1 byte), then 9-byte entries
+    let entry_count = *data.get(image_start + 20)? as usize;
+    let mut bmp = None;
+    for index in 0..entry_count {
+        let entry = image_start + 21 + index * 9;
+        let code = *data.get(entry)?;
+        let start = read_u32(entry + 1)?;
+        let size = read_u32(entry + 5)?;
+        let image = data.get(start..start.checked_add(size)?)?;
+
+        match code {
+            6 => return Some(Preview { format: "png", data: image.to_vec(), mesh: None }),
+            2 => bmp = bmp_from_dib(image),
+            _ => {}
+        }
+    }
+
+    bmp.map(|data| Preview { format: "bmp", data, mesh: None })
+}
+
+/// DWG stores a bare DIB; prepend the 14-byte BITMAPFILEHEADER to make a .bmp.
+fn bmp_from_dib(dib: &[u8]) -> Option<Vec<u8>> {
+    let header_size = u32::from_le_bytes(dib.get(0..4)?.try_into().ok()?);
+    let bit_count = u16::from_le_bytes(dib.get(14..16)?.try_into().ok()?);
+    let colors_used = u32::from_le_bytes(dib.get(32..36)?.try_into().ok()?);
+    let palette_entries = match (colors_used, bit_count) {
+        (0, bits) if bits <= 8 => 1u32 << bits,
+        (used, _) => used,
+    };
+    let pixel_offset = 14 + header_size + palette_entries * 4;
+
+    let mut bmp = Vec::with_capacity(dib.len() + 14);
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&((dib.len() + 14) as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&pixel_offset.to_le_bytes());
+    bmp.extend_from_slice(dib);
+    Some(bmp)
+}
+
+/// Folder holding the cached previews of a profile, mirroring its local tree.
+pub fn thumbnail_root(profile_id: i64) -> Result<PathBuf> {
+    let project_dirs = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| UvcadError::InvalidConfig("Failed to get project directory".to_string()))?;
+    Ok(project_dirs.data_dir().join("thumbnails").join(profile_id.to_string()))
+}
+
+fn cache_path(root: &Path, relative: &str, format: &str) -> PathBuf {
+    let mut name = Path::new(relative).file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(format);
+    root.join(relative).with_file_name(name)
+}
+
+/// The cached preview of a file, if one exists.
+pub fn cached_thumbnail(root: &Path, relative: &str) -> Option<PathBuf> {
+    PREVIEW_FORMATS.iter()
+        .map(|format| cache_path(root, relative, format))
+        .find(|path| path.exists())
+}
+
+/// The cached summary of a mesh file, if one exists.
+pub fn cached_mesh_info(root: &Path, relative: &str) -> Option<MeshInfo> {
+    let data = std::fs::read(cache_path(root, relative, MESH_INFO_SUFFIX)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Write a preview to the cache, replacing any older one. Returns its path.
+pub fn store_thumbnail(root: &Path, relative: &str, preview: &Preview) -> Result<PathBuf> {
+    let target = cache_path(root, relative, preview.format);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Drop a stale preview, possibly in the other format
+    if let Some(stale) = cached_thumbnail(root, relative) {
+        let _ = std::fs::remove_file(stale);
+    }
+    std::fs::write(&target, &preview.data)?;
+    if let Some(info) = &preview.mesh {
+        std::fs::write(cache_path(root, relative, MESH_INFO_SUFFIX), serde_json::to_vec(info)?)?;
+    }
+    Ok(target)
+}
+
+/// Extract previews for local files whose extension has the thumbnail policy and
+/// whose cached preview is missing or older than the file. Returns how many were written.
+pub fn refresh_thumbnails<'a>(
+    root: &Path,
+    local_root: &Path,
+    policies: &ExtensionPolicies,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> Result<usize> {
+    let mut written = 0;
+
+    for relative in paths {
+        if !policies.applies(Path::new(relative), ExtensionBehavior::Thumbnail) {
+            continue;
+        }
+
+        let source = local_root.join(relative);
+        let Ok(source_modified) = std::fs::metadata(&source).and_then(|m| m.modified()) else {
+            continue;
+        };
+        let fresh = cached_thumbnail(root, relative)
+            .and_then(|cached| std::fs::metadata(cached).and_then(|m| m.modified()).ok())
+            .is_some_and(|cached_modified| cached_modified >= source_modified);
+        if fresh {
+            continue;
+        }
+
+        match extract_preview(&source) {
+            Ok(Some(preview)) => {
+                store_thumbnail(root, relative, &preview)?;
+                written += 1;
+            }
+            Ok(None) => tracing::debug!("No preview available for {}", relative),
+            Err(e) => tracing::warn!("Failed to extract preview of {}: {}", relative, e),
+        }
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dwg_png_preview() {
+        let png = b"\x89PNG\r\n\x1a\nfake".to_vec();
+        let image_start = 0x20;
+
+        let mut data = b"AC1027".to_vec();
+        data.resize(image_start, 0);
+        data[0x0D..0x11].copy_from_slice(&(image_start as u32).to_le_bytes());
+        data.extend_from_slice(&DWG_IMAGE_SENTINEL);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.push(1);
+        let png_start = (data.len() + 9) as u32;
+        data.push(6);
+        data.extend_from_slice(&png_start.to_le_bytes());
+        data.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        data.extend_from_slice(&png);
+
+        assert_eq!(parse_dwg_preview(&data), Some(Preview { format: "png", data: png, mesh: None }));
+        assert_eq!(parse_dwg_preview(b"AC1027 without a preview"), None);
+    }
+}
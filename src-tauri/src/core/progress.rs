@@ -0,0 +1,62 @@
+use crate::models::sync_phase::SyncPhase;
+use std::time::{Duration, Instant};
+
+/// Most per-file progress events sent to the UI each second.
+pub const MAX_EVENTS_PER_SECOND: u32 = 10;
+
+/// Coalesces per-file progress updates so huge runs don't flood the IPC channel.
+/// Phase changes always go out; transfer updates at most once per interval, and
+/// the last one of a run too, so the bar always reaches its end.
+pub struct ProgressThrottle {
+    interval: Duration,
+    last_sent: Option<Instant>,
+    held: usize,
+}
+
+impl ProgressThrottle {
+    pub fn new(max_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / max_per_second.max(1),
+            last_sent: None,
+            held: 0,
+        }
+    }
+
+    /// Whether to send an update now. If so, returns how many updates were held
+    /// back since the last one sent, which this one stands in for.
+    pub fn admit(&mut self, phase: &SyncPhase, processed: usize, total: usize, now: Instant) -> Option<usize> {
+        let per_file = matches!(phase, SyncPhase::Transferring { .. } | SyncPhase::Verifying);
+        let due = self.last_sent.is_none_or(|last| now.duration_since(last) >= self.interval);
+        let last_of_run = total > 0 && processed + 1 >= total;
+
+        if per_file && !due && !last_of_run {
+            self.held += 1;
+            return None;
+        }
+        self.last_sent = Some(now);
+        Some(std::mem::take(&mut self.held))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::file_state::FileLocation;
+
+    #[test]
+    fn test_transfer_updates_are_coalesced() {
+        let mut throttle = ProgressThrottle::new(10);
+        let start = Instant::now();
+        let transfer = SyncPhase::Transferring { from: Some(FileLocation::Local), to: FileLocation::Smb };
+
+        assert_eq!(throttle.admit(&transfer, 0, 1000, start), Some(0));
+        let held = (1..50).filter(|i| throttle.admit(&transfer, *i, 1000, start).is_none()).count();
+        assert_eq!(held, 49);
+
+        // Phase changes are never held back
+        assert_eq!(throttle.admit(&SyncPhase::Finalizing, 0, 0, start), Some(49));
+        assert_eq!(throttle.admit(&transfer, 50, 1000, start), None);
+        assert_eq!(throttle.admit(&transfer, 51, 1000, start + Duration::from_millis(100)), Some(1));
+        assert_eq!(throttle.admit(&transfer, 999, 1000, start + Duration::from_millis(101)), Some(0));
+    }
+}
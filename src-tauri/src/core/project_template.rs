@@ -0,0 +1,81 @@
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, FileState};
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::path::Path;
+
+/// Placeholder in template file and folder names replaced with the project name.
+const PROJECT_PLACEHOLDER: &str = "{project}";
+
+/// Copy the template folder into `local_root/<name>`, replacing `{project}` in
+/// file and folder names. Returns the relative paths of all files created.
+///
+/// Only files are synced, so template folders meant to reach every endpoint
+/// should contain at least one seed file.
+pub fn instantiate_template(template_dir: &Path, local_root: &Path, name: &str) -> Result<Vec<String>> {
+    validate_project_name(name)?;
+
+    if !template_dir.is_dir() {
+        return Err(UvcadError::InvalidConfig(format!(
+            "Project template folder does not exist: {}",
+            template_dir.display()
+        )));
+    }
+
+    let project_dir = local_root.join(name);
+    if project_dir.exists() {
+        return Err(UvcadError::InvalidConfig(format!("Project folder already exists: {}", name)));
+    }
+
+    let mut created = Vec::new();
+    copy_dir(template_dir, &project_dir, Path::new(name), name, &mut created)?;
+
+    tracing::info!("Created project {} from template with {} files", name, created.len());
+    Ok(created)
+}
+
+/// Register freshly created local files so they show up immediately. The hash is
+/// left empty so the next sync sees them as new and pushes them to every endpoint.
+pub fn register_files(conn: &Connection, profile_id: i64, local_root: &Path, paths: &[String]) -> Result<()> {
+    for path in paths {
+        let metadata = std::fs::metadata(local_root.join(path))?;
+        let mut state = FileState::new(profile_id, path.clone(), FileLocation::Local);
+        state.size_bytes = Some(metadata.len() as i64);
+        state.modified_at = metadata.modified().ok().map(DateTime::<Utc>::from);
+        DbOperations::upsert_file_state(conn, &state)?;
+    }
+    Ok(())
+}
+
+fn validate_project_name(name: &str) -> Result<()> {
+    let invalid = name.trim().is_empty()
+        || name != name.trim()
+        || name == "."
+        || name == ".."
+        || name.chars().any(|c| matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'));
+
+    if invalid {
+        return Err(UvcadError::InvalidConfig(format!("Invalid project name: '{}'", name)));
+    }
+    Ok(())
+}
+
+fn copy_dir(source: &Path, dest: &Path, relative: &Path, name: &str, created: &mut Vec<String>) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().replace(PROJECT_PLACEHOLDER, name);
+        let target = dest.join(&file_name);
+        let target_relative = relative.join(&file_name);
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target, &target_relative, name, created)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+            created.push(target_relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
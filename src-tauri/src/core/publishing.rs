@@ -0,0 +1,155 @@
+use crate::utils::command;
+use crate::utils::error::{Result, UvcadError};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Outcome of a publishing pass.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PublishResult {
+    /// Outputs written, relative to the published root.
+    pub published: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Converter command and target formats, taken from the profile.
+#[derive(Debug, Clone)]
+pub struct PublishPolicy {
+    command: String,
+    formats: HashMap<String, Vec<String>>,
+}
+
+impl PublishPolicy {
+    /// `None` when no converter is configured, i.e. publishing is disabled.
+    pub fn from_config(command: Option<&str>, formats_config: Option<&str>) -> Result<Option<Self>> {
+        let Some(command) = command.filter(|c| !c.trim().is_empty()).map(str::to_string) else {
+            return Ok(None);
+        };
+
+        let mut formats: HashMap<String, Vec<String>> = HashMap::new();
+        for line in formats_config.unwrap_or("").lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || UvcadError::InvalidConfig(format!("Invalid publish format: {}", line));
+
+            let (extension, targets) = line.split_once('=').ok_or_else(invalid)?;
+            let extension = extension.trim().trim_start_matches('.').to_lowercase();
+            let targets: Vec<String> = targets.split(',')
+                .map(|t| t.trim().trim_start_matches('.').to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            if extension.is_empty() || targets.is_empty() {
+                return Err(invalid());
+            }
+            formats.entry(extension).or_default().extend(targets);
+        }
+
+        Ok(Some(Self { command, formats }))
+    }
+
+    /// Relative output paths published for a source file, e.g. `a/b.dwg` -> `a/b.pdf`.
+    pub fn outputs_for(&self, relative: &str) -> Vec<String> {
+        let path = Path::new(relative);
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| self.formats.get(&e.to_lowercase()))
+            .map(|targets| targets.iter()
+                .map(|target| path.with_extension(target).to_string_lossy().replace('\\', "/"))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Run the converter. `{input}` and `{output}` in the command are replaced
+    /// with the paths; without placeholders both are appended in that order.
+    fn convert(&self, input: &Path, output: &Path) -> std::result::Result<(), String> {
+        let values = [("input", &*input.to_string_lossy()), ("output", &*output.to_string_lossy())];
+        let result = command::build(&self.command, &values)
+            .map_err(|e| format!("Converter could not be run: {}", e))?
+            .output()
+            .map_err(|e| format!("Converter could not be run: {}", e))?;
+
+        if !result.status.success() {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            return Err(format!("Converter failed: {}", stderr.trim()));
+        }
+        if !output.exists() {
+            return Err("Converter did not produce an output file".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Folder published files are written to: a "<project> Published" sibling of
+/// the local root, so the outputs never sync back into the project.
+pub fn published_root(local_root: &Path) -> PathBuf {
+    let name = local_root.file_name().unwrap_or_default().to_string_lossy();
+    local_root.with_file_name(format!("{} Published", name))
+}
+
+/// Convert every source whose outputs are missing or older than the source.
+pub fn publish_stale<'a>(
+    policy: &PublishPolicy,
+    local_root: &Path,
+    published_root: &Path,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> PublishResult {
+    let mut result = PublishResult::default();
+
+    for relative in paths {
+        let source = local_root.join(relative);
+        let Ok(source_modified) = std::fs::metadata(&source).and_then(|m| m.modified()) else {
+            continue;
+        };
+
+        for output_relative in policy.outputs_for(relative) {
+            let output = published_root.join(&output_relative);
+            let current = std::fs::metadata(&output)
+                .and_then(|m| m.modified())
+                .is_ok_and(|output_modified| output_modified >= source_modified);
+            if current {
+                continue;
+            }
+
+            if let Some(parent) = output.parent() {
+                if let Err(e) = std::fs::create_dir_all(parent) {
+                    result.errors.push(format!("{}: {}", output_relative, e));
+                    continue;
+                }
+            }
+            match policy.convert(&source, &output) {
+                Ok(()) => result.published.push(output_relative),
+                Err(e) => {
+                    tracing::warn!("Failed to publish {}: {}", output_relative, e);
+                    result.errors.push(format!("{}: {}", output_relative, e));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outputs_for() {
+        let command = Some("dwgconvert {input} {output}");
+        let policy = PublishPolicy::from_config(command, Some("# drawings\n.DWG = pdf, dxf\n"))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(policy.outputs_for("plans/site.dwg"), vec!["plans/site.pdf", "plans/site.dxf"]);
+        assert!(policy.outputs_for("parts/bracket.sldprt").is_empty());
+
+        assert!(PublishPolicy::from_config(command, Some("dwg =")).is_err());
+        assert!(PublishPolicy::from_config(None, Some("dwg = pdf")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_published_root_is_sibling() {
+        assert_eq!(published_root(Path::new("/work/Bridge")), Path::new("/work/Bridge Published"));
+    }
+}
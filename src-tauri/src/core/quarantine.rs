@@ -0,0 +1,128 @@
+use crate::models::file_state::FileLocation;
+use crate::utils::command;
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Scanner output kept in the quarantine record is cut to this many characters.
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+/// Written next to each quarantined file as `<name>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineRecord {
+    /// Relative path the file would have been written to.
+    pub path: String,
+    pub source: FileLocation,
+    pub exit_code: Option<i32>,
+    pub scanner_output: String,
+    pub quarantined_at: DateTime<Utc>,
+}
+
+/// What the scanner made of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// The scanner exited with a non-zero status.
+    Flagged { exit_code: Option<i32>, output: String },
+}
+
+/// Scanner command run on downloads before they are written into the local
+/// folder, e.g. `clamscan --no-summary {file}`. Flagged files go to the
+/// quarantine folder instead.
+#[derive(Debug, Clone)]
+pub struct ScanPolicy {
+    command: String,
+    quarantine_dir: PathBuf,
+}
+
+impl ScanPolicy {
+    /// `None` when no scanner is configured.
+    pub fn from_config(command: Option<&str>, quarantine_dir: PathBuf) -> Option<Self> {
+        command.map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|command| Self { command: command.to_string(), quarantine_dir })
+    }
+
+    /// Run the scanner on `file`. `{file}` in the command is replaced with the
+    /// path; without a placeholder the path is appended.
+    pub async fn scan(&self, file: &Path) -> Result<ScanVerdict> {
+        let mut scanner = command::build(&self.command, &[("file", &file.to_string_lossy())])?;
+        let output = tokio::task::spawn_blocking(move || scanner.output())
+            .await
+            .map_err(|e| UvcadError::ProviderError(format!("Malware scanner did not finish: {}", e)))?
+            .map_err(|e| UvcadError::InvalidConfig(format!("Malware scanner could not be run: {}", e)))?;
+        if output.status.success() {
+            return Ok(ScanVerdict::Clean);
+        }
+
+        let mut text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            text.push('\n');
+            text.push_str(stderr.trim());
+        }
+        Ok(ScanVerdict::Flagged {
+            exit_code: output.status.code(),
+            output: text.trim().chars().take(MAX_OUTPUT_CHARS).collect(),
+        })
+    }
+
+    /// Move a flagged download into the quarantine folder with a record of why.
+    /// Returns where it was put.
+    pub fn quarantine(&self, file: &Path, record: &QuarantineRecord) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.quarantine_dir)?;
+        let name = Path::new(&record.path).file_name().unwrap_or_default().to_string_lossy().to_string();
+        let dest = self.quarantine_dir.join(format!("{}-{}", record.quarantined_at.format("%Y%m%d-%H%M%S"), name));
+        // The download may sit on another volume than the quarantine folder
+        if std::fs::rename(file, &dest).is_err() {
+            std::fs::copy(file, &dest)?;
+            let _ = std::fs::remove_file(file);
+        }
+        let mut record_name = dest.file_name().unwrap_or_default().to_os_string();
+        record_name.push(".json");
+        std::fs::write(dest.with_file_name(record_name), serde_json::to_string_pretty(record)?)?;
+        Ok(dest)
+    }
+}
+
+/// Folder flagged downloads of a profile are moved to.
+pub fn quarantine_root(profile_id: i64) -> Result<PathBuf> {
+    let project_dirs = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| UvcadError::InvalidConfig("Failed to get project directory".to_string()))?;
+    Ok(project_dirs.data_dir().join("quarantine").join(profile_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flagged_download_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let download = dir.path().join("download.tmp");
+        std::fs::write(&download, b"payload").unwrap();
+
+        assert!(ScanPolicy::from_config(Some("  "), dir.path().join("q")).is_none());
+        let clean = ScanPolicy::from_config(Some("true"), dir.path().join("q")).unwrap();
+        assert_eq!(clean.scan(&download).await.unwrap(), ScanVerdict::Clean);
+
+        let policy = ScanPolicy::from_config(Some("false {file}"), dir.path().join("q")).unwrap();
+        let ScanVerdict::Flagged { exit_code, output } = policy.scan(&download).await.unwrap() else {
+            panic!("expected the scanner to flag the file");
+        };
+        let record = QuarantineRecord {
+            path: "parts/bracket.dwg".to_string(),
+            source: FileLocation::Smb,
+            exit_code,
+            scanner_output: output,
+            quarantined_at: Utc::now(),
+        };
+        let dest = policy.quarantine(&download, &record).unwrap();
+        assert!(!download.exists());
+        assert!(dest.starts_with(dir.path().join("q")));
+        assert_eq!(std::fs::read(&dest).unwrap(), b"payload");
+        assert!(dest.to_string_lossy().ends_with("bracket.dwg"));
+    }
+}
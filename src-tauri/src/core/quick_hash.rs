@@ -0,0 +1,169 @@
+use crate::utils::error::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+/// Bytes read per sample.
+const SAMPLE_SIZE: u64 = 16 * 1024;
+/// Samples spread evenly over a file, the first and last block included.
+const SAMPLE_COUNT: u64 = 16;
+
+/// Marks content hashes computed from samples rather than the whole file.
+pub const SAMPLED_PREFIX: &str = "sampled:";
+
+/// xxh3 over the size and evenly spaced blocks of a file. Reads at most
+/// `SAMPLE_COUNT * SAMPLE_SIZE` bytes however large the file is.
+pub fn sample_hash(path: &Path, size: u64) -> Result<u128> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    hasher.update(&size.to_le_bytes());
+
+    let mut buffer = vec![0u8; SAMPLE_SIZE as usize];
+    if size <= SAMPLE_SIZE * SAMPLE_COUNT {
+        loop {
+            let count = file.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buffer[..count]);
+        }
+    } else {
+        let stride = (size - SAMPLE_SIZE) / (SAMPLE_COUNT - 1);
+        for i in 0..SAMPLE_COUNT {
+            // The last block ends exactly at the end of the file
+            let offset = if i == SAMPLE_COUNT - 1 { size - SAMPLE_SIZE } else { i * stride };
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buffer)?;
+            hasher.update(&buffer);
+        }
+    }
+    Ok(hasher.digest128())
+}
+
+/// Content hash of a file too large to read in full on every scan: its size,
+/// modification time and sampled blocks, marked with `SAMPLED_PREFIX`. The
+/// modification time catches same-size edits between samples; in exchange
+/// copies of a file hash differently wherever their times differ.
+pub fn sampled_content_hash(path: &Path) -> Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    let mut hasher = Xxh3::new();
+    hasher.update(&modified_nanos(&metadata)?.to_le_bytes());
+    hasher.update(&sample_hash(path, metadata.len())?.to_le_bytes());
+    Ok(format!("{}{:032x}", SAMPLED_PREFIX, hasher.digest128()))
+}
+
+fn modified_nanos(metadata: &std::fs::Metadata) -> Result<u128> {
+    Ok(metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0))
+}
+
+/// Whether a content hash was computed from samples only.
+pub fn is_sampled(hash: &str) -> bool {
+    hash.starts_with(SAMPLED_PREFIX)
+}
+
+/// What a full hash of a file depends on: the hash kind, the file's path,
+/// size and modification time, and its sampled blocks. Stored next to the
+/// content hash in `file_states`; `kind` tells apart hashes computed in
+/// different ways.
+pub fn fingerprint(path: &Path, kind: &str) -> Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    let size = metadata.len();
+    let modified = modified_nanos(&metadata)?;
+    let location = xxh3_64(path.to_string_lossy().as_bytes());
+    Ok(format!("{}:{}:{}:{:016x}:{:032x}", kind, size, modified, location, sample_hash(path, size)?))
+}
+
+/// Hash a file with `full`, unless `known` has a hash recorded for its current
+/// fingerprint; then that hash is reused. Returns the hash and the fingerprint
+/// to record with it.
+pub fn hash_with_prefilter(
+    path: &Path,
+    kind: &str,
+    known: impl FnOnce(&str) -> Option<String>,
+    full: impl FnOnce(&Path) -> Result<String>,
+) -> Result<(String, String)> {
+    let fingerprint = fingerprint(path, kind)?;
+    let hash = match known(&fingerprint) {
+        Some(hash) => hash,
+        None => full(path)?,
+    };
+    Ok((hash, fingerprint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_full_hash_only_after_change() {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), vec![7u8; 1024 * 1024]).unwrap();
+        // Stands in for the fingerprints recorded in file_states
+        let recorded: RefCell<HashMap<String, String>> = RefCell::default();
+        let calls = Cell::new(0);
+        let full = |_: &Path| {
+            calls.set(calls.get() + 1);
+            Ok(format!("full-{}", calls.get()))
+        };
+        let scan = || {
+            let (hash, fingerprint) = hash_with_prefilter(
+                file.path(), "test", |f| recorded.borrow().get(f).cloned(), full,
+            ).unwrap();
+            recorded.borrow_mut().insert(fingerprint, hash.clone());
+            hash
+        };
+
+        assert_eq!(scan(), "full-1");
+        assert_eq!(scan(), "full-1");
+        assert_eq!(calls.get(), 1);
+
+        std::fs::write(file.path(), vec![8u8; 1024 * 1024 + 1]).unwrap();
+        assert_eq!(scan(), "full-2");
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_sampled_hash_follows_sampled_content() {
+        let file = NamedTempFile::new().unwrap();
+        let mut content = vec![0u8; 4 * 1024 * 1024];
+        std::fs::write(file.path(), &content).unwrap();
+        let size = content.len() as u64;
+        let time = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        file.as_file().set_modified(time(1_000)).unwrap();
+        let original = sampled_content_hash(file.path()).unwrap();
+        assert!(is_sampled(&original));
+
+        // The last block is always sampled
+        content[size as usize - 1] = 1;
+        std::fs::write(file.path(), &content).unwrap();
+        file.as_file().set_modified(time(1_000)).unwrap();
+        assert_ne!(sampled_content_hash(file.path()).unwrap(), original);
+    }
+
+    #[test]
+    fn test_edit_between_samples_changes_sampled_hash() {
+        let file = NamedTempFile::new().unwrap();
+        let mut content = vec![0u8; 4 * 1024 * 1024];
+        std::fs::write(file.path(), &content).unwrap();
+        let size = content.len() as u64;
+        let time = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        file.as_file().set_modified(time(1_000)).unwrap();
+        let (samples, original) = (sample_hash(file.path(), size).unwrap(), sampled_content_hash(file.path()).unwrap());
+
+        // Same size, and the edit lies between the first and second sample
+        content[2 * SAMPLE_SIZE as usize] = 1;
+        std::fs::write(file.path(), &content).unwrap();
+        file.as_file().set_modified(time(2_000)).unwrap();
+        assert_eq!(sample_hash(file.path(), size).unwrap(), samples);
+        assert_ne!(sampled_content_hash(file.path()).unwrap(), original);
+    }
+}
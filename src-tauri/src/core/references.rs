@@ -0,0 +1,418 @@
+use crate::db::models::DbOperations;
+use crate::models::file_reference::FileReference;
+use crate::models::file_state::{FileLocation, FileState};
+use crate::utils::error::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+/// Files that reference other files and are worth scanning.
+const ASSEMBLY_EXTENSIONS: &[&str] = &["sldasm", "iam", "dwg"];
+
+/// Extensions a reference must end with to be recorded.
+const REFERENCED_EXTENSIONS: &[&str] = &["sldprt", "sldasm", "ipt", "iam", "dwg"];
+
+/// Assemblies above this size are skipped rather than read into memory.
+const MAX_SCAN_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Shortest string run considered when scanning for paths ("a.ipt").
+const MIN_RUN_LEN: usize = 5;
+
+pub fn is_assembly(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| ASSEMBLY_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Extract referenced component paths from an assembly file.
+///
+/// SLDASM, IAM and DWG are proprietary binary formats, but all of them embed
+/// referenced file paths as plain ASCII or UTF-16LE strings. We scan for string
+/// runs ending in a known CAD extension rather than parsing the formats.
+pub fn extract_references(path: &Path) -> Result<Vec<String>> {
+    let size = std::fs::metadata(path)?.len();
+    if size > MAX_SCAN_BYTES {
+        tracing::warn!("Skipping reference scan of {} ({} bytes)", path.display(), size);
+        return Ok(Vec::new());
+    }
+
+    let data = std::fs::read(path)?;
+    let own_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    Ok(extract_references_from_bytes(&data)
+        .into_iter()
+        .filter(|r| !file_name_of(r).eq_ignore_ascii_case(own_name))
+        .collect())
+}
+
+/// Scan raw bytes for ASCII and UTF-16LE strings that look like CAD file paths.
+pub fn extract_references_from_bytes(data: &[u8]) -> Vec<String> {
+    let mut found = BTreeSet::new();
+
+    for run in ascii_runs(data).into_iter().chain(utf16_runs(data)) {
+        for candidate in path_candidates(&run) {
+            found.insert(candidate);
+        }
+    }
+
+    found.into_iter().collect()
+}
+
+fn is_printable(c: char) -> bool {
+    c == ' ' || c.is_ascii_graphic() || (!c.is_control() && !c.is_ascii() && c.is_alphanumeric())
+}
+
+fn ascii_runs(data: &[u8]) -> Vec<String> {
+    let mut runs = Vec::new();
+    let mut current = String::new();
+
+    for &b in data {
+        let c = b as char;
+        if b.is_ascii() && is_printable(c) {
+            current.push(c);
+        } else {
+            if current.len() >= MIN_RUN_LEN {
+                runs.push(std::mem::take(&mut current));
+            }
+            current.clear();
+        }
+    }
+    if current.len() >= MIN_RUN_LEN {
+        runs.push(current);
+    }
+
+    runs
+}
+
+fn utf16_runs(data: &[u8]) -> Vec<String> {
+    let mut runs = Vec::new();
+
+    // Strings may start at either byte parity
+    for offset in 0..2 {
+        let mut current = String::new();
+        for chunk in data[offset.min(data.len())..].chunks_exact(2) {
+            let unit = u16::from_le_bytes([chunk[0], chunk[1]]);
+            match char::from_u32(unit as u32).filter(|&c| unit != 0 && is_printable(c)) {
+                Some(c) => current.push(c),
+                None => {
+                    if current.chars().count() >= MIN_RUN_LEN {
+                        runs.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                }
+            }
+        }
+        if current.chars().count() >= MIN_RUN_LEN {
+            runs.push(current);
+        }
+    }
+
+    runs
+}
+
+/// Find every substring of `run` that ends in `.<referenced extension>` and trim it
+/// back to where the path plausibly begins (drive letter, UNC prefix or separator junk).
+fn path_candidates(run: &str) -> Vec<String> {
+    let lower = run.to_ascii_lowercase();
+    let mut candidates = Vec::new();
+
+    for ext in REFERENCED_EXTENSIONS {
+        let needle = format!(".{}", ext);
+        let mut search_from = 0;
+
+        while let Some(pos) = lower[search_from..].find(&needle) {
+            let dot = search_from + pos;
+            let end = dot + needle.len();
+            search_from = end;
+
+            // Reject matches that continue with more identifier characters (".dwgx")
+            if lower[end..].chars().next().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                continue;
+            }
+
+            let prefix = &run[..dot];
+            let start = prefix
+                .rfind(['"', '<', '>', '|', '*', '?', '\t'])
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            let mut candidate = &run[start..end];
+
+            // Anchor at a drive letter or UNC prefix if one is present
+            if let Some(i) = candidate.find(":\\").or_else(|| candidate.find(":/")) {
+                if i > 0 {
+                    candidate = &candidate[i - 1..];
+                }
+            } else if let Some(i) = candidate.find("\\\\") {
+                candidate = &candidate[i..];
+            }
+
+            let candidate = candidate.trim();
+            if file_name_of(candidate).len() > needle.len() {
+                candidates.push(candidate.to_string());
+            }
+        }
+    }
+
+    candidates
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_matches('/').to_lowercase()
+}
+
+fn file_name_of(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Resolve a raw reference to one of the profile's known relative paths.
+///
+/// 1. The longest known path that the reference ends with (absolute author paths).
+/// 2. A file with the same name in the parent's directory.
+/// 3. The only file in the profile with that name.
+pub fn resolve_reference(reference: &str, parent_path: &str, known_paths: &[String]) -> Option<String> {
+    let reference_norm = normalize(reference);
+    let reference_name = normalize(file_name_of(reference));
+
+    let suffix_match = known_paths.iter()
+        .filter(|known| {
+            let known_norm = normalize(known);
+            reference_norm == known_norm || reference_norm.ends_with(&format!("/{}", known_norm))
+        })
+        .max_by_key(|known| known.len());
+    if let Some(known) = suffix_match {
+        return Some(known.clone());
+    }
+
+    let same_name: Vec<&String> = known_paths.iter()
+        .filter(|known| normalize(file_name_of(known)) == reference_name)
+        .collect();
+
+    let parent_dir = Path::new(parent_path).parent().map(|p| normalize(&p.to_string_lossy()));
+    let in_parent_dir = same_name.iter().find(|known| {
+        Path::new(known.as_str()).parent().map(|p| normalize(&p.to_string_lossy())) == parent_dir
+    });
+    if let Some(known) = in_parent_dir {
+        return Some((*known).clone());
+    }
+
+    if same_name.len() == 1 {
+        return Some(same_name[0].clone());
+    }
+
+    None
+}
+
+/// Re-scan local assemblies whose content changed and re-resolve references
+/// against the current set of known files. Returns the number of assemblies scanned.
+pub fn rebuild_reference_graph(conn: &Connection, profile_id: i64, local_root: &Path) -> Result<usize> {
+    let states = DbOperations::get_file_states(conn, profile_id)?;
+    let known_paths: Vec<String> = states.iter()
+        .map(|s| s.file_path.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut existing: HashMap<String, Vec<FileReference>> = HashMap::new();
+    for reference in DbOperations::get_file_references(conn, profile_id)? {
+        existing.entry(reference.parent_path.clone()).or_default().push(reference);
+    }
+
+    let mut local_assemblies = HashSet::new();
+    let mut scanned = 0;
+
+    for state in states.iter().filter(|s| s.location == FileLocation::Local) {
+        if !is_assembly(Path::new(&state.file_path)) {
+            continue;
+        }
+        local_assemblies.insert(state.file_path.clone());
+
+        let previous = existing.remove(&state.file_path).unwrap_or_default();
+        let unchanged = !previous.is_empty()
+            && previous.iter().all(|r| r.parent_hash == state.content_hash);
+
+        let raw_references = if unchanged {
+            previous.into_iter().map(|r| r.reference).collect()
+        } else {
+            scanned += 1;
+            match extract_references(&local_root.join(&state.file_path)) {
+                Ok(references) => references,
+                Err(e) => {
+                    tracing::warn!("Failed to scan references of {}: {}", state.file_path, e);
+                    continue;
+                }
+            }
+        };
+
+        let references: Vec<FileReference> = raw_references.into_iter()
+            .map(|reference| FileReference {
+                id: None,
+                profile_id,
+                parent_path: state.file_path.clone(),
+                parent_hash: state.content_hash.clone(),
+                resolved_path: resolve_reference(&reference, &state.file_path, &known_paths),
+                reference,
+            })
+            .collect();
+
+        DbOperations::replace_file_references(conn, profile_id, &state.file_path, &references)?;
+    }
+
+    // Drop graph entries for assemblies that no longer exist locally
+    for parent_path in existing.keys().filter(|p| !local_assemblies.contains(*p)) {
+        DbOperations::replace_file_references(conn, profile_id, parent_path, &[])?;
+    }
+
+    tracing::info!("Reference graph updated for profile {} ({} assemblies scanned)", profile_id, scanned);
+    Ok(scanned)
+}
+
+/// Transitive closure of `roots` over resolved references, including the roots themselves.
+pub fn collect_dependencies(conn: &Connection, profile_id: i64, roots: &[String]) -> Result<Vec<String>> {
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+    for reference in DbOperations::get_file_references(conn, profile_id)? {
+        if let Some(resolved) = reference.resolved_path {
+            children.entry(reference.parent_path).or_default().push(resolved);
+        }
+    }
+
+    let mut seen: BTreeSet<String> = roots.iter().cloned().collect();
+    let mut queue: VecDeque<String> = roots.iter().cloned().collect();
+
+    while let Some(path) = queue.pop_front() {
+        for child in children.get(&path).into_iter().flatten() {
+            if seen.insert(child.clone()) {
+                queue.push_back(child.clone());
+            }
+        }
+    }
+
+    Ok(seen.into_iter().collect())
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum ReferenceIssueKind {
+    /// The reference could not be matched to any file in the profile.
+    Unresolved,
+    /// The referenced file exists elsewhere but not on the checked endpoint.
+    Missing,
+    /// The endpoint's copy is older than a differing copy at another location.
+    OutOfDate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferenceIssue {
+    pub assembly_path: String,
+    pub reference: String,
+    pub resolved_path: Option<String>,
+    pub kind: ReferenceIssueKind,
+}
+
+/// Whether `copy` is stale compared to `other`: the content differs and `other` is newer.
+/// Hashes are only comparable between locations using the same algorithm (same length),
+/// otherwise size is used as the content signal.
+fn is_stale(copy: &FileState, other: &FileState) -> bool {
+    let differs = match (&copy.content_hash, &other.content_hash) {
+        (Some(a), Some(b)) if a.len() == b.len() => a != b,
+        _ => copy.size_bytes != other.size_bytes,
+    };
+    differs && other.modified_at > copy.modified_at
+}
+
+/// Report references of assemblies present on `location` that are unresolved,
+/// missing on that endpoint, or out of date there compared to another location.
+pub fn check_references(conn: &Connection, profile_id: i64, location: &FileLocation) -> Result<Vec<ReferenceIssue>> {
+    let mut states_by_path: HashMap<String, Vec<FileState>> = HashMap::new();
+    for state in DbOperations::get_file_states(conn, profile_id)? {
+        states_by_path.entry(state.file_path.clone()).or_default().push(state);
+    }
+    let at_location = |path: &str| {
+        states_by_path.get(path)
+            .and_then(|states| states.iter().find(|s| &s.location == location))
+    };
+
+    let mut issues = Vec::new();
+    for reference in DbOperations::get_file_references(conn, profile_id)? {
+        if at_location(&reference.parent_path).is_none() {
+            continue;
+        }
+
+        let kind = match &reference.resolved_path {
+            None => Some(ReferenceIssueKind::Unresolved),
+            Some(resolved) => match at_location(resolved) {
+                None => Some(ReferenceIssueKind::Missing),
+                Some(copy) => {
+                    let stale = states_by_path[resolved].iter()
+                        .any(|other| &other.location != location && is_stale(copy, other));
+                    stale.then_some(ReferenceIssueKind::OutOfDate)
+                }
+            },
+        };
+
+        if let Some(kind) = kind {
+            issues.push(ReferenceIssue {
+                assembly_path: reference.parent_path,
+                reference: reference.reference,
+                resolved_path: reference.resolved_path,
+                kind,
+            });
+        }
+    }
+
+    issues.sort_by(|a, b| a.assembly_path.cmp(&b.assembly_path).then(a.reference.cmp(&b.reference)));
+    tracing::info!("Reference check on {:?}: {} issues", location, issues.len());
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_extracts_ascii_and_utf16_paths() {
+        let mut data = vec![0u8, 1, 2];
+        data.extend_from_slice(b"junk\x00C:\\Vault\\Proj\\bracket.SLDPRT\x00\x07");
+        data.extend_from_slice(&utf16("\u{1}\\\\nas\\eng\\Proj\\base.ipt"));
+        data.extend_from_slice(&[0, 0, 9]);
+
+        let refs = extract_references_from_bytes(&data);
+        assert!(refs.contains(&"C:\\Vault\\Proj\\bracket.SLDPRT".to_string()));
+        assert!(refs.contains(&"\\\\nas\\eng\\Proj\\base.ipt".to_string()));
+    }
+
+    #[test]
+    fn test_ignores_extension_prefixes() {
+        let refs = extract_references_from_bytes(b"\x00notes.dwgx\x00 .dwg\x00");
+        assert!(refs.is_empty());
+    }
+
+    #[test]
+    fn test_resolves_by_longest_suffix_then_name() {
+        let known = vec![
+            "bracket.sldprt".to_string(),
+            "Proj/parts/bracket.sldprt".to_string(),
+            "Proj/base.sldprt".to_string(),
+            "Other/base.sldprt".to_string(),
+            "Proj/lonely.sldprt".to_string(),
+        ];
+
+        assert_eq!(
+            resolve_reference("C:\\Users\\bob\\Proj\\parts\\Bracket.SLDPRT", "Proj/top.sldasm", &known),
+            Some("Proj/parts/bracket.sldprt".to_string())
+        );
+        assert_eq!(
+            resolve_reference("D:\\elsewhere\\base.sldprt", "Proj/top.sldasm", &known),
+            Some("Proj/base.sldprt".to_string())
+        );
+        assert_eq!(
+            resolve_reference("D:\\elsewhere\\lonely.sldprt", "Top.sldasm", &known),
+            Some("Proj/lonely.sldprt".to_string())
+        );
+        assert_eq!(resolve_reference("D:\\x\\missing.sldprt", "Proj/top.sldasm", &known), None);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::core::extension_policy::{self, ExtensionPolicies};
+use crate::models::file_state::FileLocation;
+use crate::providers::traits::StorageProvider;
+use crate::utils::error::{Result, UvcadError};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A remote location a release is published to.
+pub struct Endpoint {
+    pub location: FileLocation,
+    pub provider: Box<dyn StorageProvider>,
+    /// Folder the synced files are kept in there (see `core::path_prefix`).
+    pub prefix: Option<PathBuf>,
+}
+
+impl Endpoint {
+    /// Where `path` is kept at this endpoint.
+    pub fn stored_path(&self, policies: &ExtensionPolicies, path: &Path) -> PathBuf {
+        let stored = policies.stored_path(path, &self.location);
+        match &self.prefix {
+            Some(prefix) => prefix.join(stored),
+            None => stored,
+        }
+    }
+}
+
+/// Outcome of a committed release.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseResult {
+    pub release_id: String,
+    pub files: Vec<String>,
+    pub locations: Vec<FileLocation>,
+}
+
+/// Where a file is staged before commit: a hidden sibling of its final name.
+fn staged_path(stored: &Path, release_id: &str) -> PathBuf {
+    sibling(stored, "release", release_id)
+}
+
+/// Where the previous version is kept until the release is committed everywhere.
+fn backup_path(stored: &Path, release_id: &str) -> PathBuf {
+    sibling(stored, "backup", release_id)
+}
+
+fn sibling(stored: &Path, kind: &str, release_id: &str) -> PathBuf {
+    let name = stored.file_name().unwrap_or_default().to_string_lossy();
+    stored.with_file_name(format!(".{}.uvcad-{}-{}", name, kind, release_id))
+}
+
+/// A stored file moved into place during commit.
+struct Committed<'a> {
+    endpoint: &'a Endpoint,
+    stored: PathBuf,
+    had_previous: bool,
+}
+
+/// Publish `paths` (relative to `local_root`) to every endpoint or to none.
+///
+/// 1. Check that every endpoint is reachable and every file exists locally.
+/// 2. Upload each file next to its destination under a staging name. If any
+///    upload fails, all staged copies are deleted.
+/// 3. Move the previous versions aside and the staged copies into place. If any
+///    move fails, the moves made so far are undone and the previous versions restored.
+/// 4. Delete the previous versions.
+pub async fn publish(
+    release_id: &str,
+    local_root: &Path,
+    policies: &ExtensionPolicies,
+    endpoints: &[Endpoint],
+    paths: &[String],
+) -> Result<ReleaseResult> {
+    if endpoints.is_empty() {
+        return Err(UvcadError::InvalidConfig("No remote locations configured".to_string()));
+    }
+
+    // Phase 1: verify
+    for endpoint in endpoints {
+        let reachable = endpoint.provider.test_connection().await.unwrap_or(false);
+        if !reachable {
+            return Err(UvcadError::SyncFailed(format!("{} is not reachable", endpoint.location.as_str())));
+        }
+    }
+    for path in paths {
+        if !local_root.join(path).is_file() {
+            return Err(UvcadError::FileNotFound { path: path.clone() });
+        }
+    }
+
+    // Compressed copies are shared by all endpoints that store the file compressed
+    let mut sources = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        let local = local_root.join(path);
+        let compressed = std::env::temp_dir().join(format!(
+            "uvcad_release_{}_{}.{}", release_id, index, extension_policy::COMPRESSED_SUFFIX
+        ));
+        if policies.applies(Path::new(path), extension_policy::ExtensionBehavior::Compress) {
+            extension_policy::compress_file(&local, &compressed)?;
+        }
+        sources.push((local, compressed));
+    }
+
+    let result = stage_and_commit(release_id, policies, endpoints, paths, &sources).await;
+    for (_, compressed) in &sources {
+        let _ = std::fs::remove_file(compressed);
+    }
+    result?;
+
+    tracing::info!("Release {} published {} files to {} locations", release_id, paths.len(), endpoints.len());
+    Ok(ReleaseResult {
+        release_id: release_id.to_string(),
+        files: paths.to_vec(),
+        locations: endpoints.iter().map(|e| e.location.clone()).collect(),
+    })
+}
+
+/// Phases 2-4 of `publish`. `sources` holds the local and compressed copy of each path.
+async fn stage_and_commit(
+    release_id: &str,
+    policies: &ExtensionPolicies,
+    endpoints: &[Endpoint],
+    paths: &[String],
+    sources: &[(PathBuf, PathBuf)],
+) -> Result<()> {
+    // Phase 2: stage
+    let mut staged: Vec<(&Endpoint, PathBuf)> = Vec::new();
+    for endpoint in endpoints {
+        for (path, (local, compressed)) in paths.iter().zip(sources) {
+            let path = Path::new(path);
+            let source = if policies.is_compressed_at(path, &endpoint.location) { compressed } else { local };
+            let target = staged_path(&endpoint.stored_path(policies, path), release_id);
+
+            if let Err(e) = endpoint.provider.upload(source, &target).await {
+                tracing::error!("Staging {} on {} failed: {}", path.display(), endpoint.location.as_str(), e);
+                staged.push((endpoint, target));
+                discard_staged(&staged).await;
+                return Err(UvcadError::SyncFailed(format!(
+                    "Staging {} on {} failed: {}", path.display(), endpoint.location.as_str(), e
+                )));
+            }
+            staged.push((endpoint, target));
+        }
+    }
+
+    // Phase 3: commit
+    let mut committed: Vec<Committed> = Vec::new();
+    for endpoint in endpoints {
+        for path in paths {
+            let stored = endpoint.stored_path(policies, Path::new(path));
+            match commit_one(endpoint, &stored, release_id).await {
+                Ok(had_previous) => committed.push(Committed { endpoint, stored, had_previous }),
+                Err(e) => {
+                    tracing::error!("Committing {} on {} failed: {}", path, endpoint.location.as_str(), e);
+                    roll_back(&committed, release_id).await;
+                    discard_staged(&staged).await;
+                    return Err(UvcadError::SyncFailed(format!(
+                        "Committing {} on {} failed, release rolled back: {}", path, endpoint.location.as_str(), e
+                    )));
+                }
+            }
+        }
+    }
+
+    // Phase 4: clean up
+    for entry in committed.iter().filter(|c| c.had_previous) {
+        let backup = backup_path(&entry.stored, release_id);
+        if let Err(e) = entry.endpoint.provider.delete(&backup).await {
+            tracing::warn!("Failed to remove {} on {}: {}", backup.display(), entry.endpoint.location.as_str(), e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the previous version aside and the staged copy into place. Returns
+/// whether there was a previous version.
+async fn commit_one(endpoint: &Endpoint, stored: &Path, release_id: &str) -> Result<bool> {
+    let provider = &endpoint.provider;
+    let had_previous = provider.exists(stored).await?;
+    if had_previous {
+        provider.rename(stored, &backup_path(stored, release_id)).await?;
+    }
+    if let Err(e) = provider.rename(&staged_path(stored, release_id), stored).await {
+        if had_previous {
+            let _ = provider.rename(&backup_path(stored, release_id), stored).await;
+        }
+        return Err(e);
+    }
+    Ok(had_previous)
+}
+
+/// Undo committed moves in reverse order: the new version goes back to its
+/// staging name (deleted afterwards) and the previous version is restored.
+async fn roll_back(committed: &[Committed<'_>], release_id: &str) {
+    for entry in committed.iter().rev() {
+        let provider = &entry.endpoint.provider;
+        let location = entry.endpoint.location.as_str();
+        if let Err(e) = provider.rename(&entry.stored, &staged_path(&entry.stored, release_id)).await {
+            tracing::error!("Rollback of {} on {} failed: {}", entry.stored.display(), location, e);
+            continue;
+        }
+        if entry.had_previous {
+            if let Err(e) = provider.rename(&backup_path(&entry.stored, release_id), &entry.stored).await {
+                tracing::error!("Restoring {} on {} failed: {}", entry.stored.display(), location, e);
+            }
+        }
+    }
+}
+
+async fn discard_staged(staged: &[(&Endpoint, PathBuf)]) {
+    for (endpoint, path) in staged {
+        if endpoint.provider.exists(path).await.unwrap_or(false) {
+            if let Err(e) = endpoint.provider.delete(path).await {
+                tracing::warn!("Failed to remove staged {} on {}: {}", path.display(), endpoint.location.as_str(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::local_fs::LocalFsProvider;
+
+    fn endpoint(location: FileLocation, root: &Path) -> Endpoint {
+        Endpoint { location, provider: Box::new(LocalFsProvider::new(root.to_path_buf())), prefix: None }
+    }
+
+    #[tokio::test]
+    async fn test_publish_replaces_everywhere() {
+        let (local, drive, nas) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        std::fs::create_dir_all(local.path().join("rel")).unwrap();
+        std::fs::write(local.path().join("rel/a.step"), "new").unwrap();
+        std::fs::create_dir_all(nas.path().join("rel")).unwrap();
+        std::fs::write(nas.path().join("rel/a.step"), "old").unwrap();
+
+        let endpoints = [endpoint(FileLocation::GoogleDrive, drive.path()), endpoint(FileLocation::Smb, nas.path())];
+        let paths = vec!["rel/a.step".to_string()];
+        publish("r1", local.path(), &ExtensionPolicies::default(), &endpoints, &paths).await.unwrap();
+
+        for root in [drive.path(), nas.path()] {
+            assert_eq!(std::fs::read_to_string(root.join("rel/a.step")).unwrap(), "new");
+            assert_eq!(std::fs::read_dir(root.join("rel")).unwrap().count(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_staging_leaves_endpoints_untouched() {
+        let (local, drive, nas) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        std::fs::write(local.path().join("a.step"), "new").unwrap();
+        std::fs::write(nas.path().join("a.step"), "old").unwrap();
+        // A directory where the staged file should go makes the NAS upload fail
+        std::fs::create_dir(nas.path().join(".a.step.uvcad-release-r1")).unwrap();
+
+        let endpoints = [endpoint(FileLocation::GoogleDrive, drive.path()), endpoint(FileLocation::Smb, nas.path())];
+        let paths = vec!["a.step".to_string()];
+        assert!(publish("r1", local.path(), &ExtensionPolicies::default(), &endpoints, &paths).await.is_err());
+
+        assert_eq!(std::fs::read_dir(drive.path()).unwrap().count(), 0);
+        assert_eq!(std::fs::read_to_string(nas.path().join("a.step")).unwrap(), "old");
+    }
+}
@@ -0,0 +1,128 @@
+use crate::models::file_state::{FileLocation, FileState};
+use crate::providers::traits::FileMetadata;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Paths listed per kind of mismatch in the error for a relocation that doesn't check out.
+const LISTED_PATHS: usize = 10;
+
+/// How the files at a new local root compare to the recorded local state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RelocationCheck {
+    /// Recorded files found at the new root with the same content.
+    pub verified: usize,
+    /// Recorded files not at the new root.
+    pub missing: Vec<String>,
+    /// Recorded files with other content at the new root.
+    pub changed: Vec<String>,
+    /// Files at the new root that were never synced; the next sync treats them as new.
+    pub new_files: usize,
+}
+
+impl RelocationCheck {
+    /// Whether the new root holds everything recorded, so the recorded state
+    /// still describes it and nothing needs to be transferred again.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty()
+    }
+
+    /// Why the new root can't take over, naming the first few mismatches.
+    pub fn describe_mismatches(&self, new_root: &Path) -> String {
+        let list = |paths: &[String]| {
+            let mut listed = paths.iter().take(LISTED_PATHS).cloned().collect::<Vec<_>>().join(", ");
+            if paths.len() > LISTED_PATHS {
+                listed.push_str(&format!(" and {} more", paths.len() - LISTED_PATHS));
+            }
+            listed
+        };
+        let mut reasons = Vec::new();
+        if !self.missing.is_empty() {
+            reasons.push(format!("{} files are missing ({})", self.missing.len(), list(&self.missing)));
+        }
+        if !self.changed.is_empty() {
+            reasons.push(format!("{} files differ ({})", self.changed.len(), list(&self.changed)));
+        }
+        format!("{} does not hold the synced files: {}", new_root.display(), reasons.join("; "))
+    }
+}
+
+/// Compare the files `found` at a new local root with the `recorded` state of the
+/// profile. Content is compared by hash, or by size where no hash was recorded.
+pub fn check(recorded: &[FileState], found: &[FileMetadata]) -> RelocationCheck {
+    let found: HashMap<&Path, &FileMetadata> = found.iter().map(|f| (f.path.as_path(), f)).collect();
+    let mut result = RelocationCheck::default();
+    let mut matched = 0;
+
+    for state in recorded.iter().filter(|s| s.location == FileLocation::Local) {
+        let path = PathBuf::from(&state.file_path);
+        let Some(file) = found.get(path.as_path()) else {
+            result.missing.push(state.file_path.clone());
+            continue;
+        };
+        matched += 1;
+        let same = match (&state.content_hash, &file.hash) {
+            (Some(recorded), Some(hash)) => recorded == hash,
+            _ => state.size_bytes.is_none_or(|size| size == file.size as i64),
+        };
+        if same {
+            result.verified += 1;
+        } else {
+            result.changed.push(state.file_path.clone());
+        }
+    }
+
+    result.new_files = found.len() - matched;
+    result.missing.sort();
+    result.changed.sort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::file_state::SyncStatus;
+    use chrono::Utc;
+
+    fn state(path: &str, location: FileLocation, hash: &str) -> FileState {
+        FileState {
+            id: None,
+            profile_id: 1,
+            file_path: path.to_string(),
+            location,
+            content_hash: Some(hash.to_string()),
+            hash_fingerprint: None,
+            size_bytes: Some(1),
+            modified_at: None,
+            synced_at: None,
+            status: SyncStatus::Synced,
+            metadata: None,
+        }
+    }
+
+    fn file(path: &str, hash: &str) -> FileMetadata {
+        FileMetadata { path: PathBuf::from(path), size: 1, modified: Utc::now(), hash: Some(hash.to_string()), fingerprint: None, exists: true }
+    }
+
+    #[test]
+    fn test_new_root_must_hold_the_recorded_files() {
+        let recorded = [
+            state("parts/a.step", FileLocation::Local, "a"),
+            state("parts/b.step", FileLocation::Local, "b"),
+            // Only the local state describes the local root
+            state("drive-only.step", FileLocation::GoogleDrive, "d"),
+        ];
+
+        let moved = check(&recorded, &[file("parts/a.step", "a"), file("parts/b.step", "b"), file("new.step", "n")]);
+        assert!(moved.is_complete());
+        assert_eq!((moved.verified, moved.new_files), (2, 1));
+
+        let wrong = check(&recorded, &[file("parts/a.step", "edited")]);
+        assert!(!wrong.is_complete());
+        assert_eq!((wrong.missing.as_slice(), wrong.changed.as_slice()), (&["parts/b.step".to_string()][..], &["parts/a.step".to_string()][..]));
+        assert_eq!(
+            wrong.describe_mismatches(Path::new("E:/cad")),
+            "E:/cad does not hold the synced files: 1 files are missing (parts/b.step); 1 files differ (parts/a.step)"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, SyncStatus};
+use crate::models::part_revision::PartRevision;
+use crate::utils::error::Result;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// Key under which the parsed revision is stored in file_states.metadata.
+const METADATA_KEY: &str = "revision";
+
+/// Matches a trailing revision indicator on a file stem, e.g. `_revB`, `-Rev.C`,
+/// ` rev 2`, `-R03`.
+static REVISION_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?P<base>.+?)[_\-\s.](?:rev(?:ision)?[\s._-]?(?P<rev>[a-z]{1,2}|\d{1,3})|r(?P<num>\d{1,3}))$")
+        .expect("valid revision pattern")
+});
+
+/// Revision indicator parsed from a file name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedRevision {
+    /// File stem without the revision suffix (the part number), as written.
+    pub base_part: String,
+    /// Revision as written, upper-cased (e.g. "B", "03").
+    pub revision: String,
+}
+
+/// Parse a revision indicator from a file name (directory is ignored).
+pub fn parse_revision(path: &Path) -> Option<ParsedRevision> {
+    let stem = path.file_stem()?.to_str()?;
+    let captures = REVISION_PATTERN.captures(stem)?;
+    let revision = captures.name("rev").or_else(|| captures.name("num"))?;
+
+    Some(ParsedRevision {
+        base_part: captures["base"].to_string(),
+        revision: revision.as_str().to_ascii_uppercase(),
+    })
+}
+
+/// Compare two revisions. Numeric revisions compare by value, letter revisions
+/// alphabetically with longer sequences later (Z < AA). Mixed schemes are not comparable.
+pub fn compare_revisions(a: &str, b: &str) -> Option<Ordering> {
+    let numeric = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let alphabetic = |s: &str| s.chars().all(|c| c.is_ascii_alphabetic());
+
+    if numeric(a) && numeric(b) {
+        Some(a.parse::<u32>().ok()?.cmp(&b.parse::<u32>().ok()?))
+    } else if alphabetic(a) && alphabetic(b) {
+        Some(a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    } else {
+        None
+    }
+}
+
+/// Find a file among `existing` with the same base part number as `path` but a
+/// higher revision, i.e. one that writing `path` would appear to roll back.
+pub fn find_higher_revision<'a>(path: &Path, existing: impl IntoIterator<Item = &'a PathBuf>) -> Option<PathBuf> {
+    let incoming = parse_revision(path)?;
+
+    existing.into_iter()
+        .filter(|other| other.as_path() != path)
+        .filter_map(|other| parse_revision(other).map(|rev| (other, rev)))
+        .filter(|(_, rev)| rev.base_part.eq_ignore_ascii_case(&incoming.base_part))
+        .filter(|(_, rev)| compare_revisions(&incoming.revision, &rev.revision) == Some(Ordering::Less))
+        .map(|(other, _)| other.clone())
+        .next()
+}
+
+/// Store parsed revisions of local files into their metadata and record every
+/// newly seen revision in the per-part revision history.
+pub fn record_revisions(conn: &Connection, profile_id: i64) -> Result<usize> {
+    let mut recorded = 0;
+
+    for mut state in DbOperations::get_file_states(conn, profile_id)? {
+        if state.location != FileLocation::Local || state.status == SyncStatus::Deleted {
+            continue;
+        }
+        let Some(parsed) = parse_revision(Path::new(&state.file_path)) else {
+            continue;
+        };
+
+        if read_revision(state.metadata.as_deref()).as_ref() != Some(&parsed) {
+            state.metadata = Some(write_revision(state.metadata.as_deref(), &parsed)?);
+            DbOperations::upsert_file_state(conn, &state)?;
+        }
+
+        let is_new = DbOperations::record_part_revision(conn, &PartRevision {
+            id: None,
+            profile_id,
+            base_part: parsed.base_part,
+            revision: parsed.revision,
+            file_path: state.file_path.clone(),
+            content_hash: state.content_hash.clone(),
+            first_seen_at: Utc::now(),
+        })?;
+        if is_new {
+            recorded += 1;
+        }
+    }
+
+    if recorded > 0 {
+        tracing::info!("Recorded {} new part revisions for profile {}", recorded, profile_id);
+    }
+    Ok(recorded)
+}
+
+pub fn read_revision(metadata: Option<&str>) -> Option<ParsedRevision> {
+    let value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    serde_json::from_value(value.get(METADATA_KEY)?.clone()).ok()
+}
+
+/// Store the parsed revision into a metadata JSON blob, preserving any other keys.
+fn write_revision(metadata: Option<&str>, revision: &ParsedRevision) -> Result<String> {
+    let mut value = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+
+    value[METADATA_KEY] = serde_json::to_value(revision)?;
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(name: &str) -> Option<(String, String)> {
+        parse_revision(Path::new(name)).map(|r| (r.base_part, r.revision))
+    }
+
+    #[test]
+    fn test_parses_common_revision_suffixes() {
+        assert_eq!(parsed("PRJ-1042_Housing_revB.step"), Some(("PRJ-1042_Housing".into(), "B".into())));
+        assert_eq!(parsed("bracket-R03.sldprt"), Some(("bracket".into(), "03".into())));
+        assert_eq!(parsed("plate Rev.C.dwg"), Some(("plate".into(), "C".into())));
+        assert_eq!(parsed("frame_rev 12.pdf"), Some(("frame".into(), "12".into())));
+        assert_eq!(parsed("gearbox.step"), None);
+        assert_eq!(parsed("driver.sldprt"), None);
+    }
+
+    #[test]
+    fn test_revision_ordering() {
+        assert_eq!(compare_revisions("B", "C"), Some(Ordering::Less));
+        assert_eq!(compare_revisions("Z", "AA"), Some(Ordering::Less));
+        assert_eq!(compare_revisions("10", "09"), Some(Ordering::Greater));
+        assert_eq!(compare_revisions("B", "02"), None);
+    }
+
+    #[test]
+    fn test_finds_higher_revision_at_destination() {
+        let existing = vec![PathBuf::from("parts/housing_revC.step"), PathBuf::from("parts/cover_revA.step")];
+        assert_eq!(
+            find_higher_revision(Path::new("parts/housing_revB.step"), &existing),
+            Some(PathBuf::from("parts/housing_revC.step"))
+        );
+        assert_eq!(find_higher_revision(Path::new("parts/housing_revD.step"), &existing), None);
+        assert_eq!(find_higher_revision(Path::new("parts/cover_revA.step"), &existing), None);
+    }
+}
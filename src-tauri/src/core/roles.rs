@@ -0,0 +1,118 @@
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::secrets;
+
+/// Secret store entry holding the role of this installation.
+const ROLE_SECRET: &str = "uvcad_role";
+
+/// What the local user may do. Kept in the secret store so it cannot be changed
+/// by editing the profile database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Everything, including destructive operations and managing roles.
+    Admin,
+    /// Day-to-day syncing; destructive operations need an admin.
+    Operator,
+}
+
+/// Operations restricted to some roles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Approve sync plans that propagate deletions.
+    ApproveDeletions,
+    /// Change the deletion safety thresholds.
+    ChangeDeletionThresholds,
+    /// Delete a sync profile and its history.
+    DeleteProfile,
+    /// Change the role of this installation.
+    ManageRoles,
+    /// Lift a legal hold from files.
+    ReleaseLegalHold,
+    /// Change where the managed policy comes from, or keep local values of the
+    /// settings it decides.
+    OverrideManagedPolicy,
+}
+
+impl Permission {
+    fn describe(&self) -> &str {
+        match self {
+            Permission::ApproveDeletions => "approving deletions",
+            Permission::ChangeDeletionThresholds => "changing deletion thresholds",
+            Permission::DeleteProfile => "deleting profiles",
+            Permission::ManageRoles => "managing roles",
+            Permission::ReleaseLegalHold => "releasing legal holds",
+            Permission::OverrideManagedPolicy => "overriding the managed policy",
+        }
+    }
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Admin => "admin",
+            Role::Operator => "operator",
+        }
+    }
+
+    pub fn from_str_opt(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "admin" => Some(Role::Admin),
+            "operator" => Some(Role::Operator),
+            _ => None,
+        }
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Operator => match permission {
+                Permission::ApproveDeletions
+                | Permission::ChangeDeletionThresholds
+                | Permission::DeleteProfile
+                | Permission::ManageRoles
+                | Permission::ReleaseLegalHold
+                | Permission::OverrideManagedPolicy => false,
+            },
+        }
+    }
+}
+
+/// The stored role. Installations without one are single-user setups and act as admin.
+pub fn current_role() -> Result<Role> {
+    match secrets::store()?.get(ROLE_SECRET)? {
+        Some(value) => Role::from_str_opt(&value)
+            .ok_or_else(|| UvcadError::InvalidConfig(format!("Unknown role: {}", value))),
+        None => Ok(Role::Admin),
+    }
+}
+
+/// Fail unless the current role allows `permission`.
+pub fn require(permission: Permission) -> Result<()> {
+    let role = current_role()?;
+    if role.allows(permission) {
+        Ok(())
+    } else {
+        Err(UvcadError::PermissionDenied(format!("the {} role does not allow {}", role.as_str(), permission.describe())))
+    }
+}
+
+/// Store a new role. Only an admin may do this.
+pub fn set_role(role: Role) -> Result<()> {
+    require(Permission::ManageRoles)?;
+    secrets::store()?.set(ROLE_SECRET, role.as_str())?;
+    tracing::info!("Role set to {}", role.as_str());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_cannot_run_destructive_operations() {
+        assert!(Role::Admin.allows(Permission::DeleteProfile));
+        assert!(!Role::Operator.allows(Permission::ApproveDeletions));
+        assert!(!Role::Operator.allows(Permission::ManageRoles));
+        assert_eq!(Role::from_str_opt(" Operator"), Some(Role::Operator));
+        assert_eq!(Role::from_str_opt("root"), None);
+    }
+}
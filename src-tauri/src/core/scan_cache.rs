@@ -0,0 +1,122 @@
+use crate::models::file_state::FileLocation;
+use crate::providers::traits::{FileMetadata, StorageProvider};
+use crate::utils::error::Result;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// How long a full scan is reused when nothing invalidated it. Changes made
+/// behind the cache's back can't cause data loss: transfers re-check their
+/// destination before writing and report a conflict instead.
+const SCAN_TTL_MINUTES: i64 = 5;
+
+/// The last full listing of one location, and the subtrees changed since.
+#[derive(Clone)]
+struct CachedScan {
+    files: Vec<FileMetadata>,
+    scanned_at: DateTime<Utc>,
+    stale: BTreeSet<PathBuf>,
+}
+
+/// Cached listings by profile and location.
+static CACHE: Lazy<Mutex<HashMap<(i64, FileLocation), CachedScan>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// List every file at `location` through `provider`, reusing the profile's last
+/// scan when it is recent. Only invalidated subtrees are listed again, unless the
+/// provider can't list a subtree on its own; then the whole location is.
+pub async fn list_files(profile_id: i64, location: &FileLocation, provider: &dyn StorageProvider) -> Result<Vec<FileMetadata>> {
+    let key = (profile_id, location.clone());
+    let cached = CACHE.lock().unwrap().get(&key).cloned()
+        .filter(|scan| Utc::now() - scan.scanned_at < Duration::minutes(SCAN_TTL_MINUTES));
+
+    let Some(mut scan) = cached else {
+        let files = provider.list_files(Path::new("")).await?;
+        store(key, CachedScan { files: files.clone(), scanned_at: Utc::now(), stale: BTreeSet::new() }, &BTreeSet::new());
+        return Ok(files);
+    };
+
+    let refreshed = std::mem::take(&mut scan.stale);
+    for subtree in &refreshed {
+        match provider.list_subtree(subtree).await? {
+            Some(files) => {
+                scan.files.retain(|f| !f.path.starts_with(subtree));
+                scan.files.extend(files);
+            }
+            None => {
+                tracing::debug!("{} can't list {} on its own, rescanning", provider.name(), subtree.display());
+                scan = CachedScan { files: provider.list_files(Path::new("")).await?, scanned_at: Utc::now(), stale: BTreeSet::new() };
+                break;
+            }
+        }
+    }
+
+    let files = scan.files.clone();
+    store(key, scan, &refreshed);
+    Ok(files)
+}
+
+/// Store a scan, keeping subtrees invalidated while it was being taken.
+fn store(key: (i64, FileLocation), mut scan: CachedScan, refreshed: &BTreeSet<PathBuf>) {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(previous) = cache.get(&key) {
+        scan.stale.extend(previous.stale.difference(refreshed).cloned());
+    }
+    cache.insert(key, scan);
+}
+
+/// Mark `subtree` (a directory or a single file, relative to the location root)
+/// as changed, at one location or all of them. An empty path drops the whole scan.
+pub fn invalidate(profile_id: i64, location: Option<&FileLocation>, subtree: &Path) {
+    let mut cache = CACHE.lock().unwrap();
+    if subtree.as_os_str().is_empty() {
+        cache.retain(|(id, loc), _| *id != profile_id || location.is_some_and(|l| l != loc));
+        return;
+    }
+    for ((id, loc), scan) in cache.iter_mut() {
+        if *id == profile_id && location.is_none_or(|l| l == loc) {
+            scan.stale.insert(subtree.to_path_buf());
+        }
+    }
+}
+
+/// Drop every cached scan of a profile, e.g. after its locations were reconfigured.
+pub fn clear_profile(profile_id: i64) {
+    invalidate(profile_id, None, Path::new(""));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::local_fs::LocalFsProvider;
+
+    #[tokio::test]
+    async fn test_invalidated_subtree_is_listed_again() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("parts")).unwrap();
+        std::fs::write(root.path().join("parts/a.step"), "a").unwrap();
+        std::fs::write(root.path().join("top.step"), "top").unwrap();
+        let provider = LocalFsProvider::new(root.path().to_path_buf());
+        let paths = |files: Vec<FileMetadata>| {
+            let mut paths: Vec<_> = files.into_iter().map(|f| f.path).collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(list_files(-1, &FileLocation::Local, &provider).await.unwrap().len(), 2);
+
+        // Unnoticed until the subtree is invalidated
+        std::fs::write(root.path().join("parts/b.step"), "b").unwrap();
+        std::fs::remove_file(root.path().join("top.step")).unwrap();
+        assert_eq!(list_files(-1, &FileLocation::Local, &provider).await.unwrap().len(), 2);
+
+        invalidate(-1, Some(&FileLocation::Local), Path::new("parts"));
+        invalidate(-1, None, Path::new("top.step"));
+        let files = list_files(-1, &FileLocation::Local, &provider).await.unwrap();
+        assert_eq!(paths(files), vec![PathBuf::from("parts/a.step"), PathBuf::from("parts/b.step")]);
+
+        clear_profile(-1);
+        assert!(CACHE.lock().unwrap().keys().all(|(id, _)| *id != -1));
+    }
+}
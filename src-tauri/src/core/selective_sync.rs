@@ -0,0 +1,97 @@
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::glob;
+use std::path::{Component, Path};
+
+/// Which files a profile syncs, by glob patterns (see `utils::glob`). A file is
+/// synced when it matches an include pattern, or there are none, and no exclude
+/// pattern. Files left out are not scanned at all, so they are never
+/// transferred and don't count towards the deletion thresholds.
+///
+/// Patterns without a `/` match the file name or the name of any folder on the
+/// path (`*.bak`, `~$*`, `OldVersions`); patterns with one match the whole
+/// relative path, and a trailing `/` takes in everything below a folder
+/// (`Archive/`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectiveSync {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl SelectiveSync {
+    /// Parse patterns separated by commas or line breaks, e.g. `*.dwg, *.step`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn from_config(include: Option<&str>, exclude: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            include: parse_patterns(include)?,
+            exclude: parse_patterns(exclude)?,
+        })
+    }
+
+    /// Whether `path`, relative to the profile's root, is synced.
+    pub fn selects(&self, path: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| matches(p, path)))
+            && !self.exclude.iter().any(|p| matches(p, path))
+    }
+}
+
+fn parse_patterns(config: Option<&str>) -> Result<Vec<String>> {
+    let mut patterns = Vec::new();
+
+    for line in config.unwrap_or("").lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        for pattern in line.split(',').map(|p| p.trim().replace('\\', "/")).filter(|p| !p.is_empty()) {
+            let relative = pattern.trim_start_matches('/');
+            if relative.is_empty() || relative.split('/').any(|part| part == "..") {
+                return Err(UvcadError::InvalidConfig(format!("Invalid sync pattern: {}", pattern)));
+            }
+            // `Archive/` stands for everything below the folder
+            patterns.push(match relative.strip_suffix('/') {
+                Some(folder) => format!("{}/*", folder),
+                None => relative.to_string(),
+            });
+        }
+    }
+
+    Ok(patterns)
+}
+
+fn matches(pattern: &str, path: &Path) -> bool {
+    if pattern.contains('/') {
+        return glob::matches(pattern, &path.to_string_lossy().replace('\\', "/"));
+    }
+    path.components().any(|c| match c {
+        Component::Normal(name) => glob::matches(pattern, &name.to_string_lossy()),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_includes_and_excludes() {
+        let selection = SelectiveSync::from_config(
+            Some("*.dwg, *.step\n# released drawings\nreleased/*.pdf"),
+            Some("*.bak, ~$*\nArchive/"),
+        ).unwrap();
+
+        let selects = |path: &str| selection.selects(Path::new(path));
+        assert!(selects("ProjectX/plan.DWG"));
+        assert!(selects("ProjectX/bracket.step"));
+        assert!(selects("released/plan.pdf"));
+        assert!(!selects("ProjectX/notes.pdf"));
+        assert!(!selects("ProjectX/~$plan.dwg"));
+        assert!(!selects("Archive/2019/plan.dwg"));
+
+        // Without include patterns everything not excluded is synced
+        let selection = SelectiveSync::from_config(None, Some("OldVersions, *.bak")).unwrap();
+        assert!(selection.selects(Path::new("ProjectX/notes.txt")));
+        assert!(!selection.selects(Path::new("ProjectX/OldVersions/part.ipt")));
+        assert!(!selection.selects(Path::new("ProjectX/part.ipt.bak")));
+
+        assert!(SelectiveSync::from_config(Some("../*.dwg"), None).is_err());
+    }
+}
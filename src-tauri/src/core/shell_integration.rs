@@ -0,0 +1,429 @@
+use crate::utils::error::{Result, UvcadError};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Scheme of the links the file manager actions start UVCAD with, e.g.
+/// `uvcad://sync?path=C:\Projects\frame.dwg`.
+pub const SCHEME: &str = "uvcad";
+
+/// Right-click actions UVCAD adds to the file manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShellAction {
+    SyncNow,
+    ViewVersions,
+    LockFile,
+}
+
+impl ShellAction {
+    pub const ALL: [ShellAction; 3] = [ShellAction::SyncNow, ShellAction::ViewVersions, ShellAction::LockFile];
+
+    /// Host part of the action's links.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShellAction::SyncNow => "sync",
+            ShellAction::ViewVersions => "versions",
+            ShellAction::LockFile => "lock",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.as_str().eq_ignore_ascii_case(s))
+    }
+
+    /// The menu entry.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShellAction::SyncNow => "UVCAD: Sync now",
+            ShellAction::ViewVersions => "UVCAD: View versions",
+            ShellAction::LockFile => "UVCAD: Lock file",
+        }
+    }
+
+    /// Whether the action is offered on folders too, not only on files.
+    #[cfg(any(target_os = "windows", target_os = "macos", test))]
+    pub fn applies_to_folders(&self) -> bool {
+        !matches!(self, ShellAction::ViewVersions)
+    }
+}
+
+/// A `uvcad://` link: an action on a file or folder, by absolute path or
+/// relative to the active profile's root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepLink {
+    pub action: ShellAction,
+    pub path: PathBuf,
+}
+
+impl DeepLink {
+    /// Parse `uvcad://<action>?path=<path>`. Everything after `path=` is the
+    /// path, percent-decoded, so the file managers can pass theirs as is.
+    pub fn parse(url: &str) -> Result<Self> {
+        let invalid = || UvcadError::InvalidConfig(format!("Invalid UVCAD link: {}", url));
+        let rest = url.trim()
+            .strip_prefix(SCHEME)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .ok_or_else(invalid)?;
+        let (action, query) = rest.split_once('?').ok_or_else(invalid)?;
+        let action = ShellAction::from_str(action.trim_end_matches('/')).ok_or_else(invalid)?;
+        let path = percent_decode(query.strip_prefix("path=").ok_or_else(invalid)?);
+        if path.trim().is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self { action, path: PathBuf::from(path) })
+    }
+}
+
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.trim().starts_with(&format!("{}://", SCHEME))
+}
+
+/// Add the actions to the file manager's right-click menu for the current
+/// user, starting `exe` with the action's link: context menu entries and the
+/// `uvcad` URL protocol in the registry on Windows, Quick Actions in
+/// ~/Library/Services on macOS and Nautilus scripts on Linux.
+pub fn register(exe: &Path) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        for (key, name, data) in registry_entries(exe) {
+            let mut args = vec!["add".to_string(), key];
+            match name {
+                Some(name) => args.extend(["/v".to_string(), name.to_string()]),
+                None => args.push("/ve".to_string()),
+            }
+            args.extend(["/d".to_string(), data, "/f".to_string()]);
+            reg(&args)?;
+        }
+        Ok(())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let services = home_dir()?.join("Library").join("Services");
+        for action in ShellAction::ALL {
+            let contents = services.join(format!("{}.workflow", action.label())).join("Contents");
+            std::fs::create_dir_all(&contents)?;
+            std::fs::write(contents.join("Info.plist"), service_info_plist(action))?;
+            std::fs::write(contents.join("document.wflow"), service_workflow(action, exe))?;
+        }
+        // Make the new services show up without logging out
+        let _ = std::process::Command::new("/System/Library/CoreServices/pbs").arg("-update").output();
+        Ok(())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let scripts = nautilus_scripts_dir()?;
+        std::fs::create_dir_all(&scripts)?;
+        for action in ShellAction::ALL {
+            let script = scripts.join(action.label());
+            std::fs::write(&script, nautilus_script(action, exe))?;
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = exe;
+        Err(UvcadError::InvalidConfig("File manager integration is not supported on this platform".to_string()))
+    }
+}
+
+/// Remove what `register` added. Entries already gone are skipped.
+pub fn unregister() -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        for key in registry_keys() {
+            // Fails when the key doesn't exist
+            let _ = reg(&["delete".to_string(), key, "/f".to_string()]);
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let services = home_dir()?.join("Library").join("Services");
+        for action in ShellAction::ALL {
+            let workflow = services.join(format!("{}.workflow", action.label()));
+            if workflow.exists() {
+                std::fs::remove_dir_all(workflow)?;
+            }
+        }
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let scripts = nautilus_scripts_dir()?;
+        for action in ShellAction::ALL {
+            let script = scripts.join(action.label());
+            if script.exists() {
+                std::fs::remove_file(script)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Open the file manager at `path`, with the file selected where the platform
+/// allows; on Linux the folder holding it is opened.
+pub fn reveal(path: &Path) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("explorer").arg(format!("/select,{}", path.display())).spawn()?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open").arg("-R").arg(path).spawn()?;
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+        open::that(folder)?;
+    }
+    Ok(())
+}
+
+/// The per-user registry values `register` sets on Windows: the `uvcad` URL
+/// protocol, and an entry per action for all files (`*`) and, where the
+/// action applies to them, for folders (`Directory`).
+#[cfg(any(target_os = "windows", test))]
+fn registry_entries(exe: &Path) -> Vec<(String, Option<&'static str>, String)> {
+    let exe = exe.display();
+    let protocol = format!(r"HKCU\Software\Classes\{}", SCHEME);
+    let mut entries = vec![
+        (protocol.clone(), None, "URL:UVCAD".to_string()),
+        (protocol.clone(), Some("URL Protocol"), String::new()),
+        (format!(r"{}\shell\open\command", protocol), None, format!("\"{}\" \"%1\"", exe)),
+    ];
+    for action in ShellAction::ALL {
+        for key in action_keys(action) {
+            entries.push((key.clone(), Some("MUIVerb"), action.label().to_string()));
+            entries.push((
+                format!(r"{}\command", key),
+                None,
+                format!("\"{}\" \"{}://{}?path=%1\"", exe, SCHEME, action.as_str()),
+            ));
+        }
+    }
+    entries
+}
+
+#[cfg(any(target_os = "windows", test))]
+fn registry_keys() -> Vec<String> {
+    let mut keys = vec![format!(r"HKCU\Software\Classes\{}", SCHEME)];
+    keys.extend(ShellAction::ALL.into_iter().flat_map(action_keys));
+    keys
+}
+
+#[cfg(any(target_os = "windows", test))]
+fn action_keys(action: ShellAction) -> Vec<String> {
+    let classes: &[&str] = if action.applies_to_folders() { &["*", "Directory"] } else { &["*"] };
+    classes.iter()
+        .map(|class| format!(r"HKCU\Software\Classes\{}\shell\UVCAD.{}", class, action.as_str()))
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn reg(args: &[String]) -> Result<()> {
+    let output = std::process::Command::new("reg").args(args).output()?;
+    if !output.status.success() {
+        return Err(UvcadError::InvalidConfig(format!(
+            "reg {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn home_dir() -> Result<PathBuf> {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .ok_or_else(|| UvcadError::InvalidConfig("Failed to get home directory".to_string()))
+}
+
+#[cfg(target_os = "linux")]
+fn nautilus_scripts_dir() -> Result<PathBuf> {
+    Ok(home_dir()?.join(".local").join("share").join("nautilus").join("scripts"))
+}
+
+/// Shell commands starting `exe` once with the action's link for every
+/// selected file passed as an argument.
+#[cfg(any(target_os = "macos", target_os = "linux", test))]
+fn launch_command(action: ShellAction, exe: &Path) -> String {
+    format!(
+        "links=()\nfor f in \"$@\"; do links+=(\"{}://{}?path=$f\"); done\n'{}' \"${{links[@]}}\" >/dev/null 2>&1 &\n",
+        SCHEME,
+        action.as_str(),
+        exe.display().to_string().replace('\'', r"'\''"),
+    )
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn nautilus_script(action: ShellAction, exe: &Path) -> String {
+    format!("#!/bin/bash\n# {}\n{}", action.label(), launch_command(action, exe))
+}
+
+#[cfg(target_os = "macos")]
+fn service_info_plist(action: ShellAction) -> String {
+    let types = if action.applies_to_folders() { "public.item" } else { "public.data" };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict><key>default</key><string>{}</string></dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict><key>NSApplicationIdentifier</key><string>com.apple.finder</string></dict>
+			<key>NSSendFileTypes</key>
+			<array><string>{}</string></array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#,
+        action.label(),
+        types,
+    )
+}
+
+/// An Automator Quick Action with a single Run Shell Script step getting the
+/// selected files as arguments.
+#[cfg(target_os = "macos")]
+fn service_workflow(action: ShellAction, exe: &Path) -> String {
+    let script = launch_command(action, exe).replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>521</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key><string>List</string>
+					<key>Optional</key><true/>
+					<key>Types</key><array><string>com.apple.cocoa.path</string></array>
+				</dict>
+				<key>AMActionVersion</key><string>2.0.3</string>
+				<key>AMParameterProperties</key>
+				<dict>
+					<key>COMMAND_STRING</key><dict/>
+					<key>inputMethod</key><dict/>
+					<key>shell</key><dict/>
+				</dict>
+				<key>AMProvides</key>
+				<dict>
+					<key>Container</key><string>List</string>
+					<key>Types</key><array><string>com.apple.cocoa.string</string></array>
+				</dict>
+				<key>ActionBundlePath</key><string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key><string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key><string>{}</string>
+					<key>CheckedForUserDefaultShell</key><true/>
+					<key>inputMethod</key><integer>1</integer>
+					<key>shell</key><string>/bin/bash</string>
+					<key>source</key><string></string>
+				</dict>
+				<key>BundleIdentifier</key><string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key><string>2.0.3</string>
+				<key>CanShowSelectedItemsWhenRun</key><false/>
+				<key>CanShowWhenRun</key><true/>
+				<key>Class Name</key><string>RunShellScriptAction</string>
+				<key>InputUUID</key><string>{}</string>
+				<key>UUID</key><string>{}</string>
+			</dict>
+		</dict>
+	</array>
+	<key>connectors</key>
+	<dict/>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceApplicationBundleID</key><string>com.apple.finder</string>
+		<key>serviceInputTypeIdentifier</key><string>com.apple.Automator.fileSystemObject</string>
+		<key>serviceOutputTypeIdentifier</key><string>com.apple.Automator.nothing</string>
+		<key>workflowTypeIdentifier</key><string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#,
+        script,
+        uuid::Uuid::new_v4().to_string().to_uppercase(),
+        uuid::Uuid::new_v4().to_string().to_uppercase(),
+    )
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = value.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_carry_the_action_and_path() {
+        // As the file managers pass them, unencoded
+        let link = DeepLink::parse(r"uvcad://sync?path=C:\Projects\Frame & Base\frame.dwg").unwrap();
+        assert_eq!(link.action, ShellAction::SyncNow);
+        assert_eq!(link.path, PathBuf::from(r"C:\Projects\Frame & Base\frame.dwg"));
+
+        let link = DeepLink::parse("uvcad://versions/?path=ProjectX/Bracket%20A%C3%A9.step").unwrap();
+        assert_eq!(link, DeepLink { action: ShellAction::ViewVersions, path: PathBuf::from("ProjectX/Bracket Aé.step") });
+
+        assert!(DeepLink::parse("uvcad://erase?path=frame.dwg").is_err());
+        assert!(DeepLink::parse("uvcad://lock?path=").is_err());
+        assert!(DeepLink::parse("https://lock?path=frame.dwg").is_err());
+        assert!(is_deep_link("uvcad://lock?path=frame.dwg"));
+    }
+
+    #[test]
+    fn test_menu_entries_start_uvcad_with_the_link() {
+        let exe = Path::new(r"C:\Program Files\UVCAD\uvcad.exe");
+        let entries = registry_entries(exe);
+        assert!(entries.contains(&(
+            r"HKCU\Software\Classes\*\shell\UVCAD.lock\command".to_string(),
+            None,
+            r#""C:\Program Files\UVCAD\uvcad.exe" "uvcad://lock?path=%1""#.to_string(),
+        )));
+        // Versions are only offered on files
+        assert!(entries.iter().any(|(key, _, _)| key == r"HKCU\Software\Classes\Directory\shell\UVCAD.sync"));
+        assert!(!entries.iter().any(|(key, _, _)| key == r"HKCU\Software\Classes\Directory\shell\UVCAD.versions"));
+        assert_eq!(registry_keys().len(), 6);
+
+        let script = nautilus_script(ShellAction::SyncNow, Path::new("/opt/UVCAD's/uvcad"));
+        assert!(script.contains(r#"links+=("uvcad://sync?path=$f")"#));
+        assert!(script.contains(r"'/opt/UVCAD'\''s/uvcad' "));
+    }
+}
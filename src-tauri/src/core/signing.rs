@@ -0,0 +1,189 @@
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::secrets;
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Extension appended to the file name for the signature kept next to each
+/// signed file at the share and on Drive.
+pub const SIGNATURE_EXTENSION: &str = "uvcadsig";
+
+/// Secret holding this computer's signing key (PKCS#8, hex).
+const KEY_SECRET: &str = "digest_signing_key";
+
+/// Contents of a `.uvcadsig` file: who vouched for which content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    /// SHA-256 of the uncompressed content.
+    pub sha256: String,
+    /// Ed25519 signature over the path and `sha256`, hex.
+    pub signature: String,
+    /// The signer's public key, hex.
+    pub public_key: String,
+    /// The user who synced the file.
+    pub signer: String,
+    pub signed_at: DateTime<Utc>,
+}
+
+/// Outcome of checking a downloaded file against its signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Valid,
+    /// The file has no signature, e.g. it was added before signing was enabled.
+    Unsigned,
+    /// The content differs from what was signed: it was changed outside UVCAD.
+    ContentChanged,
+    /// The signature does not match, e.g. it was copied from another file.
+    BadSignature,
+    /// Signed by a key that is neither this computer's nor a trusted one.
+    UntrustedSigner,
+}
+
+impl Verification {
+    pub fn is_tampered(&self) -> bool {
+        !matches!(self, Verification::Valid | Verification::Unsigned)
+    }
+
+    pub fn describe(&self) -> &str {
+        match self {
+            Verification::Valid => "the signature is valid",
+            Verification::Unsigned => "the file is not signed",
+            Verification::ContentChanged => "the content changed after it was signed",
+            Verification::BadSignature => "the signature does not match the file",
+            Verification::UntrustedSigner => "it was signed by an untrusted key",
+        }
+    }
+}
+
+pub fn is_signature(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case(SIGNATURE_EXTENSION))
+        .unwrap_or(false)
+}
+
+/// `part.sldprt` -> `part.sldprt.uvcadsig`
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// What gets signed. The path is included so a signature can't vouch for another file.
+fn message(path: &Path, sha256: &str) -> Vec<u8> {
+    format!("{}\n{}", path.to_string_lossy().replace('\\', "/"), sha256).into_bytes()
+}
+
+/// The public keys in a `trusted_signing_keys` setting, lowercased.
+pub fn parse_trusted_keys(value: Option<&str>) -> Result<HashSet<String>> {
+    let mut keys = HashSet::new();
+    for key in value.unwrap_or("").split(',').map(str::trim).filter(|k| !k.is_empty()) {
+        match hex::decode(key) {
+            Ok(bytes) if bytes.len() == 32 => keys.insert(key.to_lowercase()),
+            _ => return Err(UvcadError::InvalidConfig(format!("Invalid public key: {}", key))),
+        };
+    }
+    Ok(keys)
+}
+
+/// Signs with this computer's key and trusts signatures by it and by the
+/// configured keys of the other computers syncing the profile.
+pub struct DigestSigner {
+    key_pair: Ed25519KeyPair,
+    signer: String,
+    trusted: HashSet<String>,
+}
+
+impl DigestSigner {
+    pub fn new(pkcs8: &[u8], signer: String, trusted_keys: Option<&str>) -> Result<Self> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|_| UvcadError::InvalidConfig("Invalid signing key".to_string()))?;
+        let mut trusted = parse_trusted_keys(trusted_keys)?;
+        trusted.insert(hex::encode(key_pair.public_key().as_ref()));
+        Ok(Self { key_pair, signer, trusted })
+    }
+
+    /// Sign with the key stored for this computer, creating it on first use.
+    pub fn for_this_machine(signer: String, trusted_keys: Option<&str>) -> Result<Self> {
+        Self::new(&machine_key()?, signer, trusted_keys)
+    }
+
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key_pair.public_key().as_ref())
+    }
+
+    pub fn sign(&self, path: &Path, sha256: &str) -> Signature {
+        Signature {
+            sha256: sha256.to_string(),
+            signature: hex::encode(self.key_pair.sign(&message(path, sha256)).as_ref()),
+            public_key: self.public_key(),
+            signer: self.signer.clone(),
+            signed_at: Utc::now(),
+        }
+    }
+
+    pub fn verify(&self, path: &Path, sha256: &str, signature: &Signature) -> Verification {
+        let key = signature.public_key.to_lowercase();
+        let valid = match (hex::decode(&key), hex::decode(&signature.signature)) {
+            (Ok(public_key), Ok(bytes)) => UnparsedPublicKey::new(&signature::ED25519, public_key)
+                .verify(&message(path, &signature.sha256), &bytes)
+                .is_ok(),
+            _ => false,
+        };
+        if !valid {
+            Verification::BadSignature
+        } else if !self.trusted.contains(&key) {
+            Verification::UntrustedSigner
+        } else if !signature.sha256.eq_ignore_ascii_case(sha256) {
+            Verification::ContentChanged
+        } else {
+            Verification::Valid
+        }
+    }
+}
+
+/// This computer's signing key, generated and stored on first use.
+fn machine_key() -> Result<Vec<u8>> {
+    let store = secrets::store()?;
+    if let Some(stored) = store.get(KEY_SECRET)? {
+        return hex::decode(stored.trim())
+            .map_err(|_| UvcadError::InvalidConfig("Stored signing key is corrupt".to_string()));
+    }
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map_err(|_| UvcadError::InvalidConfig("Failed to generate a signing key".to_string()))?;
+    store.set(KEY_SECRET, &hex::encode(pkcs8.as_ref()))?;
+    tracing::info!("Generated a signing key for this computer");
+    Ok(pkcs8.as_ref().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(trusted: Option<&str>) -> DigestSigner {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        DigestSigner::new(pkcs8.as_ref(), "alice".to_string(), trusted).unwrap()
+    }
+
+    #[test]
+    fn test_detects_changes_made_outside_uvcad() {
+        let ours = signer(None);
+        let path = Path::new("parts/bracket.sldprt");
+        let signed = ours.sign(path, "abc123");
+        assert_eq!(ours.verify(path, "abc123", &signed), Verification::Valid);
+        assert_eq!(ours.verify(path, "def456", &signed), Verification::ContentChanged);
+        assert_eq!(ours.verify(Path::new("parts/other.sldprt"), "abc123", &signed), Verification::BadSignature);
+
+        let theirs = signer(None);
+        assert_eq!(ours.verify(path, "abc123", &theirs.sign(path, "abc123")), Verification::UntrustedSigner);
+        let trusting = signer(Some(&theirs.public_key()));
+        assert_eq!(trusting.verify(path, "abc123", &theirs.sign(path, "abc123")), Verification::Valid);
+
+        assert!(is_signature(&signature_path(path)));
+        assert!(DigestSigner::new(&[0; 8], String::new(), None).is_err());
+    }
+}
@@ -0,0 +1,937 @@
+use crate::core::circuit_breaker::{CircuitBreaker, TRIP_AFTER_FAILURES};
+use crate::core::consistency::ConsistencyGroups;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::naming::NamingConvention;
+use crate::core::sync_engine::SyncEngine;
+use crate::db::{handle::DbHandle, models::DbOperations, schema::Database};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_plan::PlanStep;
+use crate::models::sync_profile::SyncProfile;
+use crate::models::transfer::TransferStatus;
+use crate::providers::mock::{MockProvider, Outage};
+use crate::providers::traits::StorageProvider;
+use crate::utils::error::{Result, UvcadError};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Files seeded when the caller has none of its own.
+const SYNTHETIC_FILES: usize = 100;
+/// Real file names used as seed are capped to keep simulations quick.
+const MAX_SEED_FILES: usize = 500;
+
+/// A situation played against the engine after a clean baseline sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// Most local files disappear, as when a drive is unmounted.
+    MassDelete,
+    /// Some files are edited both locally and on Google Drive, others only on the NAS.
+    DivergentEdits,
+    /// New local files are uploaded over a slow connection that drops every third transfer.
+    FlakyNetwork,
+    /// New local files need more space than is left in the Google Drive quota.
+    QuotaExceeded,
+    /// The NAS goes offline partway through uploading new local files.
+    SmbDropout,
+    /// Google Drive rejects every call because the access token expired.
+    ExpiredToken,
+}
+
+impl Scenario {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Scenario::MassDelete => "mass_delete",
+            Scenario::DivergentEdits => "divergent_edits",
+            Scenario::FlakyNetwork => "flaky_network",
+            Scenario::QuotaExceeded => "quota_exceeded",
+            Scenario::SmbDropout => "smb_dropout",
+            Scenario::ExpiredToken => "expired_token",
+        }
+    }
+
+    pub fn from_str_opt(s: &str) -> Option<Self> {
+        match s {
+            "mass_delete" => Some(Scenario::MassDelete),
+            "divergent_edits" => Some(Scenario::DivergentEdits),
+            "flaky_network" => Some(Scenario::FlakyNetwork),
+            "quota_exceeded" => Some(Scenario::QuotaExceeded),
+            "smb_dropout" => Some(Scenario::SmbDropout),
+            "expired_token" => Some(Scenario::ExpiredToken),
+            _ => None,
+        }
+    }
+
+    /// Whether a location becomes unavailable, after which the report also
+    /// covers how the engine recovers once it is back.
+    pub fn is_outage(&self) -> bool {
+        matches!(self, Scenario::SmbDropout | Scenario::ExpiredToken)
+    }
+}
+
+/// Profile settings that shape what the engine does.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationPolicies {
+    pub naming: NamingConvention,
+    pub extensions: ExtensionPolicies,
+    pub groups: ConsistencyGroups,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationReport {
+    pub scenario: String,
+    pub files: usize,
+    /// What the scenario changed between the baseline and the simulated sync.
+    pub changes: Vec<String>,
+    /// Why the engine refused to sync, e.g. the deletion safety check.
+    pub aborted: Option<String>,
+    pub files_synced: usize,
+    pub files_failed: usize,
+    pub conflicts: Vec<String>,
+    pub blocked: Vec<String>,
+    pub warnings: Vec<String>,
+    pub plan: Vec<PlanStep>,
+    /// Files transferred after a simulated outage ended, by retrying the failed
+    /// transfers or syncing again. None when the scenario has no outage.
+    pub recovered: Option<usize>,
+}
+
+#[derive(Clone)]
+struct Locations {
+    local: MockProvider,
+    gdrive: MockProvider,
+    smb: MockProvider,
+}
+
+impl Locations {
+    fn get(&self, location: &FileLocation) -> &MockProvider {
+        match location {
+            FileLocation::Local => &self.local,
+            FileLocation::GoogleDrive => &self.gdrive,
+            FileLocation::Smb => &self.smb,
+        }
+    }
+}
+
+/// Run `scenario` against in-memory copies of the three locations and an
+/// in-memory database. `seed` names the files to simulate with (e.g. the real
+/// local file list); synthetic part files are used when it is empty.
+pub async fn simulate(scenario: Scenario, seed: &[PathBuf], policies: &SimulationPolicies) -> Result<SimulationReport> {
+    let mut paths: Vec<PathBuf> = seed.iter().take(MAX_SEED_FILES).cloned().collect();
+    if paths.is_empty() {
+        paths = (1..=SYNTHETIC_FILES).map(|i| PathBuf::from(format!("parts/part-{:03}.sldprt", i))).collect();
+    }
+
+    let database = Database::in_memory()?;
+    database.initialize()?;
+    let db = DbHandle::new(database);
+    let profile_id = db.call(|conn| {
+        DbOperations::create_sync_profile(conn, &SyncProfile::new("Simulation".to_string(), String::new()))
+    }).await?;
+
+    // Baseline: everything local, synced once so the engine knows the last state
+    let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+    for path in &paths {
+        locations.local.put(path.clone(), format!("{} v1", path.display()));
+    }
+    engine(profile_id, &locations, db.clone(), policies).start_sync().await?;
+
+    let mut changes = Vec::new();
+    // Outages only affect the run's view of the locations; recovery uses these
+    let healthy = locations.clone();
+    let run_locations = match scenario {
+        Scenario::MassDelete => {
+            let removed = paths.len() * 3 / 5;
+            for path in &paths[..removed] {
+                locations.local.remove(path);
+            }
+            changes.push(format!("Deleted {} of {} local files", removed, paths.len()));
+            locations
+        }
+        Scenario::DivergentEdits => {
+            // Edit only files stored uncompressed so the edits can be written as-is
+            let extensions = &policies.extensions;
+            let editable = |location: FileLocation| move |path: &&PathBuf| !extensions.is_compressed_at(path, &location);
+            let both: Vec<&PathBuf> = paths.iter().filter(editable(FileLocation::GoogleDrive)).take(3).collect();
+            for path in &both {
+                locations.local.put((*path).clone(), format!("{} local edit", path.display()));
+                edit_at(&locations, policies, &FileLocation::GoogleDrive, path, "drive edit");
+                changes.push(format!("Edited {} locally and on Google Drive", path.display()));
+            }
+            for path in paths.iter().filter(editable(FileLocation::Smb)).filter(|p| !both.contains(p)).take(2) {
+                edit_at(&locations, policies, &FileLocation::Smb, path, "NAS edit");
+                changes.push(format!("Edited {} on the NAS", path.display()));
+            }
+            locations
+        }
+        Scenario::FlakyNetwork => {
+            for i in 1..=20 {
+                locations.local.put(format!("simulated/new-{:03}.step", i), format!("new file {}", i));
+            }
+            changes.push("Added 20 local files; Google Drive drops every third transfer".to_string());
+            Locations {
+                gdrive: locations.gdrive.clone()
+                    .with_latency(Duration::from_millis(20))
+                    .with_fail_every(3),
+                ..locations
+            }
+        }
+        Scenario::QuotaExceeded => {
+            for i in 1..=5 {
+                locations.local.put(format!("simulated/large-{}.step", i), vec![b'x'; i * 1024]);
+            }
+            changes.push("Added 5 local files (15 KB); Google Drive has 4 KB free".to_string());
+            let used: usize = paths.iter().map(|p| format!("{} v1", p.display()).len()).sum();
+            Locations {
+                gdrive: locations.gdrive.clone().with_quota(used as u64 + 4 * 1024),
+                ..locations
+            }
+        }
+        Scenario::SmbDropout => {
+            for i in 1..=20 {
+                locations.local.put(format!("simulated/new-{:03}.step", i), format!("new file {}", i));
+            }
+            changes.push("Added 20 local files; the NAS goes offline after 5 transfers".to_string());
+            Locations {
+                smb: locations.smb.clone().with_outage(Outage::Disconnect { after_transfers: 5 }),
+                ..locations
+            }
+        }
+        Scenario::ExpiredToken => {
+            for i in 1..=5 {
+                locations.local.put(format!("simulated/new-{}.step", i), format!("new file {}", i));
+            }
+            changes.push("Added 5 local files; the Google Drive access token has expired".to_string());
+            Locations {
+                gdrive: locations.gdrive.clone().with_outage(Outage::ExpiredToken),
+                ..locations
+            }
+        }
+    };
+
+    let mut report = SimulationReport {
+        scenario: scenario.as_str().to_string(),
+        files: paths.len(),
+        changes,
+        aborted: None,
+        files_synced: 0,
+        files_failed: 0,
+        conflicts: Vec::new(),
+        blocked: Vec::new(),
+        warnings: Vec::new(),
+        plan: Vec::new(),
+        recovered: None,
+    };
+    match engine(profile_id, &run_locations, db.clone(), policies).start_sync().await {
+        Ok(result) => {
+            report.files_synced = result.files_synced;
+            report.files_failed = result.files_failed;
+            report.conflicts = result.conflicts.iter().map(|c| c.file_path.clone()).collect();
+            report.blocked = result.blocked;
+            report.warnings = result.warnings;
+            report.plan = result.plan;
+        }
+        Err(UvcadError::SyncFailed(reason)) => report.aborted = Some(reason),
+        Err(e) if scenario.is_outage() => report.aborted = Some(e.to_string()),
+        Err(e) => return Err(e),
+    }
+
+    if scenario.is_outage() {
+        report.recovered = Some(recover(profile_id, &healthy, db, policies).await?);
+    }
+    Ok(report)
+}
+
+/// Once the outage is over: retry what failed during the run, or sync again if
+/// nothing was attempted. Returns how many files were transferred.
+async fn recover(profile_id: i64, locations: &Locations, db: DbHandle, policies: &SimulationPolicies) -> Result<usize> {
+    let failed: Vec<i64> = db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+        .await?
+        .into_iter()
+        .filter(|t| t.status == TransferStatus::Failed)
+        .filter_map(|t| t.id)
+        .collect();
+
+    let engine = engine(profile_id, locations, db.clone(), policies);
+    let result = if failed.is_empty() {
+        engine.start_sync().await?
+    } else {
+        engine.retry_transfers(&failed).await?
+    };
+
+    let run_id = result.run_id;
+    let timings = db.call(move |conn| DbOperations::get_slowest_files(conn, &run_id, i64::MAX as usize)).await?;
+    Ok(timings.iter().map(|t| &t.file_path).collect::<HashSet<_>>().len())
+}
+
+fn edit_at(locations: &Locations, policies: &SimulationPolicies, location: &FileLocation, path: &Path, edit: &str) {
+    let stored = policies.extensions.stored_path(path, location);
+    locations.get(location).put(stored, format!("{} {}", path.display(), edit));
+}
+
+fn engine(profile_id: i64, locations: &Locations, db: DbHandle, policies: &SimulationPolicies) -> SyncEngine {
+    let provider = |mock: &MockProvider| -> Arc<Mutex<dyn StorageProvider>> { Arc::new(Mutex::new(mock.clone())) };
+    SyncEngine::new(
+        profile_id,
+        provider(&locations.local),
+        Some(provider(&locations.gdrive)),
+        Some(provider(&locations.smb)),
+        db,
+    )
+    .with_naming_convention(policies.naming.clone())
+    .with_extension_policies(policies.extensions.clone())
+    .with_consistency_groups(policies.groups.clone())
+    // Simulated failures are instant; backing off would only slow the report down
+    .with_circuit_breaker(CircuitBreaker::new(TRIP_AFTER_FAILURES, Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conflict_resolver::{Conflict, ConflictResolver};
+    use crate::core::drive_encryption::ProfileKeys;
+    use crate::core::path_prefix::PathPrefixes;
+    use crate::core::plan_approval::ApprovalPolicy;
+    use crate::core::selective_sync::SelectiveSync;
+    use crate::core::trash::{self, TrashPolicy};
+    use crate::models::conflict::ConflictResolution;
+    use crate::models::legal_hold::LegalHold;
+    use crate::models::paused_location::PausedLocation;
+    use crate::models::sync_phase::SyncPhase;
+    use crate::models::sync_plan::{Authorization, ChangeReason, OperationReason, PlannedOperation};
+    use crate::models::sync_run::{RunPhase, RunStatus};
+
+    #[tokio::test]
+    async fn test_mass_delete_is_stopped_by_safety_check() {
+        let report = simulate(Scenario::MassDelete, &[], &SimulationPolicies::default()).await.unwrap();
+        assert!(report.aborted.is_some());
+        assert_eq!(report.files_synced, 0);
+    }
+
+    #[tokio::test]
+    async fn test_divergent_edits_conflict() {
+        let report = simulate(Scenario::DivergentEdits, &[], &SimulationPolicies::default()).await.unwrap();
+        assert_eq!(report.aborted, None);
+        assert_eq!(report.conflicts.len(), 3);
+        assert_eq!(report.files_failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_plan_order_is_stable() {
+        let first = simulate(Scenario::DivergentEdits, &[], &SimulationPolicies::default()).await.unwrap();
+        let second = simulate(Scenario::DivergentEdits, &[], &SimulationPolicies::default()).await.unwrap();
+        assert_eq!(first.plan, second.plan);
+        assert!(first.plan.windows(2).all(|w| w[0].path <= w[1].path));
+        assert!(first.plan.iter().enumerate().all(|(i, step)| step.index == i));
+    }
+
+    #[tokio::test]
+    async fn test_plan_waits_for_approval() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Approval".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("parts/a.sldprt", "a");
+        let policy = ApprovalPolicy::from_config(Some("alice"), None).unwrap();
+        let policies = SimulationPolicies::default();
+
+        let held = engine(profile_id, &locations, db.clone(), &policies)
+            .with_plan_approval(policy.clone())
+            .start_sync().await.unwrap();
+        assert!(held.awaiting_approval);
+        let run_id = held.run_id.clone();
+        let run = db.call(move |conn| DbOperations::get_sync_run(conn, &run_id)).await.unwrap().unwrap();
+        assert_eq!(run.phase, Some(RunPhase::SafetyCheck));
+        assert!(locations.gdrive.content(Path::new("parts/a.sldprt")).is_none());
+
+        let approved = engine(profile_id, &locations, db.clone(), &policies)
+            .with_plan_approval(policy)
+            .with_approved_plan(held.plan, "alice")
+            .start_sync().await.unwrap();
+        assert!(!approved.awaiting_approval);
+        assert_eq!(locations.gdrive.content(Path::new("parts/a.sldprt")), Some(b"a".to_vec()));
+
+        // The file history says why the copies were made
+        let run_id = approved.run_id.clone();
+        let events = db.call(move |conn| DbOperations::get_file_events_for_run(conn, &run_id)).await.unwrap();
+        let copied: Vec<_> = events.iter().filter(|e| e.location != FileLocation::Local).collect();
+        assert_eq!(copied.len(), 2);
+        assert!(copied.iter().all(|e| e.reason == Some(OperationReason {
+            change: ChangeReason::Added { at: FileLocation::Local },
+            allowed_by: Authorization::Approved { by: "alice".to_string() },
+        })));
+        // The change found by the scan was not made by UVCAD
+        assert!(events.iter().any(|e| e.location == FileLocation::Local && e.reason.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_run_is_recorded_with_its_events() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Runs".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("parts/a.sldprt", "a");
+        // A run the app stopped during
+        db.call(move |conn| {
+            DbOperations::start_sync_run(conn, profile_id, "run-0")?;
+            DbOperations::set_sync_run_phase(conn, "run-0", RunPhase::Execute)?;
+            DbOperations::record_sync_outcome(conn, profile_id, false, Some("Share unreachable"))
+        }).await.unwrap();
+        let failed = db.call(move |conn| DbOperations::get_sync_profile(conn, profile_id)).await.unwrap().unwrap();
+        assert_eq!((failed.last_error.as_deref(), failed.last_successful_sync_at), (Some("Share unreachable"), None));
+
+        let result = engine(profile_id, &locations, db.clone(), &SimulationPolicies::default())
+            .with_run_id("run-1")
+            .start_sync().await.unwrap();
+        assert_eq!(result.run_id, "run-1");
+
+        let (run, events) = db.call(|conn| Ok((
+            DbOperations::get_sync_run(conn, "run-1")?.unwrap(),
+            DbOperations::get_file_events_for_run(conn, "run-1")?,
+        ))).await.unwrap();
+        assert_eq!((run.status, run.phase), (RunStatus::Completed, Some(RunPhase::Commit)));
+        assert_eq!(run.files_synced, 1);
+        let interrupted = db.call(|conn| DbOperations::get_sync_run(conn, "run-0")).await.unwrap().unwrap();
+        assert_eq!((interrupted.status, interrupted.phase), (RunStatus::Failed, Some(RunPhase::Execute)));
+        assert_eq!(interrupted.error_message.as_deref(), Some("Interrupted during the execute phase"));
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|e| e.run_id.as_deref() == Some("run-1")));
+        // The successful sync is noted on the profile and clears the earlier error
+        let profile = db.call(move |conn| DbOperations::get_sync_profile(conn, profile_id)).await.unwrap().unwrap();
+        assert_eq!(profile.last_error, None);
+        assert!(profile.last_successful_sync_at.is_some() && profile.last_successful_sync_at == profile.last_sync_at);
+
+        let timings = db.call(|conn| DbOperations::get_slowest_files(conn, "run-1", 10)).await.unwrap();
+        assert_eq!(timings.len(), 2);
+        assert!(timings.windows(2).all(|pair| pair[0].total_ms >= pair[1].total_ms));
+        assert!(timings.iter().all(|t| t.file_path == "parts/a.sldprt" && t.source == FileLocation::Local));
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_drive_folder_pairs_with_the_other_locations() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Prefixes".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.gdrive.put("Engineering/ProjectX/a.dwg", "a");
+        locations.gdrive.put("Sales/quote.pdf", "q");
+        locations.smb.put("ProjectX/b.dwg", "b");
+        let prefixes = PathPrefixes::from_config(Some("gdrive = Engineering")).unwrap();
+
+        let result = engine(profile_id, &locations, db, &SimulationPolicies::default())
+            .with_path_prefixes(prefixes)
+            .start_sync().await.unwrap();
+        assert_eq!(result.files_synced, 2);
+        assert_eq!(locations.local.content(Path::new("ProjectX/a.dwg")), Some(b"a".to_vec()));
+        assert_eq!(locations.smb.content(Path::new("ProjectX/a.dwg")), Some(b"a".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("Engineering/ProjectX/b.dwg")), Some(b"b".to_vec()));
+        // Drive files outside the prefix are not part of the sync
+        assert!(locations.local.content(Path::new("Sales/quote.pdf")).is_none());
+        assert!(locations.gdrive.content(Path::new("ProjectX/b.dwg")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_drive_copies_are_encrypted() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Encrypted".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        let keys = Arc::new(ProfileKeys::generate().unwrap());
+        locations.local.put("a.dwg", "a");
+        locations.gdrive.put("b.dwg", keys.encrypt(b"b").unwrap());
+
+        let result = engine(profile_id, &locations, db, &SimulationPolicies::default())
+            .with_drive_encryption(keys.clone())
+            .start_sync().await.unwrap();
+        assert_eq!(result.files_synced, 2);
+        let on_drive = locations.gdrive.content(Path::new("a.dwg")).unwrap();
+        assert_ne!(on_drive, b"a");
+        assert_eq!(keys.decrypt(&on_drive).unwrap(), b"a");
+        assert_eq!(locations.local.content(Path::new("b.dwg")), Some(b"b".to_vec()));
+        // Only Drive holds encrypted copies
+        assert_eq!(locations.smb.content(Path::new("b.dwg")), Some(b"b".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_working_set_syncs_first() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Working set".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("archive/old.dwg", "o");
+        locations.local.put("projects/x/part.sldprt", "p");
+        locations.local.put("readme.txt", "r");
+
+        let result = engine(profile_id, &locations, db, &SimulationPolicies::default())
+            .with_working_set(vec![PathBuf::from("projects/x")])
+            .start_sync().await.unwrap();
+        let first = result.plan.first().unwrap();
+        assert_eq!(first.path, "projects/x/part.sldprt");
+        // The rest keeps its usual order
+        let rest: Vec<&str> = result.plan.iter().map(|s| s.path.as_str()).filter(|p| !p.starts_with("projects/")).collect();
+        assert!(rest.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_selective_sync_leaves_out_unselected_files() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Selective".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("parts/plan.dwg", "p");
+        locations.local.put("parts/plan.bak", "b");
+        locations.local.put("parts/notes.txt", "n");
+        locations.smb.put("parts/~$plan.dwg", "l");
+        let selection = SelectiveSync::from_config(Some("*.dwg, *.bak"), Some("*.bak, ~$*")).unwrap();
+
+        let result = engine(profile_id, &locations, db, &SimulationPolicies::default())
+            .with_selective_sync(selection)
+            .start_sync().await.unwrap();
+        assert_eq!(result.files_synced, 1);
+        assert_eq!(locations.gdrive.content(Path::new("parts/plan.dwg")), Some(b"p".to_vec()));
+        assert!(locations.gdrive.content(Path::new("parts/plan.bak")).is_none());
+        assert!(locations.gdrive.content(Path::new("parts/notes.txt")).is_none());
+        assert!(locations.local.content(Path::new("parts/~$plan.dwg")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legal_hold_blocks_deletions_and_overwrites() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Hold".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("dispute/frame.dwg", "v1");
+        locations.local.put("dispute/bolt.sldprt", "b");
+        locations.local.put("other/plate.dwg", "p");
+        let policies = SimulationPolicies::default();
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        db.call(move |conn| DbOperations::place_legal_hold(conn, &LegalHold {
+            id: None,
+            profile_id,
+            path: "dispute".to_string(),
+            reason: "Claim by customer".to_string(),
+            placed_by: "anna".to_string(),
+            placed_at: chrono::Utc::now(),
+            released_by: None,
+            released_at: None,
+            release_reason: None,
+        })).await.unwrap();
+        locations.local.put("dispute/frame.dwg", "v2");
+        locations.local.remove(Path::new("dispute/bolt.sldprt"));
+        locations.local.put("dispute/notes.txt", "n");
+        locations.local.put("other/plate.dwg", "p2");
+
+        let result = engine(profile_id, &locations, db, &policies).start_sync().await.unwrap();
+        assert_eq!(result.held, ["dispute/bolt.sldprt", "dispute/frame.dwg"]);
+        assert_eq!(locations.gdrive.content(Path::new("dispute/frame.dwg")), Some(b"v1".to_vec()));
+        assert_eq!(locations.smb.content(Path::new("dispute/bolt.sldprt")), Some(b"b".to_vec()));
+        // Copies to where a held file is missing are still made
+        assert_eq!(locations.gdrive.content(Path::new("dispute/notes.txt")), Some(b"n".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("other/plate.dwg")), Some(b"p2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_paused_location_catches_up_when_resumed() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Pause".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("frame.dwg", "f1");
+        locations.local.put("bolt.sldprt", "b1");
+        locations.local.put("asm/plate.dwg", "p");
+        let policies = SimulationPolicies::default();
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        db.call(move |conn| DbOperations::pause_location(conn, &PausedLocation {
+            profile_id,
+            location: FileLocation::GoogleDrive,
+            reason: Some("Quota exhausted".to_string()),
+            paused_by: "anna".to_string(),
+            paused_at: chrono::Utc::now(),
+        })).await.unwrap();
+        locations.local.put("frame.dwg", "f2");
+        locations.gdrive.put("bolt.sldprt", "b2");
+        locations.local.remove(Path::new("asm/plate.dwg"));
+        locations.local.put("released/plate.dwg", "p");
+
+        // The share keeps up with local changes, the Drive is left alone
+        let result = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        assert_eq!(result.paused, ["bolt.sldprt", "frame.dwg", "released/plate.dwg"]);
+        assert_eq!(locations.smb.content(Path::new("frame.dwg")), Some(b"f2".to_vec()));
+        assert_eq!(locations.smb.content(Path::new("released/plate.dwg")), Some(b"p".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("frame.dwg")), Some(b"f1".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("asm/plate.dwg")), Some(b"p".to_vec()));
+        assert_eq!(locations.local.content(Path::new("bolt.sldprt")), Some(b"b1".to_vec()));
+
+        let resumed = db.call(move |conn| DbOperations::resume_location(conn, profile_id, &FileLocation::GoogleDrive))
+            .await
+            .unwrap();
+        assert!(resumed);
+        let result = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        let steps: Vec<(&str, PlannedOperation)> = result.plan.iter().map(|s| (s.path.as_str(), s.operation.clone())).collect();
+        assert_eq!(steps, [
+            ("bolt.sldprt", PlannedOperation::Copy { from: FileLocation::GoogleDrive, to: FileLocation::Local }),
+            ("bolt.sldprt", PlannedOperation::Copy { from: FileLocation::GoogleDrive, to: FileLocation::Smb }),
+            ("frame.dwg", PlannedOperation::Copy { from: FileLocation::Local, to: FileLocation::GoogleDrive }),
+            ("released/plate.dwg", PlannedOperation::Move { at: FileLocation::GoogleDrive, from: "asm/plate.dwg".to_string() }),
+        ]);
+        assert!(result.paused.is_empty());
+        assert_eq!(locations.local.content(Path::new("bolt.sldprt")), Some(b"b2".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("released/plate.dwg")), Some(b"p".to_vec()));
+
+        let again = engine(profile_id, &locations, db, &policies).start_sync().await.unwrap();
+        assert!(again.plan.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_sync_keeps_what_it_transferred() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Cancel".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        for name in ["a.dwg", "b.dwg", "c.dwg"] {
+            locations.local.put(name, &name[..1]);
+        }
+
+        // Cancel while the second transfer starts; it finishes, the rest don't run
+        let token = tokio_util::sync::CancellationToken::new();
+        let cancel = token.clone();
+        let result = engine(profile_id, &locations, db.clone(), &SimulationPolicies::default())
+            .with_progress_callback(Arc::new(move |processed, _, _, phase| {
+                if processed == 1 && matches!(phase, SyncPhase::Transferring { .. }) {
+                    cancel.cancel();
+                }
+            }))
+            .with_cancellation(token)
+            .start_sync()
+            .await
+            .unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.completed, ["a.dwg"]);
+        assert!(result.warnings.is_empty());
+        assert_eq!(locations.gdrive.content(Path::new("a.dwg")), Some(b"a".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("c.dwg")), None);
+
+        let run_id = result.run_id.clone();
+        let (run, queued) = db.call(move |conn| {
+            Ok((DbOperations::get_sync_run(conn, &run_id)?.unwrap(), DbOperations::get_transfers(conn, profile_id)?))
+        }).await.unwrap();
+        assert_eq!(run.status, RunStatus::Cancelled);
+        assert!(queued.is_empty());
+
+        // The next sync picks up where the cancelled one stopped
+        let result = engine(profile_id, &locations, db.clone(), &SimulationPolicies::default()).start_sync().await.unwrap();
+        assert!(!result.cancelled);
+        assert_eq!(result.completed, ["b.dwg", "c.dwg"]);
+        assert_eq!(locations.smb.content(Path::new("c.dwg")), Some(b"c".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_renamed_folder_is_moved_without_copying() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Move".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("asm/frame.sldasm", "f");
+        locations.local.put("asm/bolt.sldprt", "b");
+        let policies = SimulationPolicies::default();
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        for name in ["frame.sldasm", "bolt.sldprt"] {
+            locations.local.remove(&Path::new("asm").join(name));
+        }
+        locations.local.put("assembly/frame.sldasm", "f");
+        locations.local.put("assembly/bolt.sldprt", "b");
+
+        let result = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        let steps: Vec<(&str, PlannedOperation)> = result.plan.iter().map(|s| (s.path.as_str(), s.operation.clone())).collect();
+        let moved = |path: &'static str, at: FileLocation| (path, PlannedOperation::Move { at, from: path.replace("assembly", "asm") });
+        assert_eq!(steps, [
+            moved("assembly/bolt.sldprt", FileLocation::GoogleDrive),
+            moved("assembly/bolt.sldprt", FileLocation::Smb),
+            moved("assembly/frame.sldasm", FileLocation::GoogleDrive),
+            moved("assembly/frame.sldasm", FileLocation::Smb),
+        ]);
+        assert_eq!(result.completed, ["assembly/bolt.sldprt", "assembly/frame.sldasm"]);
+        assert_eq!(locations.smb.content(Path::new("assembly/frame.sldasm")), Some(b"f".to_vec()));
+        assert_eq!(locations.gdrive.content(Path::new("asm/frame.sldasm")), None);
+
+        // The old paths are forgotten and the new ones known everywhere
+        let again = engine(profile_id, &locations, db, &policies).start_sync().await.unwrap();
+        assert!(again.plan.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_keeping_both_versions_sets_the_other_aside_everywhere() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Conflict".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("asm/frame.dwg", "v1");
+        let policies = SimulationPolicies::default();
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        locations.local.put("asm/frame.dwg", "local edit");
+        locations.gdrive.put("asm/frame.dwg", "drive edit");
+        let result = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        let conflict = result.conflicts[0].clone();
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let all = [FileLocation::Local, FileLocation::GoogleDrive, FileLocation::Smb];
+        let preview = ConflictResolver::new().preview(&conflict, ConflictResolution::KeepBoth, &all, date);
+        let resolved = engine(profile_id, &locations, db.clone(), &policies)
+            .resolve_conflict(&conflict, &preview, "anna")
+            .await
+            .unwrap();
+        assert!(resolved.warnings.is_empty());
+
+        let copy = Path::new("asm/frame (conflict from gdrive 2024-05-01).dwg");
+        for location in &all {
+            let provider = locations.get(location);
+            assert_eq!(provider.content(Path::new("asm/frame.dwg")), Some(b"local edit".to_vec()), "{:?}", location);
+            assert_eq!(provider.content(copy), Some(b"drive edit".to_vec()), "{:?}", location);
+        }
+        let recorded: (bool, String) = db.call(move |conn| Ok(conn.query_row(
+            "SELECT resolved, resolution FROM conflicts WHERE profile_id = ?1", [profile_id], |row| Ok((row.get(0)?, row.get(1)?)),
+        )?)).await.unwrap();
+        assert_eq!(recorded, (true, "keep_both".to_string()));
+
+        let again = engine(profile_id, &locations, db, &policies).start_sync().await.unwrap();
+        assert!(again.plan.is_empty() && again.conflicts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_are_kept_until_resolved() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Conflict".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("asm/frame.dwg", "v1");
+        let policies = SimulationPolicies::default();
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        locations.local.put("asm/frame.dwg", "local edit");
+        locations.gdrive.put("asm/frame.dwg", "drive edit!");
+        let result = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        assert_eq!(result.conflicts.len(), 1);
+        let conflicts = |db: DbHandle| async move {
+            db.call(move |conn| DbOperations::get_conflicts(conn, profile_id)).await.unwrap()
+        };
+        let recorded = conflicts(db.clone()).await;
+        assert_eq!(recorded.len(), 1);
+        assert!(!recorded[0].resolved);
+        assert_eq!((recorded[0].local_size, recorded[0].gdrive_size, recorded[0].smb_size), (Some(10), Some(11), Some(2)));
+        assert_eq!(recorded[0].gdrive_hash, result.conflicts[0].gdrive_hash);
+
+        // The next run no longer sees the conflict, but it is still recorded
+        let again = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        assert!(again.conflicts.is_empty());
+        assert_eq!(conflicts(db.clone()).await.len(), 1);
+
+        // Detected again, it replaces the copies recorded instead of adding a row
+        locations.local.put("asm/frame.dwg", "second local edit");
+        locations.smb.put("asm/frame.dwg", "share edit");
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        let recorded = conflicts(db.clone()).await;
+        assert_eq!(recorded.len(), 1);
+        assert_eq!((recorded[0].local_size, recorded[0].smb_size), (Some(17), Some(10)));
+
+        let conflict = Conflict::from(&recorded[0]);
+        let all = [FileLocation::Local, FileLocation::GoogleDrive, FileLocation::Smb];
+        let preview = ConflictResolver::new().preview(&conflict, ConflictResolution::KeepLocal, &all, chrono::Local::now().date_naive());
+        engine(profile_id, &locations, db.clone(), &policies).resolve_conflict(&conflict, &preview, "anna").await.unwrap();
+        let recorded = conflicts(db).await;
+        assert_eq!(recorded.len(), 1);
+        assert!(recorded[0].resolved);
+        assert_eq!(recorded[0].resolution, Some(ConflictResolution::KeepLocal));
+    }
+
+    #[tokio::test]
+    async fn test_soft_deleted_files_can_be_restored() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Trash".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("asm/frame.dwg", "asm/frame.dwg");
+        for n in 1..=8 {
+            locations.local.put(format!("parts/p{}.sldprt", n), "part");
+        }
+        let policies = SimulationPolicies::default();
+        let soft = |db: DbHandle, trash: TrashPolicy| engine(profile_id, &locations, db, &policies).with_trash(trash);
+        soft(db.clone(), TrashPolicy::default()).start_sync().await.unwrap();
+
+        // The deletion moves the other copies to the trash, which is never synced
+        locations.local.remove(Path::new("asm/frame.dwg"));
+        soft(db.clone(), TrashPolicy::default()).start_sync().await.unwrap();
+        assert_eq!(locations.smb.content(Path::new("asm/frame.dwg")), None);
+        let trashed_at_share: Vec<PathBuf> = locations.smb.list_files(Path::new(trash::TRASH_FOLDER)).await.unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(trashed_at_share.len(), 1);
+        assert!(trashed_at_share[0].ends_with("asm/frame.dwg"));
+        let again = soft(db.clone(), TrashPolicy::default()).start_sync().await.unwrap();
+        assert!(again.plan.is_empty());
+
+        let restored = soft(db.clone(), TrashPolicy::default()).restore_deleted("asm/frame.dwg").await.unwrap();
+        assert_eq!(restored.restored, [FileLocation::GoogleDrive, FileLocation::Smb]);
+        assert!(restored.failed.is_empty());
+        soft(db.clone(), TrashPolicy::default()).start_sync().await.unwrap();
+        assert_eq!(locations.local.content(Path::new("asm/frame.dwg")), Some(b"asm/frame.dwg".to_vec()));
+        let trashed = |db: DbHandle| async move {
+            db.call(move |conn| DbOperations::get_trashed_files(conn, profile_id)).await.unwrap()
+        };
+        assert!(trashed(db.clone()).await.is_empty());
+
+        // Copies older than the retention are deleted for good
+        locations.local.remove(Path::new("parts/p1.sldprt"));
+        let expiring = TrashPolicy { retention: chrono::Duration::zero() };
+        soft(db.clone(), expiring).start_sync().await.unwrap();
+        assert!(trashed(db.clone()).await.is_empty());
+        assert!(locations.smb.list_files(Path::new(trash::TRASH_FOLDER)).await.unwrap().is_empty());
+        assert!(soft(db, expiring).restore_deleted("parts/p1.sldprt").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keeping_one_version_replaces_the_others() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Conflict".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("asm/frame.dwg", "v1");
+        locations.local.put("asm/bolt.dwg", "b1");
+        let policies = SimulationPolicies::default();
+        engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        locations.local.put("asm/frame.dwg", "local edit");
+        locations.gdrive.put("asm/frame.dwg", "drive edit");
+        locations.local.remove(Path::new("asm/bolt.dwg"));
+        locations.smb.put("asm/bolt.dwg", "share edit");
+        let result = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        assert_eq!(result.conflicts.len(), 2);
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let all = [FileLocation::Local, FileLocation::GoogleDrive, FileLocation::Smb];
+        for (path, resolution) in [("asm/frame.dwg", ConflictResolution::KeepGoogleDrive), ("asm/bolt.dwg", ConflictResolution::KeepLocal)] {
+            let conflict = result.conflicts.iter().find(|c| c.file_path == path).unwrap();
+            let preview = ConflictResolver::new().preview(conflict, resolution, &all, date);
+            let resolved = engine(profile_id, &locations, db.clone(), &policies)
+                .resolve_conflict(conflict, &preview, "anna")
+                .await
+                .unwrap();
+            assert!(resolved.warnings.is_empty(), "{}", path);
+        }
+
+        // The Drive edit is everywhere and the local deletion of the bolt was kept
+        for location in &all {
+            let provider = locations.get(location);
+            assert_eq!(provider.content(Path::new("asm/frame.dwg")), Some(b"drive edit".to_vec()), "{:?}", location);
+            assert_eq!(provider.content(Path::new("asm/bolt.dwg")), None, "{:?}", location);
+        }
+        let again = engine(profile_id, &locations, db, &policies).start_sync().await.unwrap();
+        assert!(again.plan.is_empty() && again.conflicts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_runs_are_kept_in_the_sync_history() {
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("History".to_string(), String::new()))
+        }).await.unwrap();
+        let locations = Locations { local: MockProvider::new(), gdrive: MockProvider::new(), smb: MockProvider::new() };
+        locations.local.put("a.dwg", "a");
+        let policies = SimulationPolicies::default();
+
+        let first = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+        locations.local.put("b.dwg", "b");
+        let second = engine(profile_id, &locations, db.clone(), &policies).start_sync().await.unwrap();
+
+        let (runs, total) = db.call(move |conn| {
+            Ok((DbOperations::get_sync_runs(conn, profile_id, 1, 1)?, DbOperations::count_sync_runs(conn, profile_id)?))
+        }).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(runs.iter().map(|r| r.run_id.as_str()).collect::<Vec<_>>(), [first.run_id.as_str()]);
+        assert_eq!((runs[0].status, runs[0].files_synced), (RunStatus::Completed, 1));
+        assert!(runs[0].completed_at.is_some());
+        assert_ne!(first.run_id, second.run_id);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_over_quota_fail_before_transferring() {
+        let report = simulate(Scenario::QuotaExceeded, &[], &SimulationPolicies::default()).await.unwrap();
+        let reason = report.aborted.unwrap();
+        assert!(reason.contains("simulated/large-5.step (5.0 KB), simulated/large-4.step (4.0 KB)"));
+        assert_eq!(report.files_synced, 0);
+    }
+
+    #[tokio::test]
+    async fn test_outages_are_recovered_from() {
+        let dropout = simulate(Scenario::SmbDropout, &[], &SimulationPolicies::default()).await.unwrap();
+        assert_eq!(dropout.aborted, None);
+        assert_eq!(dropout.files_failed, 15);
+        // The share is given up on after a few failures instead of failing every file
+        assert_eq!(dropout.warnings.len(), 1);
+        assert!(dropout.warnings[0].starts_with("Skipped smb for the rest of the run"));
+        assert!(dropout.warnings[0].ends_with("10 transfers were not attempted"));
+        assert_eq!(dropout.recovered, Some(15));
+
+        let expired = simulate(Scenario::ExpiredToken, &[], &SimulationPolicies::default()).await.unwrap();
+        assert!(expired.aborted.unwrap().contains("access token expired"));
+        assert_eq!(expired.files_synced, 0);
+        assert_eq!(expired.recovered, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_flaky_network_reports_failures() {
+        let report = simulate(Scenario::FlakyNetwork, &[], &SimulationPolicies::default()).await.unwrap();
+        assert!(report.files_failed > 0);
+        assert!(report.files_synced > 0);
+    }
+}
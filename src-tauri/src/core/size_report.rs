@@ -0,0 +1,153 @@
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+/// Files listed by `largest` unless the caller asks for another number.
+pub const DEFAULT_TOP_N: usize = 50;
+
+/// Upper bounds of the size classes: under 1 MB, 10 MB, 100 MB and 1 GB; the
+/// last class holds everything larger.
+const CLASS_LIMITS: [u64; 4] = [1 << 20, 10 << 20, 100 << 20, 1 << 30];
+
+/// A synced file with its size, as the largest copy of it at any location.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrackedFile {
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub locations: Vec<FileLocation>,
+}
+
+/// Files from `min_bytes` up to, but not including, `max_bytes`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeClass {
+    pub min_bytes: u64,
+    /// None for the largest class.
+    pub max_bytes: Option<u64>,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Files and bytes below one top-level folder; "" holds the files at the root.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FolderSize {
+    pub folder: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeDistribution {
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub classes: Vec<SizeClass>,
+    /// Largest first, so a dump of simulation results tops the list.
+    pub folders: Vec<FolderSize>,
+}
+
+/// The files of a profile from its recorded states, one per path. Deleted
+/// files are left out; offloaded ones still count, their content is archived.
+fn tracked_files(states: &[FileState]) -> Vec<TrackedFile> {
+    let mut files: HashMap<&str, TrackedFile> = HashMap::new();
+    for state in states.iter().filter(|s| s.status != SyncStatus::Deleted) {
+        let file = files.entry(state.file_path.as_str()).or_insert_with(|| TrackedFile {
+            path: state.file_path.clone(),
+            size_bytes: 0,
+            modified_at: None,
+            locations: Vec::new(),
+        });
+        file.size_bytes = file.size_bytes.max(state.size_bytes.unwrap_or(0).max(0) as u64);
+        file.modified_at = file.modified_at.max(state.modified_at);
+        file.locations.push(state.location.clone());
+    }
+    files.into_values()
+        .map(|mut file| {
+            file.locations.sort_by_key(|l| l.as_str().to_string());
+            file
+        })
+        .collect()
+}
+
+/// The `top_n` largest files, largest first.
+pub fn largest(states: &[FileState], top_n: usize) -> Vec<TrackedFile> {
+    let mut files = tracked_files(states);
+    files.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then_with(|| a.path.cmp(&b.path)));
+    files.truncate(top_n);
+    files
+}
+
+/// How the files of a profile spread over size classes and top-level folders.
+pub fn distribution(states: &[FileState]) -> SizeDistribution {
+    let files = tracked_files(states);
+
+    let mut classes: Vec<SizeClass> = (0..=CLASS_LIMITS.len())
+        .map(|i| SizeClass {
+            min_bytes: if i == 0 { 0 } else { CLASS_LIMITS[i - 1] },
+            max_bytes: CLASS_LIMITS.get(i).copied(),
+            files: 0,
+            bytes: 0,
+        })
+        .collect();
+    let mut folders: HashMap<String, FolderSize> = HashMap::new();
+
+    for file in &files {
+        let class = CLASS_LIMITS.iter().position(|limit| file.size_bytes < *limit).unwrap_or(CLASS_LIMITS.len());
+        classes[class].files += 1;
+        classes[class].bytes += file.size_bytes;
+
+        let path = Path::new(&file.path);
+        let folder = match path.components().next() {
+            Some(Component::Normal(first)) if path.components().count() > 1 => first.to_string_lossy().to_string(),
+            _ => String::new(),
+        };
+        let entry = folders.entry(folder.clone()).or_insert(FolderSize { folder, files: 0, bytes: 0 });
+        entry.files += 1;
+        entry.bytes += file.size_bytes;
+    }
+
+    let mut folders: Vec<FolderSize> = folders.into_values().collect();
+    folders.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.folder.cmp(&b.folder)));
+
+    SizeDistribution {
+        total_files: files.len(),
+        total_bytes: files.iter().map(|f| f.size_bytes).sum(),
+        classes,
+        folders,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(path: &str, location: FileLocation, size: i64, status: SyncStatus) -> FileState {
+        FileState { size_bytes: Some(size), status, ..FileState::new(1, path.to_string(), location) }
+    }
+
+    #[test]
+    fn test_reports_largest_files_and_distribution() {
+        use FileLocation::*;
+        let states = [
+            state("sim/run1/result.odb", Local, 40 << 30, SyncStatus::Synced),
+            state("sim/run1/result.odb", GoogleDrive, 40 << 30, SyncStatus::Synced),
+            state("sim/run2/result.odb", Local, 2 << 30, SyncStatus::Synced),
+            state("parts/bracket.sldprt", Local, 3 << 20, SyncStatus::Synced),
+            state("readme.txt", Smb, 200, SyncStatus::Synced),
+            state("old.dwg", Local, 5 << 30, SyncStatus::Deleted),
+        ];
+
+        let top = largest(&states, 2);
+        assert_eq!(top.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), ["sim/run1/result.odb", "sim/run2/result.odb"]);
+        assert_eq!(top[0].locations, [GoogleDrive, Local]);
+
+        let summary = distribution(&states);
+        assert_eq!((summary.total_files, summary.total_bytes), (4, (42 << 30) + (3 << 20) + 200));
+        let counts: Vec<usize> = summary.classes.iter().map(|c| c.files).collect();
+        assert_eq!(counts, [1, 1, 0, 0, 2]);
+        assert_eq!(summary.classes[4].max_bytes, None);
+        let folders: Vec<(&str, usize)> = summary.folders.iter().map(|f| (f.folder.as_str(), f.files)).collect();
+        assert_eq!(folders, [("sim", 2), ("parts", 1), ("", 1)]);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::utils::error::Result;
+use serde::Serialize;
+
+/// A mounted network share the SMB location can point at.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SmbMount {
+    /// Where the share is reachable locally: a mount point or drive letter.
+    pub path: String,
+    pub server: String,
+    pub share: String,
+}
+
+/// Mounted SMB shares: network volumes under /Volumes on macOS, mapped drives
+/// (as listed by `net use`) on Windows, and cifs/smb3 mounts on Linux.
+pub fn discover() -> Result<Vec<SmbMount>> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("mount").output()?;
+        Ok(parse_mount_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Ok(('A'..='Z')
+            .filter_map(|letter| {
+                let remote = crate::utils::unc::mapped_drive(letter)?;
+                let (server, share) = split_remote(&remote)?;
+                Some(SmbMount { path: format!("{}:\\", letter), server, share })
+            })
+            .collect())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(parse_proc_mounts(&std::fs::read_to_string("/proc/mounts")?))
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+/// Parse macOS `mount` lines such as
+/// `//jane@nas._smb._tcp.local/Eng%20Data on /Volumes/Eng Data (smbfs, nodev, nosuid)`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_mount_output(output: &str) -> Vec<SmbMount> {
+    output.lines()
+        .filter_map(|line| {
+            let (device, rest) = line.split_once(" on ")?;
+            let (path, options) = rest.rsplit_once(" (")?;
+            if !options.starts_with("smbfs") || !path.starts_with("/Volumes/") {
+                return None;
+            }
+            let (server, share) = split_remote(device)?;
+            Some(SmbMount { path: path.to_string(), server, share })
+        })
+        .collect()
+}
+
+/// Parse `/proc/mounts` lines such as `//nas/eng /mnt/eng cifs rw,relatime 0 0`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_mounts(mounts: &str) -> Vec<SmbMount> {
+    mounts.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (device, path, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+            if !matches!(fs_type, "cifs" | "smb3" | "smbfs") {
+                return None;
+            }
+            let (server, share) = split_remote(&unescape_octal(device))?;
+            Some(SmbMount { path: unescape_octal(path), server, share })
+        })
+        .collect()
+}
+
+/// Server and share of `//user@server/share`, `\\server\share` or a longer path.
+/// Bonjour suffixes and percent-encoding are removed.
+fn split_remote(remote: &str) -> Option<(String, String)> {
+    let remote = remote.replace('\\', "/");
+    let mut parts = remote.strip_prefix("//")?.split('/');
+    let host = parts.next()?;
+    let share = parts.next().filter(|s| !s.is_empty())?;
+
+    let server = host.rsplit_once('@').map_or(host, |(_, server)| server);
+    let server = server.trim_end_matches("._smb._tcp.local");
+    Some((percent_decode(server), percent_decode(share)))
+}
+
+/// Undo the `\040`-style escapes /proc/mounts uses for spaces and tabs.
+#[cfg(any(target_os = "linux", test))]
+fn unescape_octal(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        let escape = rest.get(index + 1..index + 4);
+        match escape.and_then(|digits| u8::from_str_radix(digits, 8).ok()) {
+            Some(byte) => {
+                result.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = value.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_macos_mounts() {
+        let output = "/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)\n\
+                      //jane@nas._smb._tcp.local/Eng%20Data on /Volumes/Eng Data (smbfs, nodev, nosuid, mounted by jane)\n";
+        assert_eq!(parse_mount_output(output), vec![SmbMount {
+            path: "/Volumes/Eng Data".to_string(),
+            server: "nas".to_string(),
+            share: "Eng Data".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_parse_proc_mounts() {
+        let mounts = "proc /proc proc rw 0 0\n//nas/eng /mnt/eng\\040data cifs rw,relatime 0 0\n";
+        assert_eq!(parse_proc_mounts(mounts), vec![SmbMount {
+            path: "/mnt/eng data".to_string(),
+            server: "nas".to_string(),
+            share: "eng".to_string(),
+        }]);
+        assert_eq!(split_remote(r"\\nas\eng\Projects"), Some(("nas".to_string(), "eng".to_string())));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::core::legal_hold::LegalHolds;
+use crate::core::planner::location_rank;
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use crate::models::snapshot::SnapshotFile;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// How two manifests differ, by relative path.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SnapshotDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Where the content of a snapshot file can be copied back from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RestoreStep {
+    pub path: String,
+    pub content_hash: String,
+    pub source: FileLocation,
+    /// The file holding the content now; another path when it was moved or copied.
+    pub source_path: String,
+}
+
+/// What restoring a snapshot into the local folder takes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RestorePlan {
+    pub steps: Vec<RestoreStep>,
+    pub unchanged: usize,
+    /// Files whose content no location holds any more.
+    pub unavailable: Vec<String>,
+    /// Files created since the snapshot; restoring leaves them alone.
+    pub added_since: Vec<String>,
+    /// Changed files under legal hold, left as they are.
+    pub held: Vec<String>,
+}
+
+/// The manifest of a profile: every tracked file with the content of its local
+/// copy, or for files not kept locally of the first remote copy. Deleted copies
+/// and copies without a hash are left out.
+pub fn manifest(states: &[FileState]) -> Vec<SnapshotFile> {
+    let mut chosen: BTreeMap<&str, &FileState> = BTreeMap::new();
+    for state in states.iter().filter(|s| s.status != SyncStatus::Deleted && s.content_hash.is_some()) {
+        let entry = chosen.entry(state.file_path.as_str()).or_insert(state);
+        if location_rank(&state.location) < location_rank(&entry.location) {
+            *entry = state;
+        }
+    }
+
+    chosen.into_values()
+        .map(|state| SnapshotFile {
+            file_path: state.file_path.clone(),
+            content_hash: state.content_hash.clone().unwrap_or_default(),
+            size_bytes: state.size_bytes,
+            modified_at: state.modified_at,
+        })
+        .collect()
+}
+
+/// What changed going from the `from` manifest to the `to` one.
+pub fn compare(from: &[SnapshotFile], to: &[SnapshotFile]) -> SnapshotDiff {
+    let before: HashMap<&str, &str> = from.iter().map(|f| (f.file_path.as_str(), f.content_hash.as_str())).collect();
+    let after: HashMap<&str, &str> = to.iter().map(|f| (f.file_path.as_str(), f.content_hash.as_str())).collect();
+    let paths: BTreeSet<&str> = before.keys().chain(after.keys()).copied().collect();
+
+    let mut diff = SnapshotDiff::default();
+    for path in paths {
+        match (before.get(path), after.get(path)) {
+            (None, Some(_)) => diff.added.push(path.to_string()),
+            (Some(_), None) => diff.removed.push(path.to_string()),
+            (Some(a), Some(b)) if a != b => diff.changed.push(path.to_string()),
+            _ => diff.unchanged += 1,
+        }
+    }
+    diff
+}
+
+/// Plan bringing the files of a snapshot back into the local folder, taking
+/// each content from wherever `states` say it still is: the same path first,
+/// the local folder before remote locations. Local copies under legal hold
+/// are not overwritten.
+pub fn plan_restore(files: &[SnapshotFile], states: &[FileState], holds: &LegalHolds) -> RestorePlan {
+    let current = manifest(states);
+    let current_hashes: HashMap<&str, &str> = current.iter().map(|f| (f.file_path.as_str(), f.content_hash.as_str())).collect();
+    let mut holders: HashMap<&str, Vec<&FileState>> = HashMap::new();
+    for state in states.iter().filter(|s| !matches!(s.status, SyncStatus::Deleted | SyncStatus::Offloaded)) {
+        if let Some(hash) = state.content_hash.as_deref() {
+            holders.entry(hash).or_default().push(state);
+        }
+    }
+
+    let mut plan = RestorePlan::default();
+    for file in files {
+        if current_hashes.get(file.file_path.as_str()) == Some(&file.content_hash.as_str()) {
+            plan.unchanged += 1;
+            continue;
+        }
+        if holds.covering(Path::new(&file.file_path)).is_some() && current_hashes.contains_key(file.file_path.as_str()) {
+            plan.held.push(file.file_path.clone());
+            continue;
+        }
+        let source = holders.get(file.content_hash.as_str()).and_then(|candidates| {
+            candidates.iter().min_by_key(|s| (s.file_path != file.file_path, location_rank(&s.location), s.file_path.as_str()))
+        });
+        match source {
+            Some(source) => plan.steps.push(RestoreStep {
+                path: file.file_path.clone(),
+                content_hash: file.content_hash.clone(),
+                source: source.location.clone(),
+                source_path: source.file_path.clone(),
+            }),
+            None => plan.unavailable.push(file.file_path.clone()),
+        }
+    }
+
+    let recorded: BTreeSet<&str> = files.iter().map(|f| f.file_path.as_str()).collect();
+    plan.added_since = current.iter()
+        .filter(|f| !recorded.contains(f.file_path.as_str()))
+        .map(|f| f.file_path.clone())
+        .collect();
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FileLocation::*;
+
+    fn state(path: &str, location: FileLocation, hash: &str) -> FileState {
+        FileState {
+            content_hash: Some(hash.to_string()),
+            status: SyncStatus::Synced,
+            ..FileState::new(1, path.to_string(), location)
+        }
+    }
+
+    #[test]
+    fn test_records_compares_and_restores_manifests() {
+        let sent = manifest(&[
+            state("asm/frame.sldasm", Local, "f1"),
+            state("asm/frame.sldasm", GoogleDrive, "f1"),
+            state("drawings/frame.dwg", GoogleDrive, "d1"),
+            state("notes.txt", Local, "n1"),
+            FileState { status: SyncStatus::Deleted, ..state("old.dwg", Local, "o1") },
+        ]);
+        assert_eq!(sent.iter().map(|f| f.file_path.as_str()).collect::<Vec<_>>(), ["asm/frame.sldasm", "drawings/frame.dwg", "notes.txt"]);
+
+        // Since then the frame changed locally and the Drive still has the old
+        // one, the drawing moved, the notes are gone and a part was added
+        let now = [
+            state("asm/frame.sldasm", Local, "f2"),
+            state("asm/frame.sldasm", GoogleDrive, "f1"),
+            state("released/frame.dwg", Smb, "d1"),
+            state("parts/bolt.sldprt", Local, "b1"),
+        ];
+        let diff = compare(&sent, &manifest(&now));
+        assert_eq!(diff.added, ["parts/bolt.sldprt", "released/frame.dwg"]);
+        assert_eq!(diff.removed, ["drawings/frame.dwg", "notes.txt"]);
+        assert_eq!(diff.changed, ["asm/frame.sldasm"]);
+
+        let plan = plan_restore(&sent, &now, &LegalHolds::default());
+        let steps: Vec<(&str, &FileLocation, &str)> = plan.steps.iter()
+            .map(|s| (s.path.as_str(), &s.source, s.source_path.as_str()))
+            .collect();
+        assert_eq!(steps, [("asm/frame.sldasm", &GoogleDrive, "asm/frame.sldasm"), ("drawings/frame.dwg", &Smb, "released/frame.dwg")]);
+        assert_eq!(plan.unavailable, ["notes.txt"]);
+        assert_eq!(plan.added_since, ["parts/bolt.sldprt", "released/frame.dwg"]);
+    }
+}
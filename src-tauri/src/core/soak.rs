@@ -0,0 +1,300 @@
+use crate::core::circuit_breaker::{CircuitBreaker, TRIP_AFTER_FAILURES};
+use crate::core::sync_engine::SyncEngine;
+use crate::db::{handle::DbHandle, models::DbOperations, schema::Database};
+use crate::models::file_state::FileLocation;
+use crate::models::sync_profile::SyncProfile;
+use crate::models::transfer::TransferStatus;
+use crate::providers::mock::MockProvider;
+use crate::providers::traits::StorageProvider;
+use crate::utils::error::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Files in the sandbox before the first round.
+const INITIAL_FILES: usize = 30;
+/// Creations stop above this many files and deletions below the minimum,
+/// keeping every round within the deletion safety thresholds.
+const MAX_FILES: usize = 60;
+const MIN_FILES: usize = 20;
+const MAX_CHANGES_PER_ROUND: usize = 8;
+/// Times failed transfers are retried before the round counts as failed.
+const RETRY_ROUNDS: usize = 5;
+/// Violations kept in the report; the soak stops after the first bad round anyway.
+const MAX_VIOLATIONS: usize = 100;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+const LOCATIONS: [FileLocation; 3] = [FileLocation::Local, FileLocation::GoogleDrive, FileLocation::Smb];
+
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    /// Stop after this many rounds even if time is left.
+    pub max_rounds: Option<usize>,
+    /// Same seed, same sequence of changes.
+    pub seed: u64,
+    /// Make every nth transfer to Google Drive fail; 0 never fails.
+    pub fail_every: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SoakReport {
+    pub seed: u64,
+    pub rounds: usize,
+    /// Files created, edited or deleted across all rounds.
+    pub changes: usize,
+    pub files_synced: usize,
+    /// Transfers that failed and succeeded when retried.
+    pub transfers_retried: usize,
+    pub elapsed_secs: u64,
+    /// Broken invariants, from the round that broke them.
+    pub violations: Vec<String>,
+    pub stopped: bool,
+}
+
+/// Small deterministic generator, so a failing soak can be replayed from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self((seed ^ 0x9E37_79B9_7F4A_7C15).max(1))
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n.max(1) as u64) as usize
+    }
+}
+
+/// Ask a running soak to stop after its current round.
+pub fn stop() {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+/// Create, edit and delete random files at random locations of a sandbox
+/// profile (in-memory locations and database), syncing after every round, until
+/// the time is up, `stop` is called or an invariant breaks:
+/// - every location holds exactly the files last written, with their content
+/// - the database has one state per file and location, matching what is stored
+/// - no transfer is left behind and the database passes its integrity check
+///
+/// Each file changes at only one location per round, so conflicts never occur
+/// legitimately and count as violations too.
+pub async fn run(config: &SoakConfig) -> Result<SoakReport> {
+    STOP.store(false, Ordering::SeqCst);
+    let started = Instant::now();
+    let mut rng = Rng::new(config.seed);
+
+    let database = Database::in_memory()?;
+    database.initialize()?;
+    let db = DbHandle::new(database);
+    let profile_id = db.call(|conn| {
+        DbOperations::create_sync_profile(conn, &SyncProfile::new("Soak".to_string(), String::new()))
+    }).await?;
+
+    let local = MockProvider::new();
+    let gdrive = MockProvider::new();
+    let smb = MockProvider::new();
+    let flaky_gdrive = gdrive.clone().with_fail_every(config.fail_every);
+    let provider = |mock: &MockProvider| -> Arc<Mutex<dyn StorageProvider>> { Arc::new(Mutex::new(mock.clone())) };
+    // Injected failures are instant, so backing off after them would only cost rounds
+    let engine = || SyncEngine::new(profile_id, provider(&local), Some(provider(&flaky_gdrive)), Some(provider(&smb)), db.clone())
+        .with_circuit_breaker(CircuitBreaker::new(TRIP_AFTER_FAILURES, Duration::ZERO));
+    let at = |location: &FileLocation| match location {
+        FileLocation::Local => &local,
+        FileLocation::GoogleDrive => &gdrive,
+        FileLocation::Smb => &smb,
+    };
+
+    let mut report = SoakReport {
+        seed: config.seed,
+        rounds: 0,
+        changes: 0,
+        files_synced: 0,
+        transfers_retried: 0,
+        elapsed_secs: 0,
+        violations: Vec::new(),
+        stopped: false,
+    };
+    let mut expected: BTreeMap<PathBuf, Vec<u8>> = BTreeMap::new();
+    let mut next_file = 0;
+    for _ in 0..INITIAL_FILES {
+        next_file += 1;
+        let path = PathBuf::from(format!("soak/part-{:05}.step", next_file));
+        let content = format!("{} v0", path.display());
+        local.put(path.clone(), content.clone());
+        expected.insert(path, content.into_bytes());
+    }
+
+    while started.elapsed() < config.duration && config.max_rounds.is_none_or(|max| report.rounds < max) {
+        if STOP.load(Ordering::SeqCst) {
+            report.stopped = true;
+            break;
+        }
+        let round = report.rounds + 1;
+
+        // The initial round only syncs what was seeded
+        let mut touched: HashSet<PathBuf> = HashSet::new();
+        let mut deleted = false;
+        let changes = if round == 1 { 0 } else { 1 + rng.below(MAX_CHANGES_PER_ROUND) };
+        for _ in 0..changes {
+            let location = &LOCATIONS[rng.below(LOCATIONS.len())];
+            let untouched: Vec<PathBuf> = expected.keys().filter(|p| !touched.contains(*p)).cloned().collect();
+            let action = rng.below(3);
+            if untouched.is_empty() || (action == 0 && expected.len() < MAX_FILES) {
+                next_file += 1;
+                let path = PathBuf::from(format!("soak/{}/part-{:05}.step", rng.below(5), next_file));
+                let content = format!("{} created at {} in round {}", path.display(), location.as_str(), round);
+                at(location).put(path.clone(), content.clone());
+                expected.insert(path.clone(), content.into_bytes());
+                touched.insert(path);
+            } else if action == 1 && !deleted && expected.len() > MIN_FILES {
+                let path = untouched[rng.below(untouched.len())].clone();
+                at(location).remove(&path);
+                expected.remove(&path);
+                touched.insert(path);
+                deleted = true;
+            } else {
+                let path = untouched[rng.below(untouched.len())].clone();
+                let content = format!("{} edited at {} in round {}", path.display(), location.as_str(), round);
+                at(location).put(path.clone(), content.clone());
+                expected.insert(path.clone(), content.into_bytes());
+                touched.insert(path);
+            }
+            report.changes += 1;
+        }
+
+        let mut violations = Vec::new();
+        match engine().start_sync().await {
+            Ok(result) => {
+                report.files_synced += result.files_synced;
+                for conflict in &result.conflicts {
+                    violations.push(format!("Unexpected conflict on {}", conflict.file_path));
+                }
+            }
+            Err(e) => violations.push(format!("Sync failed: {}", e)),
+        }
+        let retried = retry_failed(profile_id, &db, &engine).await?;
+        if retried > 0 {
+            // Retries record only their destinations; the next sync records the rest
+            if let Err(e) = engine().start_sync().await {
+                violations.push(format!("Sync after retrying failed: {}", e));
+            }
+            report.transfers_retried += retried;
+        }
+        violations.extend(check(profile_id, &db, &expected, &at).await?);
+
+        report.rounds = round;
+        if !violations.is_empty() {
+            tracing::error!("Soak round {} broke {} invariants", round, violations.len());
+            report.violations = violations.into_iter().take(MAX_VIOLATIONS).collect();
+            break;
+        }
+        if round.is_multiple_of(100) {
+            tracing::info!("Soak round {}: {} files, {} changes so far", round, expected.len(), report.changes);
+        }
+    }
+
+    report.elapsed_secs = started.elapsed().as_secs();
+    Ok(report)
+}
+
+/// Retry failed transfers until none are left. Returns how many succeeded.
+async fn retry_failed(profile_id: i64, db: &DbHandle, engine: &impl Fn() -> SyncEngine) -> Result<usize> {
+    let mut retried = 0;
+    for _ in 0..RETRY_ROUNDS {
+        let failed: Vec<i64> = db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| t.status == TransferStatus::Failed)
+            .filter_map(|t| t.id)
+            .collect();
+        if failed.is_empty() {
+            break;
+        }
+        retried += engine().retry_transfers(&failed).await?.files_synced;
+    }
+    Ok(retried)
+}
+
+/// The invariants that must hold after a round, as violations.
+async fn check<'a>(
+    profile_id: i64,
+    db: &DbHandle,
+    expected: &BTreeMap<PathBuf, Vec<u8>>,
+    at: &impl Fn(&FileLocation) -> &'a MockProvider,
+) -> Result<Vec<String>> {
+    let mut violations = Vec::new();
+
+    let mut stored: HashMap<(PathBuf, FileLocation), Option<String>> = HashMap::new();
+    for location in &LOCATIONS {
+        for file in at(location).list_files(Path::new("")).await? {
+            match expected.get(&file.path) {
+                None => violations.push(format!("{} reappeared at {}", file.path.display(), location.as_str())),
+                Some(content) if at(location).content(&file.path).as_ref() != Some(content) => {
+                    violations.push(format!("{} at {} has lost its latest content", file.path.display(), location.as_str()));
+                }
+                Some(_) => {}
+            }
+            stored.insert((file.path, location.clone()), file.hash);
+        }
+        for path in expected.keys() {
+            if !stored.contains_key(&(path.clone(), location.clone())) {
+                violations.push(format!("{} is missing at {}", path.display(), location.as_str()));
+            }
+        }
+    }
+
+    let (states, transfers, integrity) = db.call(move |conn| {
+        let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        Ok((DbOperations::get_file_states(conn, profile_id)?, DbOperations::get_transfers(conn, profile_id)?, integrity))
+    }).await?;
+
+    let mut seen = HashSet::new();
+    for state in &states {
+        let key = (PathBuf::from(&state.file_path), state.location.clone());
+        if !seen.insert(key.clone()) {
+            violations.push(format!("Duplicate state for {} at {}", state.file_path, state.location.as_str()));
+        }
+        match stored.get(&key) {
+            None => violations.push(format!("State left for {} at {}, which is gone", state.file_path, state.location.as_str())),
+            Some(hash) if hash != &state.content_hash => {
+                violations.push(format!("State of {} at {} does not match what is stored", state.file_path, state.location.as_str()));
+            }
+            Some(_) => {}
+        }
+    }
+    for key in stored.keys().filter(|key| !seen.contains(*key)) {
+        violations.push(format!("No state for {} at {}", key.0.display(), key.1.as_str()));
+    }
+
+    for transfer in &transfers {
+        violations.push(format!("Transfer of {} left {}", transfer.file_path, transfer.status.as_str()));
+    }
+    if integrity != "ok" {
+        violations.push(format!("Database integrity check failed: {}", integrity));
+    }
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_short_soak_keeps_invariants() {
+        let config = SoakConfig { duration: Duration::from_secs(60), max_rounds: Some(15), seed: 7, fail_every: 5 };
+        let report = run(&config).await.unwrap();
+        assert_eq!(report.violations, Vec::<String>::new());
+        assert_eq!(report.rounds, 15);
+        assert!(report.changes > 0);
+        assert!(report.transfers_retried > 0);
+    }
+}
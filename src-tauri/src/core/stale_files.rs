@@ -0,0 +1,140 @@
+use crate::models::file_state::{FileLocation, FileState, SyncStatus};
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Months, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A copy of a file nobody has changed or, where it is known, opened since the cutoff.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StaleFile {
+    pub path: String,
+    pub location: FileLocation,
+    pub size_bytes: u64,
+    pub modified_at: DateTime<Utc>,
+    /// Only known for local files, from the file system.
+    pub accessed_at: Option<DateTime<Utc>>,
+}
+
+/// Stale copies and their total at one location.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocationStaleness {
+    pub location: FileLocation,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StaleReport {
+    pub cutoff: DateTime<Utc>,
+    pub locations: Vec<LocationStaleness>,
+    /// Least recently used first.
+    pub files: Vec<StaleFile>,
+}
+
+/// The time `months` months before `now`.
+pub fn cutoff(months: u32, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if months == 0 {
+        return Err(UvcadError::InvalidConfig("The stale period must be at least one month".to_string()));
+    }
+    now.checked_sub_months(Months::new(months))
+        .ok_or_else(|| UvcadError::InvalidConfig(format!("Invalid stale period: {} months", months)))
+}
+
+/// When the local files in `paths` below `root` were last opened. Files the
+/// system doesn't report an access time for are left out.
+pub fn local_access_times(root: &Path, paths: &[String]) -> HashMap<String, DateTime<Utc>> {
+    paths.iter()
+        .filter_map(|path| {
+            let accessed = std::fs::metadata(root.join(path)).and_then(|m| m.accessed()).ok()?;
+            Some((path.clone(), DateTime::<Utc>::from(accessed)))
+        })
+        .collect()
+}
+
+/// The copies in `states` last modified, and last accessed per `accessed`,
+/// before `cutoff`. Deleted and offloaded copies and copies without a known
+/// modification time are left out.
+pub fn stale_files(states: &[FileState], accessed: &HashMap<String, DateTime<Utc>>, cutoff: DateTime<Utc>) -> StaleReport {
+    let mut files: Vec<StaleFile> = states.iter()
+        .filter(|state| !matches!(state.status, SyncStatus::Deleted | SyncStatus::Offloaded))
+        .filter_map(|state| {
+            let accessed_at = match state.location {
+                FileLocation::Local => accessed.get(&state.file_path).copied(),
+                _ => None,
+            };
+            let modified_at = state.modified_at?;
+            if modified_at.max(accessed_at.unwrap_or(modified_at)) >= cutoff {
+                return None;
+            }
+            Some(StaleFile {
+                path: state.file_path.clone(),
+                location: state.location.clone(),
+                size_bytes: state.size_bytes.unwrap_or(0).max(0) as u64,
+                modified_at,
+                accessed_at,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| {
+        let used = |f: &StaleFile| f.modified_at.max(f.accessed_at.unwrap_or(f.modified_at));
+        used(a).cmp(&used(b))
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| a.location.as_str().cmp(b.location.as_str()))
+    });
+
+    let mut locations: HashMap<FileLocation, LocationStaleness> = HashMap::new();
+    for file in &files {
+        let total = locations.entry(file.location.clone())
+            .or_insert_with(|| LocationStaleness { location: file.location.clone(), files: 0, bytes: 0 });
+        total.files += 1;
+        total.bytes += file.size_bytes;
+    }
+    let mut locations: Vec<LocationStaleness> = locations.into_values().collect();
+    locations.sort_by_key(|l| l.location.as_str().to_string());
+
+    StaleReport { cutoff, locations, files }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn state(path: &str, location: FileLocation, modified: DateTime<Utc>) -> FileState {
+        FileState {
+            size_bytes: Some(100),
+            modified_at: Some(modified),
+            status: SyncStatus::Synced,
+            ..FileState::new(1, path.to_string(), location)
+        }
+    }
+
+    #[test]
+    fn test_reports_files_unused_since_the_cutoff() {
+        use FileLocation::*;
+        let now = Utc.with_ymd_and_hms(2026, 6, 30, 12, 0, 0).unwrap();
+        let cutoff = cutoff(6, now).unwrap();
+        assert_eq!(cutoff, Utc.with_ymd_and_hms(2025, 12, 30, 12, 0, 0).unwrap());
+        assert!(super::cutoff(0, now).is_err());
+
+        let old = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let recent = Utc.with_ymd_and_hms(2026, 5, 1, 0, 0, 0).unwrap();
+        let states = [
+            state("dead/plan.dwg", Local, old),
+            state("dead/plan.dwg", GoogleDrive, old),
+            // Opened last month, so still in use locally
+            state("reference/std.dwg", Local, old),
+            state("reference/std.dwg", GoogleDrive, old),
+            state("active/part.sldprt", GoogleDrive, recent),
+            FileState { status: SyncStatus::Offloaded, ..state("archived.dwg", Local, old) },
+        ];
+        let accessed = HashMap::from([("reference/std.dwg".to_string(), recent)]);
+
+        let report = stale_files(&states, &accessed, cutoff);
+        let files: Vec<(&str, &FileLocation)> = report.files.iter().map(|f| (f.path.as_str(), &f.location)).collect();
+        assert_eq!(files, [("dead/plan.dwg", &GoogleDrive), ("dead/plan.dwg", &Local), ("reference/std.dwg", &GoogleDrive)]);
+        let drive = report.locations.iter().find(|l| l.location == GoogleDrive).unwrap();
+        assert_eq!((drive.files, drive.bytes), (2, 200));
+    }
+}
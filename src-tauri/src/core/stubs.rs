@@ -0,0 +1,84 @@
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to the original file name for placeholder files.
+pub const STUB_EXTENSION: &str = "uvcadstub";
+
+/// Contents of a `.uvcadstub` placeholder left where an offloaded file used to be.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StubFile {
+    /// Relative path of the original file within the profile.
+    pub path: String,
+    pub original_name: String,
+    pub size: Option<i64>,
+    pub content_hash: Option<String>,
+    /// Where the content lives now, e.g. "gdrive-archive".
+    pub location: String,
+    /// Google Drive folder the content was archived to.
+    pub archive_folder_id: String,
+    pub offloaded_at: DateTime<Utc>,
+}
+
+pub fn is_stub(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case(STUB_EXTENSION))
+        .unwrap_or(false)
+}
+
+/// `part.sldprt` -> `part.sldprt.uvcadstub`
+pub fn stub_path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(STUB_EXTENSION);
+    path.with_file_name(name)
+}
+
+pub fn write_stub(local_root: &Path, stub: &StubFile) -> Result<PathBuf> {
+    let stub_path = stub_path_for(&local_root.join(&stub.path));
+    std::fs::write(&stub_path, serde_json::to_string_pretty(stub)?)?;
+    Ok(stub_path)
+}
+
+pub fn read_stub(stub_path: &Path) -> Result<StubFile> {
+    if !is_stub(stub_path) {
+        return Err(UvcadError::InvalidConfig(format!(
+            "Not a placeholder file: {}",
+            stub_path.display()
+        )));
+    }
+    let content = std::fs::read_to_string(stub_path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stub_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("parts")).unwrap();
+
+        let stub = StubFile {
+            path: "parts/housing.sldprt".to_string(),
+            original_name: "housing.sldprt".to_string(),
+            size: Some(1024),
+            content_hash: Some("abc".to_string()),
+            location: "gdrive-archive".to_string(),
+            archive_folder_id: "folder".to_string(),
+            offloaded_at: Utc::now(),
+        };
+
+        let stub_path = write_stub(dir.path(), &stub).unwrap();
+        assert_eq!(stub_path, dir.path().join("parts/housing.sldprt.uvcadstub"));
+        assert!(is_stub(&stub_path));
+
+        let read = read_stub(&stub_path).unwrap();
+        assert_eq!(read.path, stub.path);
+        assert_eq!(read.size, Some(1024));
+        assert!(read_stub(&dir.path().join("parts/housing.sldprt")).is_err());
+    }
+}
@@ -1,35 +1,153 @@
-use crate::core::conflict_resolver::{Conflict as ConflictInfo, ConflictResolver};
+use crate::core::bootstrap::{self, BootstrapProgress};
+use crate::core::cad_temp;
+use crate::core::circuit_breaker::CircuitBreaker;
+use crate::core::concurrency::AdaptiveConcurrency;
+use crate::core::conflict_resolver::{Conflict as ConflictInfo, ConflictResolver, ResolutionAction, ResolutionPreview};
+use crate::core::consistency::ConsistencyGroups;
+use crate::core::drive_encryption::ProfileKeys;
+use crate::core::estimate::{self, SyncEstimate};
+use crate::core::extension_policy::{self, ExtensionBehavior, ExtensionPolicies};
 use crate::core::file_hasher;
+use crate::core::legal_hold::LegalHolds;
+use crate::core::naming::NamingConvention;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::notifications::{self, Notification, NotificationKind, Severity};
+use crate::core::plan_approval::ApprovalPolicy;
+use crate::core::planner::{self, location_rank, PlanPolicies, Snapshots, SyncAction, SyncOperation};
+use crate::core::quarantine::{QuarantineRecord, ScanPolicy, ScanVerdict};
+use crate::core::revisions;
+use crate::core::scan_cache;
+use crate::core::selective_sync::SelectiveSync;
+use crate::core::signing::{self, DigestSigner, Signature};
+use crate::core::stubs;
+use crate::core::sync_policy::CompiledPolicy;
+use crate::core::trash::{self, TrashPolicy};
 use crate::db::models::DbOperations;
-use crate::db::schema::Database;
+use crate::db::handle::DbHandle;
+use crate::models::conflict::Conflict;
+use crate::models::file_event::{FileEvent, FileEventKind};
 use crate::models::file_state::{FileLocation, FileState, SyncStatus};
-use crate::providers::traits::StorageProvider;
+use crate::models::file_timing::FileTiming;
+use crate::models::sync_phase::SyncPhase;
+use crate::models::sync_plan::{Authorization, ChangeReason, OperationReason, PlanStep, PlannedOperation};
+use crate::models::sync_run::{RunPhase, RunStatus};
+use crate::models::transfer::{Transfer, TransferStatus};
+use crate::models::trashed_file::TrashedFile;
+use crate::providers::traits::{FileMetadata, StorageProvider};
 use crate::utils::error::{Result, UvcadError};
+use crate::utils::i18n;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::ops::ControlFlow;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-pub type ProgressCallback = Arc<dyn Fn(usize, usize, String, String) + Send + Sync>;
+/// A fresh ID for a sync run, correlating its logs, progress events and history.
+pub fn new_run_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Called with (processed, total, file name, phase); the counts are 0 outside of transfers.
+pub type ProgressCallback = Arc<dyn Fn(usize, usize, String, SyncPhase) + Send + Sync>;
+
+/// Called with each plan once it is made, and whether it is only a preview.
+pub type PlanCallback = Arc<dyn Fn(&[PlanStep], bool) + Send + Sync>;
 
-// Deletion safety thresholds
+// Default deletion safety thresholds
 const MAX_DELETION_PERCENTAGE: f32 = 0.30; // 30% of total files
 const MAX_DELETION_COUNT: usize = 50; // Maximum 50 files
 
+/// Uploads listed by name when planned uploads exceed a storage quota.
+const MAX_QUOTA_BREAKDOWN: usize = 20;
+
+/// How often an idle transfer worker checks whether it may start the next transfer,
+/// e.g. once the concurrency limit was raised or a folder it needs was created.
+const WORKER_WAIT: Duration = Duration::from_millis(200);
+
+/// Queued transfers read at a time while looking for the next one to run.
+const QUEUE_PAGE: usize = 64;
+
+/// How many deletions a sync may propagate before the safety check stops it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeletionLimits {
+    pub max_count: usize,
+    /// Fraction of all files, 0.0 to 1.0.
+    pub max_fraction: f32,
+}
+
+impl Default for DeletionLimits {
+    fn default() -> Self {
+        Self { max_count: MAX_DELETION_COUNT, max_fraction: MAX_DELETION_PERCENTAGE }
+    }
+}
+
+impl DeletionLimits {
+    /// Parse the profile settings; unset values keep the defaults.
+    pub fn from_config(max_count: Option<i64>, max_percentage: Option<i64>) -> Result<Self> {
+        let mut limits = Self::default();
+        if let Some(count) = max_count {
+            limits.max_count = usize::try_from(count)
+                .map_err(|_| UvcadError::InvalidConfig(format!("Invalid deletion limit: {}", count)))?;
+        }
+        if let Some(percentage) = max_percentage {
+            if !(0..=100).contains(&percentage) {
+                return Err(UvcadError::InvalidConfig(format!("Invalid deletion percentage: {}", percentage)));
+            }
+            limits.max_fraction = percentage as f32 / 100.0;
+        }
+        Ok(limits)
+    }
+}
+
 pub struct SyncEngine {
     profile_id: i64,
     local_provider: Arc<Mutex<dyn StorageProvider>>,
     gdrive_provider: Option<Arc<Mutex<dyn StorageProvider>>>,
     smb_provider: Option<Arc<Mutex<dyn StorageProvider>>>,
-    db: Arc<std::sync::Mutex<Database>>,
+    db: DbHandle,
     conflict_resolver: ConflictResolver,
     progress_callback: Option<ProgressCallback>,
+    plan_callback: Option<PlanCallback>,
+    path_filter: Option<HashSet<PathBuf>>,
+    naming: NamingConvention,
+    policies: ExtensionPolicies,
+    groups: ConsistencyGroups,
+    prefixes: PathPrefixes,
+    selection: SelectiveSync,
+    /// Folders whose files are queued first, most important first.
+    working_set: Vec<PathBuf>,
+    approval: Option<ApprovalPolicy>,
+    approved_plan: Option<Vec<PlanStep>>,
+    approved_by: Option<String>,
+    deletion_limits: DeletionLimits,
+    trash: Option<TrashPolicy>,
+    scan_cache: bool,
+    skip_cad_temp: bool,
+    save_settle: Duration,
+    quiet_period: Duration,
+    signer: Option<Arc<DigestSigner>>,
+    scanner: Option<Arc<ScanPolicy>>,
+    drive_keys: Option<Arc<ProfileKeys>>,
+    /// Downloads that failed signature verification during the current pass.
+    tampered: std::sync::Mutex<Vec<String>>,
+    /// Failed transfers in a row per location during the current pass.
+    breaker: std::sync::Mutex<CircuitBreaker>,
+    /// Where content was uploaded this run, keyed by destination, content hash and
+    /// whether it is stored compressed, so identical files can be copied there instead.
+    uploaded: std::sync::Mutex<HashMap<(FileLocation, String, bool), PathBuf>>,
+    /// More connections to a location, so transfers to it can run in parallel.
+    connections: HashMap<FileLocation, Vec<Arc<Mutex<dyn StorageProvider>>>>,
+    cancellation: CancellationToken,
+    run_id: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct FileSnapshot {
-    pub path: PathBuf,
     pub hash: Option<String>,
+    pub fingerprint: Option<String>,
     pub size: u64,
     pub modified: chrono::DateTime<chrono::Utc>,
     pub location: FileLocation,
@@ -41,7 +159,7 @@ impl SyncEngine {
         local_provider: Arc<Mutex<dyn StorageProvider>>,
         gdrive_provider: Option<Arc<Mutex<dyn StorageProvider>>>,
         smb_provider: Option<Arc<Mutex<dyn StorageProvider>>>,
-        db: Arc<std::sync::Mutex<Database>>,
+        db: DbHandle,
     ) -> Self {
         Self {
             profile_id,
@@ -51,6 +169,32 @@ impl SyncEngine {
             db,
             conflict_resolver: ConflictResolver::new(),
             progress_callback: None,
+            plan_callback: None,
+            path_filter: None,
+            naming: NamingConvention::default(),
+            policies: ExtensionPolicies::default(),
+            groups: ConsistencyGroups::default(),
+            prefixes: PathPrefixes::default(),
+            selection: SelectiveSync::default(),
+            working_set: Vec::new(),
+            approval: None,
+            approved_plan: None,
+            approved_by: None,
+            deletion_limits: DeletionLimits::default(),
+            trash: None,
+            scan_cache: false,
+            skip_cad_temp: false,
+            save_settle: Duration::ZERO,
+            quiet_period: Duration::ZERO,
+            signer: None,
+            scanner: None,
+            drive_keys: None,
+            tampered: std::sync::Mutex::new(Vec::new()),
+            breaker: std::sync::Mutex::new(CircuitBreaker::default()),
+            uploaded: std::sync::Mutex::new(HashMap::new()),
+            connections: HashMap::new(),
+            cancellation: CancellationToken::new(),
+            run_id: new_run_id(),
         }
     }
 
@@ -59,498 +203,2159 @@ impl SyncEngine {
         self
     }
 
-    pub async fn start_sync(&mut self) -> Result<SyncResult> {
+    /// Hand every plan to `callback` before it is checked and carried out, e.g.
+    /// to show it for review while the sync runs.
+    pub fn with_plan_callback(mut self, callback: PlanCallback) -> Self {
+        self.plan_callback = Some(callback);
+        self
+    }
+
+    /// Restrict the run to the given relative paths (on-demand sync).
+    /// Files outside the filter are neither transferred nor have their state updated.
+    pub fn with_path_filter(mut self, paths: HashSet<PathBuf>) -> Self {
+        self.path_filter = Some(paths);
+        self
+    }
+
+    /// Apply every setting of a profile's sync policy at once.
+    pub fn with_policy(self, policy: CompiledPolicy) -> Self {
+        let engine = self
+            .with_extension_policies(policy.extensions)
+            .with_naming_convention(policy.naming)
+            .with_consistency_groups(policy.groups)
+            .with_path_prefixes(policy.prefixes)
+            .with_selective_sync(policy.selection)
+            .with_deletion_limits(policy.deletion_limits)
+            .with_save_settle(policy.save_settle)
+            .with_quiet_period(policy.quiet_period);
+        let engine = if policy.skip_cad_temp { engine.with_cad_temp_skipping() } else { engine };
+        let engine = match policy.trash {
+            Some(trash) => engine.with_trash(trash),
+            None => engine,
+        };
+        match policy.approval {
+            Some(approval) => engine.with_plan_approval(approval),
+            None => engine,
+        }
+    }
+
+    /// Refuse uploads of files violating the naming convention to its blocked locations.
+    pub fn with_naming_convention(mut self, naming: NamingConvention) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Apply per-extension handling (never-sync, local-only, remote compression).
+    pub fn with_extension_policies(mut self, policies: ExtensionPolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Propagate members of each consistency group together or not at all.
+    pub fn with_consistency_groups(mut self, groups: ConsistencyGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Sync only the files under each location's prefix folder, planned by their
+    /// path below it.
+    pub fn with_path_prefixes(mut self, prefixes: PathPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Sync only the files the include and exclude patterns select; the others
+    /// are left out of the scan.
+    pub fn with_selective_sync(mut self, selection: SelectiveSync) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Override the deletion safety thresholds.
+    pub fn with_deletion_limits(mut self, limits: DeletionLimits) -> Self {
+        self.deletion_limits = limits;
+        self
+    }
+
+    /// Stop before changing anything when the plan has steps the policy does not
+    /// auto-approve; the result then only carries the plan.
+    pub fn with_plan_approval(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval = Some(policy);
+        self
+    }
+
+    /// Let the steps of a plan approved by `approved_by` run. Steps not in it
+    /// still need approval.
+    pub fn with_approved_plan(mut self, steps: Vec<PlanStep>, approved_by: &str) -> Self {
+        self.approved_plan = Some(steps);
+        self.approved_by = Some(approved_by.to_string());
+        self
+    }
+
+    /// Make this engine's runs part of sync run `run_id` instead of a run of its own.
+    pub fn with_run_id(mut self, run_id: &str) -> Self {
+        self.run_id = run_id.to_string();
+        self
+    }
+
+    /// Stop the run when `token` is cancelled: before anything is changed if
+    /// it is cancelled while scanning or planning, otherwise once the transfers
+    /// under way have finished. What was transferred is recorded as synced.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Reuse the profile's recent scans instead of listing every location again.
+    /// Paths this engine transfers are invalidated as it goes.
+    pub fn with_scan_cache(mut self) -> Self {
+        self.scan_cache = true;
+        self
+    }
+
+    /// Leave CAD autosaves, lock files and old-version folders out of the sync.
+    pub fn with_cad_temp_skipping(mut self) -> Self {
+        self.skip_cad_temp = true;
+        self
+    }
+
+    /// Hold back local files written less than `delay` ago that are still
+    /// changing, so a drawing is not synced halfway through a save.
+    pub fn with_save_settle(mut self, delay: Duration) -> Self {
+        self.save_settle = delay;
+        self
+    }
+
+    /// Only upload local files that have not changed for `period`; newer changes
+    /// wait for a later run. For saves too long for `with_save_settle` to catch.
+    pub fn with_quiet_period(mut self, period: Duration) -> Self {
+        self.quiet_period = period;
+        self
+    }
+
+    /// Queue the files under `folders`, e.g. the projects someone is working on,
+    /// ahead of the rest, in the order given.
+    pub fn with_working_set(mut self, folders: Vec<PathBuf>) -> Self {
+        self.working_set = folders;
+        self
+    }
+
+    /// Move the files deletions remove into the trash of their location instead,
+    /// and delete trashed copies for good once `policy` no longer keeps them.
+    pub fn with_trash(mut self, policy: TrashPolicy) -> Self {
+        self.trash = Some(policy);
+        self
+    }
+
+    /// Sign what is uploaded to the share and Drive, and verify what is
+    /// downloaded from them against its signature.
+    pub fn with_signing(mut self, signer: Arc<DigestSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Back off after failed transfers and skip a location that keeps failing
+    /// as `breaker` says, instead of after `circuit_breaker::TRIP_AFTER_FAILURES`.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = std::sync::Mutex::new(breaker);
+        self
+    }
+
+    /// Scan downloads before they are written into the local folder and
+    /// quarantine the ones the scanner flags.
+    pub fn with_scanner(mut self, scanner: Arc<ScanPolicy>) -> Self {
+        self.scanner = Some(scanner);
+        self
+    }
+
+    /// Encrypt what is uploaded to Google Drive with the profile's key, and
+    /// decrypt what is downloaded from it.
+    pub fn with_drive_encryption(mut self, keys: Arc<ProfileKeys>) -> Self {
+        self.drive_keys = Some(keys);
+        self
+    }
+
+    /// Transfer to `location` over these connections besides its provider. As many
+    /// transfers run at once as improve the measured throughput, up to one per connection.
+    pub fn with_connections(mut self, location: FileLocation, connections: Vec<Arc<Mutex<dyn StorageProvider>>>) -> Self {
+        self.connections.insert(location, connections);
+        self
+    }
+
+    fn report(&self, processed: usize, total: usize, file: &str, phase: SyncPhase) {
+        if let Some(ref callback) = self.progress_callback {
+            callback(processed, total, file.to_string(), phase);
+        }
+    }
+
+    fn in_scope(&self, path: &Path) -> bool {
+        self.path_filter.as_ref().is_none_or(|filter| filter.contains(path))
+    }
+
+    /// The position of the first working set folder holding `path`; files
+    /// outside the working set come after all of them.
+    fn working_set_rank(&self, path: &Path) -> usize {
+        self.working_set.iter()
+            .position(|folder| match folder.as_os_str().is_empty() {
+                // The root holds every file; only the ones directly in it count
+                true => path.parent().is_some_and(|parent| parent.as_os_str().is_empty()),
+                false => path.starts_with(folder),
+            })
+            .unwrap_or(self.working_set.len())
+    }
+
+    /// Where the file planned as `path` is kept at `location`: below its prefix
+    /// folder, and under its compressed name where that applies.
+    fn stored_path(&self, path: &Path, location: &FileLocation) -> PathBuf {
+        self.prefixes.stored_path(&self.policies.stored_path(path, location), location)
+    }
+
+    pub async fn start_sync(&self) -> Result<SyncResult> {
+        self.tracked(self.plan_and_run(true)).await
+    }
+
+    /// Plan a sync without carrying it out or recording a run, and estimate how
+    /// much it would transfer and, from the profile's recent transfers, how long it
+    /// would take.
+    pub async fn estimate(&self) -> Result<SyncEstimate> {
+        let result = self.plan_and_run(false).await?;
+        let profile_id = self.profile_id;
+        let timings = self.db.call(move |conn| {
+            DbOperations::get_recent_file_timings(conn, profile_id, estimate::SAMPLE_TRANSFERS)
+        }).await?;
+        Ok(estimate::estimate(&result.plan, &timings))
+    }
+
+    /// Run one pass of the engine as part of sync run `run_id`: the pass's log lines
+    /// carry the ID and the sync history records how it ended.
+    async fn tracked(&self, pass: impl std::future::Future<Output = Result<SyncResult>>) -> Result<SyncResult> {
+        let span = tracing::info_span!("sync_run", run_id = %self.run_id);
+        let (profile_id, run_id) = (self.profile_id, self.run_id.clone());
+        let interrupted = self.db.call(move |conn| {
+            let interrupted = DbOperations::fail_interrupted_runs(conn, profile_id, &run_id)?;
+            DbOperations::start_sync_run(conn, profile_id, &run_id)?;
+            Ok(interrupted)
+        }).await?;
+        if interrupted > 0 {
+            tracing::warn!("Marked {} earlier runs that were interrupted as failed", interrupted);
+        }
+
+        let mut outcome = pass.instrument(span).await;
+        let (status, synced, failed, error) = match &mut outcome {
+            Ok(result) => {
+                result.run_id = self.run_id.clone();
+                result.tampered = std::mem::take(&mut *self.tampered.lock().unwrap());
+                for trip in self.breaker.lock().unwrap().take_tripped() {
+                    result.warnings.push(format!(
+                        "Skipped {} for the rest of the run after repeated failures ({}); {} transfers were not attempted",
+                        trip.location.as_str(), trip.last_error, trip.skipped,
+                    ));
+                    result.degraded.push(trip.location);
+                }
+                let status = if result.awaiting_approval {
+                    RunStatus::AwaitingApproval
+                } else if result.cancelled {
+                    RunStatus::Cancelled
+                } else {
+                    RunStatus::Completed
+                };
+                (status, result.files_synced as i64, result.files_failed as i64, None)
+            }
+            Err(e) => (RunStatus::Failed, 0, 0, Some(e.to_string())),
+        };
+        // A sync succeeds when every file it set out to sync was synced
+        let succeeded = status == RunStatus::Completed && failed == 0;
+        let profile_error = match (&error, status) {
+            (None, RunStatus::Completed) if failed > 0 => Some(format!("{} files failed to sync", failed)),
+            _ => error.clone(),
+        };
+        let run_id = self.run_id.clone();
+        self.db.call(move |conn| {
+            DbOperations::finish_sync_run(conn, &run_id, status, synced, failed, error.as_deref())?;
+            DbOperations::record_sync_outcome(conn, profile_id, succeeded, profile_error.as_deref())
+        }).await?;
+        outcome
+    }
+
+    /// Scan, plan and, with `execute`, carry out the plan. Without it the result
+    /// only holds the plan and its copies. The run records each phase as it enters
+    /// it, so a run that stops shows where: scan, plan, safety check, execute, commit.
+    async fn plan_and_run(&self, execute: bool) -> Result<SyncResult> {
         tracing::info!("Starting sync for profile {}", self.profile_id);
 
         let mut result = SyncResult::default();
 
-        // Step 1: Scan all locations
-        tracing::info!("Scanning local files...");
-        let local_files = self.scan_location(&self.local_provider, FileLocation::Local).await?;
-        tracing::info!("Found {} local files", local_files.len());
+        self.enter(RunPhase::Scan).await?;
+        let snapshots = self.scan_all().await?;
 
-        let gdrive_files = if let Some(ref provider) = self.gdrive_provider {
-            tracing::info!("Scanning Google Drive files...");
-            let files = self.scan_location(provider, FileLocation::GoogleDrive).await?;
-            tracing::info!("Found {} Google Drive files", files.len());
-            files
-        } else {
-            HashMap::new()
-        };
+        if execute && self.stop_requested(&mut result) {
+            return Ok(result);
+        }
 
-        let smb_files = if let Some(ref provider) = self.smb_provider {
-            tracing::info!("Scanning Samba files...");
-            let files = self.scan_location(provider, FileLocation::Smb).await?;
-            tracing::info!("Found {} Samba files", files.len());
-            files
-        } else {
-            HashMap::new()
-        };
+        self.enter(RunPhase::Plan).await?;
+        let plan = self.plan(&snapshots, &mut result).await?;
+        if let Some(ref callback) = self.plan_callback {
+            callback(&result.plan, !execute);
+        }
+        if !execute || self.stop_requested(&mut result) {
+            return Ok(result);
+        }
 
-        // Step 2: Get last known state from database
-        let last_known_state = self.get_last_known_state().await?;
+        self.enter(RunPhase::SafetyCheck).await?;
+        if !self.safety_check(&snapshots, &plan, &mut result).await? {
+            return Ok(result);
+        }
 
-        // Step 3: Determine sync actions for each file
-        let all_paths = self.collect_all_paths(&local_files, &gdrive_files, &smb_files);
-        let total_files = all_paths.len();
-        tracing::info!("Processing {} unique files", total_files);
+        self.enter(RunPhase::Execute).await?;
+        let execution = self.execute(&snapshots, plan, &mut result).await?;
 
-        // First pass: collect all sync actions
-        let mut planned_actions: Vec<(PathBuf, SyncAction)> = Vec::new();
-        for path in &all_paths {
-            let local = local_files.get(path);
-            let gdrive = gdrive_files.get(path);
-            let smb = smb_files.get(path);
-            let last_known = last_known_state.get(path);
+        self.enter(RunPhase::Commit).await?;
+        self.commit(&snapshots, execution).await?;
+        self.record_conflicts(&snapshots, &result.conflicts).await?;
+        self.purge_expired_trash().await?;
+
+        tracing::info!("Sync completed: synced={}, failed={}, conflicts={}",
+                       result.files_synced, result.files_failed, result.files_conflict);
+        Ok(result)
+    }
 
-            let action = self.determine_sync_action(path, local, gdrive, smb, last_known);
-            planned_actions.push((path.clone(), action));
+    /// Whether the run was cancelled; if so `result` says it stopped early.
+    fn stop_requested(&self, result: &mut SyncResult) -> bool {
+        if self.cancellation.is_cancelled() && !result.cancelled {
+            tracing::info!("Sync cancelled");
+            result.cancelled = true;
         }
+        result.cancelled
+    }
 
-        // Step 3a: Check deletion safety
-        self.check_deletion_safety(&planned_actions, total_files)?;
+    /// Record that the run has entered `phase`. Outside a recorded run, e.g. for
+    /// an estimate, there is no run to record it with.
+    async fn enter(&self, phase: RunPhase) -> Result<()> {
+        tracing::debug!("Entering the {} phase", phase.as_str());
+        let run_id = self.run_id.clone();
+        self.db.call(move |conn| DbOperations::set_sync_run_phase(conn, &run_id, phase)).await
+    }
 
-        // Step 3b: Execute sync actions
-        let mut processed = 0;
-        for (path, action) in planned_actions {
-            // Report progress
-            if let Some(ref callback) = self.progress_callback {
-                let filename = path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                callback(processed, total_files, filename.clone(), "processing".to_string());
-            }
+    /// Scan phase: list the files at every configured location.
+    async fn scan_all(&self) -> Result<Snapshots> {
+        let snapshots = Snapshots::new()?;
 
-            match action {
-                SyncAction::NoAction => {
-                    tracing::debug!("No action needed for: {}", path.display());
-                    result.files_synced += 1;
-                }
-                SyncAction::Sync { operations } => {
-                    tracing::info!("Syncing: {} ({} operations)", path.display(), operations.len());
+        tracing::info!("Scanning local files...");
+        self.report(0, 0, "", SyncPhase::Scanning { provider: FileLocation::Local });
+        let count = self.scan_location(&self.local_provider, FileLocation::Local, &snapshots).await?;
+        tracing::info!("Found {} local files", count);
 
-                    // Report syncing operation
-                    if let Some(ref callback) = self.progress_callback {
-                        let filename = path.file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown")
-                            .to_string();
-                        callback(processed, total_files, filename.clone(), "syncing".to_string());
-                    }
+        if let Some(ref provider) = self.gdrive_provider {
+            tracing::info!("Scanning Google Drive files...");
+            self.report(0, 0, "", SyncPhase::Scanning { provider: FileLocation::GoogleDrive });
+            let count = self.scan_location(provider, FileLocation::GoogleDrive, &snapshots).await?;
+            tracing::info!("Found {} Google Drive files", count);
+        }
 
-                    match self.execute_sync_operations(&path, operations).await {
-                        Ok(_) => {
-                            result.files_synced += 1;
-                            tracing::info!("Successfully synced: {}", path.display());
+        if let Some(ref provider) = self.smb_provider {
+            tracing::info!("Scanning Samba files...");
+            self.report(0, 0, "", SyncPhase::Scanning { provider: FileLocation::Smb });
+            let count = self.scan_location(provider, FileLocation::Smb, &snapshots).await?;
+            tracing::info!("Found {} Samba files", count);
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Plan phase: decide what to do with every file. The plan, its copies and
+    /// anything held back are reported in `result`.
+    async fn plan(&self, snapshots: &Snapshots, result: &mut SyncResult) -> Result<RunPlan> {
+        let planning_started = Instant::now();
+        self.report(0, 0, "", SyncPhase::Planning);
+        self.load_last_known_state(snapshots).await?;
+        let profile_id = self.profile_id;
+        let (holds, paused) = self.db.call(move |conn| {
+            Ok((LegalHolds::load(conn, profile_id)?, DbOperations::get_paused_locations(conn, profile_id)?))
+        }).await?;
+        let paused: HashSet<FileLocation> = paused.into_iter().map(|p| p.location).collect();
+
+        let gdrive_deletions = match &self.gdrive_provider {
+            Some(provider) => provider.lock().await.lists_every_file(),
+            None => true,
+        };
+        let policies = PlanPolicies {
+            gdrive: self.gdrive_provider.is_some(),
+            smb: self.smb_provider.is_some(),
+            extensions: &self.policies,
+            scope: self.path_filter.as_ref(),
+            // A signature names the path it was made for, so signed files are copied anew
+            moves: self.signer.is_none(),
+            gdrive_deletions,
+        };
+        let planner::Plan { actions: mut planned_actions, total_files, in_sync, duplicates } =
+            planner::plan(snapshots, &policies)?;
+        tracing::info!("Processing {} unique files", total_files);
+        result.files_synced += in_sync;
+        for path in duplicates {
+            tracing::warn!("Skipping {}: several files share this path", path.display());
+            result.duplicates.push(path.to_string_lossy().to_string());
+        }
+        let moved_from: HashMap<PathBuf, PathBuf> = planned_actions.iter()
+            .filter_map(|(path, action)| match action {
+                SyncAction::Sync { reason: ChangeReason::Moved { from, .. }, .. } => Some((path.clone(), PathBuf::from(from))),
+                _ => None,
+            })
+            .collect();
+
+        // Hold back files mid-save, changes involving paused locations and
+        // incomplete consistency groups, and keep group members adjacent in the
+        // queue, behind the working set when a member is in it
+        let mut deferred = self.defer_unsettled(&mut planned_actions, snapshots, result).await?;
+        deferred.extend(self.defer_paused(&mut planned_actions, &paused, result));
+        deferred.extend(self.defer_incomplete_groups(&mut planned_actions, result).await);
+        let mut group_ranks: HashMap<String, usize> = HashMap::new();
+        for (path, _) in &planned_actions {
+            if let Some(group) = self.groups.group_of(path) {
+                let rank = group_ranks.entry(group.name.clone()).or_insert(usize::MAX);
+                *rank = (*rank).min(self.working_set_rank(path));
+            }
+        }
+        planned_actions.sort_by_cached_key(|(path, _)| {
+            let group = self.groups.group_of(path).map(|g| g.name.clone());
+            let rank = group.as_ref().map_or_else(|| self.working_set_rank(path), |name| group_ranks[name]);
+            (rank, group, path.clone())
+        });
+
+        let mut to_queue: Vec<(PathBuf, Vec<SyncOperation>)> = Vec::new();
+        let mut reasons = HashMap::new();
+        for (path, action) in planned_actions {
+            match action {
+                // Counted while planning
+                SyncAction::NoAction => {}
+                SyncAction::Sync { mut operations, reason } => {
+                    let planned = operations.len();
+                    operations.retain(|op| match op {
+                        SyncOperation::Upload { to, path, .. } => !self.naming.blocks(path, to),
+                        SyncOperation::Move { location, path, .. } => !self.naming.blocks(path, location),
+                        SyncOperation::Delete { .. } => true,
+                    });
+                    if operations.len() < planned {
+                        tracing::warn!("Blocked sync of {}: name violates the naming convention", path.display());
+                        result.blocked.push(path.to_string_lossy().to_string());
+                    }
+                    // Held files are never moved away; the new path gets a copy instead
+                    if let ChangeReason::Moved { at, from } = &reason {
+                        if holds.covering(Path::new(from)).is_some() {
+                            for operation in operations.iter_mut() {
+                                if let SyncOperation::Move { location, path, .. } = operation {
+                                    *operation = SyncOperation::Upload { from: at.clone(), to: location.clone(), path: path.clone() };
+                                }
+                            }
+                        }
+                    }
+                    // Held files only get copies where they are missing
+                    if let Some(hold) = holds.covering(&path) {
+                        let planned = operations.len();
+                        let mut kept = Vec::new();
+                        for operation in operations {
+                            let missing = match &operation {
+                                SyncOperation::Upload { to, path, .. } => !snapshots.contains(to, path)?,
+                                SyncOperation::Move { location, path, .. } => !snapshots.contains(location, path)?,
+                                SyncOperation::Delete { .. } => false,
+                            };
+                            if missing {
+                                kept.push(operation);
+                            }
+                        }
+                        operations = kept;
+                        if operations.len() < planned {
+                            tracing::warn!("Held back changes to {}: under legal hold since {} ({})",
+                                path.display(), hold.placed_at.format("%Y-%m-%d"), hold.reason);
+                            result.held.push(path.to_string_lossy().to_string());
                         }
-                        Err(e) => {
-                            result.files_failed += 1;
-                            tracing::error!("Failed to sync {}: {}", path.display(), e);
+                    }
+                    if operations.is_empty() {
+                        continue;
+                    }
+
+                    for operation in &operations {
+                        if let SyncOperation::Upload { to, path: file_path, .. } = operation {
+                            // Only files new to the destination introduce a revision there
+                            if snapshots.contains(to, file_path)? {
+                                continue;
+                            }
+                            let Some(incoming) = revisions::parse_revision(file_path) else {
+                                continue;
+                            };
+                            let same_part = snapshots.named_like(to, &incoming.base_part)?;
+                            if let Some(higher) = revisions::find_higher_revision(file_path, &same_part) {
+                                let warning = format!(
+                                    "{} is an older revision than {} on {}",
+                                    file_path.display(), higher.display(), to.as_str()
+                                );
+                                tracing::warn!("{}", warning);
+                                result.warnings.push(warning);
+                            }
                         }
                     }
+
+                    for operation in &operations {
+                        let size_bytes = match operation {
+                            SyncOperation::Upload { from, .. } => snapshots.get(from, &path)?.map_or(0, |s| s.size),
+                            SyncOperation::Delete { .. } | SyncOperation::Move { .. } => 0,
+                        };
+                        let step = PlanStep {
+                            index: result.plan.len(),
+                            path: path.to_string_lossy().to_string(),
+                            operation: operation.planned(),
+                            size_bytes,
+                            reason: Some(reason.clone()),
+                        };
+                        tracing::debug!("Plan step {}: {:?}", step.index, step.operation);
+                        result.plan.push(step);
+                    }
+                    reasons.insert(path.clone(), reason);
+                    to_queue.push((path, operations));
                 }
                 SyncAction::Conflict(conflict) => {
                     tracing::warn!("Conflict detected: {}", path.display());
+                    result.plan.push(PlanStep {
+                        index: result.plan.len(),
+                        path: path.to_string_lossy().to_string(),
+                        operation: PlannedOperation::Conflict,
+                        size_bytes: 0,
+                        reason: Some(ChangeReason::Conflicting),
+                    });
                     result.conflicts.push(conflict);
                     result.files_conflict += 1;
                 }
             }
-            processed += 1;
         }
 
-        // Step 4: Update last known state in database
-        self.update_last_known_state(&local_files, &gdrive_files, &smb_files).await?;
+        tracing::info!("Planned {} files in {} ms", total_files, planning_started.elapsed().as_millis());
 
-        tracing::info!("Sync completed: synced={}, failed={}, conflicts={}",
-                       result.files_synced, result.files_failed, result.files_conflict);
-        Ok(result)
+        Ok(RunPlan { to_queue, reasons, moved_from, deferred, total_files })
+    }
+
+    /// Safety check phase: stop before anything is changed when the plan deletes
+    /// too much or its uploads don't fit. Returns false, with the result marked as
+    /// awaiting approval, for a plan that needs an approval it doesn't have.
+    async fn safety_check(&self, snapshots: &Snapshots, plan: &RunPlan, result: &mut SyncResult) -> Result<bool> {
+        self.check_deletion_safety(&plan.to_queue, plan.total_files)?;
+
+        if let Some(ref policy) = self.approval {
+            let approved = self.approved_plan.as_deref().unwrap_or(&[]);
+            if !policy.is_covered(&result.plan, approved) {
+                tracing::info!("Sync plan with {} steps is awaiting approval", result.plan.len());
+                result.awaiting_approval = true;
+                return Ok(false);
+            }
+        }
+
+        self.check_storage_quota(&plan.to_queue, snapshots).await?;
+        Ok(true)
+    }
+
+    /// Execute phase: queue a transfer for every planned operation and run them.
+    async fn execute(&self, snapshots: &Snapshots, plan: RunPlan, result: &mut SyncResult) -> Result<Execution> {
+        self.discard_stale_transfers().await?;
+
+        let mut queued = Vec::new();
+        let mut queued_paths = Vec::new();
+        let mut transfer_targets: HashSet<(PathBuf, FileLocation)> = HashSet::new();
+        for (path, operations) in plan.to_queue {
+            tracing::info!("Queueing: {} ({} operations)", path.display(), operations.len());
+            for operation in &operations {
+                // The planning snapshot of what the operation replaces, re-checked before it runs
+                let (size, target) = match operation {
+                    SyncOperation::Upload { from, to, .. } => {
+                        (snapshots.get(from, &path)?.map(|s| s.size as i64), snapshots.get(to, &path)?)
+                    }
+                    SyncOperation::Delete { location, .. } => (None, snapshots.get(location, &path)?),
+                    SyncOperation::Move { location, from, .. } => (None, snapshots.get(location, from)?),
+                };
+                let reason = plan.reasons.get(&path).map(|change| OperationReason {
+                    change: change.clone(),
+                    allowed_by: self.authorization(&operation.planned()),
+                });
+                queued.push(self.enqueue_operation(operation, size, target.as_ref(), reason).await?);
+                let target = match operation {
+                    SyncOperation::Upload { to, .. } => to.clone(),
+                    SyncOperation::Delete { location, .. } => location.clone(),
+                    SyncOperation::Move { location, from, .. } => {
+                        transfer_targets.insert((from.clone(), location.clone()));
+                        location.clone()
+                    }
+                };
+                transfer_targets.insert((path.clone(), target));
+            }
+            queued_paths.push(path);
+        }
+
+        let drive_folders = if self.connections.is_empty() { HashSet::new() } else { folders_of(snapshots)? };
+        let outcomes = self.run_transfers(&queued, drive_folders).await?;
+        if self.stop_requested(result) {
+            self.drop_queued_transfers(&queued).await?;
+        }
+        let mut unfinished: HashSet<PathBuf> = plan.deferred;
+        unfinished.extend(snapshots.duplicates()?);
+        for path in queued_paths {
+            match outcomes.get(path.to_string_lossy().as_ref()) {
+                Some(TransferOutcome::Succeeded) => {
+                    result.files_synced += 1;
+                    tracing::info!("Successfully synced: {}", path.display());
+                    result.completed.push(path.to_string_lossy().to_string());
+                }
+                Some(TransferOutcome::Failed) => {
+                    result.files_failed += 1;
+                    unfinished.insert(path);
+                }
+                Some(TransferOutcome::Conflict) => {
+                    result.conflicts.push(ConflictInfo {
+                        file_path: path.to_string_lossy().to_string(),
+                        local_hash: snapshots.get(&FileLocation::Local, &path)?.and_then(|s| s.hash),
+                        gdrive_hash: snapshots.get(&FileLocation::GoogleDrive, &path)?.and_then(|s| s.hash),
+                        smb_hash: snapshots.get(&FileLocation::Smb, &path)?.and_then(|s| s.hash),
+                    });
+                    result.files_conflict += 1;
+                    unfinished.insert(path);
+                }
+                // Not started before the sync was cancelled
+                None if result.cancelled => {
+                    unfinished.insert(path);
+                }
+                None => {
+                    result.warnings.push(format!("Transfers of {} were cancelled", path.display()));
+                    unfinished.insert(path);
+                }
+            }
+        }
+        // The old path of a moved file keeps its state until the file has arrived
+        for (path, from) in &plan.moved_from {
+            if outcomes.get(path.to_string_lossy().as_ref()) != Some(&TransferOutcome::Succeeded) {
+                unfinished.insert(from.clone());
+            }
+        }
+
+        Ok(Execution { transfers: queued.len(), unfinished, transfer_targets })
+    }
+
+    /// Commit phase: record the last known state of everything the transfers did
+    /// not record. Files with failed, cancelled or deferred transfers keep their old
+    /// state so the divergence is detected again on the next sync.
+    async fn commit(&self, snapshots: &Snapshots, execution: Execution) -> Result<()> {
+        self.report(execution.transfers, execution.transfers, "", SyncPhase::Finalizing);
+        self.update_last_known_state(snapshots, |path, location| {
+            !execution.unfinished.contains(path)
+                && !execution.transfer_targets.contains(&(path.to_path_buf(), location.clone()))
+        }).await
+    }
+
+    /// Keep the conflicts of the run until they are resolved: the states just
+    /// committed hide them from the next run. A file already awaiting resolution
+    /// has its recorded copies replaced with the ones seen now.
+    async fn record_conflicts(&self, snapshots: &Snapshots, conflicts: &[ConflictInfo]) -> Result<()> {
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        let mut detected = Vec::new();
+        for conflict in conflicts {
+            let path = Path::new(&conflict.file_path);
+            let local = snapshots.get(&FileLocation::Local, path)?;
+            let gdrive = snapshots.get(&FileLocation::GoogleDrive, path)?;
+            let smb = snapshots.get(&FileLocation::Smb, path)?;
+            detected.push(Conflict {
+                local_hash: conflict.local_hash.clone(),
+                gdrive_hash: conflict.gdrive_hash.clone(),
+                smb_hash: conflict.smb_hash.clone(),
+                local_modified: local.as_ref().map(|s| s.modified),
+                gdrive_modified: gdrive.as_ref().map(|s| s.modified),
+                smb_modified: smb.as_ref().map(|s| s.modified),
+                local_size: local.map(|s| s.size as i64),
+                gdrive_size: gdrive.map(|s| s.size as i64),
+                smb_size: smb.map(|s| s.size as i64),
+                ..Conflict::new(self.profile_id, conflict.file_path.clone())
+            });
+        }
+
+        let profile_id = self.profile_id;
+        self.db.call(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let unresolved: HashMap<String, i64> = DbOperations::get_conflicts(&tx, profile_id)?
+                .into_iter()
+                .filter(|c| !c.resolved)
+                .filter_map(|c| Some((c.file_path, c.id?)))
+                .collect();
+            for conflict in &detected {
+                match unresolved.get(&conflict.file_path) {
+                    Some(&id) => DbOperations::refresh_conflict(&tx, id, conflict)?,
+                    None => {
+                        DbOperations::create_conflict(&tx, conflict)?;
+                    }
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        }).await
     }
 
     async fn scan_location(
         &self,
         provider: &Arc<Mutex<dyn StorageProvider>>,
         location: FileLocation,
-    ) -> Result<HashMap<PathBuf, FileSnapshot>> {
+        snapshots: &Snapshots,
+    ) -> Result<usize> {
         let provider_lock = provider.lock().await;
-        let files = provider_lock.list_files(Path::new("")).await?;
-
-        let mut file_map = HashMap::new();
-        for file_meta in files {
-            let snapshot = FileSnapshot {
-                path: file_meta.path.clone(),
-                hash: file_meta.hash.clone(),
-                size: file_meta.size,
-                modified: file_meta.modified,
-                location: location.clone(),
+        let files = async {
+            if self.scan_cache {
+                scan_cache::list_files(self.profile_id, &location, &*provider_lock).await
+            } else {
+                provider_lock.list_files(Path::new("")).await
+            }
+        }
+        .instrument(tracing::info_span!("scan", location = location.as_str()))
+        .await?;
+
+        // Offload placeholders, signatures and the trash are bookkeeping, never synced
+        let bookkeeping = |path: &Path| stubs::is_stub(path) || signing::is_signature(path) || trash::is_trash(path);
+        let scanned = files.into_iter()
+            .filter(|f| !bookkeeping(&f.path))
+            .filter_map(|file_meta| {
+                let unprefixed = self.prefixes.logical_path(&file_meta.path, &location)?;
+                let path = self.policies.logical_path(&unprefixed, &location);
+                if self.policies.applies(&path, ExtensionBehavior::NeverSync)
+                    || !self.selection.selects(&path)
+                    || (self.skip_cad_temp && cad_temp::is_cad_temp(&path))
+                    || (location != FileLocation::Local && self.policies.applies(&path, ExtensionBehavior::LocalOnly)) {
+                    return None;
+                }
+                let snapshot = FileSnapshot {
+                    hash: file_meta.hash,
+                    fingerprint: file_meta.fingerprint,
+                    size: file_meta.size,
+                    modified: file_meta.modified,
+                    location: location.clone(),
+                };
+                Some((path, snapshot))
+            });
+
+        snapshots.insert_all(scanned)
+    }
+
+    /// Remove the planned transfers of files the program saving them may not be done
+    /// with: uploads of local changes newer than the quiet period, and files written
+    /// within the settle delay whose size or modification time changed while waiting
+    /// it out. Returns the deferred paths.
+    async fn defer_unsettled(
+        &self,
+        planned_actions: &mut Vec<(PathBuf, SyncAction)>,
+        snapshots: &Snapshots,
+        result: &mut SyncResult,
+    ) -> Result<HashSet<PathBuf>> {
+        let mut deferred = HashSet::new();
+        let now = chrono::Utc::now();
+        let since = |delay: Duration| now - chrono::Duration::from_std(delay).unwrap_or_default();
+
+        if !self.quiet_period.is_zero() {
+            let cutoff = since(self.quiet_period);
+            for (path, action) in planned_actions.iter() {
+                let SyncAction::Sync { operations, .. } = action else {
+                    continue;
+                };
+                let uploads_local = operations.iter()
+                    .any(|op| matches!(op, SyncOperation::Upload { from: FileLocation::Local, .. }));
+                let Some(local) = snapshots.get(&FileLocation::Local, path)?.filter(|s| uploads_local && s.modified > cutoff) else {
+                    continue;
+                };
+                let warning = format!(
+                    "Deferred {}: changed {}s ago, within the quiet period of {}s",
+                    path.display(), (now - local.modified).num_seconds().max(0), self.quiet_period.as_secs(),
+                );
+                tracing::info!("{}", warning);
+                result.warnings.push(warning);
+                deferred.insert(path.clone());
+            }
+        }
+
+        let cutoff = since(self.save_settle);
+        let mut recent: Vec<(&PathBuf, FileSnapshot)> = Vec::new();
+        for (path, action) in planned_actions.iter() {
+            if !matches!(action, SyncAction::Sync { .. }) || deferred.contains(path) {
+                continue;
+            }
+            if let Some(local) = snapshots.get(&FileLocation::Local, path)?.filter(|s| s.modified > cutoff) {
+                recent.push((path, local));
+            }
+        }
+        if !self.save_settle.is_zero() && !recent.is_empty() {
+            // One wait covers all of them
+            tracing::info!("Waiting {:?} for {} recently saved files to settle", self.save_settle, recent.len());
+            tokio::time::sleep(self.save_settle).await;
+            let provider = self.local_provider.lock().await;
+            for (path, before) in recent {
+                let after = provider.get_metadata(path).await?;
+                if after.is_some_and(|after| after.size == before.size && after.modified == before.modified) {
+                    continue;
+                }
+                let warning = format!("Deferred {}: it is still being saved", path.display());
+                tracing::warn!("{}", warning);
+                result.warnings.push(warning);
+                deferred.insert(path.clone());
+            }
+        }
+
+        planned_actions.retain(|(path, _)| !deferred.contains(path));
+        Ok(deferred)
+    }
+
+    /// Remove the planned transfers of every consistency group with a member that
+    /// cannot be propagated this run: it is in conflict, its name is blocked, or a
+    /// destination is unreachable. Returns the deferred paths.
+    async fn defer_incomplete_groups(
+        &self,
+        planned_actions: &mut Vec<(PathBuf, SyncAction)>,
+        result: &mut SyncResult,
+    ) -> HashSet<PathBuf> {
+        let mut blockers: HashMap<String, String> = HashMap::new();
+        let mut reachable: HashMap<FileLocation, bool> = HashMap::new();
+
+        for (path, action) in planned_actions.iter() {
+            let Some(group) = self.groups.group_of(path) else {
+                continue;
+            };
+            if blockers.contains_key(&group.name) {
+                continue;
+            }
+
+            let operations = match action {
+                SyncAction::NoAction => continue,
+                SyncAction::Conflict(_) => {
+                    blockers.insert(group.name.clone(), format!("{} has a conflict", path.display()));
+                    continue;
+                }
+                SyncAction::Sync { operations, .. } => operations,
             };
-            file_map.insert(file_meta.path, snapshot);
+            for operation in operations {
+                let destination = match operation {
+                    SyncOperation::Upload { to, .. } => to,
+                    SyncOperation::Delete { location, .. } | SyncOperation::Move { location, .. } => location,
+                };
+                let reason = if !matches!(operation, SyncOperation::Delete { .. }) && self.naming.blocks(path, destination) {
+                    Some(format!("{} violates the naming convention", path.display()))
+                } else if !self.is_reachable(destination, &mut reachable).await {
+                    Some(format!("{} is unreachable", destination.as_str()))
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    blockers.insert(group.name.clone(), reason);
+                    break;
+                }
+            }
         }
 
-        Ok(file_map)
+        let mut deferred = HashSet::new();
+        planned_actions.retain(|(path, action)| {
+            let Some((name, reason)) = self.groups.group_of(path)
+                .and_then(|group| blockers.get_key_value(&group.name))
+            else {
+                return true;
+            };
+            if !matches!(action, SyncAction::Sync { .. }) {
+                return true;
+            }
+
+            let warning = format!("Deferred {}: consistency group '{}' is incomplete ({})", path.display(), name, reason);
+            tracing::warn!("{}", warning);
+            result.warnings.push(warning);
+            deferred.insert(path.clone());
+            false
+        });
+        deferred
     }
 
-    fn collect_all_paths(
+    /// Remove the planned operations to and from paused locations, and all of
+    /// those propagating a change made at one. Returns the paths whose changes
+    /// are left for a run after the locations are resumed.
+    fn defer_paused(
         &self,
-        local: &HashMap<PathBuf, FileSnapshot>,
-        gdrive: &HashMap<PathBuf, FileSnapshot>,
-        smb: &HashMap<PathBuf, FileSnapshot>,
-    ) -> Vec<PathBuf> {
-        let mut paths = HashSet::new();
+        planned_actions: &mut Vec<(PathBuf, SyncAction)>,
+        paused: &HashSet<FileLocation>,
+        result: &mut SyncResult,
+    ) -> HashSet<PathBuf> {
+        let mut deferred = HashSet::new();
+        if paused.is_empty() {
+            return deferred;
+        }
 
-        for path in local.keys() {
-            paths.insert(path.clone());
+        for (path, action) in planned_actions.iter_mut() {
+            let SyncAction::Sync { operations, reason } = action else {
+                continue;
+            };
+            let planned = operations.len();
+            match reason {
+                ChangeReason::Added { at } | ChangeReason::Modified { at }
+                | ChangeReason::Deleted { at } | ChangeReason::Moved { at, .. } if paused.contains(at) => operations.clear(),
+                _ => operations.retain(|op| match op {
+                    SyncOperation::Upload { from, to, .. } => !paused.contains(from) && !paused.contains(to),
+                    SyncOperation::Delete { location, .. } | SyncOperation::Move { location, .. } => !paused.contains(location),
+                }),
+            }
+            if operations.len() == planned {
+                continue;
+            }
+
+            tracing::info!("Deferred changes to {}: a location is paused", path.display());
+            result.paused.push(path.to_string_lossy().to_string());
+            deferred.insert(path.clone());
+            // The old path stays tracked until the file is moved everywhere
+            if let ChangeReason::Moved { from, .. } = reason {
+                deferred.insert(PathBuf::from(from.as_str()));
+            }
+        }
+        planned_actions.retain(|(_, action)| !matches!(action, SyncAction::Sync { operations, .. } if operations.is_empty()));
+
+        if !result.paused.is_empty() {
+            let mut locations: Vec<&str> = paused.iter().map(FileLocation::as_str).collect();
+            locations.sort_unstable();
+            let warning = format!(
+                "Deferred changes to {} files until {} {} resumed",
+                result.paused.len(), locations.join(" and "), if locations.len() == 1 { "is" } else { "are" },
+            );
+            tracing::warn!("{}", warning);
+            result.warnings.push(warning);
+        }
+        deferred
+    }
+
+    async fn is_reachable(&self, location: &FileLocation, cache: &mut HashMap<FileLocation, bool>) -> bool {
+        if let Some(reachable) = cache.get(location) {
+            return *reachable;
+        }
+        let reachable = match self.get_provider(location) {
+            Ok(provider) => provider.lock().await.test_connection().await.unwrap_or(false),
+            Err(_) => false,
+        };
+        cache.insert(location.clone(), reachable);
+        reachable
+    }
+
+    /// After a member of a consistency group failed, drop the group's transfers
+    /// that have not run yet so the other members are not propagated alone.
+    async fn cancel_group_transfers(&self, failed_path: &Path, remaining: &HashSet<i64>) -> Result<()> {
+        let Some(group) = self.groups.group_of(failed_path) else {
+            return Ok(());
+        };
+
+        let mut cancelled: Vec<i64> = Vec::new();
+        self.walk_queue(None, |t| {
+            if t.id.is_some_and(|id| remaining.contains(&id))
+                && self.groups.group_of(Path::new(&t.file_path)).is_some_and(|g| g.name == group.name)
+            {
+                cancelled.extend(t.id);
+            }
+            ControlFlow::<()>::Continue(())
+        }).await?;
+        if cancelled.is_empty() {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            "Cancelling {} transfers of consistency group '{}' after {} failed",
+            cancelled.len(), group.name, failed_path.display()
+        );
+        self.db.call(move |conn| {
+            for id in cancelled {
+                DbOperations::delete_transfer(conn, id)?;
+            }
+            Ok(())
+        }).await
+    }
+
+    /// Retry transfers that previously failed, outside of a full sync.
+    pub async fn retry_transfers(&self, transfer_ids: &[i64]) -> Result<SyncResult> {
+        self.tracked(self.retry_queued(transfer_ids)).await
+    }
+
+    async fn retry_queued(&self, transfer_ids: &[i64]) -> Result<SyncResult> {
+        self.enter(RunPhase::Execute).await?;
+        let ids = transfer_ids.to_vec();
+        self.db.call(move |conn| {
+            for id in ids {
+                DbOperations::update_transfer_status(conn, id, &TransferStatus::Queued, None)?;
+            }
+            Ok(())
+        }).await?;
+
+        let mut result = SyncResult::default();
+        for (path, outcome) in self.run_transfers(transfer_ids, HashSet::new()).await? {
+            match outcome {
+                TransferOutcome::Succeeded => result.files_synced += 1,
+                TransferOutcome::Failed => {
+                    result.files_failed += 1;
+                    tracing::error!("Retry failed for {}", path);
+                }
+                TransferOutcome::Conflict => {
+                    result.files_conflict += 1;
+                    tracing::warn!("Retry of {} stopped: the destination changed", path);
+                }
+            }
         }
-        for path in gdrive.keys() {
-            paths.insert(path.clone());
+        Ok(result)
+    }
+
+    /// Carry out a resolution of `conflict` chosen by `user`, as previewed: each
+    /// step runs as a transfer, in the preview's order, and only if the copy it
+    /// changes is still as the conflict saw it. Once every step succeeded the
+    /// copies left as they were are recorded as synced and the conflict as
+    /// resolved, so the next sync finds the file in sync.
+    pub async fn resolve_conflict(&self, conflict: &ConflictInfo, preview: &ResolutionPreview, user: &str) -> Result<SyncResult> {
+        self.tracked(self.run_resolution(conflict, preview, user)).await
+    }
+
+    async fn run_resolution(&self, conflict: &ConflictInfo, preview: &ResolutionPreview, user: &str) -> Result<SyncResult> {
+        tracing::info!("Resolving the conflict on {} with {}", preview.file_path, preview.resolution.as_str());
+        self.enter(RunPhase::Execute).await?;
+
+        // What the conflict saw at each location, for the transfers to check against
+        let seen = |location: &FileLocation| conflict.hash_at(location).map(|hash| FileSnapshot {
+            hash: Some(hash.to_string()),
+            fingerprint: None,
+            size: 0,
+            modified: chrono::Utc::now(),
+            location: location.clone(),
+        });
+        let reason = OperationReason {
+            change: ChangeReason::Conflicting,
+            allowed_by: Authorization::ConflictResolution { by: user.to_string() },
+        };
+        let mut queued = Vec::new();
+        for step in &preview.steps {
+            let (operation, target) = match &step.action {
+                ResolutionAction::Overwrite { from } | ResolutionAction::Copy { from } => {
+                    let target = matches!(step.action, ResolutionAction::Overwrite { .. }).then(|| seen(&step.location)).flatten();
+                    (SyncOperation::Upload { from: from.clone(), to: step.location.clone(), path: PathBuf::from(&step.path) }, target)
+                }
+                ResolutionAction::Rename { to } => (
+                    SyncOperation::Move { location: step.location.clone(), from: PathBuf::from(&step.path), path: PathBuf::from(to) },
+                    seen(&step.location),
+                ),
+                ResolutionAction::Delete => {
+                    (SyncOperation::Delete { location: step.location.clone(), path: PathBuf::from(&step.path) }, seen(&step.location))
+                }
+            };
+            queued.push(self.enqueue_operation(&operation, None, target.as_ref(), Some(reason.clone())).await?);
         }
-        for path in smb.keys() {
-            paths.insert(path.clone());
+
+        let mut result = SyncResult::default();
+        for (path, outcome) in self.run_transfers(&queued, HashSet::new()).await? {
+            match outcome {
+                TransferOutcome::Succeeded => {
+                    result.files_synced += 1;
+                    result.completed.push(path);
+                }
+                TransferOutcome::Failed => result.files_failed += 1,
+                TransferOutcome::Conflict => result.files_conflict += 1,
+            }
+        }
+        result.completed.sort();
+        if result.files_failed > 0 || result.files_conflict > 0 || self.cancellation.is_cancelled() {
+            result.warnings.push(format!("The conflict on {} is not fully resolved", preview.file_path));
+            return Ok(result);
+        }
+
+        let path = Path::new(&preview.file_path);
+        let mut kept = Vec::new();
+        for location in &preview.unchanged {
+            let provider = self.get_provider(location)?.lock().await;
+            if let Some(metadata) = provider.get_metadata(&self.stored_path(path, location)).await? {
+                kept.push(FileState {
+                    id: None,
+                    profile_id: self.profile_id,
+                    file_path: preview.file_path.clone(),
+                    location: location.clone(),
+                    content_hash: metadata.hash,
+                    hash_fingerprint: metadata.fingerprint,
+                    size_bytes: Some(metadata.size as i64),
+                    modified_at: Some(metadata.modified),
+                    synced_at: Some(chrono::Utc::now()),
+                    status: SyncStatus::Synced,
+                    metadata: None,
+                });
+            }
+        }
+        let resolved = Conflict {
+            resolved: true,
+            resolution: Some(preview.resolution.clone()),
+            local_hash: conflict.local_hash.clone(),
+            gdrive_hash: conflict.gdrive_hash.clone(),
+            smb_hash: conflict.smb_hash.clone(),
+            ..Conflict::new(self.profile_id, preview.file_path.clone())
+        };
+        let resolution = preview.resolution.clone();
+        self.db.call(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            DbOperations::upsert_file_states(&tx, &kept)?;
+            // Conflicts recorded when they were detected are marked resolved; others are recorded resolved
+            if DbOperations::resolve_conflicts(&tx, resolved.profile_id, &resolved.file_path, &resolution)? == 0 {
+                DbOperations::create_conflict(&tx, &resolved)?;
+            }
+            tx.commit()?;
+            Ok(())
+        }).await?;
+
+        tracing::info!("Resolved the conflict on {}", preview.file_path);
+        Ok(result)
+    }
+
+    /// Upload every local file missing from Google Drive, as many at once as the
+    /// Drive connections (see `with_connections`) and the measured throughput allow,
+    /// for the first push of a large vault. Uploads still queued from an interrupted
+    /// run are picked up without planning again; files already on Drive are left
+    /// to the next sync.
+    pub async fn bootstrap_upload(
+        &self,
+        deprioritized: &[PathBuf],
+        on_progress: &(dyn Fn(&BootstrapProgress) + Sync),
+    ) -> Result<SyncResult> {
+        self.tracked(self.run_bootstrap(deprioritized, on_progress)).await
+    }
+
+    async fn run_bootstrap(
+        &self,
+        deprioritized: &[PathBuf],
+        on_progress: &(dyn Fn(&BootstrapProgress) + Sync),
+    ) -> Result<SyncResult> {
+        let Some(gdrive) = &self.gdrive_provider else {
+            return Err(UvcadError::InvalidConfig("Google Drive is not configured".to_string()));
+        };
+        let mut result = SyncResult::default();
+
+        let profile_id = self.profile_id;
+        let pending: Vec<Transfer> = self.db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| t.source == Some(FileLocation::Local)
+                && t.destination == FileLocation::GoogleDrive
+                && self.in_scope(Path::new(&t.file_path)))
+            .collect();
+
+        // The local scan is needed either way to record the state of uploaded files
+        self.enter(RunPhase::Scan).await?;
+        let snapshots = Snapshots::new()?;
+        self.report(0, 0, "", SyncPhase::Scanning { provider: FileLocation::Local });
+        self.scan_location(&self.local_provider, FileLocation::Local, &snapshots).await?;
+
+        // Folders known to exist on Drive, which uploads can go into in parallel
+        let mut drive_folders: HashSet<PathBuf> = HashSet::new();
+        let queued = if pending.is_empty() {
+            self.report(0, 0, "", SyncPhase::Scanning { provider: FileLocation::GoogleDrive });
+            self.scan_location(gdrive, FileLocation::GoogleDrive, &snapshots).await?;
+            drive_folders = folders_of(&snapshots)?;
+
+            self.enter(RunPhase::Plan).await?;
+            self.report(0, 0, "", SyncPhase::Planning);
+            let mut missing = Vec::new();
+            let mut after: Option<PathBuf> = None;
+            loop {
+                let page = snapshots.page_at(&FileLocation::Local, after.as_deref())?;
+                let Some((last, _)) = page.last() else {
+                    break;
+                };
+                after = Some(last.clone());
+                for (path, _) in page {
+                    if snapshots.contains(&FileLocation::GoogleDrive, &path)?
+                        || snapshots.is_duplicate(&path)?
+                        || !self.in_scope(&path)
+                        || self.policies.applies(&path, ExtensionBehavior::LocalOnly) {
+                        continue;
+                    }
+                    if self.naming.blocks(&path, &FileLocation::GoogleDrive) {
+                        tracing::warn!("Blocked upload of {}: name violates the naming convention", path.display());
+                        result.blocked.push(path.to_string_lossy().to_string());
+                        continue;
+                    }
+                    missing.push(path);
+                }
+            }
+            result.duplicates = snapshots.duplicates()?.iter().map(|p| p.to_string_lossy().to_string()).collect();
+
+            let to_queue: Vec<(PathBuf, Vec<SyncOperation>)> = bootstrap::upload_order(missing, deprioritized)
+                .into_iter()
+                .map(|path| {
+                    let upload = SyncOperation::Upload { from: FileLocation::Local, to: FileLocation::GoogleDrive, path: path.clone() };
+                    (path, vec![upload])
+                })
+                .collect();
+            self.enter(RunPhase::SafetyCheck).await?;
+            self.check_storage_quota(&to_queue, &snapshots).await?;
+
+            self.enter(RunPhase::Execute).await?;
+            self.discard_stale_transfers().await?;
+
+            let mut queued = Vec::new();
+            for (path, operations) in &to_queue {
+                let size = snapshots.get(&FileLocation::Local, path)?.map(|s| s.size as i64);
+                let reason = OperationReason {
+                    change: ChangeReason::Added { at: FileLocation::Local },
+                    allowed_by: Authorization::InitialUpload,
+                };
+                queued.push(self.enqueue_operation(&operations[0], size, None, Some(reason)).await?);
+            }
+            tracing::info!("Queued {} files for the initial upload", queued.len());
+            queued
+        } else {
+            tracing::info!("Resuming the initial upload: {} files left", pending.len());
+            self.enter(RunPhase::Execute).await?;
+            let ids: Vec<i64> = pending.iter().filter_map(|t| t.id).collect();
+            let restart = ids.clone();
+            self.db.call(move |conn| {
+                for id in restart {
+                    DbOperations::update_transfer_status(conn, id, &TransferStatus::Queued, None)?;
+                }
+                Ok(())
+            }).await?;
+            ids
+        };
+
+        let outcomes = self.run_concurrent(&queued, drive_folders, Some(on_progress)).await?;
+        let mut uploaded = HashSet::new();
+        for (path, outcome) in outcomes {
+            match outcome {
+                TransferOutcome::Succeeded => {
+                    result.files_synced += 1;
+                    uploaded.insert(PathBuf::from(path));
+                }
+                TransferOutcome::Failed => result.files_failed += 1,
+                TransferOutcome::Conflict => {
+                    result.files_conflict += 1;
+                    result.warnings.push(format!("{} was created on Google Drive during the upload", path));
+                }
+            }
         }
 
-        paths.into_iter().collect()
+        // Record the local side too, so the next sync sees these files as in sync
+        self.enter(RunPhase::Commit).await?;
+        self.report(queued.len(), queued.len(), "", SyncPhase::Finalizing);
+        self.update_last_known_state(&snapshots, |path, location| {
+            *location == FileLocation::Local && uploaded.contains(path)
+        }).await?;
+
+        tracing::info!("Initial upload finished: uploaded={}, failed={}, conflicts={}",
+                       result.files_synced, result.files_failed, result.files_conflict);
+        Ok(result)
     }
 
-    fn determine_sync_action(
+    /// Run queued transfers with one worker per connection to their destination,
+    /// each taking the next transfer to its destination in queue order, so
+    /// reordering takes effect at once. How many run at once per destination follows
+    /// its measured throughput. Uploads into Drive folders not in `drive_folders`
+    /// run one at a time: Drive allows several folders of the same name, and
+    /// parallel uploads into a new folder would each create one.
+    async fn run_concurrent(
         &self,
-        path: &Path,
-        local: Option<&FileSnapshot>,
-        gdrive: Option<&FileSnapshot>,
-        smb: Option<&FileSnapshot>,
-        last_known: Option<&LastKnownState>,
-    ) -> SyncAction {
-        // Three-way merge logic
-        // Compare current state with last known state to detect changes
-
-        let local_changed = Self::has_changed(local, last_known.and_then(|s| s.local.as_ref()));
-        let gdrive_changed = Self::has_changed(gdrive, last_known.and_then(|s| s.gdrive.as_ref()));
-        let smb_changed = Self::has_changed(smb, last_known.and_then(|s| s.smb.as_ref()));
-
-        tracing::debug!("File: {} - local_changed={}, gdrive_changed={}, smb_changed={}",
-                       path.display(), local_changed, gdrive_changed, smb_changed);
-
-        // No changes anywhere
-        if !local_changed && !gdrive_changed && !smb_changed {
-            return SyncAction::NoAction;
-        }
-
-        // Conflict: Multiple locations changed
-        let change_count = [local_changed, gdrive_changed, smb_changed].iter().filter(|&&c| c).count();
-        if change_count > 1 {
-            // Check if changes are identical by comparing all changed locations' hashes.
-            // Note: Cross-provider hash comparison (e.g. SHA-256 vs MD5) will never match,
-            // so we compare modification times as a fallback heuristic for cross-provider
-            // "same content" detection. If only one hash algorithm is used across all
-            // providers, hash comparison works directly.
-            let changed_snapshots: Vec<&FileSnapshot> = [
-                local.filter(|_| local_changed),
-                gdrive.filter(|_| gdrive_changed),
-                smb.filter(|_| smb_changed),
-            ].into_iter().flatten().collect();
-
-            let all_same = if changed_snapshots.len() >= 2 {
-                let first = &changed_snapshots[0];
-                changed_snapshots[1..].iter().all(|s| {
-                    // Compare hashes if both are Some and non-empty
-                    match (&first.hash, &s.hash) {
-                        (Some(h1), Some(h2)) if h1.len() == h2.len() => h1 == h2,
-                        _ => false, // Different hash algorithms or missing hashes — treat as conflict
-                    }
-                })
-            } else {
-                false
-            };
-
-            if all_same {
-                // Same content across all changed locations, not a conflict
-                return self.sync_to_missing(path, local, gdrive, smb);
-            }
+        transfer_ids: &[i64],
+        drive_folders: HashSet<PathBuf>,
+        on_progress: Option<&(dyn Fn(&BootstrapProgress) + Sync)>,
+    ) -> Result<HashMap<String, TransferOutcome>> {
+        struct Pool {
+            remaining: HashSet<i64>,
+            drive_folders: HashSet<PathBuf>,
+            creating_folders: bool,
+            running: HashMap<FileLocation, usize>,
+            limits: HashMap<FileLocation, AdaptiveConcurrency>,
+            outcomes: HashMap<String, TransferOutcome>,
+            progress: BootstrapProgress,
+        }
 
-            return SyncAction::Conflict(ConflictInfo {
-                file_path: path.to_string_lossy().to_string(),
-                local_hash: local.and_then(|f| f.hash.clone()),
-                gdrive_hash: gdrive.and_then(|f| f.hash.clone()),
-                smb_hash: smb.and_then(|f| f.hash.clone()),
-            });
+        let profile_id = self.profile_id;
+        let remaining: HashSet<i64> = transfer_ids.iter().copied().collect();
+        let transfers: Vec<Transfer> = self.db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| t.id.is_some_and(|id| remaining.contains(&id)))
+            .collect();
+        let bytes_total: u64 = transfers.iter().map(|t| t.size_bytes.unwrap_or(0).max(0) as u64).sum();
+
+        // One worker per connection to each destination
+        let mut workers: Vec<(FileLocation, &Arc<Mutex<dyn StorageProvider>>)> = Vec::new();
+        let mut limits = HashMap::new();
+        let mut destinations: Vec<&FileLocation> = transfers.iter().map(|t| &t.destination).collect();
+        destinations.sort_by_key(|l| location_rank(l));
+        destinations.dedup();
+        for location in destinations {
+            let extra = self.connections.get(location).map_or(&[][..], Vec::as_slice);
+            let connections: Vec<_> = std::iter::once(self.get_provider(location)?).chain(extra).collect();
+            limits.insert(location.clone(), AdaptiveConcurrency::new(connections.len()));
+            workers.extend(connections.into_iter().map(|c| (location.clone(), c)));
         }
 
-        // Single location changed - propagate to others
-        if local_changed {
-            self.sync_from_local(path, local, gdrive, smb)
-        } else if gdrive_changed {
-            self.sync_from_gdrive(path, local, gdrive, smb)
-        } else if smb_changed {
-            self.sync_from_smb(path, local, gdrive, smb)
-        } else {
-            SyncAction::NoAction
+        let total = remaining.len();
+        let pool = std::sync::Mutex::new(Pool {
+            remaining,
+            drive_folders,
+            creating_folders: false,
+            running: HashMap::new(),
+            limits,
+            outcomes: HashMap::new(),
+            progress: BootstrapProgress::new(total, bytes_total),
+        });
+        let started = Instant::now();
+        if let Some(on_progress) = on_progress {
+            on_progress(&pool.lock().unwrap().progress);
         }
-    }
 
-    fn has_changed(current: Option<&FileSnapshot>, last_known: Option<&String>) -> bool {
-        match (current, last_known) {
-            (Some(curr), Some(known)) => {
-                // File exists now and existed before - check if hash changed
-                curr.hash.as_ref() != Some(known)
-            }
-            (Some(_), None) => {
-                // File exists now but didn't before - it's new
-                true
-            }
-            (None, Some(_)) => {
-                // File doesn't exist now but did before - it was deleted
-                true
+        let workers = workers.into_iter().map(|(location, connection)| {
+            let pool = &pool;
+            async move {
+                loop {
+                    if self.cancellation.is_cancelled() {
+                        return Ok::<_, UvcadError>(());
+                    }
+                    // Claimed under the pool lock, so no two workers take the same transfer
+                    let mut waiting = false;
+                    let claimed = self.walk_queue(Some(&location), |transfer| {
+                        let mut pool = pool.lock().unwrap();
+                        let pool = &mut *pool;
+                        let Some(id) = transfer.id.filter(|id| pool.remaining.contains(id)) else {
+                            return ControlFlow::Continue(());
+                        };
+                        let running = pool.running.entry(location.clone()).or_default();
+                        if *running >= pool.limits[&location].limit() {
+                            waiting = true;
+                            return ControlFlow::Break(None);
+                        }
+                        let folder = Path::new(&transfer.file_path).parent().unwrap_or(Path::new("")).to_path_buf();
+                        // The root folder exists, unless it is a prefix folder
+                        let creates_folders = location == FileLocation::GoogleDrive
+                            && (transfer.source.is_some() || transfer.moved_from.is_some())
+                            && (folder != Path::new("") || self.prefixes.at(&location).is_some())
+                            && !pool.drive_folders.contains(&folder);
+                        if creates_folders && pool.creating_folders {
+                            waiting = true;
+                            return ControlFlow::Continue(());
+                        }
+                        pool.remaining.remove(&id);
+                        pool.creating_folders |= creates_folders;
+                        *running += 1;
+                        pool.limits.get_mut(&location).unwrap().started(Instant::now());
+                        ControlFlow::Break(Some((transfer, creates_folders)))
+                    }).await?;
+                    let Some((transfer, creates_folders)) = claimed.flatten() else {
+                        if waiting {
+                            tokio::time::sleep(WORKER_WAIT).await;
+                            continue;
+                        }
+                        // Anything left was cancelled or is being run by another worker
+                        return Ok(());
+                    };
+
+                    let id = transfer.id.unwrap_or_default();
+                    let path = Path::new(&transfer.file_path);
+                    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string();
+                    let processed = pool.lock().unwrap().progress.files_done;
+                    self.report(processed, total, &filename, SyncPhase::Transferring {
+                        from: transfer.source.clone(),
+                        to: transfer.destination.clone(),
+                    });
+                    self.db.call(move |conn| DbOperations::update_transfer_status(conn, id, &TransferStatus::InProgress, None))
+                        .await?;
+
+                    let outcome = self.run_transfer(&transfer, Some(connection)).await;
+                    let remaining = pool.lock().unwrap().remaining.clone();
+                    let outcome = self.finish_transfer(&transfer, outcome, &remaining).await?;
+
+                    let progress = {
+                        let mut pool = pool.lock().unwrap();
+                        *pool.running.entry(location.clone()).or_default() -= 1;
+                        if creates_folders {
+                            pool.creating_folders = false;
+                        }
+                        let size = transfer.size_bytes.unwrap_or(0).max(0) as u64;
+                        let limit = pool.limits.get_mut(&location).unwrap();
+                        match outcome {
+                            TransferOutcome::Succeeded if transfer.source.is_some() => limit.finished(size, Instant::now()),
+                            TransferOutcome::Failed => limit.failed(),
+                            _ => {}
+                        }
+                        if outcome == TransferOutcome::Succeeded && location == FileLocation::GoogleDrive {
+                            pool.drive_folders.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+                        }
+                        let worst = pool.outcomes.entry(transfer.file_path.clone()).or_insert(outcome);
+                        *worst = (*worst).max(outcome);
+                        pool.progress.record(&transfer.file_path, size, outcome == TransferOutcome::Succeeded, started.elapsed());
+                        pool.progress.clone()
+                    };
+                    if let Some(on_progress) = on_progress {
+                        on_progress(&progress);
+                    }
+                }
             }
-            (None, None) => {
-                // File doesn't exist now and didn't before - no change
-                false
+        });
+        futures::future::try_join_all(workers).await?;
+
+        let pool = pool.into_inner().unwrap();
+        for (location, limit) in &pool.limits {
+            if let Some(throughput) = limit.throughput() {
+                tracing::info!(
+                    "Throughput to {}: {}/s, ending at {} transfers at a time",
+                    location.as_str(), format_size(throughput), limit.limit()
+                );
             }
         }
+        Ok(pool.outcomes)
     }
 
-    /// Check if the destination is missing or has a different hash than the source.
-    fn needs_update(source: &FileSnapshot, dest: Option<&FileSnapshot>) -> bool {
-        match dest {
-            None => true, // destination missing
-            Some(d) => source.hash != d.hash, // destination exists but hash differs
-        }
+    /// Drop queued or in-flight transfers left over from an interrupted run; anything
+    /// still needed is planned again. Failed transfers stay until retried or cancelled.
+    async fn discard_stale_transfers(&self) -> Result<()> {
+        let profile_id = self.profile_id;
+        let stale: Vec<i64> = self.db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| t.status != TransferStatus::Failed && self.in_scope(Path::new(&t.file_path)))
+            .filter_map(|t| t.id)
+            .collect();
+
+        self.db.call(move |conn| {
+            for id in stale {
+                DbOperations::delete_transfer(conn, id)?;
+            }
+            Ok(())
+        }).await
     }
 
-    fn sync_from_local(&self, path: &Path, local: Option<&FileSnapshot>,
-                      gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>) -> SyncAction {
-        let mut operations = Vec::new();
+    /// Drop the transfers among `transfer_ids` that a cancelled run did not
+    /// start; the next sync plans them again.
+    async fn drop_queued_transfers(&self, transfer_ids: &[i64]) -> Result<()> {
+        let profile_id = self.profile_id;
+        let ids: HashSet<i64> = transfer_ids.iter().copied().collect();
+        let unstarted: Vec<i64> = self.db.call(move |conn| DbOperations::get_transfers(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| t.status == TransferStatus::Queued)
+            .filter_map(|t| t.id)
+            .filter(|id| ids.contains(id))
+            .collect();
+        if !unstarted.is_empty() {
+            tracing::info!("Dropping {} transfers not started before the sync was cancelled", unstarted.len());
+        }
 
-        if let Some(local_file) = local {
-            // Local file exists - sync to other locations (missing OR stale)
-            if self.gdrive_provider.is_some() && Self::needs_update(local_file, gdrive) {
-                operations.push(SyncOperation::Upload {
-                    from: FileLocation::Local,
-                    to: FileLocation::GoogleDrive,
-                    path: path.to_path_buf(),
-                });
-            }
-            if self.smb_provider.is_some() && Self::needs_update(local_file, smb) {
-                operations.push(SyncOperation::Upload {
-                    from: FileLocation::Local,
-                    to: FileLocation::Smb,
-                    path: path.to_path_buf(),
-                });
+        self.db.call(move |conn| {
+            for id in unstarted {
+                DbOperations::delete_transfer(conn, id)?;
             }
-        } else {
-            // Local file deleted - delete from other locations
-            if gdrive.is_some() {
-                operations.push(SyncOperation::Delete {
-                    location: FileLocation::GoogleDrive,
-                    path: path.to_path_buf(),
-                });
-            }
-            if smb.is_some() {
-                operations.push(SyncOperation::Delete {
-                    location: FileLocation::Smb,
-                    path: path.to_path_buf(),
-                });
-            }
-        }
+            Ok(())
+        }).await
+    }
 
-        if operations.is_empty() {
-            SyncAction::NoAction
-        } else {
-            SyncAction::Sync { operations }
+    /// What lets `operation` run in this sync: the approval settings, or the
+    /// approved plan it is part of.
+    fn authorization(&self, operation: &PlannedOperation) -> Authorization {
+        match &self.approval {
+            None => Authorization::NoApprovalRequired,
+            Some(policy) if policy.auto_approves(operation) => Authorization::AutoApproved,
+            Some(_) => Authorization::Approved { by: self.approved_by.clone().unwrap_or_default() },
         }
     }
 
-    fn sync_from_gdrive(&self, path: &Path, local: Option<&FileSnapshot>,
-                       gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>) -> SyncAction {
-        let mut operations = Vec::new();
-
-        if let Some(gdrive_file) = gdrive {
-            // Google Drive file exists - sync to other locations (missing OR stale)
-            if Self::needs_update(gdrive_file, local) {
-                operations.push(SyncOperation::Upload {
-                    from: FileLocation::GoogleDrive,
-                    to: FileLocation::Local,
-                    path: path.to_path_buf(),
-                });
-            }
-            if self.smb_provider.is_some() && Self::needs_update(gdrive_file, smb) {
-                operations.push(SyncOperation::Upload {
-                    from: FileLocation::GoogleDrive,
-                    to: FileLocation::Smb,
-                    path: path.to_path_buf(),
-                });
-            }
-        } else {
-            // Google Drive file deleted - delete from other locations
-            if local.is_some() {
-                operations.push(SyncOperation::Delete {
-                    location: FileLocation::Local,
-                    path: path.to_path_buf(),
-                });
-            }
-            if smb.is_some() {
-                operations.push(SyncOperation::Delete {
-                    location: FileLocation::Smb,
-                    path: path.to_path_buf(),
-                });
+    async fn enqueue_operation(
+        &self,
+        operation: &SyncOperation,
+        size_bytes: Option<i64>,
+        target: Option<&FileSnapshot>,
+        reason: Option<OperationReason>,
+    ) -> Result<i64> {
+        let (file_path, source, destination, moved_from) = match operation {
+            SyncOperation::Upload { from, to, path } => (path, Some(from.clone()), to.clone(), None),
+            SyncOperation::Delete { location, path } => (path, None, location.clone(), None),
+            SyncOperation::Move { location, from, path } => {
+                (path, None, location.clone(), Some(from.to_string_lossy().to_string()))
             }
-        }
+        };
+        let now = chrono::Utc::now();
 
-        if operations.is_empty() {
-            SyncAction::NoAction
-        } else {
-            SyncAction::Sync { operations }
-        }
+        let transfer = Transfer {
+            id: None,
+            profile_id: self.profile_id,
+            file_path: file_path.to_string_lossy().to_string(),
+            source,
+            destination,
+            // Moves are only planned onto free paths
+            expect_absent: target.is_none() || moved_from.is_some(),
+            moved_from,
+            status: TransferStatus::Queued,
+            priority: 0,
+            attempts: 0,
+            size_bytes,
+            expected_hash: target.and_then(|s| s.hash.clone()),
+            // Without a hash, the target is recognised by its size and time instead
+            expected_size: target.filter(|s| s.hash.is_none()).map(|s| s.size as i64),
+            expected_modified: target.filter(|s| s.hash.is_none()).map(|s| s.modified),
+            reason,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.db.call(move |conn| DbOperations::enqueue_transfer(conn, &transfer)).await
     }
 
-    fn sync_from_smb(&self, path: &Path, local: Option<&FileSnapshot>,
-                    gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>) -> SyncAction {
-        let mut operations = Vec::new();
-
-        if let Some(smb_file) = smb {
-            // Samba file exists - sync to other locations (missing OR stale)
-            if Self::needs_update(smb_file, local) {
-                operations.push(SyncOperation::Upload {
-                    from: FileLocation::Smb,
-                    to: FileLocation::Local,
-                    path: path.to_path_buf(),
-                });
-            }
-            if self.gdrive_provider.is_some() && Self::needs_update(smb_file, gdrive) {
-                operations.push(SyncOperation::Upload {
-                    from: FileLocation::Smb,
-                    to: FileLocation::GoogleDrive,
-                    path: path.to_path_buf(),
-                });
-            }
-        } else {
-            // Samba file deleted - delete from other locations
-            if local.is_some() {
-                operations.push(SyncOperation::Delete {
-                    location: FileLocation::Local,
-                    path: path.to_path_buf(),
-                });
+    /// Walk the queued transfers in queue order, only those to `destination` when
+    /// given, until `visit` breaks with a value. The queue is read a page at a time
+    /// from its head, so finding the next transfer reads only the ones before it.
+    async fn walk_queue<T>(
+        &self,
+        destination: Option<&FileLocation>,
+        mut visit: impl FnMut(Transfer) -> ControlFlow<T>,
+    ) -> Result<Option<T>> {
+        let profile_id = self.profile_id;
+        let mut after = None;
+        loop {
+            let destination = destination.cloned();
+            let page = self.db.call(move |conn| {
+                DbOperations::get_queued_transfers(conn, profile_id, destination.as_ref(), after, QUEUE_PAGE)
+            }).await?;
+            let full = page.len() == QUEUE_PAGE;
+            after = page.last().map(|t| (t.priority, t.id.unwrap_or_default()));
+            for transfer in page {
+                if let ControlFlow::Break(found) = visit(transfer) {
+                    return Ok(Some(found));
+                }
             }
-            if gdrive.is_some() {
-                operations.push(SyncOperation::Delete {
-                    location: FileLocation::GoogleDrive,
-                    path: path.to_path_buf(),
-                });
+            if !full {
+                return Ok(None);
             }
         }
+    }
 
-        if operations.is_empty() {
-            SyncAction::NoAction
-        } else {
-            SyncAction::Sync { operations }
+    /// Execute the given queued transfers in queue order, concurrently where there
+    /// are more connections to a destination; `drive_folders` are the folders known
+    /// to exist on Drive. The head of the queue is looked up before each transfer so
+    /// reordering and cancellation take effect while a sync runs. Returns, per file path, the
+    /// worst outcome of its executed transfers.
+    async fn run_transfers(&self, transfer_ids: &[i64], drive_folders: HashSet<PathBuf>) -> Result<HashMap<String, TransferOutcome>> {
+        if !self.connections.is_empty() {
+            return self.run_concurrent(transfer_ids, drive_folders, None).await;
         }
-    }
 
-    fn sync_to_missing(&self, path: &Path, local: Option<&FileSnapshot>,
-                      gdrive: Option<&FileSnapshot>, smb: Option<&FileSnapshot>) -> SyncAction {
-        let mut operations = Vec::new();
+        let mut remaining: HashSet<i64> = transfer_ids.iter().copied().collect();
+        let total = remaining.len();
+        let mut outcomes: HashMap<String, TransferOutcome> = HashMap::new();
+        let mut processed = 0;
+
+        while !remaining.is_empty() && !self.cancellation.is_cancelled() {
+            let next = self.walk_queue(None, |t| match t.id {
+                Some(id) if remaining.contains(&id) => ControlFlow::Break(t),
+                _ => ControlFlow::Continue(()),
+            }).await?;
+            // Anything left was cancelled
+            let Some(transfer) = next else {
+                break;
+            };
+            let id = transfer.id.unwrap_or_default();
+            remaining.remove(&id);
+
+            let filename = Path::new(&transfer.file_path).file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+            self.report(processed, total, &filename, SyncPhase::Transferring {
+                from: transfer.source.clone(),
+                to: transfer.destination.clone(),
+            });
+
+            self.db.call(move |conn| DbOperations::update_transfer_status(conn, id, &TransferStatus::InProgress, None))
+                .await?;
 
-        // If we have the file in at least one location, sync to missing locations
-        let source = local.or(gdrive).or(smb);
-        if source.is_none() {
-            return SyncAction::NoAction;
+            let outcome = self.run_transfer(&transfer, None).await;
+            if outcome.is_ok() && transfer.source.is_some() {
+                self.report(processed, total, &filename, SyncPhase::Verifying);
+            }
+            let outcome = self.finish_transfer(&transfer, outcome, &remaining).await?;
+            let worst = outcomes.entry(transfer.file_path.clone()).or_insert(outcome);
+            *worst = (*worst).max(outcome);
+            processed += 1;
         }
 
-        let source_location = source.unwrap().location.clone();
+        Ok(outcomes)
+    }
 
-        if local.is_none() && source_location != FileLocation::Local {
-            operations.push(SyncOperation::Upload {
-                from: source_location.clone(),
-                to: FileLocation::Local,
-                path: path.to_path_buf(),
-            });
+    /// Carry out a queued copy, move or deletion, writing through `connection`
+    /// when given. Waits after recent failures at the transfer's location, and
+    /// fails at once when the location is skipped for the rest of the run.
+    async fn run_transfer(
+        &self,
+        transfer: &Transfer,
+        connection: Option<&Arc<Mutex<dyn StorageProvider>>>,
+    ) -> Result<Option<FileTiming>> {
+        let (skipped, backoff) = {
+            let mut breaker = self.breaker.lock().unwrap();
+            let skipped = breaker.skip(transfer.source.as_ref(), &transfer.destination);
+            (skipped, breaker.backoff(transfer.source.as_ref(), &transfer.destination))
+        };
+        if let Some(location) = skipped {
+            return Err(UvcadError::LocationSkipped(location.as_str().to_string()));
+        }
+        if !backoff.is_zero() {
+            tracing::debug!("Waiting {:?} after failed transfers before {}", backoff, transfer.file_path);
+            tokio::time::sleep(backoff).await;
         }
 
-        if self.gdrive_provider.is_some() && gdrive.is_none() && source_location != FileLocation::GoogleDrive {
-            operations.push(SyncOperation::Upload {
-                from: source_location.clone(),
-                to: FileLocation::GoogleDrive,
-                path: path.to_path_buf(),
-            });
+        let path = Path::new(&transfer.file_path);
+        let span = tracing::debug_span!("transfer", path = %transfer.file_path, to = transfer.destination.as_str());
+        match (&transfer.source, &transfer.moved_from) {
+            (Some(source), _) => match connection {
+                Some(connection) => self.transfer_file_with(source, path, transfer, connection).instrument(span).await,
+                None => self.transfer_file(source, path, transfer).instrument(span).await,
+            }
+            .map(Some),
+            (None, Some(from)) => self.move_file(&transfer.destination, Path::new(from), path, transfer)
+                .instrument(span)
+                .await
+                .map(|_| None),
+            (None, None) => self.delete_file(&transfer.destination, path, transfer)
+                .instrument(span)
+                .await
+                .map(|_| None),
         }
+    }
 
-        if self.smb_provider.is_some() && smb.is_none() && source_location != FileLocation::Smb {
-            operations.push(SyncOperation::Upload {
-                from: source_location.clone(),
-                to: FileLocation::Smb,
-                path: path.to_path_buf(),
-            });
+    /// Record how a transfer that ran ended: commit it, drop it if the destination
+    /// changed since the scan, or mark it failed. A failure also cancels the rest of
+    /// its consistency group among `remaining`, and counts towards skipping its
+    /// location for the rest of the run.
+    async fn finish_transfer(
+        &self,
+        transfer: &Transfer,
+        outcome: Result<Option<FileTiming>>,
+        remaining: &HashSet<i64>,
+    ) -> Result<TransferOutcome> {
+        let id = transfer.id.unwrap_or_default();
+        let path = Path::new(&transfer.file_path);
+        if self.scan_cache {
+            // Whatever the outcome, the cached scan may no longer match the destination
+            scan_cache::invalidate(self.profile_id, Some(&transfer.destination), &self.stored_path(path, &transfer.destination));
         }
 
-        if operations.is_empty() {
-            SyncAction::NoAction
-        } else {
-            SyncAction::Sync { operations }
+        match outcome {
+            Ok(timing) => {
+                self.breaker.lock().unwrap().succeeded(transfer.source.as_ref(), &transfer.destination);
+                self.commit_transfer(transfer).await?;
+                if let Some(timing) = timing {
+                    let recorded = self.db.call(move |conn| DbOperations::insert_file_timing(conn, &timing)).await;
+                    if let Err(e) = recorded {
+                        tracing::warn!("Failed to record timing of {}: {}", transfer.file_path, e);
+                    }
+                }
+                Ok(TransferOutcome::Succeeded)
+            }
+            // Changed since the scan: drop the transfer so the next sync plans again from the new state
+            Err(UvcadError::ConflictDetected { .. }) => {
+                tracing::warn!("Not overwriting {} on {:?}: it changed since the scan", transfer.file_path, transfer.destination);
+                self.db.call(move |conn| DbOperations::delete_transfer(conn, id)).await?;
+                self.cancel_group_transfers(path, remaining).await?;
+                Ok(TransferOutcome::Conflict)
+            }
+            Err(e) => {
+                if matches!(e, UvcadError::LocationSkipped(_)) {
+                    tracing::debug!("Transfer of {} skipped: {}", transfer.file_path, e);
+                } else {
+                    tracing::error!("Transfer of {} failed: {}", transfer.file_path, e);
+                    let tripped = self.breaker.lock().unwrap().failed(transfer.source.as_ref(), &transfer.destination, &e.to_string());
+                    if let Some(location) = tripped {
+                        tracing::warn!("Skipping {} for the rest of the run: it keeps failing", location.as_str());
+                    }
+                }
+                let message = e.to_string();
+                self.db.call(move |conn| {
+                    DbOperations::update_transfer_status(conn, id, &TransferStatus::Failed, Some(&message))
+                }).await?;
+                self.cancel_group_transfers(path, remaining).await?;
+                Ok(TransferOutcome::Failed)
+            }
         }
     }
 
-    async fn execute_sync_operations(&self, _path: &Path, operations: Vec<SyncOperation>) -> Result<()> {
-        for operation in operations {
-            match operation {
-                SyncOperation::Upload { from, to, path: file_path } => {
-                    self.transfer_file(&from, &to, &file_path).await?;
+    /// Record a completed transfer: update the destination's last known state and
+    /// drop the queue entry in one transaction, so a later failure for the same
+    /// file cannot leave this destination's state unrecorded or stale.
+    async fn commit_transfer(&self, transfer: &Transfer) -> Result<()> {
+        let path = Path::new(&transfer.file_path);
+        let provider = self.get_provider(&transfer.destination)?;
+        let provider = provider.lock().await;
+        let uploaded = if transfer.source.is_some() || transfer.moved_from.is_some() {
+            match provider.get_metadata(&self.stored_path(path, &transfer.destination)).await {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    tracing::warn!("Could not read back {} on {:?}: {}", path.display(), transfer.destination, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let endpoint = provider.served_by();
+        drop(provider);
+
+        let transfer = transfer.clone();
+        let run_id = self.run_id.clone();
+        self.db.call(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let known = DbOperations::file_state_exists(&tx, transfer.profile_id, &transfer.file_path, &transfer.destination)?;
+            let event = |path: &str, kind, content_hash| FileEvent::new(
+                transfer.profile_id, path.to_string(), transfer.destination.clone(), kind, content_hash,
+            ).with_endpoint(endpoint.clone()).with_run_id(&run_id).with_reason(transfer.reason.clone());
+            match (&transfer.source, &transfer.moved_from, uploaded) {
+                (None, None, _) => {
+                    DbOperations::delete_file_state(
+                        &tx, transfer.profile_id, &transfer.file_path, transfer.destination.as_str()
+                    )?;
+                    DbOperations::insert_file_events(&tx, &[event(&transfer.file_path, FileEventKind::Deleted, None)])?;
                 }
-                SyncOperation::Delete { location, path: file_path } => {
-                    self.delete_file(&location, &file_path).await?;
+                (_, moved_from, Some(metadata)) => {
+                    if let Some(from) = moved_from {
+                        DbOperations::delete_file_state(&tx, transfer.profile_id, from, transfer.destination.as_str())?;
+                        DbOperations::insert_file_events(&tx, &[event(from, FileEventKind::Deleted, None)])?;
+                    }
+                    let kind = if known { FileEventKind::Modified } else { FileEventKind::Added };
+                    DbOperations::insert_file_events(&tx, &[event(&transfer.file_path, kind, metadata.hash.clone())])?;
+                    DbOperations::upsert_file_state(&tx, &FileState {
+                        id: None,
+                        profile_id: transfer.profile_id,
+                        file_path: transfer.file_path.clone(),
+                        location: transfer.destination.clone(),
+                        content_hash: metadata.hash,
+                        hash_fingerprint: metadata.fingerprint,
+                        size_bytes: Some(metadata.size as i64),
+                        modified_at: Some(metadata.modified),
+                        synced_at: Some(chrono::Utc::now()),
+                        status: SyncStatus::Synced,
+                        metadata: None,
+                    })?;
                 }
+                // Unknown destination state: leave it for the next scan to pick up
+                (_, _, None) => {}
             }
-        }
-        Ok(())
+            DbOperations::delete_transfer(&tx, transfer.id.unwrap_or_default())?;
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    /// Copy `path` for `transfer`. The destination is re-checked against the planning
+    /// snapshot first, so a file changed or created there during the run is not clobbered.
+    async fn transfer_file(&self, from: &FileLocation, path: &Path, transfer: &Transfer) -> Result<FileTiming> {
+        let dest_provider = self.get_provider(&transfer.destination)?;
+        self.transfer_file_with(from, path, transfer, dest_provider).await
     }
 
-    async fn transfer_file(&self, from: &FileLocation, to: &FileLocation, path: &Path) -> Result<()> {
+    /// `transfer_file` writing through `dest_provider`, e.g. one of several
+    /// connections to the destination used in parallel.
+    async fn transfer_file_with(
+        &self,
+        from: &FileLocation,
+        path: &Path,
+        transfer: &Transfer,
+        dest_provider: &Arc<Mutex<dyn StorageProvider>>,
+    ) -> Result<FileTiming> {
+        let to = &transfer.destination;
         tracing::info!("Transferring: {} from {:?} to {:?}", path.display(), from, to);
+        let started = Instant::now();
 
         // Get source provider
         let source_provider = self.get_provider(from)?;
 
-        // Get destination provider
-        let dest_provider = self.get_provider(to)?;
-
-        // Create temp file for transfer
+        // Create temp file for transfer; the transfer ID keeps same-named files apart
         let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!("uvcad_{}_{}",
+        let temp_file = temp_dir.join(format!("uvcad_{}_{}_{}",
+            transfer.id.unwrap_or_default(),
             path.file_name().unwrap_or_default().to_string_lossy(),
             chrono::Utc::now().timestamp()
         ));
 
         // Download from source to temp
+        let download_started = Instant::now();
         {
             let provider = source_provider.lock().await;
-            provider.download(path, &temp_file).await?;
+            provider.download(&self.stored_path(path, from), &temp_file)
+                .instrument(tracing::debug_span!("download"))
+                .await?;
         }
+        let download = download_started.elapsed();
+
+        let mut temp_files = vec![temp_file.clone()];
+        let result = async {
+            let mut payload = temp_file.clone();
+            if let (FileLocation::GoogleDrive, Some(keys)) = (from, &self.drive_keys) {
+                let plain = temp_file.with_extension("plain");
+                temp_files.push(plain.clone());
+                keys.decrypt_file(&payload, &plain)?;
+                payload = plain;
+            }
+            if self.policies.is_compressed_at(path, from) {
+                let raw = temp_file.with_extension("raw");
+                temp_files.push(raw.clone());
+                extension_policy::decompress_file(&payload, &raw)?;
+                payload = raw;
+            }
 
-        // Verify file integrity
-        let temp_hash = file_hasher::compute_file_hash(&temp_file)?;
-        tracing::debug!("Temp file hash: {}", temp_hash);
+            // Verify file integrity
+            let hash_started = Instant::now();
+            let temp_hash = tracing::debug_span!("hash").in_scope(|| file_hasher::compute_file_hash(&payload))?;
+            tracing::debug!("Temp file hash: {}", temp_hash);
+            let hash = hash_started.elapsed();
+            if *from != FileLocation::Local {
+                self.verify_download(from, path, &temp_hash).await;
+            }
 
-        // Upload from temp to destination
-        {
+            let compressed_at_dest = self.policies.is_compressed_at(path, to);
+            if compressed_at_dest {
+                let compressed = temp_file.with_extension(extension_policy::COMPRESSED_SUFFIX);
+                temp_files.push(compressed.clone());
+                extension_policy::compress_file(&payload, &compressed)?;
+                payload = compressed;
+            }
+            if let (FileLocation::GoogleDrive, Some(keys)) = (to, &self.drive_keys) {
+                let encrypted = temp_file.with_extension("enc");
+                temp_files.push(encrypted.clone());
+                keys.encrypt_file(&payload, &encrypted)?;
+                payload = encrypted;
+            }
+            if *to == FileLocation::Local {
+                self.scan_download(from, path, &payload).await?;
+            }
+
+            // Upload from temp to destination
+            let upload_started = Instant::now();
             let provider = dest_provider.lock().await;
-            provider.upload(&temp_file, path).await?;
-        }
+            let dest = self.stored_path(path, to);
+            let content_key = (to.clone(), temp_hash.clone(), compressed_at_dest);
+            let copied = match self.uploaded_copy(&content_key, &dest, transfer) {
+                Some(existing) => copy_instead_of_upload(&*provider, &existing, &dest).await,
+                None => false,
+            };
+            if !copied {
+                async {
+                    match &transfer.expected_hash {
+                        Some(hash) => provider.upload_if_unchanged(&payload, &dest, hash).await,
+                        None => {
+                            if transfer.expect_absent || transfer.expected_size.is_some() {
+                                let changed = match provider.get_metadata(&dest).await? {
+                                    Some(current) => transfer.expect_absent || !as_planned(transfer, &current),
+                                    None => !transfer.expect_absent,
+                                };
+                                if changed {
+                                    return Err(UvcadError::ConflictDetected { path: dest.to_string_lossy().to_string() });
+                                }
+                            }
+                            provider.upload(&payload, &dest).await
+                        }
+                    }
+                }
+                .instrument(tracing::debug_span!("upload"))
+                .await?;
+            }
+            self.uploaded.lock().unwrap().insert(content_key, dest.clone());
+            if *to != FileLocation::Local {
+                if let Some(signer) = &self.signer {
+                    // The file is already in place; a missing signature only means no alert later
+                    if let Err(e) = upload_signature(&*provider, &dest, &signer.sign(path, &temp_hash)).await {
+                        tracing::warn!("Failed to store the signature of {} on {:?}: {}", path.display(), to, e);
+                    }
+                }
+            }
+            Ok::<_, UvcadError>((hash, upload_started.elapsed()))
+        }.await;
 
-        // Clean up temp file
-        let _ = tokio::fs::remove_file(&temp_file).await;
+        // Clean up temp files
+        for temp in temp_files {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+        let (hash, upload) = result?;
 
         tracing::info!("Transfer complete: {} from {:?} to {:?}", path.display(), from, to);
-        Ok(())
+        let millis = |duration: Duration| duration.as_millis() as i64;
+        Ok(FileTiming {
+            id: None,
+            profile_id: self.profile_id,
+            run_id: self.run_id.clone(),
+            file_path: transfer.file_path.clone(),
+            source: from.clone(),
+            destination: to.clone(),
+            size_bytes: transfer.size_bytes,
+            download_ms: millis(download),
+            hash_ms: millis(hash),
+            upload_ms: millis(upload),
+            total_ms: millis(started.elapsed()),
+            recorded_at: chrono::Utc::now(),
+        })
     }
 
-    async fn delete_file(&self, location: &FileLocation, path: &Path) -> Result<()> {
+    /// Delete `path` at `location`, unless it changed since the deletion was
+    /// planned (see `as_planned`). With `with_trash` the file is moved to the
+    /// trash instead.
+    async fn delete_file(&self, location: &FileLocation, path: &Path, transfer: &Transfer) -> Result<()> {
         tracing::info!("Deleting: {} from {:?}", path.display(), location);
 
         let provider = self.get_provider(location)?;
         let provider_lock = provider.lock().await;
-        provider_lock.delete(path).await?;
+        let stored = self.stored_path(path, location);
+        let mut current = None;
+        if transfer.expected_hash.is_some() || transfer.expected_size.is_some() {
+            match provider_lock.get_metadata(&stored).await? {
+                None => {
+                    tracing::info!("{} is already gone from {:?}", path.display(), location);
+                    return Ok(());
+                }
+                Some(found) if !as_planned(transfer, &found) => {
+                    return Err(UvcadError::ConflictDetected { path: stored.to_string_lossy().to_string() });
+                }
+                found => current = found,
+            }
+        }
+        match self.trash {
+            Some(_) => self.trash_file(&*provider_lock, location, path, &stored, current).await?,
+            None => provider_lock.delete(&stored).await?,
+        }
+        self.uploaded.lock().unwrap().retain(|_, path| path != &stored);
+        if self.signer.is_some() && *location != FileLocation::Local {
+            let signature = signing::signature_path(&stored);
+            if provider_lock.get_metadata(&signature).await?.is_some() {
+                provider_lock.delete(&signature).await?;
+            }
+        }
 
         tracing::info!("Deletion complete: {} from {:?}", path.display(), location);
         Ok(())
     }
 
+    /// Move the copy of `path`, kept at `stored`, into the location's trash and
+    /// record where it went, for `restore_deleted`.
+    async fn trash_file(
+        &self,
+        provider: &dyn StorageProvider,
+        location: &FileLocation,
+        path: &Path,
+        stored: &Path,
+        current: Option<FileMetadata>,
+    ) -> Result<()> {
+        let current = match current {
+            Some(current) => Some(current),
+            None => provider.get_metadata(stored).await?,
+        };
+        let deleted_at = chrono::Utc::now();
+        let handle = provider.trash(stored, &trash::trash_path(stored, deleted_at)).await?;
+        let trashed = TrashedFile {
+            id: None,
+            profile_id: self.profile_id,
+            file_path: path.to_string_lossy().to_string(),
+            location: location.clone(),
+            handle,
+            content_hash: current.as_ref().and_then(|m| m.hash.clone()),
+            size_bytes: current.map(|m| m.size as i64),
+            deleted_at,
+            run_id: Some(self.run_id.clone()),
+        };
+        self.db.call(move |conn| DbOperations::insert_trashed_file(conn, &trashed)).await?;
+        Ok(())
+    }
+
+    /// Bring back the copies of `file_path` that its latest deletion moved to the
+    /// trash, at each location where nothing took the path since. The next sync
+    /// copies the file on to the locations it was not restored at.
+    pub async fn restore_deleted(&self, file_path: &str) -> Result<RestoredFile> {
+        let profile_id = self.profile_id;
+        let trashed: Vec<TrashedFile> = self.db.call(move |conn| DbOperations::get_trashed_files(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| t.file_path == file_path)
+            .collect();
+        // Most recent first: the copies the same run trashed as the latest one
+        let Some(latest) = trashed.first() else {
+            return Err(UvcadError::FileNotFound { path: file_path.to_string() });
+        };
+        let latest_run = latest.run_id.clone();
+        let latest_at = latest.deleted_at;
+
+        let mut copies: Vec<&TrashedFile> = trashed.iter()
+            .filter(|t| t.run_id == latest_run && (latest_run.is_some() || t.deleted_at == latest_at))
+            .collect();
+        copies.sort_by_key(|t| location_rank(&t.location));
+
+        let path = Path::new(file_path);
+        let mut restored = RestoredFile { file_path: file_path.to_string(), ..Default::default() };
+        for copy in copies {
+            let stored = self.stored_path(path, &copy.location);
+            let outcome = async {
+                let provider = self.get_provider(&copy.location)?.lock().await;
+                if provider.get_metadata(&stored).await?.is_some() {
+                    return Err(UvcadError::ConflictDetected { path: stored.to_string_lossy().to_string() });
+                }
+                provider.restore_trashed(&copy.handle, &stored).await
+            }.await;
+            if self.scan_cache {
+                scan_cache::invalidate(self.profile_id, Some(&copy.location), &stored);
+            }
+
+            match outcome {
+                Ok(()) => {
+                    let id = copy.id.unwrap_or_default();
+                    self.db.call(move |conn| DbOperations::delete_trashed_file(conn, id)).await?;
+                    tracing::info!("Restored {} on {:?}", file_path, copy.location);
+                    restored.restored.push(copy.location.clone());
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to restore {} on {:?}: {}", file_path, copy.location, e);
+                    restored.failed.push((copy.location.clone(), e.to_string()));
+                }
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Delete trashed copies for good once they are older than the retention,
+    /// the default one when soft deletion was turned off since. A copy that
+    /// can't be deleted is tried again on the next run.
+    async fn purge_expired_trash(&self) -> Result<()> {
+        let policy = self.trash.unwrap_or_default();
+        let profile_id = self.profile_id;
+        let now = chrono::Utc::now();
+        let expired: Vec<TrashedFile> = self.db.call(move |conn| DbOperations::get_trashed_files(conn, profile_id))
+            .await?
+            .into_iter()
+            .filter(|t| policy.expired(t.deleted_at, now))
+            .collect();
+
+        for copy in expired {
+            let Ok(provider) = self.get_provider(&copy.location) else {
+                continue;
+            };
+            let purged = provider.lock().await.purge_trashed(&copy.handle).await;
+            match purged {
+                Ok(()) => {
+                    let id = copy.id.unwrap_or_default();
+                    self.db.call(move |conn| DbOperations::delete_trashed_file(conn, id)).await?;
+                    tracing::info!("Deleted the trashed copy of {} on {:?} for good", copy.file_path, copy.location);
+                }
+                Err(e) => tracing::warn!("Failed to delete the trashed copy of {} on {:?}: {}", copy.file_path, copy.location, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the copy of `from` at `location` to `path`, unless it changed since
+    /// the move was planned (see `as_planned`) or something was created at
+    /// `path` since.
+    async fn move_file(&self, location: &FileLocation, from: &Path, path: &Path, transfer: &Transfer) -> Result<()> {
+        tracing::info!("Moving: {} to {} on {:?}", from.display(), path.display(), location);
+
+        let provider = self.get_provider(location)?;
+        let provider_lock = provider.lock().await;
+        let stored_from = self.stored_path(from, location);
+        let stored = self.stored_path(path, location);
+        match provider_lock.get_metadata(&stored_from).await? {
+            Some(current) if as_planned(transfer, &current) => {}
+            _ => return Err(UvcadError::ConflictDetected { path: stored_from.to_string_lossy().to_string() }),
+        }
+        if provider_lock.get_metadata(&stored).await?.is_some() {
+            return Err(UvcadError::ConflictDetected { path: stored.to_string_lossy().to_string() });
+        }
+        provider_lock.rename(&stored_from, &stored).await?;
+        self.uploaded.lock().unwrap().retain(|_, path| path != &stored_from);
+
+        tracing::info!("Move complete: {} to {} on {:?}", from.display(), path.display(), location);
+        Ok(())
+    }
+
+    /// A file uploaded to the transfer's destination earlier in this run with the
+    /// same content, to copy from. Only for new files: overwrites keep the
+    /// destination's conditional upload.
+    fn uploaded_copy(&self, key: &(FileLocation, String, bool), dest: &Path, transfer: &Transfer) -> Option<PathBuf> {
+        let mut uploaded = self.uploaded.lock().unwrap();
+        // Whatever was uploaded to `dest` before is about to be replaced
+        uploaded.retain(|_, path| path != dest);
+        if transfer.expected_hash.is_some() || transfer.expected_size.is_some() {
+            return None;
+        }
+        uploaded.get(key).cloned()
+    }
+
+    /// Check a file downloaded from `from` against the signature stored next to
+    /// it and raise an alert when it was changed outside UVCAD. The transfer goes
+    /// ahead either way; the alert is for someone to look into.
+    async fn verify_download(&self, from: &FileLocation, path: &Path, sha256: &str) {
+        let Some(signer) = &self.signer else {
+            return;
+        };
+        let stored = signing::signature_path(&self.stored_path(path, from));
+        let signature = match self.get_provider(from) {
+            Ok(provider) => download_signature(&*provider.lock().await, &stored).await,
+            Err(e) => Err(e),
+        };
+        let verification = match signature {
+            Ok(Some(signature)) => signer.verify(path, sha256, &signature),
+            Ok(None) => signing::Verification::Unsigned,
+            Err(e) => {
+                tracing::warn!("Could not read the signature of {} on {:?}: {}", path.display(), from, e);
+                return;
+            }
+        };
+        if !verification.is_tampered() {
+            tracing::debug!("{} from {:?}: {}", path.display(), from, verification.describe());
+            return;
+        }
+
+        tracing::error!("Tampering detected on {} from {:?}: {}", path.display(), from, verification.describe());
+        self.tampered.lock().unwrap().push(path.to_string_lossy().to_string());
+        notifications::notify(
+            Notification::new(
+                NotificationKind::TamperDetected,
+                Severity::Error,
+                i18n::tr("notification.tamper_detected", &[
+                    ("path", path.display().to_string()),
+                    ("location", from.as_str().to_string()),
+                    ("reason", verification.describe().to_string()),
+                ]),
+            )
+            .with_run_id(&self.run_id),
+        ).await;
+    }
+
+    /// Run the scanner on a download bound for the local folder. A flagged file
+    /// is moved to quarantine and the transfer fails, so it is not placed among
+    /// the production files.
+    async fn scan_download(&self, from: &FileLocation, path: &Path, payload: &Path) -> Result<()> {
+        let Some(scanner) = &self.scanner else {
+            return Ok(());
+        };
+        let (exit_code, output) = match scanner.scan(payload).instrument(tracing::debug_span!("scan")).await? {
+            ScanVerdict::Clean => return Ok(()),
+            ScanVerdict::Flagged { exit_code, output } => (exit_code, output),
+        };
+
+        let record = QuarantineRecord {
+            path: path.to_string_lossy().replace('\\', "/"),
+            source: from.clone(),
+            exit_code,
+            scanner_output: output,
+            quarantined_at: chrono::Utc::now(),
+        };
+        let quarantined = scanner.quarantine(payload, &record)?;
+        tracing::error!("Malware scanner flagged {} from {:?}; quarantined at {}", path.display(), from, quarantined.display());
+        notifications::notify(
+            Notification::new(
+                NotificationKind::FileQuarantined,
+                Severity::Error,
+                i18n::tr("notification.file_quarantined", &[
+                    ("path", path.display().to_string()),
+                    ("location", from.as_str().to_string()),
+                ]),
+            )
+            .with_run_id(&self.run_id)
+            .with_details(serde_json::json!({
+                "quarantined_at": quarantined.to_string_lossy(),
+                "exit_code": record.exit_code,
+                "scanner_output": record.scanner_output,
+            })),
+        ).await;
+        Err(UvcadError::Quarantined { path: record.path })
+    }
+
     fn get_provider(&self, location: &FileLocation) -> Result<&Arc<Mutex<dyn StorageProvider>>> {
         match location {
             FileLocation::Local => Ok(&self.local_provider),
@@ -565,23 +2370,21 @@ impl SyncEngine {
         }
     }
 
-    fn check_deletion_safety(&self, planned_actions: &[(PathBuf, SyncAction)], total_files: usize) -> Result<()> {
+    fn check_deletion_safety(&self, to_queue: &[(PathBuf, Vec<SyncOperation>)], total_files: usize) -> Result<()> {
         let mut deletion_count = 0;
         let mut local_deletions = Vec::new();
         let mut gdrive_deletions = Vec::new();
         let mut smb_deletions = Vec::new();
 
         // Count all planned deletions
-        for (_path, action) in planned_actions {
-            if let SyncAction::Sync { operations } = action {
-                for operation in operations {
-                    if let SyncOperation::Delete { location, path } = operation {
-                        deletion_count += 1;
-                        match location {
-                            FileLocation::Local => local_deletions.push(path.clone()),
-                            FileLocation::GoogleDrive => gdrive_deletions.push(path.clone()),
-                            FileLocation::Smb => smb_deletions.push(path.clone()),
-                        }
+        for (_path, operations) in to_queue {
+            for operation in operations {
+                if let SyncOperation::Delete { location, path } = operation {
+                    deletion_count += 1;
+                    match location {
+                        FileLocation::Local => local_deletions.push(path.clone()),
+                        FileLocation::GoogleDrive => gdrive_deletions.push(path.clone()),
+                        FileLocation::Smb => smb_deletions.push(path.clone()),
                     }
                 }
             }
@@ -592,10 +2395,7 @@ impl SyncEngine {
         }
 
         if total_files == 0 {
-            return Err(UvcadError::SyncFailed(
-                "SAFETY CHECK FAILED: Deletions planned but no files found. \
-                Please verify your sync folders are accessible and try again.".to_string()
-            ));
+            return Err(UvcadError::SyncFailed(i18n::tr("safety.no_files_found", &[])));
         }
 
         let deletion_percentage = (deletion_count as f32 / total_files as f32) * 100.0;
@@ -606,29 +2406,29 @@ impl SyncEngine {
         );
 
         // Check against thresholds
-        if deletion_count > MAX_DELETION_COUNT {
-            let error_msg = format!(
-                "SAFETY CHECK FAILED: Sync would delete {} files (exceeds limit of {}). \
-                This may indicate accidental data loss. Deletions by location: \
-                Local: {}, Google Drive: {}, Samba: {}. \
-                Please verify your sync folders are accessible and try again.",
-                deletion_count, MAX_DELETION_COUNT,
-                local_deletions.len(), gdrive_deletions.len(), smb_deletions.len()
-            );
+        let limits = self.deletion_limits;
+        if deletion_count > limits.max_count {
+            let error_msg = i18n::tr("safety.too_many_deletions", &[
+                ("count", deletion_count.to_string()),
+                ("limit", limits.max_count.to_string()),
+                ("local", local_deletions.len().to_string()),
+                ("gdrive", gdrive_deletions.len().to_string()),
+                ("smb", smb_deletions.len().to_string()),
+            ]);
             tracing::error!("{}", error_msg);
             return Err(UvcadError::SyncFailed(error_msg));
         }
 
         let deletion_percentage_decimal = deletion_count as f32 / total_files as f32;
-        if deletion_percentage_decimal > MAX_DELETION_PERCENTAGE {
-            let error_msg = format!(
-                "SAFETY CHECK FAILED: Sync would delete {:.1}% of files ({} files, exceeds {:.0}% threshold). \
-                This may indicate a drive is unmounted or accidentally emptied. Deletions by location: \
-                Local: {}, Google Drive: {}, Samba: {}. \
-                Please verify your sync folders are accessible and try again.",
-                deletion_percentage, deletion_count, MAX_DELETION_PERCENTAGE * 100.0,
-                local_deletions.len(), gdrive_deletions.len(), smb_deletions.len()
-            );
+        if deletion_percentage_decimal > limits.max_fraction {
+            let error_msg = i18n::tr("safety.deletion_percentage", &[
+                ("percentage", format!("{:.1}", deletion_percentage)),
+                ("count", deletion_count.to_string()),
+                ("threshold", format!("{:.0}", limits.max_fraction * 100.0)),
+                ("local", local_deletions.len().to_string()),
+                ("gdrive", gdrive_deletions.len().to_string()),
+                ("smb", smb_deletions.len().to_string()),
+            ]);
             tracing::error!("{}", error_msg);
             return Err(UvcadError::SyncFailed(error_msg));
         }
@@ -637,149 +2437,352 @@ impl SyncEngine {
         Ok(())
     }
 
-    async fn get_last_known_state(&self) -> Result<HashMap<PathBuf, LastKnownState>> {
-        let db_guard = self.db.lock()
-            .map_err(|e| UvcadError::SyncFailed(format!("Failed to lock database: {}", e)))?;
-        let conn = db_guard.get_connection();
-
-        let file_states = DbOperations::get_file_states(conn, self.profile_id)?;
-
-        let mut state_map: HashMap<PathBuf, LastKnownState> = HashMap::new();
+    /// Fail before any transfer when the uploads planned to a location with a
+    /// storage quota exceed its free space. The error lists the uploads by size
+    /// and the largest ones to exclude to make the rest fit.
+    async fn check_storage_quota(
+        &self,
+        to_queue: &[(PathBuf, Vec<SyncOperation>)],
+        snapshots: &Snapshots,
+    ) -> Result<()> {
+        // Bytes each upload adds at its destination; replacing a file only adds the difference
+        let mut uploads: HashMap<FileLocation, Vec<(PathBuf, u64)>> = HashMap::new();
+        for (path, operations) in to_queue {
+            for operation in operations {
+                if let SyncOperation::Upload { from, to, .. } = operation {
+                    let size = snapshots.get(from, path)?.map_or(0, |s| s.size);
+                    let replaced = snapshots.get(to, path)?.map_or(0, |s| s.size);
+                    uploads.entry(to.clone()).or_default().push((path.clone(), size.saturating_sub(replaced)));
+                }
+            }
+        }
 
-        for state in file_states {
-            let path = PathBuf::from(&state.file_path);
-            let entry = state_map.entry(path).or_insert_with(|| LastKnownState {
-                local: None,
-                gdrive: None,
-                smb: None,
-            });
+        for (location, mut files) in uploads {
+            let provider = self.get_provider(&location)?;
+            let Some(available) = provider.lock().await.available_space().await? else {
+                continue;
+            };
+            let needed: u64 = files.iter().map(|(_, size)| size).sum();
+            tracing::info!("Storage check for {}: {} bytes planned, {} bytes free", location.as_str(), needed, available);
+            if needed <= available {
+                continue;
+            }
 
-            match state.location {
-                FileLocation::Local => entry.local = state.content_hash,
-                FileLocation::GoogleDrive => entry.gdrive = state.content_hash,
-                FileLocation::Smb => entry.smb = state.content_hash,
+            files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            let describe = |(path, size): &(PathBuf, u64)| format!("{} ({})", path.display(), format_size(*size));
+            let mut breakdown: Vec<String> = files.iter().take(MAX_QUOTA_BREAKDOWN).map(describe).collect();
+            if files.len() > MAX_QUOTA_BREAKDOWN {
+                breakdown.push(format!("+{}", files.len() - MAX_QUOTA_BREAKDOWN));
             }
+            let mut excess = needed - available;
+            let exclude: Vec<String> = files.iter()
+                .take_while(|(_, size)| {
+                    let take = excess > 0;
+                    excess = excess.saturating_sub(*size);
+                    take
+                })
+                .map(describe)
+                .collect();
+
+            let error_msg = i18n::tr("safety.quota_exceeded", &[
+                ("location", i18n::tr(&format!("location.{}", location.as_str()), &[])),
+                ("needed", format_size(needed)),
+                ("available", format_size(available)),
+                ("files", breakdown.join(", ")),
+                ("exclude", exclude.join(", ")),
+            ]);
+            tracing::error!("{}", error_msg);
+            return Err(UvcadError::SyncFailed(error_msg));
+        }
+        Ok(())
+    }
+
+    /// Copy the recorded file states into `snapshots`, a page at a time.
+    async fn load_last_known_state(&self, snapshots: &Snapshots) -> Result<()> {
+        let profile_id = self.profile_id;
+        let mut after_id = 0;
+        let mut loaded = 0;
+        loop {
+            let page = self.db.call(move |conn| {
+                DbOperations::get_file_states_page(conn, profile_id, after_id, planner::PAGE_SIZE)
+            }).await?;
+            let Some(last_id) = page.last().and_then(|state| state.id) else {
+                break;
+            };
+            after_id = last_id;
+            loaded += page.len();
+            snapshots.record_last_known(&page)?;
         }
 
-        tracing::debug!("Loaded {} file states from database", state_map.len());
-        Ok(state_map)
+        tracing::debug!("Loaded {} file states from database", loaded);
+        Ok(())
     }
 
+    /// Record the scanned files as the last known state, a page at a time.
+    /// Each page is committed on its own; files of a page that didn't make it
+    /// keep their old state and are compared against it again next time.
     async fn update_last_known_state(
         &self,
-        local_files: &HashMap<PathBuf, FileSnapshot>,
-        gdrive_files: &HashMap<PathBuf, FileSnapshot>,
-        smb_files: &HashMap<PathBuf, FileSnapshot>,
+        snapshots: &Snapshots,
+        should_record: impl Fn(&Path, &FileLocation) -> bool,
     ) -> Result<()> {
-        let db_guard = self.db.lock()
-            .map_err(|e| UvcadError::SyncFailed(format!("Failed to lock database: {}", e)))?;
-        let conn = db_guard.get_connection();
-
         let now = chrono::Utc::now();
-
-        // Get existing file states to detect deletions
-        let existing_states = DbOperations::get_file_states(conn, self.profile_id)?;
-
-        // Remove DB records for files that no longer exist at their location
-        for state in &existing_states {
-            let path = PathBuf::from(&state.file_path);
-            let still_exists = match state.location {
-                FileLocation::Local => local_files.contains_key(&path),
-                FileLocation::GoogleDrive => gdrive_files.contains_key(&path),
-                FileLocation::Smb => smb_files.contains_key(&path),
-            };
-            if !still_exists {
-                DbOperations::delete_file_state(
-                    conn, self.profile_id, &state.file_path, state.location.as_str()
-                )?;
-            }
-        }
-
-        // Save local file states
-        for (path, snapshot) in local_files {
-            let file_state = FileState {
-                id: None,
-                profile_id: self.profile_id,
-                file_path: path.to_string_lossy().to_string(),
-                location: FileLocation::Local,
-                content_hash: snapshot.hash.clone(),
-                size_bytes: Some(snapshot.size as i64),
-                modified_at: Some(snapshot.modified),
-                synced_at: Some(now),
-                status: SyncStatus::Synced,
-                metadata: None,
-            };
-            DbOperations::upsert_file_state(conn, &file_state)?;
-        }
-
-        // Save Google Drive file states
-        for (path, snapshot) in gdrive_files {
-            let file_state = FileState {
-                id: None,
-                profile_id: self.profile_id,
-                file_path: path.to_string_lossy().to_string(),
-                location: FileLocation::GoogleDrive,
-                content_hash: snapshot.hash.clone(),
-                size_bytes: Some(snapshot.size as i64),
-                modified_at: Some(snapshot.modified),
-                synced_at: Some(now),
-                status: SyncStatus::Synced,
-                metadata: None,
+        let profile_id = self.profile_id;
+        let (mut saved_count, mut removed_count) = (0, 0);
+
+        // Remove DB records for files that no longer exist at their location.
+        // Files the patterns leave out were not scanned; their records stay.
+        let mut after_id = 0;
+        loop {
+            let page = self.db.call(move |conn| {
+                DbOperations::get_file_states_page(conn, profile_id, after_id, planner::PAGE_SIZE)
+            }).await?;
+            let Some(last_id) = page.last().and_then(|state| state.id) else {
+                break;
             };
-            DbOperations::upsert_file_state(conn, &file_state)?;
-        }
-
-        // Save Samba file states
-        for (path, snapshot) in smb_files {
-            let file_state = FileState {
-                id: None,
-                profile_id: self.profile_id,
-                file_path: path.to_string_lossy().to_string(),
-                location: FileLocation::Smb,
-                content_hash: snapshot.hash.clone(),
-                size_bytes: Some(snapshot.size as i64),
-                modified_at: Some(snapshot.modified),
-                synced_at: Some(now),
-                status: SyncStatus::Synced,
-                metadata: None,
-            };
-            DbOperations::upsert_file_state(conn, &file_state)?;
+            after_id = last_id;
+
+            let mut removed = Vec::new();
+            let mut events = Vec::new();
+            for state in &page {
+                let path = PathBuf::from(&state.file_path);
+                if !self.in_scope(&path) || !self.selection.selects(&path) || !should_record(&path, &state.location) {
+                    continue;
+                }
+                // Offloaded files are expected to be missing locally
+                let still_exists = snapshots.contains(&state.location, &path)?
+                    || (state.location == FileLocation::Local && state.status == SyncStatus::Offloaded);
+                if !still_exists {
+                    removed.push((state.file_path.clone(), state.location.clone()));
+                    events.push(FileEvent::new(
+                        profile_id, state.file_path.clone(), state.location.clone(), FileEventKind::Deleted, None,
+                    ).with_run_id(&self.run_id));
+                }
+            }
+            if removed.is_empty() {
+                continue;
+            }
+            removed_count += removed.len();
+            self.db.call(move |conn| {
+                let tx = conn.unchecked_transaction()?;
+                DbOperations::delete_file_states(&tx, profile_id, &removed)?;
+                DbOperations::insert_file_events(&tx, &events)?;
+                tx.commit()?;
+                Ok(())
+            }).await?;
         }
 
-        let total_saved = local_files.len() + gdrive_files.len() + smb_files.len();
-        tracing::debug!("Saved {} file states to database", total_saved);
+        // Save states that are new or differ from what is stored
+        for location in [FileLocation::Local, FileLocation::GoogleDrive, FileLocation::Smb] {
+            let mut after: Option<PathBuf> = None;
+            loop {
+                let page = snapshots.page_at(&location, after.as_deref())?;
+                let Some((last, _)) = page.last() else {
+                    break;
+                };
+                after = Some(last.clone());
+
+                let files: Vec<(String, FileSnapshot)> = page.into_iter()
+                    .filter(|(path, _)| self.in_scope(path) && should_record(path, &location))
+                    .map(|(path, snapshot)| (path.to_string_lossy().to_string(), snapshot))
+                    .collect();
+                let paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+                let at = location.clone();
+                let existing = self.db.call(move |conn| {
+                    paths.iter()
+                        .map(|path| DbOperations::get_file_state(conn, profile_id, path, &at))
+                        .collect::<Result<Vec<_>>>()
+                }).await?;
+
+                let mut saved = Vec::new();
+                let mut events = Vec::new();
+                for ((file_path, snapshot), previous) in files.into_iter().zip(existing) {
+                    let unchanged = previous.as_ref().is_some_and(|state| {
+                        state.status == SyncStatus::Synced
+                            && state.content_hash == snapshot.hash
+                            && state.size_bytes == Some(snapshot.size as i64)
+                            && state.modified_at == Some(snapshot.modified)
+                            && state.hash_fingerprint == snapshot.fingerprint
+                    });
+                    if unchanged {
+                        continue;
+                    }
+
+                    // Metadata-only updates (timestamps, status) are not content changes
+                    let kind = match &previous {
+                        None => Some(FileEventKind::Added),
+                        Some(state) if state.content_hash != snapshot.hash => Some(FileEventKind::Modified),
+                        Some(_) => None,
+                    };
+                    if let Some(kind) = kind {
+                        events.push(
+                            FileEvent::new(profile_id, file_path.clone(), location.clone(), kind, snapshot.hash.clone())
+                                .with_run_id(&self.run_id),
+                        );
+                    }
+
+                    saved.push(FileState {
+                        id: None,
+                        profile_id: self.profile_id,
+                        file_path,
+                        location: location.clone(),
+                        content_hash: snapshot.hash,
+                        hash_fingerprint: snapshot.fingerprint,
+                        size_bytes: Some(snapshot.size as i64),
+                        modified_at: Some(snapshot.modified),
+                        synced_at: Some(now),
+                        status: SyncStatus::Synced,
+                        metadata: None,
+                    });
+                }
+                if saved.is_empty() {
+                    continue;
+                }
+                saved_count += saved.len();
+                self.db.call(move |conn| {
+                    let tx = conn.unchecked_transaction()?;
+                    DbOperations::upsert_file_states(&tx, &saved)?;
+                    DbOperations::insert_file_events(&tx, &events)?;
+                    tx.commit()?;
+                    Ok(())
+                }).await?;
+            }
+        }
 
+        tracing::debug!("Saved {} and removed {} file states", saved_count, removed_count);
         Ok(())
     }
 }
 
-#[derive(Debug)]
-enum SyncAction {
-    NoAction,
-    Sync {
-        operations: Vec<SyncOperation>,
-    },
-    Conflict(ConflictInfo),
+/// How the transfers of one file ended, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TransferOutcome {
+    Succeeded,
+    Failed,
+    /// The destination changed after the scan and was left alone.
+    Conflict,
 }
 
-#[derive(Debug)]
-enum SyncOperation {
-    Upload {
-        from: FileLocation,
-        to: FileLocation,
-        path: PathBuf,
-    },
-    Delete {
-        location: FileLocation,
-        path: PathBuf,
-    },
+/// What the plan phase decided to change.
+struct RunPlan {
+    /// The operations of each file, in queue order.
+    to_queue: Vec<(PathBuf, Vec<SyncOperation>)>,
+    /// The change each file in `to_queue` is synced for.
+    reasons: HashMap<PathBuf, ChangeReason>,
+    /// The old path of each moved file.
+    moved_from: HashMap<PathBuf, PathBuf>,
+    /// Files held back this run, e.g. while they are being saved.
+    deferred: HashSet<PathBuf>,
+    total_files: usize,
+}
+
+/// What the execute phase leaves for the commit phase.
+struct Execution {
+    transfers: usize,
+    /// Files whose state is not recorded: failed, cancelled, deferred or duplicated.
+    unfinished: HashSet<PathBuf>,
+    /// Destinations whose state is committed by the transfer itself
+    transfer_targets: HashSet<(PathBuf, FileLocation)>,
+}
+
+/// Every folder holding a file on Google Drive, at any depth.
+fn folders_of(snapshots: &Snapshots) -> Result<HashSet<PathBuf>> {
+    let mut folders = HashSet::new();
+    let mut after: Option<PathBuf> = None;
+    loop {
+        let page = snapshots.page_at(&FileLocation::GoogleDrive, after.as_deref())?;
+        let Some((last, _)) = page.last() else {
+            break;
+        };
+        after = Some(last.clone());
+        for (path, _) in &page {
+            for folder in path.ancestors().skip(1) {
+                if !folders.insert(folder.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(folders)
+}
+
+/// Bytes in the largest fitting unit, e.g. `1.5 GB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Whether `current` is still the file a transfer was planned against: the
+/// same content hash, or for a file without one the same size and modification
+/// time. A transfer planned without either takes any file.
+fn as_planned(transfer: &Transfer, current: &FileMetadata) -> bool {
+    match (&transfer.expected_hash, transfer.expected_size) {
+        (Some(hash), _) => current.hash.as_deref() == Some(hash.as_str()),
+        (None, Some(size)) => size == current.size as i64 && transfer.expected_modified == Some(current.modified),
+        (None, None) => true,
+    }
+}
+
+/// Copy `existing` to `dest` on the provider's side instead of uploading the
+/// same content again. False when the provider did not copy, so the caller uploads.
+async fn copy_instead_of_upload(provider: &dyn StorageProvider, existing: &Path, dest: &Path) -> bool {
+    match provider.copy(existing, dest).instrument(tracing::debug_span!("copy")).await {
+        Ok(copied) => {
+            if copied {
+                tracing::info!("Copied {} from {} instead of uploading it again", dest.display(), existing.display());
+            }
+            copied
+        }
+        Err(e) => {
+            tracing::warn!("Could not copy {} to {}, uploading instead: {}", existing.display(), dest.display(), e);
+            false
+        }
+    }
 }
 
-#[derive(Debug)]
-struct LastKnownState {
-    local: Option<String>,    // Last known hash for local
-    gdrive: Option<String>,   // Last known hash for gdrive
-    smb: Option<String>,      // Last known hash for smb
+fn signature_temp_file() -> PathBuf {
+    std::env::temp_dir().join(format!("uvcad_{}.{}", uuid::Uuid::new_v4().simple(), signing::SIGNATURE_EXTENSION))
+}
+
+async fn upload_signature(provider: &dyn StorageProvider, dest: &Path, signature: &Signature) -> Result<()> {
+    let temp = signature_temp_file();
+    tokio::fs::write(&temp, serde_json::to_vec_pretty(signature)?).await?;
+    let uploaded = provider.upload(&temp, &signing::signature_path(dest)).await;
+    let _ = tokio::fs::remove_file(&temp).await;
+    uploaded
+}
+
+/// The signature stored at `stored`, or None when there is none.
+async fn download_signature(provider: &dyn StorageProvider, stored: &Path) -> Result<Option<Signature>> {
+    if provider.get_metadata(stored).await?.is_none() {
+        return Ok(None);
+    }
+    let temp = signature_temp_file();
+    let signature = async {
+        provider.download(stored, &temp).await?;
+        Ok::<_, UvcadError>(serde_json::from_slice(&tokio::fs::read(&temp).await?)?)
+    }.await;
+    let _ = tokio::fs::remove_file(&temp).await;
+    signature.map(Some)
+}
+
+/// What `SyncEngine::restore_deleted` brought back.
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct RestoredFile {
+    pub file_path: String,
+    pub restored: Vec<FileLocation>,
+    /// Locations whose trashed copy could not be restored, with why.
+    pub failed: Vec<(FileLocation, String)>,
 }
 
 #[derive(Debug, Default, Clone, serde::Serialize)]
@@ -788,4 +2791,29 @@ pub struct SyncResult {
     pub files_failed: usize,
     pub files_conflict: usize,
     pub conflicts: Vec<ConflictInfo>,
+    /// Files held back by the naming convention.
+    pub blocked: Vec<String>,
+    /// Files whose deletion or overwrite a legal hold held back.
+    pub held: Vec<String>,
+    /// Non-fatal issues, e.g. a lower revision synced next to a higher one.
+    pub warnings: Vec<String>,
+    /// What this run set out to do, in execution order.
+    pub plan: Vec<PlanStep>,
+    /// The plan needs approval; nothing was transferred.
+    pub awaiting_approval: bool,
+    /// Paths held back because a location lists several files under them.
+    pub duplicates: Vec<String>,
+    /// The sync run this result belongs to.
+    pub run_id: String,
+    /// Downloaded files whose content or signature did not match; see `with_signing`.
+    pub tampered: Vec<String>,
+    /// Files whose transfers all completed in this run.
+    pub completed: Vec<String>,
+    /// The run stopped early; see `with_cancellation`.
+    pub cancelled: bool,
+    /// Files whose changes wait, in whole or in part, for a paused location.
+    pub paused: Vec<String>,
+    /// Locations skipped for the rest of the run after failing repeatedly; see
+    /// `with_circuit_breaker`.
+    pub degraded: Vec<FileLocation>,
 }
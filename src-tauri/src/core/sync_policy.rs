@@ -0,0 +1,170 @@
+use crate::core::cad_temp;
+use crate::core::consistency::ConsistencyGroups;
+use crate::core::extension_policy::ExtensionPolicies;
+use crate::core::naming::NamingConvention;
+use crate::core::path_prefix::PathPrefixes;
+use crate::core::plan_approval::ApprovalPolicy;
+use crate::core::selective_sync::SelectiveSync;
+use crate::core::sync_engine::DeletionLimits;
+use crate::core::trash::TrashPolicy;
+use crate::models::sync_profile::SyncProfile;
+use crate::utils::error::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Every setting of a profile that decides what a sync plans and carries out,
+/// kept in its configuration form so the policy can be exported, edited and
+/// imported as a whole. `compile` parses it for a run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncPolicy {
+    /// Per-extension handling (see `core::extension_policy`).
+    pub extension_policies: Option<String>,
+    pub sync_cad_temp_files: bool,
+    pub naming_rules: Option<String>,
+    pub naming_blocked_locations: Option<String>,
+    pub consistency_groups: Option<String>,
+    pub path_prefixes: Option<String>,
+    /// Which files are synced (see `core::selective_sync`).
+    pub include_patterns: Option<String>,
+    pub exclude_patterns: Option<String>,
+    pub max_deletion_count: Option<i64>,
+    pub max_deletion_percentage: Option<i64>,
+    /// Deletions move files to the trash (see `core::trash`).
+    pub soft_delete: bool,
+    pub trash_retention_days: Option<i64>,
+    pub save_settle_secs: Option<i64>,
+    pub quiet_period_secs: Option<i64>,
+    pub require_plan_approval: bool,
+    pub plan_approvers: Option<String>,
+    pub auto_approved_operations: Option<String>,
+}
+
+/// A parsed `SyncPolicy`, ready to hand to the engine.
+#[derive(Debug, Clone)]
+pub struct CompiledPolicy {
+    pub extensions: ExtensionPolicies,
+    pub skip_cad_temp: bool,
+    pub naming: NamingConvention,
+    pub groups: ConsistencyGroups,
+    pub prefixes: PathPrefixes,
+    pub selection: SelectiveSync,
+    pub deletion_limits: DeletionLimits,
+    /// None when deletions are permanent.
+    pub trash: Option<TrashPolicy>,
+    pub save_settle: Duration,
+    pub quiet_period: Duration,
+    /// None when plans run without approval.
+    pub approval: Option<ApprovalPolicy>,
+}
+
+impl SyncPolicy {
+    pub fn from_profile(profile: &SyncProfile) -> Self {
+        Self {
+            extension_policies: profile.extension_policies.clone(),
+            sync_cad_temp_files: profile.sync_cad_temp_files,
+            naming_rules: profile.naming_rules.clone(),
+            naming_blocked_locations: profile.naming_blocked_locations.clone(),
+            consistency_groups: profile.consistency_groups.clone(),
+            path_prefixes: profile.path_prefixes.clone(),
+            include_patterns: profile.include_patterns.clone(),
+            exclude_patterns: profile.exclude_patterns.clone(),
+            max_deletion_count: profile.max_deletion_count,
+            max_deletion_percentage: profile.max_deletion_percentage,
+            soft_delete: profile.soft_delete,
+            trash_retention_days: profile.trash_retention_days,
+            save_settle_secs: profile.save_settle_secs,
+            quiet_period_secs: profile.quiet_period_secs,
+            require_plan_approval: profile.require_plan_approval,
+            plan_approvers: profile.plan_approvers.clone(),
+            auto_approved_operations: profile.auto_approved_operations.clone(),
+        }
+    }
+
+    pub fn apply_to(self, profile: &mut SyncProfile) {
+        profile.extension_policies = self.extension_policies;
+        profile.sync_cad_temp_files = self.sync_cad_temp_files;
+        profile.naming_rules = self.naming_rules;
+        profile.naming_blocked_locations = self.naming_blocked_locations;
+        profile.consistency_groups = self.consistency_groups;
+        profile.path_prefixes = self.path_prefixes;
+        profile.include_patterns = self.include_patterns;
+        profile.exclude_patterns = self.exclude_patterns;
+        profile.max_deletion_count = self.max_deletion_count;
+        profile.max_deletion_percentage = self.max_deletion_percentage;
+        profile.soft_delete = self.soft_delete;
+        profile.trash_retention_days = self.trash_retention_days;
+        profile.save_settle_secs = self.save_settle_secs;
+        profile.quiet_period_secs = self.quiet_period_secs;
+        profile.require_plan_approval = self.require_plan_approval;
+        profile.plan_approvers = self.plan_approvers;
+        profile.auto_approved_operations = self.auto_approved_operations;
+    }
+
+    /// Parse every part, failing on the first invalid one.
+    pub fn compile(&self) -> Result<CompiledPolicy> {
+        let approval = if self.require_plan_approval {
+            Some(ApprovalPolicy::from_config(self.plan_approvers.as_deref(), self.auto_approved_operations.as_deref())?)
+        } else {
+            None
+        };
+        let trash = if self.soft_delete {
+            Some(TrashPolicy::from_config(self.trash_retention_days)?)
+        } else {
+            None
+        };
+        Ok(CompiledPolicy {
+            extensions: ExtensionPolicies::from_config(self.extension_policies.as_deref())?,
+            skip_cad_temp: !self.sync_cad_temp_files,
+            naming: NamingConvention::from_config(self.naming_rules.as_deref(), self.naming_blocked_locations.as_deref())?,
+            groups: ConsistencyGroups::from_config(self.consistency_groups.as_deref())?,
+            prefixes: PathPrefixes::from_config(self.path_prefixes.as_deref())?,
+            selection: SelectiveSync::from_config(self.include_patterns.as_deref(), self.exclude_patterns.as_deref())?,
+            deletion_limits: DeletionLimits::from_config(self.max_deletion_count, self.max_deletion_percentage)?,
+            trash,
+            save_settle: cad_temp::settle_delay(self.save_settle_secs)?,
+            quiet_period: cad_temp::quiet_period(self.quiet_period_secs)?,
+            approval,
+        })
+    }
+
+    /// Whether applying this policy changes the deletion safety check, which is
+    /// reserved for admins.
+    pub fn changes_deletion_limits(&self, current: &SyncPolicy) -> bool {
+        self.max_deletion_count != current.max_deletion_count
+            || self.max_deletion_percentage != current.max_deletion_percentage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_through_json_and_profiles() {
+        let mut profile = SyncProfile::new("Policy".to_string(), String::new());
+        profile.extension_policies = Some(".bak = never-sync".to_string());
+        profile.max_deletion_count = Some(10);
+        profile.quiet_period_secs = Some(60);
+
+        let policy = SyncPolicy::from_profile(&profile);
+        let exported = serde_json::to_string(&policy).unwrap();
+        let imported: SyncPolicy = serde_json::from_str(&exported).unwrap();
+        assert_eq!(imported, policy);
+
+        // Settings left out of an edited policy take their defaults
+        let edited: SyncPolicy = serde_json::from_str(r#"{"max_deletion_count": 20}"#).unwrap();
+        assert!(edited.changes_deletion_limits(&policy));
+        let mut other = SyncProfile::new("Other".to_string(), String::new());
+        edited.apply_to(&mut other);
+        assert_eq!((other.max_deletion_count, other.quiet_period_secs), (Some(20), None));
+
+        let compiled = policy.compile().unwrap();
+        assert_eq!(compiled.deletion_limits.max_count, 10);
+        assert_eq!(compiled.quiet_period, Duration::from_secs(60));
+        assert!(compiled.skip_cad_temp && compiled.approval.is_none() && compiled.trash.is_none());
+
+        let invalid = SyncPolicy { max_deletion_percentage: Some(150), ..policy };
+        assert!(invalid.compile().is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::core::legal_hold::LegalHolds;
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, SyncStatus};
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::glob;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Key under which archive details are stored in file_states.metadata.
+const METADATA_KEY: &str = "tier";
+
+/// Offload local files matching `pattern` (glob on the relative path) that have
+/// not been modified for `min_age_days`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TieringRule {
+    pub pattern: String,
+    pub min_age_days: i64,
+}
+
+impl TieringRule {
+    /// Parse rules written one per line as `pattern = days`, e.g. `*.sldprt = 365`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse_rules(text: &str) -> Result<Vec<TieringRule>> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let invalid = || UvcadError::InvalidConfig(format!("Invalid tiering rule: {}", line));
+                let (pattern, days) = line.rsplit_once('=').ok_or_else(invalid)?;
+                let min_age_days = days.trim().parse::<i64>().ok().filter(|d| *d > 0).ok_or_else(invalid)?;
+                Ok(TieringRule {
+                    pattern: pattern.trim().to_string(),
+                    min_age_days,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A local file a tiering rule would offload.
+#[derive(Debug, Clone, Serialize)]
+pub struct TieringCandidate {
+    pub path: String,
+    pub size: i64,
+    pub modified_at: DateTime<Utc>,
+    pub content_hash: Option<String>,
+    /// Pattern of the rule that selected the file.
+    pub rule: String,
+}
+
+/// Where an offloaded file's content was archived.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRecord {
+    pub archive_folder_id: String,
+    pub archived_at: DateTime<Utc>,
+    pub content_hash: Option<String>,
+    pub size: Option<i64>,
+}
+
+/// List local files that the rules would offload at `now`. The first matching
+/// rule decides; files without a known modification time or under legal hold
+/// are never selected.
+pub fn plan_tiering(conn: &Connection, profile_id: i64, rules: &[TieringRule], now: DateTime<Utc>) -> Result<Vec<TieringCandidate>> {
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let holds = LegalHolds::load(conn, profile_id)?;
+    let mut candidates: Vec<TieringCandidate> = DbOperations::get_file_states(conn, profile_id)?
+        .into_iter()
+        .filter(|state| state.location == FileLocation::Local && state.status == SyncStatus::Synced)
+        .filter(|state| holds.covering(Path::new(&state.file_path)).is_none())
+        .filter_map(|state| {
+            let modified_at = state.modified_at?;
+            let rule = rules.iter().find(|rule| glob::matches(&rule.pattern, &state.file_path))?;
+            if now - modified_at < Duration::days(rule.min_age_days) {
+                return None;
+            }
+            Some(TieringCandidate {
+                path: state.file_path,
+                size: state.size_bytes.unwrap_or(0),
+                modified_at,
+                content_hash: state.content_hash,
+                rule: rule.pattern.clone(),
+            })
+        })
+        .collect();
+
+    candidates.sort_by_key(|candidate| candidate.modified_at);
+    Ok(candidates)
+}
+
+/// Record that a local file was archived and removed, so syncs treat it as
+/// online-only instead of a local deletion.
+pub fn mark_offloaded(conn: &Connection, profile_id: i64, path: &str, archive_folder_id: &str) -> Result<()> {
+    let mut state = DbOperations::get_file_states(conn, profile_id)?
+        .into_iter()
+        .find(|s| s.location == FileLocation::Local && s.file_path == path)
+        .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string() })?;
+
+    let record = ArchiveRecord {
+        archive_folder_id: archive_folder_id.to_string(),
+        archived_at: Utc::now(),
+        content_hash: state.content_hash.clone(),
+        size: state.size_bytes,
+    };
+
+    let mut metadata = state.metadata.as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    metadata[METADATA_KEY] = serde_json::to_value(&record)?;
+
+    state.metadata = Some(metadata.to_string());
+    state.status = SyncStatus::Offloaded;
+    DbOperations::upsert_file_state(conn, &state)?;
+    Ok(())
+}
+
+/// Record that an offloaded file was downloaded back from the archive.
+pub fn mark_restored(conn: &Connection, profile_id: i64, path: &str) -> Result<()> {
+    let mut state = DbOperations::get_file_states(conn, profile_id)?
+        .into_iter()
+        .find(|s| s.location == FileLocation::Local && s.file_path == path)
+        .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string() })?;
+
+    // upsert_file_state keeps existing metadata when given None, so write the
+    // object back without the archive record instead
+    let mut metadata = state.metadata.as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if let Some(object) = metadata.as_object_mut() {
+        object.remove(METADATA_KEY);
+    }
+
+    state.metadata = Some(metadata.to_string());
+    state.status = SyncStatus::Synced;
+    DbOperations::upsert_file_state(conn, &state)?;
+    Ok(())
+}
+
+pub fn read_archive_record(metadata: Option<&str>) -> Option<ArchiveRecord> {
+    let value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    serde_json::from_value(value.get(METADATA_KEY)?.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = TieringRule::parse_rules("# old parts\n*.sldprt = 365\narchive/* = 30\n").unwrap();
+        assert_eq!(rules, vec![
+            TieringRule { pattern: "*.sldprt".to_string(), min_age_days: 365 },
+            TieringRule { pattern: "archive/*".to_string(), min_age_days: 30 },
+        ]);
+
+        assert!(TieringRule::parse_rules("*.step").is_err());
+        assert!(TieringRule::parse_rules("*.step = soon").is_err());
+        assert!(TieringRule::parse_rules("*.step = 0").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::utils::error::{Result, UvcadError};
+use chrono::{DateTime, Duration, Utc};
+use std::path::{Component, Path, PathBuf};
+
+/// Folder at the root of the local folder and the share that deleted files are
+/// moved into. Google Drive uses its own trash instead.
+pub const TRASH_FOLDER: &str = ".uvcad-trash";
+
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+const MAX_RETENTION_DAYS: i64 = 3650;
+
+/// Deletions a sync propagates move the file into the trash of each location
+/// instead of deleting it, so it can be restored with `restore_deleted`. Trashed
+/// copies are deleted for good once they are older than `retention`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrashPolicy {
+    pub retention: Duration,
+}
+
+impl Default for TrashPolicy {
+    fn default() -> Self {
+        Self { retention: Duration::days(DEFAULT_RETENTION_DAYS) }
+    }
+}
+
+impl TrashPolicy {
+    /// Parse the profile setting; unset keeps the default.
+    pub fn from_config(retention_days: Option<i64>) -> Result<Self> {
+        match retention_days {
+            None => Ok(Self::default()),
+            Some(days) if (1..=MAX_RETENTION_DAYS).contains(&days) => Ok(Self { retention: Duration::days(days) }),
+            Some(days) => Err(UvcadError::InvalidConfig(format!(
+                "Invalid trash retention: {} days (1 to {})", days, MAX_RETENTION_DAYS
+            ))),
+        }
+    }
+
+    /// Whether a copy trashed at `deleted_at` is due to be deleted for good.
+    pub fn expired(&self, deleted_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - deleted_at >= self.retention
+    }
+}
+
+/// Whether `path`, as stored at a location, is in the trash folder.
+pub fn is_trash(path: &Path) -> bool {
+    path.components().next() == Some(Component::Normal(TRASH_FOLDER.as_ref()))
+}
+
+/// Where the file stored at `path` goes when it is trashed at `deleted_at`:
+/// `parts/frame.dwg` -> `.uvcad-trash/20261016T093000.123Z/parts/frame.dwg`
+pub fn trash_path(path: &Path, deleted_at: DateTime<Utc>) -> PathBuf {
+    Path::new(TRASH_FOLDER)
+        .join(deleted_at.format("%Y%m%dT%H%M%S%.3fZ").to_string())
+        .join(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_paths_and_retention() {
+        let deleted_at: DateTime<Utc> = "2026-10-16T09:30:00.123Z".parse().unwrap();
+        let trashed = trash_path(Path::new("parts/frame.dwg"), deleted_at);
+        assert_eq!(trashed, Path::new(".uvcad-trash/20261016T093000.123Z/parts/frame.dwg"));
+        assert!(is_trash(&trashed));
+        assert!(!is_trash(Path::new("parts/.uvcad-trash/frame.dwg")));
+
+        let policy = TrashPolicy::from_config(Some(7)).unwrap();
+        assert!(!policy.expired(deleted_at, deleted_at + Duration::days(6)));
+        assert!(policy.expired(deleted_at, deleted_at + Duration::days(7)));
+        assert_eq!(TrashPolicy::from_config(None).unwrap().retention, Duration::days(DEFAULT_RETENTION_DAYS));
+        assert!(TrashPolicy::from_config(Some(0)).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::utils::error::{Result, UvcadError};
+
+/// Release feeds in the Tauri updater format, one per channel.
+#[cfg_attr(not(feature = "updater"), allow(dead_code))]
+const FEED_BASE_URL: &str = "https://raw.githubusercontent.com/netarcx/UVCAD/main/updates";
+
+/// App setting holding the channel.
+pub const CHANNEL_SETTING: &str = "update_channel";
+
+/// Which releases a user is offered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Pre-releases as well as stable releases.
+    Beta,
+}
+
+#[cfg_attr(not(feature = "updater"), allow(dead_code))]
+impl UpdateChannel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+
+    /// Parse the app setting; unset means stable.
+    pub fn from_config(config: Option<&str>) -> Result<Self> {
+        match config.map(|c| c.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("stable") => Ok(UpdateChannel::Stable),
+            Some("beta") => Ok(UpdateChannel::Beta),
+            Some(other) => Err(UvcadError::InvalidConfig(format!("Unknown update channel: {}", other))),
+        }
+    }
+
+    pub fn feed_url(&self) -> String {
+        format!("{}/{}.json", FEED_BASE_URL, self.as_str())
+    }
+}
+
+/// Whether this build can replace itself. Linux packages are updated by the
+/// package manager; only AppImages update in place.
+#[cfg_attr(not(feature = "updater"), allow(dead_code))]
+pub fn can_self_update() -> bool {
+    if cfg!(target_os = "linux") {
+        std::env::var_os("APPIMAGE").is_some()
+    } else {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_from_config() {
+        assert_eq!(UpdateChannel::from_config(None).unwrap(), UpdateChannel::Stable);
+        assert_eq!(UpdateChannel::from_config(Some(" Beta ")).unwrap(), UpdateChannel::Beta);
+        assert!(UpdateChannel::from_config(Some("nightly")).is_err());
+        assert!(UpdateChannel::Beta.feed_url().ends_with("/beta.json"));
+    }
+}
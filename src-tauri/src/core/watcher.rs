@@ -0,0 +1,132 @@
+use crate::core::cad_temp;
+use crate::utils::error::{Result, UvcadError};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Extensions of the CAD files whose changes start a sync.
+const WATCHED_EXTENSIONS: &[&str] = &["dwg", "sldprt", "sldasm"];
+
+/// Shortest wait after the last change, even when the profile turns the settle delay off.
+pub const MIN_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Longest a change waits while writes keep coming, so a folder that is never
+/// quiet still gets synced.
+pub const MAX_DEBOUNCE: Duration = Duration::from_secs(120);
+
+/// Whether a change to `path` should start a sync.
+pub fn is_watched(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    WATCHED_EXTENSIONS.contains(&extension.as_str()) && !cad_temp::is_cad_temp(path)
+}
+
+/// Collects changed paths until the folder has been quiet for a while. A CAD
+/// save writes the same file several times and renames temporaries into place;
+/// the burst becomes one sync.
+#[derive(Debug)]
+pub struct Debouncer {
+    quiet: Duration,
+    pending: BTreeSet<PathBuf>,
+    first_change: Option<Instant>,
+    last_change: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(quiet: Duration) -> Self {
+        Self { quiet: quiet.max(MIN_DEBOUNCE), pending: BTreeSet::new(), first_change: None, last_change: None }
+    }
+
+    pub fn record(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path);
+        self.first_change.get_or_insert(now);
+        self.last_change = Some(now);
+    }
+
+    /// The changed paths once nothing changed for the quiet period, or once
+    /// they waited `MAX_DEBOUNCE`.
+    pub fn take_ready(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+        let (Some(first), Some(last)) = (self.first_change, self.last_change) else {
+            return None;
+        };
+        if now.duration_since(last) < self.quiet && now.duration_since(first) < MAX_DEBOUNCE {
+            return None;
+        }
+        self.first_change = None;
+        self.last_change = None;
+        Some(std::mem::take(&mut self.pending).into_iter().collect())
+    }
+}
+
+/// Watches the local sync folder and sends the path of every changed CAD file,
+/// relative to the folder. Watching stops when it is dropped.
+pub struct FolderWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl FolderWatcher {
+    pub fn start(root: &Path, changes: UnboundedSender<PathBuf>) -> Result<Self> {
+        let base = root.to_path_buf();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("File watcher error: {}", e);
+                    return;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+                return;
+            }
+            for path in event.paths {
+                let Ok(relative) = path.strip_prefix(&base) else {
+                    continue;
+                };
+                if is_watched(relative) {
+                    let _ = changes.send(relative.to_path_buf());
+                }
+            }
+        })
+        .map_err(|e| UvcadError::ProviderError(format!("Failed to create file watcher: {}", e)))?;
+
+        watcher.watch(root, RecursiveMode::Recursive)
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to watch {}: {}", root.display(), e)))?;
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bursts_of_saves_become_one_batch() {
+        assert!(is_watched(Path::new("site/plan.DWG")));
+        assert!(is_watched(Path::new("parts/bracket.sldprt")));
+        assert!(!is_watched(Path::new("parts/~$bracket.sldprt")));
+        assert!(!is_watched(Path::new("notes.txt")));
+
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut debouncer = Debouncer::new(Duration::from_secs(5));
+
+        debouncer.record(PathBuf::from("a.dwg"), at(0));
+        debouncer.record(PathBuf::from("a.dwg"), at(3));
+        debouncer.record(PathBuf::from("b.sldprt"), at(4));
+        assert_eq!(debouncer.take_ready(at(8)), None);
+        assert_eq!(debouncer.take_ready(at(9)), Some(vec![PathBuf::from("a.dwg"), PathBuf::from("b.sldprt")]));
+        assert_eq!(debouncer.take_ready(at(20)), None);
+
+        // Writes that never stop are flushed after the longest wait
+        for secs in (30..200).step_by(2) {
+            debouncer.record(PathBuf::from("busy.dwg"), at(secs));
+            if let Some(batch) = debouncer.take_ready(at(secs)) {
+                assert_eq!((batch, secs), (vec![PathBuf::from("busy.dwg")], 150));
+                return;
+            }
+        }
+        panic!("busy folder was never flushed");
+    }
+}
@@ -0,0 +1,475 @@
+use crate::models::file_state::{FileState, SyncStatus};
+use crate::utils::error::{Result, UvcadError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Request bodies (PROPFIND queries) are read and ignored up to this size.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const ALLOW: &str = "OPTIONS, GET, HEAD, PROPFIND";
+
+/// A file in the merged view of the synced locations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DavFile {
+    /// Relative path with `/` separators.
+    pub path: String,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+/// A file's content on local disk, ready to be sent.
+pub struct DavContent {
+    pub path: PathBuf,
+    /// Staged for this request only and removed once sent.
+    pub temporary: bool,
+}
+
+/// Where the server gets its listing and file contents from.
+#[async_trait]
+pub trait DavSource: Send + Sync {
+    async fn files(&self) -> Result<Vec<DavFile>>;
+
+    async fn open(&self, path: &str) -> Result<DavContent>;
+}
+
+/// One file per path across all locations, with the size and time of its newest
+/// copy. Deleted and offloaded copies don't count.
+pub fn merged_files(states: &[FileState]) -> Vec<DavFile> {
+    let mut files: BTreeMap<String, DavFile> = BTreeMap::new();
+    for state in states.iter().filter(|s| !matches!(s.status, SyncStatus::Deleted | SyncStatus::Offloaded)) {
+        let file = DavFile {
+            path: state.file_path.replace('\\', "/"),
+            size: state.size_bytes.unwrap_or(0).max(0) as u64,
+            modified: state.modified_at.or(state.synced_at).unwrap_or_default(),
+        };
+        match files.get(&file.path) {
+            Some(existing) if existing.modified >= file.modified => {}
+            _ => {
+                files.insert(file.path.clone(), file);
+            }
+        }
+    }
+    files.into_values().collect()
+}
+
+/// Serve `source` read-only over WebDAV until the task is aborted. Enough of
+/// class 1 for Windows Explorer, macOS Finder and davfs2 to mount it: PROPFIND
+/// at depth 0 and 1, GET and HEAD. Writing methods are refused.
+pub async fn serve(listener: TcpListener, source: Arc<dyn DavSource>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let source = source.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, source.as_ref()).await {
+                        tracing::debug!("WebDAV connection ended: {}", e);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("Failed to accept WebDAV connection: {}", e),
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    /// Decoded relative path without leading or trailing slashes.
+    path: String,
+    depth: Option<String>,
+    host: Option<String>,
+    keep_alive: bool,
+}
+
+async fn handle_connection(stream: TcpStream, source: &dyn DavSource) -> Result<()> {
+    let port = stream.local_addr()?.port();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(request) = read_request(&mut reader).await? {
+        // A web page could otherwise read the files through a name of its own
+        // that resolves to this computer (DNS rebinding)
+        if request.host.as_deref().is_some_and(|host| is_local_host(host, port)) {
+            respond(&request, source, &mut writer).await?;
+        } else {
+            write_error(&mut writer, "403 Forbidden", "Only requests to localhost are served", request.keep_alive).await?;
+        }
+        writer.flush().await?;
+        if !request.keep_alive {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn read_request<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), version) = (parts.next(), parts.next(), parts.next().unwrap_or("HTTP/1.0")) else {
+        return Err(UvcadError::ProviderError(format!("Malformed request line: {}", line.trim())));
+    };
+
+    let mut request = Request {
+        method: method.to_uppercase(),
+        path: decode_path(target),
+        depth: None,
+        host: None,
+        keep_alive: version == "HTTP/1.1",
+    };
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_lowercase().as_str() {
+            "content-length" => content_length = value.parse().unwrap_or(0),
+            "depth" => request.depth = Some(value.to_lowercase()),
+            "host" => request.host = Some(value.to_lowercase()),
+            "connection" if value.eq_ignore_ascii_case("close") => request.keep_alive = false,
+            "connection" if value.eq_ignore_ascii_case("keep-alive") => request.keep_alive = true,
+            _ => {}
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(UvcadError::ProviderError(format!("Request body too large: {} bytes", content_length)));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(request))
+}
+
+async fn respond<W: AsyncWrite + Unpin>(request: &Request, source: &dyn DavSource, writer: &mut W) -> Result<()> {
+    let keep_alive = request.keep_alive;
+    match request.method.as_str() {
+        "OPTIONS" => {
+            let headers = [("DAV", "1".to_string()), ("Allow", ALLOW.to_string()), ("MS-Author-Via", "DAV".to_string())];
+            write_head(writer, "200 OK", &headers, 0, keep_alive).await
+        }
+        "PROPFIND" => {
+            let files = match source.files().await {
+                Ok(files) => files,
+                Err(e) => return write_error(writer, "500 Internal Server Error", &e.to_string(), keep_alive).await,
+            };
+            let Some(node) = find(&files, &request.path) else {
+                return write_error(writer, "404 Not Found", "Not found", keep_alive).await;
+            };
+            let mut nodes = vec![node.clone()];
+            if request.depth.as_deref() != Some("0") {
+                nodes.extend(children(&files, &request.path));
+            }
+            let body = multistatus(&nodes);
+            let headers = [("Content-Type", "application/xml; charset=utf-8".to_string())];
+            write_head(writer, "207 Multi-Status", &headers, body.len() as u64, keep_alive).await?;
+            writer.write_all(body.as_bytes()).await?;
+            Ok(())
+        }
+        "GET" | "HEAD" => {
+            let files = match source.files().await {
+                Ok(files) => files,
+                Err(e) => return write_error(writer, "500 Internal Server Error", &e.to_string(), keep_alive).await,
+            };
+            let Some(file) = files.iter().find(|f| f.path == request.path) else {
+                return match find(&files, &request.path) {
+                    Some(_) => write_error(writer, "405 Method Not Allowed", "Folders can't be downloaded", keep_alive).await,
+                    None => write_error(writer, "404 Not Found", "Not found", keep_alive).await,
+                };
+            };
+            let content = match source.open(&file.path).await {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("WebDAV failed to open {}: {}", file.path, e);
+                    return write_error(writer, "502 Bad Gateway", &e.to_string(), keep_alive).await;
+                }
+            };
+            let sent = send_file(request, file, &content, writer).await;
+            if content.temporary {
+                let _ = tokio::fs::remove_file(&content.path).await;
+            }
+            sent
+        }
+        "PUT" | "DELETE" | "MKCOL" | "COPY" | "MOVE" | "PROPPATCH" | "LOCK" | "UNLOCK" => {
+            write_error(writer, "403 Forbidden", "UVCAD serves files read-only", keep_alive).await
+        }
+        _ => write_error(writer, "501 Not Implemented", "Unsupported method", keep_alive).await,
+    }
+}
+
+async fn send_file<W: AsyncWrite + Unpin>(request: &Request, file: &DavFile, content: &DavContent, writer: &mut W) -> Result<()> {
+    let mut opened = tokio::fs::File::open(&content.path).await?;
+    let length = opened.metadata().await?.len();
+    let headers = [
+        ("Content-Type", "application/octet-stream".to_string()),
+        ("Last-Modified", http_date(&file.modified)),
+    ];
+    write_head(writer, "200 OK", &headers, length, request.keep_alive).await?;
+    if request.method == "GET" {
+        tokio::io::copy(&mut opened, writer).await?;
+    }
+    Ok(())
+}
+
+async fn write_head<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: &str,
+    headers: &[(&str, String)],
+    length: u64,
+    keep_alive: bool,
+) -> Result<()> {
+    let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, length);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(if keep_alive { "Connection: keep-alive\r\n\r\n" } else { "Connection: close\r\n\r\n" });
+    writer.write_all(head.as_bytes()).await?;
+    Ok(())
+}
+
+async fn write_error<W: AsyncWrite + Unpin>(writer: &mut W, status: &str, message: &str, keep_alive: bool) -> Result<()> {
+    let mut headers = vec![("Content-Type", "text/plain; charset=utf-8".to_string())];
+    if status.starts_with("405") {
+        headers.push(("Allow", ALLOW.to_string()));
+    }
+    write_head(writer, status, &headers, message.len() as u64, keep_alive).await?;
+    writer.write_all(message.as_bytes()).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Collection { path: String, modified: Option<DateTime<Utc>> },
+    File(DavFile),
+}
+
+/// The file or folder at `path`; folders exist as long as a file is inside.
+fn find(files: &[DavFile], path: &str) -> Option<Node> {
+    if let Some(file) = files.iter().find(|f| f.path == path) {
+        return Some(Node::File(file.clone()));
+    }
+    let prefix = format!("{}/", path);
+    let inside: Vec<&DavFile> = files.iter().filter(|f| path.is_empty() || f.path.starts_with(&prefix)).collect();
+    if inside.is_empty() && !path.is_empty() {
+        return None;
+    }
+    Some(Node::Collection { path: path.to_string(), modified: inside.iter().map(|f| f.modified).max() })
+}
+
+/// Files and folders directly inside the folder at `path`.
+fn children(files: &[DavFile], path: &str) -> Vec<Node> {
+    let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+    let mut folders = BTreeSet::new();
+    let mut nodes = Vec::new();
+    for file in files.iter().filter(|f| f.path.starts_with(&prefix)) {
+        match file.path[prefix.len()..].split_once('/') {
+            Some((folder, _)) => {
+                folders.insert(format!("{}{}", prefix, folder));
+            }
+            None => nodes.push(Node::File(file.clone())),
+        }
+    }
+    for folder in folders {
+        nodes.extend(find(files, &folder));
+    }
+    nodes
+}
+
+fn multistatus(nodes: &[Node]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n");
+    for node in nodes {
+        let (path, props) = match node {
+            Node::Collection { path, modified } => {
+                let href = if path.is_empty() { "/".to_string() } else { format!("/{}/", encode_path(path)) };
+                let modified = modified.map(|m| format!("<D:getlastmodified>{}</D:getlastmodified>", http_date(&m)));
+                (href, format!("<D:resourcetype><D:collection/></D:resourcetype>{}", modified.unwrap_or_default()))
+            }
+            Node::File(file) => (
+                format!("/{}", encode_path(&file.path)),
+                format!(
+                    "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>\
+                     <D:getcontenttype>application/octet-stream</D:getcontenttype>\
+                     <D:getlastmodified>{}</D:getlastmodified>",
+                    file.size,
+                    http_date(&file.modified),
+                ),
+            ),
+        };
+        let name = match node {
+            Node::Collection { path, .. } | Node::File(DavFile { path, .. }) => path.rsplit('/').next().unwrap_or(""),
+        };
+        xml.push_str(&format!(
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:displayname>{}</D:displayname>{}</D:prop>\
+             <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n",
+            path,
+            escape_xml(name),
+            props,
+        ));
+    }
+    xml.push_str("</D:multistatus>\n");
+    xml
+}
+
+/// Whether the Host header `host` names this server by a loopback address or
+/// `localhost`, with its port or none.
+fn is_local_host(host: &str, port: u16) -> bool {
+    let host = host.trim();
+    let (name, host_port) = if let Some(rest) = host.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((name, "")) => (name, None),
+            Some((name, rest)) => match rest.strip_prefix(':') {
+                Some(host_port) => (name, Some(host_port)),
+                None => return false,
+            },
+            None => return false,
+        }
+    } else if host.matches(':').count() > 1 {
+        // An IPv6 address without brackets can't carry a port
+        (host, None)
+    } else {
+        match host.split_once(':') {
+            Some((name, host_port)) => (name, Some(host_port)),
+            None => (host, None),
+        }
+    };
+    let loopback = name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback());
+    loopback && host_port.is_none_or(|host_port| host_port.parse::<u16>() == Ok(port))
+}
+
+fn http_date(time: &DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// The relative path of a request target: query dropped, percent-decoded,
+/// `.` and `..` segments ignored so nothing outside the view can be named.
+fn decode_path(target: &str) -> String {
+    let path = target.split('?').next().unwrap_or("");
+    // Some clients send absolute URIs
+    let path = match path.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |i| &rest[i..]),
+        None => path,
+    };
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut raw = path.bytes();
+    while let Some(b) = raw.next() {
+        if b == b'%' {
+            let hex: Vec<u8> = raw.by_ref().take(2).collect();
+            match std::str::from_utf8(&hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(decoded) => bytes.push(decoded),
+                None => {
+                    bytes.push(b'%');
+                    bytes.extend(hex);
+                }
+            }
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8_lossy(&bytes)
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct DirSource(tempfile::TempDir, Vec<DavFile>);
+
+    #[async_trait]
+    impl DavSource for DirSource {
+        async fn files(&self) -> Result<Vec<DavFile>> {
+            Ok(self.1.clone())
+        }
+
+        async fn open(&self, path: &str) -> Result<DavContent> {
+            Ok(DavContent { path: self.0.path().join(path), temporary: false })
+        }
+    }
+
+    #[test]
+    fn test_decode_path_stays_inside() {
+        assert_eq!(decode_path("/Parts/Bracket%20A%C3%A9.step?x=1"), "Parts/Bracket Aé.step");
+        assert_eq!(decode_path("/../../etc/passwd"), "etc/passwd");
+        assert_eq!(decode_path("http://localhost:4918/Parts/"), "Parts");
+        assert_eq!(encode_path("Parts/Bracket Aé.step"), "Parts/Bracket%20A%C3%A9.step");
+    }
+
+    #[test]
+    fn test_only_loopback_hosts_are_local() {
+        for host in ["localhost:4918", "127.0.0.1:4918", "[::1]:4918", "[::1]", "::1", "localhost", "LocalHost:4918"] {
+            assert!(is_local_host(host, 4918), "{}", host);
+        }
+        for host in ["localhost:80", "evil.example:4918", "[::1]4918", "[::2]:4918", "192.168.1.5", "localhost.evil.example"] {
+            assert!(!is_local_host(host, 4918), "{}", host);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lists_and_serves_files_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("Parts")).unwrap();
+        std::fs::write(dir.path().join("Parts/Bracket A.step"), "ISO-10303-21;").unwrap();
+        let files = vec![
+            DavFile { path: "Parts/Bracket A.step".to_string(), size: 13, modified: Utc::now() },
+            DavFile { path: "Parts/Sub/Bolt.sldprt".to_string(), size: 4, modified: Utc::now() },
+            DavFile { path: "readme.txt".to_string(), size: 2, modified: Utc::now() },
+        ];
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(listener, Arc::new(DirSource(dir, files))));
+
+        let client = reqwest::Client::new();
+        let propfind = reqwest::Method::from_bytes(b"PROPFIND").unwrap();
+        let listing = client.request(propfind.clone(), format!("{}/Parts/", url)).header("Depth", "1").send().await.unwrap();
+        assert_eq!(listing.status().as_u16(), 207);
+        let xml = listing.text().await.unwrap();
+        assert!(xml.contains("<D:href>/Parts/</D:href>"));
+        assert!(xml.contains("<D:href>/Parts/Bracket%20A.step</D:href>"));
+        assert!(xml.contains("<D:href>/Parts/Sub/</D:href>"));
+        assert!(!xml.contains("Bolt") && !xml.contains("readme"));
+
+        let root = client.request(propfind, &url).header("Depth", "0").send().await.unwrap().text().await.unwrap();
+        assert_eq!(root.matches("<D:response>").count(), 1);
+
+        let file = client.get(format!("{}/Parts/Bracket%20A.step", url)).send().await.unwrap();
+        assert_eq!(file.status().as_u16(), 200);
+        assert_eq!(file.text().await.unwrap(), "ISO-10303-21;");
+
+        let put = client.put(format!("{}/Parts/new.step", url)).body("x").send().await.unwrap();
+        assert_eq!(put.status().as_u16(), 403);
+        let missing = client.get(format!("{}/Parts/missing.step", url)).send().await.unwrap();
+        assert_eq!(missing.status().as_u16(), 404);
+        let rebound = client.get(format!("{}/readme.txt", url)).header("Host", "attacker.example").send().await.unwrap();
+        assert_eq!(rebound.status().as_u16(), 403);
+        server.abort();
+    }
+}
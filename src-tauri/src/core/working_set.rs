@@ -0,0 +1,160 @@
+use crate::db::models::DbOperations;
+use crate::models::file_state::{FileLocation, FileState};
+use crate::models::folder_usage::FolderUsage;
+use crate::utils::error::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Folders synced ahead of the rest.
+pub const WORKING_SET_SIZE: usize = 10;
+
+/// Opens count half as much after this many days, so the working set follows
+/// the projects someone moves on to.
+const HALF_LIFE_DAYS: f64 = 14.0;
+
+/// Access times this close after the last write come from writing the file,
+/// e.g. a download or the scan hashing it, not from someone opening it.
+const WRITE_MARGIN: Duration = Duration::minutes(10);
+
+/// A folder of a user's working set.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkingFolder {
+    pub folder: String,
+    pub opens: i64,
+    pub last_opened_at: DateTime<Utc>,
+    /// Opens weighted by how recent they are.
+    pub score: f64,
+}
+
+/// The folder of a file, relative to the profile's root.
+pub fn folder_of(path: &str) -> String {
+    Path::new(path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Count files someone opened per folder: local files accessed later than
+/// their last write and later than their folder's last recorded open. Returns
+/// the new opens and the time of the latest per folder.
+pub fn opened_since(
+    states: &[FileState],
+    accessed: &HashMap<String, DateTime<Utc>>,
+    usage: &[FolderUsage],
+) -> HashMap<String, (i64, DateTime<Utc>)> {
+    let recorded: HashMap<&str, DateTime<Utc>> = usage.iter().map(|u| (u.folder.as_str(), u.last_opened_at)).collect();
+    let mut opened: HashMap<String, (i64, DateTime<Utc>)> = HashMap::new();
+
+    for state in states.iter().filter(|s| s.location == FileLocation::Local) {
+        let (Some(accessed_at), Some(modified_at)) = (accessed.get(&state.file_path), state.modified_at) else {
+            continue;
+        };
+        if *accessed_at <= modified_at + WRITE_MARGIN {
+            continue;
+        }
+        let folder = folder_of(&state.file_path);
+        if recorded.get(folder.as_str()).is_some_and(|last| accessed_at <= last) {
+            continue;
+        }
+        let entry = opened.entry(folder).or_insert((0, *accessed_at));
+        entry.0 += 1;
+        entry.1 = entry.1.max(*accessed_at);
+    }
+    opened
+}
+
+/// The folders a user works in most, highest score first.
+pub fn rank(usage: &[FolderUsage], now: DateTime<Utc>, limit: usize) -> Vec<WorkingFolder> {
+    let mut folders: Vec<WorkingFolder> = usage.iter()
+        .map(|u| {
+            let age_days = (now - u.last_opened_at).num_seconds().max(0) as f64 / 86_400.0;
+            WorkingFolder {
+                folder: u.folder.clone(),
+                opens: u.opens,
+                last_opened_at: u.last_opened_at,
+                score: u.opens as f64 * 0.5f64.powf(age_days / HALF_LIFE_DAYS),
+            }
+        })
+        .collect();
+    folders.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.folder.cmp(&b.folder)));
+    folders.truncate(limit);
+    folders
+}
+
+/// Record that `user_name` opened the files at `paths` at `now`, e.g. by
+/// fetching them on demand.
+pub fn record_opened(conn: &Connection, profile_id: i64, user_name: &str, paths: &[String], now: DateTime<Utc>) -> Result<()> {
+    let mut opens: HashMap<String, i64> = HashMap::new();
+    for path in paths {
+        *opens.entry(folder_of(path)).or_default() += 1;
+    }
+    for (folder, count) in opens {
+        DbOperations::record_folder_opens(conn, profile_id, user_name, &folder, count, now)?;
+    }
+    Ok(())
+}
+
+/// Learn from the local access times what `user_name` opened since the last
+/// sync, and return their working set.
+pub fn refresh(
+    conn: &Connection,
+    profile_id: i64,
+    user_name: &str,
+    accessed: &HashMap<String, DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<Vec<WorkingFolder>> {
+    let states = DbOperations::get_file_states(conn, profile_id)?;
+    let usage = DbOperations::get_folder_usage(conn, profile_id, user_name)?;
+    for (folder, (opens, last)) in opened_since(&states, accessed, &usage) {
+        DbOperations::record_folder_opens(conn, profile_id, user_name, &folder, opens, last)?;
+    }
+    Ok(rank(&DbOperations::get_folder_usage(conn, profile_id, user_name)?, now, WORKING_SET_SIZE))
+}
+
+/// The folders of a working set as paths for `SyncEngine::with_working_set`.
+pub fn folder_paths(folders: &[WorkingFolder]) -> Vec<PathBuf> {
+    folders.iter().map(|f| PathBuf::from(&f.folder)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn usage(folder: &str, opens: i64, last_opened_at: DateTime<Utc>) -> FolderUsage {
+        FolderUsage { profile_id: 1, user_name: "anna".to_string(), folder: folder.to_string(), opens, last_opened_at }
+    }
+
+    #[test]
+    fn test_learns_and_ranks_working_folders() {
+        let now = Utc.with_ymd_and_hms(2026, 6, 1, 12, 0, 0).unwrap();
+        let days_ago = |days: i64| now - Duration::days(days);
+        let state = |path: &str, modified: DateTime<Utc>| FileState {
+            modified_at: Some(modified),
+            ..FileState::new(1, path.to_string(), FileLocation::Local)
+        };
+
+        let states = [
+            state("ProjectX/parts/a.sldprt", days_ago(30)),
+            state("ProjectX/parts/b.sldprt", days_ago(30)),
+            // Written by the last download, not opened since
+            state("ProjectY/plan.dwg", days_ago(1)),
+            state("Archive/old.dwg", days_ago(300)),
+        ];
+        let accessed = HashMap::from([
+            ("ProjectX/parts/a.sldprt".to_string(), days_ago(2)),
+            ("ProjectX/parts/b.sldprt".to_string(), days_ago(1)),
+            ("ProjectY/plan.dwg".to_string(), days_ago(1) + Duration::minutes(1)),
+            ("Archive/old.dwg".to_string(), days_ago(100)),
+        ]);
+        // Archive was last opened after its file's access time was recorded
+        let recorded = [usage("Archive", 1, days_ago(100))];
+
+        let opened = opened_since(&states, &accessed, &recorded);
+        assert_eq!(opened, HashMap::from([("ProjectX/parts".to_string(), (2, days_ago(1)))]));
+
+        let ranked = rank(&[usage("Old", 40, days_ago(90)), usage("ProjectX/parts", 2, days_ago(1)), usage("Docs", 1, days_ago(3))], now, 2);
+        assert_eq!(ranked.iter().map(|f| f.folder.as_str()).collect::<Vec<_>>(), ["ProjectX/parts", "Docs"]);
+        assert_eq!(folder_of("top.dwg"), "");
+    }
+}
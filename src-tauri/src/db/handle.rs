@@ -0,0 +1,45 @@
+use crate::db::schema::Database;
+use crate::utils::error::{Result, UvcadError};
+use rusqlite::Connection;
+use std::sync::{Arc, Mutex};
+
+/// Async facade over the SQLite connection. Every call runs on tokio's blocking
+/// thread pool, so big writes never stall the async runtime while they hold the lock.
+#[derive(Clone)]
+pub struct DbHandle {
+    db: Arc<Mutex<Database>>,
+}
+
+impl DbHandle {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(db)),
+        }
+    }
+
+    /// Run `f` against the connection on a blocking thread.
+    pub async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let handle = self.clone();
+        tokio::task::spawn_blocking(move || handle.call_blocking(f))
+            .await
+            .map_err(|e| UvcadError::DatabaseUnavailable(format!("Database task failed: {}", e)))?
+    }
+
+    /// Run `f` against the connection on the current thread. Only for code
+    /// that already runs on a blocking thread, such as file hashing.
+    pub fn call_blocking<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let db_guard = self.db.lock()
+            .map_err(|e| UvcadError::DatabaseUnavailable(format!("Failed to lock database: {}", e)))?;
+        f(db_guard.get_connection())
+    }
+}
+
+impl std::fmt::Debug for DbHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DbHandle").finish_non_exhaustive()
+    }
+}
@@ -1,15 +1,155 @@
 // Database migration utilities
-// Future migrations can be added here
+// Tables are created in schema.rs; column additions to existing tables go here
+// so that databases created by older versions are upgraded in place.
 
 use crate::utils::error::Result;
 use rusqlite::Connection;
 
+/// Ordered list of migrations. The index + 1 of each entry is the schema
+/// version it upgrades to (tracked via SQLite's `user_version` pragma).
+/// Never reorder or edit existing entries; append new ones instead. A table
+/// created here by mistake moves to schema.rs and leaves an empty entry behind.
+const MIGRATIONS: &[&str] = &[
+    // 1: format-aware change detection for STEP/IGES
+    "ALTER TABLE sync_profiles ADD COLUMN normalized_extensions TEXT",
+    // 2: zip/container content indexing
+    "ALTER TABLE sync_profiles ADD COLUMN inspect_archives BOOLEAN NOT NULL DEFAULT FALSE",
+    // 3: inbound supplier delivery inbox
+    "ALTER TABLE sync_profiles ADD COLUMN inbox_location TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN inbox_path TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN inbox_extensions TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN inbox_scan_command TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN inbox_rules TEXT;",
+    // 4: naming convention enforcement
+    "ALTER TABLE sync_profiles ADD COLUMN naming_rules TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN naming_blocked_locations TEXT;",
+    // 5: project templates
+    "ALTER TABLE sync_profiles ADD COLUMN project_template_path TEXT",
+    // 6: storage tiering
+    "ALTER TABLE sync_profiles ADD COLUMN tiering_rules TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN tiering_archive_folder_id TEXT;",
+    // 7: per-extension handling policies
+    "ALTER TABLE sync_profiles ADD COLUMN extension_policies TEXT",
+    // 8: drawing publishing
+    "ALTER TABLE sync_profiles ADD COLUMN publish_command TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN publish_formats TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN publish_folder_id TEXT;",
+    // 9: external compare tool
+    "ALTER TABLE sync_profiles ADD COLUMN compare_command TEXT",
+    // 10: consistency groups
+    "ALTER TABLE sync_profiles ADD COLUMN consistency_groups TEXT",
+    // 11: secret store backend
+    "ALTER TABLE sync_profiles ADD COLUMN secret_store TEXT",
+    // 12: update channel
+    "ALTER TABLE sync_profiles ADD COLUMN update_channel TEXT",
+    // 13: message language
+    "ALTER TABLE sync_profiles ADD COLUMN locale TEXT",
+    // 14: sync plan approval
+    "ALTER TABLE sync_profiles ADD COLUMN require_plan_approval BOOLEAN NOT NULL DEFAULT FALSE;
+     ALTER TABLE sync_profiles ADD COLUMN plan_approvers TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN auto_approved_operations TEXT;",
+    // 15: configurable deletion thresholds
+    "ALTER TABLE sync_profiles ADD COLUMN max_deletion_count INTEGER;
+     ALTER TABLE sync_profiles ADD COLUMN max_deletion_percentage INTEGER;",
+    // 16: SMB failover
+    "ALTER TABLE sync_profiles ADD COLUMN smb_fallback_paths TEXT;
+     ALTER TABLE file_events ADD COLUMN endpoint TEXT;",
+    // 17: conditional overwrites
+    "ALTER TABLE transfers ADD COLUMN expected_hash TEXT",
+    // 18: re-check of new destinations
+    "ALTER TABLE transfers ADD COLUMN expect_absent BOOLEAN NOT NULL DEFAULT FALSE",
+    // 19: sampled hashes for very large files
+    "ALTER TABLE sync_profiles ADD COLUMN sampled_hash_min_mb INTEGER",
+    // 20: sync run correlation
+    "ALTER TABLE sync_history ADD COLUMN run_id TEXT;
+     CREATE UNIQUE INDEX IF NOT EXISTS idx_sync_history_run ON sync_history(run_id);
+     ALTER TABLE file_events ADD COLUMN run_id TEXT;
+     CREATE INDEX IF NOT EXISTS idx_file_events_run ON file_events(run_id);",
+    // 21: per-file transfer timings (table created in schema.rs)
+    "",
+    // 22: notification channels
+    "ALTER TABLE sync_profiles ADD COLUMN notification_channels TEXT",
+    // 23: read-only WebDAV endpoint
+    "ALTER TABLE sync_profiles ADD COLUMN webdav_port INTEGER",
+    // 24: digest signing
+    "ALTER TABLE sync_profiles ADD COLUMN sign_digests BOOLEAN NOT NULL DEFAULT FALSE;
+     ALTER TABLE sync_profiles ADD COLUMN trusted_signing_keys TEXT;",
+    // 25: malware scanning of downloads
+    "ALTER TABLE sync_profiles ADD COLUMN scan_command TEXT",
+    // 26: CAD temporary files and saves in progress
+    "ALTER TABLE sync_profiles ADD COLUMN sync_cad_temp_files BOOLEAN NOT NULL DEFAULT FALSE;
+     ALTER TABLE sync_profiles ADD COLUMN save_settle_secs INTEGER;",
+    // 27: quiet period before uploads
+    "ALTER TABLE sync_profiles ADD COLUMN quiet_period_secs INTEGER",
+    // 28: persisted sync run phases
+    "ALTER TABLE sync_history ADD COLUMN phase TEXT",
+    // 29: reasons of executed operations
+    "ALTER TABLE transfers ADD COLUMN reason TEXT;
+     ALTER TABLE file_events ADD COLUMN reason TEXT;",
+    // 30: outcome of the last sync of each profile
+    "ALTER TABLE sync_profiles ADD COLUMN last_successful_sync_at TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN last_error TEXT;",
+    // 31: per-location path prefixes
+    "ALTER TABLE sync_profiles ADD COLUMN path_prefixes TEXT",
+    // 32: the profile commands work on
+    "ALTER TABLE sync_profiles ADD COLUMN is_active INTEGER NOT NULL DEFAULT 0",
+    // 33: folders each user works in (table created in schema.rs)
+    "",
+    // 34: selective sync patterns
+    "ALTER TABLE sync_profiles ADD COLUMN include_patterns TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN exclude_patterns TEXT;",
+    // 35: labelled snapshots of the file manifest (tables created in schema.rs)
+    "",
+    // 36: legal holds on files and folders (table created in schema.rs)
+    "",
+    // 37: moves following renames at another location
+    "ALTER TABLE transfers ADD COLUMN moved_from TEXT",
+    // 38: locations paused per profile (table created in schema.rs)
+    "",
+    // 39: Google Drive scope requested at sign-in
+    "ALTER TABLE sync_profiles ADD COLUMN drive_scope TEXT",
+    // 40: soft deletion into each location's trash (table created in schema.rs)
+    "ALTER TABLE sync_profiles ADD COLUMN soft_delete BOOLEAN NOT NULL DEFAULT FALSE;
+     ALTER TABLE sync_profiles ADD COLUMN trash_retention_days INTEGER;",
+    // 41: Google Drive sign-in with a service account
+    "ALTER TABLE sync_profiles ADD COLUMN drive_auth TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN drive_impersonate TEXT;",
+    // 42: company-wide managed policy
+    "ALTER TABLE sync_profiles ADD COLUMN managed_policy_source TEXT;
+     ALTER TABLE sync_profiles ADD COLUMN override_managed_policy BOOLEAN NOT NULL DEFAULT FALSE;",
+    // 43: taking the next queued transfer without reading the whole queue
+    "CREATE INDEX IF NOT EXISTS idx_transfers_queued ON transfers(profile_id, status, priority, id)",
+    // 44: files on Google Drive encrypted with the profile's key
+    "ALTER TABLE sync_profiles ADD COLUMN encrypt_drive BOOLEAN NOT NULL DEFAULT FALSE",
+    // 45: re-checking destinations without a content hash by size and time
+    "ALTER TABLE transfers ADD COLUMN expected_size INTEGER;
+     ALTER TABLE transfers ADD COLUMN expected_modified TEXT;",
+    // 46: the update channel is an app setting; sync_profiles.update_channel is no longer read
+    "INSERT OR IGNORE INTO app_settings (name, value)
+     SELECT 'update_channel', update_channel FROM sync_profiles
+     WHERE update_channel IS NOT NULL AND update_channel != ''
+     ORDER BY is_active DESC, id LIMIT 1",
+    // 47: recognising unchanged files without hashing them again
+    "ALTER TABLE file_states ADD COLUMN hash_fingerprint TEXT;
+     CREATE INDEX IF NOT EXISTS idx_file_states_fingerprint ON file_states(hash_fingerprint);",
+];
+
 pub struct Migrations;
 
 impl Migrations {
-    pub fn run(_conn: &Connection) -> Result<()> {
-        // Future migrations will be added here
-        // For now, the schema is created in schema.rs
+    pub fn run(conn: &Connection) -> Result<()> {
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        for (index, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            tracing::info!("Applying database migration {}", index + 1);
+            // A migration that fails halfway leaves neither its changes nor
+            // the new version behind, so it is applied again next start
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(sql)?;
+            tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+            tx.commit()?;
+        }
+
         Ok(())
     }
 }
@@ -1,9 +1,118 @@
 // Database model operations
 // This module provides CRUD operations for our domain models
 
-use crate::models::{conflict::Conflict, file_state::FileState, sync_profile::SyncProfile};
+use crate::models::{
+    conflict::{Conflict, ConflictResolution}, file_event::{FileEvent, FileEventKind}, file_reference::FileReference, file_state::{FileLocation, FileState}, file_timing::FileTiming, folder_usage::FolderUsage, inbox_entry::{InboxEntry, InboxStatus}, legal_hold::LegalHold, paused_location::PausedLocation,
+    part_revision::PartRevision, snapshot::{Snapshot, SnapshotFile}, sync_plan::{PlanStatus, SyncPlan}, sync_profile::SyncProfile, sync_run::{RunPhase, RunStatus, SyncRun},
+    transfer::{Transfer, TransferStatus}, trashed_file::TrashedFile,
+};
 use crate::utils::error::Result;
-use rusqlite::{Connection, OptionalExtension};
+use rusqlite::{Connection, OptionalExtension, Transaction};
+
+/// An optional value stored as a JSON column.
+fn to_json<T: serde::Serialize>(value: &Option<T>) -> Result<Option<String>> {
+    Ok(value.as_ref().map(serde_json::to_string).transpose()?)
+}
+
+/// Read a JSON column; values that no longer parse are dropped.
+fn from_json<T: serde::de::DeserializeOwned>(value: Option<String>) -> Option<T> {
+    value.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+const UPSERT_FILE_STATE_SQL: &str =
+    "INSERT INTO file_states (profile_id, file_path, location, content_hash, size_bytes, modified_at, synced_at, status, metadata,
+                              hash_fingerprint)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+     ON CONFLICT(profile_id, file_path, location) DO UPDATE SET
+        content_hash = excluded.content_hash,
+        hash_fingerprint = excluded.hash_fingerprint,
+        size_bytes = excluded.size_bytes,
+        modified_at = excluded.modified_at,
+        synced_at = excluded.synced_at,
+        status = excluded.status,
+        metadata = COALESCE(excluded.metadata, file_states.metadata)";
+
+/// Columns read into a `FileState` by `file_state_from_row`.
+const FILE_STATE_COLUMNS: &str =
+    "id, profile_id, file_path, location, content_hash, size_bytes, modified_at, synced_at, status, metadata,
+     hash_fingerprint";
+
+/// Columns read into a `SyncProfile` by `profile_from_row`.
+const PROFILE_COLUMNS: &str =
+    "id, name, local_path, gdrive_folder_id, smb_share_path, created_at, last_sync_at,
+     normalized_extensions, inspect_archives, inbox_location, inbox_path,
+     inbox_extensions, inbox_scan_command, inbox_rules, naming_rules, naming_blocked_locations,
+     project_template_path, tiering_rules, tiering_archive_folder_id, extension_policies,
+     publish_command, publish_formats, publish_folder_id, compare_command, consistency_groups,
+     secret_store, locale, require_plan_approval, plan_approvers,
+     auto_approved_operations, max_deletion_count, max_deletion_percentage, smb_fallback_paths,
+     sampled_hash_min_mb, notification_channels, webdav_port, sign_digests,
+     trusted_signing_keys, scan_command, sync_cad_temp_files, save_settle_secs,
+     quiet_period_secs, last_successful_sync_at, last_error, path_prefixes, include_patterns,
+     exclude_patterns, drive_scope, soft_delete, trash_retention_days, drive_auth,
+     drive_impersonate, managed_policy_source, override_managed_policy, encrypt_drive";
+
+fn profile_from_row(row: &rusqlite::Row) -> rusqlite::Result<SyncProfile> {
+    Ok(SyncProfile {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        local_path: row.get(2)?,
+        gdrive_folder_id: row.get(3)?,
+        smb_share_path: row.get(4)?,
+        created_at: row.get::<_, String>(5)?.parse().unwrap(),
+        last_sync_at: row.get::<_, Option<String>>(6)?
+            .and_then(|s| s.parse().ok()),
+        normalized_extensions: row.get(7)?,
+        inspect_archives: row.get(8)?,
+        inbox_location: row.get(9)?,
+        inbox_path: row.get(10)?,
+        inbox_extensions: row.get(11)?,
+        inbox_scan_command: row.get(12)?,
+        inbox_rules: row.get(13)?,
+        naming_rules: row.get(14)?,
+        naming_blocked_locations: row.get(15)?,
+        project_template_path: row.get(16)?,
+        tiering_rules: row.get(17)?,
+        tiering_archive_folder_id: row.get(18)?,
+        extension_policies: row.get(19)?,
+        publish_command: row.get(20)?,
+        publish_formats: row.get(21)?,
+        publish_folder_id: row.get(22)?,
+        compare_command: row.get(23)?,
+        consistency_groups: row.get(24)?,
+        secret_store: row.get(25)?,
+        locale: row.get(26)?,
+        require_plan_approval: row.get(27)?,
+        plan_approvers: row.get(28)?,
+        auto_approved_operations: row.get(29)?,
+        max_deletion_count: row.get(30)?,
+        max_deletion_percentage: row.get(31)?,
+        smb_fallback_paths: row.get(32)?,
+        sampled_hash_min_mb: row.get(33)?,
+        notification_channels: row.get(34)?,
+        webdav_port: row.get(35)?,
+        sign_digests: row.get(36)?,
+        trusted_signing_keys: row.get(37)?,
+        scan_command: row.get(38)?,
+        sync_cad_temp_files: row.get(39)?,
+        save_settle_secs: row.get(40)?,
+        quiet_period_secs: row.get(41)?,
+        last_successful_sync_at: row.get::<_, Option<String>>(42)?
+            .and_then(|s| s.parse().ok()),
+        last_error: row.get(43)?,
+        path_prefixes: row.get(44)?,
+        include_patterns: row.get(45)?,
+        exclude_patterns: row.get(46)?,
+        drive_scope: row.get(47)?,
+        soft_delete: row.get(48)?,
+        trash_retention_days: row.get(49)?,
+        drive_auth: row.get(50)?,
+        drive_impersonate: row.get(51)?,
+        managed_policy_source: row.get(52)?,
+        override_managed_policy: row.get(53)?,
+        encrypt_drive: row.get(54)?,
+    })
+}
 
 pub struct DbOperations;
 
@@ -11,8 +120,22 @@ impl DbOperations {
     // Sync Profile operations
     pub fn create_sync_profile(conn: &Connection, profile: &SyncProfile) -> Result<i64> {
         conn.execute(
-            "INSERT INTO sync_profiles (name, local_path, gdrive_folder_id, smb_share_path, created_at, last_sync_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO sync_profiles (name, local_path, gdrive_folder_id, smb_share_path, created_at, last_sync_at,
+                                        normalized_extensions, inspect_archives, inbox_location, inbox_path,
+                                        inbox_extensions, inbox_scan_command, inbox_rules, naming_rules,
+                                        naming_blocked_locations, project_template_path, tiering_rules,
+                                        tiering_archive_folder_id, extension_policies, publish_command,
+                                        publish_formats, publish_folder_id, compare_command,
+                                        consistency_groups, secret_store, locale,
+                                        require_plan_approval, plan_approvers, auto_approved_operations,
+                                        max_deletion_count, max_deletion_percentage, smb_fallback_paths,
+                                        sampled_hash_min_mb, notification_channels, webdav_port, sign_digests,
+                                        trusted_signing_keys, scan_command, sync_cad_temp_files,
+                                        save_settle_secs, quiet_period_secs, path_prefixes, include_patterns,
+                                        exclude_patterns, drive_scope, soft_delete, trash_retention_days,
+                                        drive_auth, drive_impersonate, managed_policy_source,
+                                        override_managed_policy, encrypt_drive)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38, ?39, ?40, ?41, ?42, ?43, ?44, ?45, ?46, ?47, ?48, ?49, ?50, ?51, ?52)",
             rusqlite::params![
                 profile.name,
                 profile.local_path,
@@ -20,97 +143,837 @@ impl DbOperations {
                 profile.smb_share_path,
                 profile.created_at.to_rfc3339(),
                 profile.last_sync_at.map(|dt| dt.to_rfc3339()),
+                profile.normalized_extensions,
+                profile.inspect_archives,
+                profile.inbox_location,
+                profile.inbox_path,
+                profile.inbox_extensions,
+                profile.inbox_scan_command,
+                profile.inbox_rules,
+                profile.naming_rules,
+                profile.naming_blocked_locations,
+                profile.project_template_path,
+                profile.tiering_rules,
+                profile.tiering_archive_folder_id,
+                profile.extension_policies,
+                profile.publish_command,
+                profile.publish_formats,
+                profile.publish_folder_id,
+                profile.compare_command,
+                profile.consistency_groups,
+                profile.secret_store,
+                profile.locale,
+                profile.require_plan_approval,
+                profile.plan_approvers,
+                profile.auto_approved_operations,
+                profile.max_deletion_count,
+                profile.max_deletion_percentage,
+                profile.smb_fallback_paths,
+                profile.sampled_hash_min_mb,
+                profile.notification_channels,
+                profile.webdav_port,
+                profile.sign_digests,
+                profile.trusted_signing_keys,
+                profile.scan_command,
+                profile.sync_cad_temp_files,
+                profile.save_settle_secs,
+                profile.quiet_period_secs,
+                profile.path_prefixes,
+                profile.include_patterns,
+                profile.exclude_patterns,
+                profile.drive_scope,
+                profile.soft_delete,
+                profile.trash_retention_days,
+                profile.drive_auth,
+                profile.drive_impersonate,
+                profile.managed_policy_source,
+                profile.override_managed_policy,
+                profile.encrypt_drive,
             ],
         )?;
         Ok(conn.last_insert_rowid())
     }
 
     pub fn get_sync_profile(conn: &Connection, id: i64) -> Result<Option<SyncProfile>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, name, local_path, gdrive_folder_id, smb_share_path, created_at, last_sync_at
-             FROM sync_profiles WHERE id = ?1"
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM sync_profiles WHERE id = ?1", PROFILE_COLUMNS))?;
+        Ok(stmt.query_row([id], profile_from_row).optional()?)
+    }
+
+    /// Every profile, oldest first.
+    pub fn list_sync_profiles(conn: &Connection) -> Result<Vec<SyncProfile>> {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM sync_profiles ORDER BY id", PROFILE_COLUMNS))?;
+        let profiles = stmt.query_map([], profile_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(profiles)
+    }
+
+    /// The profile commands work on unless they are given another one.
+    pub fn get_active_sync_profile_id(conn: &Connection) -> Result<Option<i64>> {
+        Ok(conn.query_row(
+            "SELECT id FROM sync_profiles WHERE is_active = 1 ORDER BY id LIMIT 1",
+            [],
+            |row| row.get(0),
+        ).optional()?)
+    }
+
+    pub fn set_active_sync_profile(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("UPDATE sync_profiles SET is_active = (id = ?1)", [id])?;
+        Ok(())
+    }
+
+    /// The lowest id of a profile named `name`.
+    pub fn find_sync_profile_id(conn: &Connection, name: &str) -> Result<Option<i64>> {
+        Ok(conn.query_row(
+            "SELECT id FROM sync_profiles WHERE name = ?1 ORDER BY id LIMIT 1",
+            [name],
+            |row| row.get(0),
+        ).optional()?)
+    }
+
+    /// Save the settings of a profile. The outcome of its syncs is kept by
+    /// `record_sync_outcome`.
+    pub fn update_sync_profile(conn: &Connection, profile: &SyncProfile) -> Result<()> {
+        let id = profile.id.ok_or_else(|| crate::utils::error::UvcadError::InvalidConfig(
+            "Cannot update a sync profile without an id".to_string()
+        ))?;
+
+        conn.execute(
+            "UPDATE sync_profiles SET name = ?1, local_path = ?2, gdrive_folder_id = ?3, smb_share_path = ?4,
+                                      normalized_extensions = ?5, inspect_archives = ?6,
+                                      inbox_location = ?7, inbox_path = ?8, inbox_extensions = ?9,
+                                      inbox_scan_command = ?10, inbox_rules = ?11, naming_rules = ?12,
+                                      naming_blocked_locations = ?13, project_template_path = ?14,
+                                      tiering_rules = ?15, tiering_archive_folder_id = ?16,
+                                      extension_policies = ?17, publish_command = ?18, publish_formats = ?19,
+                                      publish_folder_id = ?20, compare_command = ?21, consistency_groups = ?22,
+                                      secret_store = ?23, locale = ?24,
+                                      require_plan_approval = ?25, plan_approvers = ?26,
+                                      auto_approved_operations = ?27, max_deletion_count = ?28,
+                                      max_deletion_percentage = ?29, smb_fallback_paths = ?30,
+                                      sampled_hash_min_mb = ?31, notification_channels = ?32,
+                                      webdav_port = ?33, sign_digests = ?34, trusted_signing_keys = ?35,
+                                      scan_command = ?36, sync_cad_temp_files = ?37, save_settle_secs = ?38,
+                                      quiet_period_secs = ?39, path_prefixes = ?40, include_patterns = ?41,
+                                      exclude_patterns = ?42, drive_scope = ?43, soft_delete = ?44,
+                                      trash_retention_days = ?45, drive_auth = ?46, drive_impersonate = ?47,
+                                      managed_policy_source = ?48, override_managed_policy = ?49,
+                                      encrypt_drive = ?50
+             WHERE id = ?51",
+            rusqlite::params![
+                profile.name,
+                profile.local_path,
+                profile.gdrive_folder_id,
+                profile.smb_share_path,
+                profile.normalized_extensions,
+                profile.inspect_archives,
+                profile.inbox_location,
+                profile.inbox_path,
+                profile.inbox_extensions,
+                profile.inbox_scan_command,
+                profile.inbox_rules,
+                profile.naming_rules,
+                profile.naming_blocked_locations,
+                profile.project_template_path,
+                profile.tiering_rules,
+                profile.tiering_archive_folder_id,
+                profile.extension_policies,
+                profile.publish_command,
+                profile.publish_formats,
+                profile.publish_folder_id,
+                profile.compare_command,
+                profile.consistency_groups,
+                profile.secret_store,
+                profile.locale,
+                profile.require_plan_approval,
+                profile.plan_approvers,
+                profile.auto_approved_operations,
+                profile.max_deletion_count,
+                profile.max_deletion_percentage,
+                profile.smb_fallback_paths,
+                profile.sampled_hash_min_mb,
+                profile.notification_channels,
+                profile.webdav_port,
+                profile.sign_digests,
+                profile.trusted_signing_keys,
+                profile.scan_command,
+                profile.sync_cad_temp_files,
+                profile.save_settle_secs,
+                profile.quiet_period_secs,
+                profile.path_prefixes,
+                profile.include_patterns,
+                profile.exclude_patterns,
+                profile.drive_scope,
+                profile.soft_delete,
+                profile.trash_retention_days,
+                profile.drive_auth,
+                profile.drive_impersonate,
+                profile.managed_policy_source,
+                profile.override_managed_policy,
+                profile.encrypt_drive,
+                id,
+            ],
         )?;
+        Ok(())
+    }
 
-        let profile = stmt.query_row([id], |row| {
-            Ok(SyncProfile {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                local_path: row.get(2)?,
-                gdrive_folder_id: row.get(3)?,
-                smb_share_path: row.get(4)?,
-                created_at: row.get::<_, String>(5)?.parse().unwrap(),
-                last_sync_at: row.get::<_, Option<String>>(6)?
-                    .and_then(|s| s.parse().ok()),
-            })
-        }).optional()?;
+    /// Delete a profile together with everything recorded for it.
+    pub fn delete_sync_profile(conn: &Connection, id: i64) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        for table in [
+            "file_states", "sync_history", "conflicts", "file_references", "inbox_log",
+            "part_revisions", "transfers", "file_events", "sync_plans", "file_timings",
+            "folder_usage", "legal_holds", "paused_locations", "trashed_files",
+        ] {
+            tx.execute(&format!("DELETE FROM {} WHERE profile_id = ?1", table), [id])?;
+        }
+        tx.execute(
+            "DELETE FROM snapshot_files WHERE snapshot_id IN (SELECT id FROM snapshots WHERE profile_id = ?1)",
+            [id],
+        )?;
+        tx.execute("DELETE FROM snapshots WHERE profile_id = ?1", [id])?;
+        tx.execute("DELETE FROM sync_profiles WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        Ok(profile)
+    // App setting operations
+    /// A setting of the whole app rather than of a profile.
+    pub fn get_app_setting(conn: &Connection, name: &str) -> Result<Option<String>> {
+        Ok(conn.query_row("SELECT value FROM app_settings WHERE name = ?1", [name], |row| row.get(0)).optional()?)
+    }
+
+    /// Save an app setting; None removes it.
+    pub fn set_app_setting(conn: &Connection, name: &str, value: Option<&str>) -> Result<()> {
+        match value {
+            Some(value) => conn.execute(
+                "INSERT INTO app_settings (name, value) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET value = excluded.value",
+                [name, value],
+            )?,
+            None => conn.execute("DELETE FROM app_settings WHERE name = ?1", [name])?,
+        };
+        Ok(())
     }
 
     // File State operations
     pub fn upsert_file_state(conn: &Connection, state: &FileState) -> Result<()> {
+        let mut stmt = conn.prepare_cached(UPSERT_FILE_STATE_SQL)?;
+        Self::execute_file_state_upsert(&mut stmt, state)
+    }
+
+    /// Upsert many file states with one prepared statement inside the caller's transaction.
+    pub fn upsert_file_states(tx: &Transaction, states: &[FileState]) -> Result<()> {
+        let mut stmt = tx.prepare_cached(UPSERT_FILE_STATE_SQL)?;
+        for state in states {
+            Self::execute_file_state_upsert(&mut stmt, state)?;
+        }
+        Ok(())
+    }
+
+    fn execute_file_state_upsert(stmt: &mut rusqlite::CachedStatement, state: &FileState) -> Result<()> {
+        stmt.execute(rusqlite::params![
+            state.profile_id,
+            state.file_path,
+            state.location.as_str(),
+            state.content_hash,
+            state.size_bytes,
+            state.modified_at.map(|dt| dt.to_rfc3339()),
+            state.synced_at.map(|dt| dt.to_rfc3339()),
+            state.status.as_str(),
+            state.metadata,
+            state.hash_fingerprint,
+        ])?;
+        Ok(())
+    }
+
+    pub fn get_file_states(conn: &Connection, profile_id: i64) -> Result<Vec<FileState>> {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM file_states WHERE profile_id = ?1", FILE_STATE_COLUMNS))?;
+        let states = stmt.query_map([profile_id], Self::file_state_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(states)
+    }
+
+    /// Up to `limit` file states of a profile with an id above `after_id`, by id.
+    pub fn get_file_states_page(conn: &Connection, profile_id: i64, after_id: i64, limit: usize) -> Result<Vec<FileState>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM file_states WHERE profile_id = ?1 AND id > ?2 ORDER BY id LIMIT ?3",
+            FILE_STATE_COLUMNS
+        ))?;
+        let states = stmt.query_map(rusqlite::params![profile_id, after_id, limit as i64], Self::file_state_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(states)
+    }
+
+    /// The state of one file at one location.
+    pub fn get_file_state(conn: &Connection, profile_id: i64, file_path: &str, location: &FileLocation) -> Result<Option<FileState>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM file_states WHERE profile_id = ?1 AND file_path = ?2 AND location = ?3",
+            FILE_STATE_COLUMNS
+        ))?;
+        Ok(stmt.query_row(rusqlite::params![profile_id, file_path, location.as_str()], Self::file_state_from_row).optional()?)
+    }
+
+    /// The content hash recorded for a file with this quick-hash fingerprint.
+    pub fn find_hash_by_fingerprint(conn: &Connection, fingerprint: &str) -> Result<Option<String>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT content_hash FROM file_states
+             WHERE hash_fingerprint = ?1 AND content_hash IS NOT NULL LIMIT 1"
+        )?;
+        Ok(stmt.query_row([fingerprint], |row| row.get(0)).optional()?)
+    }
+
+    fn file_state_from_row(row: &rusqlite::Row) -> rusqlite::Result<FileState> {
+        Ok(FileState {
+            id: Some(row.get(0)?),
+            profile_id: row.get(1)?,
+            file_path: row.get(2)?,
+            location: row.get::<_, String>(3)?.parse().unwrap_or(crate::models::file_state::FileLocation::Local),
+            content_hash: row.get(4)?,
+            hash_fingerprint: row.get(10)?,
+            size_bytes: row.get(5)?,
+            modified_at: row.get::<_, Option<String>>(6)?
+                .and_then(|s| s.parse().ok()),
+            synced_at: row.get::<_, Option<String>>(7)?
+                .and_then(|s| s.parse().ok()),
+            status: row.get::<_, String>(8)?.parse().unwrap_or(crate::models::file_state::SyncStatus::Pending),
+            metadata: row.get(9)?,
+        })
+    }
+
+    /// Delete file state records for a specific profile, file_path, and location.
+    pub fn delete_file_state(conn: &Connection, profile_id: i64, file_path: &str, location: &str) -> Result<()> {
+        conn.execute(
+            "DELETE FROM file_states WHERE profile_id = ?1 AND file_path = ?2 AND location = ?3",
+            rusqlite::params![profile_id, file_path, location],
+        )?;
+        Ok(())
+    }
+
+    /// Delete many `(file_path, location)` states with one prepared statement inside
+    /// the caller's transaction.
+    pub fn delete_file_states(tx: &Transaction, profile_id: i64, entries: &[(String, FileLocation)]) -> Result<()> {
+        let mut stmt = tx.prepare_cached(
+            "DELETE FROM file_states WHERE profile_id = ?1 AND file_path = ?2 AND location = ?3"
+        )?;
+        for (file_path, location) in entries {
+            stmt.execute(rusqlite::params![profile_id, file_path, location.as_str()])?;
+        }
+        Ok(())
+    }
+
+    pub fn file_state_exists(conn: &Connection, profile_id: i64, file_path: &str, location: &FileLocation) -> Result<bool> {
+        let found: Option<i64> = conn.query_row(
+            "SELECT 1 FROM file_states WHERE profile_id = ?1 AND file_path = ?2 AND location = ?3",
+            rusqlite::params![profile_id, file_path, location.as_str()],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(found.is_some())
+    }
+
+    // File event history operations
+    pub fn insert_file_events(tx: &Transaction, events: &[FileEvent]) -> Result<()> {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO file_events (profile_id, file_path, location, kind, content_hash, recorded_at, endpoint, run_id, reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+        )?;
+        for event in events {
+            stmt.execute(rusqlite::params![
+                event.profile_id,
+                event.file_path,
+                event.location.as_str(),
+                event.kind.as_str(),
+                event.content_hash,
+                event.recorded_at.to_rfc3339(),
+                event.endpoint,
+                event.run_id,
+                to_json(&event.reason)?,
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Events recorded at or after `since`, oldest first.
+    pub fn get_file_events_since(conn: &Connection, profile_id: i64, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<FileEvent>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_path, location, kind, content_hash, recorded_at, endpoint, run_id, reason
+             FROM file_events WHERE profile_id = ?1 AND recorded_at >= ?2
+             ORDER BY recorded_at, id"
+        )?;
+
+        let events = stmt.query_map(rusqlite::params![profile_id, since.to_rfc3339()], Self::file_event_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(events)
+    }
+
+    /// Events recorded by one sync run, oldest first.
+    pub fn get_file_events_for_run(conn: &Connection, run_id: &str) -> Result<Vec<FileEvent>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_path, location, kind, content_hash, recorded_at, endpoint, run_id, reason
+             FROM file_events WHERE run_id = ?1
+             ORDER BY recorded_at, id"
+        )?;
+
+        let events = stmt.query_map([run_id], Self::file_event_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(events)
+    }
+
+    fn file_event_from_row(row: &rusqlite::Row) -> rusqlite::Result<FileEvent> {
+        Ok(FileEvent {
+            id: Some(row.get(0)?),
+            profile_id: row.get(1)?,
+            file_path: row.get(2)?,
+            location: row.get::<_, String>(3)?.parse().unwrap_or(FileLocation::Local),
+            kind: row.get::<_, String>(4)?.parse().unwrap_or(FileEventKind::Modified),
+            content_hash: row.get(5)?,
+            recorded_at: row.get::<_, String>(6)?.parse().unwrap(),
+            endpoint: row.get(7)?,
+            run_id: row.get(8)?,
+            reason: from_json(row.get(9)?),
+        })
+    }
+
+    // Sync run history operations
+
+    /// Record the start of a run. A run made of several engine passes, e.g. an
+    /// on-demand sync pulling in references, keeps its first start time.
+    pub fn start_sync_run(conn: &Connection, profile_id: i64, run_id: &str) -> Result<()> {
+        conn.execute(
+            "INSERT OR IGNORE INTO sync_history (profile_id, run_id, started_at, status)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![profile_id, run_id, chrono::Utc::now().to_rfc3339(), RunStatus::Running.as_str()],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_sync_run_phase(conn: &Connection, run_id: &str, phase: RunPhase) -> Result<()> {
         conn.execute(
-            "INSERT INTO file_states (profile_id, file_path, location, content_hash, size_bytes, modified_at, synced_at, status, metadata)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-             ON CONFLICT(profile_id, file_path, location) DO UPDATE SET
-                content_hash = excluded.content_hash,
-                size_bytes = excluded.size_bytes,
-                modified_at = excluded.modified_at,
-                synced_at = excluded.synced_at,
-                status = excluded.status,
-                metadata = excluded.metadata",
+            "UPDATE sync_history SET phase = ?1 WHERE run_id = ?2",
+            rusqlite::params![phase.as_str(), run_id],
+        )?;
+        Ok(())
+    }
+
+    /// Mark runs of the profile still recorded as running, other than `run_id`, as
+    /// failed: the app stopped during them. Their phase says where.
+    pub fn fail_interrupted_runs(conn: &Connection, profile_id: i64, run_id: &str) -> Result<usize> {
+        Ok(conn.execute(
+            "UPDATE sync_history SET status = ?1, error_message = 'Interrupted during the ' || REPLACE(COALESCE(phase, 'scan'), '_', ' ') || ' phase'
+             WHERE profile_id = ?2 AND status = ?3 AND run_id != ?4",
+            rusqlite::params![RunStatus::Failed.as_str(), profile_id, RunStatus::Running.as_str(), run_id],
+        )?)
+    }
+
+    /// Note on the profile that a sync ended now: with `succeeded`, without errors,
+    /// otherwise with `error` if it failed. A sync awaiting approval neither
+    /// succeeded nor failed.
+    pub fn record_sync_outcome(conn: &Connection, profile_id: i64, succeeded: bool, error: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE sync_profiles SET last_sync_at = ?1,
+                    last_successful_sync_at = CASE WHEN ?2 THEN ?1 ELSE last_successful_sync_at END,
+                    last_error = CASE WHEN ?2 THEN NULL ELSE COALESCE(?3, last_error) END
+             WHERE id = ?4",
+            rusqlite::params![chrono::Utc::now().to_rfc3339(), succeeded, error, profile_id],
+        )?;
+        Ok(())
+    }
+
+    /// Record how a run (or one more of its passes) ended; file counts add up.
+    pub fn finish_sync_run(
+        conn: &Connection,
+        run_id: &str,
+        status: RunStatus,
+        files_synced: i64,
+        files_failed: i64,
+        error_message: Option<&str>,
+    ) -> Result<()> {
+        conn.execute(
+            "UPDATE sync_history SET completed_at = ?1, status = ?2, files_synced = files_synced + ?3,
+                    files_failed = files_failed + ?4, error_message = ?5
+             WHERE run_id = ?6",
             rusqlite::params![
-                state.profile_id,
-                state.file_path,
-                state.location.as_str(),
-                state.content_hash,
-                state.size_bytes,
-                state.modified_at.map(|dt| dt.to_rfc3339()),
-                state.synced_at.map(|dt| dt.to_rfc3339()),
-                state.status.as_str(),
-                state.metadata,
+                chrono::Utc::now().to_rfc3339(), status.as_str(), files_synced, files_failed, error_message, run_id,
             ],
         )?;
         Ok(())
     }
 
-    pub fn get_file_states(conn: &Connection, profile_id: i64) -> Result<Vec<FileState>> {
+    pub fn insert_file_timing(conn: &Connection, timing: &FileTiming) -> Result<()> {
+        conn.execute(
+            "INSERT INTO file_timings (profile_id, run_id, file_path, source, destination, size_bytes,
+                                       download_ms, hash_ms, upload_ms, total_ms, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![
+                timing.profile_id,
+                timing.run_id,
+                timing.file_path,
+                timing.source.as_str(),
+                timing.destination.as_str(),
+                timing.size_bytes,
+                timing.download_ms,
+                timing.hash_ms,
+                timing.upload_ms,
+                timing.total_ms,
+                timing.recorded_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The `limit` slowest transfers of a run, slowest first.
+    pub fn get_slowest_files(conn: &Connection, run_id: &str, limit: usize) -> Result<Vec<FileTiming>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, run_id, file_path, source, destination, size_bytes,
+                    download_ms, hash_ms, upload_ms, total_ms, recorded_at
+             FROM file_timings WHERE run_id = ?1 ORDER BY total_ms DESC, id LIMIT ?2"
+        )?;
+
+        let timings = stmt.query_map(rusqlite::params![run_id, limit as i64], Self::file_timing_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(timings)
+    }
+
+    /// The `limit` most recent transfers of a profile, newest first.
+    pub fn get_recent_file_timings(conn: &Connection, profile_id: i64, limit: usize) -> Result<Vec<FileTiming>> {
         let mut stmt = conn.prepare(
-            "SELECT id, profile_id, file_path, location, content_hash, size_bytes,
-                    modified_at, synced_at, status, metadata
-             FROM file_states WHERE profile_id = ?1"
+            "SELECT id, profile_id, run_id, file_path, source, destination, size_bytes,
+                    download_ms, hash_ms, upload_ms, total_ms, recorded_at
+             FROM file_timings WHERE profile_id = ?1 ORDER BY id DESC LIMIT ?2"
         )?;
 
-        let states = stmt.query_map([profile_id], |row| {
-            Ok(FileState {
+        let timings = stmt.query_map(rusqlite::params![profile_id, limit as i64], Self::file_timing_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(timings)
+    }
+
+    /// Add `opens` to the files a user opened in `folder`, the last at `opened_at`.
+    pub fn record_folder_opens(
+        conn: &Connection,
+        profile_id: i64,
+        user_name: &str,
+        folder: &str,
+        opens: i64,
+        opened_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO folder_usage (profile_id, user_name, folder, opens, last_opened_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(profile_id, user_name, folder) DO UPDATE SET
+                opens = opens + excluded.opens,
+                last_opened_at = MAX(last_opened_at, excluded.last_opened_at)",
+            rusqlite::params![profile_id, user_name, folder, opens, opened_at.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_folder_usage(conn: &Connection, profile_id: i64, user_name: &str) -> Result<Vec<FolderUsage>> {
+        let mut stmt = conn.prepare(
+            "SELECT profile_id, user_name, folder, opens, last_opened_at
+             FROM folder_usage WHERE profile_id = ?1 AND user_name = ?2"
+        )?;
+
+        let usage = stmt.query_map(rusqlite::params![profile_id, user_name], |row| {
+            Ok(FolderUsage {
+                profile_id: row.get(0)?,
+                user_name: row.get(1)?,
+                folder: row.get(2)?,
+                opens: row.get(3)?,
+                last_opened_at: row.get::<_, String>(4)?.parse().unwrap(),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(usage)
+    }
+
+    /// Record a snapshot with its files; returns its id.
+    pub fn create_snapshot(conn: &Connection, snapshot: &Snapshot, files: &[SnapshotFile]) -> Result<i64> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO snapshots (profile_id, label, created_at, created_by) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![snapshot.profile_id, snapshot.label, snapshot.created_at.to_rfc3339(), snapshot.created_by],
+        )?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO snapshot_files (snapshot_id, file_path, content_hash, size_bytes, modified_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for file in files {
+                stmt.execute(rusqlite::params![
+                    id,
+                    file.file_path,
+                    file.content_hash,
+                    file.size_bytes,
+                    file.modified_at.map(|dt| dt.to_rfc3339()),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// The snapshots of a profile with their totals, newest first.
+    pub fn list_snapshots(conn: &Connection, profile_id: i64) -> Result<Vec<Snapshot>> {
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.profile_id, s.label, s.created_at, s.created_by,
+                    COUNT(f.file_path), COALESCE(SUM(f.size_bytes), 0)
+             FROM snapshots s LEFT JOIN snapshot_files f ON f.snapshot_id = s.id
+             WHERE s.profile_id = ?1
+             GROUP BY s.id ORDER BY s.created_at DESC, s.id DESC"
+        )?;
+
+        let snapshots = stmt.query_map([profile_id], |row| {
+            Ok(Snapshot {
                 id: Some(row.get(0)?),
                 profile_id: row.get(1)?,
-                file_path: row.get(2)?,
-                location: row.get::<_, String>(3)?.parse().unwrap_or(crate::models::file_state::FileLocation::Local),
-                content_hash: row.get(4)?,
-                size_bytes: row.get(5)?,
-                modified_at: row.get::<_, Option<String>>(6)?
-                    .and_then(|s| s.parse().ok()),
-                synced_at: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| s.parse().ok()),
-                status: row.get::<_, String>(8)?.parse().unwrap_or(crate::models::file_state::SyncStatus::Pending),
-                metadata: row.get(9)?,
+                label: row.get(2)?,
+                created_at: row.get::<_, String>(3)?.parse().unwrap(),
+                created_by: row.get(4)?,
+                file_count: row.get::<_, i64>(5)? as usize,
+                total_bytes: row.get::<_, i64>(6)?.max(0) as u64,
             })
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        Ok(states)
+        Ok(snapshots)
     }
 
-    /// Delete file state records for a specific profile, file_path, and location.
-    pub fn delete_file_state(conn: &Connection, profile_id: i64, file_path: &str, location: &str) -> Result<()> {
+    pub fn get_snapshot_files(conn: &Connection, snapshot_id: i64) -> Result<Vec<SnapshotFile>> {
+        let mut stmt = conn.prepare(
+            "SELECT file_path, content_hash, size_bytes, modified_at
+             FROM snapshot_files WHERE snapshot_id = ?1 ORDER BY file_path"
+        )?;
+
+        let files = stmt.query_map([snapshot_id], |row| {
+            Ok(SnapshotFile {
+                file_path: row.get(0)?,
+                content_hash: row.get(1)?,
+                size_bytes: row.get(2)?,
+                modified_at: row.get::<_, Option<String>>(3)?.and_then(|s| s.parse().ok()),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(files)
+    }
+
+    pub fn place_legal_hold(conn: &Connection, hold: &LegalHold) -> Result<i64> {
         conn.execute(
-            "DELETE FROM file_states WHERE profile_id = ?1 AND file_path = ?2 AND location = ?3",
-            rusqlite::params![profile_id, file_path, location],
+            "INSERT INTO legal_holds (profile_id, path, reason, placed_by, placed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![hold.profile_id, hold.path, hold.reason, hold.placed_by, hold.placed_at.to_rfc3339()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The holds of a profile, released ones included, oldest first.
+    pub fn get_legal_holds(conn: &Connection, profile_id: i64) -> Result<Vec<LegalHold>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, path, reason, placed_by, placed_at, released_by, released_at, release_reason
+             FROM legal_holds WHERE profile_id = ?1 ORDER BY id"
+        )?;
+
+        let holds = stmt.query_map([profile_id], |row| {
+            Ok(LegalHold {
+                id: Some(row.get(0)?),
+                profile_id: row.get(1)?,
+                path: row.get(2)?,
+                reason: row.get(3)?,
+                placed_by: row.get(4)?,
+                placed_at: row.get::<_, String>(5)?.parse().unwrap(),
+                released_by: row.get(6)?,
+                released_at: row.get::<_, Option<String>>(7)?.and_then(|s| s.parse().ok()),
+                release_reason: row.get(8)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(holds)
+    }
+
+    /// Release an active hold; returns false when there is none with that id.
+    pub fn release_legal_hold(
+        conn: &Connection,
+        id: i64,
+        released_by: &str,
+        released_at: chrono::DateTime<chrono::Utc>,
+        reason: &str,
+    ) -> Result<bool> {
+        let updated = conn.execute(
+            "UPDATE legal_holds SET released_by = ?1, released_at = ?2, release_reason = ?3
+             WHERE id = ?4 AND released_at IS NULL",
+            rusqlite::params![released_by, released_at.to_rfc3339(), reason, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Pause a location, replacing an earlier pause of it.
+    pub fn pause_location(conn: &Connection, paused: &PausedLocation) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO paused_locations (profile_id, location, reason, paused_by, paused_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                paused.profile_id, paused.location.as_str(), paused.reason, paused.paused_by, paused.paused_at.to_rfc3339(),
+            ],
         )?;
         Ok(())
     }
 
+    /// Resume a paused location; returns false when it was not paused.
+    pub fn resume_location(conn: &Connection, profile_id: i64, location: &FileLocation) -> Result<bool> {
+        let deleted = conn.execute(
+            "DELETE FROM paused_locations WHERE profile_id = ?1 AND location = ?2",
+            rusqlite::params![profile_id, location.as_str()],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    pub fn get_paused_locations(conn: &Connection, profile_id: i64) -> Result<Vec<PausedLocation>> {
+        let mut stmt = conn.prepare(
+            "SELECT profile_id, location, reason, paused_by, paused_at
+             FROM paused_locations WHERE profile_id = ?1 ORDER BY paused_at"
+        )?;
+
+        let paused = stmt.query_map([profile_id], |row| {
+            Ok(PausedLocation {
+                profile_id: row.get(0)?,
+                location: row.get::<_, String>(1)?.parse().unwrap_or(FileLocation::Local),
+                reason: row.get(2)?,
+                paused_by: row.get(3)?,
+                paused_at: row.get::<_, String>(4)?.parse().unwrap(),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(paused)
+    }
+
+    pub fn insert_trashed_file(conn: &Connection, trashed: &TrashedFile) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO trashed_files (profile_id, file_path, location, handle, content_hash, size_bytes, deleted_at, run_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                trashed.profile_id,
+                trashed.file_path,
+                trashed.location.as_str(),
+                trashed.handle,
+                trashed.content_hash,
+                trashed.size_bytes,
+                trashed.deleted_at.to_rfc3339(),
+                trashed.run_id,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The copies of a profile's files in the trash, most recently deleted first.
+    pub fn get_trashed_files(conn: &Connection, profile_id: i64) -> Result<Vec<TrashedFile>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_path, location, handle, content_hash, size_bytes, deleted_at, run_id
+             FROM trashed_files WHERE profile_id = ?1 ORDER BY deleted_at DESC, id DESC"
+        )?;
+
+        let trashed = stmt.query_map([profile_id], |row| {
+            Ok(TrashedFile {
+                id: Some(row.get(0)?),
+                profile_id: row.get(1)?,
+                file_path: row.get(2)?,
+                location: row.get::<_, String>(3)?.parse().unwrap_or(FileLocation::Local),
+                handle: row.get(4)?,
+                content_hash: row.get(5)?,
+                size_bytes: row.get(6)?,
+                deleted_at: row.get::<_, String>(7)?.parse().unwrap(),
+                run_id: row.get(8)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(trashed)
+    }
+
+    /// Forget a trashed copy once it was restored or deleted for good.
+    pub fn delete_trashed_file(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM trashed_files WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    pub fn delete_snapshot(conn: &Connection, snapshot_id: i64) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM snapshot_files WHERE snapshot_id = ?1", [snapshot_id])?;
+        tx.execute("DELETE FROM snapshots WHERE id = ?1", [snapshot_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn file_timing_from_row(row: &rusqlite::Row) -> rusqlite::Result<FileTiming> {
+        Ok(FileTiming {
+            id: Some(row.get(0)?),
+            profile_id: row.get(1)?,
+            run_id: row.get(2)?,
+            file_path: row.get(3)?,
+            source: row.get::<_, String>(4)?.parse().unwrap_or(FileLocation::Local),
+            destination: row.get::<_, String>(5)?.parse().unwrap_or(FileLocation::Local),
+            size_bytes: row.get(6)?,
+            download_ms: row.get(7)?,
+            hash_ms: row.get(8)?,
+            upload_ms: row.get(9)?,
+            total_ms: row.get(10)?,
+            recorded_at: row.get::<_, String>(11)?.parse().unwrap(),
+        })
+    }
+
+    pub fn get_sync_run(conn: &Connection, run_id: &str) -> Result<Option<SyncRun>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, run_id, started_at, completed_at, status, files_synced, files_failed, error_message, phase
+             FROM sync_history WHERE run_id = ?1"
+        )?;
+
+        Ok(stmt.query_row([run_id], Self::sync_run_from_row).optional()?)
+    }
+
+    /// Up to `limit` runs of a profile, newest first, skipping the `offset` newest.
+    pub fn get_sync_runs(conn: &Connection, profile_id: i64, limit: usize, offset: usize) -> Result<Vec<SyncRun>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, run_id, started_at, completed_at, status, files_synced, files_failed, error_message, phase
+             FROM sync_history WHERE profile_id = ?1 AND run_id IS NOT NULL
+             ORDER BY started_at DESC, id DESC LIMIT ?2 OFFSET ?3"
+        )?;
+
+        let runs = stmt.query_map(rusqlite::params![profile_id, limit as i64, offset as i64], Self::sync_run_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(runs)
+    }
+
+    pub fn count_sync_runs(conn: &Connection, profile_id: i64) -> Result<usize> {
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sync_history WHERE profile_id = ?1 AND run_id IS NOT NULL",
+            [profile_id],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    fn sync_run_from_row(row: &rusqlite::Row) -> rusqlite::Result<SyncRun> {
+        Ok(SyncRun {
+            id: Some(row.get(0)?),
+            profile_id: row.get(1)?,
+            run_id: row.get(2)?,
+            started_at: row.get::<_, String>(3)?.parse().unwrap(),
+            completed_at: row.get::<_, Option<String>>(4)?.and_then(|s| s.parse().ok()),
+            status: row.get::<_, String>(5)?.parse().unwrap_or(RunStatus::Failed),
+            files_synced: row.get(6)?,
+            files_failed: row.get(7)?,
+            error_message: row.get(8)?,
+            phase: row.get::<_, Option<String>>(9)?.and_then(|s| s.parse().ok()),
+        })
+    }
+
     // Conflict operations
     pub fn create_conflict(conn: &Connection, conflict: &Conflict) -> Result<i64> {
         conn.execute(
@@ -138,4 +1001,486 @@ impl DbOperations {
         )?;
         Ok(conn.last_insert_rowid())
     }
+
+    /// Replace the copies recorded for conflict `id` with those of a newer
+    /// detection of it.
+    pub fn refresh_conflict(conn: &Connection, id: i64, conflict: &Conflict) -> Result<()> {
+        conn.execute(
+            "UPDATE conflicts SET local_hash = ?2, gdrive_hash = ?3, smb_hash = ?4,
+                                  local_modified = ?5, gdrive_modified = ?6, smb_modified = ?7,
+                                  local_size = ?8, gdrive_size = ?9, smb_size = ?10
+             WHERE id = ?1",
+            rusqlite::params![
+                id,
+                conflict.local_hash,
+                conflict.gdrive_hash,
+                conflict.smb_hash,
+                conflict.local_modified.map(|dt| dt.to_rfc3339()),
+                conflict.gdrive_modified.map(|dt| dt.to_rfc3339()),
+                conflict.smb_modified.map(|dt| dt.to_rfc3339()),
+                conflict.local_size,
+                conflict.gdrive_size,
+                conflict.smb_size,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The conflicts of a profile, resolved ones included, oldest first.
+    pub fn get_conflicts(conn: &Connection, profile_id: i64) -> Result<Vec<Conflict>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_path, detected_at, resolved, resolution,
+                    local_hash, gdrive_hash, smb_hash,
+                    local_modified, gdrive_modified, smb_modified,
+                    local_size, gdrive_size, smb_size
+             FROM conflicts WHERE profile_id = ?1 ORDER BY detected_at, id"
+        )?;
+
+        let timestamp = |value: Option<String>| value.and_then(|s| s.parse().ok());
+        let conflicts = stmt.query_map([profile_id], |row| {
+            Ok(Conflict {
+                id: Some(row.get(0)?),
+                profile_id: row.get(1)?,
+                file_path: row.get(2)?,
+                detected_at: row.get::<_, String>(3)?.parse().unwrap(),
+                resolved: row.get::<_, Option<bool>>(4)?.unwrap_or(false),
+                resolution: row.get::<_, Option<String>>(5)?.and_then(|r| ConflictResolution::from_str(&r)),
+                local_hash: row.get(6)?,
+                gdrive_hash: row.get(7)?,
+                smb_hash: row.get(8)?,
+                local_modified: timestamp(row.get(9)?),
+                gdrive_modified: timestamp(row.get(10)?),
+                smb_modified: timestamp(row.get(11)?),
+                local_size: row.get(12)?,
+                gdrive_size: row.get(13)?,
+                smb_size: row.get(14)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(conflicts)
+    }
+
+    /// Mark the unresolved conflicts on `file_path` resolved with `resolution`.
+    /// Returns how many there were.
+    pub fn resolve_conflicts(conn: &Connection, profile_id: i64, file_path: &str, resolution: &ConflictResolution) -> Result<usize> {
+        Ok(conn.execute(
+            "UPDATE conflicts SET resolved = TRUE, resolution = ?3
+             WHERE profile_id = ?1 AND file_path = ?2 AND resolved = FALSE",
+            rusqlite::params![profile_id, file_path, resolution.as_str()],
+        )?)
+    }
+
+    // File reference (dependency graph) operations
+    pub fn get_file_references(conn: &Connection, profile_id: i64) -> Result<Vec<FileReference>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, parent_path, parent_hash, reference, resolved_path
+             FROM file_references WHERE profile_id = ?1"
+        )?;
+
+        let references = stmt.query_map([profile_id], |row| {
+            Ok(FileReference {
+                id: Some(row.get(0)?),
+                profile_id: row.get(1)?,
+                parent_path: row.get(2)?,
+                parent_hash: row.get(3)?,
+                reference: row.get(4)?,
+                resolved_path: row.get(5)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(references)
+    }
+
+    /// Replace all references recorded for one parent file.
+    pub fn replace_file_references(
+        conn: &Connection,
+        profile_id: i64,
+        parent_path: &str,
+        references: &[FileReference],
+    ) -> Result<()> {
+        conn.execute(
+            "DELETE FROM file_references WHERE profile_id = ?1 AND parent_path = ?2",
+            rusqlite::params![profile_id, parent_path],
+        )?;
+
+        for reference in references {
+            conn.execute(
+                "INSERT OR IGNORE INTO file_references (profile_id, parent_path, parent_hash, reference, resolved_path)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    profile_id,
+                    parent_path,
+                    reference.parent_hash,
+                    reference.reference,
+                    reference.resolved_path,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    // Inbox delivery log operations
+    pub fn insert_inbox_entry(conn: &Connection, entry: &InboxEntry) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO inbox_log (profile_id, file_name, content_hash, size_bytes, status, message,
+                                    target_path, received_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                entry.profile_id,
+                entry.file_name,
+                entry.content_hash,
+                entry.size_bytes,
+                entry.status.as_str(),
+                entry.message,
+                entry.target_path,
+                entry.received_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Most recent inbox deliveries first.
+    pub fn get_inbox_entries(conn: &Connection, profile_id: i64, limit: usize) -> Result<Vec<InboxEntry>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_name, content_hash, size_bytes, status, message, target_path, received_at
+             FROM inbox_log WHERE profile_id = ?1 ORDER BY received_at DESC, id DESC LIMIT ?2"
+        )?;
+
+        let entries = stmt.query_map(rusqlite::params![profile_id, limit as i64], |row| {
+            Ok(InboxEntry {
+                id: Some(row.get(0)?),
+                profile_id: row.get(1)?,
+                file_name: row.get(2)?,
+                content_hash: row.get(3)?,
+                size_bytes: row.get(4)?,
+                status: row.get::<_, String>(5)?.parse().unwrap_or(InboxStatus::Rejected),
+                message: row.get(6)?,
+                target_path: row.get(7)?,
+                received_at: row.get::<_, String>(8)?.parse().unwrap(),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Whether a delivery with this content hash was already accepted.
+    pub fn inbox_hash_accepted(conn: &Connection, profile_id: i64, content_hash: &str) -> Result<bool> {
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM inbox_log WHERE profile_id = ?1 AND content_hash = ?2 AND status = ?3",
+            rusqlite::params![profile_id, content_hash, InboxStatus::Accepted.as_str()],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    // Part revision history operations
+    /// Record a revision the first time it is seen; later sightings only refresh
+    /// the file path and hash. Returns true if the revision was new.
+    pub fn record_part_revision(conn: &Connection, revision: &PartRevision) -> Result<bool> {
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO part_revisions (profile_id, base_part, revision, file_path, content_hash, first_seen_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                revision.profile_id,
+                revision.base_part,
+                revision.revision,
+                revision.file_path,
+                revision.content_hash,
+                revision.first_seen_at.to_rfc3339(),
+            ],
+        )?;
+
+        if inserted == 0 {
+            conn.execute(
+                "UPDATE part_revisions SET file_path = ?1, content_hash = ?2
+                 WHERE profile_id = ?3 AND base_part = ?4 AND revision = ?5",
+                rusqlite::params![
+                    revision.file_path,
+                    revision.content_hash,
+                    revision.profile_id,
+                    revision.base_part,
+                    revision.revision,
+                ],
+            )?;
+        }
+        Ok(inserted > 0)
+    }
+
+    pub fn get_part_revisions(conn: &Connection, profile_id: i64) -> Result<Vec<PartRevision>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, base_part, revision, file_path, content_hash, first_seen_at
+             FROM part_revisions WHERE profile_id = ?1 ORDER BY base_part, first_seen_at"
+        )?;
+
+        let revisions = stmt.query_map([profile_id], |row| {
+            Ok(PartRevision {
+                id: Some(row.get(0)?),
+                profile_id: row.get(1)?,
+                base_part: row.get(2)?,
+                revision: row.get(3)?,
+                file_path: row.get(4)?,
+                content_hash: row.get(5)?,
+                first_seen_at: row.get::<_, String>(6)?.parse().unwrap(),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(revisions)
+    }
+
+    // Transfer queue operations
+    /// Queue a transfer at the end of the queue. A failed transfer of the same file
+    /// between the same locations is re-queued in place instead of duplicated.
+    pub fn enqueue_transfer(conn: &Connection, transfer: &Transfer) -> Result<i64> {
+        let existing: Option<i64> = conn.query_row(
+            "SELECT id FROM transfers
+             WHERE profile_id = ?1 AND file_path = ?2 AND source IS ?3 AND destination = ?4 AND status = ?5
+               AND moved_from IS ?6",
+            rusqlite::params![
+                transfer.profile_id,
+                transfer.file_path,
+                transfer.source.as_ref().map(|l| l.as_str()),
+                transfer.destination.as_str(),
+                TransferStatus::Failed.as_str(),
+                transfer.moved_from,
+            ],
+            |row| row.get(0),
+        ).optional()?;
+
+        if let Some(id) = existing {
+            Self::update_transfer_status(conn, id, &TransferStatus::Queued, None)?;
+            conn.execute(
+                "UPDATE transfers SET expected_hash = ?1, expect_absent = ?2, reason = ?3, expected_size = ?4,
+                                      expected_modified = ?5
+                 WHERE id = ?6",
+                rusqlite::params![
+                    transfer.expected_hash,
+                    transfer.expect_absent,
+                    to_json(&transfer.reason)?,
+                    transfer.expected_size,
+                    transfer.expected_modified.map(|t| t.to_rfc3339()),
+                    id,
+                ],
+            )?;
+            return Ok(id);
+        }
+
+        conn.execute(
+            "INSERT INTO transfers (profile_id, file_path, source, destination, status, priority, attempts,
+                                    size_bytes, error, created_at, updated_at, expected_hash, expect_absent, reason,
+                                    moved_from, expected_size, expected_modified)
+             VALUES (?1, ?2, ?3, ?4, ?5,
+                     (SELECT COALESCE(MAX(priority), 0) + 1 FROM transfers WHERE profile_id = ?1),
+                     0, ?6, NULL, ?7, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            rusqlite::params![
+                transfer.profile_id,
+                transfer.file_path,
+                transfer.source.as_ref().map(|l| l.as_str()),
+                transfer.destination.as_str(),
+                transfer.status.as_str(),
+                transfer.size_bytes,
+                transfer.created_at.to_rfc3339(),
+                transfer.expected_hash,
+                transfer.expect_absent,
+                to_json(&transfer.reason)?,
+                transfer.moved_from,
+                transfer.expected_size,
+                transfer.expected_modified.map(|t| t.to_rfc3339()),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// All queued, running and failed transfers in queue order.
+    pub fn get_transfers(conn: &Connection, profile_id: i64) -> Result<Vec<Transfer>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_path, source, destination, status, priority, attempts,
+                    size_bytes, error, created_at, updated_at, expected_hash, expect_absent, reason, moved_from,
+                    expected_size, expected_modified
+             FROM transfers WHERE profile_id = ?1 ORDER BY priority, id"
+        )?;
+
+        let transfers = stmt.query_map([profile_id], Self::transfer_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(transfers)
+    }
+
+    /// Up to `limit` queued transfers in queue order, starting after the queue
+    /// position `after` (priority, id), and only those to `destination` when given.
+    pub fn get_queued_transfers(
+        conn: &Connection,
+        profile_id: i64,
+        destination: Option<&FileLocation>,
+        after: Option<(i64, i64)>,
+        limit: usize,
+    ) -> Result<Vec<Transfer>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, profile_id, file_path, source, destination, status, priority, attempts,
+                    size_bytes, error, created_at, updated_at, expected_hash, expect_absent, reason, moved_from,
+                    expected_size, expected_modified
+             FROM transfers
+             WHERE profile_id = ?1 AND status = ?2 AND (?3 IS NULL OR destination = ?3)
+               AND (?4 IS NULL OR (priority, id) > (?4, ?5))
+             ORDER BY priority, id LIMIT ?6"
+        )?;
+
+        let transfers = stmt.query_map(
+            rusqlite::params![
+                profile_id,
+                TransferStatus::Queued.as_str(),
+                destination.map(|l| l.as_str()),
+                after.map(|(priority, _)| priority),
+                after.map(|(_, id)| id),
+                limit as i64,
+            ],
+            Self::transfer_from_row,
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(transfers)
+    }
+
+    pub fn get_transfer(conn: &Connection, id: i64) -> Result<Option<Transfer>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, file_path, source, destination, status, priority, attempts,
+                    size_bytes, error, created_at, updated_at, expected_hash, expect_absent, reason, moved_from,
+                    expected_size, expected_modified
+             FROM transfers WHERE id = ?1"
+        )?;
+
+        Ok(stmt.query_row([id], Self::transfer_from_row).optional()?)
+    }
+
+    fn transfer_from_row(row: &rusqlite::Row) -> rusqlite::Result<Transfer> {
+        Ok(Transfer {
+            id: Some(row.get(0)?),
+            profile_id: row.get(1)?,
+            file_path: row.get(2)?,
+            source: row.get::<_, Option<String>>(3)?.map(|s| Self::parse_location(3, &s)).transpose()?,
+            destination: Self::parse_location(4, &row.get::<_, String>(4)?)?,
+            moved_from: row.get(15)?,
+            status: row.get::<_, String>(5)?.parse().unwrap_or(TransferStatus::Failed),
+            priority: row.get(6)?,
+            attempts: row.get(7)?,
+            size_bytes: row.get(8)?,
+            expected_hash: row.get(12)?,
+            expect_absent: row.get(13)?,
+            expected_size: row.get(16)?,
+            expected_modified: row.get::<_, Option<String>>(17)?.and_then(|s| s.parse().ok()),
+            reason: from_json(row.get(14)?),
+            error: row.get(9)?,
+            created_at: row.get::<_, String>(10)?.parse().unwrap(),
+            updated_at: row.get::<_, String>(11)?.parse().unwrap(),
+        })
+    }
+
+    /// A transfer's location column. An unknown value is an error rather than a
+    /// default, which would copy or delete at the wrong location.
+    fn parse_location(column: usize, value: &str) -> rusqlite::Result<FileLocation> {
+        value.parse().map_err(|e: String| {
+            rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.into())
+        })
+    }
+
+    /// Set a transfer's status. Moving to in-progress counts an attempt.
+    pub fn update_transfer_status(conn: &Connection, id: i64, status: &TransferStatus, error: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE transfers SET status = ?1, error = ?2, updated_at = ?3, attempts = attempts + ?4
+             WHERE id = ?5",
+            rusqlite::params![
+                status.as_str(),
+                error,
+                chrono::Utc::now().to_rfc3339(),
+                (*status == TransferStatus::InProgress) as i64,
+                id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_transfer(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM transfers WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    pub fn set_transfer_priority(conn: &Connection, id: i64, priority: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE transfers SET priority = ?1 WHERE id = ?2",
+            rusqlite::params![priority, id],
+        )?;
+        Ok(())
+    }
+
+    // Sync plan operations
+    /// Store a plan awaiting approval. Older pending plans of the profile are
+    /// superseded, since the new plan reflects the current state of the files.
+    pub fn create_sync_plan(conn: &Connection, plan: &SyncPlan) -> Result<i64> {
+        conn.execute(
+            "UPDATE sync_plans SET status = ?1 WHERE profile_id = ?2 AND status = ?3",
+            rusqlite::params![PlanStatus::Superseded.as_str(), plan.profile_id, PlanStatus::Pending.as_str()],
+        )?;
+        conn.execute(
+            "INSERT INTO sync_plans (profile_id, steps, status, created_at, decided_by, decided_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                plan.profile_id,
+                serde_json::to_string(&plan.steps)?,
+                plan.status.as_str(),
+                plan.created_at.to_rfc3339(),
+                plan.decided_by,
+                plan.decided_at.map(|t| t.to_rfc3339()),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_sync_plan(conn: &Connection, id: i64) -> Result<Option<SyncPlan>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, steps, status, created_at, decided_by, decided_at
+             FROM sync_plans WHERE id = ?1"
+        )?;
+
+        Ok(stmt.query_row([id], Self::sync_plan_from_row).optional()?)
+    }
+
+    /// Plans of the profile awaiting approval, newest first.
+    pub fn get_pending_sync_plans(conn: &Connection, profile_id: i64) -> Result<Vec<SyncPlan>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, steps, status, created_at, decided_by, decided_at
+             FROM sync_plans WHERE profile_id = ?1 AND status = ?2 ORDER BY id DESC"
+        )?;
+
+        let plans = stmt.query_map(rusqlite::params![profile_id, PlanStatus::Pending.as_str()], Self::sync_plan_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(plans)
+    }
+
+    fn sync_plan_from_row(row: &rusqlite::Row) -> rusqlite::Result<SyncPlan> {
+        Ok(SyncPlan {
+            id: Some(row.get(0)?),
+            profile_id: row.get(1)?,
+            steps: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+            status: row.get::<_, String>(3)?.parse().unwrap_or(PlanStatus::Superseded),
+            created_at: row.get::<_, String>(4)?.parse().unwrap(),
+            decided_by: row.get(5)?,
+            decided_at: row.get::<_, Option<String>>(6)?.and_then(|s| s.parse().ok()),
+        })
+    }
+
+    /// Record a decision on a plan (or its execution) and who made it.
+    pub fn update_sync_plan_status(conn: &Connection, id: i64, status: &PlanStatus, decided_by: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE sync_plans SET status = ?1,
+                                   decided_by = COALESCE(?2, decided_by),
+                                   decided_at = CASE WHEN ?2 IS NULL THEN decided_at ELSE ?3 END
+             WHERE id = ?4",
+            rusqlite::params![status.as_str(), decided_by, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
 }
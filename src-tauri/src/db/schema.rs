@@ -1,3 +1,4 @@
+use crate::db::migrations::Migrations;
 use crate::utils::error::Result;
 use directories::ProjectDirs;
 use rusqlite::Connection;
@@ -21,6 +22,11 @@ impl Database {
         Ok(Self { conn })
     }
 
+    /// A throwaway database for simulations and tests.
+    pub fn in_memory() -> Result<Self> {
+        Ok(Self { conn: Connection::open_in_memory()? })
+    }
+
     fn get_db_path() -> Result<PathBuf> {
         let project_dirs = ProjectDirs::from("com", "uvcad", "UVCAD")
             .ok_or_else(|| crate::utils::error::UvcadError::InvalidConfig(
@@ -33,6 +39,7 @@ impl Database {
 
     pub fn initialize(&self) -> Result<()> {
         self.create_tables()?;
+        Migrations::run(&self.conn)?;
         Ok(())
     }
 
@@ -121,6 +128,128 @@ impl Database {
             [],
         )?;
 
+        // Assembly/xref dependency graph
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_references (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                parent_path TEXT NOT NULL,
+                parent_hash TEXT,
+                reference TEXT NOT NULL,
+                resolved_path TEXT,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id),
+                UNIQUE(profile_id, parent_path, reference)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_file_references_profile
+             ON file_references(profile_id)",
+            [],
+        )?;
+
+        // Supplier inbox delivery log
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS inbox_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                file_name TEXT NOT NULL,
+                content_hash TEXT,
+                size_bytes INTEGER,
+                status TEXT NOT NULL,
+                message TEXT,
+                target_path TEXT,
+                received_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_inbox_log_profile
+             ON inbox_log(profile_id)",
+            [],
+        )?;
+
+        // Revision history per base part number
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS part_revisions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                base_part TEXT NOT NULL COLLATE NOCASE,
+                revision TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                content_hash TEXT,
+                first_seen_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id),
+                UNIQUE(profile_id, base_part, revision)
+            )",
+            [],
+        )?;
+
+        // Transfer queue (pending, in-flight and failed file operations)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS transfers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                file_path TEXT NOT NULL,
+                source TEXT,
+                destination TEXT NOT NULL,
+                status TEXT NOT NULL,
+                priority INTEGER NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                size_bytes INTEGER,
+                error TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_transfers_profile
+             ON transfers(profile_id, priority)",
+            [],
+        )?;
+
+        // Change history of file states, for "what changed since" queries
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                file_path TEXT NOT NULL,
+                location TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                content_hash TEXT,
+                recorded_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_file_events_profile_time
+             ON file_events(profile_id, recorded_at)",
+            [],
+        )?;
+
+        // Sync plans held for approval, and what became of them
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS sync_plans (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                steps TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                decided_by TEXT,
+                decided_at TEXT,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
         // OAuth tokens table
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS oauth_tokens (
@@ -134,6 +263,131 @@ impl Database {
             [],
         )?;
 
+        // Time spent on each part of every file transfer
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_timings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                run_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                source TEXT NOT NULL,
+                destination TEXT NOT NULL,
+                size_bytes INTEGER,
+                download_ms INTEGER NOT NULL,
+                hash_ms INTEGER NOT NULL,
+                upload_ms INTEGER NOT NULL,
+                total_ms INTEGER NOT NULL,
+                recorded_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_file_timings_run
+             ON file_timings(run_id, total_ms)",
+            [],
+        )?;
+
+        // Folders each user opens, to sync their working set first
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS folder_usage (
+                profile_id INTEGER NOT NULL,
+                user_name TEXT NOT NULL,
+                folder TEXT NOT NULL,
+                opens INTEGER NOT NULL,
+                last_opened_at TEXT NOT NULL,
+                PRIMARY KEY (profile_id, user_name, folder),
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        // Labelled snapshots of the file manifest
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                label TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                created_by TEXT NOT NULL,
+                UNIQUE (profile_id, label),
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        // Files of each snapshot
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS snapshot_files (
+                snapshot_id INTEGER NOT NULL,
+                file_path TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                size_bytes INTEGER,
+                modified_at TEXT,
+                PRIMARY KEY (snapshot_id, file_path),
+                FOREIGN KEY (snapshot_id) REFERENCES snapshots(id)
+            )",
+            [],
+        )?;
+
+        // Legal holds on files and folders
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS legal_holds (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                placed_by TEXT NOT NULL,
+                placed_at TEXT NOT NULL,
+                released_by TEXT,
+                released_at TEXT,
+                release_reason TEXT,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        // Locations paused per profile
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS paused_locations (
+                profile_id INTEGER NOT NULL,
+                location TEXT NOT NULL,
+                reason TEXT,
+                paused_by TEXT NOT NULL,
+                paused_at TEXT NOT NULL,
+                PRIMARY KEY (profile_id, location),
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        // Files moved into a location's trash by soft deletion
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS trashed_files (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                file_path TEXT NOT NULL,
+                location TEXT NOT NULL,
+                handle TEXT NOT NULL,
+                content_hash TEXT,
+                size_bytes INTEGER,
+                deleted_at TEXT NOT NULL,
+                run_id TEXT,
+                FOREIGN KEY (profile_id) REFERENCES sync_profiles(id)
+            )",
+            [],
+        )?;
+
+        // Settings of the whole app, such as the update channel
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_settings (
+                name TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         Ok(())
     }
 
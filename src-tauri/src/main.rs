@@ -1,7 +1,7 @@
 // Prevents additional console window on Windows in release mode
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tauri::Manager;
 
 mod commands;
 mod core;
@@ -12,26 +12,161 @@ mod utils;
 
 fn main() {
     // Initialize logging
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
-        .init();
+    utils::logging::init();
 
     tracing::info!("Starting UVCAD application...");
 
     tauri::Builder::default()
+        .setup(|app| {
+            commands::notifications::set_app(app.handle());
+            if let Err(e) = tauri::async_runtime::block_on(commands::config::apply_profile_settings()) {
+                tracing::error!("{}", e);
+                // Still honour UVCAD_SECRET_STORE when the profile can't be read
+                let _ = utils::secrets::select_backend(None);
+            }
+            tauri::async_runtime::spawn(commands::managed_policy::refresh_at_startup());
+            tauri::async_runtime::spawn(commands::inbox::watch_inbox(app.handle()));
+            tauri::async_runtime::spawn(commands::updates::notify_if_update_available(app.handle()));
+
+            // Opening a .uvcadstub placeholder with UVCAD restores the original file
+            for arg in std::env::args().skip(1).filter(|a| core::stubs::is_stub(std::path::Path::new(a))) {
+                let handle = app.handle();
+                tauri::async_runtime::spawn(async move {
+                    match commands::tiering::restore_offloaded(arg).await {
+                        Ok(path) => {
+                            commands::notifications::notify(core::notifications::Notification::new(
+                                core::notifications::NotificationKind::FileRestored,
+                                core::notifications::Severity::Info,
+                                utils::i18n::tr("notification.file_restored", &[("path", path.clone())]),
+                            ));
+                            let _ = handle.emit_all("file-restored", path);
+                        }
+                        Err(e) => tracing::error!("Failed to restore placeholder: {}", e),
+                    }
+                });
+            }
+
+            // The file manager actions start UVCAD with a uvcad:// link
+            for url in std::env::args().skip(1).filter(|a| core::shell_integration::is_deep_link(a)) {
+                let handle = app.handle();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = commands::shell_integration::handle_deep_link(handle, url).await {
+                        tracing::error!("Failed to handle link: {}", e);
+                    }
+                });
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::sync::start_sync,
+            commands::sync::cancel_sync,
+            commands::sync::sync_files,
+            commands::sync::estimate_sync,
+            commands::sync::get_current_plan,
             commands::sync::pull_from_gdrive,
             commands::sync::get_sync_status,
             commands::sync::get_file_list,
+            commands::sync::get_conflicts,
             commands::sync::resolve_conflict,
+            commands::sync::resolve_conflict_preview,
+            commands::watcher::start_watcher,
+            commands::watcher::stop_watcher,
+            commands::watcher::get_watcher_status,
             commands::auth::google_auth,
             commands::auth::get_auth_status,
             commands::auth::logout,
+            commands::auth::set_service_account_key,
+            commands::auth::remove_service_account_key,
             commands::config::get_config,
             commands::config::update_config,
             commands::config::test_smb_connection,
+            commands::config::discover_smb_mounts,
+            commands::config::discover_file_servers,
+            commands::config::get_message_catalog,
+            commands::config::get_sync_policy,
+            commands::config::set_sync_policy,
+            commands::managed_policy::get_managed_policy,
+            commands::managed_policy::refresh_managed_policy,
+            commands::config::relocate_local_root,
+            commands::profiles::list_profiles,
+            commands::profiles::create_profile,
+            commands::profiles::set_active_profile,
+            commands::profiles::delete_profile,
+            commands::duplicates::dedupe_remote,
+            commands::history::get_sync_run,
+            commands::history::get_sync_history,
+            commands::history::get_slowest_files,
+            commands::reports::get_large_files,
+            commands::reports::get_size_distribution,
+            commands::reports::get_stale_files,
+            commands::reports::get_working_set,
+            commands::diagnostics::set_log_level,
+            commands::diagnostics::enable_debug_for_module,
+            commands::diagnostics::start_http_capture,
+            commands::diagnostics::stop_http_capture,
+            commands::diagnostics::get_http_capture,
+            commands::diagnostics::simulate_outage,
+            commands::diagnostics::run_soak_test,
+            commands::diagnostics::stop_soak_test,
+            commands::notifications::send_test_notification,
+            commands::webdav::get_webdav_url,
+            commands::media::export_to_media,
+            commands::media::import_from_media,
+            commands::config::get_signing_key,
+            commands::encryption::export_drive_key,
+            commands::encryption::import_drive_key,
+            commands::encryption::rotate_drive_key,
+            commands::archive::get_archive_contents,
+            commands::archive::search_archives,
+            commands::references::check_references,
+            commands::package::export_package,
+            commands::inbox::process_inbox,
+            commands::inbox::get_inbox_log,
+            commands::naming::get_naming_violations,
+            commands::revisions::get_part_revisions,
+            commands::templates::create_project_from_template,
+            commands::tiering::preview_tiering,
+            commands::tiering::apply_tiering,
+            commands::tiering::restore_offloaded,
+            commands::transfers::get_transfer_queue,
+            commands::transfers::reorder_transfer,
+            commands::transfers::cancel_transfer,
+            commands::transfers::retry_transfer,
+            commands::transfers::start_initial_upload,
+            commands::transfers::deprioritize_folder,
+            commands::trash::list_deleted_files,
+            commands::trash::restore_deleted,
+            commands::changes::get_changes_since,
+            commands::changes::invalidate_scan_cache,
+            commands::compare::compare_files,
+            commands::previews::get_thumbnail,
+            commands::previews::get_mesh_info,
+            commands::publishing::publish_drawings,
+            commands::release::publish,
+            commands::simulation::simulate_sync,
+            commands::snapshots::create_snapshot,
+            commands::snapshots::list_snapshots,
+            commands::snapshots::compare_snapshots,
+            commands::snapshots::restore_snapshot,
+            commands::snapshots::delete_snapshot,
+            commands::plans::get_pending_plans,
+            commands::plans::approve_plan,
+            commands::plans::reject_plan,
+            commands::roles::get_role,
+            commands::roles::set_role,
+            commands::legal_holds::place_legal_hold,
+            commands::legal_holds::release_legal_hold,
+            commands::legal_holds::list_legal_holds,
+            commands::shell_integration::register_shell_integration,
+            commands::shell_integration::unregister_shell_integration,
+            commands::shell_integration::handle_deep_link,
+            commands::shortcuts::open_in_drive,
+            commands::shortcuts::open_on_nas,
+            commands::pauses::pause_provider,
+            commands::pauses::resume_provider,
+            commands::pauses::list_paused_providers,
+            commands::updates::check_for_update,
+            commands::updates::install_update,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,85 @@
+use crate::models::file_state::FileLocation;
+use crate::models::sync_plan::OperationReason;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FileEventKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl FileEventKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FileEventKind::Added => "added",
+            FileEventKind::Modified => "modified",
+            FileEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+impl FromStr for FileEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(FileEventKind::Added),
+            "modified" => Ok(FileEventKind::Modified),
+            "deleted" => Ok(FileEventKind::Deleted),
+            _ => Err(format!("Invalid file event kind: {}", s)),
+        }
+    }
+}
+
+/// A change to a file at one location, recorded when its state is saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEvent {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub file_path: String,
+    pub location: FileLocation,
+    pub kind: FileEventKind,
+    pub content_hash: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+    /// The share that served the change, when the location has fallbacks.
+    pub endpoint: Option<String>,
+    /// The sync run that recorded the change, if any.
+    pub run_id: Option<String>,
+    /// Why a sync made the change; None for changes found by a scan.
+    pub reason: Option<OperationReason>,
+}
+
+impl FileEvent {
+    pub fn new(profile_id: i64, file_path: String, location: FileLocation, kind: FileEventKind, content_hash: Option<String>) -> Self {
+        Self {
+            id: None,
+            profile_id,
+            file_path,
+            location,
+            kind,
+            content_hash,
+            recorded_at: Utc::now(),
+            endpoint: None,
+            run_id: None,
+            reason: None,
+        }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: Option<String>) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    pub fn with_run_id(mut self, run_id: &str) -> Self {
+        self.run_id = Some(run_id.to_string());
+        self
+    }
+
+    pub fn with_reason(mut self, reason: Option<OperationReason>) -> Self {
+        self.reason = reason;
+        self
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// A reference from an assembly/drawing to another file (component, xref).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReference {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    /// Relative path of the referencing file (the assembly).
+    pub parent_path: String,
+    /// Content hash of the parent the reference was extracted from.
+    pub parent_hash: Option<String>,
+    /// Reference exactly as stored in the file (often an absolute path from the author's machine).
+    pub reference: String,
+    /// Relative path within the profile the reference resolved to, if any.
+    pub resolved_path: Option<String>,
+}
@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum FileLocation {
     Local,
     GoogleDrive,
@@ -48,6 +48,8 @@ pub enum SyncStatus {
     Deleted,
     Conflict,
     Pending,
+    /// Removed locally by a tiering rule; the content lives in the archive.
+    Offloaded,
 }
 
 impl SyncStatus {
@@ -58,6 +60,7 @@ impl SyncStatus {
             SyncStatus::Deleted => "deleted",
             SyncStatus::Conflict => "conflict",
             SyncStatus::Pending => "pending",
+            SyncStatus::Offloaded => "offloaded",
         }
     }
 
@@ -68,6 +71,7 @@ impl SyncStatus {
             "deleted" => Some(SyncStatus::Deleted),
             "conflict" => Some(SyncStatus::Conflict),
             "pending" => Some(SyncStatus::Pending),
+            "offloaded" => Some(SyncStatus::Offloaded),
             _ => None,
         }
     }
@@ -83,6 +87,7 @@ impl FromStr for SyncStatus {
             "deleted" => Ok(SyncStatus::Deleted),
             "conflict" => Ok(SyncStatus::Conflict),
             "pending" => Ok(SyncStatus::Pending),
+            "offloaded" => Ok(SyncStatus::Offloaded),
             _ => Err(format!("Invalid sync status: {}", s)),
         }
     }
@@ -95,6 +100,9 @@ pub struct FileState {
     pub file_path: String,
     pub location: FileLocation,
     pub content_hash: Option<String>,
+    /// Size, mtime and sample digest `content_hash` was computed from, so an
+    /// unchanged file can reuse it without being read in full.
+    pub hash_fingerprint: Option<String>,
     pub size_bytes: Option<i64>,
     pub modified_at: Option<DateTime<Utc>>,
     pub synced_at: Option<DateTime<Utc>>,
@@ -114,6 +122,7 @@ impl FileState {
             file_path,
             location,
             content_hash: None,
+            hash_fingerprint: None,
             size_bytes: None,
             modified_at: None,
             synced_at: None,
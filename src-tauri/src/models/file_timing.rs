@@ -0,0 +1,23 @@
+use crate::models::file_state::FileLocation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Where the time went while copying one file during a sync run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileTiming {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub run_id: String,
+    pub file_path: String,
+    pub source: FileLocation,
+    pub destination: FileLocation,
+    pub size_bytes: Option<i64>,
+    pub download_ms: i64,
+    /// Integrity hash of the downloaded copy.
+    pub hash_ms: i64,
+    /// Upload including the destination re-check and the provider's own verification.
+    pub upload_ms: i64,
+    /// The whole transfer, (de)compression and temp file cleanup included.
+    pub total_ms: i64,
+    pub recorded_at: DateTime<Utc>,
+}
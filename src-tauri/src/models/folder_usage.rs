@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How often a user opened files in one folder of a profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderUsage {
+    pub profile_id: i64,
+    pub user_name: String,
+    /// Relative to the profile's root; "" is the root itself.
+    pub folder: String,
+    pub opens: i64,
+    pub last_opened_at: DateTime<Utc>,
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum InboxStatus {
+    /// Validated and moved into its project folder.
+    Accepted,
+    /// Failed validation or matched no routing rule; left in the inbox's rejected folder.
+    Rejected,
+}
+
+impl InboxStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            InboxStatus::Accepted => "accepted",
+            InboxStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl FromStr for InboxStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accepted" => Ok(InboxStatus::Accepted),
+            "rejected" => Ok(InboxStatus::Rejected),
+            _ => Err(format!("Invalid inbox status: {}", s)),
+        }
+    }
+}
+
+/// One file received through the supplier inbox.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxEntry {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub file_name: String,
+    pub content_hash: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub status: InboxStatus,
+    /// Reason for rejection, or a note about the delivery.
+    pub message: Option<String>,
+    /// Relative path within the profile the file was moved to.
+    pub target_path: Option<String>,
+    pub received_at: DateTime<Utc>,
+}
@@ -1,3 +1,17 @@
 pub mod conflict;
+pub mod file_event;
+pub mod file_reference;
 pub mod file_state;
+pub mod file_timing;
+pub mod folder_usage;
+pub mod inbox_entry;
+pub mod legal_hold;
+pub mod part_revision;
+pub mod paused_location;
+pub mod snapshot;
+pub mod sync_phase;
+pub mod sync_plan;
 pub mod sync_profile;
+pub mod sync_run;
+pub mod transfer;
+pub mod trashed_file;
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One revision of a part number seen in the profile, parsed from file names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartRevision {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    /// File stem without the revision suffix.
+    pub base_part: String,
+    pub revision: String,
+    /// Relative path of the file carrying this revision.
+    pub file_path: String,
+    pub content_hash: Option<String>,
+    pub first_seen_at: DateTime<Utc>,
+}
@@ -0,0 +1,14 @@
+use crate::models::file_state::FileLocation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A location syncs of the profile leave alone until it is resumed, e.g. while
+/// its quota is exhausted. Changes to and from it wait in the meantime.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PausedLocation {
+    pub profile_id: i64,
+    pub location: FileLocation,
+    pub reason: Option<String>,
+    pub paused_by: String,
+    pub paused_at: DateTime<Utc>,
+}
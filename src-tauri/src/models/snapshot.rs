@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A labelled record of every file of a profile and its content at one moment,
+/// e.g. "As sent to customer 2024-06-01".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    /// Totals over the recorded files; filled in when listing.
+    #[serde(default)]
+    pub file_count: usize,
+    #[serde(default)]
+    pub total_bytes: u64,
+}
+
+/// One file of a snapshot. Only the hash is kept; the content itself stays
+/// wherever the sync put it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub file_path: String,
+    pub content_hash: String,
+    pub size_bytes: Option<i64>,
+    pub modified_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,48 @@
+use crate::models::file_state::FileLocation;
+use crate::utils::i18n;
+use serde::Serialize;
+
+/// What a sync is doing, reported with every progress event. Serialized as
+/// `{"phase": "transferring", "from": "Local", "to": "GoogleDrive"}` etc.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum SyncPhase {
+    Initializing,
+    /// Listing files at one location.
+    Scanning { provider: FileLocation },
+    /// Comparing the listings and deciding what to copy or delete.
+    Planning,
+    /// Copying a file between locations; `from` is `None` for a deletion at `to`.
+    Transferring { from: Option<FileLocation>, to: FileLocation },
+    /// Reading back a transferred file before its state is recorded.
+    Verifying,
+    /// Recording the new state of every file.
+    Finalizing,
+    Completed,
+}
+
+impl SyncPhase {
+    /// A sentence describing the phase in the current locale, for display and
+    /// screen readers. `file` is the file being worked on, if any.
+    pub fn describe(&self, file: &str) -> String {
+        let file = ("file", file.to_string());
+        match self {
+            SyncPhase::Initializing => i18n::tr("phase.initializing", &[]),
+            SyncPhase::Scanning { provider } => i18n::tr("phase.scanning", &[("provider", location_name(provider))]),
+            SyncPhase::Planning => i18n::tr("phase.planning", &[]),
+            SyncPhase::Transferring { from: Some(from), to } => i18n::tr("phase.transferring", &[
+                file,
+                ("from", location_name(from)),
+                ("to", location_name(to)),
+            ]),
+            SyncPhase::Transferring { from: None, to } => i18n::tr("phase.deleting", &[file, ("to", location_name(to))]),
+            SyncPhase::Verifying => i18n::tr("phase.verifying", &[file]),
+            SyncPhase::Finalizing => i18n::tr("phase.finalizing", &[]),
+            SyncPhase::Completed => i18n::tr("phase.completed", &[]),
+        }
+    }
+}
+
+fn location_name(location: &FileLocation) -> String {
+    i18n::tr(&format!("location.{}", location.as_str()), &[])
+}
@@ -0,0 +1,152 @@
+use crate::models::file_state::FileLocation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// One step of a sync plan. Steps are ordered by consistency group, path and
+/// operation, so the same inputs always produce the same indices.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub index: usize,
+    pub path: String,
+    #[serde(flatten)]
+    pub operation: PlannedOperation,
+    /// Bytes copied; 0 for deletions and conflicts.
+    #[serde(default)]
+    pub size_bytes: u64,
+    /// Why the step is planned; None in plans stored before reasons were recorded.
+    #[serde(default)]
+    pub reason: Option<ChangeReason>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum PlannedOperation {
+    Copy { from: FileLocation, to: FileLocation },
+    Delete { at: FileLocation },
+    /// The copy at `at` is moved here from `from`.
+    Move { at: FileLocation, from: String },
+    /// Left for the user to resolve.
+    Conflict,
+}
+
+/// The change since the last sync a step follows from. Serialized as
+/// `{"change": "deleted", "at": "GoogleDrive"}` etc.; the message catalog has a
+/// `reason.<change>` text for each.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ChangeReason {
+    Added { at: FileLocation },
+    Modified { at: FileLocation },
+    Deleted { at: FileLocation },
+    /// Moved or renamed from `from` at `at`.
+    Moved { at: FileLocation, from: String },
+    /// Changed to the same content at several locations; copied where it is missing.
+    SameChange,
+    /// Changed differently at several locations.
+    Conflicting,
+}
+
+/// What let an operation run. Serialized as `{"policy": "approved", "by": "alice"}`
+/// etc.; the message catalog has an `allowed_by.<policy>` text for each.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum Authorization {
+    /// The profile does not require plan approval.
+    NoApprovalRequired,
+    /// The operation type is in `auto_approved_operations`.
+    AutoApproved,
+    /// Part of a plan approved by `by`.
+    Approved { by: String },
+    /// Part of the initial upload of a new profile.
+    InitialUpload,
+    /// Carries out the resolution `by` chose for a conflict.
+    ConflictResolution { by: String },
+}
+
+/// Why an operation was carried out, kept with its transfer and the file events
+/// it records so it can be traced back later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationReason {
+    pub change: ChangeReason,
+    pub allowed_by: Authorization,
+}
+
+impl PlannedOperation {
+    /// The operation type, as named in `auto_approved_operations`.
+    pub fn kind(&self) -> &str {
+        match self {
+            PlannedOperation::Copy { .. } => "copy",
+            PlannedOperation::Delete { .. } => "delete",
+            PlannedOperation::Move { .. } => "move",
+            PlannedOperation::Conflict => "conflict",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PlanStatus {
+    /// Waiting for an approver.
+    Pending,
+    /// Approved; the next sync may carry it out.
+    Approved,
+    Rejected,
+    /// Replaced by a newer plan before anyone decided on it.
+    Superseded,
+    /// Carried out by an approved sync.
+    Executed,
+}
+
+impl PlanStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PlanStatus::Pending => "pending",
+            PlanStatus::Approved => "approved",
+            PlanStatus::Rejected => "rejected",
+            PlanStatus::Superseded => "superseded",
+            PlanStatus::Executed => "executed",
+        }
+    }
+}
+
+impl FromStr for PlanStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(PlanStatus::Pending),
+            "approved" => Ok(PlanStatus::Approved),
+            "rejected" => Ok(PlanStatus::Rejected),
+            "superseded" => Ok(PlanStatus::Superseded),
+            "executed" => Ok(PlanStatus::Executed),
+            _ => Err(format!("Invalid plan status: {}", s)),
+        }
+    }
+}
+
+/// A sync plan stored for approval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub steps: Vec<PlanStep>,
+    pub status: PlanStatus,
+    pub created_at: DateTime<Utc>,
+    /// Who approved or rejected the plan.
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl SyncPlan {
+    pub fn new(profile_id: i64, steps: Vec<PlanStep>) -> Self {
+        Self {
+            id: None,
+            profile_id,
+            steps,
+            status: PlanStatus::Pending,
+            created_at: Utc::now(),
+            decided_by: None,
+            decided_at: None,
+        }
+    }
+}
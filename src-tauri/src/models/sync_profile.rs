@@ -9,7 +9,132 @@ pub struct SyncProfile {
     pub gdrive_folder_id: Option<String>,
     pub smb_share_path: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// When the last sync ended, however it ended.
     pub last_sync_at: Option<DateTime<Utc>>,
+    /// When the last sync ended without errors.
+    pub last_successful_sync_at: Option<DateTime<Utc>>,
+    /// Why the last unsuccessful sync failed; cleared by the next successful one.
+    pub last_error: Option<String>,
+    /// Comma-separated extensions hashed with format-aware normalization
+    /// (e.g. "step,stp,igs"), so header-only changes are not seen as edits.
+    pub normalized_extensions: Option<String>,
+    /// Index member lists and hashes of local .zip bundles into file metadata.
+    pub inspect_archives: bool,
+    /// Where the supplier inbox lives: "local" or "gdrive". `None` disables the inbox.
+    pub inbox_location: Option<String>,
+    /// Local directory, or Google Drive folder id, of the inbox.
+    pub inbox_path: Option<String>,
+    /// Comma-separated extensions accepted from the inbox; empty accepts all.
+    pub inbox_extensions: Option<String>,
+    /// External scanner run on each delivery; a non-zero exit rejects the file.
+    pub inbox_scan_command: Option<String>,
+    /// Routing rules, one `pattern = target folder` per line.
+    pub inbox_rules: Option<String>,
+    /// File naming rules, one per line (see `core::naming`).
+    pub naming_rules: Option<String>,
+    /// Comma-separated locations ("gdrive", "smb") non-compliant files are not synced to.
+    pub naming_blocked_locations: Option<String>,
+    /// Folder copied into the local root when a new project is created from the template.
+    pub project_template_path: Option<String>,
+    /// Storage tiering rules, one `pattern = days` per line: local files matching
+    /// `pattern` and unmodified for `days` are archived and removed locally.
+    pub tiering_rules: Option<String>,
+    /// Google Drive folder offloaded files are archived to.
+    pub tiering_archive_folder_id: Option<String>,
+    /// Per-extension handling, one `ext = behavior[, behavior]` line each.
+    /// Behaviors: never-sync, local-only, compress, thumbnail.
+    pub extension_policies: Option<String>,
+    /// Converter run on updated drawings. `{input}` and `{output}` are replaced
+    /// with the source and target paths.
+    pub publish_command: Option<String>,
+    /// Formats to publish, one `ext = format[, format]` line each (e.g. `dwg = pdf, dxf`).
+    pub publish_formats: Option<String>,
+    /// Google Drive folder the published files are uploaded to for viewers.
+    pub publish_folder_id: Option<String>,
+    /// Diff tool launched by compare_files. `{left}` and `{right}` are replaced with the
+    /// staged versions; without placeholders both paths are appended.
+    pub compare_command: Option<String>,
+    /// Consistency groups, one `name = pattern[, pattern]` line each; members are only
+    /// propagated when all of them can be.
+    pub consistency_groups: Option<String>,
+    /// Where tokens and credentials are kept: "keyring" (default), "file" for an
+    /// encrypted file, or "env" for environment variables.
+    pub secret_store: Option<String>,
+    /// Language for backend messages ("en", "de", "ja"); unset follows the system.
+    pub locale: Option<String>,
+    /// Hold each sync plan until a user listed in `plan_approvers` approves it.
+    pub require_plan_approval: bool,
+    /// Comma-separated user names allowed to approve sync plans.
+    pub plan_approvers: Option<String>,
+    /// Comma-separated operation types ("copy", "move", "delete") that run without approval,
+    /// e.g. for unattended profiles.
+    pub auto_approved_operations: Option<String>,
+    /// Most deletions a sync may propagate; unset uses the default of 50.
+    pub max_deletion_count: Option<i64>,
+    /// Most deletions a sync may propagate, as a percentage of all files; unset uses 30.
+    pub max_deletion_percentage: Option<i64>,
+    /// Further paths to the same SMB data (e.g. a DR replica), one per line,
+    /// tried in order when `smb_share_path` is unreachable.
+    pub smb_fallback_paths: Option<String>,
+    /// Files at least this many MB are compared by size, modification time and sampled blocks
+    /// instead of a full hash. Unset hashes every file in full.
+    pub sampled_hash_min_mb: Option<i64>,
+    /// Notification channels as a JSON list, e.g. `[{"type": "webhook", "url": "...", "min_severity": "warning"}]`.
+    /// Channel types: `app`, `webhook` and `mqtt` (`broker`, `topic`, optional
+    /// `username` and `password`). None notifies the app window only.
+    pub notification_channels: Option<String>,
+    /// Serve the synced files read-only over WebDAV on this localhost port.
+    /// None turns the server off.
+    pub webdav_port: Option<i64>,
+    /// Sign uploads to the share and Drive with this computer's key and verify
+    /// downloads against their signatures, alerting on changes made outside UVCAD.
+    pub sign_digests: bool,
+    /// Comma-separated public keys of the other computers syncing this profile.
+    pub trusted_signing_keys: Option<String>,
+    /// Malware scanner run on downloads before they are written locally, e.g.
+    /// `clamscan --no-summary {file}`; files it flags are quarantined.
+    pub scan_command: Option<String>,
+    /// Sync CAD autosaves, lock files and old-version folders too; they are
+    /// skipped by default.
+    pub sync_cad_temp_files: bool,
+    /// Seconds a recently saved local file must stay unchanged before it is synced.
+    /// None uses the default of 5, 0 turns the check off.
+    pub save_settle_secs: Option<i64>,
+    /// Seconds a local change must be left alone before it is uploaded, for
+    /// assemblies whose saves take longer than the settle delay. None or 0: no wait.
+    pub quiet_period_secs: Option<i64>,
+    /// Folders the synced files are kept in at some locations, one `location = folder`
+    /// line each (e.g. `gdrive = Engineering`); see `core::path_prefix`.
+    pub path_prefixes: Option<String>,
+    /// Globs of the files to sync, e.g. `*.dwg, *.step`; None syncs every file.
+    /// See `core::selective_sync`.
+    pub include_patterns: Option<String>,
+    /// Globs of files never synced, e.g. `*.bak, ~$*`.
+    pub exclude_patterns: Option<String>,
+    /// `full` or `file` (`drive.file`): how much of Google Drive to ask for
+    /// at sign-in. See `core::auth_manager::DriveScope`.
+    pub drive_scope: Option<String>,
+    /// Move files a sync deletes into the trash of each location instead, where
+    /// `restore_deleted` can bring them back. See `core::trash`.
+    pub soft_delete: bool,
+    /// Days trashed files are kept before they are deleted for good. None keeps
+    /// them for 30.
+    pub trash_retention_days: Option<i64>,
+    /// `oauth` or `service_account`: how to sign in to Google Drive. None
+    /// signs in through the browser. See `core::service_account::DriveAuth`.
+    pub drive_auth: Option<String>,
+    /// With a service account, the user to act as through domain-wide
+    /// delegation; None acts as the service account itself.
+    pub drive_impersonate: Option<String>,
+    /// The company's policy file, a path on the share or an https URL. See
+    /// `core::managed_policy`.
+    pub managed_policy_source: Option<String>,
+    /// Keep this profile's own values of the settings the managed policy
+    /// decides. Only admins may turn it on.
+    pub override_managed_policy: bool,
+    /// Store files on Google Drive encrypted with the profile's key, so only
+    /// computers holding the key can read them. See `core::drive_encryption`.
+    pub encrypt_drive: bool,
 }
 
 impl SyncProfile {
@@ -22,6 +147,54 @@ impl SyncProfile {
             smb_share_path: None,
             created_at: Utc::now(),
             last_sync_at: None,
+            last_successful_sync_at: None,
+            last_error: None,
+            normalized_extensions: None,
+            inspect_archives: false,
+            inbox_location: None,
+            inbox_path: None,
+            inbox_extensions: None,
+            inbox_scan_command: None,
+            inbox_rules: None,
+            naming_rules: None,
+            naming_blocked_locations: None,
+            project_template_path: None,
+            tiering_rules: None,
+            tiering_archive_folder_id: None,
+            extension_policies: None,
+            publish_command: None,
+            publish_formats: None,
+            publish_folder_id: None,
+            compare_command: None,
+            consistency_groups: None,
+            secret_store: None,
+            locale: None,
+            require_plan_approval: false,
+            plan_approvers: None,
+            auto_approved_operations: None,
+            max_deletion_count: None,
+            max_deletion_percentage: None,
+            smb_fallback_paths: None,
+            sampled_hash_min_mb: None,
+            notification_channels: None,
+            webdav_port: None,
+            sign_digests: false,
+            trusted_signing_keys: None,
+            scan_command: None,
+            sync_cad_temp_files: false,
+            save_settle_secs: None,
+            quiet_period_secs: None,
+            path_prefixes: None,
+            include_patterns: None,
+            exclude_patterns: None,
+            drive_scope: None,
+            soft_delete: false,
+            trash_retention_days: None,
+            drive_auth: None,
+            drive_impersonate: None,
+            managed_policy_source: None,
+            override_managed_policy: false,
+            encrypt_drive: false,
         }
     }
 }
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,
+    Completed,
+    /// Stopped with a plan awaiting approval; nothing was transferred.
+    AwaitingApproval,
+    /// Stopped on request; files transferred before then stay synced.
+    Cancelled,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Completed => "completed",
+            RunStatus::AwaitingApproval => "awaiting_approval",
+            RunStatus::Cancelled => "cancelled",
+            RunStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for RunStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "running" => Ok(RunStatus::Running),
+            "completed" => Ok(RunStatus::Completed),
+            "awaiting_approval" => Ok(RunStatus::AwaitingApproval),
+            "cancelled" => Ok(RunStatus::Cancelled),
+            "failed" => Ok(RunStatus::Failed),
+            _ => Err(format!("Invalid run status: {}", s)),
+        }
+    }
+}
+
+/// The phases of a sync run, in order. The run records each as it enters it, so
+/// one that failed or was interrupted shows where it stopped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RunPhase {
+    Scan,
+    Plan,
+    /// Deletion limits, storage quotas and plan approval; nothing is changed yet.
+    SafetyCheck,
+    Execute,
+    /// Recording the new last known state of the files.
+    Commit,
+}
+
+impl RunPhase {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RunPhase::Scan => "scan",
+            RunPhase::Plan => "plan",
+            RunPhase::SafetyCheck => "safety_check",
+            RunPhase::Execute => "execute",
+            RunPhase::Commit => "commit",
+        }
+    }
+}
+
+impl FromStr for RunPhase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scan" => Ok(RunPhase::Scan),
+            "plan" => Ok(RunPhase::Plan),
+            "safety_check" => Ok(RunPhase::SafetyCheck),
+            "execute" => Ok(RunPhase::Execute),
+            "commit" => Ok(RunPhase::Commit),
+            _ => Err(format!("Invalid run phase: {}", s)),
+        }
+    }
+}
+
+/// One sync run as recorded in the history. Its `run_id` also tags the run's
+/// log lines, progress events and file events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub run_id: String,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub status: RunStatus,
+    /// The phase the run was last in; where it stopped unless it completed.
+    pub phase: Option<RunPhase>,
+    pub files_synced: i64,
+    pub files_failed: i64,
+    pub error_message: Option<String>,
+}
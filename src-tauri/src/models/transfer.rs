@@ -0,0 +1,72 @@
+use crate::models::file_state::FileLocation;
+use crate::models::sync_plan::OperationReason;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TransferStatus {
+    Queued,
+    InProgress,
+    Failed,
+}
+
+impl TransferStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TransferStatus::Queued => "queued",
+            TransferStatus::InProgress => "in_progress",
+            TransferStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for TransferStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queued" => Ok(TransferStatus::Queued),
+            "in_progress" => Ok(TransferStatus::InProgress),
+            "failed" => Ok(TransferStatus::Failed),
+            _ => Err(format!("Invalid transfer status: {}", s)),
+        }
+    }
+}
+
+/// One pending, running or failed file operation between locations.
+/// Completed transfers are removed from the queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub file_path: String,
+    /// Location copied from; `None` for deletions and moves.
+    pub source: Option<FileLocation>,
+    /// Location copied to, deleted from or moved at.
+    pub destination: FileLocation,
+    /// For moves, the path the file is moved from; `file_path` is where it goes.
+    pub moved_from: Option<String>,
+    pub status: TransferStatus,
+    /// Lower runs first.
+    pub priority: i64,
+    pub attempts: i64,
+    pub size_bytes: Option<i64>,
+    /// Content hash of the destination when the operation was planned, or of the
+    /// file moved. Overwrites, deletions and moves are refused as conflicts if it
+    /// changed since; None skips the check.
+    pub expected_hash: Option<String>,
+    /// The destination did not exist when the copy was planned; the copy is
+    /// refused as a conflict if something was created there since.
+    pub expect_absent: bool,
+    /// Size and modification time of the destination when the operation was
+    /// planned, for destinations without a content hash; checked instead of
+    /// `expected_hash`.
+    pub expected_size: Option<i64>,
+    pub expected_modified: Option<DateTime<Utc>>,
+    /// Why the operation was planned; None for transfers queued before reasons were recorded.
+    pub reason: Option<OperationReason>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,21 @@
+use crate::models::file_state::FileLocation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A copy that a sync moved to the trash of its location instead of deleting
+/// it. The row goes once the copy is restored or deleted for good.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedFile {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    /// Relative to the profile's root.
+    pub file_path: String,
+    pub location: FileLocation,
+    /// What the location's provider knows the trashed copy by: its path in the
+    /// trash folder, or its Google Drive file ID.
+    pub handle: String,
+    pub content_hash: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub deleted_at: DateTime<Utc>,
+    pub run_id: Option<String>,
+}
@@ -1,14 +1,38 @@
+use crate::core::auth_manager::DriveScope;
 use crate::core::file_hasher;
+use crate::core::service_account::{self, DriveAuth, ServiceAccount};
+use crate::models::sync_profile::SyncProfile;
+use crate::providers::http_capture::CapturedSend;
 use crate::providers::traits::{FileMetadata, StorageProvider};
 use crate::utils::error::{Result, UvcadError};
 use crate::utils::keyring::{OAuthTokens, TokenManager};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use reqwest::header::{CONTENT_RANGE, LOCATION, RANGE};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
-
-const DRIVE_API_BASE: &str = "https://www.googleapis.com/drive/v3";
-const DRIVE_UPLOAD_API: &str = "https://www.googleapis.com/upload/drive/v3";
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+const DRIVE_API_ROOT: &str = "https://www.googleapis.com";
+/// Times content is sent before a checksum mismatch is reported.
+const UPLOAD_ATTEMPTS: usize = 2;
+/// Files larger than this are sent in chunks of this size through a resumable
+/// upload session instead of in one request. A multiple of 256 KiB, as Drive requires.
+const UPLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+/// Times in a row a chunk may fail to arrive before the upload fails.
+const CHUNK_RETRIES: u32 = 5;
+/// Wait before asking how much of an interrupted chunk arrived; grows with each retry.
+const CHUNK_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Called with the destination path, the bytes Drive has received and the size
+/// of the file as each chunk of a resumable upload arrives.
+pub type UploadProgressCallback = Arc<dyn Fn(&Path, u64, u64) + Send + Sync>;
 
 #[derive(Debug, Deserialize)]
 struct DriveFile {
@@ -23,6 +47,14 @@ struct DriveFile {
     md5_checksum: Option<String>,
 }
 
+/// The fields requested back from an upload.
+#[derive(Debug, Deserialize)]
+struct UploadedFile {
+    id: String,
+    #[serde(rename = "md5Checksum")]
+    md5_checksum: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct FileList {
     files: Vec<DriveFile>,
@@ -30,31 +62,168 @@ struct FileList {
     next_page_token: Option<String>,
 }
 
+/// What `dedupe` did with the copies of one path.
+#[derive(Debug, Clone, Serialize)]
+pub struct DedupeOutcome {
+    pub path: String,
+    /// Copies identical to the kept one, removed.
+    pub merged: usize,
+    /// New names of differing copies, which from now on sync as separate files.
+    pub renamed: Vec<String>,
+}
+
+/// Files sharing one name in a Drive folder.
+struct DuplicateGroup {
+    path: PathBuf,
+    copies: Vec<DriveFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct About {
+    #[serde(rename = "storageQuota")]
+    storage_quota: StorageQuota,
+}
+
+/// Byte counts are decimal strings; `limit` is absent for unlimited accounts.
+#[derive(Debug, Deserialize)]
+struct StorageQuota {
+    limit: Option<String>,
+    usage: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct FileMetadataUpload {
     name: String,
     parents: Vec<String>,
 }
 
+/// Where the content of an upload goes.
+enum UploadTarget {
+    New { name: String, parent_id: String },
+    Existing { file_id: String },
+}
+
+/// How Drive answered a request of a resumable upload session.
+enum ChunkStatus {
+    Done(UploadedFile),
+    /// Drive has the first this many bytes.
+    Received(u64),
+    /// The request failed in a way worth retrying, e.g. the connection dropped.
+    Interrupted(String),
+}
+
+/// Where the provider gets its access tokens.
+enum Credentials {
+    /// The OAuth tokens a user granted, in `token_manager`.
+    OAuth,
+    /// A service account key; None when none is stored.
+    ServiceAccount(Option<ServiceAccount>),
+}
+
 pub struct GoogleDriveProvider {
     folder_id: String,
     token_manager: TokenManager,
+    credentials: Credentials,
     client: reqwest::Client,
+    api_base: String,
+    upload_api: String,
+    /// Used instead of the stored OAuth tokens when set.
+    access_token: Option<String>,
+    chunk_size: u64,
+    upload_progress: Option<UploadProgressCallback>,
+    /// The scope the tokens were granted, or a service account asks for.
+    scope: DriveScope,
 }
 
 impl GoogleDriveProvider {
-    pub fn new(folder_id: String) -> Result<Self> {
+    /// A provider for `folder_id` that signs in as `profile` says.
+    pub fn for_profile(profile: &SyncProfile, folder_id: String) -> Result<Self> {
+        Self::new(folder_id, DriveAuth::for_profile(profile)?)
+    }
+
+    pub fn new(folder_id: String, auth: DriveAuth) -> Result<Self> {
         let token_manager = TokenManager::new("google_drive")?;
         let client = reqwest::Client::new();
+        let (credentials, scope) = match auth {
+            DriveAuth::OAuth => {
+                let scope = token_manager.get_tokens()
+                    .map(|tokens| DriveScope::granted(tokens.scope.as_deref()))
+                    .unwrap_or_default();
+                (Credentials::OAuth, scope)
+            }
+            DriveAuth::ServiceAccount { subject, scope } => {
+                let account = service_account::stored_key()?.map(|key| ServiceAccount::new(key, subject, scope));
+                (Credentials::ServiceAccount(account), scope)
+            }
+        };
 
         Ok(Self {
             folder_id,
             token_manager,
+            credentials,
             client,
+            api_base: format!("{}/drive/v3", DRIVE_API_ROOT),
+            upload_api: format!("{}/upload/drive/v3", DRIVE_API_ROOT),
+            access_token: None,
+            chunk_size: UPLOAD_CHUNK_SIZE,
+            upload_progress: None,
+            scope,
         })
     }
 
+    /// Report the progress of large uploads to `callback`.
+    pub fn with_upload_progress(mut self, callback: UploadProgressCallback) -> Self {
+        self.upload_progress = Some(callback);
+        self
+    }
+
+    /// Talk to another server implementing the Drive API, such as a
+    /// `FakeDrive`, with a fixed access token.
+    #[cfg(test)]
+    pub fn with_server(self, api_root: &str, access_token: &str) -> Self {
+        let mut provider = self.with_api_root(api_root);
+        provider.access_token = Some(access_token.to_string());
+        provider
+    }
+
+    /// Talk to another server implementing the Drive API, signing in as usual.
+    #[cfg(test)]
+    pub fn with_api_root(mut self, api_root: &str) -> Self {
+        self.api_base = format!("{}/drive/v3", api_root);
+        self.upload_api = format!("{}/upload/drive/v3", api_root);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_service_account(mut self, account: ServiceAccount) -> Self {
+        self.credentials = Credentials::ServiceAccount(Some(account));
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_scope(mut self, scope: DriveScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Send files larger than `bytes` in chunks of that size.
+    #[cfg(test)]
+    pub fn with_chunk_size(mut self, bytes: u64) -> Self {
+        self.chunk_size = bytes;
+        self
+    }
+
     async fn get_access_token(&self) -> Result<String> {
+        if let Some(token) = &self.access_token {
+            return Ok(token.clone());
+        }
+        match &self.credentials {
+            Credentials::ServiceAccount(Some(account)) => return account.access_token(&self.client).await,
+            Credentials::ServiceAccount(None) => {
+                return Err(UvcadError::AuthenticationFailed("No service account key is stored".to_string()));
+            }
+            Credentials::OAuth => {}
+        }
         let tokens = self.token_manager.get_tokens()?;
 
         // Check if token is expired or expiring within 5 minutes
@@ -71,13 +240,117 @@ impl GoogleDriveProvider {
     }
 
     pub fn is_authenticated(&self) -> bool {
-        self.token_manager.has_tokens()
+        self.access_token.is_some() || match &self.credentials {
+            Credentials::OAuth => self.token_manager.has_tokens(),
+            Credentials::ServiceAccount(account) => account.is_some(),
+        }
     }
 
     pub fn store_tokens(&self, tokens: OAuthTokens) -> Result<()> {
         self.token_manager.store_tokens(&tokens)
     }
 
+    /// Share a file with anyone who has the link (read-only) and return its web URL.
+    pub async fn create_share_link(&self, path: &Path) -> Result<String> {
+        let file = self.resolve_path(path).await?
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string_lossy().to_string() })?;
+
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}/permissions", self.api_base, file.id);
+        let permission = serde_json::json!({ "role": "reader", "type": "anyone" });
+
+        let response = self.client
+            .post(&url)
+            .bearer_auth(&token)
+            .header("Content-Type", "application/json")
+            .body(permission.to_string())
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to share file: {} - {}",
+                status, error_text
+            )));
+        }
+
+        self.web_view_link(&file.id).await
+    }
+
+    /// With the `drive.file` scope, fail unless the synced folder is one UVCAD
+    /// can see. Otherwise its listing would only hold the files UVCAD put
+    /// there, if any, and the rest would look deleted.
+    async fn check_folder_visible(&self) -> Result<()> {
+        if self.scope == DriveScope::Full {
+            return Ok(());
+        }
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}?fields=id", self.api_base, self.folder_id);
+        let response = self.client
+            .get(&url)
+            .bearer_auth(token)
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Err(UvcadError::ProviderError(format!(
+                "The Google Drive folder {} was not created by UVCAD and can't be seen with the {} scope; \
+                 choose a folder UVCAD created, or sign in with the full scope",
+                self.folder_id, DriveScope::File.as_str()
+            ))),
+            status => {
+                let error_text = response.text().await.unwrap_or_default();
+                Err(UvcadError::ProviderError(format!(
+                    "Failed to check the synced folder: {} - {}",
+                    status, error_text
+                )))
+            }
+        }
+    }
+
+    /// The address of a file or folder in the Drive web UI; for an empty path
+    /// the synced folder itself. Nothing is shared.
+    pub async fn web_link(&self, path: &Path) -> Result<String> {
+        if path.as_os_str().is_empty() {
+            return Ok(format!("https://drive.google.com/drive/folders/{}", self.folder_id));
+        }
+        let file = self.resolve_path(path).await?
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string_lossy().to_string() })?;
+        self.web_view_link(&file.id).await
+    }
+
+    async fn web_view_link(&self, file_id: &str) -> Result<String> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}?fields=webViewLink", self.api_base, file_id);
+        let response = self.client
+            .get(&url)
+            .bearer_auth(&token)
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to get web link: {} - {}",
+                status, error_text
+            )));
+        }
+
+        let value: serde_json::Value = response.json().await
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))?;
+
+        value["webViewLink"].as_str()
+            .map(String::from)
+            .ok_or_else(|| UvcadError::ProviderError("Drive did not return a web link".to_string()))
+    }
+
     /// Escape a string for use in a Google Drive API query parameter.
     /// Single quotes must be escaped with a backslash.
     fn escape_drive_query(s: &str) -> String {
@@ -117,6 +390,7 @@ impl GoogleDriveProvider {
                             size,
                             modified,
                             hash: file.md5_checksum,
+                            fingerprint: None,
                             exists: true,
                         });
                     }
@@ -169,13 +443,13 @@ impl GoogleDriveProvider {
         let safe_name = Self::escape_drive_query(name);
         let url = format!(
             "{}/files?q='{}'+in+parents+and+name='{}'+and+trashed=false&fields=files(id,name,mimeType,size,modifiedTime,md5Checksum)",
-            DRIVE_API_BASE, safe_folder_id, safe_name
+            self.api_base, safe_folder_id, safe_name
         );
 
         let response = self.client
             .get(&url)
             .bearer_auth(token)
-            .send()
+            .send_captured()
             .await
             .map_err(|e| UvcadError::NetworkError(e))?;
 
@@ -186,9 +460,213 @@ impl GoogleDriveProvider {
         let file_list: FileList = response.json().await
             .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))?;
 
+        // Drive allows several items with one name; picking one could update or delete the wrong copy
+        if file_list.files.len() > 1 {
+            return Err(UvcadError::ProviderError(format!(
+                "{} items named '{}' share a folder on Google Drive; resolve the duplicates first",
+                file_list.files.len(), name
+            )));
+        }
         Ok(file_list.files.into_iter().next())
     }
 
+    /// Files under a folder that share their name with another file in the same folder.
+    fn find_duplicates<'a>(&'a self, folder_id: &'a str, prefix: &'a Path) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DuplicateGroup>>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut by_name: HashMap<String, Vec<DriveFile>> = HashMap::new();
+            let mut subfolders = Vec::new();
+            let mut page_token: Option<String> = None;
+            loop {
+                let file_list = self.list_files_in_folder(folder_id, page_token).await?;
+                for file in file_list.files {
+                    if file.mime_type == "application/vnd.google-apps.folder" {
+                        subfolders.push(file);
+                    } else {
+                        by_name.entry(file.name.clone()).or_default().push(file);
+                    }
+                }
+                page_token = file_list.next_page_token;
+                if page_token.is_none() {
+                    break;
+                }
+            }
+
+            let mut groups: Vec<DuplicateGroup> = by_name.into_iter()
+                .filter(|(_, copies)| copies.len() > 1)
+                .map(|(name, copies)| DuplicateGroup { path: prefix.join(name), copies })
+                .collect();
+            for folder in subfolders {
+                groups.extend(self.find_duplicates(&folder.id, &prefix.join(&folder.name)).await?);
+            }
+            Ok(groups)
+        })
+    }
+
+    /// Resolve files sharing a name in one folder, for all of them or only `only`.
+    /// The most recently modified copy keeps the name; copies with the same
+    /// content are removed and the others renamed to `name (duplicate N).ext`.
+    pub async fn dedupe(&self, only: Option<&Path>) -> Result<Vec<DedupeOutcome>> {
+        let mut groups = self.find_duplicates(&self.folder_id, Path::new("")).await?;
+        groups.retain(|group| only.is_none_or(|path| group.path == path));
+        groups.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut outcomes = Vec::new();
+        for mut group in groups {
+            group.copies.sort_by(|a, b| b.modified_time.cmp(&a.modified_time).then_with(|| a.id.cmp(&b.id)));
+            let kept = group.copies.remove(0);
+            let mut outcome = DedupeOutcome {
+                path: group.path.to_string_lossy().to_string(),
+                merged: 0,
+                renamed: Vec::new(),
+            };
+
+            for copy in group.copies {
+                if copy.md5_checksum.is_some() && copy.md5_checksum == kept.md5_checksum {
+                    self.delete_file_by_id(&copy.id).await?;
+                    outcome.merged += 1;
+                    continue;
+                }
+                let new_path = self.free_duplicate_name(&group.path).await?;
+                let new_name = new_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                self.rename_file_by_id(&copy.id, new_name, None).await?;
+                outcome.renamed.push(new_path.to_string_lossy().to_string());
+            }
+
+            tracing::info!("Deduplicated {} on Google Drive: {} merged, {} renamed",
+                           outcome.path, outcome.merged, outcome.renamed.len());
+            outcomes.push(outcome);
+        }
+        Ok(outcomes)
+    }
+
+    /// The first `name (duplicate N).ext` next to `path` that is not taken.
+    async fn free_duplicate_name(&self, path: &Path) -> Result<PathBuf> {
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        for n in 2.. {
+            let candidate = path.with_file_name(format!("{} (duplicate {}){}", stem, n, extension));
+            if self.resolve_path(&candidate).await?.is_none() {
+                return Ok(candidate);
+            }
+        }
+        unreachable!()
+    }
+
+    async fn delete_file_by_id(&self, file_id: &str) -> Result<()> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}", self.api_base, file_id);
+
+        let response = self.client
+            .delete(&url)
+            .bearer_auth(token)
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to delete file: {} - {}",
+                status, error_text
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Move a file to or out of the Drive trash. False when there is no file
+    /// with that ID any more, e.g. the trash was emptied.
+    async fn set_trashed(&self, file_id: &str, trashed: bool) -> Result<bool> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}", self.api_base, file_id);
+        let metadata = serde_json::json!({ "trashed": trashed });
+
+        let response = self.client
+            .patch(&url)
+            .bearer_auth(token)
+            .header("Content-Type", "application/json")
+            .body(metadata.to_string())
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => {
+                let error_text = response.text().await.unwrap_or_default();
+                Err(UvcadError::ProviderError(format!(
+                    "Failed to {} file: {} - {}",
+                    if trashed { "trash" } else { "restore" }, status, error_text
+                )))
+            }
+        }
+    }
+
+    /// Rename a file, and move it from the first of `parents` to the second
+    /// when given.
+    async fn rename_file_by_id(&self, file_id: &str, name: &str, parents: Option<(&str, &str)>) -> Result<()> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}", self.api_base, file_id);
+        let metadata = serde_json::json!({ "name": name });
+        let query: Vec<(&str, &str)> = parents
+            .map(|(from, to)| vec![("removeParents", from), ("addParents", to)])
+            .unwrap_or_default();
+
+        let response = self.client
+            .patch(&url)
+            .query(&query)
+            .bearer_auth(token)
+            .header("Content-Type", "application/json")
+            .body(metadata.to_string())
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to rename file: {} - {}",
+                status, error_text
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn copy_file_to_folder(&self, file_id: &str, name: &str, parent_id: &str) -> Result<UploadedFile> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}/copy?fields=id,md5Checksum", self.api_base, file_id);
+        let metadata = FileMetadataUpload {
+            name: name.to_string(),
+            parents: vec![parent_id.to_string()],
+        };
+        let metadata_json = serde_json::to_string(&metadata)?;
+
+        let response = self.client
+            .post(&url)
+            .bearer_auth(token)
+            .header("Content-Type", "application/json")
+            .body(metadata_json)
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to copy file: {} - {}",
+                status, error_text
+            )));
+        }
+
+        response.json().await
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))
+    }
+
     /// Find the folder ID for a parent path, creating folders as needed for uploads.
     async fn resolve_or_create_parent_folder(&self, path: &Path) -> Result<String> {
         let components: Vec<&str> = path.iter()
@@ -223,14 +701,14 @@ impl GoogleDriveProvider {
             "parents": [parent_id]
         });
 
-        let url = format!("{}/files", DRIVE_API_BASE);
+        let url = format!("{}/files", self.api_base);
 
         let response = self.client
             .post(&url)
             .bearer_auth(token)
             .header("Content-Type", "application/json")
             .body(metadata.to_string())
-            .send()
+            .send_captured()
             .await
             .map_err(|e| UvcadError::NetworkError(e))?;
 
@@ -255,7 +733,7 @@ impl GoogleDriveProvider {
         let safe_folder_id = Self::escape_drive_query(folder_id);
         let mut url = format!(
             "{}/files?q='{}'+in+parents+and+trashed=false&fields=files(id,name,mimeType,size,modifiedTime,md5Checksum),nextPageToken",
-            DRIVE_API_BASE, safe_folder_id
+            self.api_base, safe_folder_id
         );
 
         if let Some(pt) = page_token {
@@ -265,7 +743,7 @@ impl GoogleDriveProvider {
         let response = self.client
             .get(&url)
             .bearer_auth(token)
-            .send()
+            .send_captured()
             .await
             .map_err(|e| UvcadError::NetworkError(e))?;
 
@@ -284,15 +762,18 @@ impl GoogleDriveProvider {
         Ok(file_list)
     }
 
-    async fn download_file_content(&self, file_id: &str) -> Result<Vec<u8>> {
+    /// Stream the content of a file into `dest` and return its MD5, computed
+    /// along the way. Only one chunk of the response is held in memory at a
+    /// time; a partly written `dest` is removed when the download breaks off.
+    async fn download_file_content(&self, file_id: &str, dest: &Path) -> Result<String> {
         let token = self.get_access_token().await?;
 
-        let url = format!("{}/files/{}?alt=media", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}?alt=media", self.api_base, file_id);
 
         let response = self.client
             .get(&url)
             .bearer_auth(token)
-            .send()
+            .send_captured()
             .await
             .map_err(|e| UvcadError::NetworkError(e))?;
 
@@ -305,13 +786,27 @@ impl GoogleDriveProvider {
             )));
         }
 
-        let bytes = response.bytes().await
-            .map_err(|e| UvcadError::NetworkError(e))?;
+        let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(dest).await?);
+        let streamed = async {
+            let mut context = md5::Context::new();
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(UvcadError::NetworkError)?;
+                context.consume(&chunk);
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            Ok(format!("{:x}", context.compute()))
+        }.await;
 
-        Ok(bytes.to_vec())
+        if streamed.is_err() {
+            drop(file);
+            let _ = tokio::fs::remove_file(dest).await;
+        }
+        streamed
     }
 
-    async fn upload_file_to_folder(&self, name: &str, parent_id: &str, content: Vec<u8>) -> Result<String> {
+    async fn upload_file_to_folder(&self, name: &str, parent_id: &str, content: Vec<u8>) -> Result<UploadedFile> {
         let token = self.get_access_token().await?;
 
         let metadata = FileMetadataUpload {
@@ -333,14 +828,14 @@ impl GoogleDriveProvider {
         body.extend_from_slice(&content);
         body.extend_from_slice(format!("\r\n--{}--", boundary).as_bytes());
 
-        let url = format!("{}/files?uploadType=multipart", DRIVE_UPLOAD_API);
+        let url = format!("{}/files?uploadType=multipart&fields=id,md5Checksum", self.upload_api);
 
         let response = self.client
             .post(&url)
             .bearer_auth(token)
             .header("Content-Type", format!("multipart/related; boundary={}", boundary))
             .body(body)
-            .send()
+            .send_captured()
             .await
             .map_err(|e| UvcadError::NetworkError(e))?;
 
@@ -353,23 +848,21 @@ impl GoogleDriveProvider {
             )));
         }
 
-        let file: DriveFile = response.json().await
-            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))?;
-
-        Ok(file.id)
+        response.json().await
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))
     }
 
-    async fn update_file_content(&self, file_id: &str, content: Vec<u8>) -> Result<()> {
+    async fn update_file_content(&self, file_id: &str, content: Vec<u8>) -> Result<UploadedFile> {
         let token = self.get_access_token().await?;
 
-        let url = format!("{}/files/{}?uploadType=media", DRIVE_UPLOAD_API, file_id);
+        let url = format!("{}/files/{}?uploadType=media&fields=id,md5Checksum", self.upload_api, file_id);
 
         let response = self.client
             .patch(&url)
             .bearer_auth(token)
             .header("Content-Type", "application/octet-stream")
             .body(content)
-            .send()
+            .send_captured()
             .await
             .map_err(|e| UvcadError::NetworkError(e))?;
 
@@ -382,10 +875,177 @@ impl GoogleDriveProvider {
             )));
         }
 
-        Ok(())
+        response.json().await
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))
+    }
+
+    /// Send the content of `source` to `target` and compare the MD5 Drive computed
+    /// with that of the file. A mismatch re-sends the content; if it persists the
+    /// upload fails with `HashMismatch`. Returns the ID of the uploaded file.
+    async fn send_verified(&self, source: &Path, mut target: UploadTarget, path: &Path) -> Result<String> {
+        let expected = file_hasher::compute_file_md5(source)?;
+        for attempt in 1..=UPLOAD_ATTEMPTS {
+            let uploaded = self.send_content(source, &target, path).await?;
+            let Some(actual) = uploaded.md5_checksum.as_deref() else {
+                tracing::warn!("Google Drive returned no checksum for '{}'; upload not verified", path.display());
+                return Ok(uploaded.id);
+            };
+            if actual.eq_ignore_ascii_case(&expected) {
+                tracing::debug!("Upload integrity verified for '{}' (MD5: {})", path.display(), expected);
+                return Ok(uploaded.id);
+            }
+            tracing::warn!(
+                "Upload integrity check failed for '{}' (attempt {}): expected MD5 {}, got {}",
+                path.display(), attempt, expected, actual
+            );
+            target = UploadTarget::Existing { file_id: uploaded.id };
+        }
+        Err(UvcadError::HashMismatch { path: path.to_string_lossy().to_string() })
+    }
+
+    /// Send small files in one request and larger ones in chunks, so they are
+    /// never held in memory whole.
+    async fn send_content(&self, source: &Path, target: &UploadTarget, path: &Path) -> Result<UploadedFile> {
+        let size = tokio::fs::metadata(source).await?.len();
+        if size <= self.chunk_size {
+            let content = tokio::fs::read(source).await?;
+            return match target {
+                UploadTarget::New { name, parent_id } => self.upload_file_to_folder(name, parent_id, content).await,
+                UploadTarget::Existing { file_id } => self.update_file_content(file_id, content).await,
+            };
+        }
+
+        let session = self.start_resumable_upload(target, size).await?;
+        self.send_chunks(&session, source, size, path).await
+    }
+
+    /// Open a resumable upload session for `size` bytes; returns its URI.
+    async fn start_resumable_upload(&self, target: &UploadTarget, size: u64) -> Result<String> {
+        let token = self.get_access_token().await?;
+
+        let request = match target {
+            UploadTarget::New { name, parent_id } => self.client
+                .post(format!("{}/files?uploadType=resumable&fields=id,md5Checksum", self.upload_api))
+                .json(&FileMetadataUpload { name: name.clone(), parents: vec![parent_id.clone()] }),
+            UploadTarget::Existing { file_id } => self.client
+                .patch(format!("{}/files/{}?uploadType=resumable&fields=id,md5Checksum", self.upload_api, file_id))
+                .body(Vec::new()),
+        };
+        let response = request
+            .bearer_auth(token)
+            .header("X-Upload-Content-Type", "application/octet-stream")
+            .header("X-Upload-Content-Length", size)
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to start upload: {} - {}",
+                status, error_text
+            )));
+        }
+
+        response.headers().get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+            .ok_or_else(|| UvcadError::ProviderError("Google Drive returned no upload session".to_string()))
+    }
+
+    /// Send `source` to an upload session chunk by chunk. When a chunk does not
+    /// arrive, ask the session how much did and carry on from there.
+    async fn send_chunks(&self, session: &str, source: &Path, size: u64, path: &Path) -> Result<UploadedFile> {
+        let mut file = tokio::fs::File::open(source).await?;
+        let mut offset = 0;
+        let mut failures = 0;
+        let mut resend = true;
+
+        loop {
+            let token = self.get_access_token().await?;
+            let request = self.client.put(session).bearer_auth(token);
+            let request = if resend {
+                let end = (offset + self.chunk_size).min(size);
+                let mut chunk = vec![0; (end - offset) as usize];
+                file.seek(SeekFrom::Start(offset)).await?;
+                file.read_exact(&mut chunk).await?;
+                request.header(CONTENT_RANGE, format!("bytes {}-{}/{}", offset, end - 1, size)).body(chunk)
+            } else {
+                request.header(CONTENT_RANGE, format!("bytes */{}", size)).body(Vec::new())
+            };
+
+            match chunk_status(request.send_captured().await).await? {
+                ChunkStatus::Done(uploaded) => {
+                    self.report_upload(path, size, size);
+                    return Ok(uploaded);
+                }
+                ChunkStatus::Received(received) => {
+                    if received > offset {
+                        failures = 0;
+                    }
+                    offset = received;
+                    resend = offset < size;
+                    self.report_upload(path, offset, size);
+                }
+                ChunkStatus::Interrupted(reason) => {
+                    failures += 1;
+                    if failures > CHUNK_RETRIES {
+                        return Err(UvcadError::ProviderError(format!(
+                            "Upload of {} failed after {} of {} bytes: {}",
+                            path.display(), offset, size, reason
+                        )));
+                    }
+                    tracing::warn!("Upload of {} interrupted after {} bytes ({}), resuming", path.display(), offset, reason);
+                    tokio::time::sleep(CHUNK_RETRY_DELAY * failures).await;
+                    resend = false;
+                }
+            }
+        }
+    }
+
+    fn report_upload(&self, path: &Path, sent: u64, size: u64) {
+        tracing::debug!("Uploaded {} of {} bytes of {}", sent, size, path.display());
+        if let Some(ref callback) = self.upload_progress {
+            callback(path, sent, size);
+        }
     }
 }
 
+/// Read Drive's answer to a request of an upload session: done, incomplete with
+/// `Range` saying how much arrived, or failed. Dropped connections, server errors
+/// and rate limiting are worth retrying.
+async fn chunk_status(sent: reqwest::Result<reqwest::Response>) -> Result<ChunkStatus> {
+    let response = match sent {
+        Ok(response) => response,
+        Err(e) => return Ok(ChunkStatus::Interrupted(e.to_string())),
+    };
+    let status = response.status();
+    if status.is_success() {
+        return response.json().await
+            .map(ChunkStatus::Done)
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)));
+    }
+    // "Resume Incomplete", with the bytes received as `bytes=0-<last>`
+    if status == StatusCode::PERMANENT_REDIRECT {
+        let received = response.headers().get(RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|range| range.rsplit('-').next())
+            .and_then(|last| last.parse::<u64>().ok())
+            .map_or(0, |last| last + 1);
+        return Ok(ChunkStatus::Received(received));
+    }
+
+    let error_text = response.text().await.unwrap_or_default();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return Ok(ChunkStatus::Interrupted(format!("{} - {}", status, error_text)));
+    }
+    Err(UvcadError::ProviderError(format!(
+        "Failed to upload file: {} - {}",
+        status, error_text
+    )))
+}
+
 #[async_trait]
 impl StorageProvider for GoogleDriveProvider {
     fn name(&self) -> &str {
@@ -393,6 +1053,7 @@ impl StorageProvider for GoogleDriveProvider {
     }
 
     async fn list_files(&self, _path: &Path) -> Result<Vec<FileMetadata>> {
+        self.check_folder_visible().await?;
         self.list_files_recursive(&self.folder_id, Path::new("")).await
     }
 
@@ -414,6 +1075,7 @@ impl StorageProvider for GoogleDriveProvider {
                 size,
                 modified,
                 hash: file.md5_checksum,
+                fingerprint: None,
                 exists: true,
             }))
         } else {
@@ -429,14 +1091,10 @@ impl StorageProvider for GoogleDriveProvider {
         let file = self.resolve_path(path).await?
             .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string_lossy().to_string() })?;
 
-        let content = self.download_file_content(&file.id).await?;
-
-        // Write to destination
-        tokio::fs::write(dest, &content).await?;
+        let computed_md5 = self.download_file_content(&file.id, dest).await?;
 
         // Verify hash using MD5 (Google Drive's native hash algorithm)
         if let Some(expected_md5) = file.md5_checksum {
-            let computed_md5 = file_hasher::compute_file_md5(dest)?;
             if !computed_md5.eq_ignore_ascii_case(&expected_md5) {
                 return Err(UvcadError::SyncFailed(format!(
                     "Download integrity check failed for '{}': expected MD5 {}, got {}",
@@ -454,48 +1112,117 @@ impl StorageProvider for GoogleDriveProvider {
             .and_then(|n| n.to_str())
             .ok_or_else(|| UvcadError::InvalidConfig("Invalid file path".to_string()))?;
 
-        // Read file content
-        let content = tokio::fs::read(source).await?;
-
         // Check if file already exists at this path
         if let Some(existing_file) = self.resolve_path(dest).await? {
             // Update existing file
-            self.update_file_content(&existing_file.id, content).await?;
+            self.send_verified(source, UploadTarget::Existing { file_id: existing_file.id }, dest).await?;
             tracing::info!("Updated existing file in Google Drive: {}", dest.display());
         } else {
             // Resolve or create parent folders, then upload
             let parent_id = self.resolve_or_create_parent_folder(dest).await?;
-            let file_id = self.upload_file_to_folder(name, &parent_id, content).await?;
+            let target = UploadTarget::New { name: name.to_string(), parent_id };
+            let file_id = self.send_verified(source, target, dest).await?;
             tracing::info!("Uploaded new file to Google Drive: {} (ID: {})", dest.display(), file_id);
         }
 
         Ok(())
     }
 
+    async fn upload_if_unchanged(&self, source: &Path, dest: &Path, expected_hash: &str) -> Result<()> {
+        // Drive has no conditional update; re-check the checksum right before overwriting
+        let file = self.resolve_path(dest).await?
+            .filter(|file| file.md5_checksum.as_deref() == Some(expected_hash))
+            .ok_or_else(|| UvcadError::ConflictDetected { path: dest.to_string_lossy().to_string() })?;
+        self.send_verified(source, UploadTarget::Existing { file_id: file.id }, dest).await?;
+        tracing::info!("Updated unchanged file in Google Drive: {}", dest.display());
+        Ok(())
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<bool> {
+        let name = to.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| UvcadError::InvalidConfig("Invalid file path".to_string()))?;
+        // Copying creates a new file; overwriting would lose the existing file's revisions
+        if self.resolve_path(to).await?.is_some() {
+            return Ok(false);
+        }
+        let Some(source) = self.resolve_path(from).await? else {
+            return Ok(false);
+        };
+
+        let parent_id = self.resolve_or_create_parent_folder(to).await?;
+        let copied = self.copy_file_to_folder(&source.id, name, &parent_id).await?;
+        if copied.md5_checksum.is_some() && copied.md5_checksum != source.md5_checksum {
+            self.delete_file_by_id(&copied.id).await?;
+            return Err(UvcadError::HashMismatch { path: to.to_string_lossy().to_string() });
+        }
+        tracing::info!("Copied {} to {} in Google Drive (ID: {})", from.display(), to.display(), copied.id);
+        Ok(true)
+    }
+
     async fn delete(&self, path: &Path) -> Result<()> {
         let file = self.resolve_path(path).await?
             .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string_lossy().to_string() })?;
 
-        let token = self.get_access_token().await?;
-        let url = format!("{}/files/{}", DRIVE_API_BASE, file.id);
+        self.delete_file_by_id(&file.id).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let name = to.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| UvcadError::InvalidConfig("Invalid file path".to_string()))?;
+        let file = self.resolve_path(from).await?
+            .ok_or_else(|| UvcadError::FileNotFound { path: from.to_string_lossy().to_string() })?;
+
+        if from.parent() == to.parent() {
+            return self.rename_file_by_id(&file.id, name, None).await;
+        }
+        let old_parent = self.resolve_or_create_parent_folder(from).await?;
+        let new_parent = self.resolve_or_create_parent_folder(to).await?;
+        self.rename_file_by_id(&file.id, name, Some((&old_parent, &new_parent))).await
+    }
+
+    /// Drive's own trash, which keeps the file where it was: the handle is
+    /// its file ID and `trashed` is not used.
+    async fn trash(&self, path: &Path, _trashed: &Path) -> Result<String> {
+        let file = self.resolve_path(path).await?
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.to_string_lossy().to_string() })?;
+        if !self.set_trashed(&file.id, true).await? {
+            return Err(UvcadError::FileNotFound { path: path.to_string_lossy().to_string() });
+        }
+        Ok(file.id)
+    }
 
+    /// Restores the file to the folder it was trashed from, which is where
+    /// `path` is unless the folder prefix changed since.
+    async fn restore_trashed(&self, handle: &str, path: &Path) -> Result<()> {
+        if self.set_trashed(handle, false).await? {
+            Ok(())
+        } else {
+            Err(UvcadError::FileNotFound { path: path.to_string_lossy().to_string() })
+        }
+    }
+
+    async fn purge_trashed(&self, handle: &str) -> Result<()> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/files/{}", self.api_base, handle);
         let response = self.client
             .delete(&url)
             .bearer_auth(token)
-            .send()
+            .send_captured()
             .await
-            .map_err(|e| UvcadError::NetworkError(e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(UvcadError::ProviderError(format!(
-                "Failed to delete file: {} - {}",
-                status, error_text
-            )));
+            .map_err(UvcadError::NetworkError)?;
+
+        match response.status() {
+            status if status.is_success() || status == StatusCode::NOT_FOUND => Ok(()),
+            status => {
+                let error_text = response.text().await.unwrap_or_default();
+                Err(UvcadError::ProviderError(format!(
+                    "Failed to delete trashed file: {} - {}",
+                    status, error_text
+                )))
+            }
         }
-
-        Ok(())
     }
 
     async fn initialize(&mut self) -> Result<()> {
@@ -513,10 +1240,360 @@ impl StorageProvider for GoogleDriveProvider {
             return Ok(false);
         }
 
+        if self.check_folder_visible().await.is_err() {
+            return Ok(false);
+        }
+
         // Try to list files to verify connection
         match self.list_files_in_folder(&self.folder_id, None).await {
             Ok(_) => Ok(true),
             Err(_) => Ok(false),
         }
     }
+
+    fn lists_every_file(&self) -> bool {
+        self.scope == DriveScope::Full
+    }
+
+    async fn available_space(&self) -> Result<Option<u64>> {
+        let token = self.get_access_token().await?;
+        let url = format!("{}/about?fields=storageQuota", self.api_base);
+
+        let response = self.client
+            .get(&url)
+            .bearer_auth(token)
+            .send_captured()
+            .await
+            .map_err(UvcadError::NetworkError)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(UvcadError::ProviderError(format!(
+                "Failed to get storage quota: {} - {}",
+                status, error_text
+            )));
+        }
+
+        let about: About = response.json().await
+            .map_err(|e| UvcadError::ProviderError(format!("Failed to parse response: {}", e)))?;
+
+        let parse = |value: Option<String>| value.and_then(|v| v.parse::<u64>().ok());
+        let quota = about.storage_quota;
+        Ok(parse(quota.limit).map(|limit| limit.saturating_sub(parse(quota.usage).unwrap_or(0))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sync_engine::SyncEngine;
+    use crate::db::{handle::DbHandle, models::DbOperations, schema::Database};
+    use crate::models::sync_profile::SyncProfile;
+    use crate::providers::fake_drive::FakeDrive;
+    use crate::providers::http_capture;
+    use crate::providers::local_fs::LocalFsProvider;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_round_trip_against_fake_drive() {
+        let drive = FakeDrive::start().await;
+        for i in 0..4 {
+            drive.put(format!("parts/p{}.step", i), format!("part {}", i));
+        }
+        let provider = drive.provider();
+
+        // More files than fit on one page of the listing
+        let listed = provider.list_files(Path::new("")).await.unwrap();
+        assert_eq!(listed.len(), 4);
+        let p1 = listed.iter().find(|f| f.path == Path::new("parts/p1.step")).unwrap();
+        assert_eq!(p1.hash, Some(file_hasher::compute_bytes_md5(b"part 1")));
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("new.step");
+        std::fs::write(&source, "new").unwrap();
+        provider.upload(&source, Path::new("assemblies/top/new.step")).await.unwrap();
+        assert_eq!(drive.content("assemblies/top/new.step"), Some(b"new".to_vec()));
+
+        let dest = dir.path().join("p1.step");
+        provider.download(Path::new("parts/p1.step"), &dest).await.unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"part 1");
+
+        // Edited on Drive after the caller looked
+        let seen = file_hasher::compute_bytes_md5(b"part 2");
+        drive.put("parts/p2.step", "edited on Drive");
+        let overwrite = provider.upload_if_unchanged(&source, Path::new("parts/p2.step"), &seen).await;
+        assert!(matches!(overwrite, Err(UvcadError::ConflictDetected { .. })));
+
+        let link = provider.web_link(Path::new("parts/p0.step")).await.unwrap();
+        assert!(link.starts_with("https://drive.google.com/file/d/"));
+        assert!(matches!(provider.web_link(Path::new("parts/p9.step")).await, Err(UvcadError::FileNotFound { .. })));
+
+        provider.rename(Path::new("parts/p0.step"), Path::new("parts/p0-old.step")).await.unwrap();
+        provider.rename(Path::new("parts/p1.step"), Path::new("released/p1.step")).await.unwrap();
+        provider.delete(Path::new("parts/p3.step")).await.unwrap();
+        assert_eq!(drive.paths(), vec![
+            PathBuf::from("assemblies/top/new.step"),
+            PathBuf::from("parts/p0-old.step"),
+            PathBuf::from("parts/p2.step"),
+            PathBuf::from("released/p1.step"),
+        ]);
+
+        drive.set_quota(1024);
+        let used: usize = ["new", "part 0", "part 1", "edited on Drive"].iter().map(|c| c.len()).sum();
+        assert_eq!(provider.available_space().await.unwrap(), Some(1024 - used as u64));
+    }
+
+    #[tokio::test]
+    async fn test_large_uploads_are_chunked_and_resume() {
+        let drive = FakeDrive::start().await;
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = sent.clone();
+        let provider = drive.provider()
+            .with_chunk_size(4)
+            .with_upload_progress(Arc::new(move |_, bytes, total| record.lock().unwrap().push((bytes, total))));
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("scan.e57");
+        std::fs::write(&source, "point cloud!").unwrap();
+
+        // Half of the second chunk is lost; the rest is sent again from there
+        drive.interrupt_upload(1);
+        provider.upload(&source, Path::new("scans/scan.e57")).await.unwrap();
+        assert_eq!(drive.content("scans/scan.e57"), Some(b"point cloud!".to_vec()));
+        assert_eq!(*sent.lock().unwrap(), [(4, 12), (6, 12), (10, 12), (12, 12)]);
+
+        std::fs::write(&source, "denser point cloud").unwrap();
+        provider.upload(&source, Path::new("scans/scan.e57")).await.unwrap();
+        assert_eq!(drive.content("scans/scan.e57"), Some(b"denser point cloud".to_vec()));
+        assert_eq!(drive.upload_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_captured_without_credentials() {
+        let drive = FakeDrive::start().await;
+        drive.put("parts/bracket.step", "bracket");
+        let provider = drive.provider().with_chunk_size(4);
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("scan.e57");
+        std::fs::write(&source, "point cloud!").unwrap();
+
+        let session = http_capture::start_in(&dir.path().join("captures")).unwrap();
+        provider.upload(&source, Path::new("scans/scan.e57")).await.unwrap();
+        let dest = dir.path().join("bracket.step");
+        provider.download(Path::new("parts/bracket.step"), &dest).await.unwrap();
+        let captured = http_capture::stop().unwrap();
+
+        // Captured responses still reach the provider whole
+        assert_eq!(drive.content("scans/scan.e57"), Some(b"point cloud!".to_vec()));
+        assert_eq!(std::fs::read(&dest).unwrap(), b"bracket");
+        let log = std::fs::read_to_string(&session.path).unwrap();
+        assert!(captured.exchanges >= 5 && log.lines().count() >= captured.exchanges);
+        assert!(log.contains("/upload/drive/v3/files") && log.contains("[redacted]"));
+        assert!(!log.contains(crate::providers::fake_drive::ACCESS_TOKEN) && !log.contains("upload_id=upload-"));
+        assert!(!log.contains("point cloud!") && !log.contains("\"bracket\""));
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_fails_calls() {
+        let drive = FakeDrive::start().await;
+        let provider = drive.provider();
+        assert!(provider.test_connection().await.unwrap());
+
+        drive.expire_token();
+        assert!(!provider.test_connection().await.unwrap());
+        assert!(provider.list_files(Path::new("")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drive_file_scope_needs_a_folder_it_can_see() {
+        let drive = FakeDrive::start().await;
+        drive.put("parts/bracket.step", "bracket");
+        assert!(drive.provider().lists_every_file());
+
+        // The fake's root folder is not one of its files, like a folder made in the web UI
+        let provider = drive.provider().with_scope(DriveScope::File);
+        assert!(!provider.lists_every_file());
+        assert!(!provider.test_connection().await.unwrap());
+        let listed = provider.list_files(Path::new("")).await;
+        assert!(matches!(listed, Err(UvcadError::ProviderError(ref e)) if e.contains("not created by UVCAD")), "{:?}", listed);
+    }
+
+    #[tokio::test]
+    async fn test_service_account_signs_in_without_a_user() {
+        let drive = FakeDrive::start().await;
+        drive.put("parts/bracket.step", "bracket");
+
+        let provider = drive.service_account_provider(Some("cad@example.com"));
+        assert!(provider.is_authenticated());
+        assert_eq!(provider.list_files(Path::new("")).await.unwrap().len(), 1);
+        assert!(provider.exists(Path::new("parts/bracket.step")).await.unwrap());
+        // The access token is kept until it is about to expire
+        assert_eq!(drive.sign_ins(), vec!["cad@example.com".to_string()]);
+
+        drive.service_account_provider(None).test_connection().await.unwrap();
+        assert_eq!(drive.sign_ins()[1], crate::providers::fake_drive::SERVICE_ACCOUNT_EMAIL);
+    }
+
+    #[tokio::test]
+    async fn test_trashed_files_stay_in_place_until_purged() {
+        let drive = FakeDrive::start().await;
+        drive.put("parts/bracket.step", "bracket");
+        drive.put("parts/plate.step", "plate");
+        let provider = drive.provider();
+
+        let handle = provider.trash(Path::new("parts/bracket.step"), Path::new("unused")).await.unwrap();
+        assert_eq!(drive.paths(), [PathBuf::from("parts/plate.step")]);
+        assert_eq!(drive.trashed(), [PathBuf::from("parts/bracket.step")]);
+        assert_eq!(provider.list_files(Path::new("")).await.unwrap().len(), 1);
+
+        provider.restore_trashed(&handle, Path::new("parts/bracket.step")).await.unwrap();
+        assert_eq!(drive.content("parts/bracket.step"), Some(b"bracket".to_vec()));
+
+        provider.trash(Path::new("parts/bracket.step"), Path::new("unused")).await.unwrap();
+        provider.purge_trashed(&handle).await.unwrap();
+        assert!(drive.trashed().is_empty());
+        // Gone already, e.g. the Drive trash was emptied
+        provider.purge_trashed(&handle).await.unwrap();
+        assert!(matches!(provider.restore_trashed(&handle, Path::new("parts/bracket.step")).await, Err(UvcadError::FileNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_engine_syncs_through_fake_drive() {
+        let drive = FakeDrive::start().await;
+        drive.put("parts/from-drive.step", "drive");
+        let local = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(local.path().join("parts")).unwrap();
+        std::fs::write(local.path().join("parts/from-local.step"), "local").unwrap();
+
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Fake Drive".to_string(), String::new()))
+        }).await.unwrap();
+        let engine = || SyncEngine::new(
+            profile_id,
+            Arc::new(Mutex::new(LocalFsProvider::new(local.path().to_path_buf()))),
+            Some(Arc::new(Mutex::new(drive.provider()))),
+            None,
+            db.clone(),
+        );
+
+        let first = engine().start_sync().await.unwrap();
+        assert_eq!(first.files_failed, 0);
+        assert_eq!(drive.content("parts/from-local.step"), Some(b"local".to_vec()));
+        assert_eq!(std::fs::read(local.path().join("parts/from-drive.step")).unwrap(), b"drive");
+
+        drive.put("parts/from-local.step", "edited on Drive");
+        let second = engine().start_sync().await.unwrap();
+        assert_eq!(second.files_failed, 0);
+        assert_eq!(std::fs::read(local.path().join("parts/from-local.step")).unwrap(), b"edited on Drive");
+    }
+
+    #[tokio::test]
+    async fn test_identical_files_are_uploaded_once() {
+        let drive = FakeDrive::start().await;
+        let local = tempfile::tempdir().unwrap();
+        for dir in ["a", "b", "c"] {
+            std::fs::create_dir_all(local.path().join(dir)).unwrap();
+            std::fs::write(local.path().join(dir).join("M8-bolt.step"), "standard part").unwrap();
+        }
+        std::fs::write(local.path().join("a/bracket.step"), "bracket").unwrap();
+
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Fake Drive".to_string(), String::new()))
+        }).await.unwrap();
+        let result = SyncEngine::new(
+            profile_id,
+            Arc::new(Mutex::new(LocalFsProvider::new(local.path().to_path_buf()))),
+            Some(Arc::new(Mutex::new(drive.provider()))),
+            None,
+            db,
+        ).start_sync().await.unwrap();
+
+        assert_eq!(result.files_failed, 0);
+        assert_eq!(drive.upload_count(), 2);
+        for dir in ["a", "b", "c"] {
+            assert_eq!(drive.content(format!("{}/M8-bolt.step", dir)), Some(b"standard part".to_vec()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_initial_upload_runs_in_parallel_and_resumes() {
+        let drive = FakeDrive::start().await;
+        drive.put("existing/6.step", "already there");
+        let local = tempfile::tempdir().unwrap();
+        for file in ["a/1.step", "a/2.step", "a/3.step", "b/c/4.step", "renders/5.png", "existing/6.step"] {
+            let path = local.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+
+        let database = Database::in_memory().unwrap();
+        database.initialize().unwrap();
+        let db = DbHandle::new(database);
+        let profile_id = db.call(|conn| {
+            DbOperations::create_sync_profile(conn, &SyncProfile::new("Fake Drive".to_string(), String::new()))
+        }).await.unwrap();
+        let engine = SyncEngine::new(
+            profile_id,
+            Arc::new(Mutex::new(LocalFsProvider::new(local.path().to_path_buf()))),
+            Some(Arc::new(Mutex::new(drive.provider()))),
+            None,
+            db.clone(),
+        ).with_connections(crate::models::file_state::FileLocation::GoogleDrive, (0..2)
+            .map(|_| Arc::new(Mutex::new(drive.provider())) as Arc<Mutex<dyn StorageProvider>>)
+            .collect());
+        let progress = std::sync::Mutex::new(Vec::new());
+        let record = |p: &crate::core::bootstrap::BootstrapProgress| progress.lock().unwrap().push(p.clone());
+
+        let result = engine.bootstrap_upload(&[PathBuf::from("renders")], &record).await.unwrap();
+        assert_eq!((result.files_synced, result.files_failed), (5, 0));
+        assert_eq!(drive.upload_count(), 5);
+        // Parallel uploads into the same new folder must not create it twice
+        let folders: Vec<String> = drive.folders().iter().map(|f| f.to_string_lossy().replace('\\', "/")).collect();
+        assert_eq!(folders, ["a", "b", "b/c", "existing", "renders"]);
+        let last = progress.lock().unwrap().last().cloned().unwrap();
+        assert_eq!((last.files_done, last.eta_secs), (5, Some(0)));
+        assert_eq!(last.current_file, "renders/5.png");
+
+        // An upload left running when the app closed is picked up from the queue
+        std::fs::write(local.path().join("a/7.step"), "late").unwrap();
+        db.call(move |conn| {
+            let now = chrono::Utc::now();
+            DbOperations::enqueue_transfer(conn, &crate::models::transfer::Transfer {
+                id: None,
+                profile_id,
+                file_path: "a/7.step".to_string(),
+                source: Some(crate::models::file_state::FileLocation::Local),
+                destination: crate::models::file_state::FileLocation::GoogleDrive,
+                moved_from: None,
+                status: crate::models::transfer::TransferStatus::InProgress,
+                priority: 0,
+                attempts: 0,
+                size_bytes: Some(4),
+                expected_hash: None,
+                expect_absent: true,
+                expected_size: None,
+                expected_modified: None,
+                reason: None,
+                error: None,
+                created_at: now,
+                updated_at: now,
+            })
+        }).await.unwrap();
+        let resumed = engine.bootstrap_upload(&[], &|_| {}).await.unwrap();
+        assert_eq!(resumed.files_synced, 1);
+        assert_eq!(drive.content("a/7.step"), Some(b"late".to_vec()));
+
+        // The uploaded files are recorded as in sync on both sides; only the file that
+        // already differed on Drive is left for the sync, which reports it
+        let after = engine.start_sync().await.unwrap();
+        assert_eq!((after.files_failed, after.files_conflict), (0, 1));
+        assert_eq!(drive.upload_count(), 6);
+    }
 }
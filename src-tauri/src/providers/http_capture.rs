@@ -0,0 +1,280 @@
+use crate::utils::error::{Result, UvcadError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, LOCATION};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Bodies are cut short in the log after this many bytes.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+const REDACTED: &str = "[redacted]";
+
+/// Headers whose values are credentials.
+const SECRET_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie"];
+
+/// Query parameters and JSON fields whose values are credentials. The
+/// `upload_id` of a resumable upload lets anyone holding it write the file.
+const SECRET_FIELDS: &[&str] = &["access_token", "refresh_token", "id_token", "client_secret", "key", "upload_id"];
+
+/// A capture log being written.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureSession {
+    pub path: PathBuf,
+    pub started_at: DateTime<Utc>,
+    /// Requests logged so far.
+    pub exchanges: usize,
+}
+
+struct Capture {
+    session: CaptureSession,
+    file: std::fs::File,
+}
+
+static CAPTURE: Lazy<Mutex<Option<Capture>>> = Lazy::new(|| Mutex::new(None));
+
+/// One request and its response as logged, one JSON object per line.
+#[derive(Debug, Default, Serialize)]
+struct Exchange {
+    at: DateTime<Utc>,
+    method: String,
+    url: String,
+    request_headers: Vec<(String, String)>,
+    request_body: Option<String>,
+    status: Option<u16>,
+    response_headers: Vec<(String, String)>,
+    response_body: Option<String>,
+    error: Option<String>,
+    elapsed_ms: u64,
+}
+
+/// Log every Google Drive request and response from now on, with credentials
+/// redacted, to a new file in the app's `captures` folder, e.g. to hand to
+/// Google support. A capture already running is ended.
+pub fn start() -> Result<CaptureSession> {
+    let project_dirs = ProjectDirs::from("com", "uvcad", "UVCAD")
+        .ok_or_else(|| UvcadError::InvalidConfig("Failed to get project directory".to_string()))?;
+    start_in(&project_dirs.data_dir().join("captures"))
+}
+
+/// Like `start`, writing the log to `dir`.
+pub fn start_in(dir: &Path) -> Result<CaptureSession> {
+    std::fs::create_dir_all(dir)?;
+    let started_at = Utc::now();
+    let path = dir.join(format!("drive-{}.jsonl", started_at.format("%Y%m%d-%H%M%S%3f")));
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+
+    let session = CaptureSession { path, started_at, exchanges: 0 };
+    *CAPTURE.lock().unwrap() = Some(Capture { session: session.clone(), file });
+    tracing::info!("Capturing Google Drive requests to {}", session.path.display());
+    Ok(session)
+}
+
+/// End the capture; None when none was running.
+pub fn stop() -> Option<CaptureSession> {
+    let capture = CAPTURE.lock().unwrap().take()?;
+    tracing::info!("Captured {} Google Drive requests to {}", capture.session.exchanges, capture.session.path.display());
+    Some(capture.session)
+}
+
+/// The capture running, if any.
+pub fn session() -> Option<CaptureSession> {
+    CAPTURE.lock().unwrap().as_ref().map(|capture| capture.session.clone())
+}
+
+fn is_capturing() -> bool {
+    CAPTURE.lock().unwrap().is_some()
+}
+
+/// Sending a request through the capture, if one is running.
+#[async_trait]
+pub trait CapturedSend {
+    async fn send_captured(self) -> reqwest::Result<reqwest::Response>;
+}
+
+#[async_trait]
+impl CapturedSend for reqwest::RequestBuilder {
+    async fn send_captured(self) -> reqwest::Result<reqwest::Response> {
+        if !is_capturing() {
+            return self.send().await;
+        }
+        let (client, request) = self.build_split();
+        let request = request?;
+        let mut exchange = Exchange {
+            at: Utc::now(),
+            method: request.method().to_string(),
+            url: redact_url(request.url().as_str()),
+            request_headers: redact_headers(request.headers()),
+            request_body: request.body().map(|body| match body.as_bytes() {
+                Some(bytes) => body_text(request.headers(), bytes),
+                None => "[streamed]".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let sent = client.execute(request).await;
+        let response = match sent {
+            Ok(response) => read_response(response, &mut exchange).await,
+            Err(e) => Err(e),
+        };
+        exchange.elapsed_ms = started.elapsed().as_millis() as u64;
+        if let Err(e) = &response {
+            exchange.error = Some(e.to_string());
+        }
+        record(&exchange);
+        response
+    }
+}
+
+/// Note the response in `exchange`. JSON bodies, which is how Drive answers
+/// everything but downloads, are read to be logged and handed on as read;
+/// anything else is left to stream.
+async fn read_response(response: reqwest::Response, exchange: &mut Exchange) -> reqwest::Result<reqwest::Response> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    exchange.status = Some(status.as_u16());
+    exchange.response_headers = redact_headers(&headers);
+    if !is_json(&headers) {
+        return Ok(response);
+    }
+
+    let version = response.version();
+    let body = response.bytes().await?;
+    exchange.response_body = Some(body_text(&headers, &body));
+    let mut read = http::Response::new(body);
+    *read.status_mut() = status;
+    *read.version_mut() = version;
+    *read.headers_mut() = headers;
+    Ok(reqwest::Response::from(read))
+}
+
+fn record(exchange: &Exchange) {
+    let mut capture = CAPTURE.lock().unwrap();
+    let Some(capture) = capture.as_mut() else {
+        return;
+    };
+    let written = serde_json::to_string(exchange)
+        .map_err(|e| e.to_string())
+        .and_then(|line| writeln!(capture.file, "{}", line).map_err(|e| e.to_string()));
+    match written {
+        Ok(()) => capture.session.exchanges += 1,
+        Err(e) => tracing::warn!("Failed to write to {}: {}", capture.session.path.display(), e),
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    SECRET_FIELDS.iter().any(|secret| secret.eq_ignore_ascii_case(name))
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers.get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("json"))
+}
+
+/// `url` with the values of credential parameters replaced.
+fn redact_url(url: &str) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    if !parsed.query_pairs().any(|(name, _)| is_secret(&name)) {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = parsed.query_pairs()
+        .map(|(name, value)| {
+            let value = if is_secret(&name) { REDACTED.to_string() } else { value.into_owned() };
+            (name.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers.iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                let text = value.to_str().unwrap_or("[binary]");
+                if *name == LOCATION { redact_url(text) } else { text.to_string() }
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// A body as logged: JSON with credential fields replaced, cut short when
+/// long; the size of anything else, such as file content.
+fn body_text(headers: &HeaderMap, body: &[u8]) -> String {
+    let json = is_json(headers).then(|| serde_json::from_slice::<serde_json::Value>(body).ok()).flatten();
+    let Some(mut json) = json else {
+        return format!("[{} bytes]", body.len());
+    };
+    redact_json(&mut json);
+    let mut text = json.to_string();
+    if text.len() > MAX_BODY_BYTES {
+        let mut end = MAX_BODY_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str(&format!("... [{} bytes]", body.len()));
+    }
+    text
+}
+
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if is_secret(name) {
+                    *field = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, AUTHORIZATION};
+
+    #[test]
+    fn test_credentials_are_redacted() {
+        assert_eq!(
+            redact_url("https://www.googleapis.com/upload/drive/v3/files?uploadType=resumable&upload_id=ABC123"),
+            "https://www.googleapis.com/upload/drive/v3/files?uploadType=resumable&upload_id=%5Bredacted%5D",
+        );
+        let listing = "https://www.googleapis.com/drive/v3/files?q='root'+in+parents&pageToken=2";
+        assert_eq!(redact_url(listing), listing);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer ya29.secret"));
+        headers.insert(LOCATION, HeaderValue::from_static("https://example.com/upload?upload_id=ABC123"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json; charset=UTF-8"));
+        let logged = format!("{:?}", redact_headers(&headers));
+        assert!(!logged.contains("ya29.secret") && !logged.contains("ABC123"), "{}", logged);
+        assert!(logged.contains("application/json"));
+
+        let body = br#"{"error":{"code":401},"tokens":[{"access_token":"ya29.secret","expires_in":3599}]}"#;
+        assert_eq!(
+            body_text(&headers, body),
+            r#"{"error":{"code":401},"tokens":[{"access_token":"[redacted]","expires_in":3599}]}"#,
+        );
+        // File content is never logged
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"));
+        assert_eq!(body_text(&headers, b"part geometry"), "[13 bytes]");
+    }
+}
@@ -1,5 +1,6 @@
-use crate::core::file_hasher;
+use crate::core::cad_normalizer::HashPolicy;
 use crate::providers::traits::{FileMetadata, StorageProvider};
+use crate::providers::walk;
 use crate::utils::error::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -8,11 +9,20 @@ use tokio::fs;
 
 pub struct LocalFsProvider {
     root_path: PathBuf,
+    hash_policy: HashPolicy,
 }
 
 impl LocalFsProvider {
     pub fn new(root_path: PathBuf) -> Self {
-        Self { root_path }
+        Self {
+            root_path,
+            hash_policy: HashPolicy::default(),
+        }
+    }
+
+    pub fn with_hash_policy(mut self, hash_policy: HashPolicy) -> Self {
+        self.hash_policy = hash_policy;
+        self
     }
 
     /// Convert a relative path to an absolute path under root_path.
@@ -40,7 +50,7 @@ impl LocalFsProvider {
                 let modified_dt: DateTime<Utc> = modified.into();
 
                 let hash = if metadata.is_file() {
-                    Some(file_hasher::compute_file_hash(absolute_path)?)
+                    Some(self.hash_policy.hash_file(absolute_path)?)
                 } else {
                     None
                 };
@@ -49,7 +59,8 @@ impl LocalFsProvider {
                     path: self.to_relative(absolute_path),
                     size: metadata.len(),
                     modified: modified_dt,
-                    hash,
+                    fingerprint: hash.as_ref().and_then(|h| h.fingerprint.clone()),
+                    hash: hash.map(|h| h.hash),
                     exists: true,
                 }))
             }
@@ -66,25 +77,33 @@ impl StorageProvider for LocalFsProvider {
 
     async fn list_files(&self, path: &Path) -> Result<Vec<FileMetadata>> {
         let full_path = self.to_absolute(path);
-        let mut files = Vec::new();
-
-        let mut entries = fs::read_dir(&full_path).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            let entry_path = entry.path();
-            let file_type = entry.file_type().await?;
-
-            if file_type.is_file() {
-                if let Some(metadata) = self.get_file_metadata_absolute(&entry_path).await? {
-                    files.push(metadata);
-                }
-            } else if file_type.is_dir() {
-                // Recursively list subdirectories using the absolute path
-                let subfiles = self.list_files(&entry_path).await?;
-                files.extend(subfiles);
-            }
-        }
+        let files = walk::walk(&full_path, false).await?;
+
+        walk::hash_files(files, &self.hash_policy)
+            .await
+            .into_iter()
+            .map(|(file, hash)| {
+                let modified: DateTime<Utc> = file.metadata.modified()?.into();
+                let hash = hash?;
+                Ok(FileMetadata {
+                    path: self.to_relative(&file.path),
+                    size: file.metadata.len(),
+                    modified,
+                    hash: Some(hash.hash),
+                    fingerprint: hash.fingerprint,
+                    exists: true,
+                })
+            })
+            .collect()
+    }
 
-        Ok(files)
+    async fn list_subtree(&self, path: &Path) -> Result<Option<Vec<FileMetadata>>> {
+        match fs::metadata(self.to_absolute(path)).await {
+            Ok(metadata) if metadata.is_dir() => Ok(Some(self.list_files(path).await?)),
+            Ok(_) => Ok(Some(self.get_metadata(path).await?.into_iter().collect())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Some(Vec::new())),
+            Err(e) => Err(e.into()),
+        }
     }
 
     async fn get_metadata(&self, path: &Path) -> Result<Option<FileMetadata>> {
@@ -115,12 +134,33 @@ impl StorageProvider for LocalFsProvider {
         Ok(())
     }
 
+    async fn copy(&self, from: &Path, to: &Path) -> Result<bool> {
+        let (from, to) = (self.to_absolute(from), self.to_absolute(to));
+        if !from.is_file() || to.exists() {
+            return Ok(false);
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::copy(&from, &to).await?;
+        Ok(true)
+    }
+
     async fn delete(&self, path: &Path) -> Result<()> {
         let full_path = self.to_absolute(path);
         fs::remove_file(&full_path).await?;
         Ok(())
     }
 
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let dest = self.to_absolute(to);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(self.to_absolute(from), dest).await?;
+        Ok(())
+    }
+
     async fn initialize(&mut self) -> Result<()> {
         // Ensure root directory exists
         if !self.root_path.exists() {
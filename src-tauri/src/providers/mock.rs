@@ -0,0 +1,207 @@
+use crate::core::file_hasher;
+use crate::providers::traits::{FileMetadata, StorageProvider};
+use crate::utils::error::{Result, UvcadError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A simulated loss of access to a location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outage {
+    /// The connection drops for good once this many transfers were attempted,
+    /// like a NAS going offline mid-run.
+    Disconnect { after_transfers: usize },
+    /// Every call is rejected, like an expired OAuth token.
+    ExpiredToken,
+}
+
+#[derive(Debug, Clone)]
+struct MockFile {
+    content: Vec<u8>,
+    modified: DateTime<Utc>,
+}
+
+/// In-memory storage for simulations and tests. Clones share the same files,
+/// so a scenario can keep a handle and edit files between engine runs.
+#[derive(Clone, Default)]
+pub struct MockProvider {
+    files: Arc<Mutex<HashMap<PathBuf, MockFile>>>,
+    latency: Duration,
+    /// Fail every nth upload or download; 0 never fails.
+    fail_every: usize,
+    operations: Arc<AtomicUsize>,
+    /// Storage quota in bytes; None is unlimited.
+    quota: Option<u64>,
+    outage: Option<Outage>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every call by `latency`.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Make every nth upload or download fail, like a flaky connection.
+    pub fn with_fail_every(mut self, n: usize) -> Self {
+        self.fail_every = n;
+        self
+    }
+
+    /// Limit the total size of stored files, like a cloud storage quota.
+    pub fn with_quota(mut self, bytes: u64) -> Self {
+        self.quota = Some(bytes);
+        self
+    }
+
+    /// Lose access to the storage as described by `outage`. Transfers are
+    /// counted from here on, not from the first use of the shared files.
+    pub fn with_outage(mut self, outage: Outage) -> Self {
+        self.outage = Some(outage);
+        self.operations = Arc::new(AtomicUsize::new(0));
+        self
+    }
+
+    pub fn put(&self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) {
+        self.files.lock().unwrap().insert(path.into(), MockFile {
+            content: content.into(),
+            modified: Utc::now(),
+        });
+    }
+
+    pub fn remove(&self, path: &Path) {
+        self.files.lock().unwrap().remove(path);
+    }
+
+    pub fn content(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.lock().unwrap().get(path).map(|f| f.content.clone())
+    }
+
+    async fn simulate(&self, transfer_of: Option<&Path>) -> Result<()> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        self.check_outage()?;
+        if let Some(path) = transfer_of {
+            let count = self.operations.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.fail_every > 0 && count.is_multiple_of(self.fail_every) {
+                return Err(UvcadError::ProviderError(format!("Simulated network failure for {}", path.display())));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_outage(&self) -> Result<()> {
+        match self.outage {
+            Some(Outage::Disconnect { after_transfers }) if self.operations.load(Ordering::SeqCst) >= after_transfers => {
+                Err(UvcadError::ProviderError("Simulated outage: connection lost".to_string()))
+            }
+            Some(Outage::ExpiredToken) => {
+                Err(UvcadError::AuthenticationFailed("Simulated outage: access token expired".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn metadata(path: &Path, file: &MockFile) -> FileMetadata {
+        FileMetadata {
+            path: path.to_path_buf(),
+            size: file.content.len() as u64,
+            modified: file.modified,
+            hash: Some(file_hasher::compute_bytes_hash(&file.content)),
+            fingerprint: None,
+            exists: true,
+        }
+    }
+}
+
+#[async_trait]
+impl StorageProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn list_files(&self, path: &Path) -> Result<Vec<FileMetadata>> {
+        self.simulate(None).await?;
+        let files = self.files.lock().unwrap();
+        Ok(files.iter()
+            .filter(|(p, _)| p.starts_with(path))
+            .map(|(p, f)| Self::metadata(p, f))
+            .collect())
+    }
+
+    async fn get_metadata(&self, path: &Path) -> Result<Option<FileMetadata>> {
+        self.simulate(None).await?;
+        Ok(self.files.lock().unwrap().get(path).map(|f| Self::metadata(path, f)))
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        self.simulate(None).await?;
+        Ok(self.files.lock().unwrap().contains_key(path))
+    }
+
+    async fn download(&self, path: &Path, dest: &Path) -> Result<PathBuf> {
+        self.simulate(Some(path)).await?;
+        let content = self.content(path)
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.display().to_string() })?;
+        tokio::fs::write(dest, content).await?;
+        Ok(dest.to_path_buf())
+    }
+
+    async fn upload(&self, source: &Path, dest: &Path) -> Result<()> {
+        self.simulate(Some(dest)).await?;
+        let content = tokio::fs::read(source).await?;
+        self.put(dest, content);
+        Ok(())
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<bool> {
+        self.simulate(None).await?;
+        let mut files = self.files.lock().unwrap();
+        if files.contains_key(to) {
+            return Ok(false);
+        }
+        let Some(file) = files.get(from).cloned() else {
+            return Ok(false);
+        };
+        files.insert(to.to_path_buf(), file);
+        Ok(true)
+    }
+
+    async fn delete(&self, path: &Path) -> Result<()> {
+        self.simulate(None).await?;
+        self.files.lock().unwrap().remove(path)
+            .map(|_| ())
+            .ok_or_else(|| UvcadError::FileNotFound { path: path.display().to_string() })
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.simulate(None).await?;
+        let mut files = self.files.lock().unwrap();
+        let file = files.remove(from)
+            .ok_or_else(|| UvcadError::FileNotFound { path: from.display().to_string() })?;
+        files.insert(to.to_path_buf(), file);
+        Ok(())
+    }
+
+    async fn initialize(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn test_connection(&self) -> Result<bool> {
+        Ok(self.check_outage().is_ok())
+    }
+
+    async fn available_space(&self) -> Result<Option<u64>> {
+        let used: u64 = self.files.lock().unwrap().values().map(|f| f.content.len() as u64).sum();
+        Ok(self.quota.map(|quota| quota.saturating_sub(used)))
+    }
+}
@@ -1,4 +1,9 @@
+#[cfg(test)]
+pub mod fake_drive;
 pub mod google_drive;
+pub mod http_capture;
 pub mod local_fs;
+pub mod mock;
 pub mod samba;
 pub mod traits;
+pub mod walk;
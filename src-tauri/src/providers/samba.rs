@@ -1,86 +1,99 @@
-use crate::core::file_hasher;
+use crate::core::cad_normalizer::HashPolicy;
 use crate::providers::traits::{FileMetadata, StorageProvider};
+use crate::providers::walk;
 use crate::utils::error::{Result, UvcadError};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::fs;
 
+/// Fallback share paths from the profile setting, one per line.
+pub fn fallback_paths(config: Option<&str>) -> Vec<PathBuf> {
+    config.unwrap_or("")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
 pub struct SambaProvider {
-    share_path: PathBuf,
-    mounted: bool,
+    /// The primary share followed by its fallbacks, e.g. a DR replica.
+    endpoints: Vec<PathBuf>,
+    /// Index of the endpoint that served the last operation.
+    active: AtomicUsize,
+    hash_policy: HashPolicy,
 }
 
 impl SambaProvider {
     pub fn new(share_path: PathBuf) -> Self {
         Self {
-            share_path,
-            mounted: false,
+            endpoints: vec![share_path],
+            active: AtomicUsize::new(0),
+            hash_policy: HashPolicy::default(),
         }
     }
 
-    /// Convert a relative path to an absolute path under share_path.
-    fn to_absolute(&self, path: &Path) -> PathBuf {
+    pub fn with_hash_policy(mut self, hash_policy: HashPolicy) -> Self {
+        self.hash_policy = hash_policy;
+        self
+    }
+
+    /// Paths to the same data, tried in order when the primary is unreachable.
+    pub fn with_fallbacks(mut self, paths: Vec<PathBuf>) -> Self {
+        self.endpoints.extend(paths);
+        self
+    }
+
+    /// Where `path` is on the share in use, e.g. to open it in the file manager.
+    pub fn absolute_path(&self, path: &Path) -> Result<PathBuf> {
+        Ok(Self::to_absolute(self.endpoint()?, path))
+    }
+
+    /// Convert a relative path to an absolute path under the share root.
+    fn to_absolute(root: &Path, path: &Path) -> PathBuf {
         if path.is_absolute() {
             path.to_path_buf()
         } else {
-            self.share_path.join(path)
+            root.join(path)
         }
     }
 
-    /// Convert an absolute path to a relative path from share_path.
-    fn to_relative(&self, path: &Path) -> PathBuf {
-        path.strip_prefix(&self.share_path)
+    /// Convert an absolute path to a relative path from the share root.
+    fn to_relative(root: &Path, path: &Path) -> PathBuf {
+        path.strip_prefix(root)
             .unwrap_or(path)
             .to_path_buf()
     }
 
-    async fn check_mount(&self) -> Result<bool> {
+    fn is_reachable(share_path: &Path) -> bool {
         // SMB shares are mounted as regular directories on both macOS (/Volumes/...)
         // and Windows (\\server\share or mapped drives). Check accessibility.
-        Ok(self.share_path.exists() && self.share_path.is_dir())
-    }
-
-    /// Recursively list files under the given absolute directory path.
-    fn list_files_recursive<'a>(&'a self, dir: &'a Path) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<FileMetadata>>> + Send + 'a>> {
-        Box::pin(async move {
-            let mut files = Vec::new();
-
-            let mut entries = fs::read_dir(dir).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let entry_path = entry.path();
-                let file_type = entry.file_type().await?;
-
-                if file_type.is_file() {
-                    match fs::metadata(&entry_path).await {
-                        Ok(metadata) => {
-                            let modified: DateTime<Utc> = metadata.modified()?.into();
-                            let hash = file_hasher::compute_file_hash(&entry_path).ok();
-
-                            files.push(FileMetadata {
-                                path: self.to_relative(&entry_path),
-                                size: metadata.len(),
-                                modified,
-                                hash,
-                                exists: true,
-                            });
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to get metadata for {}: {}", entry_path.display(), e);
-                        }
-                    }
-                } else if file_type.is_dir() {
-                    match self.list_files_recursive(&entry_path).await {
-                        Ok(subfiles) => files.extend(subfiles),
-                        Err(e) => {
-                            tracing::warn!("Failed to list directory {}: {}", entry_path.display(), e);
-                        }
-                    }
-                }
+        share_path.is_dir()
+    }
+
+    /// The share to use: the one that served the last operation while it stays
+    /// reachable, otherwise the first reachable endpoint in configured order.
+    fn endpoint(&self) -> Result<&Path> {
+        let active = self.active.load(Ordering::SeqCst);
+        let current = &self.endpoints[active];
+        if Self::is_reachable(current) {
+            return Ok(current);
+        }
+
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            if index != active && Self::is_reachable(endpoint) {
+                tracing::warn!("SMB share {} is unreachable, failing over to {}", current.display(), endpoint.display());
+                self.active.store(index, Ordering::SeqCst);
+                return Ok(endpoint);
             }
+        }
 
-            Ok(files)
-        })
+        Err(UvcadError::SmbNotAccessible(format!(
+            "No SMB share reachable at: {}",
+            self.endpoints.iter().map(|e| e.display().to_string()).collect::<Vec<_>>().join(", ")
+        )))
     }
 }
 
@@ -91,34 +104,55 @@ impl StorageProvider for SambaProvider {
     }
 
     async fn list_files(&self, path: &Path) -> Result<Vec<FileMetadata>> {
-        if !self.mounted {
-            return Err(UvcadError::SmbNotAccessible("SMB share not mounted".to_string()));
-        }
+        let root = self.endpoint()?;
+        let full_path = Self::to_absolute(root, path);
+        let files = walk::walk(&full_path, true).await?;
 
-        let full_path = self.to_absolute(path);
-        self.list_files_recursive(&full_path).await
+        walk::hash_files(files, &self.hash_policy)
+            .await
+            .into_iter()
+            .map(|(file, hash)| {
+                let modified: DateTime<Utc> = file.metadata.modified()?.into();
+                let hash = hash.ok();
+                Ok(FileMetadata {
+                    path: Self::to_relative(root, &file.path),
+                    size: file.metadata.len(),
+                    modified,
+                    fingerprint: hash.as_ref().and_then(|h| h.fingerprint.clone()),
+                    hash: hash.map(|h| h.hash),
+                    exists: true,
+                })
+            })
+            .collect()
     }
 
-    async fn get_metadata(&self, path: &Path) -> Result<Option<FileMetadata>> {
-        if !self.mounted {
-            return Err(UvcadError::SmbNotAccessible("SMB share not mounted".to_string()));
+    async fn list_subtree(&self, path: &Path) -> Result<Option<Vec<FileMetadata>>> {
+        match fs::metadata(Self::to_absolute(self.endpoint()?, path)).await {
+            Ok(metadata) if metadata.is_dir() => Ok(Some(self.list_files(path).await?)),
+            Ok(_) => Ok(Some(self.get_metadata(path).await?.into_iter().collect())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Some(Vec::new())),
+            Err(e) => Err(e.into()),
         }
+    }
 
-        let full_path = self.to_absolute(path);
+    async fn get_metadata(&self, path: &Path) -> Result<Option<FileMetadata>> {
+        let root = self.endpoint()?;
+        let full_path = Self::to_absolute(root, path);
         match fs::metadata(&full_path).await {
             Ok(metadata) => {
                 let modified: DateTime<Utc> = metadata.modified()?.into();
                 let hash = if metadata.is_file() {
-                    file_hasher::compute_file_hash(&full_path).ok()
+                    self.hash_policy.hash_file(&full_path).ok()
                 } else {
                     None
                 };
 
                 Ok(Some(FileMetadata {
-                    path: self.to_relative(&full_path),
+                    path: Self::to_relative(root, &full_path),
                     size: metadata.len(),
                     modified,
-                    hash,
+                    fingerprint: hash.as_ref().and_then(|h| h.fingerprint.clone()),
+                    hash: hash.map(|h| h.hash),
                     exists: true,
                 }))
             }
@@ -127,18 +161,18 @@ impl StorageProvider for SambaProvider {
     }
 
     async fn exists(&self, path: &Path) -> Result<bool> {
-        let full_path = self.to_absolute(path);
+        let full_path = Self::to_absolute(self.endpoint()?, path);
         Ok(full_path.exists())
     }
 
     async fn download(&self, path: &Path, dest: &Path) -> Result<PathBuf> {
-        let full_path = self.to_absolute(path);
+        let full_path = Self::to_absolute(self.endpoint()?, path);
         fs::copy(&full_path, dest).await?;
         Ok(dest.to_path_buf())
     }
 
     async fn upload(&self, source: &Path, dest: &Path) -> Result<()> {
-        let full_dest = self.to_absolute(dest);
+        let full_dest = Self::to_absolute(self.endpoint()?, dest);
 
         if let Some(parent) = full_dest.parent() {
             fs::create_dir_all(parent).await?;
@@ -148,25 +182,80 @@ impl StorageProvider for SambaProvider {
         Ok(())
     }
 
+    async fn copy(&self, from: &Path, to: &Path) -> Result<bool> {
+        let root = self.endpoint()?;
+        let (from, to) = (Self::to_absolute(root, from), Self::to_absolute(root, to));
+        if !from.is_file() || to.exists() {
+            return Ok(false);
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // The OS hands this to the file server as a server-side copy where the share supports it
+        fs::copy(&from, &to).await?;
+        Ok(true)
+    }
+
     async fn delete(&self, path: &Path) -> Result<()> {
-        let full_path = self.to_absolute(path);
+        let full_path = Self::to_absolute(self.endpoint()?, path);
         fs::remove_file(&full_path).await?;
         Ok(())
     }
 
-    async fn initialize(&mut self) -> Result<()> {
-        self.mounted = self.check_mount().await?;
-
-        if !self.mounted {
-            return Err(UvcadError::SmbNotAccessible(
-                format!("SMB share not accessible at: {}", self.share_path.display())
-            ));
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let root = self.endpoint()?;
+        let dest = Self::to_absolute(root, to);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).await?;
         }
-
+        fs::rename(Self::to_absolute(root, from), dest).await?;
         Ok(())
     }
 
+    async fn initialize(&mut self) -> Result<()> {
+        self.endpoint().map(|_| ())
+    }
+
     async fn test_connection(&self) -> Result<bool> {
-        self.check_mount().await
+        Ok(self.endpoint().is_ok())
+    }
+
+    fn served_by(&self) -> Option<String> {
+        Some(self.endpoints[self.active.load(Ordering::SeqCst)].display().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fails_over_to_reachable_share() {
+        let replica = tempfile::tempdir().unwrap();
+        std::fs::write(replica.path().join("part.step"), "ISO-10303-21;").unwrap();
+        let primary = replica.path().join("offline");
+
+        let provider = SambaProvider::new(primary.clone())
+            .with_fallbacks(fallback_paths(Some(&format!("\n  {}\n", replica.path().display()))));
+        assert_eq!(provider.served_by(), Some(primary.display().to_string()));
+
+        let files = provider.list_files(Path::new("")).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("part.step"));
+        assert_eq!(provider.served_by(), Some(replica.path().display().to_string()));
+        assert!(SambaProvider::new(primary).list_files(Path::new("")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_copies_on_the_share() {
+        let share = tempfile::tempdir().unwrap();
+        std::fs::write(share.path().join("part.step"), "ISO-10303-21;").unwrap();
+        let provider = SambaProvider::new(share.path().to_path_buf());
+
+        assert!(provider.copy(Path::new("part.step"), Path::new("copies/part.step")).await.unwrap());
+        assert_eq!(std::fs::read_to_string(share.path().join("copies/part.step")).unwrap(), "ISO-10303-21;");
+        // Never overwrites, and a missing source is not an error
+        assert!(!provider.copy(Path::new("part.step"), Path::new("copies/part.step")).await.unwrap());
+        assert!(!provider.copy(Path::new("missing.step"), Path::new("other.step")).await.unwrap());
     }
 }
@@ -1,4 +1,4 @@
-use crate::utils::error::Result;
+use crate::utils::error::{Result, UvcadError};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use std::path::{Path, PathBuf};
@@ -10,6 +10,8 @@ pub struct FileMetadata {
     pub size: u64,
     pub modified: DateTime<Utc>,
     pub hash: Option<String>,
+    /// Quick-hash fingerprint `hash` was computed from, for providers that hash locally.
+    pub fingerprint: Option<String>,
     pub exists: bool,
 }
 
@@ -22,6 +24,12 @@ pub trait StorageProvider: Send + Sync {
     /// List all files in the storage location
     async fn list_files(&self, path: &Path) -> Result<Vec<FileMetadata>>;
 
+    /// List the files under `path`, a directory or a single file, for providers
+    /// that can do so without listing everything; None otherwise
+    async fn list_subtree(&self, _path: &Path) -> Result<Option<Vec<FileMetadata>>> {
+        Ok(None)
+    }
+
     /// Get metadata for a specific file
     async fn get_metadata(&self, path: &Path) -> Result<Option<FileMetadata>>;
 
@@ -35,12 +43,99 @@ pub trait StorageProvider: Send + Sync {
     /// Upload a file from local location to this provider
     async fn upload(&self, source: &Path, dest: &Path) -> Result<()>;
 
+    /// Upload over a file only while it still has the content hash seen when the
+    /// upload was planned; fails with `ConflictDetected` if it changed or vanished
+    async fn upload_if_unchanged(&self, source: &Path, dest: &Path, expected_hash: &str) -> Result<()> {
+        let current = self.get_metadata(dest).await?;
+        if current.as_ref().and_then(|m| m.hash.as_deref()) != Some(expected_hash) {
+            return Err(UvcadError::ConflictDetected { path: dest.to_string_lossy().to_string() });
+        }
+        self.upload(source, dest).await
+    }
+
+    /// Copy a file within this provider without the content passing through this
+    /// computer where the storage allows it; `to` must not exist yet. False when
+    /// nothing was copied, e.g. `to` exists or the provider can't copy, in which
+    /// case callers fall back to uploading
+    async fn copy(&self, _from: &Path, _to: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Delete a file
     async fn delete(&self, path: &Path) -> Result<()>;
 
+    /// Rename or move a file, creating the folders above `to` as needed; `to`
+    /// must not exist yet
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Move a file to the trash instead of deleting it. Providers without a
+    /// trash of their own move it to `trashed`, in `core::trash::TRASH_FOLDER`.
+    /// Returns the handle `restore_trashed` and `purge_trashed` take
+    async fn trash(&self, path: &Path, trashed: &Path) -> Result<String> {
+        self.rename(path, trashed).await?;
+        Ok(trashed.to_string_lossy().to_string())
+    }
+
+    /// Move a trashed file back to `path`, which must not exist yet
+    async fn restore_trashed(&self, handle: &str, path: &Path) -> Result<()> {
+        self.rename(Path::new(handle), path).await
+    }
+
+    /// Delete a trashed file for good; nothing to do when it is gone already
+    async fn purge_trashed(&self, handle: &str) -> Result<()> {
+        if !self.exists(Path::new(handle)).await? {
+            return Ok(());
+        }
+        self.delete(Path::new(handle)).await
+    }
+
     /// Initialize/connect to the storage provider
     async fn initialize(&mut self) -> Result<()>;
 
     /// Test if the connection is working
     async fn test_connection(&self) -> Result<bool>;
+
+    /// Bytes that can still be stored, for providers with a storage quota;
+    /// None when there is no quota to check
+    async fn available_space(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Whether a listing shows every file stored there, so a file missing from
+    /// it was deleted
+    fn lists_every_file(&self) -> bool {
+        true
+    }
+
+    /// The endpoint that served the last operation, for providers that can fail
+    /// over between several paths to the same data
+    fn served_by(&self) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::file_hasher;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_upload_if_unchanged_refuses_changed_destination() {
+        let provider = MockProvider::new();
+        provider.put("a.step", "v1");
+        let scanned = file_hasher::compute_bytes_hash(b"v1");
+        let source = std::env::temp_dir().join(format!("uvcad_conditional_{}", std::process::id()));
+        tokio::fs::write(&source, "v2").await.unwrap();
+
+        provider.put("a.step", "edited elsewhere");
+        let refused = provider.upload_if_unchanged(&source, Path::new("a.step"), &scanned).await;
+        assert!(matches!(refused, Err(UvcadError::ConflictDetected { .. })));
+        assert_eq!(provider.content(Path::new("a.step")), Some(b"edited elsewhere".to_vec()));
+
+        let current = file_hasher::compute_bytes_hash(b"edited elsewhere");
+        provider.upload_if_unchanged(&source, Path::new("a.step"), &current).await.unwrap();
+        assert_eq!(provider.content(Path::new("a.step")), Some(b"v2".to_vec()));
+        tokio::fs::remove_file(&source).await.unwrap();
+    }
 }
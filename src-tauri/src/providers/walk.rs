@@ -0,0 +1,122 @@
+use crate::core::cad_normalizer::{FileHash, HashPolicy};
+use crate::utils::error::{Result, UvcadError};
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use std::collections::VecDeque;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Directories read, and files hashed, at the same time during a walk.
+const WALK_CONCURRENCY: usize = 16;
+
+/// A regular file found by `walk`.
+pub struct WalkedFile {
+    /// Absolute path of the file
+    pub path: PathBuf,
+    pub metadata: Metadata,
+}
+
+/// The subdirectories and files directly inside one directory.
+type DirListing = (Vec<PathBuf>, Vec<WalkedFile>);
+
+async fn read_dir(dir: PathBuf) -> (PathBuf, Result<DirListing>) {
+    let result = async {
+        let mut subdirs = Vec::new();
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let entry_path = entry.path();
+            let file_type = entry.file_type().await?;
+
+            if file_type.is_file() {
+                match fs::metadata(&entry_path).await {
+                    Ok(metadata) => files.push(WalkedFile { path: entry_path, metadata }),
+                    Err(e) => tracing::warn!("Failed to get metadata for {}: {}", entry_path.display(), e),
+                }
+            } else if file_type.is_dir() {
+                subdirs.push(entry_path);
+            }
+        }
+        Ok((subdirs, files))
+    }
+    .await;
+    (dir, result)
+}
+
+/// Every regular file under `root`, sorted by path. Up to `WALK_CONCURRENCY`
+/// directories are read at once, so wide trees don't wait on one directory at a
+/// time. Fails if `root` can't be read; a subdirectory that can't be read fails
+/// the walk too, unless `skip_unreadable`, in which case it is logged and skipped.
+pub async fn walk(root: &Path, skip_unreadable: bool) -> Result<Vec<WalkedFile>> {
+    let mut files = Vec::new();
+    let mut waiting = VecDeque::new();
+    let mut reading = FuturesUnordered::new();
+    reading.push(read_dir(root.to_path_buf()));
+
+    while let Some((dir, result)) = reading.next().await {
+        match result {
+            Ok((subdirs, found)) => {
+                waiting.extend(subdirs);
+                files.extend(found);
+            }
+            Err(e) if skip_unreadable && dir != root => {
+                tracing::warn!("Failed to list directory {}: {}", dir.display(), e);
+            }
+            Err(e) => return Err(e),
+        }
+
+        while reading.len() < WALK_CONCURRENCY {
+            match waiting.pop_front() {
+                Some(next) => reading.push(read_dir(next)),
+                None => break,
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Hash walked files with `policy` on the blocking pool, up to
+/// `WALK_CONCURRENCY` at once. Results keep the order of `files`.
+pub async fn hash_files(files: Vec<WalkedFile>, policy: &HashPolicy) -> Vec<(WalkedFile, Result<FileHash>)> {
+    stream::iter(files)
+        .map(|file| {
+            let policy = policy.clone();
+            async move {
+                let path = file.path.clone();
+                let hash = tokio::task::spawn_blocking(move || policy.hash_file(&path))
+                    .await
+                    .unwrap_or_else(|e| Err(UvcadError::ProviderError(format!("Hashing task failed: {}", e))));
+                (file, hash)
+            }
+        })
+        .buffered(WALK_CONCURRENCY)
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_walk_finds_nested_files_in_order() {
+        let root = tempfile::tempdir().unwrap();
+        for i in 0..40 {
+            let dir = root.path().join(format!("d{:02}", i)).join("sub");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("part.step"), format!("{}", i)).unwrap();
+        }
+        std::fs::write(root.path().join("top.step"), "top").unwrap();
+
+        let files = walk(root.path(), false).await.unwrap();
+        assert_eq!(files.len(), 41);
+        assert!(files.windows(2).all(|pair| pair[0].path < pair[1].path));
+        assert_eq!(files.last().unwrap().path, root.path().join("top.step"));
+
+        let hashed = hash_files(files, &HashPolicy::default()).await;
+        assert!(hashed.iter().all(|(_, hash)| hash.is_ok()));
+        assert!(walk(&root.path().join("missing"), true).await.is_err());
+    }
+}
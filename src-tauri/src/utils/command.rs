@@ -0,0 +1,96 @@
+use crate::utils::error::{Result, UvcadError};
+use std::process::Command;
+
+/// Build the command for a configured template such as
+/// `"C:\Program Files\Windows Defender\MpCmdRun.exe" -Scan -File {path}`.
+/// Words are split on whitespace; double quotes keep a word together and
+/// backslashes are taken literally, so Windows paths need no escaping.
+/// `{name}` placeholders are replaced with the matching value; a template
+/// without any of them gets every value appended as an argument, in order.
+pub fn build(template: &str, values: &[(&str, &str)]) -> Result<Command> {
+    let mut parts = split(template)?;
+    let placeholders: Vec<(String, &str)> = values.iter()
+        .map(|(name, value)| (format!("{{{}}}", name), *value))
+        .collect();
+    if parts.iter().any(|part| placeholders.iter().any(|(placeholder, _)| part.contains(placeholder.as_str()))) {
+        for part in parts.iter_mut() {
+            for (placeholder, value) in &placeholders {
+                *part = part.replace(placeholder.as_str(), value);
+            }
+        }
+    } else {
+        parts.extend(values.iter().map(|(_, value)| value.to_string()));
+    }
+
+    let mut command = Command::new(&parts[0]);
+    command.args(&parts[1..]);
+    Ok(command)
+}
+
+fn split(template: &str) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in template.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    parts.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err(UvcadError::InvalidConfig(format!("Unclosed quote in command: {}", template)));
+    }
+    if in_word {
+        parts.push(current);
+    }
+    match parts.first() {
+        Some(program) if !program.is_empty() => Ok(parts),
+        _ => Err(UvcadError::InvalidConfig("No command configured".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|word| word.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_quoted_program_and_placeholders() {
+        let command = build(
+            r#""C:\Program Files\Windows Defender\MpCmdRun.exe" -Scan -ScanType 3 -File {path}"#,
+            &[("path", r"C:\Users\cad\My Drawings\a.dwg")],
+        ).unwrap();
+        assert_eq!(words(&command), [
+            r"C:\Program Files\Windows Defender\MpCmdRun.exe", "-Scan", "-ScanType", "3", "-File",
+            r"C:\Users\cad\My Drawings\a.dwg",
+        ]);
+
+        let command = build("convert --to=\"{output}\" {input}", &[("input", "a b.dwg"), ("output", "a b.pdf")]).unwrap();
+        assert_eq!(words(&command), ["convert", "--to=a b.pdf", "a b.dwg"]);
+
+        let command = build("diff  \"\" -u", &[("left", "a"), ("right", "b")]).unwrap();
+        assert_eq!(words(&command), ["diff", "", "-u", "a", "b"]);
+
+        assert!(build("\"C:\\Program Files\\tool.exe -x", &[]).is_err());
+        assert!(build("   ", &[]).is_err());
+    }
+}
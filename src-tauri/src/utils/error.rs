@@ -11,6 +11,9 @@ pub enum UvcadError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] rusqlite::Error),
 
+    #[error("Database unavailable: {0}")]
+    DatabaseUnavailable(String),
+
     #[error("File not found: {path}")]
     FileNotFound { path: String },
 
@@ -35,6 +38,9 @@ pub enum UvcadError {
     #[error("Token storage error: {0}")]
     TokenStorageError(#[from] keyring::Error),
 
+    #[error("Secret not found: {0}")]
+    SecretNotFound(String),
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
@@ -43,13 +49,85 @@ pub enum UvcadError {
 
     #[error("Sync failed: {0}")]
     SyncFailed(String),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(String),
+
+    #[error("Malformed file: {0}")]
+    MalformedFile(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("File quarantined by the malware scanner: {path}")]
+    Quarantined { path: String },
+
+    #[error("Not attempted: {0} kept failing during this sync")]
+    LocationSkipped(String),
 }
 
 pub type Result<T> = std::result::Result<T, UvcadError>;
 
+impl UvcadError {
+    /// Message catalog code, see `utils::i18n`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            UvcadError::AuthenticationFailed(_) => "error.authentication_failed",
+            UvcadError::NetworkError(_) => "error.network",
+            UvcadError::DatabaseError(_) => "error.database",
+            UvcadError::DatabaseUnavailable(_) => "error.database_unavailable",
+            UvcadError::FileNotFound { .. } => "error.file_not_found",
+            UvcadError::ConflictDetected { .. } => "error.conflict_detected",
+            UvcadError::SmbNotAccessible(_) => "error.smb_not_accessible",
+            UvcadError::HashMismatch { .. } => "error.hash_mismatch",
+            UvcadError::InvalidConfig(_) => "error.invalid_config",
+            UvcadError::IoError(_) => "error.io",
+            UvcadError::OAuthError(_) => "error.oauth",
+            UvcadError::TokenStorageError(_) => "error.token_storage",
+            UvcadError::SecretNotFound(_) => "error.secret_not_found",
+            UvcadError::SerializationError(_) => "error.serialization",
+            UvcadError::ProviderError(_) => "error.provider",
+            UvcadError::SyncFailed(_) => "error.sync_failed",
+            UvcadError::ArchiveError(_) => "error.archive",
+            UvcadError::MalformedFile(_) => "error.malformed_file",
+            UvcadError::PermissionDenied(_) => "error.permission_denied",
+            UvcadError::Quarantined { .. } => "error.quarantined",
+            UvcadError::LocationSkipped(_) => "error.location_skipped",
+        }
+    }
+
+    /// The error in the current locale.
+    pub fn localized(&self) -> String {
+        let args = match self {
+            UvcadError::FileNotFound { path }
+            | UvcadError::ConflictDetected { path }
+            | UvcadError::HashMismatch { path }
+            | UvcadError::Quarantined { path } => [("path", path.clone())],
+            UvcadError::AuthenticationFailed(detail)
+            | UvcadError::DatabaseUnavailable(detail)
+            | UvcadError::SmbNotAccessible(detail)
+            | UvcadError::InvalidConfig(detail)
+            | UvcadError::OAuthError(detail)
+            | UvcadError::SecretNotFound(detail)
+            | UvcadError::ProviderError(detail)
+            | UvcadError::SyncFailed(detail)
+            | UvcadError::ArchiveError(detail)
+            | UvcadError::MalformedFile(detail)
+            | UvcadError::PermissionDenied(detail)
+            | UvcadError::LocationSkipped(detail) => [("detail", detail.clone())],
+            UvcadError::NetworkError(e) => [("detail", e.to_string())],
+            UvcadError::DatabaseError(e) => [("detail", e.to_string())],
+            UvcadError::IoError(e) => [("detail", e.to_string())],
+            UvcadError::TokenStorageError(e) => [("detail", e.to_string())],
+            UvcadError::SerializationError(e) => [("detail", e.to_string())],
+        };
+        crate::utils::i18n::tr(self.code(), &args)
+    }
+}
+
 // Implement conversion to String for Tauri command results
 impl From<UvcadError> for String {
     fn from(error: UvcadError) -> Self {
-        error.to_string()
+        error.localized()
     }
 }
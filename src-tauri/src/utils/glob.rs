@@ -0,0 +1,43 @@
+// Minimal case-insensitive glob matching used by user-written rules
+// (`*` matches any run of characters, `?` exactly one).
+
+/// Match `text` against `pattern`, returning the text matched by each `*` in order.
+pub fn captures(pattern: &str, text: &str) -> Option<Vec<String>> {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let original: Vec<char> = text.chars().collect();
+    let lowered: Vec<char> = text.to_lowercase().chars().collect();
+    if lowered.len() != original.len() {
+        return None;
+    }
+
+    let mut spans = Vec::new();
+    if !glob_match(&pattern, &lowered, 0, &mut spans) {
+        return None;
+    }
+
+    Some(spans.into_iter()
+        .map(|(start, end)| original[start..end].iter().collect())
+        .collect())
+}
+
+pub fn matches(pattern: &str, text: &str) -> bool {
+    captures(pattern, text).is_some()
+}
+
+fn glob_match(pattern: &[char], text: &[char], offset: usize, spans: &mut Vec<(usize, usize)>) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => {
+            for len in 0..=text.len() {
+                spans.push((offset, offset + len));
+                if glob_match(&pattern[1..], &text[len..], offset + len, spans) {
+                    return true;
+                }
+                spans.pop();
+            }
+            false
+        }
+        Some('?') => !text.is_empty() && glob_match(&pattern[1..], &text[1..], offset + 1, spans),
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..], offset + 1, spans),
+    }
+}
@@ -0,0 +1,125 @@
+use crate::utils::error::{Result, UvcadError};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Languages with a message catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Ja,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Ja => "ja",
+        }
+    }
+
+    /// Accepts tags like `de`, `de-AT` or `ja_JP.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_', '.']).next().unwrap_or("").to_lowercase();
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "ja" => Some(Locale::Ja),
+            _ => None,
+        }
+    }
+
+    /// Parse the profile setting; unset means the system language.
+    pub fn from_config(config: Option<&str>) -> Result<Self> {
+        match config.filter(|c| !c.trim().is_empty()) {
+            Some(tag) => Self::from_tag(tag)
+                .ok_or_else(|| UvcadError::InvalidConfig(format!("Unsupported language: {}", tag))),
+            None => Ok(Self::system()),
+        }
+    }
+
+    fn system() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::from_tag(&value))
+            .unwrap_or_default()
+    }
+
+    fn catalog(&self) -> &'static HashMap<String, String> {
+        match self {
+            Locale::En => &EN,
+            Locale::De => &DE,
+            Locale::Ja => &JA,
+        }
+    }
+}
+
+fn parse_catalog(json: &str) -> HashMap<String, String> {
+    serde_json::from_str(json).expect("message catalog is valid JSON")
+}
+
+static EN: Lazy<HashMap<String, String>> = Lazy::new(|| parse_catalog(include_str!("../../locales/en.json")));
+static DE: Lazy<HashMap<String, String>> = Lazy::new(|| parse_catalog(include_str!("../../locales/de.json")));
+static JA: Lazy<HashMap<String, String>> = Lazy::new(|| parse_catalog(include_str!("../../locales/ja.json")));
+
+static LOCALE: Lazy<RwLock<Locale>> = Lazy::new(|| RwLock::new(Locale::system()));
+
+pub fn set_locale(locale: Locale) {
+    *LOCALE.write().unwrap_or_else(|e| e.into_inner()) = locale;
+}
+
+pub fn locale() -> Locale {
+    *LOCALE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// All messages of the current locale, with English filling any gaps.
+pub fn catalog() -> HashMap<String, String> {
+    let mut messages = EN.clone();
+    messages.extend(locale().catalog().iter().map(|(k, v)| (k.clone(), v.clone())));
+    messages
+}
+
+/// Look up `code` in `locale` and fill in its `{name}` placeholders. Falls back
+/// to English, then to the code itself.
+pub fn tr_in(locale: Locale, code: &str, args: &[(&str, String)]) -> String {
+    let template = locale.catalog().get(code)
+        .or_else(|| EN.get(code))
+        .map(String::as_str)
+        .unwrap_or(code);
+
+    args.iter().fold(template.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// `tr_in` with the current locale.
+pub fn tr(code: &str, args: &[(&str, String)]) -> String {
+    tr_in(locale(), code, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_have_same_codes() {
+        for locale in [Locale::De, Locale::Ja] {
+            let mut missing: Vec<_> = EN.keys().filter(|k| !locale.catalog().contains_key(*k)).collect();
+            missing.sort();
+            assert!(missing.is_empty(), "{} is missing {:?}", locale.as_str(), missing);
+        }
+    }
+
+    #[test]
+    fn test_translate_with_placeholders() {
+        let args = [("path", "a/b.step".to_string())];
+        assert_eq!(tr_in(Locale::En, "error.file_not_found", &args), "File not found: a/b.step");
+        assert_eq!(tr_in(Locale::De, "error.file_not_found", &args), "Datei nicht gefunden: a/b.step");
+        assert_eq!(tr_in(Locale::Ja, "unknown.code", &args), "unknown.code");
+        assert_eq!(Locale::from_tag("ja_JP.UTF-8"), Some(Locale::Ja));
+    }
+}
@@ -1,45 +1,47 @@
-use crate::utils::error::Result;
-use keyring::Entry;
+use crate::utils::error::{Result, UvcadError};
+use crate::utils::secrets::{self, SecretStore};
 use serde::{Deserialize, Serialize};
 
-const SERVICE_NAME: &str = "com.uvcad.app";
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OAuthTokens {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub expires_at: Option<i64>,
+    /// The scopes granted, space-separated; None for tokens stored before
+    /// they were recorded.
+    #[serde(default)]
+    pub scope: Option<String>,
 }
 
+/// Tokens in the configured secret store (see `secrets::select_backend`).
 pub struct TokenManager {
-    entry: Entry,
+    store: Box<dyn SecretStore>,
+    name: String,
 }
 
 impl TokenManager {
     pub fn new(provider: &str) -> Result<Self> {
-        let entry = Entry::new(SERVICE_NAME, provider)?;
-        Ok(Self { entry })
+        Ok(Self { store: secrets::store()?, name: provider.to_string() })
     }
 
     pub fn store_tokens(&self, tokens: &OAuthTokens) -> Result<()> {
         let json = serde_json::to_string(tokens)?;
-        self.entry.set_password(&json)?;
-        Ok(())
+        self.store.set(&self.name, &json)
     }
 
     pub fn get_tokens(&self) -> Result<OAuthTokens> {
-        let json = self.entry.get_password()?;
+        let json = self.store.get(&self.name)?
+            .ok_or_else(|| UvcadError::SecretNotFound(self.name.clone()))?;
         let tokens = serde_json::from_str(&json)?;
         Ok(tokens)
     }
 
     pub fn delete_tokens(&self) -> Result<()> {
-        self.entry.delete_password()?;
-        Ok(())
+        self.store.delete(&self.name)
     }
 
     pub fn has_tokens(&self) -> bool {
-        self.entry.get_password().is_ok()
+        matches!(self.store.get(&self.name), Ok(Some(_)))
     }
 }
 
@@ -50,30 +52,28 @@ pub struct OAuthCredentials {
 }
 
 pub struct CredentialManager {
-    entry: Entry,
+    store: Box<dyn SecretStore>,
+    name: String,
 }
 
 impl CredentialManager {
     pub fn new(provider: &str) -> Result<Self> {
-        let key = format!("{}_credentials", provider);
-        let entry = Entry::new(SERVICE_NAME, &key)?;
-        Ok(Self { entry })
+        Ok(Self { store: secrets::store()?, name: format!("{}_credentials", provider) })
     }
 
     pub fn store_credentials(&self, creds: &OAuthCredentials) -> Result<()> {
         let json = serde_json::to_string(creds)?;
-        self.entry.set_password(&json)?;
-        Ok(())
+        self.store.set(&self.name, &json)
     }
 
     pub fn get_credentials(&self) -> Result<OAuthCredentials> {
-        let json = self.entry.get_password()?;
+        let json = self.store.get(&self.name)?
+            .ok_or_else(|| UvcadError::SecretNotFound(self.name.clone()))?;
         let creds = serde_json::from_str(&json)?;
         Ok(creds)
     }
 
     pub fn delete_credentials(&self) -> Result<()> {
-        self.entry.delete_password()?;
-        Ok(())
+        self.store.delete(&self.name)
     }
 }
@@ -0,0 +1,93 @@
+use crate::utils::error::{Result, UvcadError};
+use once_cell::sync::OnceCell;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{
+    fmt::{self, format::FmtSpan},
+    prelude::*,
+    reload, EnvFilter, Registry,
+};
+
+/// The current filter: a base level plus modules logged at debug level on top.
+struct Verbosity {
+    level: LevelFilter,
+    debug_modules: BTreeSet<String>,
+}
+
+struct Logging {
+    handle: reload::Handle<EnvFilter, Registry>,
+    verbosity: Mutex<Verbosity>,
+}
+
+static LOGGING: OnceCell<Logging> = OnceCell::new();
+
+/// Install the global subscriber. `RUST_LOG` directives still apply at startup;
+/// changing the verbosity at runtime replaces them.
+pub fn init() {
+    let (filter, handle) = reload::Layer::new(EnvFilter::from_default_env().add_directive(LevelFilter::INFO.into()));
+    tracing_subscriber::registry()
+        .with(filter)
+        // Closing spans log their duration, e.g. of each scan and sync run
+        .with(fmt::layer().with_span_events(FmtSpan::CLOSE))
+        .init();
+
+    let verbosity = Mutex::new(Verbosity { level: LevelFilter::INFO, debug_modules: BTreeSet::new() });
+    let _ = LOGGING.set(Logging { handle, verbosity });
+}
+
+/// Set the base log level (`error`, `warn`, `info`, `debug`, `trace` or `off`),
+/// keeping modules switched to debug level.
+pub fn set_level(level: &str) -> Result<()> {
+    let level = level.trim().parse::<LevelFilter>()
+        .map_err(|_| UvcadError::InvalidConfig(format!("Unknown log level: {}", level)))?;
+    update(|verbosity| verbosity.level = level)
+}
+
+/// Log one module at debug level regardless of the base level, e.g.
+/// `uvcad::providers::google_drive` or a dependency such as `reqwest`.
+pub fn enable_debug_for(module: &str) -> Result<()> {
+    let module = module.trim();
+    if module.is_empty() || format!("{}=debug", module).parse::<tracing_subscriber::filter::Directive>().is_err() {
+        return Err(UvcadError::InvalidConfig(format!("Invalid module path: {}", module)));
+    }
+    update(|verbosity| {
+        verbosity.debug_modules.insert(module.to_string());
+    })
+}
+
+fn update(change: impl FnOnce(&mut Verbosity)) -> Result<()> {
+    let logging = LOGGING.get()
+        .ok_or_else(|| UvcadError::InvalidConfig("Logging is not initialized".to_string()))?;
+    let mut verbosity = logging.verbosity.lock().unwrap();
+    change(&mut verbosity);
+
+    let spec = filter_spec(verbosity.level, &verbosity.debug_modules);
+    logging.handle.reload(EnvFilter::new(&spec))
+        .map_err(|e| UvcadError::InvalidConfig(format!("Failed to reload log filter: {}", e)))?;
+    tracing::info!("Log filter set to {}", spec);
+    Ok(())
+}
+
+/// The `EnvFilter` directives for a base level and debug modules. Modules are
+/// left out while the base level is already debug or trace, so they don't end
+/// up quieter than everything else.
+fn filter_spec(level: LevelFilter, debug_modules: &BTreeSet<String>) -> String {
+    let modules = debug_modules.iter().filter(|_| level < LevelFilter::DEBUG);
+    std::iter::once(level.to_string().to_lowercase())
+        .chain(modules.map(|module| format!("{}=debug", module)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_spec_keeps_debug_modules() {
+        let modules: BTreeSet<String> = ["uvcad::providers::samba".to_string(), "reqwest".to_string()].into();
+        assert_eq!(filter_spec(LevelFilter::WARN, &modules), "warn,reqwest=debug,uvcad::providers::samba=debug");
+        assert_eq!(filter_spec(LevelFilter::TRACE, &modules), "trace");
+    }
+}
@@ -1,3 +1,9 @@
+pub mod command;
 pub mod crypto;
 pub mod error;
+pub mod glob;
+pub mod i18n;
 pub mod keyring;
+pub mod logging;
+pub mod secrets;
+pub mod unc;
@@ -0,0 +1,295 @@
+use crate::utils::crypto;
+use crate::utils::error::{Result, UvcadError};
+use directories::ProjectDirs;
+use keyring::Entry;
+use once_cell::sync::Lazy;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+/// Keyring service the managers have always used, so existing entries keep working.
+const SERVICE_NAME: &str = "com.uvcad.app";
+
+/// Overrides the configured backend, e.g. `UVCAD_SECRET_STORE=env` on CI.
+const BACKEND_ENV_VAR: &str = "UVCAD_SECRET_STORE";
+
+/// Passphrase for the encrypted file backend. Without it a random key file is
+/// created next to the secrets file, which only guards against casual reading.
+const PASSPHRASE_ENV_VAR: &str = "UVCAD_SECRETS_PASSPHRASE";
+
+/// Prefix of environment variables read by the environment backend.
+const SECRET_ENV_PREFIX: &str = "UVCAD_SECRET_";
+
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Where tokens and credentials are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretBackend {
+    /// The OS keyring (Keychain, Credential Manager, Secret Service).
+    Keyring,
+    /// An AES-GCM encrypted file in the app data directory.
+    EncryptedFile,
+    /// `UVCAD_SECRET_<NAME>` environment variables, for CI and headless runs.
+    /// Values written at runtime (e.g. refreshed tokens) live in memory only.
+    Environment,
+}
+
+impl SecretBackend {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SecretBackend::Keyring => "keyring",
+            SecretBackend::EncryptedFile => "file",
+            SecretBackend::Environment => "env",
+        }
+    }
+
+    pub fn from_str_opt(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "keyring" => Some(SecretBackend::Keyring),
+            "file" => Some(SecretBackend::EncryptedFile),
+            "env" => Some(SecretBackend::Environment),
+            _ => None,
+        }
+    }
+}
+
+/// Named secret storage. Values are opaque strings (the managers store JSON).
+pub trait SecretStore: Send + Sync {
+    fn get(&self, name: &str) -> Result<Option<String>>;
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+static BACKEND: Lazy<RwLock<SecretBackend>> = Lazy::new(|| RwLock::new(SecretBackend::Keyring));
+
+/// Choose the backend from the profile setting; the environment override wins.
+pub fn select_backend(configured: Option<&str>) -> Result<SecretBackend> {
+    let override_value = std::env::var(BACKEND_ENV_VAR).ok();
+    let backend = match override_value.as_deref().or(configured).filter(|s| !s.trim().is_empty()) {
+        Some(name) => SecretBackend::from_str_opt(name)
+            .ok_or_else(|| UvcadError::InvalidConfig(format!("Unknown secret store: {}", name)))?,
+        None => SecretBackend::Keyring,
+    };
+
+    let mut current = BACKEND.write().unwrap_or_else(|e| e.into_inner());
+    if *current != backend {
+        tracing::info!("Using {} secret store", backend.as_str());
+        *current = backend;
+    }
+    Ok(backend)
+}
+
+/// The store for the selected backend.
+pub fn store() -> Result<Box<dyn SecretStore>> {
+    let backend = *BACKEND.read().unwrap_or_else(|e| e.into_inner());
+    Ok(match backend {
+        SecretBackend::Keyring => Box::new(KeyringStore),
+        SecretBackend::EncryptedFile => Box::new(FileStore::in_data_dir()?),
+        SecretBackend::Environment => Box::new(EnvStore),
+    })
+}
+
+pub struct KeyringStore;
+
+impl SecretStore for KeyringStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match Entry::new(SERVICE_NAME, name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        Entry::new(SERVICE_NAME, name)?.set_password(value)?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        match Entry::new(SERVICE_NAME, name)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Serializes read-modify-write cycles on the secrets file.
+static FILE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// All secrets in one JSON map, stored as `salt || nonce || ciphertext`.
+pub struct FileStore {
+    path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf, key_path: PathBuf) -> Self {
+        Self { path, key_path }
+    }
+
+    fn in_data_dir() -> Result<Self> {
+        let project_dirs = ProjectDirs::from("com", "uvcad", "UVCAD")
+            .ok_or_else(|| UvcadError::InvalidConfig("Failed to get project directory".to_string()))?;
+        let dir = project_dirs.data_dir();
+        Ok(Self::new(dir.join("secrets.enc"), dir.join("secrets.key")))
+    }
+
+    /// The passphrase from the environment, or the contents of the key file
+    /// (created on first use).
+    fn passphrase(&self) -> Result<Vec<u8>> {
+        if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV_VAR) {
+            return Ok(passphrase.into_bytes());
+        }
+        if let Ok(key) = std::fs::read(&self.key_path) {
+            return Ok(key);
+        }
+
+        let key = hex::encode(random_bytes::<32>()?).into_bytes();
+        if let Some(parent) = self.key_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.key_path, &key)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.key_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(key)
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+            salt,
+            &self.passphrase()?,
+            &mut key,
+        );
+        Ok(key)
+    }
+
+    fn load(&self) -> Result<HashMap<String, String>> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        if data.len() < SALT_LEN {
+            return Err(UvcadError::InvalidConfig("Secrets file is corrupt".to_string()));
+        }
+
+        let (salt, encrypted) = data.split_at(SALT_LEN);
+        let plaintext = crypto::decrypt(encrypted, &self.derive_key(salt)?)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn save(&self, secrets: &HashMap<String, String>) -> Result<()> {
+        let salt = random_bytes::<SALT_LEN>()?;
+        let mut data = salt.to_vec();
+        data.extend(crypto::encrypt(&serde_json::to_vec(secrets)?, &self.derive_key(&salt)?)?);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Replace atomically so a crash never leaves a truncated file
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, data)?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl SecretStore for FileStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut secrets = self.load()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.save(&secrets)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut secrets = self.load()?;
+        if secrets.remove(name).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// Values set or deleted at runtime by the environment backend. `None` marks a deletion.
+static ENV_OVERLAY: Lazy<Mutex<HashMap<String, Option<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct EnvStore;
+
+impl EnvStore {
+    /// `google_drive_credentials` -> `UVCAD_SECRET_GOOGLE_DRIVE_CREDENTIALS`
+    fn variable_name(name: &str) -> String {
+        let suffix: String = name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+        format!("{}{}", SECRET_ENV_PREFIX, suffix)
+    }
+}
+
+impl SecretStore for EnvStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        if let Some(value) = ENV_OVERLAY.lock().unwrap_or_else(|e| e.into_inner()).get(name) {
+            return Ok(value.clone());
+        }
+        Ok(std::env::var(Self::variable_name(name)).ok())
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        ENV_OVERLAY.lock().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), Some(value.to_string()));
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        ENV_OVERLAY.lock().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), None);
+        Ok(())
+    }
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new().fill(&mut bytes)
+        .map_err(|_| UvcadError::InvalidConfig("Random generation failed".to_string()))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path().join("secrets.enc"), dir.path().join("secrets.key"));
+
+        assert_eq!(store.get("google_drive").unwrap(), None);
+        store.set("google_drive", "{\"access_token\":\"abc\"}").unwrap();
+        store.set("google_drive_credentials", "{}").unwrap();
+        store.delete("google_drive_credentials").unwrap();
+
+        assert_eq!(store.get("google_drive").unwrap().as_deref(), Some("{\"access_token\":\"abc\"}"));
+        assert_eq!(store.get("google_drive_credentials").unwrap(), None);
+        let raw = std::fs::read(dir.path().join("secrets.enc")).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("access_token"));
+    }
+
+    #[test]
+    fn test_env_variable_name() {
+        assert_eq!(EnvStore::variable_name("google_drive_credentials"), "UVCAD_SECRET_GOOGLE_DRIVE_CREDENTIALS");
+    }
+}
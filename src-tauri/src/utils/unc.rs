@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+/// Canonical form of a folder or share path. On Windows, mapped drive letters
+/// are replaced by the UNC path they point to (`Z:\Projects` becomes
+/// `\\nas\eng\Projects`), so every machine stores the same path whatever letter
+/// it uses. Other platforms have no drive letters; paths are only trimmed.
+pub fn canonicalize(path: &str) -> String {
+    #[cfg(windows)]
+    {
+        normalize(path, mapped_drive)
+    }
+    #[cfg(not(windows))]
+    {
+        path.trim().to_string()
+    }
+}
+
+/// `path` relative to `root` when it lies under it. On Windows either may use a
+/// mapped drive letter or the UNC form, and case is ignored.
+pub fn strip_root(path: &Path, root: &str) -> Option<PathBuf> {
+    #[cfg(windows)]
+    {
+        strip_canonical(&canonicalize(&path.to_string_lossy()), &canonicalize(root))
+    }
+    #[cfg(not(windows))]
+    {
+        path.strip_prefix(root.trim()).ok().map(Path::to_path_buf)
+    }
+}
+
+/// Backslash separators, an upper-case drive letter resolved through `resolve`
+/// where mapped, and no trailing separator except on a drive root.
+#[cfg(any(windows, test))]
+fn normalize(path: &str, resolve: impl Fn(char) -> Option<String>) -> String {
+    let path = path.trim().replace('/', "\\");
+    let mut chars = path.chars();
+    let canonical = match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+            let letter = letter.to_ascii_uppercase();
+            match resolve(letter) {
+                Some(remote) => format!("{}{}", remote.trim_end_matches('\\'), &path[2..]),
+                None => format!("{}{}", letter, &path[1..]),
+            }
+        }
+        _ => path,
+    };
+
+    let trimmed = canonical.trim_end_matches('\\');
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        canonical
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// `path` relative to `root`, both canonical Windows paths.
+#[cfg(any(windows, test))]
+fn strip_canonical(path: &str, root: &str) -> Option<PathBuf> {
+    let root = root.trim_end_matches('\\');
+    let prefix = path.get(..root.len())?;
+    if !prefix.eq_ignore_ascii_case(root) {
+        return None;
+    }
+    let rest = &path[root.len()..];
+    if !rest.is_empty() && !rest.starts_with('\\') {
+        return None;
+    }
+    Some(PathBuf::from(rest.trim_start_matches('\\')))
+}
+
+/// The UNC path a network drive letter is mapped to.
+#[cfg(windows)]
+pub(crate) fn mapped_drive(letter: char) -> Option<String> {
+    use windows_sys::Win32::NetworkManagement::WNet::WNetGetConnectionW;
+
+    let local: Vec<u16> = format!("{}:", letter).encode_utf16().chain(Some(0)).collect();
+    let mut remote = vec![0u16; 1024];
+    let mut length = remote.len() as u32;
+    // SAFETY: both buffers are NUL-terminated and `length` is the size of `remote`
+    let status = unsafe { WNetGetConnectionW(local.as_ptr(), remote.as_mut_ptr(), &mut length) };
+    if status != 0 {
+        return None;
+    }
+    let end = remote.iter().position(|&c| c == 0).unwrap_or(remote.len());
+    Some(String::from_utf16_lossy(&remote[..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(letter: char) -> Option<String> {
+        (letter == 'Z').then(|| r"\\nas\eng".to_string())
+    }
+
+    #[test]
+    fn test_mapped_drive_becomes_unc() {
+        assert_eq!(normalize(r"z:\Projects\", resolve), r"\\nas\eng\Projects");
+        assert_eq!(normalize("Z:/Projects/A", resolve), r"\\nas\eng\Projects\A");
+        assert_eq!(normalize(r"c:\Work", resolve), r"C:\Work");
+        assert_eq!(normalize(r"C:\", resolve), r"C:\");
+        assert_eq!(normalize(r"\\nas\eng\Projects", resolve), r"\\nas\eng\Projects");
+    }
+
+    #[test]
+    fn test_strip_canonical_ignores_case() {
+        let root = normalize(r"Z:\Projects", resolve);
+        let path = normalize(r"\\NAS\eng\projects\A\part.step", resolve);
+        assert_eq!(strip_canonical(&path, &root).unwrap().to_string_lossy(), r"A\part.step");
+        assert_eq!(strip_canonical(r"\\nas\eng\ProjectsOld\a", &root), None);
+    }
+}
@@ -4,15 +4,7 @@ import { listen } from "@tauri-apps/api/event";
 import SyncButton from "./components/SyncButton";
 import FileList from "./components/FileList";
 import SettingsPanel from "./components/SettingsPanel";
-import { SyncStatus, FileInfo } from "./types";
-
-interface SyncProgress {
-  current_file: string;
-  total_files: number;
-  processed_files: number;
-  operation: string;
-  percentage: number;
-}
+import { SyncStatus, FileInfo, SyncProgress } from "./types";
 
 function App() {
   const [syncStatus, setSyncStatus] = useState<SyncStatus | null>(null);
@@ -31,7 +23,7 @@ function App() {
       setSyncProgress(event.payload);
 
       // Clear progress after completion
-      if (event.payload.operation === "completed") {
+      if (event.payload.operation.phase === "completed") {
         setTimeout(() => setSyncProgress(null), 3000);
       }
     });
@@ -157,7 +149,9 @@ function App() {
                     <span className="progress-text">{syncProgress.percentage.toFixed(0)}%</span>
                   </div>
                 </div>
-                <div className="progress-operation">{syncProgress.operation}</div>
+                <div className="progress-operation" role="status" aria-live="polite">
+                  {syncProgress.description}
+                </div>
               </div>
             )}
 
@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/tauri";
 import { FileInfo } from "../types";
 
 interface FileListProps {
@@ -5,6 +6,17 @@ interface FileListProps {
 }
 
 export default function FileList({ files }: FileListProps) {
+  const restoreFile = async (file: FileInfo) => {
+    if (file.status !== "offloaded") {
+      return;
+    }
+    try {
+      await invoke("restore_offloaded", { path: file.path });
+    } catch (error) {
+      console.error("Failed to restore file:", error);
+    }
+  };
+
   return (
     <div className="file-list">
       <h2>Files</h2>
@@ -22,7 +34,11 @@ export default function FileList({ files }: FileListProps) {
           </thead>
           <tbody>
             {files.map((file, index) => (
-              <tr key={index}>
+              <tr
+                key={index}
+                onDoubleClick={() => restoreFile(file)}
+                title={file.status === "offloaded" ? "Double-click to restore from archive" : undefined}
+              >
                 <td>{file.path}</td>
                 <td>{formatSize(file.size)}</td>
                 <td>{new Date(file.modified).toLocaleString()}</td>
@@ -30,6 +46,14 @@ export default function FileList({ files }: FileListProps) {
                   <span className={`status-badge status-${file.status}`}>
                     {file.status}
                   </span>
+                  {file.sampled_hash && (
+                    <span
+                      className="status-badge status-sampled"
+                      title="Large file: changes are detected from size, modification time and sampled blocks, not a full hash"
+                    >
+                      sampled
+                    </span>
+                  )}
                 </td>
               </tr>
             ))}
@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { open } from "@tauri-apps/api/dialog";
-import { AppConfig, AuthStatus } from "../types";
+import { AppConfig, AuthStatus, NasServer, SmbMount } from "../types";
 
 interface SettingsPanelProps {
   onClose: () => void;
@@ -15,6 +15,9 @@ export default function SettingsPanel({ onClose }: SettingsPanelProps) {
   });
   const [authStatus, setAuthStatus] = useState<AuthStatus | null>(null);
   const [authInProgress, setAuthInProgress] = useState(false);
+  const [smbMounts, setSmbMounts] = useState<SmbMount[] | null>(null);
+  const [nasServers, setNasServers] = useState<NasServer[] | null>(null);
+  const [scanningNetwork, setScanningNetwork] = useState(false);
 
   useEffect(() => {
     loadConfig();
@@ -54,6 +57,27 @@ export default function SettingsPanel({ onClose }: SettingsPanelProps) {
     }
   };
 
+  const handleDiscoverMounts = async () => {
+    try {
+      setSmbMounts(await invoke<SmbMount[]>("discover_smb_mounts"));
+    } catch (error) {
+      console.error("Failed to discover SMB mounts:", error);
+      alert("Failed to find mounted shares: " + error);
+    }
+  };
+
+  const handleDiscoverServers = async () => {
+    try {
+      setScanningNetwork(true);
+      setNasServers(await invoke<NasServer[]>("discover_file_servers"));
+    } catch (error) {
+      console.error("Failed to discover file servers:", error);
+      alert("Failed to scan the network: " + error);
+    } finally {
+      setScanningNetwork(false);
+    }
+  };
+
   const handleSave = async () => {
     try {
       console.log("Saving config:", config);
@@ -166,7 +190,63 @@ export default function SettingsPanel({ onClose }: SettingsPanelProps) {
               }
               placeholder="//server/share or /Volumes/share"
             />
+            <button onClick={handleDiscoverMounts}>Find Mounted Shares</button>
+            <button onClick={handleDiscoverServers} disabled={scanningNetwork}>
+              {scanningNetwork ? "Scanning..." : "Scan Network"}
+            </button>
           </div>
+          {smbMounts && (
+            <div className="setting-item">
+              {smbMounts.length === 0 ? (
+                <span>No mounted network shares found</span>
+              ) : (
+                <ul>
+                  {smbMounts.map((mount) => (
+                    <li key={mount.path}>
+                      <button
+                        onClick={() => setConfig({ ...config, smb_share_path: mount.path })}
+                      >
+                        {mount.server}/{mount.share} ({mount.path})
+                      </button>
+                    </li>
+                  ))}
+                </ul>
+              )}
+            </div>
+          )}
+          {nasServers && (
+            <div className="setting-item">
+              {nasServers.length === 0 ? (
+                <span>No file servers found on the network</span>
+              ) : (
+                <ul>
+                  {nasServers.map((server) => (
+                    <li key={server.host}>
+                      <strong>{server.name}</strong>
+                      {server.address && server.address !== server.name && ` (${server.address})`}
+                      {server.shares.length === 0 ? (
+                        <span> — no shares listed; enter the share name manually</span>
+                      ) : (
+                        <ul>
+                          {server.shares.map((share) => (
+                            <li key={share}>
+                              <button
+                                onClick={() =>
+                                  setConfig({ ...config, smb_share_path: `//${server.host}/${share}` })
+                                }
+                              >
+                                //{server.host}/{share}
+                              </button>
+                            </li>
+                          ))}
+                        </ul>
+                      )}
+                    </li>
+                  ))}
+                </ul>
+              )}
+            </div>
+          )}
         </section>
 
         <section className="settings-section">
@@ -11,16 +11,654 @@ export interface FileInfo {
   size: number;
   modified: string;
   status: string;
+  naming_violation?: boolean;
+  sampled_hash?: boolean;
 }
 
 export interface AppConfig {
   local_path: string | null;
   gdrive_folder_id: string | null;
   smb_share_path: string | null;
+  normalized_extensions?: string | null;
+  inspect_archives?: boolean;
+  inbox_location?: 'local' | 'gdrive' | null;
+  inbox_path?: string | null;
+  inbox_extensions?: string | null;
+  inbox_scan_command?: string | null;
+  inbox_rules?: string | null;
+  naming_rules?: string | null;
+  naming_blocked_locations?: string | null;
+  project_template_path?: string | null;
+  tiering_rules?: string | null;
+  tiering_archive_folder_id?: string | null;
+  extension_policies?: string | null;
+  publish_command?: string | null;
+  publish_formats?: string | null;
+  publish_folder_id?: string | null;
+  compare_command?: string | null;
+  consistency_groups?: string | null;
+  secret_store?: string | null;
+  update_channel?: string | null;
+  locale?: string | null;
+  require_plan_approval?: boolean;
+  plan_approvers?: string | null;
+  auto_approved_operations?: string | null;
+  max_deletion_count?: number | null;
+  max_deletion_percentage?: number | null;
+  smb_fallback_paths?: string | null;
+  sampled_hash_min_mb?: number | null;
+  notification_channels?: string | null;
+  webdav_port?: number | null;
+  sign_digests?: boolean;
+  trusted_signing_keys?: string | null;
+  scan_command?: string | null;
+  sync_cad_temp_files?: boolean;
+  save_settle_secs?: number | null;
+  quiet_period_secs?: number | null;
+  path_prefixes?: string | null;
+  include_patterns?: string | null;
+  exclude_patterns?: string | null;
+  drive_scope?: string | null;
+  soft_delete?: boolean;
+  trash_retention_days?: number | null;
+  drive_auth?: string | null;
+  drive_impersonate?: string | null;
+  managed_policy_source?: string | null;
+  override_managed_policy?: boolean;
+  encrypt_drive?: boolean;
+  last_sync_at?: string | null;
+  last_successful_sync_at?: string | null;
+  last_error?: string | null;
 }
 
+export interface RelocationCheck {
+  verified: number;
+  missing: string[];
+  changed: string[];
+  new_files: number;
+}
+
+export interface ProfileSummary {
+  id: number;
+  name: string;
+  local_path: string;
+  gdrive_folder_id: string | null;
+  smb_share_path: string | null;
+  active: boolean;
+  is_syncing: boolean;
+  last_sync_at: string | null;
+  last_successful_sync_at: string | null;
+  last_error: string | null;
+}
+
+export interface WatcherStatus {
+  running: boolean;
+  profile_id: number | null;
+  folder: string | null;
+  started_at: string | null;
+}
+
+export interface SyncPolicy {
+  extension_policies: string | null;
+  sync_cad_temp_files: boolean;
+  naming_rules: string | null;
+  naming_blocked_locations: string | null;
+  consistency_groups: string | null;
+  path_prefixes: string | null;
+  include_patterns: string | null;
+  exclude_patterns: string | null;
+  max_deletion_count: number | null;
+  max_deletion_percentage: number | null;
+  soft_delete: boolean;
+  trash_retention_days: number | null;
+  save_settle_secs: number | null;
+  quiet_period_secs: number | null;
+  require_plan_approval: boolean;
+  plan_approvers: string | null;
+  auto_approved_operations: string | null;
+}
+
+export type Role = 'admin' | 'operator';
+
+export type DriveScope = 'full' | 'file';
+
 export interface AuthStatus {
   is_authenticated: boolean;
   provider: string;
+  method: string;
   email: string | null;
+  scope: DriveScope | null;
+  configured_scope: DriveScope;
+  needs_reauthentication: boolean;
+}
+
+export interface ArchiveMember {
+  name: string;
+  size: number;
+  compressed_size: number;
+  crc32: number;
+  sha256: string | null;
+}
+
+export interface InboxEntry {
+  id: number | null;
+  profile_id: number;
+  file_name: string;
+  content_hash: string | null;
+  size_bytes: number | null;
+  status: 'Accepted' | 'Rejected';
+  message: string | null;
+  target_path: string | null;
+  received_at: string;
+}
+
+export interface NamingViolation {
+  path: string;
+  locations: string[];
+}
+
+export interface PartRevision {
+  id: number | null;
+  profile_id: number;
+  base_part: string;
+  revision: string;
+  file_path: string;
+  content_hash: string | null;
+  first_seen_at: string;
+}
+
+export interface TieringCandidate {
+  path: string;
+  size: number;
+  modified_at: string;
+  content_hash: string | null;
+  rule: string;
+}
+
+export type StorageLocation = 'Local' | 'GoogleDrive' | 'Smb';
+
+export type SyncPhase =
+  | { phase: 'initializing' }
+  | { phase: 'scanning'; provider: StorageLocation }
+  | { phase: 'planning' }
+  | { phase: 'transferring'; from: StorageLocation | null; to: StorageLocation }
+  | { phase: 'verifying' }
+  | { phase: 'finalizing' }
+  | { phase: 'completed' };
+
+export type ChangeReason =
+  | { change: 'added' | 'modified' | 'deleted'; at: StorageLocation }
+  | { change: 'moved'; at: StorageLocation; from: string }
+  | { change: 'same_change' | 'conflicting' };
+
+export type Authorization =
+  | { policy: 'no_approval_required' | 'auto_approved' | 'initial_upload' }
+  | { policy: 'approved' | 'conflict_resolution'; by: string };
+
+export interface OperationReason {
+  change: ChangeReason;
+  allowed_by: Authorization;
+}
+
+export type PlanStep = { index: number; path: string; size_bytes: number; reason: ChangeReason | null } & (
+  | { operation: 'copy'; from: StorageLocation; to: StorageLocation }
+  | { operation: 'delete'; at: StorageLocation }
+  | { operation: 'move'; at: StorageLocation; from: string }
+  | { operation: 'conflict' }
+);
+
+export type ConflictResolution = 'KeepLocal' | 'KeepGoogleDrive' | 'KeepSmb' | 'KeepBoth';
+
+export type ResolutionStep = { location: StorageLocation; path: string } & (
+  | { action: 'overwrite'; from: StorageLocation }
+  | { action: 'copy'; from: StorageLocation }
+  | { action: 'rename'; to: string }
+  | { action: 'delete' }
+);
+
+export interface ResolutionPreview {
+  file_path: string;
+  resolution: ConflictResolution;
+  steps: ResolutionStep[];
+  unchanged: StorageLocation[];
+}
+
+export interface RecordedConflict {
+  id: number;
+  profile_id: number;
+  file_path: string;
+  detected_at: string;
+  resolved: boolean;
+  resolution: ConflictResolution | null;
+  local_hash: string | null;
+  gdrive_hash: string | null;
+  smb_hash: string | null;
+  local_modified: string | null;
+  gdrive_modified: string | null;
+  smb_modified: string | null;
+  local_size: number | null;
+  gdrive_size: number | null;
+  smb_size: number | null;
+}
+
+export interface ConflictResolutionResult {
+  file_path: string;
+  resolution: ConflictResolution;
+  steps: ResolutionStep[];
+  unchanged: StorageLocation[];
+  conflict_copies: string[];
+  run_id: string;
+}
+
+export interface CurrentPlan {
+  run_id: string;
+  preview: boolean;
+  steps: PlanStep[];
+}
+
+export interface SyncProgress {
+  current_file: string;
+  total_files: number;
+  processed_files: number;
+  operation: SyncPhase;
+  description: string;
+  percentage: number;
+  batched_updates: number;
+  run_id: string | null;
+}
+
+export interface UploadProgress {
+  path: string;
+  bytes_sent: number;
+  bytes_total: number;
+  run_id: string;
+}
+
+export interface Transfer {
+  id: number;
+  profile_id: number;
+  file_path: string;
+  source: StorageLocation | null;
+  destination: StorageLocation;
+  moved_from: string | null;
+  status: 'Queued' | 'InProgress' | 'Failed';
+  priority: number;
+  attempts: number;
+  size_bytes: number | null;
+  expected_hash: string | null;
+  expect_absent: boolean;
+  reason: OperationReason | null;
+  error: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface BootstrapProgress {
+  files_total: number;
+  files_done: number;
+  files_failed: number;
+  bytes_total: number;
+  bytes_done: number;
+  current_file: string;
+  elapsed_secs: number;
+  eta_secs: number | null;
+}
+
+export interface LocationVolume {
+  location: StorageLocation;
+  upload_files: number;
+  upload_bytes: number;
+  download_files: number;
+  download_bytes: number;
+}
+
+export interface SyncEstimate {
+  locations: LocationVolume[];
+  files_to_copy: number;
+  bytes_to_copy: number;
+  files_to_delete: number;
+  conflicts: number;
+  estimated_secs: number | null;
+  unmeasured_bytes: number;
+}
+
+export interface TrackedFile {
+  path: string;
+  size_bytes: number;
+  modified_at: string | null;
+  locations: StorageLocation[];
+}
+
+export interface SizeClass {
+  min_bytes: number;
+  max_bytes: number | null;
+  files: number;
+  bytes: number;
+}
+
+export interface FolderSize {
+  folder: string;
+  files: number;
+  bytes: number;
+}
+
+export interface SizeDistribution {
+  total_files: number;
+  total_bytes: number;
+  classes: SizeClass[];
+  folders: FolderSize[];
+}
+
+export interface StaleFile {
+  path: string;
+  location: StorageLocation;
+  size_bytes: number;
+  modified_at: string;
+  accessed_at: string | null;
+}
+
+export interface LocationStaleness {
+  location: StorageLocation;
+  files: number;
+  bytes: number;
+}
+
+export interface StaleReport {
+  cutoff: string;
+  locations: LocationStaleness[];
+  files: StaleFile[];
+}
+
+export interface WorkingFolder {
+  folder: string;
+  opens: number;
+  last_opened_at: string;
+  score: number;
+}
+
+export interface Snapshot {
+  id: number | null;
+  profile_id: number;
+  label: string;
+  created_at: string;
+  created_by: string;
+  file_count: number;
+  total_bytes: number;
+}
+
+export interface SnapshotDiff {
+  added: string[];
+  removed: string[];
+  changed: string[];
+  unchanged: number;
+}
+
+export interface SnapshotRestore {
+  restored: string[];
+  unchanged: number;
+  unavailable: string[];
+  added_since: string[];
+  held: string[];
+  failed: string[];
+}
+
+export interface LegalHold {
+  id: number | null;
+  profile_id: number;
+  path: string;
+  reason: string;
+  placed_by: string;
+  placed_at: string;
+  released_by: string | null;
+  released_at: string | null;
+  release_reason: string | null;
+}
+
+export interface PausedLocation {
+  profile_id: number;
+  location: StorageLocation;
+  reason: string | null;
+  paused_by: string;
+  paused_at: string;
+}
+
+/** A copy a sync moved to the trash instead of deleting it (soft deletion). */
+export interface TrashedFile {
+  id: number | null;
+  profile_id: number;
+  file_path: string;
+  location: StorageLocation;
+  handle: string;
+  content_hash: string | null;
+  size_bytes: number | null;
+  deleted_at: string;
+  run_id: string | null;
+}
+
+export interface RestoredFile {
+  file_path: string;
+  restored: StorageLocation[];
+  failed: [StorageLocation, string][];
+}
+
+export interface ManagedProfile {
+  name: string;
+  local_path: string;
+  gdrive_folder_id: string | null;
+  smb_share_path: string | null;
+}
+
+export interface ManagedPolicy {
+  description: string | null;
+  /** `SyncPolicy` settings every profile gets. */
+  policy: Partial<SyncPolicy>;
+  profiles: ManagedProfile[];
+  allowed_locations: string[] | null;
+}
+
+export interface LoadedPolicy {
+  source: string;
+  policy: ManagedPolicy;
+  fetched_at: string;
+  /** Set when the copy kept from the last fetch is in use. */
+  stale: string | null;
+}
+
+export interface ManagedPolicyRefresh {
+  loaded: LoadedPolicy;
+  updated_profiles: string[];
+  created_profiles: string[];
+  skipped_profiles: [string, string][];
+}
+
+export type ShellAction = "sync_now" | "view_versions" | "lock_file";
+
+/** Emitted as `shell-action` when a file manager action opens UVCAD. */
+export interface ShellRequest {
+  action: ShellAction;
+  profile_id: number;
+  path: string;
+}
+
+export interface FileEvent {
+  id: number | null;
+  profile_id: number;
+  file_path: string;
+  location: StorageLocation;
+  kind: "Added" | "Modified" | "Deleted";
+  content_hash: string | null;
+  recorded_at: string;
+  endpoint: string | null;
+  run_id: string | null;
+  reason: OperationReason | null;
+}
+
+export interface SyncRun {
+  id: number | null;
+  profile_id: number;
+  run_id: string;
+  started_at: string;
+  completed_at: string | null;
+  status: "Running" | "Completed" | "AwaitingApproval" | "Cancelled" | "Failed";
+  phase: "Scan" | "Plan" | "SafetyCheck" | "Execute" | "Commit" | null;
+  files_synced: number;
+  files_failed: number;
+  error_message: string | null;
+}
+
+export interface SyncHistoryPage {
+  runs: SyncRun[];
+  total: number;
+}
+
+export interface FileTiming {
+  id: number | null;
+  profile_id: number;
+  run_id: string;
+  file_path: string;
+  source: StorageLocation;
+  destination: StorageLocation;
+  size_bytes: number | null;
+  download_ms: number;
+  hash_ms: number;
+  upload_ms: number;
+  total_ms: number;
+  recorded_at: string;
+}
+
+export interface SyncRunDetails {
+  run: SyncRun;
+  events: FileEvent[];
+}
+
+export interface LocationChanges {
+  location: StorageLocation;
+  added: string[];
+  modified: string[];
+  deleted: string[];
+}
+
+export interface MeshInfo {
+  triangle_count: number;
+  bounding_box: { min: [number, number, number]; max: [number, number, number] } | null;
+}
+
+export interface ReleaseResult {
+  release_id: string;
+  files: string[];
+  locations: StorageLocation[];
+}
+
+export interface KeyRotation {
+  key: string;
+  reencrypted: number;
+  skipped: number;
+  failed: [string, string][];
+  remaining: number;
+}
+
+export interface UpdateInfo {
+  channel: string;
+  current_version: string;
+  latest_version: string;
+  available: boolean;
+  notes: string | null;
+  date: string | null;
+  can_install: boolean;
+}
+
+export interface MessageCatalog {
+  locale: string;
+  messages: Record<string, string>;
+}
+
+export type SimulationScenario = 'mass_delete' | 'divergent_edits' | 'flaky_network' | 'quota_exceeded' | 'smb_dropout' | 'expired_token';
+
+export interface CaptureSession {
+  path: string;
+  started_at: string;
+  exchanges: number;
+}
+
+export interface SimulationReport {
+  scenario: SimulationScenario;
+  files: number;
+  changes: string[];
+  aborted: string | null;
+  files_synced: number;
+  files_failed: number;
+  conflicts: string[];
+  blocked: string[];
+  warnings: string[];
+  plan: PlanStep[];
+  recovered: number | null;
+}
+
+export interface SoakReport {
+  seed: number;
+  rounds: number;
+  changes: number;
+  files_synced: number;
+  transfers_retried: number;
+  elapsed_secs: number;
+  violations: string[];
+  stopped: boolean;
+}
+
+export interface MediaExportSummary {
+  archive_path: string;
+  files_listed: number;
+  files_included: number;
+  files_deleted: number;
+  bytes_included: number;
+}
+
+export type NotificationKind =
+  | "sync_completed"
+  | "sync_failed"
+  | "plan_awaiting_approval"
+  | "update_available"
+  | "inbox_processed"
+  | "file_restored"
+  | "tamper_detected"
+  | "file_quarantined"
+  | "test";
+
+export interface Notification {
+  kind: NotificationKind;
+  severity: "info" | "warning" | "error";
+  message: string;
+  run_id: string | null;
+  details: Record<string, unknown> | null;
+  created_at: string;
+}
+
+export interface SyncPlan {
+  id: number | null;
+  profile_id: number;
+  steps: PlanStep[];
+  status: 'Pending' | 'Approved' | 'Rejected' | 'Superseded' | 'Executed';
+  created_at: string;
+  decided_by: string | null;
+  decided_at: string | null;
+}
+
+export interface SmbMount {
+  path: string;
+  server: string;
+  share: string;
+}
+
+export interface DedupeOutcome {
+  path: string;
+  merged: number;
+  renamed: string[];
+}
+
+export interface NasServer {
+  name: string;
+  host: string;
+  address: string | null;
+  sources: string[];
+  shares: string[];
 }